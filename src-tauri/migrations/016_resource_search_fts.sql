@@ -0,0 +1,284 @@
+-- Migration 016: Full-text search over resource metadata (FTS5)
+-- Indexes titles, descriptions, tags and file contents of every resource.
+-- Titles, descriptions and tags are kept in sync by triggers; file contents
+-- are written by the backend (it has to read the file from disk).
+
+-- ============================================================================
+-- Helper views (one row per resource across all type-specific tables)
+-- ============================================================================
+
+CREATE VIEW IF NOT EXISTS resource_tags AS
+    SELECT resource_id, tag FROM resource_file_tags
+    UNION ALL SELECT resource_id, tag FROM resource_document_tags
+    UNION ALL SELECT resource_id, tag FROM resource_table_tags
+    UNION ALL SELECT resource_id, tag FROM resource_figure_tags
+    UNION ALL SELECT resource_id, tag FROM resource_command_tags
+    UNION ALL SELECT resource_id, tag FROM resource_package_tags
+    UNION ALL SELECT resource_id, tag FROM resource_class_tags;
+
+CREATE VIEW IF NOT EXISTS resource_descriptions AS
+    SELECT resource_id, file_description AS description FROM resource_files
+    UNION ALL SELECT resource_id, description FROM resource_documents
+    UNION ALL SELECT resource_id, trim(coalesce(caption, '') || ' ' || coalesce(description, '')) FROM resource_tables
+    UNION ALL SELECT resource_id, trim(coalesce(caption, '') || ' ' || coalesce(description, '')) FROM resource_figures
+    UNION ALL SELECT resource_id, description FROM resource_commands
+    UNION ALL SELECT resource_id, description FROM resource_packages
+    UNION ALL SELECT resource_id, description FROM resource_preambles
+    UNION ALL SELECT resource_id, description FROM resource_classes
+    UNION ALL SELECT resource_id, description FROM resource_dtx;
+
+-- ============================================================================
+-- FTS5 index
+-- ============================================================================
+
+CREATE VIRTUAL TABLE IF NOT EXISTS resources_fts USING fts5(
+    resource_id UNINDEXED,
+    title,
+    description,
+    tags,
+    content,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+-- Backfill existing resources
+INSERT INTO resources_fts (resource_id, title, description, tags, content)
+SELECT
+    r.id,
+    coalesce(r.title, ''),
+    coalesce((SELECT group_concat(d.description, ' ') FROM resource_descriptions d WHERE d.resource_id = r.id), ''),
+    coalesce((SELECT group_concat(t.tag, ' ') FROM resource_tags t WHERE t.resource_id = r.id), ''),
+    ''
+FROM resources r
+WHERE r.id NOT IN (SELECT resource_id FROM resources_fts);
+
+-- ============================================================================
+-- TRIGGERS: resources
+-- ============================================================================
+
+-- add_resource uses INSERT OR REPLACE, which does not fire DELETE triggers,
+-- so drop any stale row before inserting.
+CREATE TRIGGER IF NOT EXISTS resources_fts_insert
+AFTER INSERT ON resources
+BEGIN
+    DELETE FROM resources_fts WHERE resource_id = NEW.id;
+    INSERT INTO resources_fts (resource_id, title, description, tags, content)
+    VALUES (
+        NEW.id,
+        coalesce(NEW.title, ''),
+        coalesce((SELECT group_concat(d.description, ' ') FROM resource_descriptions d WHERE d.resource_id = NEW.id), ''),
+        coalesce((SELECT group_concat(t.tag, ' ') FROM resource_tags t WHERE t.resource_id = NEW.id), ''),
+        ''
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS resources_fts_update_title
+AFTER UPDATE OF title ON resources
+BEGIN
+    UPDATE resources_fts SET title = coalesce(NEW.title, '') WHERE resource_id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resources_fts_delete
+AFTER DELETE ON resources
+BEGIN
+    DELETE FROM resources_fts WHERE resource_id = OLD.id;
+END;
+
+-- ============================================================================
+-- TRIGGERS: tags (refresh the aggregated tag list of the affected resource)
+-- ============================================================================
+
+CREATE TRIGGER IF NOT EXISTS resource_file_tags_fts_insert
+AFTER INSERT ON resource_file_tags
+BEGIN
+    UPDATE resources_fts SET tags = coalesce((SELECT group_concat(tag, ' ') FROM resource_tags WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_file_tags_fts_delete
+AFTER DELETE ON resource_file_tags
+BEGIN
+    UPDATE resources_fts SET tags = coalesce((SELECT group_concat(tag, ' ') FROM resource_tags WHERE resource_id = OLD.resource_id), '') WHERE resource_id = OLD.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_document_tags_fts_insert
+AFTER INSERT ON resource_document_tags
+BEGIN
+    UPDATE resources_fts SET tags = coalesce((SELECT group_concat(tag, ' ') FROM resource_tags WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_document_tags_fts_delete
+AFTER DELETE ON resource_document_tags
+BEGIN
+    UPDATE resources_fts SET tags = coalesce((SELECT group_concat(tag, ' ') FROM resource_tags WHERE resource_id = OLD.resource_id), '') WHERE resource_id = OLD.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_table_tags_fts_insert
+AFTER INSERT ON resource_table_tags
+BEGIN
+    UPDATE resources_fts SET tags = coalesce((SELECT group_concat(tag, ' ') FROM resource_tags WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_table_tags_fts_delete
+AFTER DELETE ON resource_table_tags
+BEGIN
+    UPDATE resources_fts SET tags = coalesce((SELECT group_concat(tag, ' ') FROM resource_tags WHERE resource_id = OLD.resource_id), '') WHERE resource_id = OLD.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_figure_tags_fts_insert
+AFTER INSERT ON resource_figure_tags
+BEGIN
+    UPDATE resources_fts SET tags = coalesce((SELECT group_concat(tag, ' ') FROM resource_tags WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_figure_tags_fts_delete
+AFTER DELETE ON resource_figure_tags
+BEGIN
+    UPDATE resources_fts SET tags = coalesce((SELECT group_concat(tag, ' ') FROM resource_tags WHERE resource_id = OLD.resource_id), '') WHERE resource_id = OLD.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_command_tags_fts_insert
+AFTER INSERT ON resource_command_tags
+BEGIN
+    UPDATE resources_fts SET tags = coalesce((SELECT group_concat(tag, ' ') FROM resource_tags WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_command_tags_fts_delete
+AFTER DELETE ON resource_command_tags
+BEGIN
+    UPDATE resources_fts SET tags = coalesce((SELECT group_concat(tag, ' ') FROM resource_tags WHERE resource_id = OLD.resource_id), '') WHERE resource_id = OLD.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_package_tags_fts_insert
+AFTER INSERT ON resource_package_tags
+BEGIN
+    UPDATE resources_fts SET tags = coalesce((SELECT group_concat(tag, ' ') FROM resource_tags WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_package_tags_fts_delete
+AFTER DELETE ON resource_package_tags
+BEGIN
+    UPDATE resources_fts SET tags = coalesce((SELECT group_concat(tag, ' ') FROM resource_tags WHERE resource_id = OLD.resource_id), '') WHERE resource_id = OLD.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_class_tags_fts_insert
+AFTER INSERT ON resource_class_tags
+BEGIN
+    UPDATE resources_fts SET tags = coalesce((SELECT group_concat(tag, ' ') FROM resource_tags WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_class_tags_fts_delete
+AFTER DELETE ON resource_class_tags
+BEGIN
+    UPDATE resources_fts SET tags = coalesce((SELECT group_concat(tag, ' ') FROM resource_tags WHERE resource_id = OLD.resource_id), '') WHERE resource_id = OLD.resource_id;
+END;
+
+-- ============================================================================
+-- TRIGGERS: descriptions (typed metadata is saved via INSERT [OR REPLACE] or UPDATE)
+-- ============================================================================
+
+CREATE TRIGGER IF NOT EXISTS resource_files_fts_description
+AFTER INSERT ON resource_files
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_documents_fts_description
+AFTER INSERT ON resource_documents
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_tables_fts_description
+AFTER INSERT ON resource_tables
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_figures_fts_description
+AFTER INSERT ON resource_figures
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_commands_fts_description
+AFTER INSERT ON resource_commands
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_packages_fts_description
+AFTER INSERT ON resource_packages
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_preambles_fts_description
+AFTER INSERT ON resource_preambles
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_classes_fts_description
+AFTER INSERT ON resource_classes
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_dtx_fts_description
+AFTER INSERT ON resource_dtx
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_files_fts_description_update
+AFTER UPDATE ON resource_files
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_documents_fts_description_update
+AFTER UPDATE ON resource_documents
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_tables_fts_description_update
+AFTER UPDATE ON resource_tables
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_figures_fts_description_update
+AFTER UPDATE ON resource_figures
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_commands_fts_description_update
+AFTER UPDATE ON resource_commands
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_packages_fts_description_update
+AFTER UPDATE ON resource_packages
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_preambles_fts_description_update
+AFTER UPDATE ON resource_preambles
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_classes_fts_description_update
+AFTER UPDATE ON resource_classes
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS resource_dtx_fts_description_update
+AFTER UPDATE ON resource_dtx
+BEGIN
+    UPDATE resources_fts SET description = coalesce((SELECT group_concat(description, ' ') FROM resource_descriptions WHERE resource_id = NEW.resource_id), '') WHERE resource_id = NEW.resource_id;
+END;
@@ -0,0 +1,45 @@
+-- Migration 017: User-defined metadata fields per collection
+-- Field definitions are scoped to a collection; values are stored EAV-style
+-- (one row per resource/field) so new fields never require schema edits.
+
+CREATE TABLE IF NOT EXISTS custom_field_definitions (
+    id TEXT PRIMARY KEY NOT NULL,
+    collection TEXT NOT NULL,
+    name TEXT NOT NULL,  -- Stable key, e.g. "difficulty"
+    label TEXT,  -- Display label, e.g. "Δυσκολία"
+    field_type TEXT NOT NULL CHECK(field_type IN ('text', 'number', 'enum', 'date')),
+    options JSON DEFAULT '[]',  -- Allowed values for enum fields
+    required BOOLEAN DEFAULT FALSE,
+    order_index INTEGER DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(collection, name),
+    FOREIGN KEY(collection) REFERENCES collections(name) ON UPDATE CASCADE ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_custom_field_definitions_collection ON custom_field_definitions(collection);
+
+CREATE TABLE IF NOT EXISTS custom_field_values (
+    resource_id TEXT NOT NULL,
+    field_id TEXT NOT NULL,
+    value TEXT,  -- Normalized textual value (numbers as decimal, dates as YYYY-MM-DD)
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(resource_id, field_id),
+    FOREIGN KEY(resource_id) REFERENCES resources(id) ON DELETE CASCADE,
+    FOREIGN KEY(field_id) REFERENCES custom_field_definitions(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_custom_field_values_field ON custom_field_values(field_id, value);
+
+-- The app enables foreign keys, but connections from other tools may not;
+-- clean up explicitly as well
+CREATE TRIGGER IF NOT EXISTS cleanup_custom_field_values_on_resource_delete
+AFTER DELETE ON resources
+BEGIN
+    DELETE FROM custom_field_values WHERE resource_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS cleanup_custom_field_values_on_definition_delete
+AFTER DELETE ON custom_field_definitions
+BEGIN
+    DELETE FROM custom_field_values WHERE field_id = OLD.id;
+END;
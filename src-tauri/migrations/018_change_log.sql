@@ -0,0 +1,38 @@
+-- Migration 018: Append-only audit log of database changes
+-- Rows are written by per-table triggers that the backend (re)installs on
+-- startup (see audit.rs), so tables added by later migrations are covered too.
+
+CREATE TABLE IF NOT EXISTS change_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    table_name TEXT NOT NULL,
+    row_id TEXT,  -- Primary key of the changed row (JSON array for composite keys)
+    resource_id TEXT,  -- Resource the row belongs to, when known
+    action TEXT NOT NULL CHECK(action IN ('insert', 'update', 'delete')),
+    actor TEXT,  -- OS user running the app
+    old_values JSON,  -- Row before the change (update/delete)
+    new_values JSON,  -- Row after the change (insert/update)
+    changed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_change_log_resource ON change_log(resource_id, id DESC);
+CREATE INDEX IF NOT EXISTS idx_change_log_table_row ON change_log(table_name, row_id);
+
+-- Single-row table the audit triggers read the current actor from
+CREATE TABLE IF NOT EXISTS audit_context (
+    id INTEGER PRIMARY KEY CHECK(id = 1),
+    actor TEXT
+);
+
+INSERT OR IGNORE INTO audit_context (id, actor) VALUES (1, NULL);
+
+CREATE TRIGGER IF NOT EXISTS change_log_no_update
+BEFORE UPDATE ON change_log
+BEGIN
+    SELECT RAISE(ABORT, 'change_log is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS change_log_no_delete
+BEFORE DELETE ON change_log
+BEGIN
+    SELECT RAISE(ABORT, 'change_log is append-only');
+END;
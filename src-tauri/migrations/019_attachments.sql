@@ -0,0 +1,27 @@
+-- Migration 019: File attachments (solution PDFs, source images, ...)
+-- Attachment contents live in a content-addressed blob directory managed by
+-- the backend (<data dir>/attachments/<hash prefix>/<sha256>); rows only
+-- reference blobs by hash, so identical files are stored once.
+
+CREATE TABLE IF NOT EXISTS attachments (
+    id TEXT PRIMARY KEY NOT NULL,
+    resource_id TEXT NOT NULL,
+    kind TEXT NOT NULL DEFAULT 'other' CHECK(kind IN ('solution', 'figure', 'source', 'other')),
+    file_name TEXT NOT NULL,  -- Original file name, used when exporting
+    mime_type TEXT,
+    size INTEGER NOT NULL,
+    blob_hash TEXT NOT NULL,  -- SHA256 of the content
+    description TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(resource_id) REFERENCES resources(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_attachments_resource ON attachments(resource_id);
+CREATE INDEX IF NOT EXISTS idx_attachments_blob ON attachments(blob_hash);
+
+-- Keep rows consistent even where foreign keys were not enforced
+CREATE TRIGGER IF NOT EXISTS cleanup_attachments_on_resource_delete
+AFTER DELETE ON resources
+BEGIN
+    DELETE FROM attachments WHERE resource_id = OLD.id;
+END;
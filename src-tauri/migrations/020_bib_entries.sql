@@ -0,0 +1,26 @@
+-- Migration 020: Entries of .bib resources as database rows
+-- One row per citation key in a bibliography file; kept in sync whenever the
+-- file is (re)indexed. Title, authors and year are denormalized from the
+-- fields JSON for listing and searching.
+
+CREATE TABLE IF NOT EXISTS bib_entries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    resource_id TEXT NOT NULL,  -- The .bib resource the entry lives in
+    citation_key TEXT NOT NULL,
+    entry_type TEXT NOT NULL,  -- article, book, ...
+    fields JSON NOT NULL DEFAULT '{}',  -- All fields, formatted verbatim
+    title TEXT,
+    authors TEXT,  -- "Given Family; Given Family"
+    year TEXT,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(resource_id, citation_key),
+    FOREIGN KEY(resource_id) REFERENCES resources(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_bib_entries_key ON bib_entries(citation_key);
+
+CREATE TRIGGER IF NOT EXISTS cleanup_bib_entries_on_resource_delete
+AFTER DELETE ON resources
+BEGIN
+    DELETE FROM bib_entries WHERE resource_id = OLD.id;
+END;
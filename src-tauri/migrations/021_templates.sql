@@ -0,0 +1,20 @@
+-- Migration 021: User document templates
+-- Template content is LaTeX with {{placeholders}} that are filled in when a
+-- document is created from it. Built-in templates (exam, worksheet, beamer)
+-- ship with the app and are not stored here.
+
+CREATE TABLE IF NOT EXISTS templates (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+    kind TEXT NOT NULL DEFAULT 'document',  -- document, exam, worksheet, beamer, ...
+    content TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TRIGGER IF NOT EXISTS update_timestamp_templates
+AFTER UPDATE ON templates
+BEGIN
+    UPDATE templates SET updated_at = CURRENT_TIMESTAMP WHERE id = new.id;
+END;
@@ -0,0 +1,5 @@
+-- Migration 022: Solution environments per collection
+-- JSON array of environment names (e.g. ["solution", "answer"]) that the
+-- document builder treats as solutions; NULL means the default "solution".
+
+ALTER TABLE collections ADD COLUMN solution_environments JSON;
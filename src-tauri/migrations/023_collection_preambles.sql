@@ -0,0 +1,19 @@
+-- Migration 023: Shared preamble per collection
+-- Document class, packages and macros that every document assembled from a
+-- collection starts with, instead of a preamble copied into each file.
+
+CREATE TABLE IF NOT EXISTS collection_preambles (
+    collection TEXT PRIMARY KEY NOT NULL,
+    document_class TEXT NOT NULL DEFAULT 'article',
+    class_options TEXT,  -- e.g. "a4paper,11pt"
+    packages JSON NOT NULL DEFAULT '[]',  -- [{"name": "amsmath", "options": null}, ...] in load order
+    macros TEXT,  -- \newcommand definitions etc.
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(collection) REFERENCES collections(name) ON UPDATE CASCADE ON DELETE CASCADE
+);
+
+CREATE TRIGGER IF NOT EXISTS cleanup_collection_preamble_on_collection_delete
+AFTER DELETE ON collections
+BEGIN
+    DELETE FROM collection_preambles WHERE collection = OLD.name;
+END;
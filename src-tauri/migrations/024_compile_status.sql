@@ -0,0 +1,22 @@
+-- Migration 024: Last compile result per resource
+-- Written after every compile (editor, "Compile" action, document builder),
+-- so failing exercises can be spotted without rebuilding everything.
+
+CREATE TABLE IF NOT EXISTS compile_status (
+    resource_id TEXT PRIMARY KEY NOT NULL,
+    status TEXT NOT NULL CHECK(status IN ('ok', 'error')),
+    error_count INTEGER NOT NULL DEFAULT 0,
+    warning_count INTEGER NOT NULL DEFAULT 0,
+    engine TEXT,
+    message TEXT,  -- First error, for tooltips
+    compiled_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(resource_id) REFERENCES resources(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_compile_status_status ON compile_status(status);
+
+CREATE TRIGGER IF NOT EXISTS cleanup_compile_status_on_resource_delete
+AFTER DELETE ON resources
+BEGIN
+    DELETE FROM compile_status WHERE resource_id = OLD.id;
+END;
@@ -0,0 +1,24 @@
+-- Migration 025: Substring index over file contents (FTS5 trigram)
+-- resources_fts tokenizes into words, which can't answer "contains this
+-- text" the way the file search does. A trigram index can: any literal of
+-- three or more characters narrows the search to the files containing it,
+-- so plain searches only read those files instead of the whole database.
+-- Kept in sync together with resources_fts.content (indexer, file watcher).
+
+CREATE VIRTUAL TABLE IF NOT EXISTS resources_content_trigram USING fts5(
+    resource_id UNINDEXED,
+    content,
+    tokenize = 'trigram case_sensitive 0'
+);
+
+-- Backfill from the contents already indexed
+INSERT INTO resources_content_trigram (resource_id, content)
+SELECT resource_id, content FROM resources_fts
+WHERE content != ''
+  AND resource_id NOT IN (SELECT resource_id FROM resources_content_trigram);
+
+CREATE TRIGGER IF NOT EXISTS resources_content_trigram_delete
+AFTER DELETE ON resources
+BEGIN
+    DELETE FROM resources_content_trigram WHERE resource_id = OLD.id;
+END;
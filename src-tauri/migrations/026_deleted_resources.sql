@@ -0,0 +1,19 @@
+-- Migration 026: Recycle bin
+-- One row per deletion. The file (or folder) is kept under the app's trash
+-- directory and the deleted rows stay in change_log, so both can be put
+-- back by restore_deleted_resource.
+
+CREATE TABLE IF NOT EXISTS deleted_resources (
+    id TEXT PRIMARY KEY NOT NULL,
+    resource_id TEXT NOT NULL,  -- Resource the user deleted
+    resource_ids JSON NOT NULL,  -- It and everything deleted with it (folder contents)
+    collection TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    original_path TEXT NOT NULL,
+    trash_path TEXT,  -- NULL when only the database rows were deleted
+    log_start INTEGER NOT NULL,  -- change_log ids in (log_start, log_end]
+    log_end INTEGER NOT NULL,  -- hold the deleted rows
+    deleted_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_deleted_resources_collection ON deleted_resources(collection);
@@ -0,0 +1,24 @@
+-- Migration 027: Sessions
+-- What the window looked like when it was last closed (open tabs and their
+-- cursors, expanded tree folders, loaded collections), so the next start
+-- resumes from there. Only the latest few sessions are kept.
+
+CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_root TEXT,  -- Folder open in the project tree
+    active_collection TEXT,
+    loaded_collections JSON NOT NULL DEFAULT '[]',
+    expanded_nodes JSON NOT NULL DEFAULT '[]',  -- Paths of expanded tree folders
+    active_tab TEXT,  -- Path of the focused tab
+    saved_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS session_tabs (
+    session_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    path TEXT NOT NULL,
+    cursor_line INTEGER NOT NULL DEFAULT 1,
+    cursor_column INTEGER NOT NULL DEFAULT 1,
+    PRIMARY KEY (session_id, position),
+    FOREIGN KEY(session_id) REFERENCES sessions(id) ON DELETE CASCADE
+);
@@ -0,0 +1,17 @@
+-- Migration 028: Content-addressed local history
+-- Snapshot contents move to history_blobs, keyed by their SHA-256, so a
+-- version that comes back (undo, revert, restore) is stored only once.
+-- The copy and the drop run in one transaction (see
+-- DatabaseManager::ATOMIC_MIGRATIONS), so a failed copy drops nothing
+
+CREATE TABLE IF NOT EXISTS history_blobs (
+    hash TEXT PRIMARY KEY,
+    content TEXT NOT NULL
+);
+
+INSERT OR IGNORE INTO history_blobs (hash, content)
+SELECT content_hash, content FROM file_history;
+
+ALTER TABLE file_history DROP COLUMN content;
+
+CREATE INDEX IF NOT EXISTS idx_file_history_hash ON file_history(content_hash);
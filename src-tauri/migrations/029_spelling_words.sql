@@ -0,0 +1,10 @@
+-- Migration 029: Custom spelling dictionary
+-- Words the user added to the dictionary, per project folder ('' when no
+-- project is open)
+
+CREATE TABLE IF NOT EXISTS spelling_words (
+    project TEXT NOT NULL,
+    word TEXT NOT NULL,
+    added_at TEXT DEFAULT (datetime('now')),
+    PRIMARY KEY (project, word)
+);
@@ -0,0 +1,12 @@
+-- Migration 030: Zotero-synced bibliographies
+-- .bib resources whose entries are pulled from Zotero collections through
+-- Better BibTeX, and where they are pulled from
+
+CREATE TABLE IF NOT EXISTS zotero_links (
+    resource_id TEXT PRIMARY KEY NOT NULL,
+    library_id INTEGER NOT NULL DEFAULT 1,
+    collections TEXT NOT NULL DEFAULT '[]', -- JSON array of collection keys or paths
+    format TEXT NOT NULL DEFAULT 'biblatex', -- 'biblatex' or 'bibtex'
+    last_synced_at TEXT,
+    FOREIGN KEY(resource_id) REFERENCES resources(id) ON DELETE CASCADE
+);
@@ -0,0 +1,11 @@
+-- Migration 031: Quick-open usage log
+-- When resources were opened, for frecency ranking in quick open; only the
+-- most recent opens of each resource are kept
+
+CREATE TABLE IF NOT EXISTS resource_opens (
+    resource_id TEXT NOT NULL,
+    opened_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY(resource_id) REFERENCES resources(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_resource_opens_resource ON resource_opens(resource_id, opened_at);
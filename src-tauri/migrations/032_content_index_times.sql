@@ -0,0 +1,10 @@
+-- Migration 032: When each file's content was last indexed
+-- The substring index (025) only rules a file out if the file hasn't been
+-- modified since its content was indexed; files edited outside the app are
+-- searched on disk until they are indexed again
+
+CREATE TABLE IF NOT EXISTS resources_content_indexed (
+    resource_id TEXT PRIMARY KEY NOT NULL,
+    indexed_at INTEGER NOT NULL, -- Unix time
+    FOREIGN KEY(resource_id) REFERENCES resources(id) ON DELETE CASCADE
+);
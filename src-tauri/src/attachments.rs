@@ -0,0 +1,319 @@
+//! Attachments Module
+//!
+//! Files attached to a resource (solution PDFs, source images, ...) are copied
+//! into a content-addressed blob store under the data directory and referenced
+//! from the `attachments` table by hash. Blobs no longer referenced by any row
+//! are removed by `collect_garbage`.
+
+use serde::Serialize;
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+use crate::database::DatabaseManager;
+use crate::indexer;
+
+const KINDS: [&str; 4] = ["solution", "figure", "source", "other"];
+
+/// An attachment row
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub id: String,
+    pub resource_id: String,
+    pub kind: String,
+    pub file_name: String,
+    pub mime_type: Option<String>,
+    pub size: i64,
+    pub blob_hash: String,
+    pub description: Option<String>,
+    pub created_at: Option<String>,
+}
+
+/// Result of a garbage collection run
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcReport {
+    pub removed_rows: u64,
+    pub removed_blobs: usize,
+    pub freed_bytes: u64,
+}
+
+pub fn blob_root(db: &DatabaseManager) -> PathBuf {
+    db.data_dir.join("attachments")
+}
+
+/// `<root>/<first two hex chars>/<hash>`
+pub fn blob_path(root: &Path, hash: &str) -> PathBuf {
+    root.join(hash.get(..2).unwrap_or(hash)).join(hash)
+}
+
+/// Whether `name` is a blob hash: 64 lowercase hex digits, as `hash_bytes` writes
+pub fn is_blob_hash(name: &str) -> bool {
+    name.len() == 64
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// MIME type guessed from the file extension
+pub fn mime_from_name(name: &str) -> &'static str {
+    let ext = Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    match ext.as_str() {
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "eps" | "ps" => "application/postscript",
+        "tex" | "sty" | "cls" | "bib" => "text/x-tex",
+        "txt" | "md" => "text/plain",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+fn row_to_attachment(row: &sqlx::sqlite::SqliteRow) -> Attachment {
+    Attachment {
+        id: row.get("id"),
+        resource_id: row.get("resource_id"),
+        kind: row.get("kind"),
+        file_name: row.get("file_name"),
+        mime_type: row.get("mime_type"),
+        size: row.get("size"),
+        blob_hash: row.get("blob_hash"),
+        description: row.get("description"),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// Copy `source_path` into the blob store and attach it to a resource
+pub async fn attach(
+    db: &DatabaseManager,
+    resource_id: &str,
+    source_path: &str,
+    kind: &str,
+    description: Option<&str>,
+) -> Result<Attachment, String> {
+    if !KINDS.contains(&kind) {
+        return Err(format!(
+            "Invalid attachment kind '{}' (expected one of: {})",
+            kind,
+            KINDS.join(", ")
+        ));
+    }
+    if db.get_resource_by_id(resource_id).await?.is_none() {
+        return Err("Resource not found".to_string());
+    }
+
+    let bytes = fs::read(source_path).map_err(|e| format!("{}: {}", source_path, e))?;
+    let hash = indexer::hash_bytes(&bytes);
+    let file_name = Path::new(source_path)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    // Write the blob once; identical content is shared between attachments
+    let path = blob_path(&blob_root(db), &hash);
+    let wrote_blob = !path.exists();
+    if wrote_blob {
+        let dir = path.parent().ok_or("Invalid blob path")?;
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        let tmp = dir.join(format!(".{}.tmp", Uuid::new_v4()));
+        fs::write(&tmp, &bytes).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &path).map_err(|e| e.to_string())?;
+    }
+
+    let attachment = Attachment {
+        id: Uuid::new_v4().to_string(),
+        resource_id: resource_id.to_string(),
+        kind: kind.to_string(),
+        mime_type: Some(mime_from_name(&file_name).to_string()),
+        file_name,
+        size: bytes.len() as i64,
+        blob_hash: hash,
+        description: description.map(|d| d.to_string()),
+        created_at: None,
+    };
+
+    let result = sqlx::query(
+        "INSERT INTO attachments (id, resource_id, kind, file_name, mime_type, size, blob_hash, description)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&attachment.id)
+    .bind(&attachment.resource_id)
+    .bind(&attachment.kind)
+    .bind(&attachment.file_name)
+    .bind(&attachment.mime_type)
+    .bind(attachment.size)
+    .bind(&attachment.blob_hash)
+    .bind(&attachment.description)
+    .execute(&db.pool)
+    .await;
+
+    if let Err(e) = result {
+        if wrote_blob {
+            let _ = fs::remove_file(&path);
+        }
+        return Err(e.to_string());
+    }
+
+    Ok(attachment)
+}
+
+pub async fn list(pool: &Pool<Sqlite>, resource_id: &str) -> Result<Vec<Attachment>, String> {
+    let rows =
+        sqlx::query("SELECT * FROM attachments WHERE resource_id = ? ORDER BY kind, created_at")
+            .bind(resource_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    Ok(rows.iter().map(row_to_attachment).collect())
+}
+
+async fn get(pool: &Pool<Sqlite>, id: &str) -> Result<Attachment, String> {
+    let row = sqlx::query("SELECT * FROM attachments WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Attachment not found: {}", id))?;
+    Ok(row_to_attachment(&row))
+}
+
+/// Path of an attachment's blob, for opening it in place (read-only)
+pub async fn blob_path_of(db: &DatabaseManager, id: &str) -> Result<String, String> {
+    let attachment = get(&db.pool, id).await?;
+    let path = blob_path(&blob_root(db), &attachment.blob_hash);
+    if !path.exists() {
+        return Err(format!(
+            "Attachment content missing: {}",
+            attachment.file_name
+        ));
+    }
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Copy an attachment out of the store. A directory target keeps the original name.
+pub async fn export(db: &DatabaseManager, id: &str, target_path: &str) -> Result<String, String> {
+    let attachment = get(&db.pool, id).await?;
+    let source = blob_path(&blob_root(db), &attachment.blob_hash);
+
+    let mut target = PathBuf::from(target_path);
+    if target.is_dir() {
+        target = target.join(&attachment.file_name);
+    }
+    fs::copy(&source, &target).map_err(|e| format!("Failed to export attachment: {}", e))?;
+    Ok(target.to_string_lossy().to_string())
+}
+
+/// Remove an attachment row; its blob goes too if nothing else references it
+pub async fn delete(db: &DatabaseManager, id: &str) -> Result<(), String> {
+    let attachment = get(&db.pool, id).await?;
+    sqlx::query("DELETE FROM attachments WHERE id = ?")
+        .bind(id)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let still_used: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM attachments WHERE blob_hash = ?")
+            .bind(&attachment.blob_hash)
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    if still_used == 0 {
+        let _ = fs::remove_file(blob_path(&blob_root(db), &attachment.blob_hash));
+    }
+    Ok(())
+}
+
+/// Drop rows whose resource is gone and blobs that no row references
+pub async fn collect_garbage(db: &DatabaseManager) -> Result<GcReport, String> {
+    let removed_rows =
+        sqlx::query("DELETE FROM attachments WHERE resource_id NOT IN (SELECT id FROM resources)")
+            .execute(&db.pool)
+            .await
+            .map_err(|e| e.to_string())?
+            .rows_affected();
+    let mut report = GcReport {
+        removed_rows,
+        ..Default::default()
+    };
+
+    // Attachments of resources in the recycle bin come back on restore
+    let referenced: HashSet<String> = sqlx::query_scalar::<_, String>(
+        "SELECT blob_hash FROM attachments
+         UNION
+         SELECT json_extract(c.old_values, '$.blob_hash') FROM change_log c
+         JOIN deleted_resources d ON c.id > d.log_start AND c.id <= d.log_end
+         WHERE c.table_name = 'attachments' AND c.action = 'delete'
+           AND json_extract(c.old_values, '$.blob_hash') IS NOT NULL",
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .into_iter()
+    .collect();
+
+    let root = blob_root(db);
+    if !root.exists() {
+        return Ok(report);
+    }
+    for entry in WalkDir::new(&root)
+        .min_depth(2)
+        .max_depth(2)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+    {
+        let name = entry.file_name().to_string_lossy();
+        // Leftover temp files from interrupted writes are garbage as well
+        if referenced.contains(name.as_ref()) {
+            continue;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        if fs::remove_file(entry.path()).is_ok() {
+            report.removed_blobs += 1;
+            report.freed_bytes += size;
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob_path_is_sharded() {
+        let p = blob_path(Path::new("/data/attachments"), "abcdef");
+        assert_eq!(p, PathBuf::from("/data/attachments/ab/abcdef"));
+        // Never panics on a name that isn't a hash
+        blob_path(Path::new("/data/attachments"), "é");
+    }
+
+    #[test]
+    fn test_is_blob_hash() {
+        assert!(is_blob_hash(&indexer::hash_bytes(b"content")));
+        assert!(!is_blob_hash("abcdef"));
+        assert!(!is_blob_hash(&"A".repeat(64)));
+        assert!(!is_blob_hash(&format!("../{}", "a".repeat(61))));
+    }
+
+    #[test]
+    fn test_mime_from_name() {
+        assert_eq!(mime_from_name("Solution.PDF"), "application/pdf");
+        assert_eq!(mime_from_name("plot.jpeg"), "image/jpeg");
+        assert_eq!(mime_from_name("data.bin"), "application/octet-stream");
+    }
+}
@@ -0,0 +1,190 @@
+//! Citation checks.
+//!
+//! Cross-references the `\cite` keys of LaTeX documents against
+//! `bib_entries`: keys without an entry are reported per document, entries
+//! that no document cites are reported per .bib file. Both are returned as
+//! `LogEntry` diagnostics so the UI can show them like compiler output.
+
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use super::BibEntry;
+use crate::database::DatabaseManager;
+use crate::dependency_scanner;
+use crate::log_parser::LogEntry;
+
+/// Diagnostics of one document
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentCitations {
+    pub resource_id: String,
+    pub path: String,
+    pub citations: usize,
+    pub diagnostics: Vec<LogEntry>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CitationReport {
+    /// Documents that cite anything, with their undefined citations
+    pub documents: Vec<DocumentCitations>,
+    /// Entries no document cites
+    pub unused: Vec<LogEntry>,
+}
+
+fn diagnostic(kind: &str, message: String, line: usize, file: &str) -> LogEntry {
+    LogEntry {
+        r#type: kind.to_string(),
+        message,
+        line: line as i32,
+        file: Some(file.to_string()),
+    }
+}
+
+/// Line of each entry key in a .bib source
+fn entry_lines(src: &str) -> HashMap<String, usize> {
+    static ENTRY_RE: OnceLock<Regex> = OnceLock::new();
+    let re = ENTRY_RE.get_or_init(|| Regex::new(r"@\w+\s*[{(]\s*([^,\s]+)\s*,").unwrap());
+
+    re.captures_iter(src)
+        .map(|caps| {
+            let key = caps.get(1).unwrap();
+            let line = src[..key.start()].matches('\n').count() + 1;
+            (key.as_str().to_string(), line)
+        })
+        .collect()
+}
+
+/// Check the documents and bibliographies of the given collections (all if empty)
+pub async fn check_citations(
+    db: &DatabaseManager,
+    collections: &[String],
+) -> Result<CitationReport, String> {
+    let collections = if collections.is_empty() {
+        db.get_collections()
+            .await?
+            .into_iter()
+            .map(|c| c.name)
+            .collect()
+    } else {
+        collections.to_vec()
+    };
+    let resources = db.get_resources_by_collections(&collections).await?;
+    let entries = super::search_entries(&db.pool, "", &collections, i64::MAX).await?;
+
+    let mut by_key: HashMap<&str, Vec<&BibEntry>> = HashMap::new();
+    for entry in &entries {
+        by_key.entry(&entry.citation_key).or_default().push(entry);
+    }
+
+    let mut documents = Vec::new();
+    // (resource_id, key) of every entry some document cites
+    let mut used: HashSet<(String, String)> = HashSet::new();
+    // Bibliographies whose entries are all included by `\nocite{*}`
+    let mut cite_all: HashSet<String> = HashSet::new();
+
+    for doc in resources.iter().filter(|r| {
+        Path::new(&r.path)
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("tex"))
+    }) {
+        let Ok(content) = std::fs::read_to_string(&doc.path) else {
+            continue;
+        };
+        let citations = dependency_scanner::extract_citations(&content);
+        if citations.is_empty() {
+            continue;
+        }
+
+        // Documents that declare their .bib files only see those; included
+        // chapters without a \bibliography of their own see the whole scope.
+        let bibs: HashSet<String> = db
+            .get_dependencies(&doc.id, Some("bibliography"))
+            .await?
+            .into_iter()
+            .map(|r| r.id)
+            .collect();
+        let visible = |e: &BibEntry| bibs.is_empty() || bibs.contains(&e.resource_id);
+
+        let mut diagnostics = Vec::new();
+        let mut reported = HashSet::new();
+        for citation in &citations {
+            if citation.key == "*" {
+                if bibs.is_empty() {
+                    cite_all.extend(entries.iter().map(|e| e.resource_id.clone()));
+                } else {
+                    cite_all.extend(bibs.iter().cloned());
+                }
+                continue;
+            }
+
+            let found = by_key.get(citation.key.as_str());
+            let matching: Vec<&&BibEntry> = found
+                .map(|list| list.iter().filter(|e| visible(e)).collect())
+                .unwrap_or_default();
+            for entry in &matching {
+                used.insert((entry.resource_id.clone(), entry.citation_key.clone()));
+            }
+            if !matching.is_empty() || !reported.insert(citation.key.clone()) {
+                continue;
+            }
+
+            let message = match found.and_then(|list| list.first()) {
+                Some(elsewhere) => format!(
+                    "Citation '{}' is only defined in {}, which this document does not use",
+                    citation.key, elsewhere.resource_path
+                ),
+                None => format!("Citation '{}' undefined", citation.key),
+            };
+            diagnostics.push(diagnostic("warning", message, citation.line, &doc.path));
+        }
+
+        documents.push(DocumentCitations {
+            resource_id: doc.id.clone(),
+            path: doc.path.clone(),
+            citations: citations.iter().filter(|c| c.key != "*").count(),
+            diagnostics,
+        });
+    }
+
+    let mut unused = Vec::new();
+    let mut lines_cache: HashMap<String, HashMap<String, usize>> = HashMap::new();
+    for entry in &entries {
+        if cite_all.contains(&entry.resource_id)
+            || used.contains(&(entry.resource_id.clone(), entry.citation_key.clone()))
+        {
+            continue;
+        }
+        let lines = lines_cache
+            .entry(entry.resource_path.clone())
+            .or_insert_with(|| {
+                std::fs::read_to_string(&entry.resource_path)
+                    .map(|src| entry_lines(&src))
+                    .unwrap_or_default()
+            });
+        unused.push(diagnostic(
+            "info",
+            format!("Entry '{}' is never cited", entry.citation_key),
+            lines.get(&entry.citation_key).copied().unwrap_or(0),
+            &entry.resource_path,
+        ));
+    }
+
+    Ok(CitationReport { documents, unused })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_lines() {
+        let src = "% refs\n@article{knuth84,\n  title = {A}}\n\n@book( lamport94 ,\n title={B})";
+        let lines = entry_lines(src);
+        assert_eq!(lines.get("knuth84"), Some(&2));
+        assert_eq!(lines.get("lamport94"), Some(&5));
+    }
+}
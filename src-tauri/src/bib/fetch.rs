@@ -0,0 +1,619 @@
+//! DOI / arXiv metadata lookup.
+//!
+//! DOIs are resolved through the Crossref REST API and arXiv identifiers
+//! through the arXiv Atom API; both results are converted into an
+//! `EntryInput` that can be previewed or appended to a .bib resource.
+
+use regex::Regex;
+use serde::Serialize;
+use sqlx::{Pool, Sqlite};
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use super::writer::{BibField, EntryInput};
+use super::BibEntry;
+
+const CROSSREF_API: &str = "https://api.crossref.org/works/";
+const ARXIV_API: &str = "https://export.arxiv.org/api/query?id_list=";
+
+/// Title words skipped when building a citation key
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "the", "on", "of", "in", "for", "and", "to", "with",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Identifier {
+    Doi(String),
+    Arxiv(String),
+}
+
+/// A fetched entry, ready to be reviewed or appended
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchedEntry {
+    pub entry: EntryInput,
+    /// The entry as BibTeX source
+    pub bibtex: String,
+    /// Stored entries with the same DOI or arXiv id
+    pub existing: Vec<BibEntry>,
+    /// Set when the entry was appended to a .bib resource
+    pub appended: Option<BibEntry>,
+}
+
+fn new_arxiv_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^(\d{4}\.\d{4,5}|[a-z\-]+(\.[A-Z]{2})?/\d{7})(v\d+)?$").unwrap())
+}
+
+/// Recognise a DOI or arXiv id, with or without URL / `doi:` / `arXiv:` prefixes
+pub fn parse_identifier(input: &str) -> Result<Identifier, String> {
+    let mut id = input.trim();
+    for prefix in [
+        "https://doi.org/",
+        "http://doi.org/",
+        "https://dx.doi.org/",
+        "http://dx.doi.org/",
+        "doi:",
+        "DOI:",
+    ] {
+        if let Some(rest) = id.strip_prefix(prefix) {
+            return parse_doi(rest);
+        }
+    }
+    for prefix in [
+        "https://arxiv.org/abs/",
+        "http://arxiv.org/abs/",
+        "https://arxiv.org/pdf/",
+        "arXiv:",
+        "arxiv:",
+    ] {
+        if let Some(rest) = id.strip_prefix(prefix) {
+            id = rest.trim_end_matches(".pdf");
+            break;
+        }
+    }
+
+    if id.starts_with("10.") {
+        parse_doi(id)
+    } else if new_arxiv_re().is_match(id) {
+        Ok(Identifier::Arxiv(id.to_string()))
+    } else {
+        Err(format!("Not a DOI or arXiv identifier: {}", input.trim()))
+    }
+}
+
+fn parse_doi(doi: &str) -> Result<Identifier, String> {
+    let doi = doi.trim();
+    if doi.starts_with("10.") && doi.contains('/') && !doi.contains(char::is_whitespace) {
+        Ok(Identifier::Doi(doi.to_string()))
+    } else {
+        Err(format!("Invalid DOI: {}", doi))
+    }
+}
+
+/// Make free text safe inside a braced BibTeX value
+fn escape_text(text: &str) -> String {
+    static TAGS: OnceLock<Regex> = OnceLock::new();
+    let tags = TAGS.get_or_init(|| Regex::new(r"<[^>]+>").unwrap());
+
+    let collapsed = tags
+        .replace_all(text, "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut out = String::with_capacity(collapsed.len());
+    for c in collapsed.chars() {
+        match c {
+            '&' | '%' | '#' | '_' | '$' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '^' => out.push_str("\\textasciicircum{}"),
+            '~' => out.push_str("\\textasciitilde{}"),
+            // Stray braces from the source can't be balanced reliably
+            '{' | '}' => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// `<family><year><first title word>`, lowercase ASCII
+pub fn make_key(family: Option<&str>, year: Option<&str>, title: Option<&str>) -> String {
+    let ascii = |s: &str| -> String {
+        s.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase()
+    };
+    let word = title
+        .and_then(|t| {
+            t.split_whitespace()
+                .map(ascii)
+                .find(|w| !w.is_empty() && !STOP_WORDS.contains(&w.as_str()))
+        })
+        .unwrap_or_default();
+    let key = format!(
+        "{}{}{}",
+        family.map(ascii).unwrap_or_default(),
+        year.unwrap_or_default(),
+        word
+    );
+    if key.is_empty() {
+        "ref".to_string()
+    } else {
+        key
+    }
+}
+
+/// Text of an XML element with its entities resolved
+fn decode_xml(text: &str) -> String {
+    static ENTITY: OnceLock<Regex> = OnceLock::new();
+    let entity = ENTITY
+        .get_or_init(|| Regex::new(r"&(#x[0-9a-fA-F]+|#[0-9]+|amp|lt|gt|quot|apos);").unwrap());
+    entity
+        .replace_all(text, |caps: &regex::Captures| {
+            let name = &caps[1];
+            let decoded = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => match name.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => name[1..].parse().ok(),
+                }
+                .and_then(char::from_u32),
+            };
+            decoded.map_or_else(|| caps[0].to_string(), String::from)
+        })
+        .into_owned()
+}
+
+/// Crossref URL of `doi`; DOIs may contain `/`, `#`, `?` and the like
+fn crossref_url(doi: &str) -> Result<reqwest::Url, String> {
+    let mut url = reqwest::Url::parse(CROSSREF_API).map_err(|e| e.to_string())?;
+    url.path_segments_mut()
+        .map_err(|_| "Invalid Crossref URL".to_string())?
+        .pop_if_empty()
+        .push(doi);
+    Ok(url)
+}
+
+fn field(name: &str, value: impl Into<String>) -> BibField {
+    BibField {
+        name: name.to_string(),
+        value: value.into(),
+    }
+}
+
+fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(20))
+        .user_agent(concat!("DataTeX/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// Convert a Crossref `message` object into an entry
+pub fn from_crossref(message: &serde_json::Value) -> Result<EntryInput, String> {
+    let text = |key: &str| -> Option<String> {
+        match &message[key] {
+            serde_json::Value::String(s) => Some(s.clone()),
+            serde_json::Value::Array(a) => a.first().and_then(|v| v.as_str()).map(String::from),
+            serde_json::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        }
+        .filter(|s| !s.trim().is_empty())
+    };
+
+    let title = text("title").ok_or("Crossref record has no title")?;
+    let kind = message["type"].as_str().unwrap_or("");
+    let entry_type = match kind {
+        "journal-article" => "article",
+        "proceedings-article" => "inproceedings",
+        "book" | "monograph" | "edited-book" => "book",
+        "book-chapter" | "book-section" => "incollection",
+        "dissertation" => "phdthesis",
+        "report" => "techreport",
+        _ => "misc",
+    };
+
+    let authors: Vec<(Option<String>, String)> = message["author"]
+        .as_array()
+        .map(|list| {
+            list.iter()
+                .filter_map(|a| match (a["family"].as_str(), a["name"].as_str()) {
+                    (Some(family), _) => Some((
+                        Some(family.to_string()),
+                        match a["given"].as_str() {
+                            Some(given) => {
+                                format!("{}, {}", escape_text(family), escape_text(given))
+                            }
+                            None => escape_text(family),
+                        },
+                    )),
+                    // Organisations are kept as one unit
+                    (None, Some(name)) => Some((None, format!("{{{}}}", escape_text(name)))),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let year = ["issued", "published-print", "published-online", "created"]
+        .iter()
+        .find_map(|k| message[*k]["date-parts"][0][0].as_i64())
+        .map(|y| y.to_string());
+
+    let mut fields = Vec::new();
+    if !authors.is_empty() {
+        let names: Vec<&str> = authors.iter().map(|(_, n)| n.as_str()).collect();
+        fields.push(field("author", names.join(" and ")));
+    }
+    fields.push(field("title", escape_text(&title)));
+    let container_field = match entry_type {
+        "article" => Some("journal"),
+        "inproceedings" | "incollection" => Some("booktitle"),
+        _ => None,
+    };
+    if let (Some(name), Some(container)) = (container_field, text("container-title")) {
+        fields.push(field(name, escape_text(&container)));
+    }
+    if matches!(entry_type, "book" | "incollection" | "techreport" | "misc") {
+        if let Some(publisher) = text("publisher") {
+            fields.push(field("publisher", escape_text(&publisher)));
+        }
+    }
+    if let Some(volume) = text("volume") {
+        fields.push(field("volume", escape_text(&volume)));
+    }
+    if let Some(issue) = text("issue") {
+        fields.push(field("number", escape_text(&issue)));
+    }
+    if let Some(pages) = text("page") {
+        fields.push(field("pages", escape_text(&pages).replace('-', "--")));
+    }
+    if let Some(year) = &year {
+        fields.push(field("year", year.clone()));
+    }
+    if let Some(doi) = text("DOI") {
+        fields.push(field("doi", doi));
+    }
+    if let Some(url) = text("URL") {
+        fields.push(field("url", url));
+    }
+
+    let first_family = authors.iter().find_map(|(f, _)| f.as_deref());
+    Ok(EntryInput {
+        citation_key: make_key(first_family, year.as_deref(), Some(&title)),
+        entry_type: entry_type.to_string(),
+        fields,
+    })
+}
+
+/// Convert an arXiv Atom response into an entry
+pub fn from_arxiv(atom: &str, id: &str) -> Result<EntryInput, String> {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    let tag = TAG.get_or_init(|| {
+        Regex::new(r#"(?s)<(title|name|published|arxiv:doi|arxiv:journal_ref)[^>]*>(.*?)</"#)
+            .unwrap()
+    });
+    static CATEGORY: OnceLock<Regex> = OnceLock::new();
+    let category = CATEGORY
+        .get_or_init(|| Regex::new(r#"<arxiv:primary_category[^>]*term="([^"]+)""#).unwrap());
+
+    // Only look inside the entry; the feed has a title of its own
+    let entry = atom
+        .split_once("<entry>")
+        .map(|(_, rest)| rest)
+        .ok_or_else(|| format!("arXiv: no record for {}", id))?;
+
+    let mut title = None;
+    let mut authors = Vec::new();
+    let mut published = None;
+    let mut doi = None;
+    let mut journal_ref = None;
+    for cap in tag.captures_iter(entry) {
+        let value = decode_xml(&cap[2].split_whitespace().collect::<Vec<_>>().join(" "));
+        match &cap[1] {
+            "title" => title = Some(value),
+            "name" => authors.push(value),
+            "published" => published = Some(value),
+            "arxiv:doi" => doi = Some(value),
+            _ => journal_ref = Some(value),
+        }
+    }
+    let title = title.ok_or_else(|| format!("arXiv: no record for {}", id))?;
+    if title == "Error" {
+        return Err(format!("arXiv: no record for {}", id));
+    }
+
+    // The eprint field takes the id without its version suffix
+    let eprint = match id.rfind('v') {
+        Some(i) if i > 0 && i + 1 < id.len() && id[i + 1..].chars().all(|c| c.is_ascii_digit()) => {
+            &id[..i]
+        }
+        _ => id,
+    };
+    let year: Option<String> = published.map(|p| p.chars().take(4).collect());
+
+    let mut fields = Vec::new();
+    if !authors.is_empty() {
+        let names: Vec<String> = authors.iter().map(|a| escape_text(a)).collect();
+        fields.push(field("author", names.join(" and ")));
+    }
+    fields.push(field("title", escape_text(&title)));
+    if let Some(journal_ref) = journal_ref {
+        fields.push(field("note", escape_text(&journal_ref)));
+    }
+    if let Some(year) = &year {
+        fields.push(field("year", year.clone()));
+    }
+    fields.push(field("eprint", eprint));
+    fields.push(field("archiveprefix", "arXiv"));
+    if let Some(cap) = category.captures(entry) {
+        fields.push(field("primaryclass", &cap[1]));
+    }
+    if let Some(doi) = doi {
+        fields.push(field("doi", doi));
+    }
+    fields.push(field("url", format!("https://arxiv.org/abs/{}", eprint)));
+
+    let first_family = authors.first().and_then(|a| a.split_whitespace().last());
+    Ok(EntryInput {
+        citation_key: make_key(first_family, year.as_deref(), Some(&title)),
+        entry_type: "misc".to_string(),
+        fields,
+    })
+}
+
+/// Look up the metadata of a DOI or arXiv id
+pub async fn fetch(input: &str) -> Result<EntryInput, String> {
+    let client = client()?;
+    match parse_identifier(input)? {
+        Identifier::Doi(doi) => {
+            let response = client
+                .get(crossref_url(&doi)?)
+                .send()
+                .await
+                .map_err(|e| format!("Crossref request failed: {}", e))?;
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                return Err(format!("DOI not found: {}", doi));
+            }
+            if !response.status().is_success() {
+                return Err(format!("Crossref returned status {}", response.status()));
+            }
+            let body: serde_json::Value = response
+                .json()
+                .await
+                .map_err(|e| format!("Invalid Crossref response: {}", e))?;
+            from_crossref(&body["message"])
+        }
+        Identifier::Arxiv(id) => {
+            let response = client
+                .get(format!("{}{}", ARXIV_API, id))
+                .send()
+                .await
+                .map_err(|e| format!("arXiv request failed: {}", e))?;
+            if !response.status().is_success() {
+                return Err(format!("arXiv returned status {}", response.status()));
+            }
+            let body = response.text().await.map_err(|e| e.to_string())?;
+            from_arxiv(&body, &id)
+        }
+    }
+}
+
+/// Rename `entry` until its key is unused (`key`, `keya`, ..., `keyz`,
+/// `key2`, ...) and collect stored entries that already describe the same
+/// work. `reserved` holds the keys handed out earlier in the same batch;
+/// the new key is added to it.
+pub async fn deduplicate(
+    pool: &Pool<Sqlite>,
+    entry: &mut EntryInput,
+    reserved: &mut HashSet<String>,
+) -> Result<Vec<BibEntry>, String> {
+    let base = entry.citation_key.clone();
+    let suffixes = std::iter::once(String::new())
+        .chain(('a'..='z').map(String::from))
+        .chain((2..).map(|n: u32| n.to_string()));
+    for suffix in suffixes {
+        let candidate = format!("{}{}", base, suffix);
+        if !reserved.contains(&candidate) && super::find_by_key(pool, &candidate).await?.is_empty()
+        {
+            reserved.insert(candidate.clone());
+            entry.citation_key = candidate;
+            break;
+        }
+    }
+
+    let value = |name: &str| {
+        entry
+            .fields
+            .iter()
+            .find(|f| f.name == name)
+            .map(|f| f.value.clone())
+    };
+    let mut existing = Vec::new();
+    for (name, value) in [("doi", value("doi")), ("eprint", value("eprint"))] {
+        if let Some(value) = value {
+            for found in super::find_by_field(pool, name, &value).await? {
+                if !existing.iter().any(|e: &BibEntry| e.id == found.id) {
+                    existing.push(found);
+                }
+            }
+        }
+    }
+    Ok(existing)
+}
+
+/// BibTeX source of a fetched entry
+pub fn to_bibtex(entry: &EntryInput) -> String {
+    super::writer::insert_entry("", entry).unwrap_or_default()
+}
+
+impl FetchedEntry {
+    pub fn new(entry: &EntryInput, existing: Vec<BibEntry>) -> Self {
+        FetchedEntry {
+            entry: entry.clone(),
+            bibtex: to_bibtex(entry),
+            existing,
+            appended: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_identifier() {
+        assert_eq!(
+            parse_identifier("https://doi.org/10.1093/comjnl/27.2.97"),
+            Ok(Identifier::Doi("10.1093/comjnl/27.2.97".to_string()))
+        );
+        assert_eq!(
+            parse_identifier("arXiv:1706.03762v5"),
+            Ok(Identifier::Arxiv("1706.03762v5".to_string()))
+        );
+        assert_eq!(
+            parse_identifier("hep-th/9711200"),
+            Ok(Identifier::Arxiv("hep-th/9711200".to_string()))
+        );
+        assert!(parse_identifier("not an id").is_err());
+    }
+
+    #[test]
+    fn test_from_crossref() {
+        let message = serde_json::json!({
+            "type": "journal-article",
+            "title": ["Literate Programming & <i>WEB</i>"],
+            "author": [{"given": "Donald E.", "family": "Knuth"}],
+            "container-title": ["The Computer Journal"],
+            "volume": "27",
+            "issue": "2",
+            "page": "97-111",
+            "issued": {"date-parts": [[1984, 2, 1]]},
+            "DOI": "10.1093/comjnl/27.2.97"
+        });
+        let entry = from_crossref(&message).unwrap();
+        assert_eq!(entry.citation_key, "knuth1984literate");
+        assert_eq!(entry.entry_type, "article");
+        let get = |n: &str| {
+            entry
+                .fields
+                .iter()
+                .find(|f| f.name == n)
+                .unwrap()
+                .value
+                .clone()
+        };
+        assert_eq!(get("title"), "Literate Programming \\& WEB");
+        assert_eq!(get("author"), "Knuth, Donald E.");
+        assert_eq!(get("pages"), "97--111");
+        assert_eq!(get("journal"), "The Computer Journal");
+    }
+
+    #[test]
+    fn test_from_arxiv() {
+        let atom = r#"<feed><title>ArXiv Query</title><entry>
+            <id>http://arxiv.org/abs/1706.03762v5</id>
+            <published>2017-06-12T17:57:34Z</published>
+            <title>Attention Is All
+              You Need</title>
+            <author><name>Ashish Vaswani</name></author>
+            <author><name>Noam Shazeer</name></author>
+            <arxiv:primary_category xmlns:arxiv="http://arxiv.org/schemas/atom" term="cs.CL" scheme="http://arxiv.org/schemas/atom"/>
+        </entry></feed>"#;
+        let entry = from_arxiv(atom, "1706.03762v5").unwrap();
+        assert_eq!(entry.citation_key, "vaswani2017attention");
+        let get = |n: &str| {
+            entry
+                .fields
+                .iter()
+                .find(|f| f.name == n)
+                .unwrap()
+                .value
+                .clone()
+        };
+        assert_eq!(get("title"), "Attention Is All You Need");
+        assert_eq!(get("author"), "Ashish Vaswani and Noam Shazeer");
+        assert_eq!(get("eprint"), "1706.03762");
+        assert_eq!(get("primaryclass"), "cs.CL");
+    }
+
+    #[test]
+    fn test_from_arxiv_decodes_entities() {
+        let atom = r#"<feed><entry>
+            <published>2020-01-01T00:00:00Z</published>
+            <title>Proofs &amp; Types for &#x3BB;-calculus &quot;redux&quot;</title>
+            <author><name>Jean-Yves Girard</name></author>
+        </entry></feed>"#;
+        let entry = from_arxiv(atom, "2001.00001").unwrap();
+        let title = &entry
+            .fields
+            .iter()
+            .find(|f| f.name == "title")
+            .unwrap()
+            .value;
+        assert_eq!(title, "Proofs \\& Types for λ-calculus \"redux\"");
+    }
+
+    #[test]
+    fn test_escape_text() {
+        assert_eq!(
+            escape_text("50% of #1 & a_b costs $5 ^ ~ {x}"),
+            "50\\% of \\#1 \\& a\\_b costs \\$5 \\textasciicircum{} \\textasciitilde{} x"
+        );
+    }
+
+    #[test]
+    fn test_crossref_url() {
+        assert_eq!(
+            crossref_url("10.1002/(SICI)1097-4571#x?y z")
+                .unwrap()
+                .as_str(),
+            "https://api.crossref.org/works/10.1002%2F(SICI)1097-4571%23x%3Fy%20z"
+        );
+    }
+
+    #[test]
+    fn test_deduplicate_skips_reserved_keys() {
+        let dir = std::env::temp_dir().join(format!("datatex-fetch-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let db = crate::database::DatabaseManager::new(&dir.to_string_lossy())
+                .await
+                .unwrap();
+            sqlx::raw_sql(
+                "INSERT INTO collections (name, type) VALUES ('refs', 'manual');
+                 INSERT INTO resources (id, path, type, collection) VALUES ('r', '/refs.bib', 'bibliography', 'refs');
+                 INSERT INTO bib_entries (resource_id, citation_key, entry_type, fields)
+                 VALUES ('r', 'knuth1984literate', 'article', '{}');",
+            )
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+            let mut reserved = HashSet::new();
+            let mut keys = Vec::new();
+            for _ in 0..2 {
+                let mut entry = EntryInput {
+                    citation_key: "knuth1984literate".to_string(),
+                    entry_type: "article".to_string(),
+                    fields: Vec::new(),
+                };
+                deduplicate(&db.pool, &mut entry, &mut reserved).await.unwrap();
+                keys.push(entry.citation_key);
+            }
+            assert_eq!(keys, ["knuth1984literatea", "knuth1984literateb"]);
+            db.pool.close().await;
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,417 @@
+//! Bibliography Entries Module
+//!
+//! Parses `.bib` resources with the `biblatex` crate into `bib_entries` rows
+//! (migration 020), one per citation key, so citation pickers and `\cite`
+//! checks can query keys without re-reading files. Entries are edited through
+//! `writer`, which rewrites the .bib file and re-indexes it.
+
+pub mod check;
+pub mod fetch;
+pub mod writer;
+pub mod zotero;
+
+use biblatex::{Bibliography, ChunksExt, EntryType};
+use serde::Serialize;
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::BTreeMap;
+
+use crate::database::entities::Resource;
+use crate::database::DatabaseManager;
+use crate::indexer;
+use writer::EntryInput;
+
+/// A parsed entry, before it is stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedEntry {
+    pub key: String,
+    pub entry_type: String,
+    pub fields: BTreeMap<String, String>,
+    pub title: Option<String>,
+    pub authors: Option<String>,
+    pub year: Option<String>,
+}
+
+/// A stored entry
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BibEntry {
+    pub id: i64,
+    pub resource_id: String,
+    pub resource_path: String,
+    pub citation_key: String,
+    pub entry_type: String,
+    pub fields: serde_json::Value,
+    pub title: Option<String>,
+    pub authors: Option<String>,
+    pub year: Option<String>,
+}
+
+fn entry_type_name(entry_type: &EntryType) -> String {
+    match entry_type {
+        EntryType::Unknown(name) => name.to_lowercase(),
+        other => other.to_string(),
+    }
+}
+
+/// Parse the contents of a .bib file
+pub fn parse_entries(src: &str) -> Result<Vec<ParsedEntry>, String> {
+    let bibliography = Bibliography::parse(src).map_err(|e| format!("Invalid .bib file: {}", e))?;
+
+    Ok(bibliography
+        .iter()
+        .map(|entry| {
+            let fields: BTreeMap<String, String> = entry
+                .fields
+                .iter()
+                .map(|(k, v)| (k.to_lowercase(), v.format_verbatim()))
+                .collect();
+
+            let authors = entry.author().ok().map(|persons| {
+                persons
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            });
+            let year = fields
+                .get("year")
+                .cloned()
+                .or_else(|| fields.get("date").map(|d| d.chars().take(4).collect()));
+
+            ParsedEntry {
+                key: entry.key.clone(),
+                entry_type: entry_type_name(&entry.entry_type),
+                title: fields.get("title").cloned(),
+                authors: authors.filter(|a| !a.is_empty()),
+                year,
+                fields,
+            }
+        })
+        .collect())
+}
+
+/// Replace the stored entries of a .bib resource with the parsed content.
+/// Returns the number of entries.
+pub async fn sync_resource(
+    pool: &Pool<Sqlite>,
+    resource_id: &str,
+    content: &str,
+) -> Result<usize, String> {
+    let entries = parse_entries(content)?;
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query("DELETE FROM bib_entries WHERE resource_id = ?")
+        .bind(resource_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for entry in &entries {
+        let fields = serde_json::to_string(&entry.fields).map_err(|e| e.to_string())?;
+        sqlx::query(
+            "INSERT INTO bib_entries (resource_id, citation_key, entry_type, fields, title, authors, year)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(resource_id)
+        .bind(&entry.key)
+        .bind(&entry.entry_type)
+        .bind(fields)
+        .bind(&entry.title)
+        .bind(&entry.authors)
+        .bind(&entry.year)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(entries.len())
+}
+
+/// Re-read the given bibliography resources from disk. Returns the total
+/// number of entries; files that fail to parse are reported as errors.
+pub async fn rebuild(pool: &Pool<Sqlite>, resources: &[Resource]) -> (usize, Vec<String>) {
+    let mut total = 0;
+    let mut errors = Vec::new();
+    for resource in resources.iter().filter(|r| r.kind == "bibliography") {
+        let result = match std::fs::read_to_string(&resource.path) {
+            Ok(content) => sync_resource(pool, &resource.id, &content).await,
+            Err(e) => Err(e.to_string()),
+        };
+        match result {
+            Ok(n) => total += n,
+            Err(e) => errors.push(format!("{}: {}", resource.path, e)),
+        }
+    }
+    (total, errors)
+}
+
+/// Sync a bibliography resource from its file; other kinds are ignored.
+/// Parse errors are logged rather than failing the caller.
+pub async fn sync_file(pool: &Pool<Sqlite>, resource: &Resource) {
+    if resource.kind != "bibliography" {
+        return;
+    }
+    let result = match std::fs::read_to_string(&resource.path) {
+        Ok(content) => sync_resource(pool, &resource.id, &content).await,
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = result {
+        eprintln!("Bibliography sync failed for {}: {}", resource.path, e);
+    }
+}
+
+fn row_to_entry(row: &sqlx::sqlite::SqliteRow) -> BibEntry {
+    let fields: String = row.get("fields");
+    BibEntry {
+        id: row.get("id"),
+        resource_id: row.get("resource_id"),
+        resource_path: row.get("path"),
+        citation_key: row.get("citation_key"),
+        entry_type: row.get("entry_type"),
+        fields: serde_json::from_str(&fields).unwrap_or(serde_json::json!({})),
+        title: row.get("title"),
+        authors: row.get("authors"),
+        year: row.get("year"),
+    }
+}
+
+/// List or search entries. `query` matches key, title and authors; an empty
+/// `collections` list means all collections.
+pub async fn search_entries(
+    pool: &Pool<Sqlite>,
+    query: &str,
+    collections: &[String],
+    limit: i64,
+) -> Result<Vec<BibEntry>, String> {
+    let mut sql = String::from(
+        "SELECT b.*, r.path FROM bib_entries b
+         JOIN resources r ON r.id = b.resource_id
+         WHERE 1 = 1",
+    );
+    let query = query.trim();
+    if !query.is_empty() {
+        sql.push_str(" AND (b.citation_key LIKE ? OR b.title LIKE ? OR b.authors LIKE ?)");
+    }
+    if !collections.is_empty() {
+        let placeholders: Vec<&str> = collections.iter().map(|_| "?").collect();
+        sql.push_str(&format!(
+            " AND r.collection IN ({})",
+            placeholders.join(", ")
+        ));
+    }
+    sql.push_str(" ORDER BY b.citation_key LIMIT ?");
+
+    let mut q = sqlx::query(&sql);
+    if !query.is_empty() {
+        let pattern = format!("%{}%", query);
+        q = q.bind(pattern.clone()).bind(pattern.clone()).bind(pattern);
+    }
+    for c in collections {
+        q = q.bind(c);
+    }
+    let rows = q
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(rows.iter().map(row_to_entry).collect())
+}
+
+/// Entries with the given citation key (a key may appear in several .bib files)
+pub async fn find_by_key(pool: &Pool<Sqlite>, key: &str) -> Result<Vec<BibEntry>, String> {
+    let rows = sqlx::query(
+        "SELECT b.*, r.path FROM bib_entries b
+         JOIN resources r ON r.id = b.resource_id
+         WHERE b.citation_key = ?",
+    )
+    .bind(key)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows.iter().map(row_to_entry).collect())
+}
+
+/// Entries whose field `name` equals `value` (case-insensitive), e.g. a DOI
+pub async fn find_by_field(
+    pool: &Pool<Sqlite>,
+    name: &str,
+    value: &str,
+) -> Result<Vec<BibEntry>, String> {
+    let rows = sqlx::query(
+        "SELECT b.*, r.path FROM bib_entries b
+         JOIN resources r ON r.id = b.resource_id
+         WHERE json_extract(b.fields, ?) = ? COLLATE NOCASE",
+    )
+    .bind(format!("$.{}", name))
+    .bind(value)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows.iter().map(row_to_entry).collect())
+}
+
+/// Stored entry of one resource
+async fn get_entry(pool: &Pool<Sqlite>, resource_id: &str, key: &str) -> Result<BibEntry, String> {
+    let row = sqlx::query(
+        "SELECT b.*, r.path FROM bib_entries b
+         JOIN resources r ON r.id = b.resource_id
+         WHERE b.resource_id = ? AND b.citation_key = ?",
+    )
+    .bind(resource_id)
+    .bind(key)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Entry not found: {}", key))?;
+    Ok(row_to_entry(&row))
+}
+
+/// Fail if `key` is already used anywhere in the project. BibTeX compares
+/// keys case-insensitively, so `Knuth84` and `knuth84` clash.
+async fn ensure_unique_key(
+    pool: &Pool<Sqlite>,
+    key: &str,
+    except: Option<(&str, &str)>,
+) -> Result<(), String> {
+    let (except_resource, except_key) = except.unwrap_or(("", ""));
+    let clash: Option<String> = sqlx::query_scalar(
+        "SELECT r.path FROM bib_entries b
+         JOIN resources r ON r.id = b.resource_id
+         WHERE b.citation_key = ? COLLATE NOCASE
+           AND NOT (b.resource_id = ? AND b.citation_key = ?)
+         LIMIT 1",
+    )
+    .bind(key)
+    .bind(except_resource)
+    .bind(except_key)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    match clash {
+        Some(path) => Err(format!(
+            "Citation key '{}' is already used in {}",
+            key, path
+        )),
+        None => Ok(()),
+    }
+}
+
+async fn load_bib_resource(
+    db: &DatabaseManager,
+    resource_id: &str,
+) -> Result<(Resource, String), String> {
+    let resource = db
+        .get_resource_by_id(resource_id)
+        .await?
+        .ok_or("Resource not found")?;
+    if resource.kind != "bibliography" {
+        return Err(format!("{} is not a bibliography", resource.path));
+    }
+    let content =
+        std::fs::read_to_string(&resource.path).map_err(|e| format!("{}: {}", resource.path, e))?;
+    Ok((resource, content))
+}
+
+/// Write the new file content and re-index the resource (hash, FTS, entries)
+async fn write_back(
+    db: &DatabaseManager,
+    resource: &Resource,
+    content: &str,
+) -> Result<(), String> {
+    // Never write a file we can't read back
+    parse_entries(content)?;
+
+    let path = std::path::Path::new(&resource.path);
+    let tmp = path.with_extension("bib.tmp");
+    std::fs::write(&tmp, content).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, path).map_err(|e| e.to_string())?;
+
+    indexer::index_file(db, path, &resource.collection, Some(resource)).await?;
+    Ok(())
+}
+
+/// Append a new entry to a .bib resource
+pub async fn create_entry(
+    db: &DatabaseManager,
+    resource_id: &str,
+    entry: &EntryInput,
+) -> Result<BibEntry, String> {
+    let (resource, content) = load_bib_resource(db, resource_id).await?;
+    let key = entry.citation_key.trim();
+    ensure_unique_key(&db.pool, key, None).await?;
+
+    let updated = writer::insert_entry(&content, entry)?;
+    write_back(db, &resource, &updated).await?;
+    get_entry(&db.pool, resource_id, key).await
+}
+
+/// Rewrite the entry `original_key` of a .bib resource, possibly renaming it
+pub async fn update_entry(
+    db: &DatabaseManager,
+    resource_id: &str,
+    original_key: &str,
+    entry: &EntryInput,
+) -> Result<BibEntry, String> {
+    let (resource, content) = load_bib_resource(db, resource_id).await?;
+    let key = entry.citation_key.trim();
+    if key != original_key {
+        ensure_unique_key(&db.pool, key, Some((resource_id, original_key))).await?;
+    }
+
+    let updated = writer::replace_entry(&content, original_key, entry)?;
+    write_back(db, &resource, &updated).await?;
+    get_entry(&db.pool, resource_id, key).await
+}
+
+/// Remove an entry from a .bib resource
+pub async fn delete_entry(
+    db: &DatabaseManager,
+    resource_id: &str,
+    key: &str,
+) -> Result<(), String> {
+    let (resource, content) = load_bib_resource(db, resource_id).await?;
+    let updated = writer::remove_entry(&content, key)?;
+    write_back(db, &resource, &updated).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entries() {
+        let src = r#"
+@article{knuth1984,
+  author = {Donald E. Knuth and Leslie Lamport},
+  title = {Literate {P}rogramming},
+  journal = {The Computer Journal},
+  year = 1984,
+}
+@online{site, title = {A Site}, date = {2020-05-01}}
+"#;
+        let entries = parse_entries(src).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let knuth = entries.iter().find(|e| e.key == "knuth1984").unwrap();
+        assert_eq!(knuth.entry_type, "article");
+        assert_eq!(knuth.title.as_deref(), Some("Literate Programming"));
+        assert_eq!(
+            knuth.authors.as_deref(),
+            Some("Donald E. Knuth; Leslie Lamport")
+        );
+        assert_eq!(knuth.year.as_deref(), Some("1984"));
+
+        let site = entries.iter().find(|e| e.key == "site").unwrap();
+        assert_eq!(site.year.as_deref(), Some("2020"));
+    }
+
+    #[test]
+    fn test_parse_error_is_reported() {
+        assert!(parse_entries("@article{dup, title={A}}\n@article{dup, title={B}}").is_err());
+    }
+}
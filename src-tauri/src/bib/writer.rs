@@ -0,0 +1,349 @@
+//! Format-preserving edits of .bib sources.
+//!
+//! Entries are located with `RawBibliography`, which keeps source spans, so
+//! an edit only rewrites the entry it touches. Within an edited entry, fields
+//! whose value did not change keep their original text (delimiters, spacing,
+//! `#` concatenations), the original field order is kept and new fields are
+//! appended at the end.
+
+use biblatex::RawBibliography;
+use serde::{Deserialize, Serialize};
+
+use super::parse_entries;
+
+/// A field of an entry as edited in the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BibField {
+    pub name: String,
+    pub value: String,
+}
+
+/// A complete entry to create, or the new state of an updated entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryInput {
+    pub citation_key: String,
+    pub entry_type: String,
+    pub fields: Vec<BibField>,
+}
+
+/// Source layout of an existing entry
+struct Located {
+    /// From the `@` to just after the closing delimiter
+    start: usize,
+    end: usize,
+    kind: String,
+    indent: String,
+    separator: String,
+    trailing_comma: bool,
+    /// (lowercased name, original `name = value` text, separator)
+    fields: Vec<(String, String, String)>,
+}
+
+fn is_key_char(c: char) -> bool {
+    !c.is_whitespace()
+        && !matches!(
+            c,
+            ',' | '{' | '}' | '"' | '#' | '%' | '\'' | '(' | ')' | '=' | '\\'
+        )
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '.' | '+')
+}
+
+fn braces_balanced(value: &str) -> bool {
+    let mut depth = 0i32;
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth < 0 {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+    }
+    depth == 0
+}
+
+/// Check an entry before it is written
+pub fn validate(entry: &EntryInput) -> Result<(), String> {
+    let key = entry.citation_key.trim();
+    if key.is_empty() {
+        return Err("Citation key is empty".to_string());
+    }
+    if !key.chars().all(is_key_char) {
+        return Err(format!("Invalid citation key '{}'", key));
+    }
+    if entry.entry_type.is_empty() || !entry.entry_type.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("Invalid entry type '{}'", entry.entry_type));
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    for field in &entry.fields {
+        let name = field.name.trim();
+        if name.is_empty() || !name.chars().all(is_name_char) {
+            return Err(format!("Invalid field name '{}'", field.name));
+        }
+        if !seen.insert(name.to_lowercase()) {
+            return Err(format!("Duplicate field '{}'", name));
+        }
+        if !braces_balanced(&field.value) {
+            return Err(format!("Unbalanced braces in field '{}'", name));
+        }
+    }
+    Ok(())
+}
+
+fn newline_of(src: &str) -> &'static str {
+    if src.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+fn locate(src: &str, key: &str) -> Result<Option<Located>, String> {
+    let raw = RawBibliography::parse(src).map_err(|e| format!("Invalid .bib file: {}", e))?;
+    let Some(entry) = raw.entries.iter().find(|e| e.v.key.v == key) else {
+        return Ok(None);
+    };
+
+    // The entry span stops before the closing delimiter
+    let close = src[entry.span.end..]
+        .find(['}', ')'])
+        .map(|i| entry.span.end + i + 1)
+        .unwrap_or(entry.span.end);
+
+    let fields = &entry.v.fields;
+    let indent = fields
+        .first()
+        .and_then(|f| {
+            let line = src[..f.key.span.start].rsplit('\n').next()?;
+            line.chars()
+                .all(|c| c == ' ' || c == '\t')
+                .then(|| line.to_string())
+        })
+        .filter(|i| !i.is_empty())
+        .unwrap_or_else(|| "  ".to_string());
+    let separator = fields
+        .first()
+        .map(|f| src[f.key.span.end..f.value.span.start].to_string())
+        .unwrap_or_else(|| " = ".to_string());
+    let trailing_comma = fields
+        .last()
+        .map(|f| src[f.value.span.end..].trim_start().starts_with(','))
+        .unwrap_or(false);
+
+    Ok(Some(Located {
+        start: entry.span.start,
+        end: close,
+        kind: entry.v.kind.v.to_string(),
+        indent,
+        separator,
+        trailing_comma,
+        fields: fields
+            .iter()
+            .map(|f| {
+                (
+                    f.key.v.to_lowercase(),
+                    src[f.key.span.start..f.value.span.end]
+                        .trim_end()
+                        .to_string(),
+                    src[f.key.span.end..f.value.span.start].to_string(),
+                )
+            })
+            .collect(),
+    }))
+}
+
+/// Render `entry`, reusing the layout and unchanged field text of `original`
+fn render(src: &str, entry: &EntryInput, original: Option<(&Located, &str)>) -> String {
+    let nl = newline_of(src);
+    let old_values = original
+        .and_then(|(_, key)| {
+            parse_entries(src)
+                .ok()?
+                .into_iter()
+                .find(|e| e.key == key)
+                .map(|e| e.fields)
+        })
+        .unwrap_or_default();
+
+    let (indent, separator, trailing_comma) = match original {
+        Some((loc, _)) => (
+            loc.indent.as_str(),
+            loc.separator.as_str(),
+            loc.trailing_comma,
+        ),
+        None => ("  ", " = ", false),
+    };
+    let kind = match original {
+        Some((loc, _)) if loc.kind.eq_ignore_ascii_case(&entry.entry_type) => loc.kind.clone(),
+        _ => entry.entry_type.to_lowercase(),
+    };
+
+    let written = |field: &BibField, separator: &str| {
+        format!("{}{}{{{}}}", field.name.trim(), separator, field.value)
+    };
+    let find = |name: &str| {
+        entry
+            .fields
+            .iter()
+            .find(|f| f.name.trim().eq_ignore_ascii_case(name))
+    };
+
+    let mut lines = Vec::new();
+    let mut kept = std::collections::HashSet::new();
+    if let Some((loc, _)) = original {
+        for (name, text, field_separator) in &loc.fields {
+            let Some(field) = find(name) else {
+                continue;
+            };
+            kept.insert(name.clone());
+            if old_values.get(name) == Some(&field.value) {
+                lines.push(text.clone());
+            } else {
+                lines.push(written(field, field_separator));
+            }
+        }
+    }
+    for field in &entry.fields {
+        if !kept.contains(&field.name.trim().to_lowercase()) {
+            lines.push(written(field, separator));
+        }
+    }
+
+    let mut out = format!("@{}{{{}", kind, entry.citation_key.trim());
+    for line in &lines {
+        out.push(',');
+        out.push_str(nl);
+        out.push_str(indent);
+        out.push_str(line);
+    }
+    if trailing_comma && !lines.is_empty() {
+        out.push(',');
+    }
+    out.push_str(nl);
+    out.push('}');
+    out
+}
+
+/// Append a new entry at the end of the file
+pub fn insert_entry(src: &str, entry: &EntryInput) -> Result<String, String> {
+    validate(entry)?;
+    let nl = newline_of(src);
+    let mut out = src.trim_end().to_string();
+    if !out.is_empty() {
+        out.push_str(nl);
+        out.push_str(nl);
+    }
+    out.push_str(&render(src, entry, None));
+    out.push_str(nl);
+    Ok(out)
+}
+
+/// Replace the entry `original_key` with `entry`, in place
+pub fn replace_entry(src: &str, original_key: &str, entry: &EntryInput) -> Result<String, String> {
+    validate(entry)?;
+    let loc =
+        locate(src, original_key)?.ok_or_else(|| format!("Entry not found: {}", original_key))?;
+    let rendered = render(src, entry, Some((&loc, original_key)));
+    Ok(format!(
+        "{}{}{}",
+        &src[..loc.start],
+        rendered,
+        &src[loc.end..]
+    ))
+}
+
+/// Remove the entry `key` together with the line break that follows it
+pub fn remove_entry(src: &str, key: &str) -> Result<String, String> {
+    let loc = locate(src, key)?.ok_or_else(|| format!("Entry not found: {}", key))?;
+    let rest = &src[loc.end..];
+    let rest = rest
+        .strip_prefix("\r\n")
+        .or_else(|| rest.strip_prefix('\n'))
+        .unwrap_or(rest);
+
+    // Don't leave a run of blank lines where the entry was
+    let before = &src[..loc.start];
+    let rest = if before.ends_with("\n\n") || before.ends_with("\r\n\r\n") || before.is_empty() {
+        rest.trim_start_matches(['\r', '\n'])
+    } else {
+        rest
+    };
+    Ok(format!("{}{}", before, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = "% references\n\n@Article{knuth1984,\n    author  = \"Donald E. Knuth\",\n    title   = {Literate {P}rogramming},\n    year    = 1984,\n}\n\n@book{lamport1994,\n    title = {LaTeX},\n}\n";
+
+    fn field(name: &str, value: &str) -> BibField {
+        BibField {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_replace_keeps_untouched_fields() {
+        let entry = EntryInput {
+            citation_key: "knuth1984".to_string(),
+            entry_type: "article".to_string(),
+            fields: vec![
+                field("author", "Donald E. Knuth"),
+                field("title", "Literate Programming"),
+                field("year", "1992"),
+                field("journal", "The Computer Journal"),
+            ],
+        };
+        let out = replace_entry(SRC, "knuth1984", &entry).unwrap();
+        assert_eq!(
+            out,
+            "% references\n\n@Article{knuth1984,\n    author  = \"Donald E. Knuth\",\n    title   = {Literate {P}rogramming},\n    year    = {1992},\n    journal  = {The Computer Journal},\n}\n\n@book{lamport1994,\n    title = {LaTeX},\n}\n"
+        );
+        assert_eq!(parse_entries(&out).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let entry = EntryInput {
+            citation_key: "new2024".to_string(),
+            entry_type: "misc".to_string(),
+            fields: vec![field("title", "New")],
+        };
+        let added = insert_entry(SRC, &entry).unwrap();
+        assert!(added.ends_with("}\n\n@misc{new2024,\n  title = {New}\n}\n"));
+
+        let removed = remove_entry(SRC, "knuth1984").unwrap();
+        assert_eq!(
+            removed,
+            "% references\n\n@book{lamport1994,\n    title = {LaTeX},\n}\n"
+        );
+        assert!(remove_entry(SRC, "missing").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut entry = EntryInput {
+            citation_key: "a key".to_string(),
+            entry_type: "book".to_string(),
+            fields: vec![],
+        };
+        assert!(validate(&entry).is_err());
+        entry.citation_key = "ok".to_string();
+        entry.fields = vec![field("title", "Unbalanced {")];
+        assert!(validate(&entry).is_err());
+    }
+}
@@ -0,0 +1,684 @@
+//! Zotero sync through Better BibTeX.
+//!
+//! Zotero stays the place where references are managed: a .bib resource is
+//! linked to one or more Zotero collections (`zotero_links`, migration 030)
+//! and rewritten from Better BibTeX's pull export of them on every sync.
+//! Better BibTeX is asked through its JSON-RPC endpoint whether it is
+//! running; the collection list comes from Zotero's own local API.
+//!
+//! Citation keys are kept stable across syncs: when an entry that was in the
+//! file before (same DOI, eprint, or title and year) comes back under a
+//! different key, it keeps the key the documents already cite.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use super::{parse_entries, ParsedEntry};
+use crate::database::DatabaseManager;
+use crate::indexer;
+
+/// Zotero's connector server, which Better BibTeX extends
+const ZOTERO_HOST: &str = "http://127.0.0.1:23119";
+
+/// First line of a synced file
+const MANAGED_HEADER: &str =
+    "% Synced from Zotero by DataTeX. Edit the references in Zotero; changes here are overwritten.";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ZoteroFormat {
+    #[default]
+    Biblatex,
+    Bibtex,
+}
+
+impl ZoteroFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            ZoteroFormat::Biblatex => "biblatex",
+            ZoteroFormat::Bibtex => "bibtex",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "bibtex" => ZoteroFormat::Bibtex,
+            _ => ZoteroFormat::Biblatex,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZoteroStatus {
+    pub zotero_version: Option<String>,
+    pub better_bibtex_version: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZoteroCollection {
+    pub key: String,
+    pub name: String,
+    /// Names from the top-level collection down, joined with `/`
+    pub path: String,
+}
+
+/// What a .bib resource is synced from
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZoteroLink {
+    pub resource_id: String,
+    pub library_id: i64,
+    /// Collection keys, or paths like `Thesis/Chapter 2`
+    pub collections: Vec<String>,
+    pub format: ZoteroFormat,
+    pub last_synced_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZoteroSyncRequest {
+    /// A .bib resource synced before
+    pub resource_id: Option<String>,
+    /// Where to create a new synced .bib, with the collection it goes into
+    pub path: Option<String>,
+    pub collection: Option<String>,
+    /// Zotero collections to pull; the linked ones when not given
+    pub collections: Option<Vec<String>>,
+    pub format: Option<ZoteroFormat>,
+    /// Zotero library; 1 is the user's own library
+    pub library_id: Option<i64>,
+}
+
+/// An entry whose Zotero key was replaced by the key it had before
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeptKey {
+    pub zotero_key: String,
+    pub kept_key: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZoteroSyncResult {
+    pub resource_id: String,
+    pub path: String,
+    pub entries: usize,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub kept_keys: Vec<KeptKey>,
+    /// Keys also defined in other .bib resources
+    pub warnings: Vec<String>,
+}
+
+/// The new file content and how it differs from the old one
+#[derive(Debug)]
+pub struct Merged {
+    pub content: String,
+    pub entries: usize,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub kept_keys: Vec<KeptKey>,
+}
+
+fn client(timeout: Duration) -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .user_agent(concat!("DataTeX/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn request_error(e: reqwest::Error) -> String {
+    if e.is_connect() {
+        "Zotero isn't running, or Better BibTeX isn't installed".to_string()
+    } else {
+        format!("Zotero request failed: {}", e)
+    }
+}
+
+async fn rpc(method: &str, params: serde_json::Value) -> Result<serde_json::Value, String> {
+    let response = client(Duration::from_secs(10))?
+        .post(format!("{}/better-bibtex/json-rpc", ZOTERO_HOST))
+        .json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+            "id": 1,
+        }))
+        .send()
+        .await
+        .map_err(request_error)?;
+    let body: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid Better BibTeX response: {}", e))?;
+    if let Some(error) = body.get("error") {
+        return Err(format!(
+            "Better BibTeX: {}",
+            error["message"].as_str().unwrap_or("request failed")
+        ));
+    }
+    Ok(body["result"].clone())
+}
+
+/// Whether Zotero with Better BibTeX is reachable, and their versions
+pub async fn status() -> Result<ZoteroStatus, String> {
+    let ready = rpc("api.ready", serde_json::json!([])).await?;
+    let version = |key: &str| ready[key].as_str().map(str::to_string);
+    Ok(ZoteroStatus {
+        zotero_version: version("zotero"),
+        better_bibtex_version: version("betterbibtex"),
+    })
+}
+
+/// Collections of the local API's `/collections` listing, with their paths
+fn parse_collections(items: &[serde_json::Value]) -> Vec<ZoteroCollection> {
+    let mut nodes: HashMap<String, (String, Option<String>)> = HashMap::new();
+    for item in items {
+        let (Some(key), Some(name)) = (item["key"].as_str(), item["data"]["name"].as_str()) else {
+            continue;
+        };
+        // `parentCollection` is `false` at the top level
+        let parent = item["data"]["parentCollection"]
+            .as_str()
+            .map(str::to_string);
+        nodes.insert(key.to_string(), (name.to_string(), parent));
+    }
+    let path_of = |key: &str| {
+        let mut names = Vec::new();
+        let mut current = Some(key.to_string());
+        while let Some(key) = current {
+            let Some((name, parent)) = nodes.get(&key) else {
+                break;
+            };
+            if names.len() > nodes.len() {
+                break;
+            }
+            names.push(name.clone());
+            current = parent.clone();
+        }
+        names.reverse();
+        names.join("/")
+    };
+    let mut collections: Vec<ZoteroCollection> = nodes
+        .iter()
+        .map(|(key, (name, _))| ZoteroCollection {
+            key: key.clone(),
+            name: name.clone(),
+            path: path_of(key),
+        })
+        .collect();
+    collections.sort_by_key(|c| c.path.to_lowercase());
+    collections
+}
+
+/// Collections of the user's library, from Zotero's local API (Zotero 7
+/// with "Allow other applications on this computer to communicate with
+/// Zotero" turned on)
+pub async fn collections() -> Result<Vec<ZoteroCollection>, String> {
+    const PAGE: usize = 100;
+    let client = client(Duration::from_secs(20))?;
+    let mut items: Vec<serde_json::Value> = Vec::new();
+    loop {
+        let response = client
+            .get(format!(
+                "{}/api/users/0/collections?limit={}&start={}",
+                ZOTERO_HOST,
+                PAGE,
+                items.len()
+            ))
+            .send()
+            .await
+            .map_err(request_error)?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "Zotero's local API returned status {}; enable it in Zotero's Advanced settings",
+                response.status()
+            ));
+        }
+        let page: Vec<serde_json::Value> = response
+            .json()
+            .await
+            .map_err(|e| format!("Invalid Zotero response: {}", e))?;
+        let done = page.len() < PAGE;
+        items.extend(page);
+        if done {
+            break;
+        }
+    }
+    Ok(parse_collections(&items))
+}
+
+/// Percent-encode a collection key or path for the export URL
+fn encode_path(collection: &str) -> String {
+    collection
+        .split('/')
+        .map(|segment| {
+            segment
+                .bytes()
+                .map(|b| match b {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                        (b as char).to_string()
+                    }
+                    _ => format!("%{:02X}", b),
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Better BibTeX's pull export of each collection
+pub async fn export(
+    library_id: i64,
+    collections: &[String],
+    format: ZoteroFormat,
+) -> Result<Vec<String>, String> {
+    let client = client(Duration::from_secs(120))?;
+    let mut exports = Vec::new();
+    for collection in collections {
+        let url = format!(
+            "{}/better-bibtex/export/collection?/{}/{}.{}",
+            ZOTERO_HOST,
+            library_id,
+            encode_path(collection.trim_matches('/')),
+            format.as_str()
+        );
+        let response = client.get(&url).send().await.map_err(request_error)?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "Better BibTeX couldn't export '{}' (status {})",
+                collection,
+                response.status()
+            ));
+        }
+        exports.push(response.text().await.map_err(|e| e.to_string())?);
+    }
+    Ok(exports)
+}
+
+fn header_regex() -> &'static Regex {
+    static HEADER_RE: OnceLock<Regex> = OnceLock::new();
+    HEADER_RE.get_or_init(|| Regex::new(r"(?m)^@(\w+)\s*\{\s*([^,\s]*)").unwrap())
+}
+
+/// An `@...` block of an export
+struct Block {
+    /// Citation key and its span in `text`; string, preamble and comment
+    /// blocks have none
+    key: Option<(String, usize, usize)>,
+    text: String,
+}
+
+fn blocks(src: &str) -> Vec<Block> {
+    let starts: Vec<regex::Captures> = header_regex().captures_iter(src).collect();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, caps)| {
+            let start = caps.get(0).unwrap().start();
+            let end = starts
+                .get(i + 1)
+                .map(|next| next.get(0).unwrap().start())
+                .unwrap_or(src.len());
+            let text = src[start..end].trim_end().to_string();
+            let kind = caps[1].to_lowercase();
+            let key = caps.get(2).unwrap();
+            Block {
+                key: (!matches!(kind.as_str(), "string" | "preamble" | "comment")).then(|| {
+                    (
+                        key.as_str().to_string(),
+                        key.start() - start,
+                        key.end() - start,
+                    )
+                }),
+                text,
+            }
+        })
+        .collect()
+}
+
+/// What makes two entries the same work, whatever their keys
+fn identity(entry: &ParsedEntry) -> Option<String> {
+    for field in ["doi", "eprint"] {
+        if let Some(value) = entry.fields.get(field).filter(|v| !v.trim().is_empty()) {
+            return Some(format!("{}:{}", field, value.trim().to_lowercase()));
+        }
+    }
+    let title: String = entry
+        .title
+        .as_deref()?
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    (!title.is_empty()).then(|| format!("title:{}:{}", title, entry.year.as_deref().unwrap_or("")))
+}
+
+/// Combine the exports into the new file content. Entries exported by more
+/// than one collection are written once, and entries that were in
+/// `previous` keep their key there.
+pub fn merge(previous: &str, exports: &[String]) -> Result<Merged, String> {
+    // Without the old entries every kept key would revert to Zotero's
+    let old = parse_entries(previous).map_err(|e| {
+        format!(
+            "The synced file can't be read, so its citation keys would be lost; fix it first ({})",
+            e
+        )
+    })?;
+    let old_keys: HashSet<&str> = old.iter().map(|e| e.key.as_str()).collect();
+    let old_by_identity: HashMap<String, &str> = old
+        .iter()
+        .filter_map(|e| Some((identity(e)?, e.key.as_str())))
+        .collect();
+
+    let mut seen_keys = HashSet::new();
+    let mut seen_other = HashSet::new();
+    let mut kept = Vec::new();
+    for export in exports {
+        for block in blocks(export) {
+            let fresh = match &block.key {
+                Some((key, _, _)) => seen_keys.insert(key.clone()),
+                None => seen_other.insert(block.text.clone()),
+            };
+            if fresh {
+                kept.push(block);
+            }
+        }
+    }
+
+    // Zotero key -> the key the entry had before
+    let incoming = parse_entries(
+        &kept
+            .iter()
+            .map(|block| block.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+    )?;
+    let mut renames: HashMap<String, String> = HashMap::new();
+    for entry in &incoming {
+        if old_keys.contains(entry.key.as_str()) {
+            continue;
+        }
+        let Some(previous_key) = identity(entry).and_then(|id| old_by_identity.get(&id)) else {
+            continue;
+        };
+        if !seen_keys.contains(*previous_key) && !renames.values().any(|k| k == previous_key) {
+            renames.insert(entry.key.clone(), previous_key.to_string());
+        }
+    }
+
+    let mut content = format!("{}\n\n", MANAGED_HEADER);
+    let mut keys = Vec::new();
+    let mut kept_keys = Vec::new();
+    for Block { key, text } in kept {
+        let Some((key, start, end)) = key else {
+            content.push_str(&text);
+            content.push_str("\n\n");
+            continue;
+        };
+        match renames.get(&key) {
+            Some(previous_key) => {
+                content.push_str(&text[..start]);
+                content.push_str(previous_key);
+                content.push_str(&text[end..]);
+                kept_keys.push(KeptKey {
+                    zotero_key: key,
+                    kept_key: previous_key.clone(),
+                });
+                keys.push(previous_key.clone());
+            }
+            None => {
+                content.push_str(&text);
+                keys.push(key);
+            }
+        }
+        content.push_str("\n\n");
+    }
+    let content = content.trim_end().to_string() + "\n";
+
+    let new_keys: HashSet<&str> = keys.iter().map(String::as_str).collect();
+    let mut removed: Vec<String> = old_keys
+        .iter()
+        .filter(|k| !new_keys.contains(*k))
+        .map(|k| k.to_string())
+        .collect();
+    removed.sort();
+    Ok(Merged {
+        content,
+        entries: keys.len(),
+        added: keys
+            .iter()
+            .filter(|k| !old_keys.contains(k.as_str()))
+            .cloned()
+            .collect(),
+        removed,
+        kept_keys,
+    })
+}
+
+pub async fn get_link(
+    pool: &Pool<Sqlite>,
+    resource_id: &str,
+) -> Result<Option<ZoteroLink>, String> {
+    let row = sqlx::query(
+        "SELECT resource_id, library_id, collections, format, last_synced_at
+         FROM zotero_links WHERE resource_id = ?",
+    )
+    .bind(resource_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(row.map(|row| {
+        let collections: String = row.get("collections");
+        let format: String = row.get("format");
+        ZoteroLink {
+            resource_id: row.get("resource_id"),
+            library_id: row.get("library_id"),
+            collections: serde_json::from_str(&collections).unwrap_or_default(),
+            format: ZoteroFormat::parse(&format),
+            last_synced_at: row.get("last_synced_at"),
+        }
+    }))
+}
+
+async fn save_link(pool: &Pool<Sqlite>, link: &ZoteroLink) -> Result<(), String> {
+    let collections = serde_json::to_string(&link.collections).map_err(|e| e.to_string())?;
+    sqlx::query(
+        "INSERT INTO zotero_links (resource_id, library_id, collections, format, last_synced_at)
+         VALUES (?, ?, ?, ?, datetime('now'))
+         ON CONFLICT(resource_id) DO UPDATE SET
+            library_id = excluded.library_id,
+            collections = excluded.collections,
+            format = excluded.format,
+            last_synced_at = excluded.last_synced_at",
+    )
+    .bind(&link.resource_id)
+    .bind(link.library_id)
+    .bind(collections)
+    .bind(link.format.as_str())
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// The link a sync request describes: the stored one of `resource_id`
+/// with the request's changes, or a new one
+pub async fn resolve_link(
+    pool: &Pool<Sqlite>,
+    request: &ZoteroSyncRequest,
+) -> Result<ZoteroLink, String> {
+    let stored = match &request.resource_id {
+        Some(id) => Some(
+            get_link(pool, id)
+                .await?
+                .ok_or("This bibliography isn't synced from Zotero")?,
+        ),
+        None => None,
+    };
+    let link = ZoteroLink {
+        resource_id: request.resource_id.clone().unwrap_or_default(),
+        library_id: request
+            .library_id
+            .or(stored.as_ref().map(|l| l.library_id))
+            .unwrap_or(1),
+        collections: request
+            .collections
+            .clone()
+            .or(stored.as_ref().map(|l| l.collections.clone()))
+            .unwrap_or_default(),
+        format: request
+            .format
+            .or(stored.as_ref().map(|l| l.format))
+            .unwrap_or_default(),
+        last_synced_at: stored.and_then(|l| l.last_synced_at),
+    };
+    if link.collections.is_empty() {
+        return Err("Choose at least one Zotero collection".to_string());
+    }
+    Ok(link)
+}
+
+/// Write the exports into the linked .bib resource, or create it at
+/// `request.path`, and remember the link
+pub async fn apply(
+    db: &DatabaseManager,
+    request: &ZoteroSyncRequest,
+    mut link: ZoteroLink,
+    exports: &[String],
+) -> Result<ZoteroSyncResult, String> {
+    let (resource, merged) = match &request.resource_id {
+        Some(id) => {
+            let (resource, previous) = super::load_bib_resource(db, id).await?;
+            let merged = merge(&previous, exports)?;
+            super::write_back(db, &resource, &merged.content).await?;
+            (resource, merged)
+        }
+        None => {
+            let path = request
+                .path
+                .as_deref()
+                .ok_or("Give the .bib resource to sync or a path for a new one")?;
+            let collection = request
+                .collection
+                .as_deref()
+                .ok_or("Give the collection for the new .bib file")?;
+            if !path.ends_with(".bib") {
+                return Err("The synced file must be a .bib file".to_string());
+            }
+            if Path::new(path).exists() {
+                return Err(format!("{} already exists", path));
+            }
+            let merged = merge("", exports)?;
+            std::fs::write(path, &merged.content)
+                .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+            let id = indexer::index_file(db, Path::new(path), collection, None)
+                .await?
+                .ok_or("The new .bib file wasn't indexed")?;
+            let resource = db
+                .get_resource_by_id(&id)
+                .await?
+                .ok_or("Resource not found")?;
+            (resource, merged)
+        }
+    };
+
+    link.resource_id = resource.id.clone();
+    save_link(&db.pool, &link).await?;
+
+    let mut warnings = Vec::new();
+    for entry in parse_entries(&merged.content)? {
+        for other in super::find_by_key(&db.pool, &entry.key).await? {
+            if other.resource_id != resource.id {
+                warnings.push(format!(
+                    "Citation key '{}' is also defined in {}",
+                    entry.key, other.resource_path
+                ));
+            }
+        }
+    }
+
+    Ok(ZoteroSyncResult {
+        resource_id: resource.id,
+        path: resource.path,
+        entries: merged.entries,
+        added: merged.added,
+        removed: merged.removed,
+        kept_keys: merged.kept_keys,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_keeps_keys() {
+        let previous = "@article{knuth84, title = {Literate Programming}, year = {1984}, doi = {10.1093/comjnl/27.2.97}}\n\
+                        @book{gone, title = {Removed}, year = {2000}}\n";
+        let first = "@comment{jabref-meta: databaseType:biblatex;}\n\n\
+                     @article{knuthLiterateProgramming1984,\n  title = {Literate Programming},\n  doi = {10.1093/COMJNL/27.2.97},\n  date = {1984}\n}\n\n\
+                     @book{lamport94, title = {{LaTeX}: A Document Preparation System}, year = {1994}}\n";
+        let second = "@comment{jabref-meta: databaseType:biblatex;}\n\n\
+                      @book{lamport94, title = {{LaTeX}: A Document Preparation System}, year = {1994}}\n";
+        let merged = merge(previous, &[first.to_string(), second.to_string()]).unwrap();
+
+        assert_eq!(merged.entries, 2);
+        assert_eq!(
+            merged.kept_keys,
+            [KeptKey {
+                zotero_key: "knuthLiterateProgramming1984".to_string(),
+                kept_key: "knuth84".to_string(),
+            }]
+        );
+        assert_eq!(merged.added, ["lamport94"]);
+        assert_eq!(merged.removed, ["gone"]);
+        assert!(merged.content.starts_with(MANAGED_HEADER));
+        assert!(merged.content.contains("@article{knuth84,\n  title"));
+        assert_eq!(merged.content.matches("@comment").count(), 1);
+        let keys: Vec<String> = parse_entries(&merged.content)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.key)
+            .collect();
+        assert_eq!(keys.len(), 2);
+        assert!(keys.contains(&"knuth84".to_string()));
+
+        let broken = "@article{knuth84, title = {Literate Programming";
+        assert!(merge(broken, &[first.to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_collections() {
+        let items: Vec<serde_json::Value> = serde_json::from_str(
+            r#"[
+                {"key": "CHILD001", "data": {"name": "Chapter 2", "parentCollection": "TOP00001"}},
+                {"key": "TOP00001", "data": {"name": "Thesis", "parentCollection": false}},
+                {"key": "OTHER001", "data": {"name": "Articles", "parentCollection": false}}
+            ]"#,
+        )
+        .unwrap();
+        let paths: Vec<(String, String)> = parse_collections(&items)
+            .into_iter()
+            .map(|c| (c.key, c.path))
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                ("OTHER001".to_string(), "Articles".to_string()),
+                ("TOP00001".to_string(), "Thesis".to_string()),
+                ("CHILD001".to_string(), "Thesis/Chapter 2".to_string()),
+            ]
+        );
+        assert_eq!(encode_path("Thesis/Chapter 2"), "Thesis/Chapter%202");
+    }
+}
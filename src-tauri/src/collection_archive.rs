@@ -0,0 +1,1075 @@
+//! Collection Archives
+//!
+//! Packs a collection into one zip that can be imported on another machine:
+//! its files, every database row that belongs to it and the lookup rows
+//! those point at (tags, fields, chapters, ...), and its attachment blobs.
+//! The collection's own settings (preamble, solution environments, custom
+//! fields) and the project word list travel as rows too.
+//!
+//! Which rows belong to a collection is worked out from the foreign keys,
+//! so new tables are picked up without changes here: a table joins the
+//! archive when one of its foreign keys points at a table that is already
+//! in it. Links that leave the collection (a preamble from another
+//! collection, say) can't travel; nullable ones are cleared and rows that
+//! depend on them are left out.
+//!
+//! Importing extracts the files under a folder of the user's choosing and
+//! rewrites the paths in the rows to match. Resource ids are kept, so the
+//! same collection shared twice stays recognisable, unless they clash with
+//! ids already in the database; then every id in the archive is replaced.
+//!
+//! Layout: `manifest.json`, `rows.json`, `files/...` (relative to the
+//! collection root, `files/external/<n>/...` for files outside it) and
+//! `attachments/<hash>`.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Pool, Row, Sqlite, TypeInfo, ValueRef};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+use crate::attachments;
+use crate::database::schema::{self, ColumnInfo};
+use crate::database::DatabaseManager;
+use crate::indexer;
+
+const ARCHIVE_FORMAT: &str = "datatex-collection";
+const ARCHIVE_VERSION: u32 = 1;
+
+/// Logs, caches and per-machine state; never exported
+const SKIPPED_TABLES: &[&str] = &[
+    "audit_context",
+    "change_log",
+    "deleted_resources",
+    "file_history",
+    "history_blobs",
+    "resource_opens",
+    "resources_content_indexed",
+    "session_tabs",
+    "sessions",
+    "zotero_links",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    format: String,
+    version: u32,
+    collection: String,
+    /// Folder of the collection on the exporting machine ("" if it has none)
+    root: String,
+    exported_at: String,
+    /// Original path of each file stored under `files/external/`
+    external: BTreeMap<String, String>,
+}
+
+/// Rows by table
+type Rows = BTreeMap<String, Vec<Value>>;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ArchiveRows {
+    /// Rows that belong to the collection
+    owned: Rows,
+    /// Rows the owned ones point at; kept as they are when already present
+    lookup: Rows,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionExportSummary {
+    pub output_path: String,
+    pub files: usize,
+    pub rows: usize,
+    pub attachments: usize,
+    /// Links to resources of other collections that were left out
+    pub dropped_links: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionImportSummary {
+    pub collection: String,
+    pub root: String,
+    pub files: usize,
+    pub rows: usize,
+    pub resource_ids: Vec<String>,
+    /// Whether the archive's ids clashed with existing ones and were replaced
+    pub ids_replaced: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ForeignKey {
+    column: String,
+    table: String,
+    to: String,
+}
+
+/// Foreign keys of `table`; a key without a target column points at the
+/// primary key
+async fn foreign_keys(pool: &Pool<Sqlite>, table: &str) -> Result<Vec<ForeignKey>, String> {
+    let rows = sqlx::query(
+        "SELECT f.\"from\" AS col, f.\"table\" AS target,
+                COALESCE(f.\"to\", (SELECT name FROM pragma_table_info(f.\"table\") WHERE pk = 1)) AS target_col
+         FROM pragma_foreign_key_list(?) f",
+    )
+    .bind(table)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(rows
+        .iter()
+        .filter_map(|r| {
+            Some(ForeignKey {
+                column: r.get("col"),
+                table: r.get("target"),
+                to: r.try_get::<Option<String>, _>("target_col").ok()??,
+            })
+        })
+        .collect())
+}
+
+/// Rows of `table` whose `column` is one of `values`
+async fn fetch_rows(
+    pool: &Pool<Sqlite>,
+    table: &str,
+    column: &str,
+    values: &[Value],
+) -> Result<Vec<Value>, String> {
+    if values.is_empty() {
+        return Ok(Vec::new());
+    }
+    let columns = schema::table_columns(pool, table).await?;
+    let sql = format!(
+        "SELECT {} FROM {} WHERE {} IN (SELECT value FROM json_each(?))",
+        columns
+            .iter()
+            .map(|c| schema::quote_identifier(c))
+            .collect::<Vec<_>>()
+            .join(", "),
+        schema::quote_identifier(table),
+        schema::quote_identifier(column)
+    );
+    let values = serde_json::to_string(values).map_err(|e| e.to_string())?;
+    let rows = sqlx::query(&sql)
+        .bind(values)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(rows.iter().map(|r| row_values(r, &columns)).collect())
+}
+
+/// A row as JSON by storage class. Unlike `schema::row_to_json`, NULL stays
+/// null so CHECK constraints hold on the way back in; BLOBs are left out.
+fn row_values(row: &SqliteRow, columns: &[String]) -> Value {
+    let mut map = serde_json::Map::new();
+    for column in columns {
+        let value = match row.try_get_raw(column.as_str()) {
+            Ok(raw) if !raw.is_null() => match raw.type_info().name() {
+                "INTEGER" => row.try_get::<i64, _>(column.as_str()).map(Value::from),
+                "REAL" => row.try_get::<f64, _>(column.as_str()).map(Value::from),
+                "TEXT" => row.try_get::<String, _>(column.as_str()).map(Value::from),
+                _ => Ok(Value::Null),
+            }
+            .unwrap_or(Value::Null),
+            _ => Value::Null,
+        };
+        map.insert(column.clone(), value);
+    }
+    Value::Object(map)
+}
+
+/// Distinct non-null values of `column` in `rows`
+fn column_values(rows: &[Value], column: &str) -> Vec<Value> {
+    let mut seen = HashSet::new();
+    rows.iter()
+        .filter_map(|row| row.get(column))
+        .filter(|v| !v.is_null() && seen.insert(v.to_string()))
+        .cloned()
+        .collect()
+}
+
+/// Clear or drop references from owned rows to owned tables whose target
+/// didn't make it into the archive. Returns how many links were lost.
+fn prune_dangling(
+    owned: &mut Rows,
+    keys: &HashMap<String, Vec<ForeignKey>>,
+    columns: &HashMap<String, Vec<ColumnInfo>>,
+) -> usize {
+    let mut dropped = 0;
+    loop {
+        let mut changed = false;
+        let tables: Vec<String> = owned.keys().cloned().collect();
+        for table in tables {
+            for key in keys.get(&table).into_iter().flatten() {
+                let Some(targets) = owned.get(&key.table) else {
+                    continue;
+                };
+                let present: HashSet<String> = column_values(targets, &key.to)
+                    .iter()
+                    .map(Value::to_string)
+                    .collect();
+                let nullable = columns
+                    .get(&table)
+                    .and_then(|cols| cols.iter().find(|c| c.name == key.column))
+                    .is_some_and(|c| !c.not_null && !c.primary_key);
+                let rows = owned.get_mut(&table).expect("listed above");
+                let before = rows.len();
+                rows.retain_mut(|row| {
+                    let Some(value) = row.get_mut(&key.column) else {
+                        return true;
+                    };
+                    if value.is_null() || present.contains(&value.to_string()) {
+                        return true;
+                    }
+                    dropped += 1;
+                    if nullable {
+                        *value = Value::Null;
+                        true
+                    } else {
+                        false
+                    }
+                });
+                changed |= rows.len() != before;
+            }
+        }
+        if !changed {
+            return dropped;
+        }
+    }
+}
+
+/// Tables `export_collection` takes owned rows from: the collection's own
+/// and every table with a foreign key into one of those
+fn owned_tables(keys: &HashMap<String, Vec<ForeignKey>>) -> HashSet<String> {
+    let mut owned: HashSet<String> = ["collections", "resources", "dependencies", "spelling_words"]
+        .iter()
+        .map(|t| t.to_string())
+        .collect();
+    loop {
+        let more: Vec<String> = keys
+            .iter()
+            .filter(|(table, keys)| {
+                !owned.contains(*table) && keys.iter().any(|k| owned.contains(&k.table))
+            })
+            .map(|(table, _)| table.clone())
+            .collect();
+        if more.is_empty() {
+            return owned;
+        }
+        owned.extend(more);
+    }
+}
+
+/// Tables `export_collection` takes lookup rows from: those the owned ones
+/// point at, directly or through other lookups
+fn lookup_tables(
+    owned: &HashSet<String>,
+    keys: &HashMap<String, Vec<ForeignKey>>,
+) -> HashSet<String> {
+    let mut lookup = HashSet::new();
+    let mut queue: Vec<&String> = owned.iter().collect();
+    while let Some(table) = queue.pop() {
+        for key in keys.get(table).into_iter().flatten() {
+            if !owned.contains(&key.table)
+                && keys.contains_key(&key.table)
+                && lookup.insert(key.table.clone())
+            {
+                queue.push(&key.table);
+            }
+        }
+    }
+    lookup
+}
+
+/// Name of a file inside the archive: under `files/` relative to the root,
+/// or under `files/external/<n>/` when it is elsewhere
+fn archive_file_name(
+    root: Option<&Path>,
+    path: &Path,
+    external: &mut BTreeMap<String, String>,
+) -> String {
+    if let Some(relative) = root.and_then(|root| path.strip_prefix(root).ok()) {
+        let parts: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        return format!("files/{}", parts.join("/"));
+    }
+    let name = format!(
+        "files/external/{}/{}",
+        external.len(),
+        path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    );
+    external.insert(name.clone(), path.to_string_lossy().to_string());
+    name
+}
+
+/// Write `db`'s rows and files of `collection` to a zip at `output_path`
+pub async fn export_collection(
+    db: &DatabaseManager,
+    collection: &str,
+    output_path: &str,
+) -> Result<CollectionExportSummary, String> {
+    let pool = &db.pool;
+    let collection_rows = fetch_rows(pool, "collections", "name", &[collection.into()]).await?;
+    let root = collection_rows
+        .first()
+        .ok_or_else(|| format!("Collection not found: {}", collection))?
+        .get("path")
+        .and_then(Value::as_str)
+        .filter(|p| !p.is_empty())
+        .map(str::to_string);
+
+    let tables: Vec<String> = schema::list_tables(pool)
+        .await?
+        .into_iter()
+        .filter(|t| !SKIPPED_TABLES.contains(&t.as_str()))
+        .collect();
+    let mut keys = HashMap::new();
+    let mut columns = HashMap::new();
+    for table in &tables {
+        keys.insert(table.clone(), foreign_keys(pool, table).await?);
+        columns.insert(table.clone(), schema::column_info(pool, table).await?);
+    }
+
+    // Rows that belong to the collection, following the foreign keys
+    let mut owned = Rows::new();
+    let resources = fetch_rows(pool, "resources", "collection", &[collection.into()]).await?;
+    let ids = column_values(&resources, "id");
+    owned.insert("collections".to_string(), collection_rows);
+    owned.insert("resources".to_string(), resources);
+    // Dependencies have no foreign keys (targets may be outside files)
+    owned.insert(
+        "dependencies".to_string(),
+        fetch_rows(pool, "dependencies", "source_id", &ids).await?,
+    );
+    if let Some(root) = &root {
+        owned.insert(
+            "spelling_words".to_string(),
+            fetch_rows(pool, "spelling_words", "project", &[root.as_str().into()]).await?,
+        );
+    }
+    loop {
+        let mut added = false;
+        for table in &tables {
+            if owned.contains_key(table) {
+                continue;
+            }
+            let into_owned: Vec<&ForeignKey> = keys[table]
+                .iter()
+                .filter(|k| owned.contains_key(&k.table))
+                .collect();
+            if into_owned.is_empty() {
+                continue;
+            }
+            // A row belongs to what its required keys point at; an optional
+            // link (a preamble, a solution) doesn't make it ours
+            let required: Vec<&ForeignKey> = into_owned
+                .iter()
+                .copied()
+                .filter(|k| {
+                    columns[table]
+                        .iter()
+                        .any(|c| c.name == k.column && (c.not_null || c.primary_key))
+                })
+                .collect();
+            let by = if required.is_empty() {
+                into_owned
+            } else {
+                required
+            };
+            let mut rows = Vec::new();
+            let mut seen = HashSet::new();
+            for key in by {
+                let values = column_values(&owned[&key.table], &key.to);
+                for row in fetch_rows(pool, table, &key.column, &values).await? {
+                    if seen.insert(row.to_string()) {
+                        rows.push(row);
+                    }
+                }
+            }
+            owned.insert(table.clone(), rows);
+            added = true;
+        }
+        if !added {
+            break;
+        }
+    }
+    let dropped_links = prune_dangling(&mut owned, &keys, &columns);
+
+    // Rows the owned ones point at, and the rows those point at
+    let mut lookup = Rows::new();
+    let mut queue: Vec<(String, Vec<Value>)> = owned
+        .iter()
+        .map(|(t, rows)| (t.clone(), rows.clone()))
+        .collect();
+    while let Some((table, rows)) = queue.pop() {
+        for key in keys.get(&table).into_iter().flatten() {
+            if owned.contains_key(&key.table) || !keys.contains_key(&key.table) {
+                continue;
+            }
+            let known: HashSet<String> = lookup
+                .get(&key.table)
+                .map(|rows| column_values(rows, &key.to))
+                .unwrap_or_default()
+                .iter()
+                .map(Value::to_string)
+                .collect();
+            let values: Vec<Value> = column_values(&rows, &key.column)
+                .into_iter()
+                .filter(|v| !known.contains(&v.to_string()))
+                .collect();
+            let found = fetch_rows(pool, &key.table, &key.to, &values).await?;
+            if found.is_empty() {
+                continue;
+            }
+            lookup
+                .entry(key.table.clone())
+                .or_default()
+                .extend(found.iter().cloned());
+            queue.push((key.table.clone(), found));
+        }
+    }
+    owned.retain(|_, rows| !rows.is_empty());
+
+    // Files: everything under the root, plus resources kept elsewhere
+    let root_path = root.as_ref().map(PathBuf::from);
+    let mut files: Vec<PathBuf> = Vec::new();
+    if let Some(root) = &root_path {
+        files.extend(
+            WalkDir::new(root)
+                .into_iter()
+                .filter_entry(|e| {
+                    e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.')
+                })
+                .flatten()
+                .filter(|e| e.file_type().is_file())
+                .map(|e| e.into_path()),
+        );
+    }
+    for row in owned.get("resources").into_iter().flatten() {
+        if let Some(path) = row.get("path").and_then(Value::as_str) {
+            let path = PathBuf::from(path);
+            let inside = root_path
+                .as_ref()
+                .is_some_and(|root| path.starts_with(root));
+            if !inside && path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    let output = fs::canonicalize(output_path).unwrap_or_else(|_| PathBuf::from(output_path));
+    files.retain(|f| fs::canonicalize(f).map_or(true, |f| f != output));
+
+    let blobs: Vec<String> = owned
+        .get("attachments")
+        .map(|rows| column_values(rows, "blob_hash"))
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|v| v.as_str().map(str::to_string))
+        .collect();
+
+    let mut manifest = Manifest {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        collection: collection.to_string(),
+        root: root.clone().unwrap_or_default(),
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        external: BTreeMap::new(),
+    };
+    let names: Vec<(String, &PathBuf)> = files
+        .iter()
+        .map(|f| {
+            (
+                archive_file_name(root_path.as_deref(), f, &mut manifest.external),
+                f,
+            )
+        })
+        .collect();
+    let archive = ArchiveRows { owned, lookup };
+    let row_count = archive.owned.values().map(Vec::len).sum::<usize>()
+        + archive.lookup.values().map(Vec::len).sum::<usize>();
+
+    let file = fs::File::create(output_path)
+        .map_err(|e| format!("Failed to create {}: {}", output_path, e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut add = |name: &str, content: &[u8]| -> Result<(), String> {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(content).map_err(|e| e.to_string())
+    };
+    add(
+        "manifest.json",
+        &serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?,
+    )?;
+    add(
+        "rows.json",
+        &serde_json::to_vec(&archive).map_err(|e| e.to_string())?,
+    )?;
+    for (name, path) in &names {
+        let content =
+            fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        add(name, &content)?;
+    }
+    let blob_root = attachments::blob_root(db);
+    let mut attachment_count = 0;
+    for hash in &blobs {
+        if let Ok(content) = fs::read(attachments::blob_path(&blob_root, hash)) {
+            add(&format!("attachments/{}", hash), &content)?;
+            attachment_count += 1;
+        }
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+
+    Ok(CollectionExportSummary {
+        output_path: output_path.to_string(),
+        files: names.len(),
+        rows: row_count,
+        attachments: attachment_count,
+        dropped_links,
+    })
+}
+
+fn uuid_regex() -> &'static Regex {
+    static UUID_RE: OnceLock<Regex> = OnceLock::new();
+    UUID_RE.get_or_init(|| {
+        Regex::new(r"[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}")
+            .unwrap()
+    })
+}
+
+/// How values from the archive change on the way in
+#[derive(Debug, Default)]
+struct Remap {
+    old_root: String,
+    new_root: PathBuf,
+    /// Original path of each external file to where it was extracted
+    external: HashMap<String, String>,
+    ids: HashMap<String, String>,
+}
+
+impl Remap {
+    /// `value` under the new root, if it was under the old one
+    fn path(&self, value: &str) -> Option<String> {
+        if self.old_root.is_empty() {
+            return None;
+        }
+        let rest = value.strip_prefix(&self.old_root)?;
+        if !(rest.is_empty() || rest.starts_with(['/', '\\'])) {
+            return None;
+        }
+        let mut path = self.new_root.clone();
+        for part in rest.split(['/', '\\']).filter(|p| !p.is_empty()) {
+            path.push(part);
+        }
+        Some(path.to_string_lossy().to_string())
+    }
+
+    fn string(&self, value: &str) -> String {
+        if let Some(path) = self.external.get(value) {
+            return path.clone();
+        }
+        if let Some(path) = self.path(value) {
+            return path;
+        }
+        if let Some(id) = self.ids.get(value) {
+            return id.clone();
+        }
+        if self.ids.is_empty() {
+            return value.to_string();
+        }
+        // Ids inside JSON text (metadata, document lists)
+        uuid_regex()
+            .replace_all(value, |caps: &regex::Captures| {
+                self.ids
+                    .get(&caps[0])
+                    .cloned()
+                    .unwrap_or_else(|| caps[0].to_string())
+            })
+            .into_owned()
+    }
+}
+
+fn read_entry<R: Read + std::io::Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    name: &str,
+) -> Result<Vec<u8>, String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|_| format!("Not a collection archive: {} is missing", name))?;
+    let mut content = Vec::new();
+    entry.read_to_end(&mut content).map_err(|e| e.to_string())?;
+    Ok(content)
+}
+
+/// Insert `row` into `table`, skipping columns the table doesn't have and,
+/// when `fresh_rowid`, the integer key so the database assigns a new one
+async fn insert_row(
+    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    table: &str,
+    columns: &[ColumnInfo],
+    row: &Value,
+    or_ignore: bool,
+    fresh_rowid: bool,
+) -> Result<(), String> {
+    let Some(values) = row.as_object() else {
+        return Ok(());
+    };
+    let single_key = columns.iter().filter(|c| c.primary_key).count() == 1;
+    let names: Vec<&str> = columns
+        .iter()
+        .filter(|c| values.contains_key(&c.name))
+        .filter(|c| {
+            !(fresh_rowid
+                && single_key
+                && c.primary_key
+                && c.decl_type.eq_ignore_ascii_case("INTEGER"))
+        })
+        .map(|c| c.name.as_str())
+        .collect();
+    if names.is_empty() {
+        return Ok(());
+    }
+    let sql = format!(
+        "INSERT {}INTO {} ({}) VALUES ({})",
+        if or_ignore { "OR IGNORE " } else { "" },
+        schema::quote_identifier(table),
+        names
+            .iter()
+            .map(|c| schema::quote_identifier(c))
+            .collect::<Vec<_>>()
+            .join(", "),
+        vec!["?"; names.len()].join(", ")
+    );
+    let mut query = sqlx::query(&sql);
+    for name in &names {
+        query = match &values[*name] {
+            Value::Null => query.bind(None::<String>),
+            Value::Number(n) if n.is_i64() => query.bind(n.as_i64()),
+            Value::Number(n) => query.bind(n.as_f64()),
+            Value::String(s) => query.bind(s.clone()),
+            other => query.bind(other.to_string()),
+        };
+    }
+    query
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| format!("Failed to import a row of {}: {}", table, e))?;
+    Ok(())
+}
+
+/// Write the archive's files to their targets and its attachment blobs to
+/// the blob store
+fn extract_files<R: Read + std::io::Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    files: &[(usize, PathBuf)],
+    blob_root: &Path,
+) -> Result<(), String> {
+    for (i, target) in files {
+        let mut entry = archive.by_index(*i).map_err(|e| e.to_string())?;
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let mut out = fs::File::create(target).map_err(|e| e.to_string())?;
+        std::io::copy(&mut entry, &mut out).map_err(|e| e.to_string())?;
+    }
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        let Some(hash) = entry
+            .name()
+            .strip_prefix("attachments/")
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        if entry.is_dir() {
+            continue;
+        }
+        if !attachments::is_blob_hash(&hash) {
+            return Err(format!(
+                "Invalid attachment in the archive: {}",
+                entry.name()
+            ));
+        }
+        let target = attachments::blob_path(blob_root, &hash);
+        if target.exists() {
+            continue;
+        }
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content).map_err(|e| e.to_string())?;
+        if indexer::hash_bytes(&content) != hash {
+            return Err(format!("Attachment {} in the archive is damaged", hash));
+        }
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(&target, content).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Import the archive at `archive_path` as collection `name` (the archived
+/// name when `None`), extracting its files under `target_dir`
+pub async fn import_collection(
+    db: &DatabaseManager,
+    archive_path: &str,
+    target_dir: &str,
+    name: Option<String>,
+) -> Result<CollectionImportSummary, String> {
+    let pool = &db.pool;
+    let file = fs::File::open(archive_path)
+        .map_err(|e| format!("Failed to open {}: {}", archive_path, e))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+    let manifest: Manifest = serde_json::from_slice(&read_entry(&mut archive, "manifest.json")?)
+        .map_err(|e| format!("Invalid manifest: {}", e))?;
+    if manifest.format != ARCHIVE_FORMAT || manifest.version > ARCHIVE_VERSION {
+        return Err(format!(
+            "Unsupported archive: {} version {}",
+            manifest.format, manifest.version
+        ));
+    }
+    let mut rows: ArchiveRows = serde_json::from_slice(&read_entry(&mut archive, "rows.json")?)
+        .map_err(|e| format!("Invalid archive rows: {}", e))?;
+
+    // Only the tables an export produces; ones this version doesn't have
+    // are left out
+    let local: Vec<String> = schema::list_tables(pool).await?;
+    let mut keys = HashMap::new();
+    for table in local
+        .iter()
+        .filter(|t| !SKIPPED_TABLES.contains(&t.as_str()))
+    {
+        keys.insert(table.clone(), foreign_keys(pool, table).await?);
+    }
+    let owned_allowed = owned_tables(&keys);
+    let lookup_allowed = lookup_tables(&owned_allowed, &keys);
+    for (archived, allowed) in [
+        (&mut rows.owned, &owned_allowed),
+        (&mut rows.lookup, &lookup_allowed),
+    ] {
+        archived.retain(|table, _| local.contains(table));
+        if let Some(table) = archived.keys().find(|t| !allowed.contains(*t)) {
+            return Err(format!(
+                "Not a collection archive: it holds rows of {}",
+                table
+            ));
+        }
+    }
+
+    let name = name.unwrap_or_else(|| manifest.collection.clone());
+    let exists: Option<String> = sqlx::query_scalar("SELECT name FROM collections WHERE name = ?")
+        .bind(&name)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    if exists.is_some() {
+        return Err(format!(
+            "A collection named {} already exists; import it under another name",
+            name
+        ));
+    }
+
+    // Nothing in the way of the files
+    let new_root = PathBuf::from(target_dir);
+    let mut extracted: Vec<(usize, PathBuf)> = Vec::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        if entry.is_dir() {
+            continue;
+        }
+        let Some(relative) = entry
+            .enclosed_name()
+            .and_then(|p| p.strip_prefix("files").ok().map(Path::to_path_buf))
+        else {
+            continue;
+        };
+        let target = new_root.join(relative);
+        if target.exists() {
+            return Err(format!("{} already exists", target.display()));
+        }
+        extracted.push((i, target));
+    }
+    // Fresh ids when any of the archived ones is taken
+    let archived_ids = column_values(rows.owned.get("resources").map_or(&[][..], |r| r), "id");
+    let taken: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM resources WHERE id IN (SELECT value FROM json_each(?))",
+    )
+    .bind(serde_json::to_string(&archived_ids).map_err(|e| e.to_string())?)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let mut remap = Remap {
+        old_root: manifest.root.clone(),
+        new_root: new_root.clone(),
+        external: manifest
+            .external
+            .iter()
+            .filter_map(|(archived, original)| {
+                let relative = archived.strip_prefix("files/")?;
+                let path = relative
+                    .split('/')
+                    .fold(new_root.clone(), |path, part| path.join(part));
+                Some((original.clone(), path.to_string_lossy().to_string()))
+            })
+            .collect(),
+        ids: HashMap::new(),
+    };
+    let ids_replaced = taken > 0;
+    if ids_replaced {
+        for (table, table_rows) in &rows.owned {
+            let columns = schema::column_info(pool, table).await.unwrap_or_default();
+            let text_id = columns.iter().any(|c| {
+                c.name == "id" && c.primary_key && !c.decl_type.eq_ignore_ascii_case("INTEGER")
+            });
+            if !text_id {
+                continue;
+            }
+            for id in column_values(table_rows, "id") {
+                if let Some(id) = id.as_str() {
+                    remap.ids.insert(id.to_string(), Uuid::new_v4().to_string());
+                }
+            }
+        }
+    }
+
+    // Which columns name the collection
+    let mut collection_columns: HashSet<(String, String)> = HashSet::new();
+    collection_columns.insert(("collections".to_string(), "name".to_string()));
+    for table in rows.owned.keys() {
+        for key in foreign_keys(pool, table).await? {
+            if key.table == "collections" {
+                collection_columns.insert((table.clone(), key.column));
+            }
+        }
+    }
+    for (table, table_rows) in rows.owned.iter_mut() {
+        for row in table_rows {
+            let Some(values) = row.as_object_mut() else {
+                continue;
+            };
+            for (column, value) in values.iter_mut() {
+                let Value::String(s) = value else {
+                    continue;
+                };
+                *s = if collection_columns.contains(&(table.clone(), column.clone())) {
+                    if *s != manifest.collection {
+                        return Err(format!(
+                            "The archive holds rows of another collection ({})",
+                            s
+                        ));
+                    }
+                    name.clone()
+                } else {
+                    remap.string(s)
+                };
+            }
+        }
+    }
+
+    // Every resource lands under the target folder
+    for row in rows.owned.get("resources").into_iter().flatten() {
+        let path = row.get("path").and_then(Value::as_str).unwrap_or_default();
+        let under = Path::new(path).starts_with(&new_root)
+            && !Path::new(path)
+                .components()
+                .any(|c| c == std::path::Component::ParentDir);
+        if !under {
+            return Err(format!(
+                "{} in the archive isn't under {}",
+                path,
+                new_root.display()
+            ));
+        }
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query("PRAGMA defer_foreign_keys = ON")
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut count = 0;
+    for (owned, table_rows) in rows
+        .lookup
+        .iter()
+        .map(|t| (false, t))
+        .chain(rows.owned.iter().map(|t| (true, t)))
+    {
+        let (table, table_rows) = table_rows;
+        let Ok(columns) = schema::column_info(pool, table).await else {
+            // A table this version doesn't have
+            continue;
+        };
+        for row in table_rows {
+            insert_row(&mut tx, table, &columns, row, !owned, owned).await?;
+            count += 1;
+        }
+    }
+    // Files go out before the commit, and come back out if it fails
+    fs::create_dir_all(&new_root).map_err(|e| e.to_string())?;
+    let written = extract_files(&mut archive, &extracted, &attachments::blob_root(db));
+    let committed = match written {
+        Ok(()) => tx
+            .commit()
+            .await
+            .map_err(|e| format!("Failed to import the collection: {}", e)),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = committed {
+        for (_, target) in &extracted {
+            let _ = fs::remove_file(target);
+        }
+        return Err(e);
+    }
+
+    let resource_ids = rows
+        .owned
+        .get("resources")
+        .map(|r| column_values(r, "id"))
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|v| v.as_str().map(str::to_string))
+        .collect();
+    Ok(CollectionImportSummary {
+        collection: name,
+        root: new_root.to_string_lossy().to_string(),
+        files: extracted.len(),
+        rows: count,
+        resource_ids,
+        ids_replaced,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remap_string() {
+        let id = "0b6f1a52-3c1e-4c41-9e1a-5d2f1f0d9c11";
+        let remap = Remap {
+            old_root: "/home/ann/algebra".to_string(),
+            new_root: PathBuf::from("/data/algebra"),
+            external: [(
+                "/home/ann/shared/preamble.tex".to_string(),
+                "/data/algebra/external/0/preamble.tex".to_string(),
+            )]
+            .into(),
+            ids: [(id.to_string(), "new-id".to_string())].into(),
+        };
+        assert_eq!(
+            remap.string("/home/ann/algebra/ch1/ex1.tex"),
+            "/data/algebra/ch1/ex1.tex"
+        );
+        assert_eq!(remap.string("/home/ann/algebra"), "/data/algebra");
+        assert_eq!(
+            remap.string("/home/ann/algebra-old/x.tex"),
+            "/home/ann/algebra-old/x.tex"
+        );
+        assert_eq!(
+            remap.string("/home/ann/shared/preamble.tex"),
+            "/data/algebra/external/0/preamble.tex"
+        );
+        assert_eq!(remap.string(id), "new-id");
+        assert_eq!(
+            remap.string(&format!("{{\"preamble\":\"{}\"}}", id)),
+            "{\"preamble\":\"new-id\"}"
+        );
+    }
+
+    #[test]
+    fn test_archive_tables() {
+        let key = |table: &str| ForeignKey {
+            column: "id".to_string(),
+            table: table.to_string(),
+            to: "id".to_string(),
+        };
+        let keys = HashMap::from([
+            ("resources".to_string(), vec![key("collections")]),
+            (
+                "resource_files".to_string(),
+                vec![key("resources"), key("fields")],
+            ),
+            ("fields".to_string(), vec![key("subjects")]),
+            ("subjects".to_string(), vec![]),
+            ("settings".to_string(), vec![]),
+        ]);
+        let owned = owned_tables(&keys);
+        assert!(owned.contains("resource_files"));
+        assert!(!owned.contains("fields"));
+        let lookup = lookup_tables(&owned, &keys);
+        assert_eq!(
+            lookup,
+            HashSet::from(["fields".to_string(), "subjects".to_string()])
+        );
+        assert!(!owned.contains("settings") && !lookup.contains("settings"));
+    }
+
+    #[test]
+    fn test_prune_dangling() {
+        let key = |column: &str, table: &str, to: &str| ForeignKey {
+            column: column.to_string(),
+            table: table.to_string(),
+            to: to.to_string(),
+        };
+        let info = |name: &str, not_null: bool| ColumnInfo {
+            name: name.to_string(),
+            decl_type: "TEXT".to_string(),
+            not_null,
+            has_default: false,
+            primary_key: false,
+        };
+        let mut owned = Rows::new();
+        owned.insert(
+            "resources".to_string(),
+            vec![serde_json::json!({ "id": "a" })],
+        );
+        owned.insert(
+            "resource_files".to_string(),
+            vec![serde_json::json!({ "resource_id": "a", "preamble_id": "elsewhere" })],
+        );
+        owned.insert(
+            "document_items".to_string(),
+            vec![
+                serde_json::json!({ "resource_id": "a" }),
+                serde_json::json!({ "resource_id": "elsewhere" }),
+            ],
+        );
+        let keys = HashMap::from([
+            (
+                "resource_files".to_string(),
+                vec![
+                    key("resource_id", "resources", "id"),
+                    key("preamble_id", "resources", "id"),
+                ],
+            ),
+            (
+                "document_items".to_string(),
+                vec![key("resource_id", "resources", "id")],
+            ),
+        ]);
+        let columns = HashMap::from([
+            (
+                "resource_files".to_string(),
+                vec![info("resource_id", true), info("preamble_id", false)],
+            ),
+            (
+                "document_items".to_string(),
+                vec![info("resource_id", true)],
+            ),
+        ]);
+
+        assert_eq!(prune_dangling(&mut owned, &keys, &columns), 2);
+        assert_eq!(owned["resource_files"][0]["preamble_id"], Value::Null);
+        assert_eq!(owned["document_items"].len(), 1);
+    }
+}
@@ -0,0 +1,267 @@
+//! Compile Status Cache
+//!
+//! The outcome of the last compile of each resource (ok/error, error and
+//! warning counts from the LaTeX log, first error message) is stored in
+//! `compile_status` (migration 024). The table view and the file tree show it
+//! next to each resource, so broken exercises are visible without rebuilding.
+
+use serde::Serialize;
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::log_parser;
+use crate::tree_builder::TreeNode;
+
+/// Longest first-error message kept in the table
+const MAX_MESSAGE_LEN: usize = 300;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompileStatus {
+    pub resource_id: String,
+    /// "ok" | "error"
+    pub status: String,
+    pub error_count: i64,
+    pub warning_count: i64,
+    pub engine: Option<String>,
+    pub message: Option<String>,
+    pub compiled_at: String,
+}
+
+/// Where LaTeX writes the log of `tex_path` (same stem, in `output_dir` if set)
+pub fn log_path(tex_path: &str, output_dir: &str) -> PathBuf {
+    let path = Path::new(tex_path);
+    if output_dir.is_empty() {
+        path.with_extension("log")
+    } else {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        Path::new(output_dir).join(format!("{}.log", stem))
+    }
+}
+
+/// (status, errors, warnings, first error) of a compile result and its log.
+/// A failure without a readable log (missing engine, ...) counts as one error.
+fn summarize(
+    result: &Result<String, String>,
+    log: Option<&str>,
+) -> (&'static str, i64, i64, Option<String>) {
+    let entries = log.map(log_parser::parse_log).unwrap_or_default();
+    let count = |kind: &str| entries.iter().filter(|e| e.r#type == kind).count() as i64;
+    let (errors, warnings) = (count("error"), count("warning"));
+
+    let first_error = entries
+        .iter()
+        .find(|e| e.r#type == "error")
+        .map(|e| match e.line {
+            0 => e.message.clone(),
+            line => format!("l.{}: {}", line, e.message),
+        });
+
+    match result {
+        Ok(_) if errors == 0 => ("ok", 0, warnings, None),
+        // Non-stop mode can finish with a PDF despite errors
+        Ok(_) => ("error", errors, warnings, first_error),
+        Err(e) => (
+            "error",
+            errors.max(1),
+            warnings,
+            first_error.or_else(|| e.lines().find(|l| !l.trim().is_empty()).map(str::to_string)),
+        ),
+    }
+}
+
+/// Store the outcome of compiling `resource_id`, reading the counts from the
+/// log at `log_path`
+pub async fn record(
+    pool: &Pool<Sqlite>,
+    resource_id: &str,
+    engine: &str,
+    result: &Result<String, String>,
+    log_path: &Path,
+) -> Result<CompileStatus, String> {
+    let log = std::fs::read(log_path)
+        .ok()
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+    let (status, errors, warnings, message) = summarize(result, log.as_deref());
+    let message = message.map(|m| m.chars().take(MAX_MESSAGE_LEN).collect::<String>());
+
+    sqlx::query(
+        "INSERT INTO compile_status (resource_id, status, error_count, warning_count, engine, message, compiled_at)
+         VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+         ON CONFLICT(resource_id) DO UPDATE SET
+            status = excluded.status,
+            error_count = excluded.error_count,
+            warning_count = excluded.warning_count,
+            engine = excluded.engine,
+            message = excluded.message,
+            compiled_at = CURRENT_TIMESTAMP",
+    )
+    .bind(resource_id)
+    .bind(status)
+    .bind(errors)
+    .bind(warnings)
+    .bind(engine)
+    .bind(&message)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    get(pool, resource_id)
+        .await?
+        .ok_or_else(|| "Failed to store compile status".to_string())
+}
+
+fn row_to_status(row: &sqlx::sqlite::SqliteRow) -> CompileStatus {
+    CompileStatus {
+        resource_id: row.get("resource_id"),
+        status: row.get("status"),
+        error_count: row.get("error_count"),
+        warning_count: row.get("warning_count"),
+        engine: row.get("engine"),
+        message: row.get("message"),
+        compiled_at: row.get("compiled_at"),
+    }
+}
+
+pub async fn get(pool: &Pool<Sqlite>, resource_id: &str) -> Result<Option<CompileStatus>, String> {
+    let row = sqlx::query(
+        "SELECT resource_id, status, error_count, warning_count, engine, message,
+                CAST(compiled_at AS TEXT) AS compiled_at
+         FROM compile_status WHERE resource_id = ?",
+    )
+    .bind(resource_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(row.as_ref().map(row_to_status))
+}
+
+/// Statuses of the resources of `collection` (all if None), failures only if
+/// `failed_only`; most recent first
+pub async fn list(
+    pool: &Pool<Sqlite>,
+    collection: Option<&str>,
+    failed_only: bool,
+) -> Result<Vec<CompileStatus>, String> {
+    let rows = sqlx::query(
+        "SELECT cs.resource_id, cs.status, cs.error_count, cs.warning_count, cs.engine, cs.message,
+                CAST(cs.compiled_at AS TEXT) AS compiled_at
+         FROM compile_status cs
+         JOIN resources r ON r.id = cs.resource_id
+         WHERE (? IS NULL OR r.collection = ?) AND (? = 0 OR cs.status = 'error')
+         ORDER BY cs.compiled_at DESC",
+    )
+    .bind(collection)
+    .bind(collection)
+    .bind(failed_only)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(rows.iter().map(row_to_status).collect())
+}
+
+async fn by_resource(pool: &Pool<Sqlite>) -> Result<HashMap<String, CompileStatus>, String> {
+    Ok(list(pool, None, false)
+        .await?
+        .into_iter()
+        .map(|s| (s.resource_id.clone(), s))
+        .collect())
+}
+
+/// Add `compile_status`, `compile_errors` and `compiled_at` to rows of the
+/// `resources` table view. The extra columns are read-only.
+pub async fn attach_to_rows(
+    pool: &Pool<Sqlite>,
+    rows: &mut [serde_json::Value],
+    columns: &mut Vec<String>,
+) -> Result<(), String> {
+    let statuses = by_resource(pool).await?;
+    for row in rows.iter_mut() {
+        let Some(object) = row.as_object_mut() else {
+            continue;
+        };
+        let status = object
+            .get("id")
+            .and_then(|id| id.as_str())
+            .and_then(|id| statuses.get(id));
+        object.insert(
+            "compile_status".to_string(),
+            status.map(|s| s.status.clone()).into(),
+        );
+        object.insert(
+            "compile_errors".to_string(),
+            status.map(|s| s.error_count).into(),
+        );
+        object.insert(
+            "compiled_at".to_string(),
+            status.map(|s| s.compiled_at.clone()).into(),
+        );
+    }
+    for column in ["compile_status", "compile_errors", "compiled_at"] {
+        if !columns.iter().any(|c| c == column) {
+            columns.push(column.to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Put the status of each compiled file into its node's `metadata.compileStatus`
+pub async fn attach_to_tree(pool: &Pool<Sqlite>, nodes: &mut [TreeNode]) -> Result<(), String> {
+    fn walk(nodes: &mut [TreeNode], statuses: &HashMap<String, CompileStatus>) {
+        for node in nodes {
+            if node.r#type == "file" {
+                if let Some(status) = statuses.get(&node.id) {
+                    let value = serde_json::to_value(status).unwrap_or_default();
+                    node.set_metadata("compileStatus", value);
+                }
+            }
+            walk(&mut node.children, statuses);
+        }
+    }
+
+    let statuses = by_resource(pool).await?;
+    walk(nodes, &statuses);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        let log = "! Undefined control sequence.\nl.12 \\foo\n\nLaTeX Warning: Reference `a' on page 1 undefined on input line 3.\n";
+        assert_eq!(
+            summarize(&Err("Compilation failed".to_string()), Some(log)),
+            (
+                "error",
+                1,
+                1,
+                Some("l.12: Undefined control sequence.".to_string())
+            )
+        );
+        assert_eq!(
+            summarize(
+                &Err("Failed to execute command 'pdflatex'".to_string()),
+                None
+            ),
+            (
+                "error",
+                1,
+                0,
+                Some("Failed to execute command 'pdflatex'".to_string())
+            )
+        );
+        assert_eq!(summarize(&Ok(String::new()), Some("")), ("ok", 0, 0, None));
+    }
+
+    #[test]
+    fn test_log_path() {
+        assert_eq!(log_path("/a/b/ex.tex", ""), PathBuf::from("/a/b/ex.log"));
+        assert_eq!(
+            log_path("/a/b/ex.tex", "/out"),
+            PathBuf::from("/out/ex.log")
+        );
+    }
+}
@@ -19,7 +19,7 @@ fn is_allowed_engine(engine: &str) -> bool {
 }
 
 // Helper to add common LaTeX paths.
-fn get_augmented_path() -> String {
+pub(crate) fn get_augmented_path() -> String {
     let current_path = env::var("PATH").unwrap_or_default();
     let delimiter = if cfg!(windows) { ";" } else { ":" };
 
@@ -32,9 +32,10 @@ fn get_augmented_path() -> String {
         vec![]
     };
 
-    // Construct new PATH.
+    // Construct new PATH, with the directories from the settings first.
+    let tex_bin_dirs = crate::settings::current().tools.tex_bin_dirs;
     let mut new_path = current_path;
-    for p in common_paths {
+    for p in tex_bin_dirs.iter().map(String::as_str).chain(common_paths) {
         if !new_path.contains(p) {
             // Simple validation.
             new_path.push_str(delimiter);
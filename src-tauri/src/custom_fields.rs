@@ -0,0 +1,455 @@
+//! Custom Metadata Fields Module
+//!
+//! User-defined fields (text, number, enum, date) scoped to a collection, with
+//! per-resource values. Lets a collection carry e.g. "chapter" or "difficulty"
+//! without touching the typed metadata schema.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite};
+use uuid::Uuid;
+
+const FIELD_TYPES: [&str; 4] = ["text", "number", "enum", "date"];
+
+/// A field definition belonging to a collection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldDefinition {
+    pub id: String,
+    pub collection: String,
+    pub name: String,
+    pub label: Option<String>,
+    pub field_type: String,
+    pub options: Vec<String>,
+    pub required: bool,
+    pub order_index: i64,
+}
+
+/// Input for creating or updating a field definition
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldDefinitionInput {
+    pub collection: String,
+    pub name: String,
+    pub label: Option<String>,
+    pub field_type: String,
+    #[serde(default)]
+    pub options: Vec<String>,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub order_index: i64,
+}
+
+/// A field value attached to a resource
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldValue {
+    pub field_id: String,
+    pub name: String,
+    pub field_type: String,
+    pub value: serde_json::Value,
+}
+
+fn row_to_definition(row: &sqlx::sqlite::SqliteRow) -> FieldDefinition {
+    let options_str: Option<String> = row.get("options");
+    FieldDefinition {
+        id: row.get("id"),
+        collection: row.get("collection"),
+        name: row.get("name"),
+        label: row.get("label"),
+        field_type: row.get("field_type"),
+        options: options_str
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default(),
+        required: row.get::<i64, _>("required") != 0,
+        order_index: row.get("order_index"),
+    }
+}
+
+/// Validate and normalize a value for storage. `None` clears the value.
+pub fn normalize_value(
+    def: &FieldDefinition,
+    value: &serde_json::Value,
+) -> Result<Option<String>, String> {
+    if value.is_null() || value.as_str().is_some_and(|s| s.trim().is_empty()) {
+        if def.required {
+            return Err(format!("Field '{}' is required", def.name));
+        }
+        return Ok(None);
+    }
+
+    match def.field_type.as_str() {
+        "text" => Ok(Some(match value {
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        })),
+        "number" => {
+            let n = match value {
+                serde_json::Value::Number(n) => n.as_f64(),
+                serde_json::Value::String(s) => s.trim().parse::<f64>().ok(),
+                _ => None,
+            }
+            .ok_or_else(|| format!("Field '{}' expects a number", def.name))?;
+            Ok(Some(n.to_string()))
+        }
+        "enum" => {
+            let s = value
+                .as_str()
+                .ok_or_else(|| format!("Field '{}' expects one of its options", def.name))?;
+            if !def.options.iter().any(|o| o == s) {
+                return Err(format!(
+                    "'{}' is not a valid option for '{}' (expected one of: {})",
+                    s,
+                    def.name,
+                    def.options.join(", ")
+                ));
+            }
+            Ok(Some(s.to_string()))
+        }
+        "date" => {
+            let s = value
+                .as_str()
+                .ok_or_else(|| format!("Field '{}' expects a date", def.name))?;
+            let date = chrono::NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")
+                .map_err(|_| format!("Field '{}' expects a date as YYYY-MM-DD", def.name))?;
+            Ok(Some(date.format("%Y-%m-%d").to_string()))
+        }
+        other => Err(format!("Unknown field type: {}", other)),
+    }
+}
+
+/// Convert a stored value back to JSON according to the field type
+fn stored_to_json(field_type: &str, stored: Option<String>) -> serde_json::Value {
+    match stored {
+        None => serde_json::Value::Null,
+        Some(s) if field_type == "number" => s
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::String(s)),
+        Some(s) => serde_json::Value::String(s),
+    }
+}
+
+/// Stored values (resource id, value) of a field as `new` stores them; only
+/// the ones that change are returned. Fails when any value doesn't fit the
+/// new type or options.
+fn convert_values(
+    new: &FieldDefinition,
+    stored: Vec<(String, String)>,
+) -> Result<Vec<(String, String)>, String> {
+    let mut converted = Vec::new();
+    let mut misfits = Vec::new();
+    for (resource_id, value) in stored {
+        match normalize_value(new, &serde_json::Value::String(value.clone())) {
+            Ok(Some(new_value)) if new_value != value => converted.push((resource_id, new_value)),
+            Ok(_) => {}
+            Err(_) => misfits.push(value),
+        }
+    }
+    match misfits.first() {
+        None => Ok(converted),
+        Some(example) => Err(format!(
+            "{} stored value(s) of '{}' don't fit the new definition (e.g. '{}'); change or clear them first",
+            misfits.len(),
+            new.name,
+            example
+        )),
+    }
+}
+
+/// List the field definitions of a collection in display order
+pub async fn list_definitions(
+    pool: &Pool<Sqlite>,
+    collection: &str,
+) -> Result<Vec<FieldDefinition>, String> {
+    let rows = sqlx::query(
+        "SELECT id, collection, name, label, field_type, options, required, order_index
+         FROM custom_field_definitions
+         WHERE collection = ?
+         ORDER BY order_index, name",
+    )
+    .bind(collection)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows.iter().map(row_to_definition).collect())
+}
+
+async fn get_definition(pool: &Pool<Sqlite>, field_id: &str) -> Result<FieldDefinition, String> {
+    let row = sqlx::query(
+        "SELECT id, collection, name, label, field_type, options, required, order_index
+         FROM custom_field_definitions WHERE id = ?",
+    )
+    .bind(field_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Field definition not found: {}", field_id))?;
+
+    Ok(row_to_definition(&row))
+}
+
+/// Create a field definition, or update the existing one with the same name
+pub async fn define_field(
+    pool: &Pool<Sqlite>,
+    input: &FieldDefinitionInput,
+) -> Result<FieldDefinition, String> {
+    let name = input.name.trim();
+    if name.is_empty() {
+        return Err("Field name cannot be empty".to_string());
+    }
+    if !FIELD_TYPES.contains(&input.field_type.as_str()) {
+        return Err(format!(
+            "Invalid field type '{}' (expected one of: {})",
+            input.field_type,
+            FIELD_TYPES.join(", ")
+        ));
+    }
+    if input.field_type == "enum" && input.options.is_empty() {
+        return Err("Enum fields need at least one option".to_string());
+    }
+
+    let options = serde_json::to_string(&input.options).map_err(|e| e.to_string())?;
+
+    let existing: Option<String> = sqlx::query_scalar(
+        "SELECT id FROM custom_field_definitions WHERE collection = ? AND name = ?",
+    )
+    .bind(&input.collection)
+    .bind(name)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let id = match existing {
+        Some(id) => {
+            // Stored values must still fit a new type or option list
+            let old = get_definition(pool, &id).await?;
+            let new = FieldDefinition {
+                field_type: input.field_type.clone(),
+                options: input.options.clone(),
+                ..old
+            };
+            let stored: Vec<(String, String)> = sqlx::query_as(
+                "SELECT resource_id, value FROM custom_field_values
+                 WHERE field_id = ? AND value IS NOT NULL",
+            )
+            .bind(&id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+            let converted = convert_values(&new, stored)?;
+
+            let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+            sqlx::query(
+                "UPDATE custom_field_definitions
+                 SET label = ?, field_type = ?, options = ?, required = ?, order_index = ?
+                 WHERE id = ?",
+            )
+            .bind(&input.label)
+            .bind(&input.field_type)
+            .bind(&options)
+            .bind(input.required)
+            .bind(input.order_index)
+            .bind(&id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+            for (resource_id, value) in converted {
+                sqlx::query(
+                    "UPDATE custom_field_values SET value = ?, updated_at = CURRENT_TIMESTAMP
+                     WHERE resource_id = ? AND field_id = ?",
+                )
+                .bind(value)
+                .bind(&resource_id)
+                .bind(&id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+            }
+            tx.commit().await.map_err(|e| e.to_string())?;
+            id
+        }
+        None => {
+            let id = Uuid::new_v4().to_string();
+            sqlx::query(
+                "INSERT INTO custom_field_definitions (id, collection, name, label, field_type, options, required, order_index)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&id)
+            .bind(&input.collection)
+            .bind(name)
+            .bind(&input.label)
+            .bind(&input.field_type)
+            .bind(&options)
+            .bind(input.required)
+            .bind(input.order_index)
+            .execute(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+            id
+        }
+    };
+
+    get_definition(pool, &id).await
+}
+
+/// Delete a field definition together with all its values
+pub async fn delete_definition(pool: &Pool<Sqlite>, field_id: &str) -> Result<(), String> {
+    sqlx::query("DELETE FROM custom_field_definitions WHERE id = ?")
+        .bind(field_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Get all custom field values of a resource (fields without a value included as null)
+pub async fn get_values(pool: &Pool<Sqlite>, resource_id: &str) -> Result<Vec<FieldValue>, String> {
+    let rows = sqlx::query(
+        "SELECT d.id, d.name, d.field_type, v.value
+         FROM resources r
+         JOIN custom_field_definitions d ON d.collection = r.collection
+         LEFT JOIN custom_field_values v ON v.field_id = d.id AND v.resource_id = r.id
+         WHERE r.id = ?
+         ORDER BY d.order_index, d.name",
+    )
+    .bind(resource_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let field_type: String = row.get("field_type");
+            FieldValue {
+                field_id: row.get("id"),
+                name: row.get("name"),
+                value: stored_to_json(&field_type, row.get("value")),
+                field_type,
+            }
+        })
+        .collect())
+}
+
+/// Validate and store a single field value for a resource
+pub async fn set_value(
+    pool: &Pool<Sqlite>,
+    resource_id: &str,
+    field_id: &str,
+    value: &serde_json::Value,
+) -> Result<(), String> {
+    let def = get_definition(pool, field_id).await?;
+
+    let collection: Option<String> =
+        sqlx::query_scalar("SELECT collection FROM resources WHERE id = ?")
+            .bind(resource_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    match collection {
+        None => return Err("Resource not found".to_string()),
+        Some(c) if c != def.collection => {
+            return Err(format!(
+                "Field '{}' belongs to collection '{}', not '{}'",
+                def.name, def.collection, c
+            ))
+        }
+        _ => {}
+    }
+
+    match normalize_value(&def, value)? {
+        Some(stored) => {
+            sqlx::query(
+                "INSERT OR REPLACE INTO custom_field_values (resource_id, field_id, value, updated_at)
+                 VALUES (?, ?, ?, CURRENT_TIMESTAMP)",
+            )
+            .bind(resource_id)
+            .bind(field_id)
+            .bind(stored)
+            .execute(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        }
+        None => {
+            sqlx::query("DELETE FROM custom_field_values WHERE resource_id = ? AND field_id = ?")
+                .bind(resource_id)
+                .bind(field_id)
+                .execute(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn def(field_type: &str, options: &[&str]) -> FieldDefinition {
+        FieldDefinition {
+            id: "f".to_string(),
+            collection: "physics".to_string(),
+            name: "difficulty".to_string(),
+            label: None,
+            field_type: field_type.to_string(),
+            options: options.iter().map(|s| s.to_string()).collect(),
+            required: false,
+            order_index: 0,
+        }
+    }
+
+    #[test]
+    fn test_normalize_number() {
+        let d = def("number", &[]);
+        assert_eq!(
+            normalize_value(&d, &serde_json::json!("3")).unwrap(),
+            Some("3".to_string())
+        );
+        assert!(normalize_value(&d, &serde_json::json!("hard")).is_err());
+    }
+
+    #[test]
+    fn test_normalize_enum_and_date() {
+        let e = def("enum", &["easy", "hard"]);
+        assert!(normalize_value(&e, &serde_json::json!("easy")).is_ok());
+        assert!(normalize_value(&e, &serde_json::json!("medium")).is_err());
+
+        let d = def("date", &[]);
+        assert_eq!(
+            normalize_value(&d, &serde_json::json!("2024-02-29")).unwrap(),
+            Some("2024-02-29".to_string())
+        );
+        assert!(normalize_value(&d, &serde_json::json!("29/02/2024")).is_err());
+    }
+
+    #[test]
+    fn test_convert_values() {
+        let stored = |values: &[&str]| -> Vec<(String, String)> {
+            values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (i.to_string(), v.to_string()))
+                .collect()
+        };
+        // Numbers become text unchanged
+        assert_eq!(
+            convert_values(&def("text", &[]), stored(&["3", "2.5"])).unwrap(),
+            []
+        );
+        // Text that isn't a number blocks the change
+        let err = convert_values(&def("number", &[]), stored(&["3", "hard"])).unwrap_err();
+        assert!(err.contains("1 stored value(s)") && err.contains("'hard'"));
+        // So does dropping an option still in use
+        assert!(convert_values(&def("enum", &["easy"]), stored(&["hard"])).is_err());
+        assert_eq!(
+            convert_values(&def("number", &[]), stored(&["03"])).unwrap(),
+            [("0".to_string(), "3".to_string())]
+        );
+    }
+}
@@ -0,0 +1,348 @@
+//! Audit log of database changes.
+//!
+//! Every audited table gets AFTER INSERT/UPDATE/DELETE triggers that append a
+//! row to `change_log` (migration 018) with the old and new row as JSON. The
+//! triggers are generated from the live schema and reinstalled on every
+//! startup, so columns and tables added by later migrations are picked up
+//! without hand-written SQL.
+
+use serde::Serialize;
+use sqlx::{Pool, Row, Sqlite};
+
+use super::schema::{self, ColumnInfo};
+
+/// Tables that are never audited: the log itself, content snapshots that are
+/// their own history and window state. Virtual tables, their shadow tables
+/// and SQLite's internal tables are skipped as well.
+const EXCLUDED_TABLES: &[&str] = &[
+    "change_log",
+    "audit_context",
+    "file_history",
+    "history_blobs",
+    "resources_content_indexed",
+    "sessions",
+    "session_tabs",
+    "spelling_words",
+];
+
+/// Column pairs per json_object/json_set call, well below SQLite's argument limit
+const JSON_CHUNK: usize = 40;
+
+/// One entry of the change log
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeEntry {
+    pub id: i64,
+    pub table_name: String,
+    pub row_id: Option<String>,
+    pub resource_id: Option<String>,
+    pub action: String,
+    pub actor: Option<String>,
+    pub old_values: Option<serde_json::Value>,
+    pub new_values: Option<serde_json::Value>,
+    pub changed_at: String,
+}
+
+/// SQLite refuses triggers on virtual tables, and the shadow tables behind
+/// them are written by the virtual table itself
+fn is_audited(table: &str, virtual_tables: &[String]) -> bool {
+    !EXCLUDED_TABLES.contains(&table)
+        && !table.starts_with("sqlite_")
+        && !virtual_tables.iter().any(|v| {
+            table == v
+                || table
+                    .strip_prefix(v.as_str())
+                    .is_some_and(|rest| rest.starts_with('_'))
+        })
+}
+
+fn sql_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// `json_object(...)` of the row referenced by `prefix` (NEW or OLD)
+fn row_json(prefix: &str, columns: &[ColumnInfo]) -> String {
+    // BLOB values can't be stored in JSON
+    let cols: Vec<&ColumnInfo> = columns
+        .iter()
+        .filter(|c| !c.decl_type.to_uppercase().contains("BLOB"))
+        .collect();
+
+    let mut chunks = cols.chunks(JSON_CHUNK);
+    let first = chunks.next().unwrap_or(&[]);
+    let mut expr = format!(
+        "json_object({})",
+        first
+            .iter()
+            .map(|c| format!(
+                "{}, {}.{}",
+                sql_string(&c.name),
+                prefix,
+                schema::quote_identifier(&c.name)
+            ))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    for chunk in chunks {
+        expr = format!(
+            "json_set({}, {})",
+            expr,
+            chunk
+                .iter()
+                .map(|c| format!(
+                    "{}, {}.{}",
+                    sql_string(&format!("$.\"{}\"", c.name)),
+                    prefix,
+                    schema::quote_identifier(&c.name)
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    expr
+}
+
+/// Expression for the resource a row belongs to, if the table has one
+fn resource_expr(table: &str, columns: &[ColumnInfo], prefix: &str) -> String {
+    let has = |name: &str| columns.iter().any(|c| c.name == name);
+    if table == "resources" {
+        format!("{}.id", prefix)
+    } else if has("resource_id") {
+        format!("{}.resource_id", prefix)
+    } else if has("source_id") {
+        format!("{}.source_id", prefix)
+    } else {
+        "NULL".to_string()
+    }
+}
+
+/// Expression identifying the changed row: `id`, else the primary key columns
+fn row_id_expr(columns: &[ColumnInfo], prefix: &str) -> String {
+    if columns.iter().any(|c| c.name == "id") {
+        return format!("CAST({}.id AS TEXT)", prefix);
+    }
+    let pk: Vec<String> = columns
+        .iter()
+        .filter(|c| c.primary_key)
+        .map(|c| format!("{}.{}", prefix, schema::quote_identifier(&c.name)))
+        .collect();
+    match pk.len() {
+        0 => "NULL".to_string(),
+        1 => format!("CAST({} AS TEXT)", pk[0]),
+        _ => format!("json_array({})", pk.join(", ")),
+    }
+}
+
+/// DROP/CREATE statements for the audit triggers of one table
+pub fn trigger_sql(table: &str, columns: &[ColumnInfo]) -> Vec<String> {
+    let quoted_table = schema::quote_identifier(table);
+    let actor = "(SELECT actor FROM audit_context WHERE id = 1)";
+    let mut statements = Vec::new();
+
+    for (action, prefix) in [("insert", "NEW"), ("update", "NEW"), ("delete", "OLD")] {
+        let name = schema::quote_identifier(&format!("audit_{}_{}", table, action));
+        statements.push(format!("DROP TRIGGER IF EXISTS {}", name));
+
+        let (old_values, new_values) = match action {
+            "insert" => ("NULL".to_string(), row_json("NEW", columns)),
+            "update" => (row_json("OLD", columns), row_json("NEW", columns)),
+            _ => (row_json("OLD", columns), "NULL".to_string()),
+        };
+
+        // Skip no-op updates, including the nested ones that only bump updated_at
+        let when = if action == "update" {
+            let changed: Vec<String> = columns
+                .iter()
+                .filter(|c| c.name != "updated_at")
+                .map(|c| {
+                    let col = schema::quote_identifier(&c.name);
+                    format!("OLD.{} IS NOT NEW.{}", col, col)
+                })
+                .collect();
+            if changed.is_empty() {
+                continue;
+            }
+            format!(" WHEN {}", changed.join(" OR "))
+        } else {
+            String::new()
+        };
+
+        statements.push(format!(
+            "CREATE TRIGGER {name} AFTER {upper} ON {quoted_table}{when}
+BEGIN
+    INSERT INTO change_log (table_name, row_id, resource_id, action, actor, old_values, new_values)
+    VALUES ({table_lit}, {row_id}, {resource}, '{action}', {actor}, {old_values}, {new_values});
+END",
+            upper = action.to_uppercase(),
+            table_lit = sql_string(table),
+            row_id = row_id_expr(columns, prefix),
+            resource = resource_expr(table, columns, prefix),
+        ));
+    }
+
+    statements
+}
+
+/// (Re)install audit triggers on all audited tables. Returns the table count.
+pub async fn install_triggers(pool: &Pool<Sqlite>) -> Result<usize, String> {
+    let tables = schema::list_tables(pool).await?;
+    let virtual_tables = schema::virtual_tables(pool).await?;
+    let mut count = 0;
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    for table in tables.iter().filter(|t| is_audited(t, &virtual_tables)) {
+        let columns = schema::column_info(pool, table).await?;
+        for stmt in trigger_sql(table, &columns) {
+            sqlx::query(&stmt)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Audit trigger for {}: {}", table, e))?;
+        }
+        count += 1;
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(count)
+}
+
+/// Whether deletions from `table` are being logged
+pub async fn is_logging_deletes(pool: &Pool<Sqlite>, table: &str) -> Result<bool, String> {
+    let name = format!("audit_{}_delete", table);
+    let found: Option<String> =
+        sqlx::query_scalar("SELECT name FROM sqlite_master WHERE type = 'trigger' AND name = ?")
+            .bind(&name)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    Ok(found.is_some())
+}
+
+/// The OS user name, recorded as the actor of each change
+pub fn current_actor() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+}
+
+pub async fn set_actor(pool: &Pool<Sqlite>, actor: Option<&str>) -> Result<(), String> {
+    sqlx::query("INSERT OR REPLACE INTO audit_context (id, actor) VALUES (1, ?)")
+        .bind(actor)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Changes touching a resource (its own row and rows that reference it), newest first
+pub async fn get_change_history(
+    pool: &Pool<Sqlite>,
+    resource_id: &str,
+    limit: i64,
+) -> Result<Vec<ChangeEntry>, String> {
+    let rows = sqlx::query(
+        "SELECT id, table_name, row_id, resource_id, action, actor, old_values, new_values, changed_at
+         FROM change_log
+         WHERE resource_id = ?
+         ORDER BY id DESC
+         LIMIT ?",
+    )
+    .bind(resource_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let parse = |s: Option<String>| s.and_then(|s| serde_json::from_str(&s).ok());
+
+    Ok(rows
+        .iter()
+        .map(|row| ChangeEntry {
+            id: row.get("id"),
+            table_name: row.get("table_name"),
+            row_id: row.get("row_id"),
+            resource_id: row.get("resource_id"),
+            action: row.get("action"),
+            actor: row.get("actor"),
+            old_values: parse(row.get("old_values")),
+            new_values: parse(row.get("new_values")),
+            changed_at: row.get("changed_at"),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn col(name: &str, decl_type: &str, primary_key: bool) -> ColumnInfo {
+        ColumnInfo {
+            name: name.to_string(),
+            decl_type: decl_type.to_string(),
+            not_null: false,
+            has_default: false,
+            primary_key,
+        }
+    }
+
+    #[test]
+    fn test_trigger_sql_for_resource_table() {
+        let cols = vec![
+            col("resource_id", "TEXT", true),
+            col("tag", "TEXT", true),
+            col("updated_at", "DATETIME", false),
+        ];
+        let sql = trigger_sql("resource_file_tags", &cols);
+        assert_eq!(sql.len(), 6);
+        assert!(sql[1].contains("json_array(NEW.\"resource_id\", NEW.\"tag\")"));
+        assert!(sql[1].contains("NEW.resource_id"));
+        // updated_at alone never triggers an update entry
+        assert!(!sql[3].contains("OLD.\"updated_at\" IS NOT"));
+    }
+
+    #[test]
+    fn test_excluded_tables() {
+        let virtual_tables = vec![
+            "resources_fts".to_string(),
+            "resources_content_trigram".to_string(),
+        ];
+        assert!(is_audited("resources", &virtual_tables));
+        assert!(!is_audited("change_log", &virtual_tables));
+        assert!(!is_audited("resources_fts_data", &virtual_tables));
+        assert!(!is_audited("resources_content_trigram", &virtual_tables));
+        assert!(!is_audited(
+            "resources_content_trigram_idx",
+            &virtual_tables
+        ));
+    }
+
+    #[test]
+    fn test_install_triggers_on_migrated_database() {
+        let dir = std::env::temp_dir().join(format!("datatex-audit-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let db = crate::database::DatabaseManager::new(&dir.to_string_lossy())
+                .await
+                .unwrap();
+            // Every migration applied, so every table (virtual ones too) is there
+            assert!(install_triggers(&db.pool).await.unwrap() > 0);
+
+            sqlx::query("INSERT INTO collections (name, type) VALUES ('c', 'manual')")
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            sqlx::query(
+                "INSERT INTO resources (id, path, type, collection) VALUES ('r1', '/tmp/a.tex', 'file', 'c')",
+            )
+            .execute(&db.pool)
+            .await
+            .unwrap();
+            let history = get_change_history(&db.pool, "r1", 10).await.unwrap();
+            assert!(history
+                .iter()
+                .any(|e| e.table_name == "resources" && e.action == "insert"));
+            db.pool.close().await;
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
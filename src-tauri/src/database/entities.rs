@@ -14,7 +14,7 @@ pub struct Collection {
     pub created_at: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Resource {
     pub id: String,
     pub path: String,
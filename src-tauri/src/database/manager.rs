@@ -1,8 +1,112 @@
 use crate::database::entities::{Collection, Resource};
-use sqlx::{migrate::MigrateDatabase, sqlite::SqlitePoolOptions, Pool, Row, Sqlite};
+use crate::database::{audit, schema};
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
+use sqlx::{migrate::MigrateDatabase, Pool, Row, Sqlite};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How long a connection waits on a locked database before giving up
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+/// SQLite allows a single writer; a few connections are enough for concurrent reads
+const MAX_CONNECTIONS: u32 = 8;
+
+/// Connection settings and basic statistics reported by `db_health`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbHealth {
+    pub journal_mode: String,
+    pub busy_timeout_ms: i64,
+    pub foreign_keys: bool,
+    pub synchronous: i64,
+    pub schema_version: i64,
+    pub page_count: i64,
+    pub page_size: i64,
+    pub freelist_count: i64,
+    pub pool_size: u32,
+    pub pool_idle: usize,
+    pub max_connections: u32,
+    pub quick_check: String,
+}
+
+/// Server-side sorting and filtering options for get_table_data
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableQuery {
+    pub sort: Option<TableSort>,
+    #[serde(default)]
+    pub filters: Vec<ColumnFilter>,
+}
+
+/// Sort order requested by the table view
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableSort {
+    pub column: String,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+/// Typed per-column filter for get_table_data
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnFilter {
+    pub column: String,
+    #[serde(flatten)]
+    pub op: FilterOp,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum FilterOp {
+    /// Exact match (numbers compare numerically, everything else as text)
+    Equals { value: serde_json::Value },
+    /// Substring match (LIKE %value%)
+    Contains { value: String },
+    /// Inclusive range; works for numeric columns and ISO date strings
+    Range {
+        min: Option<serde_json::Value>,
+        max: Option<serde_json::Value>,
+    },
+}
+
+/// A bound parameter for dynamically built queries
+enum SqlParam {
+    Text(String),
+    Int(i64),
+    Real(f64),
+}
+
+impl SqlParam {
+    fn from_json(value: &serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Number(n) => {
+                if let Some(i) = n.as_i64() {
+                    SqlParam::Int(i)
+                } else {
+                    SqlParam::Real(n.as_f64().unwrap_or(0.0))
+                }
+            }
+            serde_json::Value::Bool(b) => SqlParam::Int(*b as i64),
+            serde_json::Value::String(s) => SqlParam::Text(s.clone()),
+            other => SqlParam::Text(other.to_string()),
+        }
+    }
+}
+
+async fn pragma_i64(conn: &mut sqlx::SqliteConnection, name: &str) -> Result<i64, String> {
+    sqlx::query_scalar::<_, i64>(&format!("PRAGMA {}", name))
+        .fetch_one(conn)
+        .await
+        .map_err(|e| format!("PRAGMA {}: {}", name, e))
+}
 
 pub struct DatabaseManager {
     pub pool: Pool<Sqlite>,
+    /// Directory holding project.db and backend-managed files (attachments)
+    pub data_dir: PathBuf,
 }
 
 impl DatabaseManager {
@@ -14,35 +118,84 @@ impl DatabaseManager {
             Sqlite::create_database(&db_url).await?;
         }
 
-        let pool = SqlitePoolOptions::new().connect(&db_url).await?;
+        // WAL lets readers proceed while a write is in flight, and the busy
+        // timeout makes concurrent writers wait instead of failing with
+        // SQLITE_BUSY.
+        let connect_options = SqliteConnectOptions::from_str(&db_url)?
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(BUSY_TIMEOUT)
+            .foreign_keys(true);
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(MAX_CONNECTIONS)
+            .min_connections(1)
+            .acquire_timeout(Duration::from_secs(10))
+            .connect_with(connect_options)
+            .await?;
 
         // Initialize schema
         Self::init_schema(&pool).await?;
 
-        Ok(Self { pool })
+        // Audit triggers follow the current schema, so they're rebuilt after migrations
+        if let Err(e) = audit::install_triggers(&pool).await {
+            eprintln!("Failed to install audit triggers: {}", e);
+        }
+        if let Err(e) = audit::set_actor(&pool, audit::current_actor().as_deref()).await {
+            eprintln!("Failed to set audit actor: {}", e);
+        }
+
+        Ok(Self {
+            pool,
+            data_dir: PathBuf::from(data_dir),
+        })
     }
 
-    async fn init_schema(pool: &Pool<Sqlite>) -> Result<(), sqlx::Error> {
-        // Load all schema files in numeric order
-        // New migrations should be added at the end with incrementing numbers
-        let schemas = [
-            include_str!("../../migrations/init.sql"), // 0
-            include_str!("../../migrations/002_common_infrastructure.sql"), // 1
-            include_str!("../../migrations/003_resource_files.sql"), // 2
-            include_str!("../../migrations/004_resource_documents.sql"), // 3
-            include_str!("../../migrations/005_resource_tables.sql"), // 4
-            include_str!("../../migrations/006_resource_figures.sql"), // 5
-            include_str!("../../migrations/007_resource_commands.sql"), // 6
-            include_str!("../../migrations/008_resource_packages.sql"), // 7
-            include_str!("../../migrations/009_resource_preambles.sql"), // 8
-            include_str!("../../migrations/010_resource_classes.sql"), // 9
-            include_str!("../../migrations/011_migrate_json_to_typed.sql"), // 10
-            include_str!("../../migrations/012_resource_bibliographies.sql"), // 11
-            include_str!("../../migrations/013_resource_dtx_ins.sql"), // 12
-            include_str!("../../migrations/014_add_collection_path.sql"), // 13
-            include_str!("../../migrations/015_file_history.sql"), // 14 - Local history
-        ];
+    /// Schema files in numeric order. New migrations should be added at the
+    /// end with incrementing numbers
+    const MIGRATIONS: &'static [&'static str] = &[
+        include_str!("../../migrations/init.sql"), // 0
+        include_str!("../../migrations/002_common_infrastructure.sql"), // 1
+        include_str!("../../migrations/003_resource_files.sql"), // 2
+        include_str!("../../migrations/004_resource_documents.sql"), // 3
+        include_str!("../../migrations/005_resource_tables.sql"), // 4
+        include_str!("../../migrations/006_resource_figures.sql"), // 5
+        include_str!("../../migrations/007_resource_commands.sql"), // 6
+        include_str!("../../migrations/008_resource_packages.sql"), // 7
+        include_str!("../../migrations/009_resource_preambles.sql"), // 8
+        include_str!("../../migrations/010_resource_classes.sql"), // 9
+        include_str!("../../migrations/011_migrate_json_to_typed.sql"), // 10
+        include_str!("../../migrations/012_resource_bibliographies.sql"), // 11
+        include_str!("../../migrations/013_resource_dtx_ins.sql"), // 12
+        include_str!("../../migrations/014_add_collection_path.sql"), // 13
+        include_str!("../../migrations/015_file_history.sql"), // 14 - Local history
+        include_str!("../../migrations/016_resource_search_fts.sql"), // 15 - Metadata FTS
+        include_str!("../../migrations/017_custom_fields.sql"), // 16 - Custom fields
+        include_str!("../../migrations/018_change_log.sql"), // 17 - Audit log
+        include_str!("../../migrations/019_attachments.sql"), // 18 - Attachments
+        include_str!("../../migrations/020_bib_entries.sql"), // 19 - Bibliography entries
+        include_str!("../../migrations/021_templates.sql"), // 20 - Document templates
+        include_str!("../../migrations/022_collection_solution_environments.sql"), // 21 - Solution environments
+        include_str!("../../migrations/023_collection_preambles.sql"), // 22 - Collection preambles
+        include_str!("../../migrations/024_compile_status.sql"),       // 23 - Compile status
+        include_str!("../../migrations/025_content_trigram_index.sql"), // 24 - Content substring index
+        include_str!("../../migrations/026_deleted_resources.sql"),     // 25 - Recycle bin
+        include_str!("../../migrations/027_sessions.sql"),              // 26 - Sessions
+        include_str!("../../migrations/028_history_blobs.sql"),         // 27 - History blobs
+        include_str!("../../migrations/029_spelling_words.sql"),        // 28 - Spelling dictionary
+        include_str!("../../migrations/030_zotero_links.sql"), // 29 - Zotero-synced bibliographies
+        include_str!("../../migrations/031_resource_opens.sql"), // 30 - Quick-open usage log
+        include_str!("../../migrations/032_content_index_times.sql"), // 31 - Content index times
+    ];
+
+    /// Migrations that lose data if one statement fails and the rest still
+    /// run; they run in one transaction and stop the upgrade on an error
+    const ATOMIC_MIGRATIONS: &'static [usize] = &[
+        27, // 028 copies snapshot contents out, then drops the column
+    ];
 
+    async fn init_schema(pool: &Pool<Sqlite>) -> Result<(), sqlx::Error> {
         // Check current version
         let version_row: (i32,) = sqlx::query_as("PRAGMA user_version")
             .fetch_one(pool)
@@ -60,16 +213,17 @@ impl DatabaseManager {
             .await
             .unwrap_or((0,));
 
+            // Such databases have the tables of the first 15 migrations
             if has_preamble_types.0 > 0 {
-                println!("Detected legacy DB with preamble_types. Setting version to 20.");
-                current_version = 20;
+                println!("Detected legacy DB with preamble_types. Setting version to 15.");
+                current_version = 15;
                 sqlx::query(&format!("PRAGMA user_version = {}", current_version))
                     .execute(pool)
                     .await?;
             }
         }
 
-        for (i, init_script) in schemas.iter().enumerate() {
+        for (i, init_script) in Self::MIGRATIONS.iter().enumerate() {
             if i < current_version {
                 continue;
             }
@@ -111,6 +265,24 @@ impl DatabaseManager {
                 }
             }
 
+            if Self::ATOMIC_MIGRATIONS.contains(&i) {
+                let mut tx = pool.begin().await?;
+                for stmt in &statements {
+                    let stmt = stmt.trim();
+                    if !stmt.is_empty() {
+                        if let Err(e) = sqlx::query(stmt).execute(&mut *tx).await {
+                            eprintln!("Migration {} failed and was rolled back: {}", i, e);
+                            return Err(e);
+                        }
+                    }
+                }
+                sqlx::query(&format!("PRAGMA user_version = {}", i + 1))
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+                continue;
+            }
+
             for stmt in statements {
                 let stmt = stmt.trim();
                 if !stmt.is_empty() {
@@ -131,6 +303,44 @@ impl DatabaseManager {
         Ok(())
     }
 
+    /// Reports the pragmas that matter for concurrency and integrity
+    pub async fn health(&self) -> Result<DbHealth, String> {
+        // All pragmas on one connection so they describe the same session
+        let mut conn = self.pool.acquire().await.map_err(|e| e.to_string())?;
+
+        let busy_timeout_ms = pragma_i64(&mut conn, "busy_timeout").await?;
+        let foreign_keys = pragma_i64(&mut conn, "foreign_keys").await? != 0;
+        let synchronous = pragma_i64(&mut conn, "synchronous").await?;
+        let schema_version = pragma_i64(&mut conn, "user_version").await?;
+        let page_count = pragma_i64(&mut conn, "page_count").await?;
+        let page_size = pragma_i64(&mut conn, "page_size").await?;
+        let freelist_count = pragma_i64(&mut conn, "freelist_count").await?;
+
+        let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode")
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        let quick_check: String = sqlx::query_scalar("PRAGMA quick_check")
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(DbHealth {
+            journal_mode,
+            busy_timeout_ms,
+            foreign_keys,
+            synchronous,
+            schema_version,
+            page_count,
+            page_size,
+            freelist_count,
+            pool_size: self.pool.size(),
+            pool_idle: self.pool.num_idle(),
+            max_connections: MAX_CONNECTIONS,
+            quick_check,
+        })
+    }
+
     // --- New Methods ---
 
     pub async fn get_collections(&self) -> Result<Vec<Collection>, String> {
@@ -191,6 +401,17 @@ impl DatabaseManager {
         Ok(())
     }
 
+    /// Point a collection at another folder
+    pub async fn set_collection_path(&self, name: &str, path: &str) -> Result<(), String> {
+        sqlx::query("UPDATE collections SET path = ? WHERE name = ?")
+            .bind(path)
+            .bind(name)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
     pub async fn add_resource(&self, resource: &Resource) -> Result<(), String> {
         // Serialize metadata to JSON string
         let meta_str = serde_json::to_string(&resource.metadata).unwrap_or("{}".to_string());
@@ -209,17 +430,32 @@ impl DatabaseManager {
         Ok(())
     }
 
-    pub async fn validate_identifier(&self, table: &str, column: Option<&str>) -> bool {
-        let is_valid_name = |s: &str| s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
-        if !is_valid_name(table) {
-            return false;
-        }
+    /// Updates the fields a directory scan derives from disk (kind, title,
+    /// hash) in place, keeping the row id and any user metadata intact.
+    pub async fn update_scanned_resource(&self, resource: &Resource) -> Result<(), String> {
+        sqlx::query("UPDATE resources SET type = ?, title = ?, content_hash = ? WHERE id = ?")
+            .bind(&resource.kind)
+            .bind(&resource.title)
+            .bind(&resource.content_hash)
+            .bind(&resource.id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Validates `table` (and optionally `column`) against the live schema and
+    /// returns the table's column list.
+    pub async fn validate_identifier(
+        &self,
+        table: &str,
+        column: Option<&str>,
+    ) -> Result<Vec<String>, String> {
+        let columns = schema::table_columns(&self.pool, table).await?;
         if let Some(col) = column {
-            if !is_valid_name(col) {
-                return false;
-            }
+            schema::ensure_columns(table, &columns, &[col])?;
         }
-        true
+        Ok(columns)
     }
 
     pub async fn get_table_data(
@@ -229,47 +465,88 @@ impl DatabaseManager {
         page_size: i64,
         search: String,
         search_cols: Vec<String>,
+        query: TableQuery,
     ) -> Result<(Vec<serde_json::Value>, i64, Vec<String>), String> {
-        if !self.validate_identifier(&table_name, None).await {
-            return Err("Invalid table name".to_string());
-        }
-
-        // 1. Get Schema (Columns)
-        let schema_query = format!("PRAGMA table_info({})", table_name);
-        let schema_rows = sqlx::query(&schema_query)
-            .fetch_all(&self.pool)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        let columns: Vec<String> = schema_rows.iter().map(|r| r.get("name")).collect();
+        // 1. Get Schema (Columns) - also rejects unknown tables
+        let columns = self.validate_identifier(&table_name, None).await?;
+        let table = schema::quote_identifier(&table_name);
 
         // 2. Build Where Clause
-        let mut where_clause = String::new();
-        let mut params: Vec<String> = Vec::new();
+        let mut where_parts: Vec<String> = Vec::new();
+        let mut params: Vec<SqlParam> = Vec::new();
 
         if !search.is_empty() && !search_cols.is_empty() {
             let conditions: Vec<String> = search_cols
                 .iter()
                 .filter(|c| columns.contains(c))
-                .map(|c| format!("{} LIKE ?", c))
+                .map(|c| format!("{} LIKE ?", schema::quote_identifier(c)))
                 .collect();
 
             if !conditions.is_empty() {
-                where_clause = format!("WHERE {}", conditions.join(" OR "));
+                where_parts.push(format!("({})", conditions.join(" OR ")));
                 for _ in 0..conditions.len() {
-                    params.push(format!("%{}%", search));
+                    params.push(SqlParam::Text(format!("%{}%", search)));
+                }
+            }
+        }
+
+        // Typed column filters are ANDed with the free-text search
+        for filter in &query.filters {
+            schema::ensure_columns(&table_name, &columns, &[filter.column.as_str()])?;
+            let col = schema::quote_identifier(&filter.column);
+            match &filter.op {
+                FilterOp::Equals { value } => {
+                    if value.is_null() {
+                        where_parts.push(format!("{} IS NULL", col));
+                    } else {
+                        where_parts.push(format!("{} = ?", col));
+                        params.push(SqlParam::from_json(value));
+                    }
+                }
+                FilterOp::Contains { value } => {
+                    where_parts.push(format!("{} LIKE ?", col));
+                    params.push(SqlParam::Text(format!("%{}%", value)));
+                }
+                FilterOp::Range { min, max } => {
+                    if let Some(min) = min.as_ref().filter(|v| !v.is_null()) {
+                        where_parts.push(format!("{} >= ?", col));
+                        params.push(SqlParam::from_json(min));
+                    }
+                    if let Some(max) = max.as_ref().filter(|v| !v.is_null()) {
+                        where_parts.push(format!("{} <= ?", col));
+                        params.push(SqlParam::from_json(max));
+                    }
                 }
             }
         }
 
+        let where_clause = if where_parts.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", where_parts.join(" AND "))
+        };
+
+        let order_clause = match &query.sort {
+            Some(sort) => {
+                schema::ensure_columns(&table_name, &columns, &[sort.column.as_str()])?;
+                format!(
+                    "ORDER BY {} {}",
+                    schema::quote_identifier(&sort.column),
+                    if sort.descending { "DESC" } else { "ASC" }
+                )
+            }
+            None => String::new(),
+        };
+
         // 3. Count Query
-        let count_query = format!(
-            "SELECT COUNT(*) as count FROM {} {}",
-            table_name, where_clause
-        );
+        let count_query = format!("SELECT COUNT(*) as count FROM {} {}", table, where_clause);
         let mut count_q = sqlx::query(&count_query);
         for p in &params {
-            count_q = count_q.bind(p);
+            count_q = match p {
+                SqlParam::Text(v) => count_q.bind(v),
+                SqlParam::Int(v) => count_q.bind(v),
+                SqlParam::Real(v) => count_q.bind(v),
+            };
         }
         let count_row = count_q
             .fetch_one(&self.pool)
@@ -280,13 +557,17 @@ impl DatabaseManager {
         // 4. Data Query
         let offset = (page - 1) * page_size;
         let data_query = format!(
-            "SELECT * FROM {} {} LIMIT ? OFFSET ?",
-            table_name, where_clause
+            "SELECT * FROM {} {} {} LIMIT ? OFFSET ?",
+            table, where_clause, order_clause
         );
 
         let mut data_q = sqlx::query(&data_query);
         for p in &params {
-            data_q = data_q.bind(p);
+            data_q = match p {
+                SqlParam::Text(v) => data_q.bind(v),
+                SqlParam::Int(v) => data_q.bind(v),
+                SqlParam::Real(v) => data_q.bind(v),
+            };
         }
         data_q = data_q.bind(page_size).bind(offset);
 
@@ -296,24 +577,10 @@ impl DatabaseManager {
             .map_err(|e| e.to_string())?;
 
         // 5. Convert to JSON
-        let mut result_data = Vec::new();
-        for row in rows {
-            let mut map = serde_json::Map::new();
-            for col in &columns {
-                let val_res: Result<String, _> = row.try_get(col.as_str());
-                if let Ok(v) = val_res {
-                    map.insert(col.clone(), serde_json::Value::String(v));
-                } else {
-                    let int_res: Result<i64, _> = row.try_get(col.as_str());
-                    if let Ok(v) = int_res {
-                        map.insert(col.clone(), serde_json::Value::Number(v.into()));
-                    } else {
-                        map.insert(col.clone(), serde_json::Value::Null);
-                    }
-                }
-            }
-            result_data.push(serde_json::Value::Object(map));
-        }
+        let result_data = rows
+            .iter()
+            .map(|row| schema::row_to_json(row, &columns))
+            .collect();
 
         Ok((result_data, total_count, columns))
     }
@@ -325,11 +592,14 @@ impl DatabaseManager {
         column: String,
         value: String,
     ) -> Result<(), String> {
-        if !self.validate_identifier(&table_name, Some(&column)).await {
-            return Err("Invalid table or column name".to_string());
-        }
+        let columns = self.validate_identifier(&table_name, Some(&column)).await?;
+        schema::ensure_columns(&table_name, &columns, &["id"])?;
 
-        let query = format!("UPDATE {} SET {} = ? WHERE id = ?", table_name, column);
+        let query = format!(
+            "UPDATE {} SET {} = ? WHERE id = ?",
+            schema::quote_identifier(&table_name),
+            schema::quote_identifier(&column)
+        );
         sqlx::query(&query)
             .bind(value)
             .bind(id)
@@ -358,13 +628,89 @@ impl DatabaseManager {
         Ok(())
     }
 
-    pub async fn delete_resource(&self, id: &str) -> Result<(), String> {
-        sqlx::query("DELETE FROM resources WHERE id = ?")
+    /// Re-path a resource (and, for folders, every resource below it) in one
+    /// transaction.
+    pub async fn relocate_resource(
+        &self,
+        id: &str,
+        old_path: &str,
+        new_path: &str,
+        title: &str,
+        collection: &str,
+        is_folder: bool,
+    ) -> Result<(), String> {
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+
+        sqlx::query("UPDATE resources SET path = ?, title = ?, collection = ? WHERE id = ?")
+            .bind(new_path)
+            .bind(title)
+            .bind(collection)
             .bind(id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await
             .map_err(|e| e.to_string())?;
-        Ok(())
+
+        if is_folder {
+            for sep in ["/", "\\"] {
+                let old_prefix = format!("{}{}", old_path, sep);
+                sqlx::query(
+                    "UPDATE resources SET path = ? || substr(path, ?), collection = ?
+                     WHERE substr(path, 1, ?) = ?",
+                )
+                .bind(new_path)
+                .bind(old_path.chars().count() as i64 + 1)
+                .bind(collection)
+                .bind(old_prefix.chars().count() as i64)
+                .bind(&old_prefix)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+            }
+        }
+
+        tx.commit().await.map_err(|e| e.to_string())
+    }
+
+    /// Delete a resource (and, for folders, every resource below it) along
+    /// with their dependency rows. Returns the removed ids.
+    pub async fn delete_resource_tree(
+        &self,
+        id: &str,
+        path: &str,
+        is_folder: bool,
+    ) -> Result<Vec<String>, String> {
+        let mut ids = vec![id.to_string()];
+        if is_folder {
+            for sep in ["/", "\\"] {
+                let prefix = format!("{}{}", path, sep);
+                let children: Vec<String> =
+                    sqlx::query_scalar("SELECT id FROM resources WHERE substr(path, 1, ?) = ?")
+                        .bind(prefix.chars().count() as i64)
+                        .bind(&prefix)
+                        .fetch_all(&self.pool)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                ids.extend(children);
+            }
+        }
+
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+        for rid in &ids {
+            sqlx::query("DELETE FROM dependencies WHERE source_id = ? OR target_id = ?")
+                .bind(rid)
+                .bind(rid)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+            sqlx::query("DELETE FROM resources WHERE id = ?")
+                .bind(rid)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        Ok(ids)
     }
 
     // --- Dependency Management ---
@@ -385,6 +731,37 @@ impl DatabaseManager {
         Ok(())
     }
 
+    /// Replace the outgoing dependencies of `source_id` whose relation type is
+    /// one of `relation_types`; links of other types are left untouched.
+    pub async fn replace_dependencies(
+        &self,
+        source_id: &str,
+        relation_types: &[&str],
+        targets: &[(String, String)],
+    ) -> Result<(), String> {
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+
+        for relation in relation_types {
+            sqlx::query("DELETE FROM dependencies WHERE source_id = ? AND relation_type = ?")
+                .bind(source_id)
+                .bind(relation)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        for (target_id, relation) in targets {
+            sqlx::query("INSERT OR REPLACE INTO dependencies (source_id, target_id, relation_type) VALUES (?, ?, ?)")
+                .bind(source_id)
+                .bind(target_id)
+                .bind(relation)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+
+        tx.commit().await.map_err(|e| e.to_string())
+    }
+
     pub async fn get_dependencies(
         &self,
         source_id: &str,
@@ -409,6 +786,19 @@ impl DatabaseManager {
         q.fetch_all(&self.pool).await.map_err(|e| e.to_string())
     }
 
+    /// Resources with a dependency on `target_id`
+    pub async fn get_dependents(&self, target_id: &str) -> Result<Vec<Resource>, String> {
+        sqlx::query_as::<_, Resource>(
+            "SELECT DISTINCT r.* FROM resources r
+             JOIN dependencies d ON r.id = d.source_id
+             WHERE d.target_id = ?",
+        )
+        .bind(target_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| e.to_string())
+    }
+
     pub async fn get_resource_by_id(&self, id: &str) -> Result<Option<Resource>, String> {
         let r = sqlx::query_as::<_, Resource>("SELECT * FROM resources WHERE id = ?")
             .bind(id)
@@ -418,6 +808,15 @@ impl DatabaseManager {
         Ok(r)
     }
 
+    pub async fn get_resource_by_path(&self, path: &str) -> Result<Option<Resource>, String> {
+        let r = sqlx::query_as::<_, Resource>("SELECT * FROM resources WHERE path = ?")
+            .bind(path)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(r)
+    }
+
     pub async fn get_all_dependencies(&self) -> Result<Vec<(String, String, String)>, String> {
         let rows = sqlx::query("SELECT source_id, target_id, relation_type FROM dependencies")
             .fetch_all(&self.pool)
@@ -434,3 +833,86 @@ impl DatabaseManager {
         Ok(results)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_db_gets_later_migrations() {
+        let dir = std::env::temp_dir().join(format!("datatex-legacy-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let data_dir = dir.to_string_lossy().to_string();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let url = format!("sqlite://{}/project.db", data_dir);
+            Sqlite::create_database(&url).await.unwrap();
+            let pool = SqlitePoolOptions::new().connect(&url).await.unwrap();
+            // The schema of the first 15 migrations, without a version
+            for script in &DatabaseManager::MIGRATIONS[..15] {
+                let _ = sqlx::raw_sql(script).execute(&pool).await;
+            }
+            pool.close().await;
+
+            let db = DatabaseManager::new(&data_dir).await.unwrap();
+            let tables: Vec<String> = sqlx::query_scalar(
+                "SELECT name FROM sqlite_master WHERE name IN ('resources_fts', 'custom_field_definitions', 'bib_entries')",
+            )
+            .fetch_all(&db.pool)
+            .await
+            .unwrap();
+            assert_eq!(tables.len(), 3, "{:?}", tables);
+            db.pool.close().await;
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_failed_history_copy_keeps_content() {
+        let dir = std::env::temp_dir().join(format!("datatex-migrate-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let data_dir = dir.to_string_lossy().to_string();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            // Put the database back before 028, with a snapshot whose copy fails
+            let db = DatabaseManager::new(&data_dir).await.unwrap();
+            for stmt in [
+                "ALTER TABLE file_history ADD COLUMN content TEXT",
+                "INSERT INTO file_history (id, file_path, content, content_hash) VALUES ('h1', 'a.tex', 'old', 'abc')",
+                "CREATE TRIGGER fail_copy BEFORE INSERT ON history_blobs BEGIN SELECT RAISE(ABORT, 'copy failed'); END",
+                "PRAGMA user_version = 27",
+            ] {
+                sqlx::query(stmt).execute(&db.pool).await.unwrap();
+            }
+            db.pool.close().await;
+
+            assert!(DatabaseManager::new(&data_dir).await.is_err());
+
+            let db = SqlitePoolOptions::new()
+                .connect(&format!("sqlite://{}/project.db", data_dir))
+                .await
+                .unwrap();
+            let content: String =
+                sqlx::query_scalar("SELECT content FROM file_history WHERE id = 'h1'")
+                    .fetch_one(&db)
+                    .await
+                    .unwrap();
+            assert_eq!(content, "old");
+            sqlx::query("DROP TRIGGER fail_copy")
+                .execute(&db)
+                .await
+                .unwrap();
+            db.close().await;
+
+            let db = DatabaseManager::new(&data_dir).await.unwrap();
+            let blob: String =
+                sqlx::query_scalar("SELECT content FROM history_blobs WHERE hash = 'abc'")
+                    .fetch_one(&db.pool)
+                    .await
+                    .unwrap();
+            assert_eq!(blob, "old");
+            db.pool.close().await;
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,4 +1,6 @@
+pub mod audit;
 pub mod entities;
 pub mod manager;
+pub mod schema;
 
 pub use manager::DatabaseManager;
@@ -0,0 +1,148 @@
+//! Schema introspection helpers.
+//!
+//! Table and column names coming from the frontend can't be bound as SQL
+//! parameters, so every identifier is checked against `sqlite_master` /
+//! `pragma_table_info` before it is interpolated into a query.
+
+use sqlx::{Pool, Row, Sqlite};
+
+/// Lists all user tables in the database (internal `sqlite_*` tables excluded).
+pub async fn list_tables(pool: &Pool<Sqlite>) -> Result<Vec<String>, String> {
+    let rows = sqlx::query(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows.iter().map(|r| r.get("name")).collect())
+}
+
+/// Names of virtual tables (FTS5 indexes). Their shadow tables are named
+/// `<virtual table>_<suffix>`.
+pub async fn virtual_tables(pool: &Pool<Sqlite>) -> Result<Vec<String>, String> {
+    let rows = sqlx::query(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND sql LIKE 'CREATE VIRTUAL TABLE%'",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows.iter().map(|r| r.get("name")).collect())
+}
+
+/// Returns the column names of `table`, or an error if the table does not exist.
+pub async fn table_columns(pool: &Pool<Sqlite>, table: &str) -> Result<Vec<String>, String> {
+    let exists: Option<String> = sqlx::query_scalar(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name = ? AND name NOT LIKE 'sqlite_%'",
+    )
+    .bind(table)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if exists.is_none() {
+        return Err(format!("Unknown table: {}", table));
+    }
+
+    let rows = sqlx::query("SELECT name FROM pragma_table_info(?) ORDER BY cid")
+        .bind(table)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(rows.iter().map(|r| r.get("name")).collect())
+}
+
+/// Declared type and constraints of a column, from `pragma_table_info`.
+#[derive(Debug, Clone)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub decl_type: String,
+    pub not_null: bool,
+    pub has_default: bool,
+    pub primary_key: bool,
+}
+
+/// Returns the typed column list of `table`, or an error if the table does not exist.
+pub async fn column_info(pool: &Pool<Sqlite>, table: &str) -> Result<Vec<ColumnInfo>, String> {
+    // Rejects unknown tables
+    table_columns(pool, table).await?;
+
+    let rows = sqlx::query(
+        "SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?) ORDER BY cid",
+    )
+    .bind(table)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .iter()
+        .map(|r| ColumnInfo {
+            name: r.get("name"),
+            decl_type: r.get("type"),
+            not_null: r.get::<i64, _>("notnull") != 0,
+            has_default: r.get::<Option<String>, _>("dflt_value").is_some(),
+            primary_key: r.get::<i64, _>("pk") != 0,
+        })
+        .collect())
+}
+
+/// Checks that every column in `requested` exists in `columns`.
+pub fn ensure_columns(table: &str, columns: &[String], requested: &[&str]) -> Result<(), String> {
+    for col in requested {
+        if !columns.iter().any(|c| c == col) {
+            return Err(format!("Unknown column '{}' in table '{}'", col, table));
+        }
+    }
+    Ok(())
+}
+
+/// Quotes an identifier for safe interpolation (`"name"`, with embedded quotes doubled).
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Converts a row to a JSON object, trying text, integer and real for each column.
+pub fn row_to_json(row: &sqlx::sqlite::SqliteRow, columns: &[String]) -> serde_json::Value {
+    let mut map = serde_json::Map::new();
+    for col in columns {
+        let val_res: Result<String, _> = row.try_get(col.as_str());
+        if let Ok(v) = val_res {
+            map.insert(col.clone(), serde_json::Value::String(v));
+        } else {
+            let int_res: Result<i64, _> = row.try_get(col.as_str());
+            if let Ok(v) = int_res {
+                map.insert(col.clone(), serde_json::Value::Number(v.into()));
+            } else if let Some(n) = row
+                .try_get::<f64, _>(col.as_str())
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+            {
+                map.insert(col.clone(), serde_json::Value::Number(n));
+            } else {
+                map.insert(col.clone(), serde_json::Value::Null);
+            }
+        }
+    }
+    serde_json::Value::Object(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!(quote_identifier("resources"), "\"resources\"");
+        assert_eq!(quote_identifier("we\"ird"), "\"we\"\"ird\"");
+    }
+
+    #[test]
+    fn test_ensure_columns() {
+        let cols = vec!["id".to_string(), "title".to_string()];
+        assert!(ensure_columns("resources", &cols, &["id", "title"]).is_ok());
+        assert!(ensure_columns("resources", &cols, &["id; DROP TABLE x"]).is_err());
+    }
+}
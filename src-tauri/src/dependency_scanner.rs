@@ -0,0 +1,516 @@
+//! Dependency Scanner Module
+//!
+//! Extracts `\input`, `\include`, `\includegraphics`, `\usepackage`,
+//! `\documentclass` and bibliography references from LaTeX sources, resolves
+//! them to resources on disk, and stores them as rows in `dependencies`.
+//! `\cite` keys are extracted as well; they point into `bib_entries` rather
+//! than at files, so they are returned to callers instead of being stored.
+
+use regex::Regex;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::database::entities::Resource;
+use crate::database::DatabaseManager;
+
+/// Relation types owned by the scanner. Links of any other type (e.g. created
+/// manually through `link_resources_cmd`) are never touched by a rescan.
+pub const SCANNED_RELATIONS: &[&str] = &[
+    "input",
+    "include",
+    "graphics",
+    "package",
+    "class",
+    "bibliography",
+];
+
+/// Relations whose target is a file path, so it has to follow moved files
+const PATH_RELATIONS: &[&str] = &["input", "include", "graphics", "bibliography"];
+
+/// Extensions of files whose references are scanned
+const SOURCE_EXTENSIONS: &[&str] = &["tex", "sty", "cls", "dtx"];
+
+/// A reference found in a source file, before resolution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub relation: &'static str,
+    pub target: String,
+}
+
+/// A citation key and the 1-based line it is cited on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Citation {
+    pub key: String,
+    pub line: usize,
+}
+
+/// Whether a file's references should be scanned
+pub fn is_source(path: &str) -> bool {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    SOURCE_EXTENSIONS.contains(&ext.as_str())
+}
+
+/// Where the `%` comment of a line starts (the line length if it has none)
+pub fn comment_start(line: &str) -> usize {
+    let mut prev_backslash = false;
+    for (i, c) in line.char_indices() {
+        if c == '%' && !prev_backslash {
+            return i;
+        }
+        prev_backslash = c == '\\' && !prev_backslash;
+    }
+    line.len()
+}
+
+/// Remove `%` comments, keeping escaped `\%`
+pub fn strip_comments(content: &str) -> String {
+    content
+        .lines()
+        .map(|line| &line[..comment_start(line)])
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Command name in group 1, comma-separated targets in group 2
+fn reference_regex() -> &'static Regex {
+    static REF_RE: OnceLock<Regex> = OnceLock::new();
+    REF_RE.get_or_init(|| {
+        Regex::new(
+            r"\\(input|include|subfile|includegraphics|usepackage|RequirePackage|documentclass|LoadClass|bibliography|addbibresource)\*?\s*(?:\[[^\]]*\]\s*)*\{([^}]*)\}",
+        )
+        .unwrap()
+    })
+}
+
+fn relation_of(command: &str) -> &'static str {
+    match command {
+        "input" | "subfile" => "input",
+        "include" => "include",
+        "includegraphics" => "graphics",
+        "usepackage" | "RequirePackage" => "package",
+        "documentclass" | "LoadClass" => "class",
+        _ => "bibliography",
+    }
+}
+
+/// Find all file references in a LaTeX source
+pub fn extract_references(content: &str) -> Vec<Reference> {
+    let mut refs = Vec::new();
+    for caps in reference_regex().captures_iter(&strip_comments(content)) {
+        let relation = relation_of(&caps[1]);
+        for target in caps[2].split(',') {
+            let target = target.trim();
+            if target.is_empty() {
+                continue;
+            }
+            let reference = Reference {
+                relation,
+                target: target.to_string(),
+            };
+            if !refs.contains(&reference) {
+                refs.push(reference);
+            }
+        }
+    }
+    refs
+}
+
+/// Find all citation keys of `\cite`-like commands (`\citep`, `\parencite`,
+/// `\nocite`, ...). `\nocite{*}` is returned as the key `*`.
+pub fn extract_citations(content: &str) -> Vec<Citation> {
+    static CITE_RE: OnceLock<Regex> = OnceLock::new();
+    let re = CITE_RE.get_or_init(|| {
+        Regex::new(r"\\[a-zA-Z]*cite[a-zA-Z]*\*?\s*(?:\[[^\]]*\]\s*)*\{([^}]*)\}").unwrap()
+    });
+
+    // Comments are blanked line by line, so offsets map to the same lines
+    let stripped = strip_comments(content);
+    let mut citations = Vec::new();
+    for caps in re.captures_iter(&stripped) {
+        let group = caps.get(1).unwrap();
+        let line = stripped[..group.start()].matches('\n').count() + 1;
+        for key in group.as_str().split(',') {
+            let key = key.trim();
+            if !key.is_empty() {
+                citations.push(Citation {
+                    key: key.to_string(),
+                    line,
+                });
+            }
+        }
+    }
+    citations
+}
+
+/// File name candidates for a reference, in the order LaTeX would try them
+fn candidate_names(reference: &Reference) -> Vec<String> {
+    let t = &reference.target;
+    let exts: &[&str] = match reference.relation {
+        "input" | "include" => &["", ".tex"],
+        "graphics" => &["", ".pdf", ".png", ".jpg", ".jpeg", ".eps", ".svg"],
+        "package" => &[".sty"],
+        "class" => &[".cls"],
+        _ => &["", ".bib"],
+    };
+    exts.iter().map(|ext| format!("{}{}", t, ext)).collect()
+}
+
+/// Resolve `.` and `..` without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Path of `target` relative to the directory `base`, with `/` separators.
+/// `None` when they share no root (different drives on Windows).
+pub fn relative_path(base: &Path, target: &Path) -> Option<String> {
+    let base = normalize(base);
+    let target = normalize(target);
+    let base: Vec<Component> = base.components().collect();
+    let target: Vec<Component> = target.components().collect();
+    if base.first() != target.first() {
+        return None;
+    }
+    let common = base.iter().zip(&target).take_while(|(a, b)| a == b).count();
+    let mut parts: Vec<String> = vec!["..".to_string(); base.len() - common];
+    parts.extend(
+        target[common..]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy().to_string()),
+    );
+    Some(parts.join("/"))
+}
+
+/// New text for one reference target, or `None` to keep it. The target is
+/// resolved the way [`resolve`] does, against the old location of the source.
+fn rewrite_target(
+    reference: &Reference,
+    bases: &[(&Path, &Path)],
+    locate: &dyn Fn(&Path) -> Option<PathBuf>,
+) -> Option<String> {
+    for (old_base, new_base) in bases {
+        for name in candidate_names(reference) {
+            let Some(now) = locate(&normalize(&old_base.join(&name))) else {
+                continue;
+            };
+            if normalize(&new_base.join(&name)) == now {
+                return None;
+            }
+            let text = if Path::new(&reference.target).is_absolute() {
+                now.to_string_lossy().to_string()
+            } else {
+                relative_path(new_base, &now)?
+            };
+            // Keep leaving out an extension LaTeX adds by itself
+            let added = &name[reference.target.len()..];
+            return Some(text.strip_suffix(added).unwrap_or(&text).to_string());
+        }
+    }
+    None
+}
+
+/// Rewrite the file path references of a source that moved from the
+/// directory `old_dir` to `new_dir` (the same directory if only files it
+/// references moved). `locate` maps where a referenced file used to be to
+/// where it is now, or `None` if there is no such file. Returns the new
+/// content when any reference changed.
+pub fn rewrite_references(
+    content: &str,
+    old_dir: &Path,
+    new_dir: &Path,
+    root: Option<&Path>,
+    locate: &dyn Fn(&Path) -> Option<PathBuf>,
+) -> Option<String> {
+    let mut bases = vec![(old_dir, new_dir)];
+    if let Some(root) = root {
+        bases.push((root, root));
+    }
+
+    let mut out = String::with_capacity(content.len());
+    let mut changed = false;
+    for line in content.split_inclusive('\n') {
+        let code = &line[..comment_start(line)];
+        let mut last = 0;
+        for caps in reference_regex().captures_iter(code) {
+            let relation = relation_of(&caps[1]);
+            if !PATH_RELATIONS.contains(&relation) {
+                continue;
+            }
+            let group = caps.get(2).unwrap();
+            let targets: Vec<String> = group
+                .as_str()
+                .split(',')
+                .map(|piece| {
+                    let reference = Reference {
+                        relation,
+                        target: piece.trim().to_string(),
+                    };
+                    if reference.target.is_empty() {
+                        return piece.to_string();
+                    }
+                    match rewrite_target(&reference, &bases, locate) {
+                        Some(text) => piece.replacen(&reference.target, &text, 1),
+                        None => piece.to_string(),
+                    }
+                })
+                .collect();
+            let targets = targets.join(",");
+            if targets != group.as_str() {
+                out.push_str(&line[last..group.start()]);
+                out.push_str(&targets);
+                last = group.end();
+                changed = true;
+            }
+        }
+        out.push_str(&line[last..]);
+    }
+    changed.then_some(out)
+}
+
+/// The 1-based line of `content` holding the reference of type `relation`
+/// that points at `target`, for a source in the directory `source_dir`
+pub fn reference_line(
+    content: &str,
+    relation: &str,
+    source_dir: &Path,
+    target: &Path,
+) -> Option<usize> {
+    for (i, line) in content.lines().enumerate() {
+        let code = &line[..comment_start(line)];
+        for caps in reference_regex().captures_iter(code) {
+            if relation_of(&caps[1]) != relation {
+                continue;
+            }
+            for piece in caps[2].split(',') {
+                let reference = Reference {
+                    relation: relation_of(&caps[1]),
+                    target: piece.trim().to_string(),
+                };
+                if reference.target.is_empty() {
+                    continue;
+                }
+                // Next to the source, or anywhere below the collection root
+                let found = candidate_names(&reference).iter().any(|name| {
+                    let name = Path::new(name);
+                    normalize(&source_dir.join(name)) == target
+                        || (name.is_relative() && target.ends_with(normalize(name)))
+                });
+                if found {
+                    return Some(i + 1);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Where a reference may point, in the order it is looked up: next to the
+/// source file first and then at the collection root
+pub fn candidate_paths(
+    source_path: &Path,
+    root: Option<&Path>,
+    reference: &Reference,
+) -> Vec<PathBuf> {
+    let mut bases: Vec<&Path> = Vec::new();
+    if let Some(dir) = source_path.parent() {
+        bases.push(dir);
+    }
+    if let Some(root) = root {
+        bases.push(root);
+    }
+
+    bases
+        .into_iter()
+        .flat_map(|base| {
+            candidate_names(reference)
+                .into_iter()
+                .map(move |name| normalize(&base.join(name)))
+        })
+        .collect()
+}
+
+/// Resolve a reference to an indexed resource, looking next to the source
+/// file first and then at the collection root.
+async fn resolve(
+    db: &DatabaseManager,
+    source_path: &Path,
+    root: Option<&Path>,
+    reference: &Reference,
+) -> Result<Option<String>, String> {
+    for candidate in candidate_paths(source_path, root, reference) {
+        if !candidate.is_file() {
+            continue;
+        }
+        if let Some(target) = db
+            .get_resource_by_path(&candidate.to_string_lossy())
+            .await?
+        {
+            return Ok(Some(target.id));
+        }
+    }
+    Ok(None)
+}
+
+/// Rescan the references of `source` and replace its scanned dependency rows.
+/// Returns the number of resolved dependencies.
+pub async fn update_dependencies(
+    db: &DatabaseManager,
+    source: &Resource,
+    content: &str,
+    root: Option<&Path>,
+) -> Result<usize, String> {
+    let source_path = Path::new(&source.path);
+    let mut targets: Vec<(String, String)> = Vec::new();
+
+    for reference in extract_references(content) {
+        if let Some(target_id) = resolve(db, source_path, root, &reference).await? {
+            if target_id != source.id {
+                targets.push((target_id, reference.relation.to_string()));
+            }
+        }
+    }
+
+    db.replace_dependencies(&source.id, SCANNED_RELATIONS, &targets)
+        .await?;
+    Ok(targets.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_references() {
+        let src = "\\documentclass[12pt]{article}\n\
+                   \\usepackage{amsmath, mystyle}\n\
+                   % \\input{commented}\n\
+                   \\input{chapters/intro}\n\
+                   \\includegraphics[width=5cm]{figs/plot}\n\
+                   50\\% done \\bibliography{refs}";
+        let refs = extract_references(src);
+        let pairs: Vec<(&str, &str)> = refs
+            .iter()
+            .map(|r| (r.relation, r.target.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("class", "article"),
+                ("package", "amsmath"),
+                ("package", "mystyle"),
+                ("input", "chapters/intro"),
+                ("graphics", "figs/plot"),
+                ("bibliography", "refs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_citations() {
+        let src = "As shown \\cite{knuth84, lamport94}.\n\
+                   % \\cite{commented}\n\
+                   See \\parencite[p.~3]{tao}\\nocite{*}";
+        let cites: Vec<(String, usize)> = extract_citations(src)
+            .into_iter()
+            .map(|c| (c.key, c.line))
+            .collect();
+        assert_eq!(
+            cites,
+            vec![
+                ("knuth84".to_string(), 1),
+                ("lamport94".to_string(), 1),
+                ("tao".to_string(), 3),
+                ("*".to_string(), 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let rel = |a: &str, b: &str| relative_path(Path::new(a), Path::new(b));
+        assert_eq!(
+            rel("/c/ch1", "/c/figs/a.png").as_deref(),
+            Some("../figs/a.png")
+        );
+        assert_eq!(rel("/c", "/c/ch1/x.tex").as_deref(), Some("ch1/x.tex"));
+        assert_eq!(rel("/c/ch1/", "/c/ch1/x.tex").as_deref(), Some("x.tex"));
+    }
+
+    #[test]
+    fn test_rewrite_references() {
+        // ch1/intro.tex moved to ch2/intro.tex; figs/plot.pdf stayed put
+        let locate = |p: &Path| match p.to_str() {
+            Some("/c/ch1/intro.tex") => Some(PathBuf::from("/c/ch2/intro.tex")),
+            Some("/c/figs/plot.pdf") => Some(p.to_path_buf()),
+            _ => None,
+        };
+        let main = "\\input{ch1/intro}\n% \\input{ch1/intro}\n\\input{missing}\n";
+        assert_eq!(
+            rewrite_references(main, Path::new("/c"), Path::new("/c"), None, &locate).as_deref(),
+            Some("\\input{ch2/intro}\n% \\input{ch1/intro}\n\\input{missing}\n")
+        );
+
+        // The moved file itself: its own relative references follow it
+        let intro = "\\includegraphics[width=3cm]{../figs/plot.pdf}";
+        assert_eq!(
+            rewrite_references(
+                intro,
+                Path::new("/c/ch1"),
+                Path::new("/c/sub/ch2"),
+                Some(Path::new("/c")),
+                &locate
+            )
+            .as_deref(),
+            Some("\\includegraphics[width=3cm]{../../figs/plot.pdf}")
+        );
+
+        // Resolved from the collection root, which did not move
+        let rooted = "\\includegraphics{figs/plot}";
+        assert_eq!(
+            rewrite_references(
+                rooted,
+                Path::new("/c/ch1"),
+                Path::new("/c/ch2"),
+                Some(Path::new("/c")),
+                &locate
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_reference_line() {
+        let src = "\\documentclass{article}\n\
+                   % \\input{ch1/intro}\n\
+                   \\input{ch1/intro}\n\
+                   \\includegraphics{../figs/plot}";
+        let dir = Path::new("/c/main");
+        let line =
+            |relation: &str, target: &str| reference_line(src, relation, dir, Path::new(target));
+        assert_eq!(line("input", "/c/main/ch1/intro.tex"), Some(3));
+        assert_eq!(line("graphics", "/c/figs/plot.pdf"), Some(4));
+        assert_eq!(line("input", "/c/figs/plot.pdf"), None);
+        assert_eq!(line("input", "/c/main/ch2/intro.tex"), None);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize(Path::new("/a/b/../c/./d.tex")),
+            PathBuf::from("/a/c/d.tex")
+        );
+    }
+}
@@ -0,0 +1,601 @@
+//! Document Builder Module
+//!
+//! Assembles an exam or worksheet from selected exercise resources: each
+//! exercise body is wrapped with the template's per-item wrapper, the items
+//! are concatenated between the template's preamble and closing text, and
+//! the result is stored as a new resource that can be compiled right away.
+//!
+//! Solution environments (per collection, `solution` by default) can be kept,
+//! removed, or moved to an appendix; several variants (e.g. a student and a
+//! teacher copy) are generated from the same selection and order in one run.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::compiler;
+use crate::database::entities::Resource;
+use crate::database::DatabaseManager;
+use crate::preamble;
+use crate::resources;
+use crate::search;
+
+/// Relation recorded from an assembled document to each of its exercises
+pub const ASSEMBLY_RELATION: &str = "assembled_from";
+
+const DEFAULT_WRAPPER: &str = "% {{title}}\n{{content}}\n";
+const DEFAULT_SOLUTIONS_HEADER: &str = "\\clearpage\n\\section*{Solutions}\n";
+const DEFAULT_SOLUTION_WRAPPER: &str = "\\paragraph{Exercise {{number}}}\n{{content}}\n";
+
+/// Solution environment used when a collection doesn't configure any
+pub const DEFAULT_SOLUTION_ENVIRONMENT: &str = "solution";
+
+/// What happens to solution environments in a generated document
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SolutionMode {
+    /// Solutions stay where they are (teacher copy)
+    Include,
+    /// Solutions are removed (student copy)
+    #[default]
+    Exclude,
+    /// Solutions are collected in a section after the exercises
+    Appendix,
+}
+
+impl SolutionMode {
+    /// File name suffix when several variants are generated
+    fn suffix(self) -> &'static str {
+        match self {
+            SolutionMode::Include => "teacher",
+            SolutionMode::Exclude => "student",
+            SolutionMode::Appendix => "appendix",
+        }
+    }
+}
+
+/// How the document around the exercises looks
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildTemplate {
+    /// Preamble text (from `\documentclass` up to, not including, `\begin{document}`)
+    pub preamble: Option<String>,
+    /// Preamble resource id, `collection:<name>` or `builtin:<name>`, used
+    /// when `preamble` is empty
+    pub preamble_id: Option<String>,
+    /// Text right after `\begin{document}`
+    pub header: Option<String>,
+    /// Wrapper around each exercise; supports `{{content}}`, `{{number}}`,
+    /// `{{title}}`, `{{id}}` and `{{path}}`
+    pub item_wrapper: Option<String>,
+    /// Text right before `\end{document}`
+    pub footer: Option<String>,
+    /// Heading of the solutions appendix
+    pub solutions_header: Option<String>,
+    /// Wrapper around each solution in the appendix; supports the same
+    /// placeholders as `item_wrapper`
+    pub solution_wrapper: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildOptions {
+    /// "given" (default), "title", "path" or "random"
+    pub ordering: Option<String>,
+    /// Seed for "random"; the seed used is returned so a shuffle can be repeated
+    pub seed: Option<u64>,
+    /// One document is generated per mode; defaults to `[exclude]`
+    #[serde(default)]
+    pub solutions: Vec<SolutionMode>,
+    /// Compile the generated document after storing it
+    #[serde(default)]
+    pub compile: bool,
+    pub engine: Option<String>,
+    /// Replace an existing file at the output path
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildRequest {
+    pub resource_ids: Vec<String>,
+    pub template: BuildTemplate,
+    #[serde(default)]
+    pub options: BuildOptions,
+    /// Path of the generated .tex file
+    pub output_path: String,
+    /// Collection the generated document is stored in
+    pub collection: String,
+}
+
+/// One generated variant
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuiltDocument {
+    pub solutions: SolutionMode,
+    pub resource: Resource,
+    pub pdf_path: Option<String>,
+    pub compile_error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildResult {
+    pub documents: Vec<BuiltDocument>,
+    pub items: usize,
+    /// Ids that were not found or could not be read
+    pub skipped: Vec<String>,
+    pub seed: Option<u64>,
+}
+
+/// Preamble of a preamble resource, a collection (`collection:<name>`) or
+/// one of the built-in defaults
+pub async fn resolve_preamble(db: &DatabaseManager, preamble_id: &str) -> Result<String, String> {
+    if let Some(collection) = preamble_id.strip_prefix("collection:") {
+        Ok(preamble::get(&db.pool, collection).await?.render())
+    } else if preamble_id.starts_with("builtin:") {
+        // Simple built-in defaults
+        if preamble_id == "builtin:beamer" {
+            Ok("\\documentclass{beamer}\n\\usepackage[utf8]{inputenc}\n".to_string())
+        } else {
+            Ok(
+                "\\documentclass{article}\n\\usepackage[utf8]{inputenc}\n\\usepackage{amsmath}\n"
+                    .to_string(),
+            )
+        }
+    } else {
+        let preamble_res = db
+            .get_resource_by_id(preamble_id)
+            .await?
+            .ok_or("Preamble resource not found")?;
+        fs::read_to_string(&preamble_res.path)
+            .map_err(|e| format!("Failed to read preamble file: {}", e))
+    }
+}
+
+/// The body of a full document, or the whole text of a fragment
+pub fn extract_body(content: &str) -> &str {
+    match content.find("\\begin{document}") {
+        Some(start) => {
+            let body = &content[start + "\\begin{document}".len()..];
+            let end = body.find("\\end{document}").unwrap_or(body.len());
+            body[..end].trim_matches(['\r', '\n'])
+        }
+        None => content.trim_matches(['\r', '\n']),
+    }
+}
+
+/// Remove every `\begin{env}...\end{env}` block of the given environments.
+/// Returns the remaining text and the inner text of each removed block.
+pub fn split_environments(content: &str, envs: &[String]) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(content.len());
+    let mut removed = Vec::new();
+    let mut rest = content;
+    loop {
+        // Earliest opening of any of the environments
+        let next = envs
+            .iter()
+            .filter_map(|env| {
+                let begin = format!("\\begin{{{}}}", env);
+                rest.find(&begin)
+                    .map(|pos| (pos, begin, format!("\\end{{{}}}", env)))
+            })
+            .min_by_key(|(pos, ..)| *pos);
+        let Some((start, begin, end)) = next else {
+            break;
+        };
+
+        out.push_str(&rest[..start]);
+        let inner = &rest[start + begin.len()..];
+        match inner.find(&end) {
+            Some(stop) => {
+                removed.push(inner[..stop].trim_matches(['\r', '\n']).to_string());
+                rest = &inner[stop + end.len()..];
+            }
+            // Unterminated: drop the remainder rather than emit half an environment
+            None => {
+                removed.push(inner.trim_matches(['\r', '\n']).to_string());
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    (out, removed)
+}
+
+/// Solution environments configured for a collection
+pub async fn solution_environments(
+    pool: &Pool<Sqlite>,
+    collection: &str,
+) -> Result<Vec<String>, String> {
+    let stored: Option<Option<String>> =
+        sqlx::query_scalar("SELECT solution_environments FROM collections WHERE name = ?")
+            .bind(collection)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    let envs: Vec<String> = stored
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    if envs.is_empty() {
+        Ok(vec![DEFAULT_SOLUTION_ENVIRONMENT.to_string()])
+    } else {
+        Ok(envs)
+    }
+}
+
+pub async fn set_solution_environments(
+    pool: &Pool<Sqlite>,
+    collection: &str,
+    environments: &[String],
+) -> Result<(), String> {
+    let envs: Vec<&str> = environments
+        .iter()
+        .map(|e| e.trim())
+        .filter(|e| !e.is_empty())
+        .collect();
+    if let Some(bad) = envs
+        .iter()
+        .find(|e| !e.chars().all(|c| c.is_ascii_alphanumeric() || c == '*'))
+    {
+        return Err(format!("Invalid environment name: {}", bad));
+    }
+
+    let json = serde_json::to_string(&envs).map_err(|e| e.to_string())?;
+    let result = sqlx::query("UPDATE collections SET solution_environments = ? WHERE name = ?")
+        .bind(json)
+        .bind(collection)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    if result.rows_affected() == 0 {
+        return Err(format!("Collection not found: {}", collection));
+    }
+    Ok(())
+}
+
+/// Replace `{{name}}` placeholders; unknown placeholders are left as they are
+pub fn fill_placeholders(template: &str, values: &[(&str, &str)]) -> String {
+    let mut out = template.to_string();
+    for (name, value) in values {
+        out = out.replace(&format!("{{{{{}}}}}", name), value);
+    }
+    out
+}
+
+/// Deterministic Fisher-Yates shuffle (xorshift64), so a seed reproduces an order
+fn shuffle<T>(items: &mut [T], seed: u64) {
+    let mut state = seed.max(1);
+    for i in (1..items.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        items.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+}
+
+/// Order the exercises; returns the seed used for "random"
+fn order(resources: &mut [Resource], options: &BuildOptions) -> Result<Option<u64>, String> {
+    match options.ordering.as_deref().unwrap_or("given") {
+        "given" => Ok(None),
+        "title" => {
+            resources.sort_by_key(|r| r.title.clone().unwrap_or_default().to_lowercase());
+            Ok(None)
+        }
+        "path" => {
+            resources.sort_by(|a, b| a.path.cmp(&b.path));
+            Ok(None)
+        }
+        "random" => {
+            let seed = options.seed.unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or(1)
+            });
+            shuffle(resources, seed);
+            Ok(Some(seed))
+        }
+        other => Err(format!("Unknown ordering: {}", other)),
+    }
+}
+
+/// An exercise ready to be placed in a document
+struct Item<'a> {
+    resource: &'a Resource,
+    /// Body with the solution environments removed
+    exercise: String,
+    /// Body as written
+    full: String,
+    solutions: Vec<String>,
+}
+
+/// Output path of one variant: the requested path when only one variant is
+/// generated, `<stem>-<suffix>.tex` otherwise
+fn variant_path(output_path: &str, mode: SolutionMode, variants: usize) -> String {
+    if variants <= 1 {
+        return output_path.to_string();
+    }
+    let path = Path::new(output_path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_else(|| "tex".to_string());
+    path.with_file_name(format!("{}-{}.{}", stem, mode.suffix(), ext))
+        .to_string_lossy()
+        .to_string()
+}
+
+fn render_document(
+    preamble: &str,
+    template: &BuildTemplate,
+    items: &[Item],
+    mode: SolutionMode,
+) -> String {
+    let placeholders = |item: &'_ Item, number: &'_ str, content: &'_ str| -> String {
+        let wrapper = template.item_wrapper.as_deref().unwrap_or(DEFAULT_WRAPPER);
+        fill_placeholders(
+            wrapper,
+            &[
+                ("content", content),
+                ("number", number),
+                ("title", item.resource.title.as_deref().unwrap_or("")),
+                ("id", &item.resource.id),
+                ("path", &item.resource.path),
+            ],
+        )
+    };
+    let line = |text: String| {
+        if text.ends_with('\n') {
+            text
+        } else {
+            format!("{}\n", text)
+        }
+    };
+
+    let mut body = String::new();
+    for (i, item) in items.iter().enumerate() {
+        let content = match mode {
+            SolutionMode::Include => item.full.trim_end(),
+            _ => item.exercise.trim_end(),
+        };
+        body.push_str(&line(placeholders(item, &(i + 1).to_string(), content)));
+    }
+
+    if mode == SolutionMode::Appendix && items.iter().any(|i| !i.solutions.is_empty()) {
+        body.push_str(&line(
+            template
+                .solutions_header
+                .clone()
+                .unwrap_or_else(|| DEFAULT_SOLUTIONS_HEADER.to_string()),
+        ));
+        let wrapper = template
+            .solution_wrapper
+            .as_deref()
+            .unwrap_or(DEFAULT_SOLUTION_WRAPPER);
+        for (i, item) in items.iter().enumerate() {
+            if item.solutions.is_empty() {
+                continue;
+            }
+            let number = (i + 1).to_string();
+            body.push_str(&line(fill_placeholders(
+                wrapper,
+                &[
+                    ("content", &item.solutions.join("\n\n")),
+                    ("number", &number),
+                    ("title", item.resource.title.as_deref().unwrap_or("")),
+                    ("id", &item.resource.id),
+                    ("path", &item.resource.path),
+                ],
+            )));
+        }
+    }
+
+    format!(
+        "{}\n\\begin{{document}}\n{}{}{}\\end{{document}}\n",
+        preamble.trim_end(),
+        template
+            .header
+            .as_deref()
+            .map(|h| format!("{}\n", h.trim_end()))
+            .unwrap_or_default(),
+        body,
+        template
+            .footer
+            .as_deref()
+            .map(|f| format!("{}\n", f.trim_end()))
+            .unwrap_or_default(),
+    )
+}
+
+/// Store `document` at `path` as a new resource linked to its exercises
+async fn store(
+    db: &DatabaseManager,
+    request: &BuildRequest,
+    path: &str,
+    document: &str,
+    metadata: serde_json::Value,
+    included: &[String],
+) -> Result<Resource, String> {
+    if request.options.overwrite {
+        match db.get_resource_by_path(path).await? {
+            Some(existing) => {
+                resources::delete(db, &existing.id, true).await?;
+            }
+            None if Path::new(path).is_file() => {
+                fs::remove_file(path).map_err(|e| e.to_string())?;
+            }
+            None => {}
+        }
+    }
+    let resource =
+        resources::create(db, path, &request.collection, document, Some(metadata)).await?;
+    search::fts::update_content(&db.pool, &resource.id, document).await?;
+    for id in included {
+        db.add_dependency(&resource.id, id, ASSEMBLY_RELATION)
+            .await?;
+    }
+    Ok(resource)
+}
+
+/// Generate the combined document(s) and store each as a new resource.
+/// Compilation is left to `compile_output`, so callers can release the
+/// database before running LaTeX.
+pub async fn build(db: &DatabaseManager, request: &BuildRequest) -> Result<BuildResult, String> {
+    if request.resource_ids.is_empty() {
+        return Err("No exercises selected".to_string());
+    }
+
+    let template = &request.template;
+    let preamble = match (&template.preamble, &template.preamble_id) {
+        (Some(text), _) if !text.trim().is_empty() => text.clone(),
+        (_, Some(id)) => resolve_preamble(db, id).await?,
+        _ => resolve_preamble(db, "builtin:article").await?,
+    };
+
+    let mut resources_list = Vec::new();
+    let mut skipped = Vec::new();
+    for id in &request.resource_ids {
+        match db.get_resource_by_id(id).await? {
+            Some(resource) => resources_list.push(resource),
+            None => skipped.push(id.clone()),
+        }
+    }
+    let seed = order(&mut resources_list, &request.options)?;
+
+    let mut envs_by_collection: HashMap<String, Vec<String>> = HashMap::new();
+    let mut items = Vec::new();
+    for resource in &resources_list {
+        let Ok(content) = fs::read_to_string(&resource.path) else {
+            skipped.push(resource.id.clone());
+            continue;
+        };
+        if !envs_by_collection.contains_key(&resource.collection) {
+            let envs = solution_environments(&db.pool, &resource.collection).await?;
+            envs_by_collection.insert(resource.collection.clone(), envs);
+        }
+        let full = extract_body(&content).to_string();
+        let (exercise, solutions) =
+            split_environments(&full, &envs_by_collection[&resource.collection]);
+        items.push(Item {
+            resource,
+            exercise,
+            full,
+            solutions,
+        });
+    }
+    if items.is_empty() {
+        return Err("None of the selected exercises could be read".to_string());
+    }
+    let included: Vec<String> = items.iter().map(|i| i.resource.id.clone()).collect();
+
+    let mut modes: Vec<SolutionMode> = Vec::new();
+    for mode in &request.options.solutions {
+        if !modes.contains(mode) {
+            modes.push(*mode);
+        }
+    }
+    if modes.is_empty() {
+        modes.push(SolutionMode::Exclude);
+    }
+
+    let mut documents = Vec::new();
+    for mode in &modes {
+        let document = render_document(&preamble, template, &items, *mode);
+        let metadata = serde_json::json!({
+            "builtFrom": included,
+            "solutions": mode,
+            "seed": seed,
+        });
+        let path = variant_path(&request.output_path, *mode, modes.len());
+        let resource = store(db, request, &path, &document, metadata, &included).await?;
+        documents.push(BuiltDocument {
+            solutions: *mode,
+            resource,
+            pdf_path: None,
+            compile_error: None,
+        });
+    }
+
+    Ok(BuildResult {
+        documents,
+        items: items.len(),
+        skipped,
+        seed,
+    })
+}
+
+/// Compile a generated document next to itself, recording the outcome
+pub fn compile_output(document: &mut BuiltDocument, engine: &str) -> Result<String, String> {
+    let path = Path::new(&document.resource.path);
+    let output_dir = path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .to_string_lossy()
+        .to_string();
+    let result = compiler::compile(&document.resource.path, engine, vec![], &output_dir);
+    match &result {
+        Ok(_) => {
+            document.pdf_path = Some(path.with_extension("pdf").to_string_lossy().to_string());
+        }
+        Err(e) => document.compile_error = Some(e.clone()),
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_body_and_split() {
+        let full = "\\documentclass{article}\n\\begin{document}\nSolve $x^2=1$.\n\\begin{solution}\n$x=\\pm1$\n\\end{solution}\n\\end{document}\n";
+        let body = extract_body(full);
+        assert_eq!(
+            body,
+            "Solve $x^2=1$.\n\\begin{solution}\n$x=\\pm1$\n\\end{solution}"
+        );
+        let (exercise, solutions) = split_environments(body, &["solution".to_string()]);
+        assert_eq!(exercise, "Solve $x^2=1$.\n");
+        assert_eq!(solutions, vec!["$x=\\pm1$".to_string()]);
+        assert_eq!(extract_body("\nFragment\n"), "Fragment");
+    }
+
+    #[test]
+    fn test_fill_placeholders() {
+        let out = fill_placeholders(
+            "\\section*{Exercise {{number}}: {{title}}}\n{{content}} {{unknown}}",
+            &[("number", "2"), ("title", "Limits"), ("content", "Text")],
+        );
+        assert_eq!(out, "\\section*{Exercise 2: Limits}\nText {{unknown}}");
+    }
+
+    #[test]
+    fn test_variant_path() {
+        assert_eq!(
+            variant_path("/x/exam.tex", SolutionMode::Include, 1),
+            "/x/exam.tex"
+        );
+        assert_eq!(
+            variant_path("/x/exam.tex", SolutionMode::Include, 2),
+            "/x/exam-teacher.tex"
+        );
+    }
+
+    #[test]
+    fn test_shuffle_is_reproducible() {
+        let mut a: Vec<u32> = (0..10).collect();
+        let mut b = a.clone();
+        shuffle(&mut a, 42);
+        shuffle(&mut b, 42);
+        assert_eq!(a, b);
+        assert_ne!(a, (0..10).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,207 @@
+//! Autosave Drafts
+//!
+//! The editor sends unsaved content here every few seconds. Each document
+//! gets a folder under `<data dir>/drafts`, named after a hash of its URI,
+//! holding the URI and its latest few drafts (`<millis>.draft`). Drafts are
+//! dropped once the document is saved, so whatever is left after a crash is
+//! work that never reached the disk and can be offered back on startup.
+//! Nothing here touches the database or Git, so recovery works even when
+//! those are broken.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Drafts kept per document; older ones are deleted as new ones arrive
+const MAX_DRAFTS_PER_FILE: usize = 5;
+
+/// File in each document folder holding the document's URI
+const URI_FILE: &str = "uri";
+
+const DRAFT_EXTENSION: &str = "draft";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Draft {
+    /// `<document folder>/<millis>`, as accepted by `recover_draft`
+    pub id: String,
+    pub uri: String,
+    /// Milliseconds since the epoch
+    pub saved_at: u128,
+    pub size: u64,
+    /// Whether the document still exists on disk
+    pub file_exists: bool,
+}
+
+pub fn drafts_dir() -> Result<PathBuf, String> {
+    let proj_dirs = directories::ProjectDirs::from("", "", "datatex")
+        .ok_or("Could not determine project directories")?;
+    Ok(proj_dirs.data_dir().join("drafts"))
+}
+
+/// Folder name for a document: the first bytes of the SHA-256 of its URI
+fn document_key(uri: &str) -> String {
+    let digest = Sha256::digest(uri.as_bytes());
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The file a URI points to: `file://` URIs and plain paths
+fn uri_path(uri: &str) -> &Path {
+    Path::new(uri.strip_prefix("file://").unwrap_or(uri))
+}
+
+/// Drafts of one document folder, oldest first, by timestamp
+fn draft_files(dir: &Path) -> Vec<(u128, PathBuf)> {
+    let mut drafts: Vec<(u128, PathBuf)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != DRAFT_EXTENSION {
+                return None;
+            }
+            let millis = path.file_stem()?.to_str()?.parse().ok()?;
+            Some((millis, path))
+        })
+        .collect();
+    drafts.sort();
+    drafts
+}
+
+/// Store `content` as the newest draft of `uri`, unless it is the same as
+/// the previous one. Returns the draft id.
+pub fn autosave(root: &Path, uri: &str, content: &str) -> Result<String, String> {
+    let key = document_key(uri);
+    let dir = root.join(&key);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    fs::write(dir.join(URI_FILE), uri).map_err(|e| e.to_string())?;
+
+    let existing = draft_files(&dir);
+    if let Some((millis, latest)) = existing.last() {
+        if fs::read_to_string(latest).is_ok_and(|previous| previous == content) {
+            return Ok(format!("{}/{}", key, millis));
+        }
+    }
+
+    // Keep names unique and increasing even within the same millisecond
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_millis();
+    let millis = existing.last().map_or(now, |(last, _)| now.max(last + 1));
+    let path = dir.join(format!("{}.{}", millis, DRAFT_EXTENSION));
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, content).map_err(|e| e.to_string())?;
+    fs::rename(&temp, &path).map_err(|e| e.to_string())?;
+
+    let mut drafts = draft_files(&dir);
+    while drafts.len() > MAX_DRAFTS_PER_FILE {
+        let (_, oldest) = drafts.remove(0);
+        let _ = fs::remove_file(oldest);
+    }
+    Ok(format!("{}/{}", key, millis))
+}
+
+/// The newest draft of every document whose content differs from what is
+/// on disk, newest first. Folders whose drafts match the file (saved after
+/// all) are cleaned up on the way.
+pub fn list_recoverable(root: &Path) -> Result<Vec<Draft>, String> {
+    let mut recoverable = Vec::new();
+    for entry in fs::read_dir(root).into_iter().flatten().flatten() {
+        let dir = entry.path();
+        let Ok(uri) = fs::read_to_string(dir.join(URI_FILE)) else {
+            continue;
+        };
+        let Some((millis, latest)) = draft_files(&dir).pop() else {
+            let _ = fs::remove_dir_all(&dir);
+            continue;
+        };
+        let Ok(content) = fs::read_to_string(&latest) else {
+            continue;
+        };
+        let on_disk = fs::read_to_string(uri_path(&uri)).ok();
+        if on_disk.as_deref() == Some(content.as_str()) {
+            let _ = fs::remove_dir_all(&dir);
+            continue;
+        }
+        recoverable.push(Draft {
+            id: format!("{}/{}", entry.file_name().to_string_lossy(), millis),
+            size: content.len() as u64,
+            file_exists: on_disk.is_some(),
+            uri,
+            saved_at: millis,
+        });
+    }
+    recoverable.sort_by_key(|d| std::cmp::Reverse(d.saved_at));
+    Ok(recoverable)
+}
+
+/// Content of the draft `id` (from `autosave` or `list_recoverable`)
+pub fn recover(root: &Path, id: &str) -> Result<String, String> {
+    let (key, millis) = id.split_once('/').ok_or("Invalid draft id")?;
+    let valid = !key.is_empty()
+        && key.chars().all(|c| c.is_ascii_hexdigit())
+        && millis.chars().all(|c| c.is_ascii_digit());
+    if !valid {
+        return Err("Invalid draft id".to_string());
+    }
+    let path = root
+        .join(key)
+        .join(format!("{}.{}", millis, DRAFT_EXTENSION));
+    fs::read_to_string(&path).map_err(|_| "Draft not found".to_string())
+}
+
+/// Forget the drafts of `uri`, after it was saved or its recovery declined
+pub fn discard(root: &Path, uri: &str) -> Result<(), String> {
+    let dir = root.join(document_key(uri));
+    match fs::remove_dir_all(&dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drafts() {
+        let root = std::env::temp_dir().join(format!("datatex-drafts-{}", uuid::Uuid::new_v4()));
+        let doc = root.join("doc.tex");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(&doc, "saved").unwrap();
+        let uri = doc.to_string_lossy().to_string();
+        let drafts = root.join("drafts");
+
+        // Same content twice is one draft
+        let first = autosave(&drafts, &uri, "draft 0").unwrap();
+        assert_eq!(autosave(&drafts, &uri, "draft 0").unwrap(), first);
+
+        for i in 1..8 {
+            autosave(&drafts, &uri, &format!("draft {}", i)).unwrap();
+        }
+        let dir = drafts.join(document_key(&uri));
+        assert_eq!(draft_files(&dir).len(), MAX_DRAFTS_PER_FILE);
+
+        let listed = list_recoverable(&drafts).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].uri, uri);
+        assert!(listed[0].file_exists);
+        assert_eq!(recover(&drafts, &listed[0].id).unwrap(), "draft 7");
+        assert!(recover(&drafts, "../../etc/1").is_err());
+
+        // Once the file holds the draft, there is nothing to recover
+        fs::write(&doc, "draft 7").unwrap();
+        assert!(list_recoverable(&drafts).unwrap().is_empty());
+        assert!(!dir.exists());
+
+        autosave(&drafts, &uri, "unsaved").unwrap();
+        discard(&drafts, &uri).unwrap();
+        assert!(list_recoverable(&drafts).unwrap().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,326 @@
+//! Data Export Module
+//!
+//! Dumps database tables, or whole collections (resources with metadata, tags
+//! and dependencies), to JSON or CSV for processing in external tools. JSON
+//! exports go to a single file; CSV exports write one file per table into the
+//! target directory.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::path::{Path, PathBuf};
+
+use crate::database::schema;
+
+/// Columns available when exporting collections (resources table + tags)
+const RESOURCE_COLUMNS: &[&str] = &[
+    "id",
+    "path",
+    "type",
+    "collection",
+    "title",
+    "content_hash",
+    "metadata",
+    "tags",
+    "created_at",
+    "updated_at",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+/// A table to export, optionally restricted to some columns
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableSelection {
+    pub table: String,
+    pub columns: Option<Vec<String>>,
+}
+
+/// Result of an export
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub files: Vec<String>,
+    pub rows: usize,
+}
+
+/// Rows of one exported table
+struct TableDump {
+    name: String,
+    columns: Vec<String>,
+    rows: Vec<serde_json::Value>,
+}
+
+/// Render a JSON value as a CSV cell
+fn csv_cell(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn write_csv(path: &Path, dump: &TableDump) -> Result<(), String> {
+    let mut writer = csv::Writer::from_path(path).map_err(|e| e.to_string())?;
+    writer
+        .write_record(&dump.columns)
+        .map_err(|e| e.to_string())?;
+    for row in &dump.rows {
+        let record: Vec<String> = dump
+            .columns
+            .iter()
+            .map(|c| csv_cell(row.get(c).unwrap_or(&serde_json::Value::Null)))
+            .collect();
+        writer.write_record(&record).map_err(|e| e.to_string())?;
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
+
+/// Write dumps in the requested format and report what was written
+fn write_dumps(
+    dumps: Vec<TableDump>,
+    format: ExportFormat,
+    output_path: &str,
+) -> Result<ExportSummary, String> {
+    let mut summary = ExportSummary {
+        rows: dumps.iter().map(|d| d.rows.len()).sum(),
+        ..Default::default()
+    };
+
+    match format {
+        ExportFormat::Json => {
+            let path = PathBuf::from(output_path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let mut root = serde_json::Map::new();
+            for dump in dumps {
+                root.insert(dump.name, serde_json::Value::Array(dump.rows));
+            }
+            let json = serde_json::to_string_pretty(&root).map_err(|e| e.to_string())?;
+            std::fs::write(&path, json).map_err(|e| e.to_string())?;
+            summary.files.push(path.to_string_lossy().to_string());
+        }
+        ExportFormat::Csv => {
+            let dir = PathBuf::from(output_path);
+            std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            for dump in dumps {
+                let path = dir.join(format!("{}.csv", dump.name));
+                write_csv(&path, &dump)?;
+                summary.files.push(path.to_string_lossy().to_string());
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Export whole tables (optionally a subset of their columns)
+pub async fn export_tables(
+    pool: &Pool<Sqlite>,
+    tables: &[TableSelection],
+    format: ExportFormat,
+    output_path: &str,
+) -> Result<ExportSummary, String> {
+    let mut dumps = Vec::new();
+    for selection in tables {
+        let all_columns = schema::table_columns(pool, &selection.table).await?;
+        let columns = match &selection.columns {
+            Some(cols) if !cols.is_empty() => {
+                let requested: Vec<&str> = cols.iter().map(|c| c.as_str()).collect();
+                schema::ensure_columns(&selection.table, &all_columns, &requested)?;
+                cols.clone()
+            }
+            _ => all_columns,
+        };
+
+        let column_list: Vec<String> = columns
+            .iter()
+            .map(|c| schema::quote_identifier(c))
+            .collect();
+        let sql = format!(
+            "SELECT {} FROM {}",
+            column_list.join(", "),
+            schema::quote_identifier(&selection.table)
+        );
+        let rows = sqlx::query(&sql)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        dumps.push(TableDump {
+            name: selection.table.clone(),
+            rows: rows
+                .iter()
+                .map(|r| schema::row_to_json(r, &columns))
+                .collect(),
+            columns,
+        });
+    }
+
+    write_dumps(dumps, format, output_path)
+}
+
+/// Export the resources of the given collections together with their
+/// metadata, tags and dependencies. `columns` limits the resource columns.
+pub async fn export_collections(
+    pool: &Pool<Sqlite>,
+    collections: &[String],
+    columns: Option<&[String]>,
+    format: ExportFormat,
+    output_path: &str,
+) -> Result<ExportSummary, String> {
+    if collections.is_empty() {
+        return Err("No collections selected".to_string());
+    }
+
+    let columns: Vec<String> = match columns {
+        Some(cols) if !cols.is_empty() => {
+            if let Some(bad) = cols
+                .iter()
+                .find(|c| !RESOURCE_COLUMNS.contains(&c.as_str()))
+            {
+                return Err(format!("Unknown column '{}' for collection export", bad));
+            }
+            cols.to_vec()
+        }
+        _ => RESOURCE_COLUMNS.iter().map(|c| c.to_string()).collect(),
+    };
+
+    let placeholders: Vec<&str> = collections.iter().map(|_| "?").collect();
+    let in_clause = placeholders.join(", ");
+
+    // Resources (with their tags folded into one column)
+    let sql = format!(
+        "SELECT r.id, r.path, r.type, r.collection, r.title, r.content_hash, r.metadata,
+                r.created_at, r.updated_at,
+                (SELECT group_concat(t.tag, ', ') FROM resource_tags t WHERE t.resource_id = r.id) AS tags
+         FROM resources r
+         WHERE r.collection IN ({})
+         ORDER BY r.collection, r.path",
+        in_clause
+    );
+    let mut q = sqlx::query(&sql);
+    for c in collections {
+        q = q.bind(c);
+    }
+    let rows = q.fetch_all(pool).await.map_err(|e| e.to_string())?;
+
+    let all_columns: Vec<String> = RESOURCE_COLUMNS.iter().map(|c| c.to_string()).collect();
+    let resource_rows: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| {
+            let mut value = schema::row_to_json(row, &all_columns);
+            let obj = value
+                .as_object_mut()
+                .expect("row_to_json returns an object");
+            // In JSON, metadata is nested rather than a string
+            if format == ExportFormat::Json {
+                if let Some(parsed) = obj
+                    .get("metadata")
+                    .and_then(|m| m.as_str())
+                    .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+                {
+                    obj.insert("metadata".to_string(), parsed);
+                }
+            }
+            obj.retain(|k, _| columns.contains(k));
+            value
+        })
+        .collect();
+
+    // Dependencies between exported resources (and to outside targets)
+    let dep_sql = format!(
+        "SELECT d.source_id, d.target_id, d.relation_type, t.path AS target_path
+         FROM dependencies d
+         JOIN resources s ON s.id = d.source_id
+         LEFT JOIN resources t ON t.id = d.target_id
+         WHERE s.collection IN ({})
+         ORDER BY d.source_id",
+        in_clause
+    );
+    let mut q = sqlx::query(&dep_sql);
+    for c in collections {
+        q = q.bind(c);
+    }
+    let dep_columns: Vec<String> = ["source_id", "target_id", "relation_type", "target_path"]
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+    let dep_rows: Vec<serde_json::Value> = q
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|r| schema::row_to_json(r, &dep_columns))
+        .collect();
+
+    // Collection rows themselves
+    let coll_sql = format!(
+        "SELECT name, description, icon, type, path, created_at FROM collections WHERE name IN ({})",
+        in_clause
+    );
+    let mut q = sqlx::query(&coll_sql);
+    for c in collections {
+        q = q.bind(c);
+    }
+    let coll_columns: Vec<String> = ["name", "description", "icon", "type", "path", "created_at"]
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+    let coll_rows: Vec<serde_json::Value> = q
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|r| schema::row_to_json(r, &coll_columns))
+        .collect();
+
+    let dumps = vec![
+        TableDump {
+            name: "collections".to_string(),
+            columns: coll_columns,
+            rows: coll_rows,
+        },
+        TableDump {
+            name: "resources".to_string(),
+            columns,
+            rows: resource_rows,
+        },
+        TableDump {
+            name: "dependencies".to_string(),
+            columns: dep_columns,
+            rows: dep_rows,
+        },
+    ];
+
+    write_dumps(dumps, format, output_path)
+}
+
+/// Column names offered for collection exports
+pub fn resource_columns() -> Vec<String> {
+    RESOURCE_COLUMNS.iter().map(|c| c.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_cell() {
+        assert_eq!(csv_cell(&serde_json::Value::Null), "");
+        assert_eq!(csv_cell(&serde_json::json!("a,b")), "a,b");
+        assert_eq!(csv_cell(&serde_json::json!(3)), "3");
+        assert_eq!(csv_cell(&serde_json::json!({"k": 1})), "{\"k\":1}");
+    }
+
+    #[test]
+    fn test_format_deserializes_lowercase() {
+        let f: ExportFormat = serde_json::from_str("\"csv\"").unwrap();
+        assert_eq!(f, ExportFormat::Csv);
+    }
+}
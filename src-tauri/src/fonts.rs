@@ -0,0 +1,78 @@
+//! System Fonts
+//!
+//! Font families installed on the machine, for the editor font picker.
+//! Fonts are read with `fontdb`, which knows where Windows, macOS and
+//! Linux keep them (including the fontconfig setup), so no external tool
+//! is needed.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FontFamily {
+    pub name: String,
+    /// Every face of the family is fixed-width
+    pub monospace: bool,
+}
+
+/// One entry per family, sorted by name, from `(family, monospaced)` faces
+fn group_faces<'a>(faces: impl IntoIterator<Item = (&'a str, bool)>) -> Vec<FontFamily> {
+    let mut families: BTreeMap<&str, bool> = BTreeMap::new();
+    for (name, monospaced) in faces {
+        let name = name.trim();
+        if name.is_empty() || name.starts_with('.') {
+            // macOS keeps private system fonts under dotted names
+            continue;
+        }
+        families
+            .entry(name)
+            .and_modify(|mono| *mono &= monospaced)
+            .or_insert(monospaced);
+    }
+    families
+        .into_iter()
+        .map(|(name, monospace)| FontFamily {
+            name: name.to_string(),
+            monospace,
+        })
+        .collect()
+}
+
+/// Families of the installed fonts. Loading reads every font file, so call
+/// it off the main thread.
+pub fn system_fonts() -> Vec<FontFamily> {
+    let mut db = fontdb::Database::new();
+    db.load_system_fonts();
+    // The first name is the English one when the font has it
+    group_faces(db.faces().filter_map(|face| {
+        let (family, _) = face.families.first()?;
+        Some((family.as_str(), face.monospaced))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_faces() {
+        let families = group_faces([
+            ("Fira Code", true),
+            ("Arial", false),
+            ("Fira Code", true),
+            ("Mixed", true),
+            ("Mixed", false),
+            (".SF NS", false),
+            ("  ", false),
+        ]);
+        let names: Vec<(&str, bool)> = families
+            .iter()
+            .map(|f| (f.name.as_str(), f.monospace))
+            .collect();
+        assert_eq!(
+            names,
+            [("Arial", false), ("Fira Code", true), ("Mixed", false)]
+        );
+    }
+}
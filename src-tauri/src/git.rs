@@ -8,6 +8,10 @@ use git2::{
 };
 use std::path::Path;
 
+use crate::git_message;
+use crate::git_signing::{self, signing_config, CommitSignature};
+use crate::tree_builder::TreeNode;
+
 /// Git repository information
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct GitRepoInfo {
@@ -16,10 +20,53 @@ pub struct GitRepoInfo {
     pub remote_url: Option<String>,
     pub is_dirty: bool,
     pub head_commit: Option<String>,
+    /// Upstream of the current branch, e.g. "origin/main"
+    #[serde(default)]
+    pub upstream: Option<String>,
+    /// Commits on the branch but not on its upstream
+    #[serde(default)]
+    pub ahead: Option<usize>,
+    /// Commits on the upstream but not on the branch
+    #[serde(default)]
+    pub behind: Option<usize>,
+    /// HEAD points at a commit rather than a branch
+    #[serde(default)]
+    pub detached: bool,
+    /// Tags pointing at HEAD, e.g. the released version checked out
+    #[serde(default)]
+    pub head_tags: Vec<String>,
+}
+
+/// Names of the tags pointing at a commit
+fn tags_at(repo: &Repository, commit: Oid) -> Vec<String> {
+    let mut tags = Vec::new();
+    let _ = repo.tag_foreach(|oid, name| {
+        let target = repo
+            .find_object(oid, None)
+            .and_then(|o| o.peel_to_commit())
+            .map(|c| c.id());
+        if target.ok() == Some(commit) {
+            let name = String::from_utf8_lossy(name);
+            tags.push(name.trim_start_matches("refs/tags/").to_string());
+        }
+        true
+    });
+    tags.sort();
+    tags
+}
+
+/// (upstream name, ahead, behind) of a local branch, if it tracks one
+fn tracking_info(repo: &Repository, branch: &git2::Branch) -> Option<(String, usize, usize)> {
+    let upstream = branch.upstream().ok()?;
+    let name = upstream.name().ok().flatten()?.to_string();
+    let local = branch.get().target()?;
+    let remote = upstream.get().target()?;
+    let (ahead, behind) = repo.graph_ahead_behind(local, remote).ok()?;
+    Some((name, ahead, behind))
 }
 
 /// Git file status
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct GitFileStatus {
     pub path: String,
     pub status: String, // "modified", "new", "deleted", "renamed", "untracked", "staged"
@@ -37,6 +84,13 @@ pub struct GitCommitInfo {
     pub timestamp: i64,
     pub parent_ids: Vec<String>,
     pub refs: Vec<String>,
+    /// Path of the file in this commit, for per-file logs (it changes across
+    /// renames)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+    /// GPG/SSH signature, if the commit is signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<CommitSignature>,
 }
 
 /// Detect Git repository from a path (searches upward)
@@ -55,11 +109,12 @@ pub fn detect_repo(path: &str) -> Result<Option<GitRepoInfo>, String> {
         .to_string_lossy()
         .to_string();
 
-    // Get current branch
+    // Get current branch; "HEAD" when detached
     let branch = repo
         .head()
         .ok()
         .and_then(|head| head.shorthand().map(|s| s.to_string()));
+    let detached = repo.head_detached().unwrap_or(false);
 
     // Get remote URL (origin)
     let remote_url = repo
@@ -74,11 +129,20 @@ pub fn detect_repo(path: &str) -> Result<Option<GitRepoInfo>, String> {
         .unwrap_or(true);
 
     // Get HEAD commit
-    let head_commit = repo
+    let head_oid = repo
         .head()
         .ok()
         .and_then(|h| h.peel_to_commit().ok())
-        .map(|c| c.id().to_string());
+        .map(|c| c.id());
+    let head_commit = head_oid.map(|id| id.to_string());
+    let head_tags = head_oid.map(|id| tags_at(&repo, id)).unwrap_or_default();
+
+    // Ahead/behind versus the upstream of the current branch
+    let tracking = repo
+        .head()
+        .ok()
+        .filter(|h| h.is_branch())
+        .and_then(|h| tracking_info(&repo, &git2::Branch::wrap(h)));
 
     Ok(Some(GitRepoInfo {
         path: repo_path,
@@ -86,6 +150,11 @@ pub fn detect_repo(path: &str) -> Result<Option<GitRepoInfo>, String> {
         remote_url,
         is_dirty,
         head_commit,
+        upstream: tracking.as_ref().map(|(name, ..)| name.clone()),
+        ahead: tracking.as_ref().map(|(_, ahead, _)| *ahead),
+        behind: tracking.as_ref().map(|(.., behind)| *behind),
+        detached,
+        head_tags,
     }))
 }
 
@@ -140,6 +209,150 @@ pub fn get_status(repo_path: &str) -> Result<Vec<GitFileStatus>, String> {
     Ok(result)
 }
 
+/// What a cached status was computed against: the index file and HEAD
+#[derive(Debug, Clone, PartialEq)]
+struct StatusStamp {
+    index: Option<(std::time::SystemTime, u64)>,
+    head: Option<Oid>,
+}
+
+fn status_stamp(repo_path: &str) -> Result<StatusStamp, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let index = std::fs::metadata(repo.path().join("index"))
+        .ok()
+        .and_then(|m| Some((m.modified().ok()?, m.len())));
+    let head = repo.head().ok().and_then(|h| h.target());
+    Ok(StatusStamp { index, head })
+}
+
+#[derive(Default)]
+struct CachedStatus {
+    stamp: Option<StatusStamp>,
+    files: Option<Vec<GitFileStatus>>,
+    /// Working tree changed since `files` was computed
+    stale: bool,
+    /// Bumped by every invalidation, so a status computed meanwhile isn't
+    /// stored as fresh
+    generation: u64,
+}
+
+/// Status of the watched repositories, so the panel doesn't rescan a large
+/// working tree on every refresh. The watcher marks a repository stale when
+/// files change; index and HEAD changes are caught by comparing stamps.
+/// Repositories that aren't watched are never cached.
+#[derive(Default)]
+pub struct StatusCache {
+    entries: std::sync::Mutex<std::collections::HashMap<String, CachedStatus>>,
+}
+
+impl StatusCache {
+    /// Start caching a repository once its working tree is watched
+    pub fn track(&self, repo_path: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(repo_path.to_string(), CachedStatus::default());
+        }
+    }
+
+    pub fn untrack(&self, repo_path: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(repo_path);
+        }
+    }
+
+    /// Mark the cached status outdated after a file changed
+    pub fn invalidate(&self, repo_path: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            if let Some(entry) = entries.get_mut(repo_path) {
+                entry.stale = true;
+                entry.generation += 1;
+            }
+        }
+    }
+
+    /// Status of the repository, from the cache when still valid
+    pub fn get(&self, repo_path: &str) -> Result<Vec<GitFileStatus>, String> {
+        let stamp = status_stamp(repo_path)?;
+        let generation = {
+            let entries = self.entries.lock().map_err(|e| e.to_string())?;
+            match entries.get(repo_path) {
+                None => return get_status(repo_path),
+                Some(entry) => match &entry.files {
+                    Some(files) if !entry.stale && entry.stamp.as_ref() == Some(&stamp) => {
+                        return Ok(files.clone())
+                    }
+                    _ => entry.generation,
+                },
+            }
+        };
+
+        let files = get_status(repo_path)?;
+        let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+        if let Some(entry) = entries.get_mut(repo_path) {
+            if entry.generation == generation {
+                entry.stamp = Some(stamp);
+                entry.files = Some(files.clone());
+                entry.stale = false;
+            }
+        }
+        Ok(files)
+    }
+
+    /// Bring the cached status up to date, returning it and whether it
+    /// differs from what was cached before
+    pub fn refresh(&self, repo_path: &str) -> Result<(Vec<GitFileStatus>, bool), String> {
+        let previous = self
+            .entries
+            .lock()
+            .map_err(|e| e.to_string())?
+            .get(repo_path)
+            .and_then(|entry| entry.files.clone());
+        let files = self.get(repo_path)?;
+        let changed = previous.as_ref() != Some(&files);
+        Ok((files, changed))
+    }
+}
+
+/// Mark the files of a file tree that have uncommitted changes with
+/// `gitStatus` ("modified", "untracked", ...) and `gitStaged`, using one
+/// (cached) status per repository
+pub fn attach_status_to_tree(nodes: &mut [TreeNode], cache: &StatusCache) {
+    fn walk(
+        nodes: &mut [TreeNode],
+        workdir: &Path,
+        statuses: &std::collections::HashMap<String, GitFileStatus>,
+    ) {
+        for node in nodes {
+            if node.r#type == "file" {
+                let status = Path::new(&node.path)
+                    .strip_prefix(workdir)
+                    .ok()
+                    .map(|p| p.to_string_lossy().replace('\\', "/"))
+                    .and_then(|p| statuses.get(&p));
+                if let Some(status) = status {
+                    node.set_metadata("gitStatus", status.status.clone().into());
+                    node.set_metadata("gitStaged", status.is_staged.into());
+                }
+            }
+            walk(&mut node.children, workdir, statuses);
+        }
+    }
+
+    for root in nodes {
+        let Ok(repo) = Repository::discover(&root.path) else {
+            continue;
+        };
+        let Some(workdir) = repo.workdir() else {
+            continue;
+        };
+        // Same key as `detect_repo`, so a watched repository hits the cache
+        let Ok(files) = cache.get(&workdir.to_string_lossy()) else {
+            continue;
+        };
+        let statuses = files.into_iter().map(|f| (f.path.clone(), f)).collect();
+        walk(std::slice::from_mut(root), workdir, &statuses);
+    }
+}
+
 /// Stage a file (git add)
 pub fn stage_file(repo_path: &str, file_path: &str) -> Result<(), String> {
     let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
@@ -188,8 +401,9 @@ pub fn unstage_file(repo_path: &str, file_path: &str) -> Result<(), String> {
 }
 
 /// Create a commit
-pub fn commit(repo_path: &str, message: &str) -> Result<String, String> {
+pub fn commit(repo_path: &str, message: &str, sign: Option<bool>) -> Result<String, String> {
     let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let message = &git_message::prepare_message(&repo, message)?;
 
     let mut index = repo.index().map_err(|e| e.to_string())?;
     let tree_id = index.write_tree().map_err(|e| e.to_string())?;
@@ -213,23 +427,28 @@ pub fn commit(repo_path: &str, message: &str) -> Result<String, String> {
 
     let parent_refs: Vec<&Commit> = parents.iter().collect();
 
-    let commit_id = repo
-        .commit(Some("HEAD"), &sig, &sig, message, &tree, &parent_refs)
-        .map_err(|e| e.to_string())?;
+    let sign = sign.unwrap_or_else(|| signing_config(&repo).sign_commits);
+    let log_message = format!(
+        "commit{}: {}",
+        if parents.is_empty() { " (initial)" } else { "" },
+        message.lines().next().unwrap_or_default()
+    );
+    let commit_id = git_signing::create_commit(
+        &repo,
+        Some(&log_message),
+        &sig,
+        &sig,
+        message,
+        &tree,
+        &parent_refs,
+        sign,
+    )?;
 
     Ok(commit_id.to_string())
 }
 
-/// Get commit log
-pub fn get_log(
-    repo_path: &str,
-    limit: Option<i32>,
-    all: bool,
-) -> Result<Vec<GitCommitInfo>, String> {
-    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
-    let limit = limit.unwrap_or(200) as usize; // Increase default limit for graph
-
-    // NEW: Pre-fetch refs (branches and tags)
+/// Branch and tag names pointing at each commit
+fn refs_by_commit(repo: &Repository) -> std::collections::HashMap<Oid, Vec<String>> {
     let mut refs_map: std::collections::HashMap<Oid, Vec<String>> =
         std::collections::HashMap::new();
 
@@ -279,6 +498,47 @@ pub fn get_log(
         }
     }
 
+    refs_map
+}
+
+pub(crate) fn commit_info(commit: &Commit, refs: Vec<String>) -> GitCommitInfo {
+    let oid = commit.id();
+    let short_id = commit
+        .as_object()
+        .short_id()
+        .map(|s| s.as_str().unwrap_or("").to_string())
+        .unwrap_or_else(|_| oid.to_string()[..7].to_string());
+
+    let parent_ids: Vec<String> = commit.parent_ids().map(|id| id.to_string()).collect();
+
+    GitCommitInfo {
+        id: oid.to_string(),
+        short_id,
+        message: commit.message().unwrap_or("").to_string(),
+        author_name: commit.author().name().unwrap_or("Unknown").to_string(),
+        author_email: commit.author().email().unwrap_or("").to_string(),
+        timestamp: commit.time().seconds(),
+        parent_ids,
+        refs,
+        file_path: None,
+        signature: None,
+    }
+}
+
+/// Get commit log. Signed commits are only checked with gpg/ssh-keygen
+/// when `verify_signatures` is set, as that runs a process per commit.
+pub fn get_log(
+    repo_path: &str,
+    limit: Option<i32>,
+    all: bool,
+    verify_signatures: bool,
+) -> Result<Vec<GitCommitInfo>, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let limit = limit.unwrap_or(200) as usize; // Increase default limit for graph
+
+    // Pre-fetch refs (branches and tags)
+    let refs_map = refs_by_commit(&repo);
+
     let mut revwalk = repo.revwalk().map_err(|e| e.to_string())?;
 
     if all {
@@ -304,26 +564,243 @@ pub fn get_log(
         let oid = oid.map_err(|e| e.to_string())?;
         let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
 
-        let short_id = commit
-            .as_object()
-            .short_id()
-            .map(|s| s.as_str().unwrap_or("").to_string())
-            .unwrap_or_else(|_| oid.to_string()[..7].to_string());
+        let commit_refs = refs_map.get(&oid).cloned().unwrap_or_default();
 
-        let parent_ids: Vec<String> = commit.parent_ids().map(|id| id.to_string()).collect();
+        let mut info = commit_info(&commit, commit_refs);
+        info.signature = if verify_signatures {
+            git_signing::verify_commit(&repo, oid)
+        } else {
+            git_signing::commit_signature(&repo, oid)
+        };
+        result.push(info);
+    }
 
-        let commit_refs = refs_map.get(&oid).cloned().unwrap_or_default();
+    Ok(result)
+}
+
+/// A file changed by a commit
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CommitFileChange {
+    pub path: String,
+    /// Previous path, for renames and copies
+    pub old_path: Option<String>,
+    pub status: String, // "added", "modified", "deleted", "renamed", "copied", "typechange"
+    pub additions: u32,
+    pub deletions: u32,
+    pub is_binary: bool,
+}
+
+/// Everything the history panel shows for one commit
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CommitDetails {
+    pub id: String,
+    pub short_id: String,
+    pub message: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub author_time: i64,
+    pub committer_name: String,
+    pub committer_email: String,
+    pub committer_time: i64,
+    pub parent_ids: Vec<String>,
+    pub refs: Vec<String>,
+    /// Changes against the first parent (everything, for a root commit)
+    pub files: Vec<CommitFileChange>,
+    pub stats: DiffStats,
+}
+
+/// Get the details of a commit with per-file change statistics
+pub fn get_commit_info(repo_path: &str, commit_id: &str) -> Result<CommitDetails, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+
+    let commit = repo
+        .revparse_single(commit_id)
+        .and_then(|obj| obj.peel_to_commit())
+        .map_err(|e| e.to_string())?;
+    let tree = commit.tree().map_err(|e| e.to_string())?;
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree().map_err(|e| e.to_string())?),
+        Err(_) => None,
+    };
+
+    let mut diff = repo
+        .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)
+        .map_err(|e| e.to_string())?;
+    let mut find_opts = git2::DiffFindOptions::new();
+    find_opts.renames(true);
+    diff.find_similar(Some(&mut find_opts))
+        .map_err(|e| e.to_string())?;
+
+    let mut files = Vec::new();
+    for (idx, delta) in diff.deltas().enumerate() {
+        let status = match delta.status() {
+            git2::Delta::Added => "added",
+            git2::Delta::Deleted => "deleted",
+            git2::Delta::Renamed => "renamed",
+            git2::Delta::Copied => "copied",
+            git2::Delta::Typechange => "typechange",
+            _ => "modified",
+        };
+        let path_of = |file: git2::DiffFile| {
+            file.path()
+                .map(|p| p.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default()
+        };
+        let path = path_of(if delta.status() == git2::Delta::Deleted {
+            delta.old_file()
+        } else {
+            delta.new_file()
+        });
+        let old_path = matches!(delta.status(), git2::Delta::Renamed | git2::Delta::Copied)
+            .then(|| path_of(delta.old_file()));
+
+        let patch = git2::Patch::from_diff(&diff, idx).map_err(|e| e.to_string())?;
+        let is_binary = patch.is_none() || delta.flags().is_binary();
+        let (additions, deletions) = match &patch {
+            Some(patch) if !is_binary => {
+                let (_, additions, deletions) = patch.line_stats().map_err(|e| e.to_string())?;
+                (additions as u32, deletions as u32)
+            }
+            _ => (0, 0),
+        };
+
+        files.push(CommitFileChange {
+            path,
+            old_path,
+            status: status.to_string(),
+            additions,
+            deletions,
+            is_binary,
+        });
+    }
+
+    let stats = DiffStats {
+        additions: files.iter().map(|f| f.additions).sum(),
+        deletions: files.iter().map(|f| f.deletions).sum(),
+    };
+
+    let info = commit_info(
+        &commit,
+        refs_by_commit(&repo)
+            .remove(&commit.id())
+            .unwrap_or_default(),
+    );
+    let author = commit.author();
+    let committer = commit.committer();
+
+    Ok(CommitDetails {
+        id: info.id,
+        short_id: info.short_id,
+        message: info.message,
+        author_name: info.author_name,
+        author_email: info.author_email,
+        author_time: author.when().seconds(),
+        committer_name: committer.name().unwrap_or("Unknown").to_string(),
+        committer_email: committer.email().unwrap_or("").to_string(),
+        committer_time: committer.when().seconds(),
+        parent_ids: info.parent_ids,
+        refs: info.refs,
+        files,
+        stats,
+    })
+}
+
+/// Path of `file_path` relative to the repository root (it may be absolute)
+fn relative_path(repo: &Repository, file_path: &str) -> Result<std::path::PathBuf, String> {
+    let path = Path::new(file_path);
+    if !path.is_absolute() {
+        return Ok(path.to_path_buf());
+    }
+    let repo_root = repo.workdir().ok_or("No workdir")?;
+    path.strip_prefix(repo_root)
+        .map(|p| p.to_path_buf())
+        .map_err(|_| "File not in repo".to_string())
+}
+
+/// Blob id of `path` in `tree`, if it exists there
+fn blob_id_at(tree: &git2::Tree, path: &Path) -> Option<Oid> {
+    tree.get_path(path).ok().map(|entry| entry.id())
+}
+
+/// Commit log of a single file (first-parent history of HEAD), following
+/// renames like `git log --follow`
+pub fn get_file_log(
+    repo_path: &str,
+    file_path: &str,
+    limit: Option<i32>,
+) -> Result<Vec<GitCommitInfo>, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let limit = limit.unwrap_or(100) as usize;
+    let mut path = relative_path(&repo, file_path)?;
+
+    let refs_map = refs_by_commit(&repo);
+
+    let mut revwalk = repo.revwalk().map_err(|e| e.to_string())?;
+    revwalk.push_head().map_err(|e| e.to_string())?;
+    revwalk.simplify_first_parent().map_err(|e| e.to_string())?;
+    revwalk
+        .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
+        .map_err(|e| e.to_string())?;
+
+    let mut result = Vec::new();
+
+    for oid in revwalk {
+        if result.len() >= limit {
+            break;
+        }
+        let oid = oid.map_err(|e| e.to_string())?;
+        let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+        let tree = commit.tree().map_err(|e| e.to_string())?;
 
-        result.push(GitCommitInfo {
-            id: oid.to_string(),
-            short_id,
-            message: commit.message().unwrap_or("").to_string(),
-            author_name: commit.author().name().unwrap_or("Unknown").to_string(),
-            author_email: commit.author().email().unwrap_or("").to_string(),
-            timestamp: commit.time().seconds(),
-            parent_ids,
-            refs: commit_refs,
+        let Some(blob_id) = blob_id_at(&tree, &path) else {
+            // Not in this commit (e.g. deleted later and re-added)
+            continue;
+        };
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree().map_err(|e| e.to_string())?),
+            Err(_) => None, // Root commit
+        };
+
+        // Unchanged since the parent: not part of the file's history
+        if let Some(parent_tree) = &parent_tree {
+            if blob_id_at(parent_tree, &path) == Some(blob_id) {
+                continue;
+            }
+        }
+
+        let mut info = commit_info(&commit, refs_map.get(&oid).cloned().unwrap_or_default());
+        info.file_path = Some(path.to_string_lossy().replace('\\', "/"));
+        result.push(info);
+
+        // Added in this commit: look for a rename from another path
+        let Some(parent_tree) = parent_tree else {
+            break;
+        };
+        if blob_id_at(&parent_tree, &path).is_some() {
+            continue;
+        }
+        let mut diff = repo
+            .diff_tree_to_tree(
+                Some(&parent_tree),
+                Some(&tree),
+                Some(&mut DiffOptions::new()),
+            )
+            .map_err(|e| e.to_string())?;
+        let mut find_opts = git2::DiffFindOptions::new();
+        find_opts.renames(true);
+        diff.find_similar(Some(&mut find_opts))
+            .map_err(|e| e.to_string())?;
+
+        let renamed_from = diff.deltas().find_map(|delta| {
+            let is_target = delta.new_file().path() == Some(path.as_path());
+            (is_target && delta.status() == git2::Delta::Renamed)
+                .then(|| delta.old_file().path().map(|p| p.to_path_buf()))
+                .flatten()
         });
+        match renamed_from {
+            Some(old_path) => path = old_path,
+            None => break, // Created here
+        }
     }
 
     Ok(result)
@@ -409,9 +886,11 @@ pub fn discard_changes(repo_path: &str, file_path: &str) -> Result<(), String> {
     Ok(())
 }
 
-/// Initialize a new Git repository
-pub fn init_repo(path: &str) -> Result<GitRepoInfo, String> {
-    let repo = Repository::init(path).map_err(|e| e.to_string())?;
+/// Initialize a new Git repository, with `branch` as its first branch
+pub fn init_repo(path: &str, branch: &str) -> Result<GitRepoInfo, String> {
+    let mut options = git2::RepositoryInitOptions::new();
+    options.initial_head(branch);
+    let repo = Repository::init_opts(path, &options).map_err(|e| e.to_string())?;
 
     let repo_path = repo
         .workdir()
@@ -421,10 +900,15 @@ pub fn init_repo(path: &str) -> Result<GitRepoInfo, String> {
 
     Ok(GitRepoInfo {
         path: repo_path,
-        branch: Some("main".to_string()),
+        branch: Some(branch.to_string()),
         remote_url: None,
         is_dirty: false,
         head_commit: None,
+        upstream: None,
+        ahead: None,
+        behind: None,
+        detached: false,
+        head_tags: Vec::new(),
     })
 }
 
@@ -495,17 +979,79 @@ pub struct DiffStats {
     pub deletions: u32,
 }
 
+/// Which two versions of a file a structured diff compares
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffMode {
+    /// HEAD ↔ working directory (all changes)
+    #[default]
+    HeadToWorkdir,
+    /// HEAD ↔ index (staged changes)
+    Staged,
+    /// Index ↔ working directory (unstaged changes)
+    Unstaged,
+}
+
+/// Get the staged (index) content of a file; empty if it isn't in the index
+pub fn get_index_file_content(repo_path: &str, file_path: &str) -> Result<String, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let index = repo.index().map_err(|e| e.to_string())?;
+
+    let entry = match index.get_path(Path::new(file_path), 0) {
+        Some(e) => e,
+        None => return Ok(String::new()), // Untracked or deleted from the index
+    };
+
+    let blob = repo.find_blob(entry.id).map_err(|e| e.to_string())?;
+
+    let content = std::str::from_utf8(blob.content())
+        .map_err(|e| e.to_string())?
+        .to_string();
+
+    Ok(content)
+}
+
 /// Get structured diff for a file (for VSCode-style diff viewer)
-pub fn get_structured_diff(repo_path: &str, file_path: &str) -> Result<StructuredDiff, String> {
-    let full_path = Path::new(repo_path).join(file_path);
+pub fn get_structured_diff(
+    repo_path: &str,
+    file_path: &str,
+    mode: DiffMode,
+) -> Result<StructuredDiff, String> {
+    // Current file content; a file deleted from disk diffs against nothing
+    let workdir_content = || {
+        let full_path = Path::new(repo_path).join(file_path);
+        match std::fs::read_to_string(&full_path) {
+            Ok(content) => Ok(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(format!("Failed to read file: {}", e)),
+        }
+    };
 
-    // Get current file content
-    let new_content =
-        std::fs::read_to_string(&full_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    // HEAD content is empty for new repos/new files
+    let (old_content, new_content) = match mode {
+        DiffMode::HeadToWorkdir => (
+            get_head_file_content(repo_path, file_path)?,
+            workdir_content()?,
+        ),
+        DiffMode::Staged => (
+            get_head_file_content(repo_path, file_path)?,
+            get_index_file_content(repo_path, file_path)?,
+        ),
+        DiffMode::Unstaged => (
+            get_index_file_content(repo_path, file_path)?,
+            workdir_content()?,
+        ),
+    };
 
-    // Get HEAD content (empty for new repos/new files)
-    let old_content = get_head_file_content(repo_path, file_path)?;
+    Ok(build_structured_diff(file_path, old_content, new_content))
+}
 
+/// Line diff of two versions of a file
+fn build_structured_diff(
+    file_path: &str,
+    old_content: String,
+    new_content: String,
+) -> StructuredDiff {
     // Use similar crate for reliable diff generation
     use similar::{ChangeTag, TextDiff};
 
@@ -544,7 +1090,7 @@ pub fn get_structured_diff(repo_path: &str, file_path: &str) -> Result<Structure
         });
     }
 
-    Ok(StructuredDiff {
+    StructuredDiff {
         file_path: file_path.to_string(),
         old_content,
         new_content,
@@ -553,7 +1099,7 @@ pub fn get_structured_diff(repo_path: &str, file_path: &str) -> Result<Structure
             additions,
             deletions,
         },
-    })
+    }
 }
 
 /// Branch information
@@ -562,6 +1108,13 @@ pub struct BranchInfo {
     pub name: String,
     pub is_head: bool,
     pub is_remote: bool,
+    /// Upstream of a local branch, e.g. "origin/main"
+    #[serde(default)]
+    pub upstream: Option<String>,
+    #[serde(default)]
+    pub ahead: Option<usize>,
+    #[serde(default)]
+    pub behind: Option<usize>,
 }
 
 /// List all branches
@@ -589,10 +1142,19 @@ pub fn list_branches(repo_path: &str) -> Result<Vec<BranchInfo>, String> {
         // Check if it is remote based on branch_type
         let is_remote = matches!(branch_type, git2::BranchType::Remote);
 
+        let tracking = if is_remote {
+            None
+        } else {
+            tracking_info(&repo, &branch)
+        };
+
         result.push(BranchInfo {
             name,
             is_head,
             is_remote,
+            upstream: tracking.as_ref().map(|(name, ..)| name.clone()),
+            ahead: tracking.as_ref().map(|(_, ahead, _)| *ahead),
+            behind: tracking.as_ref().map(|(.., behind)| *behind),
         });
     }
 
@@ -629,12 +1191,67 @@ pub fn create_branch(repo_path: &str, name: &str) -> Result<(), String> {
     Ok(())
 }
 
-/// Switch branch (checkout)
-pub fn switch_branch(repo_path: &str, name: &str) -> Result<(), String> {
+/// Create a branch at any commit, e.g. to recover one found in the reflog
+pub fn create_branch_at(repo_path: &str, commit_id: &str, name: &str) -> Result<(), String> {
     let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let commit = repo
+        .revparse_single(commit_id)
+        .and_then(|o| o.peel_to_commit())
+        .map_err(|_| format!("Commit {} not found", commit_id))?;
+
+    repo.branch(name, &commit, false)
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Result of switching branches
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct SwitchBranchResult {
+    pub switched: bool,
+    /// Files with uncommitted changes the switch would overwrite; when set,
+    /// nothing was changed
+    pub conflicts: Vec<String>,
+    /// The changes were stashed for the switch and re-applied afterwards
+    pub stashed: bool,
+    /// Files where re-applying the changes conflicted; the stash is kept
+    pub stash_conflicts: Vec<String>,
+}
+
+/// Files whose uncommitted changes a safe checkout of `target` would
+/// overwrite
+fn checkout_conflicts(repo: &Repository, target: &git2::Object) -> Vec<String> {
+    let paths = std::cell::RefCell::new(Vec::new());
+    {
+        let mut builder = git2::build::CheckoutBuilder::new();
+        builder
+            .safe()
+            .dry_run()
+            .notify_on(git2::CheckoutNotificationType::CONFLICT)
+            .notify(|_, path, _, _, _| {
+                if let Some(path) = path {
+                    paths
+                        .borrow_mut()
+                        .push(path.to_string_lossy().replace('\\', "/"));
+                }
+                true
+            });
+        // The dry run fails when there are conflicts; they were collected
+        let _ = repo.checkout_tree(target, Some(&mut builder));
+    }
+    paths.into_inner()
+}
 
-    // Logic for switching differs if it's a local branch or remote tracking branch
-    // For simplicity, assuming local branch name or full ref name
+/// Switch to a local branch. If uncommitted changes would be overwritten,
+/// nothing happens and they are reported, unless `stash_changes` is set:
+/// then they are stashed, the branch is checked out and they are
+/// re-applied on top of it.
+pub fn switch_branch(
+    repo_path: &str,
+    name: &str,
+    stash_changes: bool,
+) -> Result<SwitchBranchResult, String> {
+    let mut repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
 
     // If name doesn't start with refs/, try to look up "refs/heads/<name>"
     let ref_name = if name.starts_with("refs/") {
@@ -642,19 +1259,124 @@ pub fn switch_branch(repo_path: &str, name: &str) -> Result<(), String> {
     } else {
         format!("refs/heads/{}", name)
     };
+    let target = repo
+        .find_reference(&ref_name)
+        .and_then(|r| r.peel_to_commit())
+        .map_err(|e| e.to_string())?
+        .id();
+
+    let mut result = SwitchBranchResult::default();
+    let conflicts = checkout_conflicts(
+        &repo,
+        &repo.find_object(target, None).map_err(|e| e.to_string())?,
+    );
+    if !conflicts.is_empty() {
+        if !stash_changes {
+            result.conflicts = conflicts;
+            return Ok(result);
+        }
+        let sig = default_signature(&repo)?;
+        repo.stash_save(
+            &sig,
+            &format!("Auto-stash before switching to {}", name),
+            Some(git2::StashFlags::INCLUDE_UNTRACKED),
+        )
+        .map_err(|e| e.to_string())?;
+        result.stashed = true;
+    }
 
-    // set HEAD
-    repo.set_head(&ref_name).map_err(|e| e.to_string())?;
+    // Update the working tree first, so a failure leaves HEAD alone
+    let checkout = repo
+        .find_object(target, None)
+        .and_then(|object| {
+            repo.checkout_tree(&object, Some(git2::build::CheckoutBuilder::new().safe()))
+        })
+        .and_then(|_| repo.set_head(&ref_name));
+    if let Err(e) = checkout {
+        if result.stashed {
+            let _ = repo.stash_pop(0, None);
+        }
+        return Err(e.to_string());
+    }
+    result.switched = true;
 
-    // checkout HEAD to update working directory
-    // Strategy: Safe - don't overwrite dirty files
-    let mut checkout_builder = git2::build::CheckoutBuilder::new();
-    checkout_builder.safe();
+    if result.stashed {
+        repo.stash_apply(0, None).map_err(|e| {
+            format!(
+                "Switched to {}, but re-applying your changes failed ({}); they are kept in the stash",
+                name, e
+            )
+        })?;
+        let index = repo.index().map_err(|e| e.to_string())?;
+        if index.has_conflicts() {
+            result.stash_conflicts = conflicted_paths(&index);
+        } else {
+            repo.stash_drop(0).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(result)
+}
 
-    repo.checkout_head(Some(&mut checkout_builder))
+/// Start working on a remote branch such as "origin/feature": create the
+/// local branch (named after it unless `local_name` is given) tracking it,
+/// then switch to it like `switch_branch`. An existing local branch that
+/// already tracks it is simply switched to.
+pub fn checkout_remote_branch(
+    repo_path: &str,
+    remote_branch: &str,
+    local_name: Option<&str>,
+    stash_changes: bool,
+) -> Result<SwitchBranchResult, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let upstream = repo
+        .find_branch(remote_branch, git2::BranchType::Remote)
+        .map_err(|_| format!("Remote branch {} not found", remote_branch))?;
+    let remote = repo
+        .branch_remote_name(&format!("refs/remotes/{}", remote_branch))
         .map_err(|e| e.to_string())?;
+    let remote = remote.as_str().ok_or("Invalid remote name")?;
+    let name = match local_name {
+        Some(name) => name,
+        None => remote_branch
+            .strip_prefix(remote)
+            .and_then(|n| n.strip_prefix('/'))
+            .ok_or("Invalid remote branch name")?,
+    };
 
-    Ok(())
+    let created = match repo.find_branch(name, git2::BranchType::Local) {
+        Ok(existing) => {
+            let tracked = existing
+                .upstream()
+                .ok()
+                .and_then(|u| u.name().ok().flatten().map(str::to_string));
+            if tracked.as_deref() != Some(remote_branch) {
+                return Err(format!(
+                    "A local branch {} already exists and doesn't track {}",
+                    name, remote_branch
+                ));
+            }
+            false
+        }
+        Err(_) => {
+            let commit = upstream.get().peel_to_commit().map_err(|e| e.to_string())?;
+            let mut branch = repo
+                .branch(name, &commit, false)
+                .map_err(|e| e.to_string())?;
+            branch
+                .set_upstream(Some(remote_branch))
+                .map_err(|e| e.to_string())?;
+            true
+        }
+    };
+
+    let result = switch_branch(repo_path, name, stash_changes);
+    // Leave nothing behind when the switch didn't happen
+    if created && !matches!(result, Ok(SwitchBranchResult { switched: true, .. })) {
+        if let Ok(mut branch) = repo.find_branch(name, git2::BranchType::Local) {
+            let _ = branch.delete();
+        }
+    }
+    result
 }
 
 /// Delete branch
@@ -833,25 +1555,192 @@ pub fn list_remotes(repo_path: &str) -> Result<Vec<RemoteInfo>, String> {
     Ok(result)
 }
 
-/// Helper to create callbacks with credentials
-fn create_callbacks<'a>() -> RemoteCallbacks<'a> {
-    let mut callbacks = RemoteCallbacks::new();
-    callbacks.credentials(|_url, username_from_url, allowed_types| {
-        if allowed_types.contains(git2::CredentialType::SSH_KEY) {
-            // Try ssh-agent
-            if let Ok(cred) = Cred::ssh_key_from_agent(username_from_url.unwrap_or("git")) {
-                return Ok(cred);
-            }
-        }
-        if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
-            if let Ok(cred) = Cred::credential_helper(
-                &git2::Config::open_default().unwrap(),
-                _url,
-                username_from_url,
-            ) {
-                return Ok(cred);
-            }
-        }
+/// Keyring service under which remembered Git credentials are stored
+const KEYRING_SERVICE: &str = "datatex-git";
+/// How often the user is asked before an operation gives up
+const MAX_CREDENTIAL_PROMPTS: u32 = 3;
+
+/// Credentials the user is asked for when the ssh-agent, the credential
+/// helper and the keyring have nothing that works
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CredentialRequest {
+    pub url: String,
+    pub username: Option<String>,
+    pub kind: String, // "userpass", "ssh_passphrase"
+    /// Private key the passphrase unlocks
+    pub key_path: Option<String>,
+    /// Whether previously supplied credentials were rejected
+    pub retry: bool,
+}
+
+/// The user's answer to a `CredentialRequest`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CredentialResponse {
+    pub username: Option<String>,
+    /// Password, token or key passphrase
+    pub secret: String,
+    /// Store the secret in the system keyring
+    #[serde(default)]
+    pub remember: bool,
+}
+
+/// Asks the user for credentials; None if they cancelled
+pub type CredentialPrompt<'a> = &'a dyn Fn(&CredentialRequest) -> Option<CredentialResponse>;
+
+fn keyring_entry(account: &str) -> Option<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, account).ok()
+}
+
+fn keyring_get(account: &str) -> Option<String> {
+    keyring_entry(account)?.get_password().ok()
+}
+
+fn keyring_set(account: &str, secret: &str) {
+    if let Some(entry) = keyring_entry(account) {
+        let _ = entry.set_password(secret);
+    }
+}
+
+fn keyring_delete(account: &str) {
+    if let Some(entry) = keyring_entry(account) {
+        let _ = entry.delete_credential();
+    }
+}
+
+/// First of the usual private keys in ~/.ssh
+fn default_ssh_key() -> Option<std::path::PathBuf> {
+    let ssh_dir = directories::BaseDirs::new()?.home_dir().join(".ssh");
+    ["id_ed25519", "id_ecdsa", "id_rsa"]
+        .iter()
+        .map(|name| ssh_dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Ask the user through `prompt`, counting the attempts in `prompts`
+fn ask_user(
+    prompt: Option<CredentialPrompt>,
+    prompts: &mut u32,
+    mut request: CredentialRequest,
+) -> Result<CredentialResponse, git2::Error> {
+    let prompt = prompt.ok_or_else(|| git2::Error::from_str("Authentication failed"))?;
+    if *prompts >= MAX_CREDENTIAL_PROMPTS {
+        return Err(git2::Error::from_str("Authentication failed"));
+    }
+    request.retry = *prompts > 0;
+    *prompts += 1;
+    prompt(&request).ok_or_else(|| git2::Error::from_str("Authentication cancelled"))
+}
+
+/// Helper to create callbacks with credentials.
+///
+/// libgit2 calls the credentials callback again whenever the returned
+/// credentials are rejected, so each source is tried once, in order: ssh-agent
+/// or credential helper, the keyring, an unencrypted key, then `prompt` (up to
+/// `MAX_CREDENTIAL_PROMPTS` times). Keyring entries that get rejected are
+/// removed.
+fn create_callbacks<'a>(prompt: Option<CredentialPrompt<'a>>) -> RemoteCallbacks<'a> {
+    let mut callbacks = RemoteCallbacks::new();
+    let mut tried_agent = false;
+    let mut tried_helper = false;
+    let mut tried_keyring = false;
+    let mut tried_plain_key = false;
+    let mut prompts = 0;
+    // Keyring account of the credentials returned last
+    let mut last_keyring_account: Option<String> = None;
+
+    callbacks.credentials(move |url, username_from_url, allowed_types| {
+        // Called again: the keyring credentials returned last were rejected
+        if let Some(account) = last_keyring_account.take() {
+            keyring_delete(&account);
+        }
+
+        if allowed_types.contains(git2::CredentialType::SSH_KEY) {
+            let user = username_from_url.unwrap_or("git");
+            // Try ssh-agent
+            if !tried_agent {
+                tried_agent = true;
+                if let Ok(cred) = Cred::ssh_key_from_agent(user) {
+                    return Ok(cred);
+                }
+            }
+            if let Some(key) = default_ssh_key() {
+                let account = format!("ssh:{}", key.display());
+                if !tried_keyring {
+                    tried_keyring = true;
+                    if let Some(passphrase) = keyring_get(&account) {
+                        last_keyring_account = Some(account);
+                        return Cred::ssh_key(user, None, &key, Some(&passphrase));
+                    }
+                }
+                if !tried_plain_key {
+                    tried_plain_key = true;
+                    return Cred::ssh_key(user, None, &key, None);
+                }
+                let response = ask_user(
+                    prompt,
+                    &mut prompts,
+                    CredentialRequest {
+                        url: url.to_string(),
+                        username: Some(user.to_string()),
+                        kind: "ssh_passphrase".to_string(),
+                        key_path: Some(key.to_string_lossy().to_string()),
+                        retry: false,
+                    },
+                )?;
+                if response.remember {
+                    keyring_set(&account, &response.secret);
+                    last_keyring_account = Some(account);
+                }
+                return Cred::ssh_key(user, None, &key, Some(&response.secret));
+            }
+        }
+
+        if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
+            if !tried_helper {
+                tried_helper = true;
+                if let Ok(config) = git2::Config::open_default() {
+                    if let Ok(cred) = Cred::credential_helper(&config, url, username_from_url) {
+                        return Ok(cred);
+                    }
+                }
+            }
+            if !tried_keyring {
+                tried_keyring = true;
+                if let Some(user) = username_from_url {
+                    let account = format!("{}@{}", user, url);
+                    if let Some(password) = keyring_get(&account) {
+                        last_keyring_account = Some(account);
+                        return Cred::userpass_plaintext(user, &password);
+                    }
+                }
+            }
+            let response = ask_user(
+                prompt,
+                &mut prompts,
+                CredentialRequest {
+                    url: url.to_string(),
+                    username: username_from_url.map(|u| u.to_string()),
+                    kind: "userpass".to_string(),
+                    key_path: None,
+                    retry: false,
+                },
+            )?;
+            let user = response
+                .username
+                .as_deref()
+                .or(username_from_url)
+                .unwrap_or("git");
+            if response.remember {
+                let account = format!("{}@{}", user, url);
+                keyring_set(&account, &response.secret);
+                last_keyring_account = Some(account);
+            }
+            return Cred::userpass_plaintext(user, &response.secret);
+        }
+
+        if allowed_types.contains(git2::CredentialType::USERNAME) {
+            return Cred::username(username_from_url.unwrap_or("git"));
+        }
 
         // Fallback to default (might fail if auth required and no agent/helper)
         Cred::default()
@@ -860,11 +1749,15 @@ fn create_callbacks<'a>() -> RemoteCallbacks<'a> {
 }
 
 /// Fetch from remote
-pub fn fetch_remote(repo_path: &str, remote_name: &str) -> Result<(), String> {
+pub fn fetch_remote(
+    repo_path: &str,
+    remote_name: &str,
+    prompt: Option<CredentialPrompt>,
+) -> Result<(), String> {
     let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
     let mut remote = repo.find_remote(remote_name).map_err(|e| e.to_string())?;
 
-    let callbacks = create_callbacks();
+    let callbacks = create_callbacks(prompt);
     let mut fo = FetchOptions::new();
     fo.remote_callbacks(callbacks);
 
@@ -876,92 +1769,498 @@ pub fn fetch_remote(repo_path: &str, remote_name: &str) -> Result<(), String> {
     Ok(())
 }
 
-/// Push to remote
-pub fn push_to_remote(repo_path: &str, remote_name: &str, branch_name: &str) -> Result<(), String> {
+/// Transfer progress of `fetch_all`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FetchProgress {
+    pub remote: String,
+    pub received_objects: usize,
+    pub total_objects: usize,
+    pub received_bytes: usize,
+}
+
+/// A remote-tracking branch or tag moved by a fetch
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FetchRefUpdate {
+    pub reference: String,
+    /// None for a new reference
+    pub old_id: Option<String>,
+    /// None for a reference pruned because it's gone from the remote
+    pub new_id: Option<String>,
+}
+
+/// What `fetch_all` did
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct FetchSummary {
+    pub remotes: Vec<String>,
+    pub updated: Vec<FetchRefUpdate>,
+    /// Remotes that couldn't be fetched, with the reason
+    pub errors: Vec<String>,
+}
+
+/// Fetch every remote, optionally pruning remote-tracking branches deleted
+/// on the remote. A remote that fails doesn't stop the others.
+pub fn fetch_all(
+    repo_path: &str,
+    prune: bool,
+    prompt: Option<CredentialPrompt>,
+    progress: &dyn Fn(&FetchProgress),
+) -> Result<FetchSummary, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let names = repo.remotes().map_err(|e| e.to_string())?;
+    let mut summary = FetchSummary::default();
+
+    for name in names.iter().flatten() {
+        summary.remotes.push(name.to_string());
+        let updates = std::cell::RefCell::new(Vec::new());
+        let result = repo.find_remote(name).and_then(|mut remote| {
+            let mut callbacks = create_callbacks(prompt);
+            // Report each whole percent, not every packet
+            let mut last_percent = None;
+            callbacks.transfer_progress(|stats| {
+                let total = stats.total_objects();
+                let percent = (stats.received_objects() * 100).checked_div(total);
+                if percent != last_percent {
+                    last_percent = percent;
+                    progress(&FetchProgress {
+                        remote: name.to_string(),
+                        received_objects: stats.received_objects(),
+                        total_objects: total,
+                        received_bytes: stats.received_bytes(),
+                    });
+                }
+                true
+            });
+            callbacks.update_tips(|reference, old, new| {
+                let id = |oid: Oid| (!oid.is_zero()).then(|| oid.to_string());
+                updates.borrow_mut().push(FetchRefUpdate {
+                    reference: reference.to_string(),
+                    old_id: id(old),
+                    new_id: id(new),
+                });
+                true
+            });
+            let mut fo = FetchOptions::new();
+            fo.remote_callbacks(callbacks);
+            fo.prune(if prune {
+                git2::FetchPrune::On
+            } else {
+                git2::FetchPrune::Unspecified
+            });
+            remote.fetch(&[] as &[&str], Some(&mut fo), None)
+        });
+        match result {
+            Ok(()) => summary.updated.extend(updates.into_inner()),
+            Err(e) => summary.errors.push(format!("{}: {}", name, e.message())),
+        }
+    }
+
+    if !summary.remotes.is_empty() && summary.errors.len() == summary.remotes.len() {
+        return Err(summary.errors.join("\n"));
+    }
+    Ok(summary)
+}
+
+/// Options for `push_to_remote`
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PushSettings {
+    /// Overwrite the remote branch, but only if it still points where our
+    /// remote-tracking branch says it does
+    #[serde(default)]
+    pub force_with_lease: bool,
+    /// Push all local tags as well
+    #[serde(default)]
+    pub tags: bool,
+}
+
+/// How the remote answered for one pushed ref
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PushRefResult {
+    pub reference: String,
+    pub ok: bool,
+    /// Rejection reason reported by the remote
+    pub message: Option<String>,
+}
+
+/// Result of a push
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PushResult {
+    pub refs: Vec<PushRefResult>,
+    /// Upstream configured for a branch that had none, e.g. "origin/main"
+    pub upstream_set: Option<String>,
+}
+
+/// Check that the remote branch is where we last saw it. libgit2 has no
+/// atomic lease, so this is a check right before the forced push.
+fn verify_lease(
+    repo: &Repository,
+    remote: &mut git2::Remote,
+    branch_name: &str,
+    prompt: Option<CredentialPrompt>,
+) -> Result<(), String> {
+    let remote_name = remote.name().unwrap_or_default().to_string();
+    let expected = repo
+        .refname_to_id(&format!("refs/remotes/{}/{}", remote_name, branch_name))
+        .ok();
+
+    let connection = remote
+        .connect_auth(git2::Direction::Push, Some(create_callbacks(prompt)), None)
+        .map_err(|e| e.to_string())?;
+    let remote_ref = format!("refs/heads/{}", branch_name);
+    let actual = connection
+        .list()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .find(|head| head.name() == remote_ref)
+        .map(|head| head.oid());
+
+    if actual.is_some() && actual != expected {
+        return Err(format!(
+            "{}/{} has changed since the last fetch; fetch and review it before force-pushing",
+            remote_name, branch_name
+        ));
+    }
+    Ok(())
+}
+
+/// Push a branch (and optionally tags) to remote. A branch without an
+/// upstream gets the pushed remote branch as its upstream.
+pub fn push_to_remote(
+    repo_path: &str,
+    remote_name: &str,
+    branch_name: &str,
+    settings: &PushSettings,
+    prompt: Option<CredentialPrompt>,
+) -> Result<PushResult, String> {
     let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
     let mut remote = repo.find_remote(remote_name).map_err(|e| e.to_string())?;
 
-    let callbacks = create_callbacks();
+    if settings.force_with_lease {
+        verify_lease(&repo, &mut remote, branch_name, prompt)?;
+    }
+
+    // Refspec: refs/heads/branch:refs/heads/branch, "+" to force
+    let mut refspecs = vec![format!(
+        "{}refs/heads/{}:refs/heads/{}",
+        if settings.force_with_lease { "+" } else { "" },
+        branch_name,
+        branch_name
+    )];
+    if settings.tags {
+        let tags = repo.tag_names(None).map_err(|e| e.to_string())?;
+        refspecs.extend(
+            tags.iter()
+                .flatten()
+                .map(|tag| format!("refs/tags/{}:refs/tags/{}", tag, tag)),
+        );
+    }
+
+    let refs = push_refspecs(&mut remote, &refspecs, prompt)?;
+
+    let branch_pushed = refs
+        .iter()
+        .any(|r| r.ok && r.reference == format!("refs/heads/{}", branch_name));
+    let mut upstream_set = None;
+    if branch_pushed {
+        let mut branch = repo
+            .find_branch(branch_name, git2::BranchType::Local)
+            .map_err(|e| e.to_string())?;
+        if branch.upstream().is_err() {
+            let upstream = format!("{}/{}", remote_name, branch_name);
+            branch
+                .set_upstream(Some(&upstream))
+                .map_err(|e| e.to_string())?;
+            upstream_set = Some(upstream);
+        }
+    }
+
+    Ok(PushResult { refs, upstream_set })
+}
+
+/// Push refspecs, returning what the remote did with each reference
+fn push_refspecs(
+    remote: &mut git2::Remote,
+    refspecs: &[String],
+    prompt: Option<CredentialPrompt>,
+) -> Result<Vec<PushRefResult>, String> {
+    let updates = std::cell::RefCell::new(Vec::new());
+    let mut callbacks = create_callbacks(prompt);
+    callbacks.push_update_reference(|reference, status| {
+        updates.borrow_mut().push(PushRefResult {
+            reference: reference.to_string(),
+            ok: status.is_none(),
+            message: status.map(str::to_string),
+        });
+        Ok(())
+    });
     let mut po = PushOptions::new();
     po.remote_callbacks(callbacks);
 
-    // Refspec: refs/heads/branch:refs/heads/branch
-    let refspec = format!("refs/heads/{}:refs/heads/{}", branch_name, branch_name);
-
     remote
-        .push(&[&refspec], Some(&mut po))
+        .push(refspecs, Some(&mut po))
         .map_err(|e| e.to_string())?;
+    drop(po);
+    Ok(updates.into_inner())
+}
 
-    Ok(())
+/// Push a single refspec and fail if the remote rejects it
+fn push_single(
+    repo: &Repository,
+    remote_name: &str,
+    refspec: String,
+    prompt: Option<CredentialPrompt>,
+) -> Result<(), String> {
+    let mut remote = repo.find_remote(remote_name).map_err(|e| e.to_string())?;
+    let refs = push_refspecs(&mut remote, &[refspec], prompt)?;
+    match refs.into_iter().find(|r| !r.ok) {
+        Some(rejected) => Err(format!(
+            "{} was rejected: {}",
+            rejected.reference,
+            rejected.message.unwrap_or_default()
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Push a tag to a remote
+pub fn push_tag(
+    repo_path: &str,
+    remote_name: &str,
+    tag: &str,
+    prompt: Option<CredentialPrompt>,
+) -> Result<(), String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let refname = format!("refs/tags/{}", tag);
+    repo.find_reference(&refname)
+        .map_err(|_| format!("Tag {} not found", tag))?;
+    push_single(
+        &repo,
+        remote_name,
+        format!("{}:{}", refname, refname),
+        prompt,
+    )
+}
+
+/// Delete a tag from a remote (the local tag is kept)
+pub fn delete_remote_tag(
+    repo_path: &str,
+    remote_name: &str,
+    tag: &str,
+    prompt: Option<CredentialPrompt>,
+) -> Result<(), String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    push_single(&repo, remote_name, format!(":refs/tags/{}", tag), prompt)
+}
+
+/// How `pull_from_remote` integrates upstream commits that can't be
+/// fast-forwarded
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PullStrategy {
+    /// Create a merge commit
+    #[default]
+    Merge,
+    /// Replay local commits on top of the upstream branch
+    Rebase,
+}
+
+/// What a pull did
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PullOutcome {
+    UpToDate,
+    FastForward,
+    Merged,
+    Rebased,
+    /// Stopped with conflicts; the merge or rebase is left in progress
+    Conflicts,
+}
+
+/// Result of a pull
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PullResult {
+    pub outcome: PullOutcome,
+    /// HEAD after the pull
+    pub head: Option<String>,
+    /// Upstream commit that was pulled
+    pub upstream: String,
+    /// Local commits replayed by a rebase
+    pub rebased_commits: usize,
+    /// Paths with conflicts when the outcome is `Conflicts`
+    pub conflicts: Vec<String>,
+}
+
+pub(crate) fn conflicted_paths(index: &git2::Index) -> Vec<String> {
+    let Ok(conflicts) = index.conflicts() else {
+        return Vec::new();
+    };
+    conflicts
+        .flatten()
+        .filter_map(|entry| {
+            entry
+                .our
+                .or(entry.their)
+                .or(entry.ancestor)
+                .map(|e| String::from_utf8_lossy(&e.path).to_string())
+        })
+        .collect()
+}
+
+pub(crate) fn default_signature(repo: &Repository) -> Result<Signature<'static>, String> {
+    repo.signature()
+        .or_else(|_| Signature::now("DataTeX", "user@datatex.local"))
+        .map_err(|e| e.to_string())
 }
 
-/// Pull from remote (Fetch + Merge)
+/// Pull from remote: fetch, then fast-forward, merge or rebase the
+/// checked-out branch onto its remote counterpart
 pub fn pull_from_remote(
     repo_path: &str,
     remote_name: &str,
     branch_name: &str,
-) -> Result<(), String> {
-    // 1. Fetch
-    fetch_remote(repo_path, remote_name)?;
+    strategy: PullStrategy,
+    prompt: Option<CredentialPrompt>,
+) -> Result<PullResult, String> {
+    fetch_remote(repo_path, remote_name, prompt)?;
 
     let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let local_ref = format!("refs/heads/{}", branch_name);
+    let head_ref = repo
+        .find_reference("HEAD")
+        .map_err(|e| e.to_string())?
+        .symbolic_target()
+        .map(str::to_string);
+    if head_ref.as_deref() != Some(local_ref.as_str()) {
+        return Err(format!("Branch {} is not checked out", branch_name));
+    }
 
-    // 2. Prepare for merge
-    let fetch_head = repo
-        .find_reference("FETCH_HEAD")
-        .map_err(|e| e.to_string())?;
-    let fetch_commit = repo
-        .reference_to_annotated_commit(&fetch_head)
+    let remote_ref = repo
+        .find_reference(&format!("refs/remotes/{}/{}", remote_name, branch_name))
+        .map_err(|_| format!("{}/{} not found", remote_name, branch_name))?;
+    let upstream = repo
+        .reference_to_annotated_commit(&remote_ref)
         .map_err(|e| e.to_string())?;
 
-    let analysis = repo
-        .merge_analysis(&[&fetch_commit])
+    let mut result = PullResult {
+        outcome: PullOutcome::UpToDate,
+        head: None,
+        upstream: upstream.id().to_string(),
+        rebased_commits: 0,
+        conflicts: Vec::new(),
+    };
+
+    let (analysis, _) = repo
+        .merge_analysis(&[&upstream])
         .map_err(|e| e.to_string())?;
 
-    if analysis.0.is_fast_forward() {
-        // Fast-forward
-        let ref_name = format!("refs/heads/{}", branch_name);
-        let mut reference = repo.find_reference(&ref_name).map_err(|e| e.to_string())?;
-        reference
-            .set_target(fetch_commit.id(), "Fast-Forward")
+    if analysis.is_up_to_date() {
+        // Nothing to do
+    } else if analysis.is_fast_forward() || analysis.is_unborn() {
+        // Safe checkout refuses to overwrite local changes
+        let target = repo
+            .find_object(upstream.id(), None)
             .map_err(|e| e.to_string())?;
-        repo.set_head(&ref_name).map_err(|e| e.to_string())?;
-        repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))
+        repo.checkout_tree(&target, Some(git2::build::CheckoutBuilder::new().safe()))
             .map_err(|e| e.to_string())?;
-    } else if analysis.0.is_normal() {
-        // Merge
-        repo.merge(&[&fetch_commit], None, None)
+        repo.reference(&local_ref, upstream.id(), true, "pull: Fast-forward")
             .map_err(|e| e.to_string())?;
-
-        // This leaves the repo in a merging state. User needs to commit.
-        // Or we can try to commit automatically if no conflicts?
-        // For now, let's leave it to user to commit if it's a merge.
-        // Actually, normal `git pull` does commit.
-
-        // Implementing full merge commit logic is complex in git2.
-        // For MVP, we stop here. The Index should be updated with merge result.
-        // If conflicts, they will be in index.
-        // Creating the merge commit is needed to finish.
-
-        // Simplification: Check index for conflicts. If none, commit.
-        if repo.index().unwrap().has_conflicts() {
-            return Err("Merge conflicts detected. Please resolve them.".to_string());
+        result.outcome = PullOutcome::FastForward;
+    } else if strategy == PullStrategy::Rebase {
+        let mut rebase = repo
+            .rebase(None, Some(&upstream), None, None)
+            .map_err(|e| format!("Failed to init rebase: {}", e))?;
+        let sig = default_signature(&repo)?;
+
+        while let Some(op) = rebase.next() {
+            if let Err(e) = op {
+                rebase.abort().ok();
+                return Err(format!("Rebase error: {}", e));
+            }
+            let index = repo.index().map_err(|e| e.to_string())?;
+            if index.has_conflicts() {
+                result.outcome = PullOutcome::Conflicts;
+                result.conflicts = conflicted_paths(&index);
+                break;
+            }
+            match rebase.commit(None, &sig, None) {
+                Ok(_) => result.rebased_commits += 1,
+                // The commit's changes are already upstream; drop it
+                Err(e) if e.code() == git2::ErrorCode::Applied => {}
+                Err(e) => {
+                    rebase.abort().ok();
+                    return Err(format!("Rebase error: {}", e));
+                }
+            }
         }
 
-        // Make the commit
-        // This is getting complicated for a single function.
-        // Let's stick to Fast-Forward only for this iteration or return "Non-fast-forward merge required".
-        // Or better: Let user know.
-
-        // Just return Ok() - the files are updated (or conflicted). User sees changes in Git Panel.
-        // BUT "merge" function updates files in working dir.
-        // We need to write the commit if no conflicts.
+        if result.outcome != PullOutcome::Conflicts {
+            rebase
+                .finish(Some(&sig))
+                .map_err(|e| format!("Failed to finish rebase: {}", e))?;
+            result.outcome = PullOutcome::Rebased;
+        }
+    } else {
+        // The merge commit is written from the index, so it must hold
+        // nothing but the merge
+        let head_tree = repo
+            .head()
+            .and_then(|h| h.peel_to_tree())
+            .map_err(|e| e.to_string())?;
+        let staged = repo
+            .diff_tree_to_index(Some(&head_tree), None, None)
+            .map_err(|e| e.to_string())?;
+        if staged.deltas().len() > 0 {
+            return Err(
+                "You have staged changes; commit or unstage them before pulling".to_string(),
+            );
+        }
+        repo.merge(&[&upstream], None, None)
+            .map_err(|e| e.to_string())?;
 
-        // Let's define: Pull only supports Fast-Forward for now to be safe.
-        return Err("Only fast-forward pull is supported currently.".to_string());
+        let mut index = repo.index().map_err(|e| e.to_string())?;
+        if index.has_conflicts() {
+            // Leave MERGE_HEAD in place so the merge can be committed
+            // once the conflicts are resolved
+            result.outcome = PullOutcome::Conflicts;
+            result.conflicts = conflicted_paths(&index);
+        } else {
+            let sig = default_signature(&repo)?;
+            let tree = repo
+                .find_tree(index.write_tree().map_err(|e| e.to_string())?)
+                .map_err(|e| e.to_string())?;
+            let head_commit = repo
+                .head()
+                .and_then(|h| h.peel_to_commit())
+                .map_err(|e| e.to_string())?;
+            let upstream_commit = repo.find_commit(upstream.id()).map_err(|e| e.to_string())?;
+            let message = format!(
+                "Merge branch '{}' of {} into {}",
+                branch_name,
+                repo.find_remote(remote_name)
+                    .ok()
+                    .and_then(|r| r.url().map(str::to_string))
+                    .unwrap_or_else(|| remote_name.to_string()),
+                branch_name
+            );
+            repo.commit(
+                Some("HEAD"),
+                &sig,
+                &sig,
+                &message,
+                &tree,
+                &[&head_commit, &upstream_commit],
+            )
+            .map_err(|e| e.to_string())?;
+            repo.cleanup_state().map_err(|e| e.to_string())?;
+            result.outcome = PullOutcome::Merged;
+        }
     }
 
-    Ok(())
+    result.head = repo
+        .head()
+        .ok()
+        .and_then(|h| h.target())
+        .map(|id| id.to_string());
+    Ok(result)
 }
 
 /// Read .gitignore content
@@ -982,6 +2281,103 @@ pub fn write_gitignore(repo_path: &str, content: &str) -> Result<(), String> {
     std::fs::write(gitignore_path, content).map_err(|e| e.to_string())
 }
 
+/// Curated ignore list for LaTeX projects, grouped under comment headers
+const LATEX_GITIGNORE: &[(&str, &[&str])] = &[
+    (
+        "Core LaTeX auxiliary files",
+        &[
+            "*.aux", "*.lof", "*.log", "*.lot", "*.fls", "*.out", "*.toc", "*.fmt", "*.fot",
+            "*.cb", "*.cb2", ".*.lb",
+        ],
+    ),
+    (
+        "Intermediate documents",
+        &["*.dvi", "*.xdv", "*-converted-to.*"],
+    ),
+    (
+        "Bibliography",
+        &[
+            "*.bbl",
+            "*.bcf",
+            "*.blg",
+            "*-blx.aux",
+            "*-blx.bib",
+            "*.run.xml",
+        ],
+    ),
+    (
+        "Build tools",
+        &[
+            "*.fdb_latexmk",
+            "*.synctex",
+            "*.synctex(busy)",
+            "*.synctex.gz",
+            "*.synctex.gz(busy)",
+            "*.pdfsync",
+        ],
+    ),
+    ("minted", &["_minted*", "*.pyg"]),
+    (
+        "Glossaries and indexes",
+        &[
+            "*.acn",
+            "*.acr",
+            "*.glg",
+            "*.glo",
+            "*.gls",
+            "*.glsdefs",
+            "*.ist",
+            "*.idx",
+            "*.ilg",
+            "*.ind",
+        ],
+    ),
+    ("Beamer", &["*.nav", "*.snm", "*.vrb"]),
+    ("Build directories", &["build/", "_build/"]),
+];
+
+/// Merge the LaTeX ignore list into `existing` .gitignore content. The
+/// user's lines are kept as they are; only patterns not already listed are
+/// appended, so running it twice changes nothing.
+pub fn generate_latex_gitignore(existing: &str) -> String {
+    let present: std::collections::HashSet<&str> = existing
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.trim_start_matches('/'))
+        .collect();
+
+    let mut added = String::new();
+    for (heading, patterns) in LATEX_GITIGNORE {
+        let missing: Vec<&str> = patterns
+            .iter()
+            .copied()
+            .filter(|pattern| !present.contains(pattern))
+            .collect();
+        if missing.is_empty() {
+            continue;
+        }
+        added.push_str(&format!("\n# {}\n", heading));
+        for pattern in missing {
+            added.push_str(pattern);
+            added.push('\n');
+        }
+    }
+
+    if added.is_empty() {
+        return existing.to_string();
+    }
+    let mut content = existing.trim_end().to_string();
+    if content.is_empty() {
+        // No leading blank line in a new file
+        added.remove(0);
+    } else {
+        content.push('\n');
+    }
+    content.push_str(&added);
+    content
+}
+
 // ============================================================================
 // Stash Support
 // ============================================================================
@@ -1012,21 +2408,174 @@ pub fn list_stashes(repo_path: &str) -> Result<Vec<StashInfo>, String> {
     Ok(stashes)
 }
 
+/// Options for `create_stash`
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct StashSettings {
+    /// Stash untracked files too (and remove them from the working tree)
+    #[serde(default)]
+    pub include_untracked: bool,
+    /// Leave staged changes in the index
+    #[serde(default)]
+    pub keep_index: bool,
+    /// Only stash these tracked paths; empty stashes everything
+    #[serde(default)]
+    pub paths: Vec<String>,
+}
+
 /// Create a new stash
-pub fn create_stash(repo_path: &str, message: Option<&str>) -> Result<Oid, String> {
+pub fn create_stash(
+    repo_path: &str,
+    message: Option<&str>,
+    settings: &StashSettings,
+) -> Result<Oid, String> {
     let mut repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
 
     let sig = repo
         .signature()
         .unwrap_or_else(|_| Signature::now("DataTeX User", "user@datatex.local").unwrap());
 
+    let mut flags = git2::StashFlags::DEFAULT;
+    if settings.include_untracked {
+        flags |= git2::StashFlags::INCLUDE_UNTRACKED;
+    }
+    if settings.keep_index {
+        flags |= git2::StashFlags::KEEP_INDEX;
+    }
+
+    let message = message.unwrap_or("WIP on stash");
+    if settings.paths.is_empty() {
+        return repo
+            .stash_save(&sig, message, Some(flags))
+            .map_err(|e| e.to_string());
+    }
+
+    // libgit2 records untracked files twice in a path-limited stash,
+    // which then can't be applied
+    if settings.include_untracked {
+        return Err(
+            "Untracked files can't be stashed by path; stash everything instead".to_string(),
+        );
+    }
+
+    // libgit2 resets every changed file after a path-limited stash, not
+    // just the stashed ones, so the others are put back afterwards
+    let workdir = repo.workdir().ok_or("No workdir")?.to_path_buf();
+    let pathspec = git2::Pathspec::new(settings.paths.iter()).map_err(|e| e.to_string())?;
+    let kept: Vec<(String, Option<Vec<u8>>, Option<git2::IndexEntry>)> = {
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(false);
+        let statuses = repo.statuses(Some(&mut opts)).map_err(|e| e.to_string())?;
+        let index = repo.index().map_err(|e| e.to_string())?;
+        statuses
+            .iter()
+            .filter_map(|entry| entry.path().map(str::to_string))
+            .filter(|path| !pathspec.matches_path(Path::new(path), git2::PathspecFlags::DEFAULT))
+            .map(|path| {
+                let content = std::fs::read(workdir.join(&path)).ok();
+                let entry = index.get_path(Path::new(&path), 0);
+                (path, content, entry)
+            })
+            .collect()
+    };
+
+    // git2 can't set a message on a path-limited stash; libgit2 uses
+    // the default "WIP on <branch>" there
+    let mut opts = git2::StashSaveOptions::new(sig);
+    opts.flags(Some(flags));
+    for path in &settings.paths {
+        opts.pathspec(path.as_str());
+    }
     let oid = repo
-        .stash_save(&sig, message.unwrap_or("WIP on stash"), None)
+        .stash_save_ext(Some(&mut opts))
         .map_err(|e| e.to_string())?;
 
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+    for (path, content, entry) in kept {
+        let full_path = workdir.join(&path);
+        match content {
+            Some(bytes) => {
+                if let Some(parent) = full_path.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                std::fs::write(&full_path, bytes).map_err(|e| e.to_string())?;
+            }
+            None => {
+                let _ = std::fs::remove_file(&full_path);
+            }
+        }
+        match entry {
+            Some(entry) => index.add(&entry).map_err(|e| e.to_string())?,
+            None => {
+                let _ = index.remove_path(Path::new(&path));
+            }
+        }
+    }
+    index.write().map_err(|e| e.to_string())?;
+
     Ok(oid)
 }
 
+/// Changes saved in a stash, one diff per file, for previewing it before
+/// applying. Untracked files stashed with it show up as added.
+pub fn get_stash_diff(repo_path: &str, index: usize) -> Result<Vec<StructuredDiff>, String> {
+    let mut repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+
+    let mut stash_id = None;
+    repo.stash_foreach(|i, _, oid| {
+        if i == index {
+            stash_id = Some(*oid);
+        }
+        stash_id.is_none()
+    })
+    .map_err(|e| e.to_string())?;
+    let stash_id = stash_id.ok_or_else(|| format!("Stash {} not found", index))?;
+
+    let stash = repo.find_commit(stash_id).map_err(|e| e.to_string())?;
+    let base = stash.parent(0).map_err(|e| e.to_string())?;
+    let base_tree = base.tree().map_err(|e| e.to_string())?;
+    let stash_tree = stash.tree().map_err(|e| e.to_string())?;
+    // The third parent holds untracked files, if they were stashed
+    let untracked_tree = stash.parent(2).ok().and_then(|c| c.tree().ok());
+
+    let blob_text = |id: Oid| -> String {
+        if id.is_zero() {
+            return String::new();
+        }
+        repo.find_blob(id)
+            .map(|blob| String::from_utf8_lossy(blob.content()).into_owned())
+            .unwrap_or_default()
+    };
+
+    let mut diffs = Vec::new();
+    let tracked = repo
+        .diff_tree_to_tree(Some(&base_tree), Some(&stash_tree), None)
+        .map_err(|e| e.to_string())?;
+    let untracked = match &untracked_tree {
+        Some(tree) => Some(
+            repo.diff_tree_to_tree(None, Some(tree), None)
+                .map_err(|e| e.to_string())?,
+        ),
+        None => None,
+    };
+    for diff in std::iter::once(&tracked).chain(untracked.as_ref()) {
+        for delta in diff.deltas() {
+            let path = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())
+                .map(|p| p.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default();
+            diffs.push(build_structured_diff(
+                &path,
+                blob_text(delta.old_file().id()),
+                blob_text(delta.new_file().id()),
+            ));
+        }
+    }
+
+    Ok(diffs)
+}
+
 /// Apply a stash by index (keeps stash in list)
 pub fn apply_stash(repo_path: &str, index: usize) -> Result<(), String> {
     let mut repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
@@ -1063,8 +2612,9 @@ pub fn get_last_commit_message(repo_path: &str) -> Result<String, String> {
 }
 
 /// Amend the last commit with new message
-pub fn commit_amend(repo_path: &str, message: &str) -> Result<String, String> {
+pub fn commit_amend(repo_path: &str, message: &str, sign: Option<bool>) -> Result<String, String> {
     let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let message = &git_message::prepare_message(&repo, message)?;
 
     // Get HEAD commit
     let head = repo.head().map_err(|e| e.to_string())?;
@@ -1082,16 +2632,23 @@ pub fn commit_amend(repo_path: &str, message: &str) -> Result<String, String> {
         .unwrap_or_else(|_| Signature::now("DataTeX User", "user@datatex.local").unwrap());
 
     // Amend: create new commit with same parents as old commit
-    let new_oid = commit
-        .amend(
-            Some("HEAD"),
-            Some(&author),
-            Some(&committer),
-            None, // encoding
-            Some(message),
-            Some(&tree),
-        )
-        .map_err(|e| e.to_string())?;
+    let parents: Vec<Commit> = commit.parents().collect();
+    let parent_refs: Vec<&Commit> = parents.iter().collect();
+    let sign = sign.unwrap_or_else(|| signing_config(&repo).sign_commits);
+    let log_message = format!(
+        "commit (amend): {}",
+        message.lines().next().unwrap_or_default()
+    );
+    let new_oid = git_signing::create_commit(
+        &repo,
+        Some(&log_message),
+        &author,
+        &committer,
+        message,
+        &tree,
+        &parent_refs,
+        sign,
+    )?;
 
     Ok(new_oid.to_string())
 }
@@ -1115,24 +2672,120 @@ pub fn checkout_commit(repo_path: &str, commit_id: &str) -> Result<(), String> {
     Ok(())
 }
 
-/// Cherry-pick a commit onto current HEAD
-pub fn cherry_pick(repo_path: &str, commit_id: &str) -> Result<String, String> {
-    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+/// One entry of the HEAD reflog
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ReflogEntry {
+    /// Position in the reflog, 0 being the latest (HEAD@{n})
+    pub index: usize,
+    pub old_id: String,
+    pub new_id: String,
+    /// e.g. "commit: Add exercise", "reset: moving to HEAD~1"
+    pub message: String,
+    pub committer_name: String,
+    pub timestamp: i64,
+    /// Summary of the commit HEAD moved to, if it still exists
+    pub summary: Option<String>,
+}
 
-    let oid = Oid::from_str(commit_id).map_err(|e| e.to_string())?;
-    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+/// Where HEAD has been, newest first. Commits lost by a reset or rebase
+/// can be found here and recovered with `create_branch_at`.
+pub fn get_reflog(repo_path: &str, limit: Option<usize>) -> Result<Vec<ReflogEntry>, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let reflog = repo.reflog("HEAD").map_err(|e| e.to_string())?;
+
+    let entries = reflog
+        .iter()
+        .take(limit.unwrap_or(100))
+        .enumerate()
+        .map(|(index, entry)| {
+            let new_id = entry.id_new();
+            ReflogEntry {
+                index,
+                old_id: entry.id_old().to_string(),
+                new_id: new_id.to_string(),
+                message: entry.message().unwrap_or("").to_string(),
+                committer_name: entry.committer().name().unwrap_or("Unknown").to_string(),
+                timestamp: entry.committer().when().seconds(),
+                summary: repo
+                    .find_commit(new_id)
+                    .ok()
+                    .and_then(|c| c.summary().map(str::to_string)),
+            }
+        })
+        .collect();
 
-    // Get current HEAD
-    let head = repo.head().map_err(|e| e.to_string())?;
-    let head_commit = head.peel_to_commit().map_err(|e| e.to_string())?;
+    Ok(entries)
+}
 
-    // Perform cherry-pick (creates an index with the changes)
-    let mut index = repo
-        .cherrypick_commit(&commit, &head_commit, 0, None)
-        .map_err(|e| e.to_string())?;
+/// How far `reset_to_commit` resets
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResetMode {
+    /// Move the branch only; changes stay staged
+    Soft,
+    /// Move the branch and reset the index; changes stay in the files
+    #[default]
+    Mixed,
+    /// Move the branch and discard all changes
+    Hard,
+}
 
-    if index.has_conflicts() {
-        return Err("Cherry-pick resulted in conflicts. Please resolve manually.".to_string());
+/// Reset the current branch to a commit (any revision, e.g. "HEAD~1" to
+/// undo the last commit). A hard reset with uncommitted changes is
+/// refused unless `force` is set. Returns the new HEAD commit id.
+pub fn reset_to_commit(
+    repo_path: &str,
+    commit_id: &str,
+    mode: ResetMode,
+    force: bool,
+) -> Result<String, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let commit = repo
+        .revparse_single(commit_id)
+        .and_then(|o| o.peel_to_commit())
+        .map_err(|_| format!("Commit {} not found", commit_id))?;
+
+    if mode == ResetMode::Hard && !force {
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(false);
+        let statuses = repo.statuses(Some(&mut opts)).map_err(|e| e.to_string())?;
+        if !statuses.is_empty() {
+            return Err(format!(
+                "{} uncommitted change(s) would be lost; commit or stash them first",
+                statuses.len()
+            ));
+        }
+    }
+
+    let reset_type = match mode {
+        ResetMode::Soft => git2::ResetType::Soft,
+        ResetMode::Mixed => git2::ResetType::Mixed,
+        ResetMode::Hard => git2::ResetType::Hard,
+    };
+    repo.reset(commit.as_object(), reset_type, None)
+        .map_err(|e| e.to_string())?;
+
+    Ok(commit.id().to_string())
+}
+
+/// Cherry-pick a commit onto current HEAD
+pub fn cherry_pick(repo_path: &str, commit_id: &str) -> Result<String, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+
+    let oid = Oid::from_str(commit_id).map_err(|e| e.to_string())?;
+    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+
+    // Get current HEAD
+    let head = repo.head().map_err(|e| e.to_string())?;
+    let head_commit = head.peel_to_commit().map_err(|e| e.to_string())?;
+
+    // Perform cherry-pick (creates an index with the changes)
+    let mut index = repo
+        .cherrypick_commit(&commit, &head_commit, 0, None)
+        .map_err(|e| e.to_string())?;
+
+    if index.has_conflicts() {
+        return Err("Cherry-pick resulted in conflicts. Please resolve manually.".to_string());
     }
 
     // Write index to tree
@@ -1167,54 +2820,183 @@ pub struct BlameInfo {
     pub author: String,
     pub timestamp: i64,
     pub line_content: String,
+    /// Line number in the blamed commit's version of the file
+    pub orig_line_number: usize,
+    /// Path of the file in the blamed commit, if it was renamed since
+    pub orig_path: Option<String>,
 }
 
-/// Get blame information for a file
-pub fn git_blame(repo_path: &str, file_path: &str) -> Result<Vec<BlameInfo>, String> {
-    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+/// Which lines of a file to blame, and at which revision
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct BlameSettings {
+    /// First line (1-based); the start of the file if unset
+    pub min_line: Option<usize>,
+    /// Last line; the end of the file if unset
+    pub max_line: Option<usize>,
+    /// Blame the file as of this commit instead of HEAD
+    pub commit: Option<String>,
+}
 
-    // Get the relative path
-    let repo_root = repo.workdir().ok_or("No workdir")?;
+/// Repository, file and commit blamed at
+type BlameKey = (String, String, Oid);
+
+/// Blame results kept between calls, so scrolling through a large file
+/// only blames the lines not seen yet. Entries are keyed by the commit
+/// blamed at, so a moved HEAD simply misses; commits clear them.
+#[derive(Default)]
+pub struct BlameCache {
+    entries: std::sync::Mutex<
+        std::collections::HashMap<BlameKey, std::collections::BTreeMap<usize, BlameInfo>>,
+    >,
+}
+
+impl BlameCache {
+    /// Forget everything cached for a repository, e.g. after a commit
+    pub fn invalidate(&self, repo_path: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|(repo, _, _), _| repo != repo_path);
+        }
+    }
+
+    /// Cached lines `min..=max`, if all of them are known
+    fn get(&self, key: &BlameKey, min: usize, max: usize) -> Option<Vec<BlameInfo>> {
+        let entries = self.entries.lock().ok()?;
+        let lines: Vec<BlameInfo> = entries
+            .get(key)?
+            .range(min..=max)
+            .map(|(_, b)| b.clone())
+            .collect();
+        (lines.len() == max + 1 - min).then_some(lines)
+    }
+
+    fn insert(&self, key: BlameKey, lines: &[BlameInfo]) {
+        if let Ok(mut entries) = self.entries.lock() {
+            let cached = entries.entry(key).or_default();
+            for line in lines {
+                cached.insert(line.line_number, line.clone());
+            }
+        }
+    }
+}
+
+fn repo_relative_path<'a>(repo: &Repository, file_path: &'a str) -> Result<&'a Path, String> {
     let abs_path = Path::new(file_path);
-    let rel_path = if abs_path.is_absolute() {
+    if abs_path.is_absolute() {
+        let repo_root = repo.workdir().ok_or("No workdir")?;
         abs_path
             .strip_prefix(repo_root)
-            .map_err(|_| "File not in repo")?
+            .map_err(|_| "File not in repo".to_string())
     } else {
-        abs_path
-    };
+        Ok(abs_path)
+    }
+}
 
-    let blame = repo.blame_file(rel_path, None).map_err(|e| e.to_string())?;
+/// Get blame information for a file, or for the lines in `settings`
+pub fn git_blame(
+    repo_path: &str,
+    file_path: &str,
+    settings: &BlameSettings,
+    cache: &BlameCache,
+) -> Result<Vec<BlameInfo>, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
 
-    // Read file content to get line content
-    let full_path = repo_root.join(rel_path);
-    let content = std::fs::read_to_string(&full_path).unwrap_or_default();
+    // Get the relative path
+    let rel_path = repo_relative_path(&repo, file_path)?;
+
+    let pinned = match &settings.commit {
+        Some(rev) => Some(
+            repo.revparse_single(rev)
+                .and_then(|o| o.peel_to_commit())
+                .map_err(|e| e.to_string())?,
+        ),
+        None => None,
+    };
+    let commit = match &pinned {
+        Some(commit) => Some(commit.clone()),
+        None => repo.head().and_then(|h| h.peel_to_commit()).ok(),
+    };
+    let committed = commit
+        .as_ref()
+        .and_then(|c| c.tree().ok())
+        .and_then(|t| t.get_path(rel_path).ok())
+        .and_then(|e| e.to_object(&repo).ok())
+        .and_then(|o| o.peel_to_blob().ok())
+        .map(|b| b.content().to_vec());
+    if pinned.is_some() && committed.is_none() {
+        return Err(format!(
+            "{} does not exist in that commit",
+            rel_path.display()
+        ));
+    }
+
+    // Line content comes from the working file, or the blamed revision
+    let content = match (&pinned, &committed) {
+        (Some(_), Some(bytes)) => String::from_utf8_lossy(bytes).to_string(),
+        _ => std::fs::read_to_string(repo.workdir().ok_or("No workdir")?.join(rel_path))
+            .unwrap_or_default(),
+    };
     let lines: Vec<&str> = content.lines().collect();
+    let with_content = |mut blame: Vec<BlameInfo>| {
+        for info in &mut blame {
+            info.line_content = lines
+                .get(info.line_number.saturating_sub(1))
+                .unwrap_or(&"")
+                .to_string();
+        }
+        blame
+    };
+
+    let mut opts = git2::BlameOptions::new();
+    let mut key = None;
+    if let (Some(commit), Some(bytes)) = (&commit, &committed) {
+        // Clamp the range to the committed file, which is what gets blamed
+        let total = bytes.split_inclusive(|&c| c == b'\n').count();
+        let min = settings.min_line.unwrap_or(1).max(1);
+        if min > total {
+            return Ok(Vec::new());
+        }
+        let max = settings.max_line.unwrap_or(total).clamp(min, total);
+        let cache_key = (
+            repo_path.to_string(),
+            rel_path.to_string_lossy().to_string(),
+            commit.id(),
+        );
+        if let Some(blame) = cache.get(&cache_key, min, max) {
+            return Ok(with_content(blame));
+        }
+        opts.min_line(min).max_line(max).newest_commit(commit.id());
+        key = Some(cache_key);
+    }
+
+    let blame = repo
+        .blame_file(rel_path, Some(&mut opts))
+        .map_err(|e| e.to_string())?;
 
     let mut result = Vec::new();
 
     for hunk in blame.iter() {
         let sig = hunk.final_signature();
         let commit_id = hunk.final_commit_id();
+        let orig_path = hunk
+            .path()
+            .filter(|p| *p != rel_path)
+            .map(|p| p.to_string_lossy().to_string());
 
         // Git blame hunks can span multiple lines
         let start_line = hunk.final_start_line();
         let num_lines = hunk.lines_in_hunk();
 
         for offset in 0..num_lines {
-            let line_num = start_line + offset;
-            let line_content = lines
-                .get(line_num.saturating_sub(1))
-                .unwrap_or(&"")
-                .to_string();
-
             result.push(BlameInfo {
-                line_number: line_num,
+                line_number: start_line + offset,
                 commit_id: commit_id.to_string(),
                 short_id: commit_id.to_string()[..7.min(commit_id.to_string().len())].to_string(),
                 author: sig.name().unwrap_or("Unknown").to_string(),
                 timestamp: sig.when().seconds(),
-                line_content,
+                line_content: String::new(),
+                orig_line_number: hunk.orig_start_line() + offset,
+                orig_path: orig_path.clone(),
             });
         }
     }
@@ -1222,7 +3004,79 @@ pub fn git_blame(repo_path: &str, file_path: &str) -> Result<Vec<BlameInfo>, Str
     // Sort by line number
     result.sort_by_key(|b| b.line_number);
 
-    Ok(result)
+    if let Some(key) = key {
+        cache.insert(key, &result);
+    }
+    Ok(with_content(result))
+}
+
+/// Blame of a file as it was just before a commit
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BlameAtParent {
+    /// The parent that was blamed
+    pub commit_id: String,
+    /// Path of the file in the parent (it may have been renamed by the
+    /// commit)
+    pub file_path: String,
+    pub lines: Vec<BlameInfo>,
+}
+
+/// Re-blame the lines of `settings` at the first parent of `commit_id`,
+/// to see who wrote them before that commit changed them. `file_path` is
+/// the path in `commit_id`.
+pub fn blame_parent(
+    repo_path: &str,
+    file_path: &str,
+    commit_id: &str,
+    settings: &BlameSettings,
+    cache: &BlameCache,
+) -> Result<BlameAtParent, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let rel_path = repo_relative_path(&repo, file_path)?;
+    let commit = repo
+        .revparse_single(commit_id)
+        .and_then(|o| o.peel_to_commit())
+        .map_err(|e| e.to_string())?;
+    let parent = commit
+        .parent(0)
+        .map_err(|_| "The commit has no parent; these lines start here".to_string())?;
+
+    // Follow a rename made by the commit back to the parent's path
+    let mut diff_opts = DiffOptions::new();
+    let mut diff = repo
+        .diff_tree_to_tree(
+            Some(&parent.tree().map_err(|e| e.to_string())?),
+            Some(&commit.tree().map_err(|e| e.to_string())?),
+            Some(&mut diff_opts),
+        )
+        .map_err(|e| e.to_string())?;
+    diff.find_similar(None).map_err(|e| e.to_string())?;
+    let mut parent_path = rel_path.to_path_buf();
+    for delta in diff.deltas() {
+        if delta.new_file().path() == Some(rel_path) {
+            if delta.status() == git2::Delta::Added {
+                return Err(format!(
+                    "{} was added by this commit; these lines start here",
+                    rel_path.display()
+                ));
+            }
+            if let Some(old) = delta.old_file().path() {
+                parent_path = old.to_path_buf();
+            }
+        }
+    }
+    let parent_path = parent_path.to_string_lossy().to_string();
+
+    let parent_settings = BlameSettings {
+        commit: Some(parent.id().to_string()),
+        ..settings.clone()
+    };
+    let lines = git_blame(repo_path, &parent_path, &parent_settings, cache)?;
+    Ok(BlameAtParent {
+        commit_id: parent.id().to_string(),
+        file_path: parent_path,
+        lines,
+    })
 }
 
 /// Tag information
@@ -1283,6 +3137,7 @@ pub fn create_tag(
     name: &str,
     commit_id: Option<&str>,
     message: Option<&str>,
+    sign: Option<bool>,
 ) -> Result<(), String> {
     let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
 
@@ -1300,8 +3155,8 @@ pub fn create_tag(
         let sig = repo
             .signature()
             .unwrap_or_else(|_| Signature::now("DataTeX User", "user@datatex.local").unwrap());
-        repo.tag(name, target_commit.as_object(), &sig, msg, false)
-            .map_err(|e| e.to_string())?;
+        let sign = sign.unwrap_or_else(|| signing_config(&repo).sign_tags);
+        git_signing::create_tag(&repo, name, target_commit.as_object(), &sig, msg, sign)?;
     } else {
         // Lightweight tag
         repo.tag_lightweight(name, target_commit.as_object(), false)
@@ -1311,6 +3166,33 @@ pub fn create_tag(
     Ok(())
 }
 
+/// Check out the commit a tag points at, detaching HEAD. Fails without
+/// touching anything if uncommitted changes would be overwritten.
+pub fn checkout_tag(repo_path: &str, name: &str) -> Result<String, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let commit = repo
+        .find_reference(&format!("refs/tags/{}", name))
+        .and_then(|r| r.peel_to_commit())
+        .map_err(|_| format!("Tag {} not found", name))?;
+
+    let conflicts = checkout_conflicts(&repo, commit.as_object());
+    if !conflicts.is_empty() {
+        return Err(format!(
+            "Commit or stash your changes to {} before checking out {}",
+            conflicts.join(", "),
+            name
+        ));
+    }
+    repo.checkout_tree(
+        commit.as_object(),
+        Some(git2::build::CheckoutBuilder::new().safe()),
+    )
+    .map_err(|e| e.to_string())?;
+    repo.set_head_detached(commit.id())
+        .map_err(|e| e.to_string())?;
+    Ok(commit.id().to_string())
+}
+
 /// Delete a tag
 pub fn delete_tag(repo_path: &str, name: &str) -> Result<(), String> {
     let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
@@ -1432,6 +3314,64 @@ pub fn get_blob_content(repo_path: &str, blob_oid: &str) -> Result<String, Strin
     Ok(content)
 }
 
+/// The three sides of a conflicted file and their merge
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ConflictMerge {
+    pub path: String,
+    /// None when the file doesn't exist on that side
+    pub ancestor: Option<String>,
+    pub ours: Option<String>,
+    pub theirs: Option<String>,
+    pub merge: crate::git_merge::ThreeWayMerge,
+}
+
+/// Three-way merge of a conflicted file for the conflict editor
+pub fn get_conflict_merge(repo_path: &str, file_path: &str) -> Result<ConflictMerge, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let index = repo.index().map_err(|e| e.to_string())?;
+    let path = relative_path(&repo, file_path)?;
+    let path = path.to_string_lossy().replace('\\', "/");
+
+    let conflict = index
+        .conflicts()
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .find(|c| {
+            [&c.ancestor, &c.our, &c.their]
+                .into_iter()
+                .flatten()
+                .any(|e| e.path == path.as_bytes())
+        })
+        .ok_or_else(|| format!("{} has no conflict", path))?;
+
+    let read = |entry: &Option<git2::IndexEntry>| -> Result<Option<String>, String> {
+        let Some(entry) = entry else {
+            return Ok(None);
+        };
+        let blob = repo.find_blob(entry.id).map_err(|e| e.to_string())?;
+        let content = std::str::from_utf8(blob.content())
+            .map_err(|_| "Binary file or invalid UTF-8")?
+            .to_string();
+        Ok(Some(content))
+    };
+    let ancestor = read(&conflict.ancestor)?;
+    let ours = read(&conflict.our)?;
+    let theirs = read(&conflict.their)?;
+
+    let merge = crate::git_merge::three_way_merge(
+        ancestor.as_deref().unwrap_or_default(),
+        ours.as_deref().unwrap_or_default(),
+        theirs.as_deref().unwrap_or_default(),
+    );
+    Ok(ConflictMerge {
+        path,
+        ancestor,
+        ours,
+        theirs,
+        merge,
+    })
+}
+
 /// Mark a conflict as resolved by staging the file
 pub fn mark_conflict_resolved(repo_path: &str, file_path: &str) -> Result<(), String> {
     let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
@@ -1445,6 +3385,924 @@ pub fn mark_conflict_resolved(repo_path: &str, file_path: &str) -> Result<(), St
     Ok(())
 }
 
+// ============================================================================
+// Operation State
+// ============================================================================
+
+/// Multi-step operation a repository can be left in the middle of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepoOperation {
+    Clean,
+    Merging,
+    Rebasing,
+    CherryPicking,
+    Reverting,
+    /// Bisect, mailbox apply and other states the app can't finish
+    Other,
+}
+
+/// What the repository is in the middle of
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RepoOperationState {
+    pub operation: RepoOperation,
+    /// Paths that still have conflicts
+    pub conflicts: Vec<String>,
+    /// Whether `continue_operation` can proceed right now
+    pub can_continue: bool,
+    /// Whether the rebase is an interactive one started by the app
+    pub interactive_rebase: bool,
+}
+
+/// Get the operation the repository is in the middle of
+pub fn get_repo_operation_state(repo_path: &str) -> Result<RepoOperationState, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let interactive_rebase = crate::git_rebase::is_in_progress(repo_path)?;
+
+    use git2::RepositoryState as S;
+    let operation = if interactive_rebase {
+        RepoOperation::Rebasing
+    } else {
+        match repo.state() {
+            S::Clean => RepoOperation::Clean,
+            S::Merge => RepoOperation::Merging,
+            S::Rebase | S::RebaseInteractive | S::RebaseMerge => RepoOperation::Rebasing,
+            S::CherryPick | S::CherryPickSequence => RepoOperation::CherryPicking,
+            S::Revert | S::RevertSequence => RepoOperation::Reverting,
+            _ => RepoOperation::Other,
+        }
+    };
+
+    let index = repo.index().map_err(|e| e.to_string())?;
+    let conflicts = conflicted_paths(&index);
+    let can_continue =
+        conflicts.is_empty() && !matches!(operation, RepoOperation::Clean | RepoOperation::Other);
+
+    Ok(RepoOperationState {
+        operation,
+        conflicts,
+        can_continue,
+        interactive_rebase,
+    })
+}
+
+/// Commit the resolved index of a merge, cherry-pick or revert
+fn commit_pending_operation(repo: &mut Repository, operation: RepoOperation) -> Result<(), String> {
+    let mut merge_heads = Vec::new();
+    if operation == RepoOperation::Merging {
+        repo.mergehead_foreach(|id| {
+            merge_heads.push(*id);
+            true
+        })
+        .map_err(|e| e.to_string())?;
+    }
+
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+    let tree = repo
+        .find_tree(index.write_tree().map_err(|e| e.to_string())?)
+        .map_err(|e| e.to_string())?;
+    let head = repo
+        .head()
+        .and_then(|h| h.peel_to_commit())
+        .map_err(|e| e.to_string())?;
+    let sig = default_signature(repo)?;
+    // MERGE_MSG holds the message git prepared for the operation
+    let message = repo
+        .message()
+        .unwrap_or_else(|_| "Merge commit".to_string());
+
+    let mut parents = vec![head];
+    let mut author = sig.clone();
+    match operation {
+        RepoOperation::Merging => {
+            for id in merge_heads {
+                parents.push(repo.find_commit(id).map_err(|e| e.to_string())?);
+            }
+        }
+        RepoOperation::CherryPicking => {
+            // Keep the author of the picked commit
+            if let Ok(picked) = repo
+                .refname_to_id("CHERRY_PICK_HEAD")
+                .and_then(|id| repo.find_commit(id))
+            {
+                author = picked.author().to_owned();
+            }
+        }
+        _ => {}
+    }
+
+    let parents: Vec<&Commit> = parents.iter().collect();
+    repo.commit(Some("HEAD"), &author, &sig, &message, &tree, &parents)
+        .map_err(|e| e.to_string())?;
+    repo.cleanup_state().map_err(|e| e.to_string())
+}
+
+/// Resume a rebase started by the command line (libgit2's rebase-merge)
+fn continue_git_rebase(repo: &Repository) -> Result<(), String> {
+    // libgit2 can't resume the interactive rebases newer git versions create
+    let mut rebase = repo.open_rebase(None).map_err(|e| {
+        format!(
+            "This rebase can't be continued from the app ({}); use `git rebase --continue`",
+            e.message()
+        )
+    })?;
+    let sig = default_signature(repo)?;
+
+    loop {
+        match rebase.commit(None, &sig, None) {
+            Ok(_) => {}
+            Err(e) if e.code() == git2::ErrorCode::Applied => {}
+            Err(e) => return Err(e.to_string()),
+        }
+        match rebase.next() {
+            Some(Ok(_)) => {
+                if repo.index().map_err(|e| e.to_string())?.has_conflicts() {
+                    return Ok(());
+                }
+            }
+            Some(Err(e)) => return Err(e.to_string()),
+            None => break,
+        }
+    }
+    rebase.finish(Some(&sig)).map_err(|e| e.to_string())
+}
+
+/// Abort a rebase started by the command line
+fn abort_git_rebase(repo: &Repository) -> Result<(), String> {
+    if let Ok(mut rebase) = repo.open_rebase(None) {
+        return rebase.abort().map_err(|e| e.to_string());
+    }
+
+    // Not one libgit2 can open: restore the branch from the rebase state
+    let dir = ["rebase-merge", "rebase-apply"]
+        .into_iter()
+        .map(|name| repo.path().join(name))
+        .find(|dir| dir.is_dir())
+        .ok_or("No rebase in progress")?;
+    let read = |name: &str| {
+        std::fs::read_to_string(dir.join(name))
+            .map(|s| s.trim().to_string())
+            .map_err(|e| e.to_string())
+    };
+    let head_name = read("head-name")?;
+    let orig_head = Oid::from_str(&read("orig-head")?).map_err(|e| e.to_string())?;
+
+    let target = repo
+        .find_object(orig_head, None)
+        .map_err(|e| e.to_string())?;
+    repo.reset(&target, git2::ResetType::Hard, None)
+        .map_err(|e| e.to_string())?;
+    if head_name.starts_with("refs/") {
+        repo.reference(&head_name, orig_head, true, "rebase: aborting")
+            .map_err(|e| e.to_string())?;
+        repo.set_head(&head_name).map_err(|e| e.to_string())?;
+    }
+    // A hard reset usually cleans up the rebase state already
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Finish the current step of a merge, rebase, cherry-pick or revert once
+/// its conflicts are resolved. Returns the state afterwards, which may be
+/// another conflicted rebase step.
+pub fn continue_operation(
+    repo_path: &str,
+    progress: &dyn Fn(&crate::git_rebase::RebaseProgress),
+) -> Result<RepoOperationState, String> {
+    let state = get_repo_operation_state(repo_path)?;
+    if !state.can_continue {
+        return Err(if state.conflicts.is_empty() {
+            "Nothing to continue".to_string()
+        } else {
+            "Resolve all conflicts before continuing".to_string()
+        });
+    }
+
+    let mut repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    if state.interactive_rebase {
+        crate::git_rebase::continue_rebase(repo_path, progress)?;
+    } else if state.operation == RepoOperation::Rebasing {
+        continue_git_rebase(&repo)?;
+    } else {
+        commit_pending_operation(&mut repo, state.operation)?;
+    }
+    get_repo_operation_state(repo_path)
+}
+
+/// Abandon the current merge, rebase, cherry-pick or revert and go back
+/// to where it started
+pub fn abort_operation(repo_path: &str) -> Result<(), String> {
+    let state = get_repo_operation_state(repo_path)?;
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+
+    match state.operation {
+        RepoOperation::Clean => Err("Nothing to abort".to_string()),
+        RepoOperation::Rebasing if state.interactive_rebase => crate::git_rebase::abort(repo_path),
+        RepoOperation::Rebasing => abort_git_rebase(&repo),
+        _ => {
+            let head = repo
+                .head()
+                .and_then(|h| h.peel(git2::ObjectType::Commit))
+                .map_err(|e| e.to_string())?;
+            repo.reset(&head, git2::ResetType::Hard, None)
+                .map_err(|e| e.to_string())?;
+            repo.cleanup_state().map_err(|e| e.to_string())
+        }
+    }
+}
+
+// ============================================================================
+// Worktrees
+// ============================================================================
+
+/// A checkout of the repository; the main one or a linked worktree
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorktreeInfo {
+    /// Name under `.git/worktrees`, None for the main checkout
+    pub name: Option<String>,
+    pub path: String,
+    /// Checked-out branch, None when detached
+    pub branch: Option<String>,
+    pub head: Option<String>,
+    pub is_main: bool,
+    pub locked: bool,
+    /// The folder is gone, only the administrative files are left
+    pub prunable: bool,
+}
+
+/// Open the main repository, even when `repo_path` is a linked worktree
+fn open_main_repo(repo_path: &str) -> Result<Repository, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    if repo.is_worktree() {
+        // `.git/worktrees/<name>/commondir` points back at the main `.git`
+        let commondir =
+            std::fs::read_to_string(repo.path().join("commondir")).map_err(|e| e.to_string())?;
+        Repository::open(repo.path().join(commondir.trim())).map_err(|e| e.to_string())
+    } else {
+        Ok(repo)
+    }
+}
+
+fn worktree_checkout_info(repo: &Repository) -> (Option<String>, Option<String>) {
+    match repo.head() {
+        Ok(head) => (
+            head.is_branch()
+                .then(|| head.shorthand().map(|s| s.to_string()))
+                .flatten(),
+            head.target().map(|id| id.to_string()),
+        ),
+        Err(_) => (None, None),
+    }
+}
+
+fn worktree_info(repo: &Repository, name: &str) -> Result<WorktreeInfo, String> {
+    let worktree = repo.find_worktree(name).map_err(|e| e.to_string())?;
+    let prunable = worktree.validate().is_err();
+    let (branch, head) = if prunable {
+        (None, None)
+    } else {
+        Repository::open_from_worktree(&worktree)
+            .map(|wt_repo| worktree_checkout_info(&wt_repo))
+            .unwrap_or((None, None))
+    };
+    Ok(WorktreeInfo {
+        name: Some(name.to_string()),
+        path: worktree.path().to_string_lossy().to_string(),
+        branch,
+        head,
+        is_main: false,
+        locked: matches!(
+            worktree.is_locked(),
+            Ok(git2::WorktreeLockStatus::Locked(_))
+        ),
+        prunable,
+    })
+}
+
+/// List the main checkout followed by the linked worktrees
+pub fn list_worktrees(repo_path: &str) -> Result<Vec<WorktreeInfo>, String> {
+    let repo = open_main_repo(repo_path)?;
+    let mut result = Vec::new();
+
+    if let Some(workdir) = repo.workdir() {
+        let (branch, head) = worktree_checkout_info(&repo);
+        result.push(WorktreeInfo {
+            name: None,
+            path: workdir
+                .to_string_lossy()
+                .trim_end_matches(['/', '\\'])
+                .to_string(),
+            branch,
+            head,
+            is_main: true,
+            locked: false,
+            prunable: false,
+        });
+    }
+
+    let names = repo.worktrees().map_err(|e| e.to_string())?;
+    for name in names.iter().flatten() {
+        result.push(worktree_info(&repo, name)?);
+    }
+    Ok(result)
+}
+
+/// Check out `branch` into a new folder at `path`. With `start_point` the
+/// branch is created there first; a remote branch like "origin/2023" gets a
+/// local tracking branch of the same short name.
+pub fn add_worktree(
+    repo_path: &str,
+    path: &str,
+    branch: &str,
+    start_point: Option<&str>,
+) -> Result<WorktreeInfo, String> {
+    let repo = open_main_repo(repo_path)?;
+    let target = Path::new(path);
+    if target.exists()
+        && target
+            .read_dir()
+            .map(|mut d| d.next().is_some())
+            .unwrap_or(true)
+    {
+        return Err(format!("{} already exists and is not empty", path));
+    }
+
+    // The worktree's name (under .git/worktrees) follows the folder name
+    let base = target
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or("Invalid worktree path")?;
+    let existing = repo.worktrees().map_err(|e| e.to_string())?;
+    let mut name = base.clone();
+    let mut n = 1;
+    while existing.iter().flatten().any(|w| w == name) {
+        n += 1;
+        name = format!("{}{}", base, n);
+    }
+
+    // Whether the branch is made here, and goes again if the worktree can't be
+    let (mut local, created) = match start_point {
+        Some(rev) => {
+            let commit = repo
+                .revparse_single(rev)
+                .and_then(|o| o.peel_to_commit())
+                .map_err(|_| format!("{} not found", rev))?;
+            let local = repo
+                .branch(branch, &commit, false)
+                .map_err(|e| e.to_string())?;
+            (local, true)
+        }
+        None => match repo.find_branch(branch, git2::BranchType::Local) {
+            Ok(local) => (local, false),
+            Err(_) => {
+                let remote = repo
+                    .find_branch(branch, git2::BranchType::Remote)
+                    .map_err(|_| format!("Branch {} not found", branch))?;
+                let commit = remote.get().peel_to_commit().map_err(|e| e.to_string())?;
+                let short = branch.split_once('/').map(|(_, b)| b).unwrap_or(branch);
+                let mut local = repo
+                    .branch(short, &commit, false)
+                    .map_err(|e| e.to_string())?;
+                if let Err(e) = local.set_upstream(Some(branch)) {
+                    let _ = local.delete();
+                    return Err(e.to_string());
+                }
+                (local, true)
+            }
+        },
+    };
+
+    let added = {
+        let mut opts = git2::WorktreeAddOptions::new();
+        opts.reference(Some(local.get()));
+        // libgit2 wants to create the folder itself
+        let cleared = if target.exists() {
+            std::fs::remove_dir(target)
+        } else {
+            Ok(())
+        };
+        cleared.map_err(|e| e.to_string()).and_then(|_| {
+            repo.worktree(&name, target, Some(&opts))
+                .map_err(|e| e.to_string())
+        })
+    };
+    if let Err(e) = added {
+        if created {
+            if let Err(e) = local.delete() {
+                eprintln!("Failed to delete branch {}: {}", branch, e);
+            }
+        }
+        return Err(e);
+    }
+    worktree_info(&repo, &name)
+}
+
+/// Remove a linked worktree and its folder. Uncommitted changes or a lock
+/// block this unless `force` is set.
+pub fn remove_worktree(repo_path: &str, name: &str, force: bool) -> Result<(), String> {
+    let repo = open_main_repo(repo_path)?;
+    let worktree = repo.find_worktree(name).map_err(|e| e.to_string())?;
+
+    if !force {
+        if let Ok(git2::WorktreeLockStatus::Locked(reason)) = worktree.is_locked() {
+            return Err(format!(
+                "Worktree {} is locked{}",
+                name,
+                reason.map(|r| format!(": {}", r)).unwrap_or_default()
+            ));
+        }
+        if worktree.validate().is_ok() {
+            let wt_repo = Repository::open_from_worktree(&worktree).map_err(|e| e.to_string())?;
+            let mut opts = StatusOptions::new();
+            opts.include_untracked(true).include_ignored(false);
+            let dirty = wt_repo
+                .statuses(Some(&mut opts))
+                .map(|s| !s.is_empty())
+                .map_err(|e| format!("Can't check {} for uncommitted changes: {}", name, e))?;
+            if dirty {
+                return Err(format!("Worktree {} has uncommitted changes", name));
+            }
+        }
+    }
+
+    let mut opts = git2::WorktreePruneOptions::new();
+    opts.valid(true).locked(true).working_tree(true);
+    worktree.prune(Some(&mut opts)).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Archive
+// ============================================================================
+
+/// File format of a revision snapshot
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    #[default]
+    Zip,
+    Tar,
+    TarGz,
+}
+
+/// A file of the snapshot: path, git file mode and blob
+type ArchiveEntry = (String, i32, Oid);
+
+const FILEMODE_EXECUTABLE: i32 = 0o100755;
+const FILEMODE_LINK: i32 = 0o120000;
+
+fn is_export_ignored(repo: &Repository, path: &str) -> bool {
+    repo.get_attr(
+        Path::new(path),
+        "export-ignore",
+        git2::AttrCheckFlags::default(),
+    )
+    .map(|value| git2::AttrValue::from_string(value) == git2::AttrValue::True)
+    .unwrap_or(false)
+}
+
+fn archive_unix_mode(filemode: i32) -> u32 {
+    if filemode == FILEMODE_EXECUTABLE {
+        0o755
+    } else {
+        0o644
+    }
+}
+
+/// Write a clean snapshot of a revision's tree to `dest_path`, like
+/// `git archive`: no history, and paths marked `export-ignore` in
+/// .gitattributes are left out. Returns the number of files written.
+pub fn archive_commit(
+    repo_path: &str,
+    commit_id: &str,
+    dest_path: &str,
+    format: ArchiveFormat,
+) -> Result<usize, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let commit = repo
+        .revparse_single(commit_id)
+        .and_then(|o| o.peel_to_commit())
+        .map_err(|e| e.to_string())?;
+    let tree = commit.tree().map_err(|e| e.to_string())?;
+
+    let mut entries: Vec<ArchiveEntry> = Vec::new();
+    tree.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+        let path = format!("{}{}", root, entry.name().unwrap_or_default());
+        if is_export_ignored(&repo, &path) {
+            return git2::TreeWalkResult::Skip;
+        }
+        // Submodules (commit entries) are not part of the snapshot
+        if entry.kind() == Some(git2::ObjectType::Blob) {
+            entries.push((path, entry.filemode(), entry.id()));
+        }
+        git2::TreeWalkResult::Ok
+    })
+    .map_err(|e| e.to_string())?;
+
+    let file = std::fs::File::create(dest_path).map_err(|e| e.to_string())?;
+    let time = commit.time();
+    let result = match format {
+        ArchiveFormat::Zip => write_zip_archive(&repo, &entries, file, time),
+        ArchiveFormat::Tar => write_tar_archive(&repo, &entries, file, time).map(|_| ()),
+        ArchiveFormat::TarGz => {
+            let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            write_tar_archive(&repo, &entries, encoder, time)
+                .and_then(|encoder| encoder.finish().map(|_| ()).map_err(|e| e.to_string()))
+        }
+    };
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(dest_path);
+        return Err(e);
+    }
+    Ok(entries.len())
+}
+
+fn write_zip_archive(
+    repo: &Repository,
+    entries: &[ArchiveEntry],
+    file: std::fs::File,
+    time: git2::Time,
+) -> Result<(), String> {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    use chrono::{Datelike, Timelike};
+
+    // Zip stores the local time without a zone; use the committer's
+    let local =
+        chrono::DateTime::from_timestamp(time.seconds() + time.offset_minutes() as i64 * 60, 0)
+            .unwrap_or_default()
+            .naive_utc();
+    let modified = zip::DateTime::from_date_and_time(
+        local.year().clamp(1980, 2107) as u16,
+        local.month() as u8,
+        local.day() as u8,
+        local.hour() as u8,
+        local.minute() as u8,
+        local.second() as u8,
+    )
+    .unwrap_or_default();
+
+    let mut zip = zip::ZipWriter::new(file);
+    for (path, filemode, oid) in entries {
+        let blob = repo.find_blob(*oid).map_err(|e| e.to_string())?;
+        let options = SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .last_modified_time(modified)
+            .unix_permissions(archive_unix_mode(*filemode));
+        if *filemode == FILEMODE_LINK {
+            let target = String::from_utf8_lossy(blob.content()).to_string();
+            zip.add_symlink(path.as_str(), target, options)
+                .map_err(|e| e.to_string())?;
+        } else {
+            zip.start_file(path.as_str(), options)
+                .map_err(|e| e.to_string())?;
+            zip.write_all(blob.content()).map_err(|e| e.to_string())?;
+        }
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn write_tar_archive<W: std::io::Write>(
+    repo: &Repository,
+    entries: &[ArchiveEntry],
+    writer: W,
+    time: git2::Time,
+) -> Result<W, String> {
+    let mut tar = tar::Builder::new(writer);
+    for (path, filemode, oid) in entries {
+        let blob = repo.find_blob(*oid).map_err(|e| e.to_string())?;
+        let mut header = tar::Header::new_gnu();
+        header.set_mtime(time.seconds().max(0) as u64);
+        header.set_mode(archive_unix_mode(*filemode));
+        if *filemode == FILEMODE_LINK {
+            let target = String::from_utf8_lossy(blob.content()).to_string();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            tar.append_link(&mut header, path, target)
+                .map_err(|e| e.to_string())?;
+        } else {
+            header.set_size(blob.content().len() as u64);
+            tar.append_data(&mut header, path, blob.content())
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    tar.into_inner().map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Commit Tree Browser
+// ============================================================================
+
+/// Kind of an entry in a commit's tree
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TreeEntryKind {
+    File,
+    Directory,
+    Symlink,
+    Submodule,
+}
+
+/// A file or folder as it was at some revision
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CommitTreeEntry {
+    pub name: String,
+    /// Path from the repository root
+    pub path: String,
+    pub kind: TreeEntryKind,
+    /// Size in bytes of files and symlinks
+    pub size: Option<usize>,
+    /// Blob, tree or (for submodules) commit id
+    pub id: String,
+}
+
+/// Entries of the folder `path` ("" for the root) at a revision, folders
+/// first
+pub fn list_tree_at_commit(
+    repo_path: &str,
+    commit_id: &str,
+    path: &str,
+) -> Result<Vec<CommitTreeEntry>, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let commit = repo
+        .revparse_single(commit_id)
+        .and_then(|o| o.peel_to_commit())
+        .map_err(|_| format!("Commit {} not found", commit_id))?;
+    let root = commit.tree().map_err(|e| e.to_string())?;
+    let folder = path.trim_matches('/');
+    let tree = if folder.is_empty() {
+        root
+    } else {
+        root.get_path(Path::new(folder))
+            .and_then(|entry| entry.to_object(&repo))
+            .and_then(|object| object.peel_to_tree())
+            .map_err(|_| format!("{} is not a folder at {}", folder, commit_id))?
+    };
+    let odb = repo.odb().map_err(|e| e.to_string())?;
+
+    let mut entries: Vec<CommitTreeEntry> = tree
+        .iter()
+        .map(|entry| {
+            let name = String::from_utf8_lossy(entry.name_bytes()).to_string();
+            let kind = match entry.kind() {
+                Some(git2::ObjectType::Tree) => TreeEntryKind::Directory,
+                Some(git2::ObjectType::Commit) => TreeEntryKind::Submodule,
+                _ if entry.filemode() == FILEMODE_LINK => TreeEntryKind::Symlink,
+                _ => TreeEntryKind::File,
+            };
+            let size = match kind {
+                TreeEntryKind::File | TreeEntryKind::Symlink => {
+                    odb.read_header(entry.id()).ok().map(|(size, _)| size)
+                }
+                _ => None,
+            };
+            CommitTreeEntry {
+                path: if folder.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}/{}", folder, name)
+                },
+                name,
+                kind,
+                size,
+                id: entry.id().to_string(),
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        (a.kind != TreeEntryKind::Directory)
+            .cmp(&(b.kind != TreeEntryKind::Directory))
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    Ok(entries)
+}
+
+/// The working directory of a repository, and every file at a revision:
+/// its path from the repository root and, when `read` accepts that path,
+/// its text
+#[allow(clippy::type_complexity)]
+pub fn read_files_at_commit(
+    repo_path: &str,
+    commit_id: &str,
+    read: impl Fn(&str) -> bool,
+) -> Result<(std::path::PathBuf, Vec<(String, Option<String>)>), String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let workdir = repo
+        .workdir()
+        .ok_or("Repository has no working directory")?;
+    let commit = repo
+        .revparse_single(commit_id)
+        .and_then(|o| o.peel_to_commit())
+        .map_err(|_| format!("Commit {} not found", commit_id))?;
+    let tree = commit.tree().map_err(|e| e.to_string())?;
+
+    let mut files = Vec::new();
+    tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+        if entry.kind() != Some(git2::ObjectType::Blob) {
+            return git2::TreeWalkResult::Ok;
+        }
+        let path = format!("{}{}", dir, String::from_utf8_lossy(entry.name_bytes()));
+        let content = read(&path)
+            .then(|| repo.find_blob(entry.id()).ok())
+            .flatten()
+            .map(|blob| String::from_utf8_lossy(blob.content()).to_string());
+        files.push((path, content));
+        git2::TreeWalkResult::Ok
+    })
+    .map_err(|e| e.to_string())?;
+
+    Ok((workdir.to_path_buf(), files))
+}
+
+// ============================================================================
+// Repository Statistics
+// ============================================================================
+
+/// Width of the activity buckets
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsInterval {
+    Day,
+    #[default]
+    Week,
+    Month,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct RepoStatsSettings {
+    pub interval: StatsInterval,
+    /// Only count commits from this Unix time on
+    pub since: Option<i64>,
+    /// Length of the most-changed files list
+    pub max_files: usize,
+}
+
+impl Default for RepoStatsSettings {
+    fn default() -> Self {
+        RepoStatsSettings {
+            interval: StatsInterval::Week,
+            since: None,
+            max_files: 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AuthorStats {
+    /// Name used in the author's latest commit
+    pub name: String,
+    pub email: String,
+    pub commits: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ActivityBucket {
+    /// Unix time (UTC) the bucket starts at
+    pub start: i64,
+    pub commits: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FileChangeStats {
+    pub path: String,
+    /// Commits that changed the file
+    pub commits: usize,
+}
+
+/// Who contributed, when, and to what, for activity charts
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct RepoStats {
+    pub total_commits: usize,
+    /// Most commits first
+    pub authors: Vec<AuthorStats>,
+    /// Oldest first, including empty buckets
+    pub activity: Vec<ActivityBucket>,
+    /// Most changed first; merge commits aren't counted
+    pub top_files: Vec<FileChangeStats>,
+}
+
+/// Start of the bucket containing `time`: midnight, Monday or the first of
+/// the month (UTC)
+fn bucket_start(time: i64, interval: StatsInterval) -> i64 {
+    use chrono::{Datelike, TimeZone};
+    let date = match chrono::Utc.timestamp_opt(time, 0).single() {
+        Some(date) => date.date_naive(),
+        None => return time,
+    };
+    let start = match interval {
+        StatsInterval::Day => date,
+        StatsInterval::Week => {
+            date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64)
+        }
+        StatsInterval::Month => date.with_day(1).unwrap_or(date),
+    };
+    start
+        .and_hms_opt(0, 0, 0)
+        .map(|t| t.and_utc().timestamp())
+        .unwrap_or(time)
+}
+
+/// Start of the bucket after the one starting at `start`
+fn next_bucket(start: i64, interval: StatsInterval) -> i64 {
+    match interval {
+        StatsInterval::Day => start + 86_400,
+        StatsInterval::Week => start + 7 * 86_400,
+        // Somewhere in the next month, then back to its first day
+        StatsInterval::Month => bucket_start(start + 32 * 86_400, interval),
+    }
+}
+
+/// Commit counts per author and over time, and the most changed files,
+/// for the history of HEAD
+pub fn get_repo_stats(repo_path: &str, settings: &RepoStatsSettings) -> Result<RepoStats, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    if repo.head().is_err() {
+        return Ok(RepoStats::default());
+    }
+    let mut revwalk = repo.revwalk().map_err(|e| e.to_string())?;
+    revwalk.push_head().map_err(|e| e.to_string())?;
+    revwalk
+        .set_sorting(git2::Sort::TIME)
+        .map_err(|e| e.to_string())?;
+
+    let mut stats = RepoStats::default();
+    // Keyed by lowercase email; the walk is newest first, so the first name
+    // seen is the latest
+    let mut authors: std::collections::HashMap<String, AuthorStats> =
+        std::collections::HashMap::new();
+    let mut buckets: std::collections::BTreeMap<i64, usize> = std::collections::BTreeMap::new();
+    let mut files: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+
+    for oid in revwalk {
+        let commit = repo
+            .find_commit(oid.map_err(|e| e.to_string())?)
+            .map_err(|e| e.to_string())?;
+        let time = commit.time().seconds();
+        if settings.since.is_some_and(|since| time < since) {
+            continue;
+        }
+        stats.total_commits += 1;
+
+        let author = commit.author();
+        let email = author.email().unwrap_or_default().to_string();
+        authors
+            .entry(email.to_lowercase())
+            .or_insert_with(|| AuthorStats {
+                name: author.name().unwrap_or("Unknown").to_string(),
+                email,
+                commits: 0,
+            })
+            .commits += 1;
+        *buckets
+            .entry(bucket_start(time, settings.interval))
+            .or_default() += 1;
+
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        let tree = commit.tree().map_err(|e| e.to_string())?;
+        let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+        let diff = repo
+            .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)
+            .map_err(|e| e.to_string())?;
+        for delta in diff.deltas() {
+            if let Some(path) = delta.new_file().path().or_else(|| delta.old_file().path()) {
+                *files.entry(path.to_string_lossy().to_string()).or_default() += 1;
+            }
+        }
+    }
+
+    stats.authors = authors.into_values().collect();
+    stats
+        .authors
+        .sort_by(|a, b| b.commits.cmp(&a.commits).then_with(|| a.name.cmp(&b.name)));
+
+    if let (Some(&first), Some(&last)) = (buckets.keys().next(), buckets.keys().next_back()) {
+        let mut start = first;
+        while start <= last {
+            stats.activity.push(ActivityBucket {
+                start,
+                commits: buckets.get(&start).copied().unwrap_or(0),
+            });
+            start = next_bucket(start, settings.interval);
+        }
+    }
+
+    let mut top_files: Vec<FileChangeStats> = files
+        .into_iter()
+        .map(|(path, commits)| FileChangeStats { path, commits })
+        .collect();
+    top_files.sort_by(|a, b| b.commits.cmp(&a.commits).then_with(|| a.path.cmp(&b.path)));
+    top_files.truncate(settings.max_files);
+    stats.top_files = top_files;
+
+    Ok(stats)
+}
+
 // ============================================================================
 // Side-by-side Diff (Enhanced)
 // ============================================================================
@@ -1522,3 +4380,237 @@ pub fn get_side_by_side_diff(
 
     Ok(generate_side_by_side_diff(&old_content, &new_content))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("datatex-{}-{}", name, uuid::Uuid::new_v4()))
+    }
+
+    fn commit_file(repo: &Repository, name: &str, content: &str) -> Oid {
+        std::fs::write(repo.workdir().unwrap().join(name), content).unwrap();
+        let mut index = repo.index().unwrap();
+        // Pick up what the functions under test wrote through their own handle
+        index.read(false).unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, content, &tree, &parents)
+            .unwrap()
+    }
+
+    fn repo_path(repo: &Repository) -> String {
+        repo.workdir().unwrap().to_string_lossy().to_string()
+    }
+
+    /// A bare "origin" under `root`, a repository "a" that pushed one
+    /// commit to it and a clone "b"; returns them with the branch name
+    fn with_remote(root: &Path) -> (Repository, Repository, String) {
+        let bare = root.join("origin.git");
+        Repository::init_bare(&bare).unwrap();
+        let a = Repository::init(root.join("a")).unwrap();
+        commit_file(&a, "main.tex", "one");
+        a.remote("origin", &bare.to_string_lossy()).unwrap();
+        let branch = a.head().unwrap().shorthand().unwrap().to_string();
+        push_to_remote(
+            &repo_path(&a),
+            "origin",
+            &branch,
+            &PushSettings::default(),
+            None,
+        )
+        .unwrap();
+        let b = Repository::clone(&bare.to_string_lossy(), root.join("b")).unwrap();
+        (a, b, branch)
+    }
+
+    #[test]
+    fn test_force_with_lease_refuses_unseen_commits() {
+        let root = temp_path("lease");
+        let (a, b, branch) = with_remote(&root);
+        let push = |repo: &Repository, force_with_lease| {
+            let settings = PushSettings {
+                force_with_lease,
+                ..Default::default()
+            };
+            push_to_remote(&repo_path(repo), "origin", &branch, &settings, None)
+        };
+
+        commit_file(&b, "b.tex", "from b");
+        assert!(push(&b, false).unwrap().refs.iter().all(|r| r.ok));
+        commit_file(&a, "a.tex", "from a");
+        let err = push(&a, true).unwrap_err();
+        assert!(err.contains("has changed since the last fetch"), "{}", err);
+
+        // Once the remote commit has been seen, the push goes through
+        fetch_remote(&repo_path(&a), "origin", None).unwrap();
+        assert!(push(&a, true).unwrap().refs.iter().all(|r| r.ok));
+        let remote = Repository::open(root.join("origin.git")).unwrap();
+        assert_eq!(
+            remote
+                .refname_to_id(&format!("refs/heads/{}", branch))
+                .unwrap(),
+            a.head().unwrap().target().unwrap()
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_pull_outcomes() {
+        let root = temp_path("pull");
+        let (a, b, branch) = with_remote(&root);
+        let pull = |repo: &Repository, strategy| {
+            pull_from_remote(&repo_path(repo), "origin", &branch, strategy, None).unwrap()
+        };
+        let push = |repo: &Repository| {
+            push_to_remote(
+                &repo_path(repo),
+                "origin",
+                &branch,
+                &PushSettings::default(),
+                None,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(pull(&a, PullStrategy::Merge).outcome, PullOutcome::UpToDate);
+
+        let upstream = commit_file(&b, "b.tex", "two");
+        push(&b);
+        let result = pull(&a, PullStrategy::Merge);
+        assert_eq!(result.outcome, PullOutcome::FastForward);
+        assert_eq!(result.head, Some(upstream.to_string()));
+        assert!(root.join("a/b.tex").is_file());
+
+        // Diverged: a merge commit joins both sides
+        let upstream = commit_file(&b, "b.tex", "three");
+        push(&b);
+        let local = commit_file(&a, "a.tex", "four");
+        let result = pull(&a, PullStrategy::Merge);
+        assert_eq!(result.outcome, PullOutcome::Merged);
+        let merge = a.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(merge.parent_ids().collect::<Vec<_>>(), [local, upstream]);
+        push(&a);
+
+        // Diverged again: the local commit is replayed on top
+        assert_eq!(
+            pull(&b, PullStrategy::Merge).outcome,
+            PullOutcome::FastForward
+        );
+        let upstream = commit_file(&b, "b.tex", "five");
+        push(&b);
+        commit_file(&a, "a.tex", "six");
+        let result = pull(&a, PullStrategy::Rebase);
+        assert_eq!(result.outcome, PullOutcome::Rebased);
+        assert_eq!(result.rebased_commits, 1);
+        let head = a.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.message(), Some("six"));
+        assert_eq!(head.parent_ids().collect::<Vec<_>>(), [upstream]);
+        assert_eq!(
+            std::fs::read_to_string(root.join("a/b.tex")).unwrap(),
+            "five"
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_merge_pull_refuses_staged_changes() {
+        let root = temp_path("pull-staged");
+        let (a, b, branch) = with_remote(&root);
+        commit_file(&b, "b.tex", "two");
+        push_to_remote(
+            &repo_path(&b),
+            "origin",
+            &branch,
+            &PushSettings::default(),
+            None,
+        )
+        .unwrap();
+        let local = commit_file(&a, "a.tex", "three");
+        std::fs::write(root.join("a/a.tex"), "staged").unwrap();
+        stage_file(&repo_path(&a), "a.tex").unwrap();
+
+        let err = pull_from_remote(&repo_path(&a), "origin", &branch, PullStrategy::Merge, None)
+            .unwrap_err();
+        assert!(err.contains("staged changes"), "{}", err);
+        assert_eq!(a.head().unwrap().target(), Some(local));
+        assert_eq!(a.state(), git2::RepositoryState::Clean);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_switch_branch_stashes_changes() {
+        let dir = temp_path("switch");
+        let repo = Repository::init(&dir).unwrap();
+        let path = dir.to_string_lossy().to_string();
+        let file = dir.join("main.tex");
+        commit_file(&repo, "main.tex", "1\n2\n3\n4\n5\n");
+        let main = repo.head().unwrap().shorthand().unwrap().to_string();
+        create_branch(&path, "feature").unwrap();
+        commit_file(&repo, "main.tex", "one\n2\n3\n4\n5\n");
+
+        // A change the checkout would overwrite is reported, not touched
+        std::fs::write(&file, "one\n2\n3\n4\nfive\n").unwrap();
+        let result = switch_branch(&path, "feature", false).unwrap();
+        assert!(!result.switched);
+        assert_eq!(result.conflicts, ["main.tex"]);
+
+        // Stashed, switched and re-applied cleanly
+        let result = switch_branch(&path, "feature", true).unwrap();
+        assert!(result.switched && result.stashed);
+        assert!(result.stash_conflicts.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "1\n2\n3\n4\nfive\n"
+        );
+        assert!(list_stashes(&path).unwrap().is_empty());
+
+        // A change that clashes with the other branch is kept in the stash
+        discard_changes(&path, "main.tex").unwrap();
+        std::fs::write(&file, "uno\n2\n3\n4\n5\n").unwrap();
+        let result = switch_branch(&path, &main, true).unwrap();
+        assert!(result.switched);
+        assert_eq!(result.stash_conflicts, ["main.tex"]);
+        assert_eq!(list_stashes(&path).unwrap().len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_add_worktree_failure_deletes_new_branch() {
+        let dir = temp_path("worktree");
+        let repo = Repository::init(&dir).unwrap();
+        commit_file(&repo, "main.tex", "one");
+        let path = dir.to_string_lossy().to_string();
+
+        // The folder can't be created under a file
+        let blocker = temp_path("worktree-blocker");
+        std::fs::write(&blocker, "x").unwrap();
+        let target = blocker.join("wt").to_string_lossy().to_string();
+        assert!(add_worktree(&path, &target, "feature", Some("HEAD")).is_err());
+        assert!(repo
+            .find_branch("feature", git2::BranchType::Local)
+            .is_err());
+
+        let target = temp_path("worktree-ok");
+        let info = add_worktree(&path, &target.to_string_lossy(), "feature", Some("HEAD")).unwrap();
+        assert!(target.join("main.tex").is_file());
+        std::fs::write(target.join("main.tex"), "changed").unwrap();
+        let name = info.name.unwrap();
+        assert!(remove_worktree(&path, &name, false).is_err());
+        remove_worktree(&path, &name, true).unwrap();
+        assert!(!target.exists());
+
+        std::fs::remove_file(&blocker).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
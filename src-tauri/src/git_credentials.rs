@@ -0,0 +1,91 @@
+//! Interactive Git Credentials
+//!
+//! Remote operations run on a blocking thread. When they need credentials
+//! nobody else can supply, `GitCredentialBroker::prompt` emits a
+//! `git-credentials-required` event and waits until the frontend answers
+//! through `git_provide_credentials_cmd` (or the prompt times out).
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::git::{CredentialPrompt, CredentialRequest, CredentialResponse};
+
+/// How long an unanswered prompt blocks the operation
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Payload of the `git-credentials-required` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CredentialsRequired<'a> {
+    request_id: u64,
+    #[serde(flatten)]
+    request: &'a CredentialRequest,
+}
+
+/// Pending credential prompts, keyed by request id
+#[derive(Default)]
+pub struct GitCredentialBroker {
+    next_id: AtomicU64,
+    pending: Mutex<HashMap<u64, mpsc::Sender<Option<CredentialResponse>>>>,
+}
+
+impl GitCredentialBroker {
+    /// Ask the frontend and block until it answers; None if cancelled
+    pub fn prompt(
+        &self,
+        app: &AppHandle,
+        request: &CredentialRequest,
+    ) -> Option<CredentialResponse> {
+        let request_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::channel();
+        self.pending.lock().ok()?.insert(request_id, tx);
+
+        let _ = app.emit(
+            "git-credentials-required",
+            CredentialsRequired {
+                request_id,
+                request,
+            },
+        );
+        let response = rx.recv_timeout(PROMPT_TIMEOUT).ok().flatten();
+
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&request_id);
+        }
+        response
+    }
+
+    /// Deliver the user's answer (None to cancel) to a waiting prompt
+    pub fn respond(
+        &self,
+        request_id: u64,
+        response: Option<CredentialResponse>,
+    ) -> Result<(), String> {
+        let pending = self.pending.lock().map_err(|e| e.to_string())?;
+        let tx = pending
+            .get(&request_id)
+            .ok_or_else(|| format!("No pending credential request {}", request_id))?;
+        tx.send(response).map_err(|e| e.to_string())
+    }
+}
+
+/// Run a remote operation on a blocking thread, prompting through the
+/// frontend whenever it needs credentials
+pub async fn run_with_prompt<T, F>(app: AppHandle, operation: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(CredentialPrompt) -> Result<T, String> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(move || {
+        let broker = app.state::<GitCredentialBroker>();
+        let prompt = |request: &CredentialRequest| broker.prompt(&app, request);
+        operation(&prompt)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
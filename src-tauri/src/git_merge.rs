@@ -0,0 +1,218 @@
+//! Three-way Merge
+//!
+//! Line-based diff3 merge of a conflicted file. Unlike libgit2's merged
+//! text with `<<<<<<<` markers, the result is a list of regions so the
+//! conflict editor can show and resolve each conflict on its own.
+
+use serde::{Deserialize, Serialize};
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+
+/// Which side a resolved region's lines come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeSource {
+    /// Same on all three sides
+    Unchanged,
+    /// Changed only in ours
+    Ours,
+    /// Changed only in theirs
+    Theirs,
+    /// Changed the same way on both sides
+    Both,
+}
+
+/// A run of lines in the merged document. Lines keep their line endings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MergeRegion {
+    Resolved {
+        source: MergeSource,
+        lines: Vec<String>,
+    },
+    Conflict {
+        base: Vec<String>,
+        ours: Vec<String>,
+        theirs: Vec<String>,
+    },
+}
+
+/// Result of a three-way merge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreeWayMerge {
+    pub regions: Vec<MergeRegion>,
+    pub conflict_count: usize,
+    /// The merged document, with git-style markers around conflicts
+    pub merged: String,
+}
+
+/// For each base line, the index of the line it is matched with in `other`
+fn match_lines(base: &[&str], other: &[&str]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; base.len()];
+    for op in capture_diff_slices(Algorithm::Myers, base, other) {
+        if let DiffOp::Equal {
+            old_index,
+            new_index,
+            len,
+        } = op
+        {
+            for offset in 0..len {
+                matches[old_index + offset] = Some(new_index + offset);
+            }
+        }
+    }
+    matches
+}
+
+fn to_owned(lines: &[&str]) -> Vec<String> {
+    lines.iter().map(|l| l.to_string()).collect()
+}
+
+fn push_resolved(regions: &mut Vec<MergeRegion>, source: MergeSource, new_lines: &[&str]) {
+    if new_lines.is_empty() {
+        return;
+    }
+    if let Some(MergeRegion::Resolved {
+        source: last,
+        lines,
+    }) = regions.last_mut()
+    {
+        if *last == source {
+            lines.extend(to_owned(new_lines));
+            return;
+        }
+    }
+    regions.push(MergeRegion::Resolved {
+        source,
+        lines: to_owned(new_lines),
+    });
+}
+
+/// Append one side of a conflict, making sure the marker after it starts
+/// on its own line
+fn push_side(merged: &mut String, lines: &[String]) {
+    merged.extend(lines.iter().map(String::as_str));
+    if !merged.is_empty() && !merged.ends_with('\n') {
+        merged.push('\n');
+    }
+}
+
+/// Merge `ours` and `theirs`, both derived from `base`
+pub fn three_way_merge(base: &str, ours: &str, theirs: &str) -> ThreeWayMerge {
+    let base: Vec<&str> = base.split_inclusive('\n').collect();
+    let ours: Vec<&str> = ours.split_inclusive('\n').collect();
+    let theirs: Vec<&str> = theirs.split_inclusive('\n').collect();
+    let in_ours = match_lines(&base, &ours);
+    let in_theirs = match_lines(&base, &theirs);
+
+    let mut regions = Vec::new();
+    let (mut i, mut j, mut k) = (0, 0, 0);
+    while i < base.len() || j < ours.len() || k < theirs.len() {
+        // A base line both sides kept, with nothing inserted before it
+        if i < base.len() && in_ours[i] == Some(j) && in_theirs[i] == Some(k) {
+            push_resolved(&mut regions, MergeSource::Unchanged, &base[i..=i]);
+            i += 1;
+            j += 1;
+            k += 1;
+            continue;
+        }
+
+        // Otherwise the chunk runs up to the next line both sides kept
+        let end = (i..base.len())
+            .find(|&n| in_ours[n].is_some() && in_theirs[n].is_some())
+            .unwrap_or(base.len());
+        let ours_end = in_ours.get(end).copied().flatten().unwrap_or(ours.len());
+        let theirs_end = in_theirs
+            .get(end)
+            .copied()
+            .flatten()
+            .unwrap_or(theirs.len());
+        let (b, o, t) = (&base[i..end], &ours[j..ours_end], &theirs[k..theirs_end]);
+
+        if o == b {
+            push_resolved(&mut regions, MergeSource::Theirs, t);
+        } else if t == b {
+            push_resolved(&mut regions, MergeSource::Ours, o);
+        } else if o == t {
+            push_resolved(&mut regions, MergeSource::Both, o);
+        } else {
+            regions.push(MergeRegion::Conflict {
+                base: to_owned(b),
+                ours: to_owned(o),
+                theirs: to_owned(t),
+            });
+        }
+        i = end;
+        j = ours_end;
+        k = theirs_end;
+    }
+
+    let mut merged = String::new();
+    let mut conflict_count = 0;
+    for region in &regions {
+        match region {
+            MergeRegion::Resolved { lines, .. } => merged.extend(lines.iter().map(String::as_str)),
+            MergeRegion::Conflict { ours, theirs, .. } => {
+                conflict_count += 1;
+                merged.push_str("<<<<<<< ours\n");
+                push_side(&mut merged, ours);
+                merged.push_str("=======\n");
+                push_side(&mut merged, theirs);
+                merged.push_str(">>>>>>> theirs\n");
+            }
+        }
+    }
+
+    ThreeWayMerge {
+        regions,
+        conflict_count,
+        merged,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_merge() {
+        let base = "a\nb\nc\nd\ne\n";
+        let ours = "A\nb\nc\nd\ne\n";
+        let theirs = "a\nb\nc\nd\nE\nf\n";
+        let result = three_way_merge(base, ours, theirs);
+        assert_eq!(result.conflict_count, 0);
+        assert_eq!(result.merged, "A\nb\nc\nd\nE\nf\n");
+        assert!(matches!(
+            result.regions[0],
+            MergeRegion::Resolved {
+                source: MergeSource::Ours,
+                ..
+            }
+        ));
+
+        let result = three_way_merge(base, "a\nsame\nc\nd\ne\n", "a\nsame\nc\nd\ne\n");
+        assert_eq!(result.conflict_count, 0);
+        assert_eq!(result.merged, "a\nsame\nc\nd\ne\n");
+    }
+
+    #[test]
+    fn test_conflict() {
+        let base = "a\nb\nc\n";
+        let ours = "a\nours\nc\n";
+        let theirs = "a\ntheirs\nc";
+        let result = three_way_merge(base, ours, theirs);
+        assert_eq!(result.conflict_count, 1);
+        assert_eq!(
+            result.regions[1],
+            MergeRegion::Conflict {
+                base: vec!["b\n".to_string(), "c\n".to_string()],
+                ours: vec!["ours\n".to_string(), "c\n".to_string()],
+                theirs: vec!["theirs\n".to_string(), "c".to_string()],
+            }
+        );
+        // The marker after a side without a final newline starts a new line
+        assert_eq!(
+            result.merged,
+            "a\n<<<<<<< ours\nours\nc\n=======\ntheirs\nc\n>>>>>>> theirs\n"
+        );
+    }
+}
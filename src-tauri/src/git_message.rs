@@ -0,0 +1,288 @@
+//! Commit Message Templates and Rules
+//!
+//! The template comes from `commit.template`, or else a `.gitmessage` file
+//! at the root of the project so it can be shared with the repository.
+//! Rules are kept in the repository config under `datatex.commit.*` and
+//! checked before every commit.
+
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// File at the project root used when `commit.template` is not set
+const PROJECT_TEMPLATE: &str = ".gitmessage";
+
+/// What a commit message has to look like
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommitMessageRules {
+    /// Longest summary line before a warning, 0 for no limit
+    pub max_summary_length: usize,
+    /// The summary must be followed by a blank line
+    pub require_blank_line: bool,
+    /// Allowed conventional-commit types ("feat", "fix", ...); empty to
+    /// accept any summary
+    pub conventional_types: Vec<String>,
+}
+
+impl Default for CommitMessageRules {
+    fn default() -> Self {
+        CommitMessageRules {
+            max_summary_length: 72,
+            require_blank_line: true,
+            conventional_types: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueSeverity {
+    /// Blocks the commit
+    Error,
+    Warning,
+}
+
+/// A rule the message breaks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommitMessageIssue {
+    /// 1-based line of the cleaned-up message
+    pub line: usize,
+    pub severity: IssueSeverity,
+    pub message: String,
+}
+
+/// Drop `#` comment lines and surrounding blank lines, like git does with
+/// a message written in its editor
+pub fn clean_message(message: &str) -> String {
+    let lines: Vec<&str> = message
+        .lines()
+        .filter(|l| !l.starts_with('#'))
+        .map(str::trim_end)
+        .collect();
+    let text = lines.join("\n");
+    let text = text.trim_matches('\n');
+    if text.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n", text)
+    }
+}
+
+/// Check a message against the rules
+pub fn validate(message: &str, rules: &CommitMessageRules) -> Vec<CommitMessageIssue> {
+    let cleaned = clean_message(message);
+    let lines: Vec<&str> = cleaned.lines().collect();
+    let mut issues = Vec::new();
+    let issue = |line, severity, message: String| CommitMessageIssue {
+        line,
+        severity,
+        message,
+    };
+
+    let Some(summary) = lines.first() else {
+        issues.push(issue(
+            1,
+            IssueSeverity::Error,
+            "The commit message is empty".to_string(),
+        ));
+        return issues;
+    };
+
+    let length = summary.chars().count();
+    if rules.max_summary_length > 0 && length > rules.max_summary_length {
+        issues.push(issue(
+            1,
+            IssueSeverity::Warning,
+            format!(
+                "Summary is {} characters long (limit {})",
+                length, rules.max_summary_length
+            ),
+        ));
+    }
+
+    if rules.require_blank_line && lines.len() > 1 && !lines[1].trim().is_empty() {
+        issues.push(issue(
+            2,
+            IssueSeverity::Error,
+            "Leave a blank line between the summary and the body".to_string(),
+        ));
+    }
+
+    if !rules.conventional_types.is_empty() {
+        match conventional_type(summary) {
+            Some(kind) if rules.conventional_types.iter().any(|t| t == kind) => {}
+            Some(kind) => issues.push(issue(
+                1,
+                IssueSeverity::Error,
+                format!(
+                    "Unknown type \"{}\" (expected one of: {})",
+                    kind,
+                    rules.conventional_types.join(", ")
+                ),
+            )),
+            None => issues.push(issue(
+                1,
+                IssueSeverity::Error,
+                format!(
+                    "Summary must look like \"type(scope): description\" with a type of: {}",
+                    rules.conventional_types.join(", ")
+                ),
+            )),
+        }
+    }
+
+    issues
+}
+
+/// Type of a conventional-commit summary: `type(scope)!: description`
+fn conventional_type(summary: &str) -> Option<&str> {
+    let (head, description) = summary.split_once(": ")?;
+    if description.trim().is_empty() {
+        return None;
+    }
+    let head = head.strip_suffix('!').unwrap_or(head);
+    let kind = match head.split_once('(') {
+        Some((kind, scope)) => {
+            scope.strip_suffix(')').filter(|s| !s.is_empty())?;
+            kind
+        }
+        None => head,
+    };
+    (!kind.is_empty() && kind.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+        .then_some(kind)
+}
+
+/// Rules from the repository config, defaults for anything unset
+pub fn load_rules(repo: &Repository) -> CommitMessageRules {
+    let defaults = CommitMessageRules::default();
+    let Ok(config) = repo.config() else {
+        return defaults;
+    };
+    CommitMessageRules {
+        max_summary_length: config
+            .get_i64("datatex.commit.maxSummaryLength")
+            .map(|n| n.max(0) as usize)
+            .unwrap_or(defaults.max_summary_length),
+        require_blank_line: config
+            .get_bool("datatex.commit.requireBlankLine")
+            .unwrap_or(defaults.require_blank_line),
+        conventional_types: config
+            .get_string("datatex.commit.types")
+            .map(|types| {
+                types
+                    .split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect()
+            })
+            .unwrap_or(defaults.conventional_types),
+    }
+}
+
+/// Rules of the repository at `repo_path`
+pub fn get_rules(repo_path: &str) -> Result<CommitMessageRules, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    Ok(load_rules(&repo))
+}
+
+/// Store the rules in the repository's own config
+pub fn set_rules(repo_path: &str, rules: &CommitMessageRules) -> Result<(), String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let mut config = repo
+        .config()
+        .and_then(|c| c.open_level(git2::ConfigLevel::Local))
+        .map_err(|e| e.to_string())?;
+    config
+        .set_i64(
+            "datatex.commit.maxSummaryLength",
+            rules.max_summary_length as i64,
+        )
+        .and_then(|_| config.set_bool("datatex.commit.requireBlankLine", rules.require_blank_line))
+        .and_then(|_| config.set_str("datatex.commit.types", &rules.conventional_types.join(",")))
+        .map_err(|e| e.to_string())
+}
+
+/// The commit template, if the repository has one
+pub fn get_commit_template(repo_path: &str) -> Result<Option<String>, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let configured = repo
+        .config()
+        .ok()
+        .and_then(|c| c.get_path("commit.template").ok());
+    let path = match configured {
+        // A relative path is relative to the working tree, like git
+        Some(path) if path.is_relative() => repo.workdir().map(|w| w.join(path)),
+        Some(path) => Some(path),
+        None => repo
+            .workdir()
+            .map(|w| w.join(PROJECT_TEMPLATE))
+            .filter(|p| p.is_file()),
+    };
+    match path {
+        Some(path) => read_template(&path).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn read_template(path: &Path) -> Result<String, String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read commit template {}: {}", path.display(), e))
+}
+
+/// Check a message against the repository's rules
+pub fn validate_message(repo_path: &str, message: &str) -> Result<Vec<CommitMessageIssue>, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    Ok(validate(message, &load_rules(&repo)))
+}
+
+/// The cleaned-up message to commit, or the rules it breaks
+pub fn prepare_message(repo: &Repository, message: &str) -> Result<String, String> {
+    let errors: Vec<String> = validate(message, &load_rules(repo))
+        .into_iter()
+        .filter(|i| i.severity == IssueSeverity::Error)
+        .map(|i| i.message)
+        .collect();
+    if errors.is_empty() {
+        Ok(clean_message(message))
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_message() {
+        let message = "\n# Summary\nAdd exam 3  \n\n# Body\nCovers limits\n\n";
+        assert_eq!(clean_message(message), "Add exam 3\n\nCovers limits\n");
+        assert_eq!(clean_message("# only comments\n"), "");
+    }
+
+    #[test]
+    fn test_validate() {
+        let rules = CommitMessageRules::default();
+        assert!(validate("Add exam 3\n\nBody", &rules).is_empty());
+        assert_eq!(
+            validate("# template\n", &rules)[0].severity,
+            IssueSeverity::Error
+        );
+
+        let issues = validate(&format!("{}\nno blank line", "x".repeat(80)), &rules);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].severity, IssueSeverity::Warning);
+        assert_eq!(issues[1].line, 2);
+
+        let rules = CommitMessageRules {
+            conventional_types: vec!["feat".to_string(), "fix".to_string()],
+            ..Default::default()
+        };
+        assert!(validate("feat(algebra)!: add exam 3", &rules).is_empty());
+        assert!(validate("fix: typo", &rules).is_empty());
+        assert_eq!(validate("docs: typo", &rules).len(), 1);
+        assert_eq!(validate("Add exam 3", &rules).len(), 1);
+        assert_eq!(validate("feat(): add", &rules).len(), 1);
+    }
+}
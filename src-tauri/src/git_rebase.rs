@@ -0,0 +1,486 @@
+//! Interactive Rebase
+//!
+//! libgit2's rebase only replays commits one by one, so the plan (pick,
+//! reword, squash, fixup, drop, in any order) is executed here by
+//! cherry-picking each commit onto a detached HEAD. The branch itself only
+//! moves once every step is done, so aborting just checks it out again.
+//! Progress is kept in `.git/datatex-rebase.json` from the first step until
+//! the branch has moved, so a rebase that stops on conflicts or errors can
+//! always be continued or aborted.
+
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::git::{commit_info, conflicted_paths, default_signature, GitCommitInfo};
+
+const STATE_FILE: &str = "datatex-rebase.json";
+
+/// What to do with a commit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RebaseAction {
+    Pick,
+    /// Pick with a new message
+    Reword,
+    /// Meld into the previous commit, combining the messages
+    Squash,
+    /// Meld into the previous commit, keeping its message
+    Fixup,
+    Drop,
+}
+
+/// One line of the rebase plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebaseStep {
+    pub commit_id: String,
+    pub action: RebaseAction,
+    /// New message for reword/squash (and optionally pick/fixup)
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Sent before each step is applied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebaseProgress {
+    /// 1-based number of the step
+    pub current: usize,
+    pub total: usize,
+    pub commit_id: String,
+    pub action: RebaseAction,
+}
+
+/// Where a rebase run stopped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebaseStatus {
+    pub finished: bool,
+    /// Commit whose step stopped on conflicts
+    pub stopped_at: Option<String>,
+    pub conflicts: Vec<String>,
+    /// HEAD after the run
+    pub head: String,
+}
+
+/// Saved between `start` and `continue`
+#[derive(Debug, Serialize, Deserialize)]
+struct RebaseState {
+    /// Branch being rebased, e.g. "refs/heads/main"
+    branch: String,
+    orig_head: String,
+    /// Commit the history is replayed onto
+    #[serde(default)]
+    onto: String,
+    steps: Vec<RebaseStep>,
+    /// Step being applied
+    next: usize,
+    /// Tip of the rewritten history so far
+    current: String,
+}
+
+fn state_path(repo: &Repository) -> PathBuf {
+    repo.path().join(STATE_FILE)
+}
+
+fn load_state(repo: &Repository) -> Result<RebaseState, String> {
+    let content = std::fs::read_to_string(state_path(repo))
+        .map_err(|_| "No interactive rebase in progress".to_string())?;
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+fn save_state(repo: &Repository, state: &RebaseState) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+    std::fs::write(state_path(repo), content).map_err(|e| e.to_string())
+}
+
+/// Put HEAD and the work tree back on the branch being rebased
+fn restore_branch(repo: &Repository, state: &RebaseState) -> Result<(), String> {
+    repo.set_head(&state.branch).map_err(|e| e.to_string())?;
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+        .map_err(|e| e.to_string())?;
+    repo.cleanup_state().map_err(|e| e.to_string())
+}
+
+fn parse_oid(id: &str) -> Result<Oid, String> {
+    Oid::from_str(id).map_err(|e| e.to_string())
+}
+
+/// Whether an interactive rebase is paused in this repository
+pub fn is_in_progress(repo_path: &str) -> Result<bool, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    Ok(state_path(&repo).exists())
+}
+
+/// Commits that rebasing the current branch onto `upstream` would replay,
+/// oldest first
+pub fn get_todo(repo_path: &str, upstream: &str) -> Result<Vec<GitCommitInfo>, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let onto = repo
+        .revparse_single(upstream)
+        .and_then(|o| o.peel_to_commit())
+        .map_err(|_| format!("Upstream {} not found", upstream))?;
+
+    let mut revwalk = repo.revwalk().map_err(|e| e.to_string())?;
+    revwalk.push_head().map_err(|e| e.to_string())?;
+    revwalk.hide(onto.id()).map_err(|e| e.to_string())?;
+    revwalk
+        .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)
+        .map_err(|e| e.to_string())?;
+
+    revwalk
+        .map(|oid| {
+            let oid = oid.map_err(|e| e.to_string())?;
+            let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+            Ok(commit_info(&commit, Vec::new()))
+        })
+        .collect()
+}
+
+/// Rebase the current branch onto `upstream` following `plan`
+pub fn start(
+    repo_path: &str,
+    upstream: &str,
+    plan: Vec<RebaseStep>,
+    progress: &dyn Fn(&RebaseProgress),
+) -> Result<RebaseStatus, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    if state_path(&repo).exists() || repo.state() != git2::RepositoryState::Clean {
+        return Err("Another merge or rebase is in progress".to_string());
+    }
+
+    let head = repo.head().map_err(|e| e.to_string())?;
+    if !head.is_branch() {
+        return Err("HEAD is detached; check out a branch to rebase".to_string());
+    }
+    let branch = head.name().ok_or("Invalid branch name")?.to_string();
+    let orig_head = head.target().ok_or("Branch has no commits")?;
+
+    let mut status_opts = git2::StatusOptions::new();
+    status_opts.include_untracked(false);
+    let statuses = repo
+        .statuses(Some(&mut status_opts))
+        .map_err(|e| e.to_string())?;
+    if !statuses.is_empty() {
+        return Err("Commit or stash your changes before rebasing".to_string());
+    }
+
+    let first = plan.iter().find(|s| s.action != RebaseAction::Drop);
+    if let Some(step) = first {
+        if matches!(step.action, RebaseAction::Squash | RebaseAction::Fixup) {
+            return Err("The first commit can't be squashed into a previous one".to_string());
+        }
+    }
+    for step in &plan {
+        let commit = repo
+            .find_commit(parse_oid(&step.commit_id)?)
+            .map_err(|e| e.to_string())?;
+        if commit.parent_count() > 1 {
+            return Err(format!(
+                "{} is a merge commit and can't be rebased",
+                step.commit_id
+            ));
+        }
+    }
+
+    let onto = repo
+        .revparse_single(upstream)
+        .and_then(|o| o.peel_to_commit())
+        .map_err(|_| format!("Upstream {} not found", upstream))?;
+    let mut state = RebaseState {
+        branch,
+        orig_head: orig_head.to_string(),
+        onto: onto.id().to_string(),
+        steps: plan,
+        next: 0,
+        current: onto.id().to_string(),
+    };
+    save_state(&repo, &state)?;
+
+    let detached = repo
+        .checkout_tree(
+            onto.as_object(),
+            Some(git2::build::CheckoutBuilder::new().safe()),
+        )
+        .and_then(|_| repo.set_head_detached(onto.id()));
+    if let Err(e) = detached {
+        if let Err(e) = restore_branch(&repo, &state) {
+            eprintln!("Failed to restore {}: {}", state.branch, e);
+        }
+        let _ = std::fs::remove_file(state_path(&repo));
+        return Err(e.to_string());
+    }
+
+    run(&repo, &mut state, progress)
+}
+
+/// Continue after the conflicts of the current step have been resolved
+pub fn continue_rebase(
+    repo_path: &str,
+    progress: &dyn Fn(&RebaseProgress),
+) -> Result<RebaseStatus, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let mut state = load_state(&repo)?;
+
+    // Without a cherry-pick in progress the step failed before anything
+    // was applied, so `run` tries it again
+    if repo.state() == git2::RepositoryState::CherryPick {
+        let mut index = repo.index().map_err(|e| e.to_string())?;
+        if index.has_conflicts() {
+            return Err("Resolve all conflicts before continuing".to_string());
+        }
+        let tree = index.write_tree().map_err(|e| e.to_string())?;
+        let step = state.steps[state.next].clone();
+        commit_step(&repo, &mut state, &step, tree)?;
+        state.next += 1;
+    }
+
+    run(&repo, &mut state, progress)
+}
+
+/// Give up and put the branch back where it was
+pub fn abort(repo_path: &str) -> Result<(), String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let state = load_state(&repo)?;
+
+    restore_branch(&repo, &state)?;
+    std::fs::remove_file(state_path(&repo)).map_err(|e| e.to_string())
+}
+
+/// Apply the remaining steps, pausing on the first conflict. On an error
+/// the progress so far is saved so the rebase can be continued or aborted.
+fn run(
+    repo: &Repository,
+    state: &mut RebaseState,
+    progress: &dyn Fn(&RebaseProgress),
+) -> Result<RebaseStatus, String> {
+    apply_steps(repo, state, progress).map_err(|e| match save_state(repo, state) {
+        Ok(()) => format!("{}; continue or abort the rebase", e),
+        Err(save_error) => format!("{} (and saving the rebase failed: {})", e, save_error),
+    })
+}
+
+fn apply_steps(
+    repo: &Repository,
+    state: &mut RebaseState,
+    progress: &dyn Fn(&RebaseProgress),
+) -> Result<RebaseStatus, String> {
+    let total = state.steps.len();
+    while state.next < total {
+        let step = state.steps[state.next].clone();
+        progress(&RebaseProgress {
+            current: state.next + 1,
+            total,
+            commit_id: step.commit_id.clone(),
+            action: step.action,
+        });
+
+        if step.action != RebaseAction::Drop {
+            let commit = repo
+                .find_commit(parse_oid(&step.commit_id)?)
+                .map_err(|e| e.to_string())?;
+            repo.cherrypick(&commit, None).map_err(|e| e.to_string())?;
+
+            let mut index = repo.index().map_err(|e| e.to_string())?;
+            if index.has_conflicts() {
+                save_state(repo, state)?;
+                return Ok(RebaseStatus {
+                    finished: false,
+                    stopped_at: Some(step.commit_id),
+                    conflicts: conflicted_paths(&index),
+                    head: state.current.clone(),
+                });
+            }
+            let tree = index.write_tree().map_err(|e| e.to_string())?;
+            commit_step(repo, state, &step, tree)?;
+        }
+        state.next += 1;
+    }
+
+    // Move the branch to the rewritten history
+    let tip = parse_oid(&state.current)?;
+    repo.reference(&state.branch, tip, true, "rebase -i (finish)")
+        .map_err(|e| e.to_string())?;
+    repo.set_head(&state.branch).map_err(|e| e.to_string())?;
+    let path = state_path(repo);
+    if path.exists() {
+        std::fs::remove_file(path).map_err(|e| e.to_string())?;
+    }
+
+    Ok(RebaseStatus {
+        finished: true,
+        stopped_at: None,
+        conflicts: Vec::new(),
+        head: state.current.clone(),
+    })
+}
+
+/// Commit the applied step on top of (or, for squash/fixup, into) the
+/// current tip and detach HEAD there
+fn commit_step(
+    repo: &Repository,
+    state: &mut RebaseState,
+    step: &RebaseStep,
+    tree: Oid,
+) -> Result<(), String> {
+    let original = repo
+        .find_commit(parse_oid(&step.commit_id)?)
+        .map_err(|e| e.to_string())?;
+    let current = repo
+        .find_commit(parse_oid(&state.current)?)
+        .map_err(|e| e.to_string())?;
+    let tree = repo.find_tree(tree).map_err(|e| e.to_string())?;
+    let sig = default_signature(repo)?;
+    let original_message = original.message().unwrap_or_default();
+    let current_message = current.message().unwrap_or_default();
+
+    // Nothing rewritten yet (the previous commits were dropped or already
+    // upstream): there's nothing to meld into, so pick it instead of
+    // rewriting `onto`
+    let melds = matches!(step.action, RebaseAction::Squash | RebaseAction::Fixup)
+        && state.current != state.onto;
+
+    let new_id = if melds {
+        let message = step.message.clone().unwrap_or_else(|| {
+            if step.action == RebaseAction::Squash {
+                format!("{}\n\n{}", current_message.trim_end(), original_message)
+            } else {
+                current_message.to_string()
+            }
+        });
+        let parents: Vec<_> = current.parents().collect();
+        let parents: Vec<_> = parents.iter().collect();
+        repo.commit(None, &current.author(), &sig, &message, &tree, &parents)
+    } else {
+        // Nothing left to apply; the changes are already upstream
+        if tree.id() == current.tree_id() {
+            repo.cleanup_state().map_err(|e| e.to_string())?;
+            return Ok(());
+        }
+        let message = step.message.as_deref().unwrap_or(original_message);
+        repo.commit(None, &original.author(), &sig, message, &tree, &[&current])
+    }
+    .map_err(|e| e.to_string())?;
+
+    repo.set_head_detached(new_id).map_err(|e| e.to_string())?;
+    repo.cleanup_state().map_err(|e| e.to_string())?;
+    state.current = new_id.to_string();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn commit_file(repo: &Repository, name: &str, message: &str) -> Oid {
+        std::fs::write(repo.workdir().unwrap().join(name), message).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .unwrap()
+    }
+
+    fn step(id: Oid, action: RebaseAction) -> RebaseStep {
+        RebaseStep {
+            commit_id: id.to_string(),
+            action,
+            message: None,
+        }
+    }
+
+    fn temp_repo(name: &str) -> (PathBuf, Repository) {
+        let dir = std::env::temp_dir().join(format!("datatex-{}-{}", name, uuid::Uuid::new_v4()));
+        let repo = Repository::init(&dir).unwrap();
+        (dir, repo)
+    }
+
+    #[test]
+    fn test_reorder_squash_and_drop() {
+        let (dir, repo) = temp_repo("rebase");
+        let path = dir.to_string_lossy().to_string();
+        let base = commit_file(&repo, "base.tex", "base");
+        let a = commit_file(&repo, "a.tex", "a");
+        let b = commit_file(&repo, "b.tex", "b");
+        let c = commit_file(&repo, "c.tex", "c");
+        let d = commit_file(&repo, "d.tex", "d");
+
+        let plan = vec![
+            step(c, RebaseAction::Pick),
+            step(a, RebaseAction::Pick),
+            step(b, RebaseAction::Squash),
+            step(d, RebaseAction::Drop),
+        ];
+        let status = start(&path, &base.to_string(), plan, &|_| {}).unwrap();
+        assert!(status.finished);
+        assert!(!is_in_progress(&path).unwrap());
+
+        let messages: Vec<String> = get_todo(&path, &base.to_string())
+            .unwrap()
+            .iter()
+            .map(|c| c.message.trim_end().to_string())
+            .collect();
+        assert_eq!(messages, vec!["c".to_string(), "a\n\nb".to_string()]);
+        assert!(dir.join("b.tex").exists());
+        assert!(!dir.join("d.tex").exists());
+        assert!(repo.head().unwrap().is_branch());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_continue_retries_a_failed_step() {
+        let (dir, repo) = temp_repo("rebase-retry");
+        let path = dir.to_string_lossy().to_string();
+        let base = commit_file(&repo, "base.tex", "base");
+        let a = commit_file(&repo, "a.tex", "a");
+        let x = commit_file(&repo, "x.tex", "x");
+
+        // An untracked x.tex stops the cherry-pick of `x` before it starts
+        let plan = vec![step(a, RebaseAction::Pick), step(x, RebaseAction::Pick)];
+        let blocker = dir.join("x.tex");
+        let err = start(&path, &base.to_string(), plan, &|p| {
+            if p.current == 2 {
+                std::fs::write(&blocker, "untracked").unwrap();
+            }
+        })
+        .unwrap_err();
+        assert!(err.contains("continue or abort"), "{}", err);
+
+        std::fs::remove_file(&blocker).unwrap();
+        let status = continue_rebase(&path, &|_| {}).unwrap();
+        assert!(status.finished);
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.message().unwrap(), "x");
+        assert_eq!(std::fs::read_to_string(&blocker).unwrap(), "x");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_squash_after_skipped_commit_is_picked() {
+        let (dir, repo) = temp_repo("rebase-skip");
+        let path = dir.to_string_lossy().to_string();
+        let base = commit_file(&repo, "base.tex", "base");
+        let a = commit_file(&repo, "a.tex", "a");
+        let b = commit_file(&repo, "b.tex", "b");
+
+        // Upstream already has `a`
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let a_tree = repo.find_commit(a).unwrap().tree().unwrap();
+        let base_commit = repo.find_commit(base).unwrap();
+        let upstream = repo
+            .commit(None, &sig, &sig, "a upstream", &a_tree, &[&base_commit])
+            .unwrap();
+
+        let plan = vec![step(a, RebaseAction::Pick), step(b, RebaseAction::Squash)];
+        let status = start(&path, &upstream.to_string(), plan, &|_| {}).unwrap();
+        assert!(status.finished);
+
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.parent_id(0).unwrap(), upstream);
+        assert_eq!(head.message().unwrap(), "b");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,517 @@
+//! Commit and Tag Signing
+//!
+//! libgit2 can't sign objects, so like git itself the signature is made by
+//! running `gpg` (or `ssh-keygen` when `gpg.format = ssh`) on the raw
+//! object and the result is stored with `commit_signed` / as the tail of a
+//! tag. Everything is driven by the usual config keys: `user.signingkey`,
+//! `gpg.format`, `gpg.program`, `gpg.ssh.program`, `commit.gpgsign`,
+//! `tag.gpgsign` and `gpg.ssh.allowedSignersFile` for verification.
+
+use git2::{Commit, Object, Oid, Repository, Signature, Tree};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// Kind of key used for signing (`gpg.format`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SigningFormat {
+    #[default]
+    Openpgp,
+    X509,
+    Ssh,
+}
+
+/// Signing settings of a repository
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SigningConfig {
+    pub format: SigningFormat,
+    /// `user.signingkey`; GPG falls back to the committer identity
+    pub key: Option<String>,
+    pub program: String,
+    /// `commit.gpgsign`
+    pub sign_commits: bool,
+    /// `tag.gpgsign`
+    pub sign_tags: bool,
+}
+
+/// Outcome of checking a commit's signature
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureStatus {
+    /// Signed, but not checked
+    Unverified,
+    Good,
+    Bad,
+    /// Good signature from an expired or revoked key
+    Expired,
+    /// The key is not known (missing public key or allowed signer)
+    UnknownKey,
+}
+
+/// Signature attached to a commit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitSignature {
+    pub format: SigningFormat,
+    pub status: SignatureStatus,
+    /// User id or principal that made the signature, when verified
+    pub signer: Option<String>,
+}
+
+fn config_string(config: &git2::Config, key: &str) -> Option<String> {
+    config.get_string(key).ok().filter(|s| !s.is_empty())
+}
+
+/// Read the signing settings from the repository (and global) config
+pub fn signing_config(repo: &Repository) -> SigningConfig {
+    let config = repo.config().ok();
+    let get = |key: &str| config.as_ref().and_then(|c| config_string(c, key));
+    let get_bool = |key: &str| {
+        config
+            .as_ref()
+            .and_then(|c| c.get_bool(key).ok())
+            .unwrap_or(false)
+    };
+
+    let format = match get("gpg.format").as_deref() {
+        Some("ssh") => SigningFormat::Ssh,
+        Some("x509") => SigningFormat::X509,
+        _ => SigningFormat::Openpgp,
+    };
+    SigningConfig {
+        format,
+        key: get("user.signingkey"),
+        program: program_for(config.as_ref(), format),
+        sign_commits: get_bool("commit.gpgsign"),
+        sign_tags: get_bool("tag.gpgsign"),
+    }
+}
+
+fn program_for(config: Option<&git2::Config>, format: SigningFormat) -> String {
+    let (key, default) = match format {
+        SigningFormat::Openpgp => ("gpg.program", "gpg"),
+        SigningFormat::X509 => ("gpg.x509.program", "gpgsm"),
+        SigningFormat::Ssh => ("gpg.ssh.program", "ssh-keygen"),
+    };
+    config
+        .and_then(|c| config_string(c, key))
+        .or_else(|| {
+            // `gpg.openpgp.program` is the newer spelling of `gpg.program`
+            (format == SigningFormat::Openpgp)
+                .then(|| config.and_then(|c| config_string(c, "gpg.openpgp.program")))
+                .flatten()
+        })
+        .unwrap_or_else(|| default.to_string())
+}
+
+/// Signing settings of the repository at `repo_path`
+pub fn get_signing_config(repo_path: &str) -> Result<SigningConfig, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    Ok(signing_config(&repo))
+}
+
+/// Turn commit (and tag) signing on or off for this repository
+pub fn set_signing_enabled(repo_path: &str, enabled: bool) -> Result<(), String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let signing = signing_config(&repo);
+    if enabled && signing.format == SigningFormat::Ssh && signing.key.is_none() {
+        return Err("Set user.signingkey to the SSH key to sign with".to_string());
+    }
+    let mut config = repo
+        .config()
+        .and_then(|c| c.open_level(git2::ConfigLevel::Local))
+        .map_err(|e| e.to_string())?;
+    config
+        .set_bool("commit.gpgsign", enabled)
+        .and_then(|_| config.set_bool("tag.gpgsign", enabled))
+        .map_err(|e| e.to_string())
+}
+
+/// Temporary file removed when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(content: &[u8]) -> Result<Self, String> {
+        let path = std::env::temp_dir().join(format!("datatex-sign-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, content).map_err(|e| e.to_string())?;
+        Ok(TempFile(path))
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Run `program` with `input` on stdin
+fn run_with_input(program: &str, args: &[&str], input: &[u8]) -> Result<Output, String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input).map_err(|e| e.to_string())?;
+    }
+    child.wait_with_output().map_err(|e| e.to_string())
+}
+
+fn stderr_message(program: &str, output: &Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // gpg mixes its status lines into stderr when signing
+    let message: Vec<&str> = stderr
+        .lines()
+        .filter(|l| !l.starts_with("[GNUPG:]"))
+        .collect();
+    format!("{} failed to sign: {}", program, message.join("\n").trim())
+}
+
+/// Sign `data` with the configured key, returning the armored signature
+pub fn sign_buffer(
+    config: &SigningConfig,
+    committer: &Signature,
+    data: &[u8],
+) -> Result<String, String> {
+    let output = match config.format {
+        SigningFormat::Openpgp | SigningFormat::X509 => {
+            let key = config.key.clone().unwrap_or_else(|| committer.to_string());
+            run_with_input(&config.program, &["--status-fd=2", "-bsau", &key], data)?
+        }
+        SigningFormat::Ssh => {
+            let key = config
+                .key
+                .as_deref()
+                .ok_or("Set user.signingkey to the SSH key to sign with")?;
+            return sign_ssh(&config.program, key, data);
+        }
+    };
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(stderr_message(&config.program, &output));
+    }
+    String::from_utf8(output.stdout).map_err(|e| e.to_string())
+}
+
+fn sign_ssh(program: &str, key: &str, data: &[u8]) -> Result<String, String> {
+    // The key is either a path or, like git allows, the public key itself
+    // (then the private half has to be in the ssh-agent)
+    let literal = key
+        .strip_prefix("key::")
+        .or_else(|| key.starts_with("ssh-").then_some(key));
+    let key_file = match literal {
+        Some(public_key) => Some(TempFile::new(public_key.as_bytes())?),
+        None => None,
+    };
+    let key_path = match &key_file {
+        Some(file) => file.path().to_string_lossy().to_string(),
+        None => match key.strip_prefix("~/") {
+            Some(rest) => directories::BaseDirs::new()
+                .map(|d| d.home_dir().join(rest).to_string_lossy().to_string())
+                .unwrap_or_else(|| key.to_string()),
+            None => key.to_string(),
+        },
+    };
+
+    let buffer = TempFile::new(data)?;
+    let buffer_path = buffer.path().to_string_lossy().to_string();
+    let mut args = vec!["-Y", "sign", "-n", "git", "-f", &key_path];
+    if key_file.is_some() {
+        args.push("-U");
+    }
+    args.push(&buffer_path);
+    let output = run_with_input(program, &args, &[])?;
+
+    let signature_file = TempFile(PathBuf::from(format!("{}.sig", buffer_path)));
+    if !output.status.success() {
+        return Err(stderr_message(program, &output));
+    }
+    std::fs::read_to_string(signature_file.path()).map_err(|e| e.to_string())
+}
+
+/// Point HEAD (or the branch it refers to) at `oid`
+fn update_head(repo: &Repository, oid: Oid, log_message: &str) -> Result<(), String> {
+    let head = repo.find_reference("HEAD").map_err(|e| e.to_string())?;
+    match head.symbolic_target() {
+        Some(target) => repo.reference(target, oid, true, log_message).map(|_| ()),
+        None => repo.set_head_detached(oid),
+    }
+    .map_err(|e| e.to_string())
+}
+
+/// Create a commit, signing it when `sign` is set, and move HEAD to it
+/// when `update_head_log` (the reflog message) is given
+#[allow(clippy::too_many_arguments)]
+pub fn create_commit(
+    repo: &Repository,
+    update_head_log: Option<&str>,
+    author: &Signature,
+    committer: &Signature,
+    message: &str,
+    tree: &Tree,
+    parents: &[&Commit],
+    sign: bool,
+) -> Result<Oid, String> {
+    let oid = if sign {
+        let buffer = repo
+            .commit_create_buffer(author, committer, message, tree, parents)
+            .map_err(|e| e.to_string())?;
+        let content = buffer.as_str().ok_or("Commit is not valid UTF-8")?;
+        let signature = sign_buffer(&signing_config(repo), committer, content.as_bytes())?;
+        repo.commit_signed(content, &signature, None)
+            .map_err(|e| e.to_string())?
+    } else {
+        repo.commit(None, author, committer, message, tree, parents)
+            .map_err(|e| e.to_string())?
+    };
+
+    if let Some(log_message) = update_head_log {
+        update_head(repo, oid, log_message)?;
+    }
+    Ok(oid)
+}
+
+/// Create an annotated tag, signing it when `sign` is set
+pub fn create_tag(
+    repo: &Repository,
+    name: &str,
+    target: &Object,
+    tagger: &Signature,
+    message: &str,
+    sign: bool,
+) -> Result<Oid, String> {
+    if !sign {
+        return repo
+            .tag(name, target, tagger, message, false)
+            .map_err(|e| e.to_string());
+    }
+
+    let refname = format!("refs/tags/{}", name);
+    if repo.find_reference(&refname).is_ok() {
+        return Err(format!("Tag '{}' already exists", name));
+    }
+    let kind = target.kind().ok_or("Unknown object type")?;
+    let mut message = message.to_string();
+    if !message.ends_with('\n') {
+        message.push('\n');
+    }
+    let mut content = format!(
+        "object {}\ntype {}\ntag {}\ntagger {}\n\n{}",
+        target.id(),
+        kind.str(),
+        name,
+        format_signature(tagger),
+        message
+    );
+    // A tag's signature simply follows its message
+    content.push_str(&sign_buffer(
+        &signing_config(repo),
+        tagger,
+        content.as_bytes(),
+    )?);
+
+    let oid = repo
+        .odb()
+        .and_then(|odb| odb.write(git2::ObjectType::Tag, content.as_bytes()))
+        .map_err(|e| e.to_string())?;
+    repo.reference(&refname, oid, false, "tag: signed")
+        .map_err(|e| e.to_string())?;
+    Ok(oid)
+}
+
+/// Identity line as written in raw objects: `Name <email> 1700000000 +0100`
+fn format_signature(sig: &Signature) -> String {
+    let when = sig.when();
+    let offset = when.offset_minutes();
+    format!(
+        "{} <{}> {} {}{:02}{:02}",
+        sig.name().unwrap_or_default(),
+        sig.email().unwrap_or_default(),
+        when.seconds(),
+        if offset < 0 { '-' } else { '+' },
+        offset.abs() / 60,
+        offset.abs() % 60
+    )
+}
+
+fn signature_format(signature: &str) -> SigningFormat {
+    if signature.starts_with("-----BEGIN SSH SIGNATURE") {
+        SigningFormat::Ssh
+    } else if signature.starts_with("-----BEGIN SIGNED MESSAGE") {
+        SigningFormat::X509
+    } else {
+        SigningFormat::Openpgp
+    }
+}
+
+/// The signature of a commit, without checking it; None if unsigned
+pub fn commit_signature(repo: &Repository, oid: Oid) -> Option<CommitSignature> {
+    let (signature, _) = repo.extract_signature(&oid, None).ok()?;
+    Some(CommitSignature {
+        format: signature_format(signature.as_str().unwrap_or_default()),
+        status: SignatureStatus::Unverified,
+        signer: None,
+    })
+}
+
+/// Check the signature of a commit; None if unsigned
+pub fn verify_commit(repo: &Repository, oid: Oid) -> Option<CommitSignature> {
+    let (signature, data) = repo.extract_signature(&oid, None).ok()?;
+    let signature = signature.as_str().unwrap_or_default().to_string();
+    let format = signature_format(&signature);
+    let config = repo.config().ok();
+
+    let (status, signer) = match format {
+        SigningFormat::Ssh => {
+            let allowed = config
+                .as_ref()
+                .and_then(|c| config_string(c, "gpg.ssh.allowedSignersFile"));
+            match allowed {
+                Some(allowed) => verify_ssh(
+                    &program_for(config.as_ref(), format),
+                    &allowed,
+                    &signature,
+                    &data,
+                ),
+                None => (SignatureStatus::UnknownKey, None),
+            }
+        }
+        _ => verify_gpg(&program_for(config.as_ref(), format), &signature, &data),
+    };
+    Some(CommitSignature {
+        format,
+        status,
+        signer,
+    })
+}
+
+fn verify_gpg(program: &str, signature: &str, data: &[u8]) -> (SignatureStatus, Option<String>) {
+    let sig_file = match TempFile::new(signature.as_bytes()) {
+        Ok(file) => file,
+        Err(_) => return (SignatureStatus::Unverified, None),
+    };
+    let sig_path = sig_file.path().to_string_lossy().to_string();
+    match run_with_input(
+        program,
+        &["--status-fd=1", "--verify", &sig_path, "-"],
+        data,
+    ) {
+        Ok(output) => parse_gpg_status(&String::from_utf8_lossy(&output.stdout)),
+        Err(_) => (SignatureStatus::Unverified, None),
+    }
+}
+
+/// Interpret gpg's `--status-fd` output
+fn parse_gpg_status(status: &str) -> (SignatureStatus, Option<String>) {
+    for line in status.lines() {
+        let Some(line) = line.strip_prefix("[GNUPG:] ") else {
+            continue;
+        };
+        let mut parts = line.splitn(3, ' ');
+        let keyword = parts.next().unwrap_or_default();
+        let _key_id = parts.next();
+        let user = parts.next().map(str::to_string);
+        let status = match keyword {
+            "GOODSIG" => SignatureStatus::Good,
+            "BADSIG" => SignatureStatus::Bad,
+            "EXPSIG" | "EXPKEYSIG" | "REVKEYSIG" => SignatureStatus::Expired,
+            "ERRSIG" => SignatureStatus::UnknownKey,
+            _ => continue,
+        };
+        return (status, user);
+    }
+    (SignatureStatus::UnknownKey, None)
+}
+
+fn verify_ssh(
+    program: &str,
+    allowed_signers: &str,
+    signature: &str,
+    data: &[u8],
+) -> (SignatureStatus, Option<String>) {
+    let sig_file = match TempFile::new(signature.as_bytes()) {
+        Ok(file) => file,
+        Err(_) => return (SignatureStatus::Unverified, None),
+    };
+    let sig_path = sig_file.path().to_string_lossy().to_string();
+
+    let principal = Command::new(program)
+        .args([
+            "-Y",
+            "find-principals",
+            "-f",
+            allowed_signers,
+            "-s",
+            &sig_path,
+        ])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .lines()
+                .next()
+                .map(str::to_string)
+        });
+    let Some(principal) = principal else {
+        return (SignatureStatus::UnknownKey, None);
+    };
+
+    let args = [
+        "-Y",
+        "verify",
+        "-n",
+        "git",
+        "-f",
+        allowed_signers,
+        "-I",
+        &principal,
+        "-s",
+        &sig_path,
+    ];
+    match run_with_input(program, &args, data) {
+        Ok(output) if output.status.success() => (SignatureStatus::Good, Some(principal)),
+        Ok(_) => (SignatureStatus::Bad, Some(principal)),
+        Err(_) => (SignatureStatus::Unverified, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gpg_status() {
+        let status = "[GNUPG:] NEWSIG\n[GNUPG:] KEY_CONSIDERED ABC 0\n\
+                      [GNUPG:] GOODSIG 1234ABCD Jane Doe <jane@example.com>\n";
+        assert_eq!(
+            parse_gpg_status(status),
+            (
+                SignatureStatus::Good,
+                Some("Jane Doe <jane@example.com>".to_string())
+            )
+        );
+        let status = "[GNUPG:] ERRSIG 1234ABCD 1 10 00 1700000000 9 -\n";
+        assert_eq!(parse_gpg_status(status).0, SignatureStatus::UnknownKey);
+        assert_eq!(
+            parse_gpg_status("[GNUPG:] BADSIG 1234ABCD Jane\n").0,
+            SignatureStatus::Bad
+        );
+    }
+
+    #[test]
+    fn test_format_signature() {
+        let time = git2::Time::new(1700000000, -90);
+        let sig = Signature::new("Jane Doe", "jane@example.com", &time).unwrap();
+        assert_eq!(
+            format_signature(&sig),
+            "Jane Doe <jane@example.com> 1700000000 -0130"
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! Compile Snapshots
+//!
+//! Opt-in lightweight versioning for users who never commit by hand: after
+//! a document compiles successfully, the project as it is on disk is
+//! committed to a dedicated snapshot branch. HEAD, the index and the working
+//! tree are left alone, so snapshots never get in the way of regular work.
+//! Settings are kept in the repository config under `datatex.snapshots.*`.
+
+use git2::{Index, Repository};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::git::default_signature;
+
+const DEFAULT_BRANCH: &str = "datatex/snapshots";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapshotSettings {
+    pub enabled: bool,
+    /// Branch the snapshots are committed to
+    pub branch: String,
+}
+
+impl Default for SnapshotSettings {
+    fn default() -> Self {
+        SnapshotSettings {
+            enabled: false,
+            branch: DEFAULT_BRANCH.to_string(),
+        }
+    }
+}
+
+fn load_settings(repo: &Repository) -> SnapshotSettings {
+    let defaults = SnapshotSettings::default();
+    let Ok(config) = repo.config() else {
+        return defaults;
+    };
+    SnapshotSettings {
+        enabled: config
+            .get_bool("datatex.snapshots.enabled")
+            .unwrap_or(defaults.enabled),
+        branch: config
+            .get_string("datatex.snapshots.branch")
+            .ok()
+            .filter(|b| !b.trim().is_empty())
+            .unwrap_or(defaults.branch),
+    }
+}
+
+pub fn get_settings(repo_path: &str) -> Result<SnapshotSettings, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    Ok(load_settings(&repo))
+}
+
+/// Store the settings in the repository's own config
+pub fn set_settings(repo_path: &str, settings: &SnapshotSettings) -> Result<(), String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let branch = settings.branch.trim();
+    if !git2::Branch::name_is_valid(branch).map_err(|e| e.to_string())? {
+        return Err(format!("{} is not a valid branch name", branch));
+    }
+    let mut config = repo
+        .config()
+        .and_then(|c| c.open_level(git2::ConfigLevel::Local))
+        .map_err(|e| e.to_string())?;
+    config
+        .set_bool("datatex.snapshots.enabled", settings.enabled)
+        .and_then(|_| config.set_str("datatex.snapshots.branch", branch))
+        .map_err(|e| e.to_string())
+}
+
+/// Commit the working tree to `branch`. Returns the new commit, or `None`
+/// when nothing changed since the last snapshot.
+fn snapshot(repo: &Repository, branch: &str, message: &str) -> Result<Option<String>, String> {
+    // Stage everything into an in-memory index, leaving the real one alone
+    let mut index = Index::new().map_err(|e| e.to_string())?;
+    repo.set_index(&mut index).map_err(|e| e.to_string())?;
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+    index
+        .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+        .map_err(|e| e.to_string())?;
+    let tree = index.write_tree_to(repo).map_err(|e| e.to_string())?;
+    let tree = repo.find_tree(tree).map_err(|e| e.to_string())?;
+
+    let refname = format!("refs/heads/{}", branch);
+    if repo.head().ok().and_then(|h| h.name().map(str::to_string)) == Some(refname.clone()) {
+        return Err(format!(
+            "{} is checked out; snapshots need a branch of their own",
+            branch
+        ));
+    }
+    let parent = repo
+        .find_reference(&refname)
+        .ok()
+        .and_then(|r| r.peel_to_commit().ok());
+    if parent.as_ref().map(|p| p.tree_id()) == Some(tree.id()) {
+        return Ok(None);
+    }
+
+    let sig = default_signature(repo)?;
+    let parents: Vec<_> = parent.iter().collect();
+    let id = repo
+        .commit(Some(&refname), &sig, &sig, message, &tree, &parents)
+        .map_err(|e| e.to_string())?;
+    Ok(Some(id.to_string()))
+}
+
+/// Snapshot the project containing a document that just compiled, if its
+/// repository has snapshots turned on
+pub fn snapshot_after_compile(file_path: &str) -> Result<Option<String>, String> {
+    let Ok(repo) = Repository::discover(Path::new(file_path)) else {
+        return Ok(None);
+    };
+    let settings = load_settings(&repo);
+    if !settings.enabled || repo.is_bare() || !crate::settings::current().git.snapshot_after_compile
+    {
+        return Ok(None);
+    }
+    let name = Path::new(file_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    snapshot(
+        &repo,
+        &settings.branch,
+        &format!("Snapshot after compiling {}", name),
+    )
+}
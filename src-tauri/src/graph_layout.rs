@@ -0,0 +1,122 @@
+//! Graph Layout
+//!
+//! Fruchterman-Reingold force-directed placement for the graph view, run on
+//! the backend so graphs with thousands of nodes arrive already laid out
+//! instead of settling in the frontend simulation. The all-pairs repulsion
+//! dominates the cost and is spread over threads with rayon.
+
+use rayon::prelude::*;
+
+/// Passes over the graph; the temperature cools to zero over them
+const ITERATIONS: usize = 120;
+
+/// Pull towards the origin, so disconnected parts don't drift apart
+const GRAVITY: f64 = 0.05;
+
+/// Ideal distance between linked nodes, in graph view units
+const SPACING: f64 = 60.0;
+
+/// Positions for `count` nodes joined by `edges` (pairs of node indexes).
+/// Deterministic: the same graph always gets the same layout.
+pub fn fruchterman_reingold(count: usize, edges: &[(usize, usize)]) -> Vec<(f64, f64)> {
+    if count == 0 {
+        return Vec::new();
+    }
+
+    // Start on a sunflower spiral, which spreads nodes evenly without
+    // randomness
+    let golden_angle = std::f64::consts::PI * (3.0 - 5f64.sqrt());
+    let mut positions: Vec<(f64, f64)> = (0..count)
+        .map(|i| {
+            let r = SPACING * (i as f64 + 0.5).sqrt();
+            let theta = i as f64 * golden_angle;
+            (r * theta.cos(), r * theta.sin())
+        })
+        .collect();
+
+    let k = SPACING;
+    let mut temperature = SPACING * (count as f64).sqrt() / 4.0;
+    let cooling = temperature / ITERATIONS as f64;
+
+    for _ in 0..ITERATIONS {
+        // Repulsion between every pair, and gravity
+        let snapshot = &positions;
+        let mut displacement: Vec<(f64, f64)> = (0..count)
+            .into_par_iter()
+            .map(|i| {
+                let (xi, yi) = snapshot[i];
+                let mut dx = -xi * GRAVITY;
+                let mut dy = -yi * GRAVITY;
+                for (j, &(xj, yj)) in snapshot.iter().enumerate() {
+                    if i == j {
+                        continue;
+                    }
+                    let (ddx, ddy) = (xi - xj, yi - yj);
+                    let dist_sq = (ddx * ddx + ddy * ddy).max(0.01);
+                    // k² / d, along the unit vector: k² · d⃗ / d²
+                    let force = k * k / dist_sq;
+                    dx += ddx * force;
+                    dy += ddy * force;
+                }
+                (dx, dy)
+            })
+            .collect();
+
+        // Attraction along links: d² / k, along the unit vector: d⃗ · d / k
+        for &(a, b) in edges {
+            if a == b || a >= count || b >= count {
+                continue;
+            }
+            let (ddx, ddy) = (
+                positions[a].0 - positions[b].0,
+                positions[a].1 - positions[b].1,
+            );
+            let dist = (ddx * ddx + ddy * ddy).sqrt();
+            let force = dist / k;
+            displacement[a].0 -= ddx * force;
+            displacement[a].1 -= ddy * force;
+            displacement[b].0 += ddx * force;
+            displacement[b].1 += ddy * force;
+        }
+
+        // Move each node at most `temperature` along its displacement
+        for (position, (dx, dy)) in positions.iter_mut().zip(displacement) {
+            let length = (dx * dx + dy * dy).sqrt();
+            if length > 0.0 {
+                let step = length.min(temperature) / length;
+                position.0 += dx * step;
+                position.1 += dy * step;
+            }
+        }
+        temperature = (temperature - cooling).max(0.0);
+    }
+
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+        ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+    }
+
+    #[test]
+    fn test_fruchterman_reingold() {
+        assert!(fruchterman_reingold(0, &[]).is_empty());
+
+        // Two triangles joined by nothing: linked nodes end up closer to
+        // each other than to the other triangle
+        let edges = [(0, 1), (1, 2), (2, 0), (3, 4), (4, 5), (5, 3)];
+        let positions = fruchterman_reingold(6, &edges);
+        assert_eq!(positions.len(), 6);
+        assert!(positions.iter().all(|p| p.0.is_finite() && p.1.is_finite()));
+
+        let within = distance(positions[0], positions[1]);
+        let across = distance(positions[0], positions[3]);
+        assert!(within < across, "{} >= {}", within, across);
+
+        assert_eq!(positions, fruchterman_reingold(6, &edges));
+    }
+}
@@ -4,12 +4,14 @@
 
 use serde::{Deserialize, Serialize};
 use sqlx::Row;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
 
 use crate::database::DatabaseManager;
+use crate::tree_groups::{self, GroupField};
 
 /// Filter options passed from the frontend
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GraphFilters {
     pub show_packages: bool,
@@ -18,10 +20,32 @@ pub struct GraphFilters {
     pub show_classes: bool,
     pub show_dtx: bool,
     pub show_ins: bool,
+    /// Add the cited bibliography entries as nodes, linked by `\cite`
+    #[serde(default)]
+    pub show_citations: bool,
+    /// Metadata whose values become nodes linked to their resources
+    #[serde(default)]
+    pub metadata_fields: Vec<GroupField>,
+    /// Custom fields (by name) whose values become nodes as well
+    #[serde(default)]
+    pub custom_fields: Vec<String>,
+    /// Lay the graph out on the backend (see `graph_layout`)
+    #[serde(default)]
+    pub layout: bool,
+    /// Relation types whose links are left out: `input`, `include`,
+    /// `package`, `class`, `graphics`, `bibliography`, `cite`, ...
+    #[serde(default)]
+    pub hidden_relations: Vec<String>,
+}
+
+impl GraphFilters {
+    fn shows_relation(&self, relation: &str) -> bool {
+        !self.hidden_relations.iter().any(|r| r == relation)
+    }
 }
 
 /// A node in the graph
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct GraphNode {
     pub id: String,
@@ -31,26 +55,245 @@ pub struct GraphNode {
     pub collection: String,
     pub path: String,
     pub val: f64, // Node size based on centrality
+    /// Hops from the focused node, in a neighborhood
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distance: Option<usize>,
+    /// Position from the backend layout, when one was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<f64>,
 }
 
-/// A link in the graph
-#[derive(Debug, Serialize, Clone)]
+/// A link in the graph. All the dependencies between the same two nodes
+/// make up one link (see `merge_links`).
+#[derive(Debug, Serialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct GraphLinkOutput {
     pub source: String,
     pub target: String,
+    /// The first relation type, which decides how the link is drawn
     #[serde(rename = "type")]
     pub link_type: String,
+    /// Every relation type between the two nodes
+    pub relations: Vec<String>,
+    /// How many dependencies the link stands for, for its thickness
+    pub weight: usize,
+}
+
+impl GraphLinkOutput {
+    fn new(source: &str, target: &str, link_type: &str) -> Self {
+        GraphLinkOutput {
+            source: source.to_string(),
+            target: target.to_string(),
+            link_type: link_type.to_string(),
+            relations: vec![link_type.to_string()],
+            weight: 1,
+        }
+    }
+}
+
+/// Fold links between the same source and target into one, adding up
+/// their weights. Order of first appearance is kept.
+fn merge_links(links: Vec<GraphLinkOutput>) -> Vec<GraphLinkOutput> {
+    let mut index: HashMap<(String, String), usize> = HashMap::new();
+    let mut merged: Vec<GraphLinkOutput> = Vec::new();
+    for link in links {
+        match index.get(&(link.source.clone(), link.target.clone())) {
+            Some(&i) => {
+                let existing = &mut merged[i];
+                existing.weight += link.weight;
+                for relation in link.relations {
+                    if !existing.relations.contains(&relation) {
+                        existing.relations.push(relation);
+                    }
+                }
+            }
+            None => {
+                index.insert((link.source.clone(), link.target.clone()), merged.len());
+                merged.push(link);
+            }
+        }
+    }
+    merged
 }
 
 /// The complete graph data returned to the frontend
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct GraphData {
     pub nodes: Vec<GraphNode>,
     pub links: Vec<GraphLinkOutput>,
 }
 
+/// Changes between two versions of a graph. When `full` is set, the
+/// frontend had no usable base and `nodes` / `links` are the whole graph.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphDelta {
+    pub version: u64,
+    pub full: bool,
+    /// New nodes, and nodes whose data changed
+    pub nodes: Vec<GraphNode>,
+    pub removed_nodes: Vec<String>,
+    pub links: Vec<GraphLinkOutput>,
+    pub removed_links: Vec<GraphLinkOutput>,
+}
+
+/// Most graphs kept at once, one per (collections, filters) combination
+const MAX_CACHED_GRAPHS: usize = 8;
+
+struct CachedGraph {
+    version: u64,
+    data: GraphData,
+    /// Resources changed since `data` was built
+    stale: bool,
+}
+
+/// Processed graphs kept between calls, so reopening the graph view or
+/// toggling back to earlier filters doesn't query and filter everything
+/// again. Any resource change marks them stale; a stale graph is kept
+/// until it is rebuilt, to answer delta requests against it.
+#[derive(Default)]
+pub struct GraphCache {
+    entries: std::sync::Mutex<HashMap<String, CachedGraph>>,
+    versions: std::sync::atomic::AtomicU64,
+    /// Bumped by `invalidate`, so a graph built while it ran is stored stale
+    generation: std::sync::atomic::AtomicU64,
+}
+
+impl GraphCache {
+    /// Mark every cached graph as out of date
+    pub fn invalidate(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            self.generation
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            for entry in entries.values_mut() {
+                entry.stale = true;
+            }
+        }
+    }
+
+    /// Read before building a graph and passed to `store`
+    fn generation(&self) -> u64 {
+        self.generation.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    fn key(collections: &[String], filters: &GraphFilters) -> String {
+        let mut collections = collections.to_vec();
+        collections.sort();
+        serde_json::to_string(&(collections, filters)).unwrap_or_default()
+    }
+
+    /// The cached graph and its version, if it is up to date
+    fn fresh(&self, key: &str) -> Option<(u64, GraphData)> {
+        let entries = self.entries.lock().ok()?;
+        let entry = entries.get(key).filter(|e| !e.stale)?;
+        Some((entry.version, entry.data.clone()))
+    }
+
+    /// Store a rebuilt graph, returning its version and the one it replaces.
+    /// It stays stale if the cache was invalidated since `generation`.
+    fn store(
+        &self,
+        key: String,
+        data: GraphData,
+        generation: u64,
+    ) -> (u64, Option<(u64, GraphData)>) {
+        let version = self
+            .versions
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+            + 1;
+        let Ok(mut entries) = self.entries.lock() else {
+            return (version, None);
+        };
+        if entries.len() >= MAX_CACHED_GRAPHS && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, e)| e.version)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        let previous = entries.insert(
+            key,
+            CachedGraph {
+                version,
+                data,
+                stale: self.generation() != generation,
+            },
+        );
+        (version, previous.map(|p| (p.version, p.data)))
+    }
+}
+
+/// What changed from `old` to `new`
+fn diff_graphs(old: &GraphData, new: &GraphData, version: u64) -> GraphDelta {
+    let old_nodes: HashMap<&str, &GraphNode> =
+        old.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+    let new_ids: HashSet<&str> = new.nodes.iter().map(|n| n.id.as_str()).collect();
+    // Links are keyed by their ends; one whose relations or weight changed
+    // is removed and added again
+    let link_key = |l: &GraphLinkOutput| (l.source.clone(), l.target.clone());
+    let old_links: HashMap<_, &GraphLinkOutput> =
+        old.links.iter().map(|l| (link_key(l), l)).collect();
+    let new_links: HashMap<_, &GraphLinkOutput> =
+        new.links.iter().map(|l| (link_key(l), l)).collect();
+
+    GraphDelta {
+        version,
+        full: false,
+        nodes: new
+            .nodes
+            .iter()
+            .filter(|n| old_nodes.get(n.id.as_str()) != Some(n))
+            .cloned()
+            .collect(),
+        removed_nodes: old
+            .nodes
+            .iter()
+            .filter(|n| !new_ids.contains(n.id.as_str()))
+            .map(|n| n.id.clone())
+            .collect(),
+        links: new
+            .links
+            .iter()
+            .filter(|l| old_links.get(&link_key(l)) != Some(l))
+            .cloned()
+            .collect(),
+        removed_links: old
+            .links
+            .iter()
+            .filter(|l| new_links.get(&link_key(l)) != Some(l))
+            .cloned()
+            .collect(),
+    }
+}
+
+/// What the graph panel shows about one node, gathered in one call
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeDetails {
+    pub id: String,
+    pub path: String,
+    pub title: Option<String>,
+    pub kind: String,
+    pub collection: String,
+    /// Words of prose in LaTeX sources, commands and comments left out
+    pub word_count: Option<usize>,
+    /// Size in bytes of the file on disk
+    pub size: Option<u64>,
+    /// Modification time of the file, in seconds since the epoch
+    pub modified: Option<i64>,
+    pub compile_status: Option<crate::compile_status::CompileStatus>,
+    pub tags: Vec<String>,
+    /// Resources depending on this one
+    pub in_degree: i64,
+    /// Resources this one depends on
+    pub out_degree: i64,
+}
+
 // Extensions that are allowed in the graph
 const ALLOWED_EXTENSIONS: &[&str] = &[".tex", ".bib", ".sty", ".cls", ".dtx", ".ins"];
 
@@ -89,6 +332,266 @@ struct LinkRow {
     link_type: String,
 }
 
+fn row_to_resource(row: &sqlx::sqlite::SqliteRow) -> ResourceRow {
+    ResourceRow {
+        id: row.get("id"),
+        path: row.get("path"),
+        title: row.get("title"),
+        kind: row.get("kind"),
+        collection: row.get("collection"),
+    }
+}
+
+async fn load_links(manager: &DatabaseManager) -> Result<Vec<LinkRow>, String> {
+    let rows = sqlx::query("SELECT source_id, target_id, relation_type FROM dependencies")
+        .fetch_all(&manager.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .iter()
+        .map(|row| LinkRow {
+            source_id: row.get("source_id"),
+            target_id: row.get("target_id"),
+            link_type: row.get("relation_type"),
+        })
+        .collect())
+}
+
+async fn load_resources_by_id(
+    manager: &DatabaseManager,
+    ids: &[&str],
+) -> Result<HashMap<String, ResourceRow>, String> {
+    let rows = sqlx::query(
+        "SELECT id, path, title, type as kind, collection FROM resources
+         WHERE id IN (SELECT value FROM json_each(?))",
+    )
+    .bind(serde_json::to_string(ids).map_err(|e| e.to_string())?)
+    .fetch_all(&manager.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .iter()
+        .map(row_to_resource)
+        .map(|r| (r.id.clone(), r))
+        .collect())
+}
+
+/// Whether a resource belongs in the graph: an allowed extension, and not
+/// hidden by the UI filter toggles (.tex files always show)
+fn is_visible(r: &ResourceRow, filters: &GraphFilters) -> bool {
+    let lower_path = r.path.to_lowercase();
+
+    // Check allowed extensions first; artifacts and images are left out
+    if !ALLOWED_EXTENSIONS
+        .iter()
+        .any(|ext| lower_path.ends_with(ext))
+        || EXCLUDED_EXTENSIONS
+            .iter()
+            .any(|ext| lower_path.ends_with(ext))
+    {
+        return false;
+    }
+
+    // Always show .tex files
+    if lower_path.ends_with(".tex") {
+        return true;
+    }
+
+    // Apply filter toggles
+    match r.kind.as_deref().unwrap_or("document") {
+        "package" => filters.show_packages,
+        "bibliography" => filters.show_bibliographies,
+        "image" => filters.show_images,
+        "class" => filters.show_classes,
+        "dtx" => filters.show_dtx,
+        "ins" => filters.show_ins,
+        _ => true,
+    }
+}
+
+/// Node for a resource, sized by its number of connections
+fn graph_node(r: &ResourceRow, connections: usize) -> GraphNode {
+    let kind = r.kind.as_deref().unwrap_or("document").to_string();
+    let name = r.title.clone().unwrap_or_else(|| {
+        r.path
+            .rsplit(|c| c == '/' || c == '\\')
+            .next()
+            .unwrap_or(&r.id)
+            .to_string()
+    });
+
+    // Cap size: val = min(10, 1 + count * 0.5)
+    let val = (1.0 + connections as f64 * 0.5).min(10.0);
+
+    GraphNode {
+        id: r.id.clone(),
+        name,
+        group: r.collection.clone(),
+        kind,
+        collection: r.collection.clone(),
+        path: r.path.clone(),
+        val,
+        distance: None,
+        x: None,
+        y: None,
+    }
+}
+
+/// Nodes for the bibliography entries the `documents` cite, and `cite`
+/// links from each document to them. A document that declares its .bib
+/// files only cites entries of those, like in `bib::check`.
+async fn citation_layer(
+    manager: &DatabaseManager,
+    collections: &[String],
+    resources: &[ResourceRow],
+    documents: &[&ResourceRow],
+    links: &[LinkRow],
+) -> Result<(Vec<GraphNode>, Vec<GraphLinkOutput>), String> {
+    let entries = crate::bib::search_entries(&manager.pool, "", collections, i64::MAX).await?;
+    let mut by_key: HashMap<&str, Vec<&crate::bib::BibEntry>> = HashMap::new();
+    for entry in &entries {
+        by_key.entry(&entry.citation_key).or_default().push(entry);
+    }
+    let mut bibliographies: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for l in links.iter().filter(|l| l.link_type == "bibliography") {
+        bibliographies
+            .entry(&l.source_id)
+            .or_default()
+            .insert(&l.target_id);
+    }
+    let collection_of: HashMap<&str, &str> = resources
+        .iter()
+        .map(|r| (r.id.as_str(), r.collection.as_str()))
+        .collect();
+
+    let mut nodes: HashMap<i64, GraphNode> = HashMap::new();
+    let mut cite_links = Vec::new();
+    for doc in documents
+        .iter()
+        .filter(|r| r.path.to_lowercase().ends_with(".tex"))
+    {
+        let Ok(content) = std::fs::read_to_string(&doc.path) else {
+            continue;
+        };
+        let bibs = bibliographies.get(doc.id.as_str());
+        let mut seen = HashSet::new();
+        for citation in crate::dependency_scanner::extract_citations(&content) {
+            if !seen.insert(citation.key.clone()) {
+                continue;
+            }
+            let cited = by_key.get(citation.key.as_str()).into_iter().flatten();
+            for entry in cited.filter(|e| bibs.is_none_or(|b| b.contains(e.resource_id.as_str()))) {
+                let node = nodes.entry(entry.id).or_insert_with(|| {
+                    let collection = collection_of
+                        .get(entry.resource_id.as_str())
+                        .copied()
+                        .unwrap_or(&doc.collection)
+                        .to_string();
+                    GraphNode {
+                        id: format!("bib:{}", entry.id),
+                        name: entry.citation_key.clone(),
+                        group: collection.clone(),
+                        kind: "citation".to_string(),
+                        collection,
+                        path: entry.resource_path.clone(),
+                        val: 1.0,
+                        distance: None,
+                        x: None,
+                        y: None,
+                    }
+                });
+                cite_links.push(GraphLinkOutput::new(&doc.id, &node.id, "cite"));
+            }
+        }
+    }
+
+    let mut nodes: Vec<GraphNode> = nodes.into_values().collect();
+    nodes.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok((nodes, cite_links))
+}
+
+/// Nodes for the values of the metadata fields in `filters` (a tag, a
+/// difficulty, a custom field value, ...), each linked to the resources in
+/// `visible` that carry it, so resources sharing a topic cluster together
+async fn metadata_layer(
+    manager: &DatabaseManager,
+    filters: &GraphFilters,
+    visible: &HashSet<&String>,
+) -> Result<(Vec<GraphNode>, Vec<GraphLinkOutput>), String> {
+    // (field label, node kind, resource id, value)
+    let mut pairs: Vec<(String, &str, String, String)> = Vec::new();
+
+    let values = tree_groups::load_values(&manager.pool, &filters.metadata_fields).await?;
+    for (field, by_resource) in &values {
+        let kind = if *field == GroupField::Tag {
+            "tag"
+        } else {
+            "metadata"
+        };
+        for (resource_id, found) in by_resource {
+            for value in found {
+                pairs.push((
+                    format!("{:?}", field),
+                    kind,
+                    resource_id.clone(),
+                    field.folder_name(value),
+                ));
+            }
+        }
+    }
+
+    if !filters.custom_fields.is_empty() {
+        let rows = sqlx::query(
+            "SELECT v.resource_id, d.name, v.value
+             FROM custom_field_values v
+             JOIN custom_field_definitions d ON d.id = v.field_id
+             WHERE d.name IN (SELECT value FROM json_each(?)) AND v.value IS NOT NULL",
+        )
+        .bind(serde_json::to_string(&filters.custom_fields).map_err(|e| e.to_string())?)
+        .fetch_all(&manager.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        for row in rows {
+            let name: String = row.get("name");
+            let value: String = row.get("value");
+            pairs.push((
+                name.clone(),
+                "metadata",
+                row.get("resource_id"),
+                format!("{}: {}", name, value),
+            ));
+        }
+    }
+
+    let mut nodes: HashMap<String, GraphNode> = HashMap::new();
+    let mut links = Vec::new();
+    for (field, kind, resource_id, value) in pairs {
+        if !visible.contains(&resource_id) {
+            continue;
+        }
+        let id = format!("meta:{}:{}", field, value);
+        nodes.entry(id.clone()).or_insert_with(|| GraphNode {
+            id: id.clone(),
+            name: value,
+            group: String::new(),
+            kind: kind.to_string(),
+            collection: String::new(),
+            path: String::new(),
+            val: 1.0,
+            distance: None,
+            x: None,
+            y: None,
+        });
+        links.push(GraphLinkOutput::new(&resource_id, &id, kind));
+    }
+
+    let mut nodes: Vec<GraphNode> = nodes.into_values().collect();
+    nodes.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok((nodes, links))
+}
+
 /// Process graph data with filtering and centrality calculation
 pub async fn process_graph_data(
     manager: &DatabaseManager,
@@ -119,147 +622,508 @@ pub async fn process_graph_data(
         .await
         .map_err(|e| e.to_string())?;
 
-    let resources: Vec<ResourceRow> = rows
+    let resources: Vec<ResourceRow> = rows.iter().map(row_to_resource).collect();
+
+    // 2. Fetch all dependencies (links)
+    let all_links = load_links(manager).await?;
+
+    // 3-4. Filter resources by extension and the UI toggles
+    let filtered_resources: Vec<&ResourceRow> = resources
         .iter()
-        .map(|row| ResourceRow {
-            id: row.get("id"),
-            path: row.get("path"),
-            title: row.get("title"),
-            kind: row.get("kind"),
-            collection: row.get("collection"),
-        })
+        .filter(|r| is_visible(r, &filters))
         .collect();
 
-    // 2. Fetch all dependencies (links)
-    let links_rows = sqlx::query("SELECT source_id, target_id, relation_type FROM dependencies")
-        .fetch_all(&manager.pool)
-        .await
-        .map_err(|e| e.to_string())?;
+    // 5. Build node ID set for link filtering
+    let node_ids: HashSet<&String> = filtered_resources.iter().map(|r| &r.id).collect();
 
-    let all_links: Vec<LinkRow> = links_rows
+    // 6. Filter links to only include those with both endpoints in our node
+    // set, and of a relation type that is shown
+    let mut filtered_links: Vec<GraphLinkOutput> = all_links
         .iter()
-        .map(|row| LinkRow {
-            source_id: row.get("source_id"),
-            target_id: row.get("target_id"),
-            link_type: row.get("relation_type"),
-        })
+        .filter(|l| node_ids.contains(&l.source_id) && node_ids.contains(&l.target_id))
+        .filter(|l| filters.shows_relation(&l.link_type))
+        .map(|l| GraphLinkOutput::new(&l.source_id, &l.target_id, &l.link_type))
         .collect();
 
-    // 3. Filter resources by extension (allowed list)
-    let active_resources: Vec<&ResourceRow> = resources
+    // 6b. Citation layer: cited .bib entries and the documents citing them
+    let (mut extra_nodes, citation_links) =
+        if filters.show_citations && filters.shows_relation("cite") {
+            citation_layer(
+                manager,
+                &collections,
+                &resources,
+                &filtered_resources,
+                &all_links,
+            )
+            .await?
+        } else {
+            (Vec::new(), Vec::new())
+        };
+    filtered_links.extend(citation_links);
+
+    // 6c. Metadata layer: tag / field values shared by resources
+    let (metadata_nodes, metadata_links) = metadata_layer(manager, &filters, &node_ids).await?;
+    extra_nodes.extend(metadata_nodes);
+    filtered_links.extend(metadata_links);
+    let filtered_links = merge_links(filtered_links);
+
+    // 7. Calculate centrality (connection count per node)
+    let mut connection_count: HashMap<&String, usize> = HashMap::new();
+    for link in &filtered_links {
+        *connection_count.entry(&link.source).or_insert(0) += link.weight;
+        *connection_count.entry(&link.target).or_insert(0) += link.weight;
+    }
+
+    // 8. Build final nodes with centrality-based sizing
+    let mut nodes: Vec<GraphNode> = filtered_resources
         .iter()
-        .filter(|r| {
-            let lower_path = r.path.to_lowercase();
+        .map(|r| graph_node(r, connection_count.get(&r.id).copied().unwrap_or(0)))
+        .collect();
+    nodes.extend(extra_nodes.into_iter().map(|mut node| {
+        let count = connection_count.get(&node.id).copied().unwrap_or(0);
+        node.val = (1.0 + count as f64 * 0.5).min(10.0);
+        node
+    }));
 
-            // Check allowed extensions first
-            if ALLOWED_EXTENSIONS
-                .iter()
-                .any(|ext| lower_path.ends_with(ext))
-            {
-                return true;
-            }
+    if filters.layout {
+        place_nodes(&mut nodes, &filtered_links).await?;
+    }
 
-            // Exclude known artifacts/images
-            if EXCLUDED_EXTENSIONS
-                .iter()
-                .any(|ext| lower_path.ends_with(ext))
-            {
-                return false;
-            }
+    Ok(GraphData {
+        nodes,
+        links: filtered_links,
+    })
+}
 
-            false
+/// Fill in the positions of `nodes` with a force-directed layout, off the
+/// async runtime since it can take seconds on large graphs
+async fn place_nodes(nodes: &mut [GraphNode], links: &[GraphLinkOutput]) -> Result<(), String> {
+    let index: HashMap<&str, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, n)| (n.id.as_str(), i))
+        .collect();
+    let edges: Vec<(usize, usize)> = links
+        .iter()
+        .filter_map(|l| {
+            Some((
+                *index.get(l.source.as_str())?,
+                *index.get(l.target.as_str())?,
+            ))
         })
         .collect();
+    let count = nodes.len();
 
-    // 4. Apply UI filter toggles
-    let filtered_resources: Vec<&ResourceRow> = active_resources
-        .into_iter()
-        .filter(|r| {
-            let kind = r.kind.as_deref().unwrap_or("document");
-            let lower_path = r.path.to_lowercase();
+    let positions = tauri::async_runtime::spawn_blocking(move || {
+        crate::graph_layout::fruchterman_reingold(count, &edges)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    for (node, (x, y)) in nodes.iter_mut().zip(positions) {
+        node.x = Some(x);
+        node.y = Some(y);
+    }
+    Ok(())
+}
 
-            // Always show .tex files
-            if lower_path.ends_with(".tex") {
-                return true;
-            }
+/// The resources within `depth` hops of `resource_id` (following links in
+/// either direction, across collections) and the links between them. With
+/// `filters`, hidden resources are neither shown nor walked through.
+pub async fn node_neighborhood(
+    manager: &DatabaseManager,
+    resource_id: &str,
+    depth: usize,
+    filters: Option<&GraphFilters>,
+) -> Result<GraphData, String> {
+    let mut all_links = load_links(manager).await?;
+    if let Some(filters) = filters {
+        all_links.retain(|l| filters.shows_relation(&l.link_type));
+    }
+    let mut adjacent: HashMap<&str, Vec<&str>> = HashMap::new();
+    for l in &all_links {
+        adjacent.entry(&l.source_id).or_default().push(&l.target_id);
+        adjacent.entry(&l.target_id).or_default().push(&l.source_id);
+    }
 
-            // Apply filter toggles
-            if kind == "package" && !filters.show_packages {
-                return false;
-            }
-            if kind == "bibliography" && !filters.show_bibliographies {
-                return false;
+    // Candidate ids by hop count, checked against the resources table below
+    let mut distances: HashMap<&str, usize> = HashMap::from([(resource_id, 0)]);
+    let mut frontier = vec![resource_id];
+    for hop in 1..=depth {
+        let mut next = Vec::new();
+        for id in frontier {
+            for &neighbor in adjacent.get(id).into_iter().flatten() {
+                if !distances.contains_key(neighbor) {
+                    distances.insert(neighbor, hop);
+                    next.push(neighbor);
+                }
             }
-            if kind == "image" && !filters.show_images {
-                return false;
+        }
+        frontier = next;
+    }
+
+    let ids: Vec<&str> = distances.keys().copied().collect();
+    let resources = load_resources_by_id(manager, &ids).await?;
+    if !resources.contains_key(resource_id) {
+        return Err("Resource not found".to_string());
+    }
+
+    // With filters, walk again through visible resources only, so a hidden
+    // package doesn't connect two documents
+    let distances = match filters {
+        None => distances,
+        Some(filters) => {
+            let visible = |id: &str| {
+                id == resource_id || resources.get(id).is_some_and(|r| is_visible(r, filters))
+            };
+            let mut kept: HashMap<&str, usize> = HashMap::from([(resource_id, 0)]);
+            let mut frontier = vec![resource_id];
+            for hop in 1..=depth {
+                let mut next = Vec::new();
+                for id in frontier {
+                    for &neighbor in adjacent.get(id).into_iter().flatten() {
+                        if visible(neighbor) && !kept.contains_key(neighbor) {
+                            kept.insert(neighbor, hop);
+                            next.push(neighbor);
+                        }
+                    }
+                }
+                frontier = next;
             }
-            if kind == "class" && !filters.show_classes {
-                return false;
+            kept
+        }
+    };
+
+    let links: Vec<GraphLinkOutput> = all_links
+        .iter()
+        .filter(|l| {
+            resources.contains_key(&l.source_id)
+                && resources.contains_key(&l.target_id)
+                && distances.contains_key(l.source_id.as_str())
+                && distances.contains_key(l.target_id.as_str())
+        })
+        .map(|l| GraphLinkOutput::new(&l.source_id, &l.target_id, &l.link_type))
+        .collect();
+    let links = merge_links(links);
+
+    let mut connection_count: HashMap<&str, usize> = HashMap::new();
+    for link in &links {
+        *connection_count.entry(&link.source).or_insert(0) += link.weight;
+        *connection_count.entry(&link.target).or_insert(0) += link.weight;
+    }
+
+    let mut nodes: Vec<GraphNode> = distances
+        .iter()
+        .filter_map(|(id, &distance)| {
+            let r = resources.get(*id)?;
+            let mut node = graph_node(r, connection_count.get(id).copied().unwrap_or(0));
+            node.distance = Some(distance);
+            Some(node)
+        })
+        .collect();
+    nodes.sort_by(|a, b| {
+        a.distance
+            .cmp(&b.distance)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    Ok(GraphData { nodes, links })
+}
+
+/// Fewest-hop chain of dependencies leading from `from` to `to`, both ends
+/// included, following links in their direction only
+fn shortest_path<'a>(links: &'a [LinkRow], from: &'a str, to: &str) -> Option<Vec<&'a str>> {
+    let mut outgoing: HashMap<&str, Vec<&str>> = HashMap::new();
+    for l in links {
+        outgoing.entry(&l.source_id).or_default().push(&l.target_id);
+    }
+
+    let mut previous: HashMap<&str, &str> = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+    while let Some(id) = queue.pop_front() {
+        if id == to {
+            let mut path = vec![id];
+            while let Some(&prev) = previous.get(path[path.len() - 1]) {
+                path.push(prev);
             }
-            if kind == "dtx" && !filters.show_dtx {
-                return false;
+            path.reverse();
+            return Some(path);
+        }
+        for &next in outgoing.get(id).into_iter().flatten() {
+            if next != from && !previous.contains_key(next) {
+                previous.insert(next, id);
+                queue.push_back(next);
             }
-            if kind == "ins" && !filters.show_ins {
-                return false;
+        }
+    }
+    None
+}
+
+/// Everything that depends on `id`, directly or through other resources,
+/// with the number of hops to it
+fn dependents<'a>(links: &'a [LinkRow], id: &'a str) -> HashMap<&'a str, usize> {
+    let mut incoming: HashMap<&str, Vec<&str>> = HashMap::new();
+    for l in links {
+        incoming.entry(&l.target_id).or_default().push(&l.source_id);
+    }
+
+    let mut distances: HashMap<&str, usize> = HashMap::new();
+    let mut queue = VecDeque::from([(id, 0)]);
+    while let Some((current, distance)) = queue.pop_front() {
+        for &source in incoming.get(current).into_iter().flatten() {
+            if source != id && !distances.contains_key(source) {
+                distances.insert(source, distance + 1);
+                queue.push_back((source, distance + 1));
             }
+        }
+    }
+    distances
+}
+
+/// The chain of resources through which `source_id` depends on `target_id`
+/// (e.g. exam → chapter → macros.sty), or `None` if it doesn't
+pub async fn dependency_path(
+    manager: &DatabaseManager,
+    source_id: &str,
+    target_id: &str,
+) -> Result<Option<GraphData>, String> {
+    let all_links = load_links(manager).await?;
+    let Some(path) = shortest_path(&all_links, source_id, target_id) else {
+        return Ok(None);
+    };
+    let resources = load_resources_by_id(manager, &path).await?;
 
-            true
+    let nodes: Vec<GraphNode> = path
+        .iter()
+        .enumerate()
+        .filter_map(|(i, id)| {
+            let mut node = graph_node(resources.get(*id)?, 0);
+            node.distance = Some(i);
+            Some(node)
+        })
+        .collect();
+    let links = path
+        .windows(2)
+        .flat_map(|pair| {
+            all_links
+                .iter()
+                .filter(move |l| l.source_id == pair[0] && l.target_id == pair[1])
         })
+        .map(|l| GraphLinkOutput::new(&l.source_id, &l.target_id, &l.link_type))
         .collect();
+    let links = merge_links(links);
 
-    // 5. Build node ID set for link filtering
-    let node_ids: HashSet<&String> = filtered_resources.iter().map(|r| &r.id).collect();
+    Ok(Some(GraphData { nodes, links }))
+}
+
+/// Resources affected by a change to `resource_id`: everything that
+/// includes, inputs or loads it, transitively. Nearest first.
+pub async fn impacted_documents(
+    manager: &DatabaseManager,
+    resource_id: &str,
+) -> Result<Vec<GraphNode>, String> {
+    let all_links = load_links(manager).await?;
+    let distances = dependents(&all_links, resource_id);
+    let ids: Vec<&str> = distances.keys().copied().collect();
+    let resources = load_resources_by_id(manager, &ids).await?;
 
-    // 6. Filter links to only include those with both endpoints in our node set
-    let filtered_links: Vec<GraphLinkOutput> = all_links
+    let mut nodes: Vec<GraphNode> = distances
         .iter()
-        .filter(|l| node_ids.contains(&l.source_id) && node_ids.contains(&l.target_id))
-        .map(|l| GraphLinkOutput {
-            source: l.source_id.clone(),
-            target: l.target_id.clone(),
-            link_type: l.link_type.clone(),
+        .filter_map(|(id, &distance)| {
+            let mut node = graph_node(resources.get(*id)?, 0);
+            node.distance = Some(distance);
+            Some(node)
         })
         .collect();
+    nodes.sort_by(|a, b| {
+        a.distance
+            .cmp(&b.distance)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    Ok(nodes)
+}
 
-    // 7. Calculate centrality (connection count per node)
-    let mut connection_count: HashMap<&String, usize> = HashMap::new();
-    for link in &filtered_links {
-        *connection_count.entry(&link.source).or_insert(0) += 1;
-        *connection_count.entry(&link.target).or_insert(0) += 1;
-    }
+/// The dependency graph as it was at a commit: the extractor runs over the
+/// blobs of that revision and references are resolved against the files
+/// that existed then. Files still in the database keep their resource id,
+/// so nodes line up with the current graph; the others get `git:<path>`.
+pub async fn graph_at_commit(
+    manager: &DatabaseManager,
+    repo_path: &str,
+    commit_id: &str,
+    filters: &GraphFilters,
+) -> Result<GraphData, String> {
+    let (repo_path, commit_id) = (repo_path.to_string(), commit_id.to_string());
+    let (workdir, files) = tauri::async_runtime::spawn_blocking(move || {
+        crate::git::read_files_at_commit(&repo_path, &commit_id, |path| {
+            crate::dependency_scanner::is_source(path)
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())??;
 
-    // 8. Build final nodes with centrality-based sizing
-    let nodes: Vec<GraphNode> = filtered_resources
+    let absolute = |rel: &str| -> PathBuf { workdir.join(rel) };
+    let existing: HashSet<PathBuf> = files.iter().map(|(rel, _)| absolute(rel)).collect();
+
+    // Current resources at the same paths
+    let paths: Vec<String> = existing
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    let rows = sqlx::query(
+        "SELECT id, path, title, type as kind, collection FROM resources
+         WHERE path IN (SELECT value FROM json_each(?))",
+    )
+    .bind(serde_json::to_string(&paths).map_err(|e| e.to_string())?)
+    .fetch_all(&manager.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let mut known: HashMap<String, ResourceRow> = rows
+        .iter()
+        .map(row_to_resource)
+        .map(|r| (r.path.clone(), r))
+        .collect();
+
+    let resources: Vec<ResourceRow> = files
+        .iter()
+        .map(|(rel, _)| {
+            let path = absolute(rel).to_string_lossy().to_string();
+            known.remove(&path).unwrap_or_else(|| ResourceRow {
+                id: format!("git:{}", rel),
+                kind: Some(crate::resources::kind_from_path(&path).to_string()),
+                title: None,
+                collection: String::new(),
+                path,
+            })
+        })
+        .collect();
+    let id_of: HashMap<&str, &str> = resources
         .iter()
-        .map(|r| {
-            let kind = r.kind.as_deref().unwrap_or("document").to_string();
-            let name = r.title.clone().unwrap_or_else(|| {
-                r.path
-                    .rsplit(|c| c == '/' || c == '\\')
-                    .next()
-                    .unwrap_or(&r.id)
-                    .to_string()
-            });
-
-            let count = connection_count.get(&r.id).copied().unwrap_or(0);
-            // Cap size: val = min(10, 1 + count * 0.5)
-            let val = (1.0 + count as f64 * 0.5).min(10.0);
-
-            GraphNode {
-                id: r.id.clone(),
-                name,
-                group: r.collection.clone(),
-                kind,
-                collection: r.collection.clone(),
-                path: r.path.clone(),
-                val,
+        .map(|r| (r.path.as_str(), r.id.as_str()))
+        .collect();
+
+    let mut all_links = Vec::new();
+    for (rel, content) in &files {
+        let Some(content) = content else {
+            continue;
+        };
+        let source = absolute(rel);
+        let Some(&source_id) = id_of.get(source.to_string_lossy().as_ref()) else {
+            continue;
+        };
+        let mut seen = HashSet::new();
+        for reference in crate::dependency_scanner::extract_references(content) {
+            let target =
+                crate::dependency_scanner::candidate_paths(&source, Some(&workdir), &reference)
+                    .into_iter()
+                    .find(|p| existing.contains(p));
+            let Some(target) = target.filter(|t| *t != source) else {
+                continue;
+            };
+            let Some(&target_id) = id_of.get(target.to_string_lossy().as_ref()) else {
+                continue;
+            };
+            if seen.insert((target_id, reference.relation)) {
+                all_links.push(LinkRow {
+                    source_id: source_id.to_string(),
+                    target_id: target_id.to_string(),
+                    link_type: reference.relation.to_string(),
+                });
             }
+        }
+    }
+
+    let visible: Vec<&ResourceRow> = resources
+        .iter()
+        .filter(|r| is_visible(r, filters))
+        .collect();
+    let node_ids: HashSet<&str> = visible.iter().map(|r| r.id.as_str()).collect();
+    let links: Vec<GraphLinkOutput> = all_links
+        .iter()
+        .filter(|l| {
+            node_ids.contains(l.source_id.as_str()) && node_ids.contains(l.target_id.as_str())
         })
+        .filter(|l| filters.shows_relation(&l.link_type))
+        .map(|l| GraphLinkOutput::new(&l.source_id, &l.target_id, &l.link_type))
         .collect();
+    let links = merge_links(links);
 
-    Ok(GraphData {
-        nodes,
-        links: filtered_links,
+    let mut connection_count: HashMap<&str, usize> = HashMap::new();
+    for link in &links {
+        *connection_count.entry(&link.source).or_insert(0) += link.weight;
+        *connection_count.entry(&link.target).or_insert(0) += link.weight;
+    }
+    let mut nodes: Vec<GraphNode> = visible
+        .iter()
+        .map(|r| graph_node(r, connection_count.get(r.id.as_str()).copied().unwrap_or(0)))
+        .collect();
+
+    if filters.layout {
+        place_nodes(&mut nodes, &links).await?;
+    }
+    Ok(GraphData { nodes, links })
+}
+
+/// Approximate number of words in a LaTeX source: comments, control
+/// sequences and environment names are dropped, and only tokens with a
+/// letter or digit count
+fn word_count(content: &str) -> usize {
+    static COMMAND_RE: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    let re = COMMAND_RE
+        .get_or_init(|| regex::Regex::new(r"\\(?:begin|end)\s*\{[^}]*\}|\\[a-zA-Z@]+\*?").unwrap());
+    let text = crate::dependency_scanner::strip_comments(content);
+    re.replace_all(&text, " ")
+        .split_whitespace()
+        .filter(|w| w.chars().any(char::is_alphanumeric))
+        .count()
+}
+
+/// Everything the graph panel shows about a resource
+pub async fn node_details(
+    manager: &DatabaseManager,
+    resource_id: &str,
+) -> Result<NodeDetails, String> {
+    let resource = manager
+        .get_resource_by_id(resource_id)
+        .await?
+        .ok_or("Resource not found")?;
+
+    let metadata = std::fs::metadata(&resource.path).ok();
+    let word_count = crate::dependency_scanner::is_source(&resource.path)
+        .then(|| std::fs::read_to_string(&resource.path).ok())
+        .flatten()
+        .map(|content| word_count(&content));
+    let tags: Vec<String> =
+        sqlx::query_scalar("SELECT tag FROM resource_tags WHERE resource_id = ? ORDER BY tag")
+            .bind(resource_id)
+            .fetch_all(&manager.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    let (in_degree, out_degree): (i64, i64) = sqlx::query_as(
+        "SELECT COUNT(DISTINCT CASE WHEN target_id = ?1 THEN source_id END),
+                COUNT(DISTINCT CASE WHEN source_id = ?1 THEN target_id END)
+         FROM dependencies WHERE source_id = ?1 OR target_id = ?1",
+    )
+    .bind(resource_id)
+    .fetch_one(&manager.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(NodeDetails {
+        word_count,
+        size: metadata.as_ref().map(|m| m.len()),
+        modified: metadata
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64),
+        compile_status: crate::compile_status::get(&manager.pool, resource_id).await?,
+        tags,
+        in_degree,
+        out_degree,
+        id: resource.id,
+        path: resource.path,
+        title: resource.title,
+        kind: resource.kind,
+        collection: resource.collection,
     })
 }
 
@@ -269,9 +1133,278 @@ pub async fn get_graph_data_cmd(
     state: tauri::State<'_, crate::AppState>,
     collections: Vec<String>,
     filters: GraphFilters,
+) -> Result<GraphData, String> {
+    let key = GraphCache::key(&collections, &filters);
+    let generation = state.graph_cache.generation();
+    if let Some((_, data)) = state.graph_cache.fresh(&key) {
+        return Ok(data);
+    }
+
+    let guard = state.db_manager.lock().await;
+    let manager = guard.as_ref().ok_or("Database not initialized")?;
+
+    let data = process_graph_data(manager, collections, filters).await?;
+    state.graph_cache.store(key, data.clone(), generation);
+    Ok(data)
+}
+
+/// Tauri command to get what changed in a graph since version `since`, so
+/// the view can patch the rendered graph instead of laying it out again.
+/// Without `since`, or when that version is no longer known, the whole
+/// graph is returned with `full` set.
+#[tauri::command]
+pub async fn get_graph_delta_cmd(
+    state: tauri::State<'_, crate::AppState>,
+    collections: Vec<String>,
+    filters: GraphFilters,
+    since: Option<u64>,
+) -> Result<GraphDelta, String> {
+    let key = GraphCache::key(&collections, &filters);
+    let full = |version: u64, data: GraphData| GraphDelta {
+        version,
+        full: true,
+        nodes: data.nodes,
+        removed_nodes: Vec::new(),
+        links: data.links,
+        removed_links: Vec::new(),
+    };
+
+    let generation = state.graph_cache.generation();
+    if let Some((version, data)) = state.graph_cache.fresh(&key) {
+        return Ok(match since {
+            Some(since) if since == version => diff_graphs(&data, &data, version),
+            _ => full(version, data),
+        });
+    }
+
+    let guard = state.db_manager.lock().await;
+    let manager = guard.as_ref().ok_or("Database not initialized")?;
+
+    let data = process_graph_data(manager, collections, filters).await?;
+    let (version, previous) = state.graph_cache.store(key, data.clone(), generation);
+    Ok(match previous {
+        Some((base, old)) if Some(base) == since => diff_graphs(&old, &data, version),
+        _ => full(version, data),
+    })
+}
+
+/// Tauri command to get the neighborhood of one resource
+#[tauri::command]
+pub async fn get_node_neighborhood_cmd(
+    state: tauri::State<'_, crate::AppState>,
+    resource_id: String,
+    depth: Option<usize>,
+    filters: Option<GraphFilters>,
+) -> Result<GraphData, String> {
+    let guard = state.db_manager.lock().await;
+    let manager = guard.as_ref().ok_or("Database not initialized")?;
+
+    node_neighborhood(manager, &resource_id, depth.unwrap_or(1), filters.as_ref()).await
+}
+
+/// Tauri command to find how one resource depends on another
+#[tauri::command]
+pub async fn get_dependency_path_cmd(
+    state: tauri::State<'_, crate::AppState>,
+    source_id: String,
+    target_id: String,
+) -> Result<Option<GraphData>, String> {
+    let guard = state.db_manager.lock().await;
+    let manager = guard.as_ref().ok_or("Database not initialized")?;
+
+    dependency_path(manager, &source_id, &target_id).await
+}
+
+/// Tauri command to list the resources affected by changing one
+#[tauri::command]
+pub async fn get_impacted_documents_cmd(
+    state: tauri::State<'_, crate::AppState>,
+    resource_id: String,
+) -> Result<Vec<GraphNode>, String> {
+    let guard = state.db_manager.lock().await;
+    let manager = guard.as_ref().ok_or("Database not initialized")?;
+
+    impacted_documents(manager, &resource_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links(pairs: &[(&str, &str)]) -> Vec<LinkRow> {
+        pairs
+            .iter()
+            .map(|(s, t)| LinkRow {
+                source_id: s.to_string(),
+                target_id: t.to_string(),
+                link_type: "input".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        // exam -> ch1 -> ch2 -> macros, and a shortcut exam -> ch2
+        let l = links(&[
+            ("exam", "ch1"),
+            ("ch1", "ch2"),
+            ("ch2", "macros"),
+            ("exam", "ch2"),
+        ]);
+        assert_eq!(
+            shortest_path(&l, "exam", "macros"),
+            Some(vec!["exam", "ch2", "macros"])
+        );
+        assert_eq!(shortest_path(&l, "exam", "exam"), Some(vec!["exam"]));
+        // Links are only followed forwards
+        assert_eq!(shortest_path(&l, "macros", "exam"), None);
+    }
+
+    fn node(id: &str, val: f64) -> GraphNode {
+        GraphNode {
+            id: id.to_string(),
+            name: id.to_string(),
+            group: "algebra".to_string(),
+            kind: "document".to_string(),
+            collection: "algebra".to_string(),
+            path: format!("/c/{}.tex", id),
+            val,
+            distance: None,
+            x: None,
+            y: None,
+        }
+    }
+
+    fn output(source: &str, target: &str) -> GraphLinkOutput {
+        GraphLinkOutput::new(source, target, "input")
+    }
+
+    #[test]
+    fn test_diff_graphs() {
+        let old = GraphData {
+            nodes: vec![node("a", 1.5), node("b", 1.5), node("c", 1.0)],
+            links: vec![output("a", "b")],
+        };
+        let new = GraphData {
+            nodes: vec![node("a", 2.0), node("b", 1.5), node("d", 1.5)],
+            links: vec![output("a", "b"), output("a", "d")],
+        };
+        let delta = diff_graphs(&old, &new, 7);
+        assert_eq!(delta.version, 7);
+        assert!(!delta.full);
+        let ids: Vec<&str> = delta.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, ["a", "d"]);
+        assert_eq!(delta.removed_nodes, ["c"]);
+        assert_eq!(delta.links, [output("a", "d")]);
+        assert!(delta.removed_links.is_empty());
+
+        let same = diff_graphs(&new, &new, 8);
+        assert!(same.nodes.is_empty() && same.links.is_empty());
+
+        // A link whose weight changed is replaced
+        let heavier = GraphData {
+            nodes: new.nodes.clone(),
+            links: merge_links(vec![
+                output("a", "b"),
+                GraphLinkOutput::new("a", "b", "package"),
+                output("a", "d"),
+            ]),
+        };
+        let delta = diff_graphs(&new, &heavier, 9);
+        assert_eq!(delta.links, [heavier.links[0].clone()]);
+        assert_eq!(delta.removed_links, [output("a", "b")]);
+    }
+
+    #[test]
+    fn test_cache_keeps_raced_builds_stale() {
+        let cache = GraphCache::default();
+        let graph = GraphData {
+            nodes: vec![node("a", 1.0)],
+            links: Vec::new(),
+        };
+
+        let generation = cache.generation();
+        cache.store("k".to_string(), graph.clone(), generation);
+        assert_eq!(cache.fresh("k").map(|(_, data)| data), Some(graph.clone()));
+
+        // Invalidated while the graph was being built
+        let generation = cache.generation();
+        cache.invalidate();
+        let (version, previous) = cache.store("k".to_string(), graph.clone(), generation);
+        assert!(previous.is_some());
+        assert!(cache.fresh("k").is_none());
+
+        let (_, previous) = cache.store("k".to_string(), graph, cache.generation());
+        assert_eq!(previous.map(|(v, _)| v), Some(version));
+        assert!(cache.fresh("k").is_some());
+    }
+
+    #[test]
+    fn test_merge_links() {
+        let merged = merge_links(vec![
+            output("a", "b"),
+            GraphLinkOutput::new("a", "c", "graphics"),
+            GraphLinkOutput::new("a", "b", "package"),
+            output("b", "a"),
+            output("a", "b"),
+        ]);
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[0].link_type, "input");
+        assert_eq!(merged[0].relations, ["input", "package"]);
+        assert_eq!(merged[0].weight, 3);
+        assert_eq!(merged[1].weight, 1);
+        // Direction matters
+        assert_eq!((merged[2].source.as_str(), merged[2].weight), ("b", 1));
+    }
+
+    #[test]
+    fn test_word_count() {
+        let src = "\\section{Limits} % a comment\n\
+                   Let $x \\to 0$. Then \\emph{the} limit is 1.\n\
+                   \\begin{align} \\end{align} --";
+        // Limits, Let, $x, 0$., Then, {the}, limit, is, 1.
+        assert_eq!(word_count(src), 9);
+    }
+
+    #[test]
+    fn test_dependents() {
+        let l = links(&[
+            ("exam", "ch1"),
+            ("ch1", "macros"),
+            ("quiz", "macros"),
+            ("macros", "ch1"),
+            ("other", "ch2"),
+        ]);
+        let found = dependents(&l, "macros");
+        assert_eq!(found.len(), 3);
+        assert_eq!(found["ch1"], 1);
+        assert_eq!(found["quiz"], 1);
+        assert_eq!(found["exam"], 2);
+    }
+}
+
+/// Tauri command to get the details of one node for the graph panel
+#[tauri::command]
+pub async fn get_node_details_cmd(
+    state: tauri::State<'_, crate::AppState>,
+    resource_id: String,
+) -> Result<NodeDetails, String> {
+    let guard = state.db_manager.lock().await;
+    let manager = guard.as_ref().ok_or("Database not initialized")?;
+
+    node_details(manager, &resource_id).await
+}
+
+/// Tauri command to get the dependency graph of a repository at a commit
+#[tauri::command]
+pub async fn get_graph_at_commit_cmd(
+    state: tauri::State<'_, crate::AppState>,
+    repo_path: String,
+    commit_id: String,
+    filters: GraphFilters,
 ) -> Result<GraphData, String> {
     let guard = state.db_manager.lock().await;
     let manager = guard.as_ref().ok_or("Database not initialized")?;
 
-    process_graph_data(manager, collections, filters).await
+    graph_at_commit(manager, &repo_path, &commit_id, &filters).await
 }
@@ -0,0 +1,148 @@
+//! Graph Reports
+//!
+//! Health checks on the dependency graph: resources nothing links to and
+//! that link to nothing (orphans), and dependency rows whose target file is
+//! gone (broken `\input` / `\includegraphics` / ...), located at the line of
+//! the source that references them.
+
+use serde::Serialize;
+use sqlx::Row;
+use std::path::Path;
+
+use crate::database::DatabaseManager;
+use crate::dependency_scanner;
+
+/// Build artifacts, which are never expected to be linked
+const ARTIFACT_EXTENSIONS: &[&str] = &[
+    ".aux",
+    ".log",
+    ".out",
+    ".toc",
+    ".synctex.gz",
+    ".fls",
+    ".fdb_latexmk",
+    ".bbl",
+    ".blg",
+    ".xdv",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanResource {
+    pub id: String,
+    pub path: String,
+    pub kind: String,
+    pub collection: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrokenLink {
+    pub source_id: String,
+    pub source_path: String,
+    pub collection: String,
+    pub relation: String,
+    pub target_id: String,
+    /// Where the target used to be; `None` when its resource is gone too
+    pub target_path: Option<String>,
+    /// 1-based line of the reference in the source, when it can be found
+    pub line: Option<usize>,
+}
+
+/// `AND <column> IN (...)` for an optional list of collections
+fn collection_clause(column: &str, collections: &Option<Vec<String>>) -> String {
+    match collections {
+        Some(list) => format!(" AND {} IN ({})", column, vec!["?"; list.len()].join(", ")),
+        None => String::new(),
+    }
+}
+
+/// Files with no dependency in either direction
+pub async fn find_orphans(
+    db: &DatabaseManager,
+    collections: Option<Vec<String>>,
+) -> Result<Vec<OrphanResource>, String> {
+    let sql = format!(
+        "SELECT id, path, type AS kind, collection FROM resources r
+         WHERE type != 'folder'
+           AND NOT EXISTS (SELECT 1 FROM dependencies d
+                           WHERE d.source_id = r.id OR d.target_id = r.id){}
+         ORDER BY collection, path",
+        collection_clause("collection", &collections)
+    );
+    let mut query = sqlx::query(&sql);
+    for c in collections.iter().flatten() {
+        query = query.bind(c);
+    }
+    let rows = query.fetch_all(&db.pool).await.map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .iter()
+        .map(|row| OrphanResource {
+            id: row.get("id"),
+            path: row.get("path"),
+            kind: row.get("kind"),
+            collection: row.get("collection"),
+        })
+        .filter(|r| {
+            let lower = r.path.to_lowercase();
+            !ARTIFACT_EXTENSIONS.iter().any(|ext| lower.ends_with(ext))
+        })
+        .collect())
+}
+
+/// Dependencies whose target resource or file no longer exists
+pub async fn find_broken_links(
+    db: &DatabaseManager,
+    collections: Option<Vec<String>>,
+) -> Result<Vec<BrokenLink>, String> {
+    let sql = format!(
+        "SELECT d.source_id, s.path AS source_path, s.collection, d.relation_type,
+                d.target_id, t.path AS target_path
+         FROM dependencies d
+         JOIN resources s ON s.id = d.source_id
+         LEFT JOIN resources t ON t.id = d.target_id
+         WHERE 1 = 1{}
+         ORDER BY s.collection, s.path",
+        collection_clause("s.collection", &collections)
+    );
+    let mut query = sqlx::query(&sql);
+    for c in collections.iter().flatten() {
+        query = query.bind(c);
+    }
+    let rows = query.fetch_all(&db.pool).await.map_err(|e| e.to_string())?;
+
+    let mut broken = Vec::new();
+    for row in rows {
+        let target_path: Option<String> = row.get("target_path");
+        if target_path
+            .as_deref()
+            .is_some_and(|p| Path::new(p).is_file())
+        {
+            continue;
+        }
+        let source_path: String = row.get("source_path");
+        let relation: String = row.get("relation_type");
+
+        let line = target_path.as_deref().and_then(|target| {
+            let content = std::fs::read_to_string(&source_path).ok()?;
+            dependency_scanner::reference_line(
+                &content,
+                &relation,
+                Path::new(&source_path).parent()?,
+                Path::new(target),
+            )
+        });
+
+        broken.push(BrokenLink {
+            source_id: row.get("source_id"),
+            source_path,
+            collection: row.get("collection"),
+            relation,
+            target_id: row.get("target_id"),
+            target_path,
+            line,
+        });
+    }
+    Ok(broken)
+}
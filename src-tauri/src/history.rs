@@ -1,12 +1,19 @@
 //! Local File History Module
 //!
 //! Provides automatic versioning of files with snapshot, diff, and restore capabilities.
+//!
+//! A snapshot is taken every time a file is saved through the app. Contents
+//! are content-addressed: `file_history` rows point at `history_blobs` by
+//! SHA-256, so a version that comes back is stored once (migration 028).
 
 use sha2::{Digest, Sha256};
 use similar::{ChangeTag, TextDiff};
 use sqlx::{Pool, Row, Sqlite};
 use uuid::Uuid;
 
+/// Automatic snapshots kept per file; manual ones are never pruned
+const MAX_AUTO_SNAPSHOTS: i32 = 200;
+
 /// Represents a single history entry for a file
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct HistoryEntry {
@@ -65,7 +72,7 @@ pub async fn save_snapshot(
 
     // Check if the last snapshot has the same hash (avoid duplicate snapshots)
     let last_hash: Option<String> = sqlx::query_scalar(
-        "SELECT content_hash FROM file_history WHERE file_path = ? ORDER BY created_at DESC LIMIT 1"
+        "SELECT content_hash FROM file_history WHERE file_path = ? ORDER BY created_at DESC, rowid DESC LIMIT 1"
     )
     .bind(file_path)
     .fetch_optional(pool)
@@ -79,20 +86,33 @@ pub async fn save_snapshot(
     }
 
     let id = Uuid::new_v4().to_string();
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    sqlx::query("INSERT OR IGNORE INTO history_blobs (hash, content) VALUES (?, ?)")
+        .bind(&content_hash)
+        .bind(content)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
 
     sqlx::query(
-        "INSERT INTO file_history (id, file_path, content, content_hash, summary, is_manual_snapshot) VALUES (?, ?, ?, ?, ?, ?)"
+        "INSERT INTO file_history (id, file_path, content_hash, summary, is_manual_snapshot) VALUES (?, ?, ?, ?, ?)"
     )
     .bind(&id)
     .bind(file_path)
-    .bind(content)
     .bind(&content_hash)
     .bind(summary)
     .bind(if is_manual { 1 } else { 0 })
-    .execute(pool)
+    .execute(&mut *tx)
     .await
     .map_err(|e| e.to_string())?;
 
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    if !is_manual {
+        cleanup_old_snapshots(pool, file_path, MAX_AUTO_SNAPSHOTS).await?;
+    }
+
     Ok(id)
 }
 
@@ -108,7 +128,7 @@ pub async fn get_file_history(
         "SELECT id, file_path, content_hash, created_at, summary, is_manual_snapshot
          FROM file_history
          WHERE file_path = ?
-         ORDER BY created_at DESC
+         ORDER BY created_at DESC, rowid DESC
          LIMIT ?",
     )
     .bind(file_path)
@@ -137,11 +157,15 @@ pub async fn get_snapshot_content(
     pool: &Pool<Sqlite>,
     snapshot_id: &str,
 ) -> Result<String, String> {
-    let content: String = sqlx::query_scalar("SELECT content FROM file_history WHERE id = ?")
-        .bind(snapshot_id)
-        .fetch_one(pool)
-        .await
-        .map_err(|e| e.to_string())?;
+    let content: String = sqlx::query_scalar(
+        "SELECT b.content FROM file_history h
+         JOIN history_blobs b ON b.hash = h.content_hash
+         WHERE h.id = ?",
+    )
+    .bind(snapshot_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())?;
 
     Ok(content)
 }
@@ -151,11 +175,15 @@ pub async fn get_restore_content(
     pool: &Pool<Sqlite>,
     snapshot_id: &str,
 ) -> Result<(String, String), String> {
-    let row = sqlx::query("SELECT file_path, content FROM file_history WHERE id = ?")
-        .bind(snapshot_id)
-        .fetch_one(pool)
-        .await
-        .map_err(|e| e.to_string())?;
+    let row = sqlx::query(
+        "SELECT h.file_path, b.content FROM file_history h
+         JOIN history_blobs b ON b.hash = h.content_hash
+         WHERE h.id = ?",
+    )
+    .bind(snapshot_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())?;
 
     let file_path: String = row.get("file_path");
     let content: String = row.get("content");
@@ -235,7 +263,7 @@ pub async fn delete_snapshot(pool: &Pool<Sqlite>, snapshot_id: &str) -> Result<(
         .await
         .map_err(|e| e.to_string())?;
 
-    Ok(())
+    remove_unused_blobs(pool).await
 }
 
 /// Cleanup old snapshots (keep last N or from last N days)
@@ -252,7 +280,7 @@ pub async fn cleanup_old_snapshots(
          AND id NOT IN (
              SELECT id FROM file_history
              WHERE file_path = ?
-             ORDER BY created_at DESC
+             ORDER BY created_at DESC, rowid DESC
              LIMIT ?
          )",
     )
@@ -263,5 +291,20 @@ pub async fn cleanup_old_snapshots(
     .await
     .map_err(|e| e.to_string())?;
 
+    if result.rows_affected() > 0 {
+        remove_unused_blobs(pool).await?;
+    }
     Ok(result.rows_affected() as usize)
 }
+
+/// Drop the contents no snapshot points to anymore
+async fn remove_unused_blobs(pool: &Pool<Sqlite>) -> Result<(), String> {
+    sqlx::query(
+        "DELETE FROM history_blobs
+         WHERE hash NOT IN (SELECT content_hash FROM file_history)",
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
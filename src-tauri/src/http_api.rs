@@ -0,0 +1,513 @@
+//! Local HTTP API
+//!
+//! An optional, read-only HTTP server for external tools (scripts, launcher
+//! plugins, other editors) that want to query the database: collections,
+//! resources and their contents, metadata search and the dependency graph.
+//! It is off by default, listens on 127.0.0.1 only, and every request needs
+//! the token from `api-token` in the data directory as a bearer token.
+//! Requests whose `Host` isn't a loopback name are refused, and no CORS
+//! headers are sent, so web pages can't read from it.
+//!
+//! Routes (all `GET`, JSON unless noted):
+//! - `/api/collections`
+//! - `/api/resources?collections=a,b&kind=tex`
+//! - `/api/resources/{id}` and `/api/resources/{id}/content` (plain text)
+//! - `/api/search?q=...&collections=a,b&limit=50`
+//! - `/api/graph?collections=a,b&packages=true&bibliographies=true&images=true&classes=true&citations=true`
+//!
+//! Without `collections`, every collection is used.
+
+use crate::database::manager::DatabaseManager;
+use crate::graph_processor::{self, GraphFilters};
+use crate::settings::ApiSettings;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::Manager;
+use tokio::sync::{oneshot, Mutex};
+
+const DEFAULT_SEARCH_LIMIT: i64 = 50;
+const MAX_SEARCH_LIMIT: i64 = 500;
+
+/// The running server, managed by Tauri
+#[derive(Default)]
+pub struct ApiServer {
+    inner: Mutex<ServerState>,
+}
+
+#[derive(Default)]
+struct ServerState {
+    running: Option<Running>,
+    /// Why the server last failed to start
+    error: Option<String>,
+}
+
+struct Running {
+    port: u16,
+    shutdown: oneshot::Sender<()>,
+    state: Arc<ApiState>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiStatus {
+    pub enabled: bool,
+    pub running: bool,
+    pub port: u16,
+    /// Base URL while the server is running
+    pub url: Option<String>,
+    /// None until the server first starts or a token is generated
+    pub token: Option<String>,
+    pub token_path: String,
+    pub error: Option<String>,
+}
+
+struct ApiState {
+    db: Arc<Mutex<Option<DatabaseManager>>>,
+    token: std::sync::RwLock<String>,
+}
+
+/// An error response: `{"error": "..."}` with a status code
+struct ApiError(StatusCode, String);
+
+impl From<String> for ApiError {
+    fn from(message: String) -> Self {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({ "error": self.1 });
+        (self.0, Json(body)).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+fn token_path() -> Result<PathBuf, String> {
+    let proj_dirs =
+        ProjectDirs::from("", "", "datatex").ok_or("Could not determine project directories")?;
+    Ok(proj_dirs.data_dir().join("api-token"))
+}
+
+fn new_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// Write the token so only the current user can read it
+fn write_token(path: &std::path::Path, token: &str) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let failed = |e: std::io::Error| format!("Failed to write {}: {}", path.display(), e);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).map_err(failed)?;
+    // The mode only applies to new files; tighten an older one before writing
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))
+            .map_err(failed)?;
+    }
+    file.write_all(token.as_bytes()).map_err(failed)
+}
+
+/// The saved API token, if there is one
+fn saved_token() -> Result<Option<String>, String> {
+    let path = token_path()?;
+    match fs::read_to_string(&path) {
+        Ok(token) if !token.trim().is_empty() => Ok(Some(token.trim().to_string())),
+        Ok(_) => Ok(None),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// The API token, created on first use
+fn token() -> Result<String, String> {
+    if let Some(token) = saved_token()? {
+        return Ok(token);
+    }
+    let token = new_token();
+    let path = token_path()?;
+    write_token(&path, &token)?;
+    Ok(token)
+}
+
+/// Compare without stopping at the first difference, so response times
+/// don't give the token away
+fn authorized(header: Option<&str>, token: &str) -> bool {
+    let Some(given) = header.and_then(|h| h.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    let given = given.trim().as_bytes();
+    given.len() == token.len()
+        && given
+            .iter()
+            .zip(token.as_bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Whether a `Host` header names this machine; anything else is a page
+/// that reached us through DNS rebinding
+fn local_host(host: &str) -> bool {
+    let name = if host.starts_with('[') {
+        host.split_inclusive(']').next().unwrap_or(host)
+    } else {
+        host.split(':').next().unwrap_or(host)
+    };
+    matches!(
+        name.to_ascii_lowercase().as_str(),
+        "localhost" | "127.0.0.1" | "[::1]"
+    )
+}
+
+/// Comma-separated query values, trimmed, without empty ones
+fn split_list(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or("")
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+async fn check_request(
+    State(state): State<Arc<ApiState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let headers = request.headers();
+    let host = headers.get(header::HOST).and_then(|h| h.to_str().ok());
+    if !host.is_some_and(local_host) {
+        return ApiError(StatusCode::FORBIDDEN, "Unexpected host".to_string()).into_response();
+    }
+    let auth = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok());
+    let token = state.token.read().map(|t| t.clone()).unwrap_or_default();
+    if token.is_empty() || !authorized(auth, &token) {
+        let mut response = ApiError(
+            StatusCode::UNAUTHORIZED,
+            "Missing or wrong token".to_string(),
+        )
+        .into_response();
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        return response;
+    }
+    next.run(request).await
+}
+
+fn not_ready() -> ApiError {
+    ApiError(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Database not initialized".to_string(),
+    )
+}
+
+/// The requested collections, or all of them
+async fn collections_or_all(
+    db: &DatabaseManager,
+    requested: Option<&str>,
+) -> Result<Vec<String>, String> {
+    let requested = split_list(requested);
+    if !requested.is_empty() {
+        return Ok(requested);
+    }
+    Ok(db
+        .get_collections()
+        .await?
+        .into_iter()
+        .map(|c| c.name)
+        .collect())
+}
+
+async fn list_collections(
+    State(state): State<Arc<ApiState>>,
+) -> ApiResult<Vec<crate::database::entities::Collection>> {
+    let guard = state.db.lock().await;
+    let db = guard.as_ref().ok_or_else(not_ready)?;
+    Ok(Json(db.get_collections().await?))
+}
+
+#[derive(Deserialize)]
+struct ResourcesQuery {
+    collections: Option<String>,
+    kind: Option<String>,
+}
+
+async fn list_resources(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<ResourcesQuery>,
+) -> ApiResult<Vec<crate::database::entities::Resource>> {
+    let guard = state.db.lock().await;
+    let db = guard.as_ref().ok_or_else(not_ready)?;
+    let collections = collections_or_all(db, query.collections.as_deref()).await?;
+    let mut resources = db.get_resources_by_collections(&collections).await?;
+    if let Some(kind) = &query.kind {
+        resources.retain(|r| &r.kind == kind);
+    }
+    Ok(Json(resources))
+}
+
+async fn find_resource(
+    state: &ApiState,
+    id: &str,
+) -> Result<crate::database::entities::Resource, ApiError> {
+    let guard = state.db.lock().await;
+    let db = guard.as_ref().ok_or_else(not_ready)?;
+    db.get_resource_by_id(id)
+        .await?
+        .ok_or_else(|| ApiError(StatusCode::NOT_FOUND, format!("No resource {}", id)))
+}
+
+async fn get_resource(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+) -> ApiResult<crate::database::entities::Resource> {
+    Ok(Json(find_resource(&state, &id).await?))
+}
+
+async fn get_resource_content(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+) -> Result<Response, ApiError> {
+    let resource = find_resource(&state, &id).await?;
+    let bytes = tokio::fs::read(&resource.path).await.map_err(|e| {
+        ApiError(
+            StatusCode::NOT_FOUND,
+            format!("Failed to read {}: {}", resource.path, e),
+        )
+    })?;
+    let text = crate::search::encoding::decode(&bytes).text;
+    Ok(([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], text).into_response())
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: String,
+    collections: Option<String>,
+    limit: Option<i64>,
+}
+
+async fn search(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<SearchQuery>,
+) -> ApiResult<Vec<crate::search::fts::MetadataMatch>> {
+    let guard = state.db.lock().await;
+    let db = guard.as_ref().ok_or_else(not_ready)?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+    let collections = split_list(query.collections.as_deref());
+    Ok(Json(
+        crate::search::fts::search_metadata(&db.pool, &query.q, &collections, limit).await?,
+    ))
+}
+
+#[derive(Deserialize)]
+struct GraphQuery {
+    collections: Option<String>,
+    #[serde(default)]
+    packages: bool,
+    #[serde(default)]
+    bibliographies: bool,
+    #[serde(default)]
+    images: bool,
+    #[serde(default)]
+    classes: bool,
+    #[serde(default)]
+    citations: bool,
+}
+
+async fn graph(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<GraphQuery>,
+) -> ApiResult<graph_processor::GraphData> {
+    let guard = state.db.lock().await;
+    let db = guard.as_ref().ok_or_else(not_ready)?;
+    let collections = collections_or_all(db, query.collections.as_deref()).await?;
+    let filters = GraphFilters {
+        show_packages: query.packages,
+        show_bibliographies: query.bibliographies,
+        show_images: query.images,
+        show_classes: query.classes,
+        show_citations: query.citations,
+        ..Default::default()
+    };
+    Ok(Json(
+        graph_processor::process_graph_data(db, collections, filters).await?,
+    ))
+}
+
+fn router(state: Arc<ApiState>) -> Router {
+    Router::new()
+        .route("/api/collections", get(list_collections))
+        .route("/api/resources", get(list_resources))
+        .route("/api/resources/{id}", get(get_resource))
+        .route("/api/resources/{id}/content", get(get_resource_content))
+        .route("/api/search", get(search))
+        .route("/api/graph", get(graph))
+        .layer(middleware::from_fn_with_state(state.clone(), check_request))
+        .with_state(state)
+}
+
+async fn start(app: &tauri::AppHandle, port: u16) -> Result<Running, String> {
+    let state = Arc::new(ApiState {
+        db: app.state::<crate::AppState>().db_manager.clone(),
+        token: std::sync::RwLock::new(token()?),
+    });
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+        .await
+        .map_err(|e| format!("Can't listen on port {}: {}", port, e))?;
+    let (shutdown, stopped) = oneshot::channel::<()>();
+    let served = state.clone();
+    tauri::async_runtime::spawn(async move {
+        let server = axum::serve(listener, router(served)).with_graceful_shutdown(async {
+            let _ = stopped.await;
+        });
+        if let Err(e) = server.await {
+            eprintln!("HTTP API server stopped: {}", e);
+        }
+    });
+    Ok(Running {
+        port,
+        shutdown,
+        state,
+    })
+}
+
+/// Start, stop or move the server so it matches `settings`
+pub async fn sync(app: &tauri::AppHandle, settings: &ApiSettings) -> Result<(), String> {
+    let server = app.state::<ApiServer>();
+    let mut inner = server.inner.lock().await;
+    let wanted = settings.enabled.then_some(settings.port);
+    if inner.running.as_ref().map(|r| r.port) == wanted {
+        return Ok(());
+    }
+    if let Some(running) = inner.running.take() {
+        let _ = running.shutdown.send(());
+    }
+    inner.error = None;
+    if let Some(port) = wanted {
+        match start(app, port).await {
+            Ok(running) => inner.running = Some(running),
+            Err(e) => {
+                inner.error = Some(e.clone());
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn status(app: &tauri::AppHandle) -> Result<ApiStatus, String> {
+    let settings = crate::settings::current().api;
+    let server = app.state::<ApiServer>();
+    let inner = server.inner.lock().await;
+    let port = inner.running.as_ref().map_or(settings.port, |r| r.port);
+    Ok(ApiStatus {
+        enabled: settings.enabled,
+        running: inner.running.is_some(),
+        port,
+        url: inner
+            .running
+            .as_ref()
+            .map(|_| format!("http://127.0.0.1:{}/api", port)),
+        token: saved_token()?,
+        token_path: token_path()?.to_string_lossy().to_string(),
+        error: inner.error.clone(),
+    })
+}
+
+/// Replace the token; a running server stops accepting the old one at once
+pub async fn regenerate_token(app: &tauri::AppHandle) -> Result<ApiStatus, String> {
+    let token = new_token();
+    write_token(&token_path()?, &token)?;
+    {
+        let server = app.state::<ApiServer>();
+        let inner = server.inner.lock().await;
+        if let Some(running) = &inner.running {
+            if let Ok(mut current) = running.state.token.write() {
+                *current = token;
+            }
+        }
+    }
+    status(app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorization() {
+        let token = "abc123";
+        assert!(authorized(Some("Bearer abc123"), token));
+        assert!(!authorized(Some("Bearer abc124"), token));
+        assert!(!authorized(Some("Bearer abc"), token));
+        assert!(!authorized(Some("abc123"), token));
+        assert!(!authorized(None, token));
+
+        assert!(local_host("localhost:27183"));
+        assert!(local_host("127.0.0.1:27183"));
+        assert!(local_host("[::1]:27183"));
+        assert!(!local_host("evil.example:27183"));
+        assert!(!local_host("127.0.0.1.evil.example"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_token_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("datatex-api-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("api-token");
+        let mode =
+            |path: &std::path::Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        write_token(&path, "first").unwrap();
+        assert_eq!(mode(&path), 0o600);
+        // An older, readable file is tightened
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        write_token(&path, "second").unwrap();
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_split_list() {
+        assert_eq!(
+            split_list(Some("exercises, notes,,")),
+            ["exercises", "notes"]
+        );
+        assert!(split_list(Some(" ")).is_empty());
+        assert!(split_list(None).is_empty());
+    }
+}
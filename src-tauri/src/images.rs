@@ -0,0 +1,330 @@
+//! Image Conversion
+//!
+//! Makes figures that `\includegraphics` can't take usable without outside
+//! tools: SVG is rendered to PDF (vector, with `svg2pdf`) or PNG (with
+//! `resvg`), and raster formats LaTeX doesn't read (GIF, WebP, BMP, TIFF)
+//! are converted to PNG or JPEG. Large photos can be scaled down on the way.
+//! Text in SVGs is drawn with the installed fonts.
+//!
+//! The result goes next to the source, or into another folder such as the
+//! build directory, under the source's name with the new extension.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use svg2pdf::usvg::{self, fontdb, TreeParsing, TreePostProc};
+
+/// Resolution SVG user units are defined at
+const SVG_DPI: f32 = 96.0;
+
+const DEFAULT_DPI: f32 = 300.0;
+const DEFAULT_JPEG_QUALITY: u8 = 90;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetFormat {
+    Pdf,
+    Png,
+    Jpeg,
+}
+
+impl TargetFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            TargetFormat::Pdf => "pdf",
+            TargetFormat::Png => "png",
+            TargetFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConvertRequest {
+    pub path: String,
+    pub format: TargetFormat,
+    /// Longest side of raster output in pixels; larger images are scaled down
+    pub max_dimension: Option<u32>,
+    /// Resolution SVGs are rendered at for PNG/JPEG (300 by default)
+    pub dpi: Option<f32>,
+    /// 1-100, 90 by default
+    pub jpeg_quality: Option<u8>,
+    /// Folder for the result; next to the source when not given
+    pub output_dir: Option<String>,
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConvertedImage {
+    pub output_path: String,
+    /// Pixels, or points for PDF
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A converted image before it is written
+enum Converted {
+    Raster(image::DynamicImage),
+    /// PDF bytes and the page size in points
+    Pdf(Vec<u8>, u32, u32),
+}
+
+fn is_svg(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg") || ext.eq_ignore_ascii_case("svgz"))
+}
+
+/// Where the converted image goes: `<stem>.<ext>` in `output_dir` or next
+/// to the source. A PNG scaled down into its own folder gets a `-<width>px`
+/// suffix instead of replacing the original.
+fn output_path(
+    source: &Path,
+    format: TargetFormat,
+    output_dir: Option<&Path>,
+    width: u32,
+) -> PathBuf {
+    let dir = output_dir
+        .or(source.parent())
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "image".to_string());
+    let target = dir.join(format!("{}.{}", stem, format.extension()));
+    if target == source {
+        dir.join(format!("{}-{}px.{}", stem, width, format.extension()))
+    } else {
+        target
+    }
+}
+
+/// Scale factor that keeps `width`×`height` within `max` on its longest side
+fn fit_scale(width: f32, height: f32, max: Option<u32>) -> f32 {
+    match max {
+        Some(max) if width.max(height) > max as f32 => max as f32 / width.max(height),
+        _ => 1.0,
+    }
+}
+
+/// Installed fonts, loaded once, for text in SVGs
+fn svg_fonts() -> &'static fontdb::Database {
+    static FONTS: OnceLock<fontdb::Database> = OnceLock::new();
+    FONTS.get_or_init(|| {
+        let mut db = fontdb::Database::new();
+        db.load_system_fonts();
+        db
+    })
+}
+
+fn load_svg(source: &Path) -> Result<usvg::Tree, String> {
+    let data =
+        fs::read(source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+    let options = usvg::Options {
+        // Images the SVG links to are relative to it
+        resources_dir: source.parent().map(Path::to_path_buf),
+        ..Default::default()
+    };
+    let mut tree = usvg::Tree::from_data(&data, &options)
+        .map_err(|e| format!("Invalid SVG {}: {}", source.display(), e))?;
+    tree.postprocess(Default::default(), svg_fonts());
+    Ok(tree)
+}
+
+fn render_svg(
+    tree: &usvg::Tree,
+    dpi: f32,
+    max: Option<u32>,
+) -> Result<image::DynamicImage, String> {
+    let size = tree.size;
+    let scale = dpi / SVG_DPI;
+    let scale = scale * fit_scale(size.width() * scale, size.height() * scale, max);
+    let width = (size.width() * scale).ceil().max(1.0) as u32;
+    let height = (size.height() * scale).ceil().max(1.0) as u32;
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| format!("Can't render an SVG at {}×{} pixels", width, height))?;
+    resvg::render(
+        tree,
+        resvg::tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    let png = pixmap.encode_png().map_err(|e| e.to_string())?;
+    image::load_from_memory(&png).map_err(|e| e.to_string())
+}
+
+/// JPEG has no transparency; see-through pixels go on white like on paper
+fn flatten_on_white(image: &image::DynamicImage) -> image::RgbImage {
+    let rgba = image.to_rgba8();
+    image::RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        image::Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+fn save_raster(
+    image: &image::DynamicImage,
+    format: TargetFormat,
+    quality: u8,
+    target: &Path,
+) -> Result<(), String> {
+    let file = fs::File::create(target)
+        .map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+    let mut writer = std::io::BufWriter::new(file);
+    match format {
+        TargetFormat::Jpeg => {
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut writer, quality.clamp(1, 100))
+                .encode_image(&flatten_on_white(image))
+        }
+        _ => image.write_to(&mut writer, image::ImageFormat::Png),
+    }
+    .map_err(|e| format!("Failed to write {}: {}", target.display(), e))
+}
+
+/// Convert the image in `request`. Blocks while decoding and encoding.
+pub fn convert(request: &ConvertRequest) -> Result<ConvertedImage, String> {
+    let source = Path::new(&request.path);
+    if !source.is_file() {
+        return Err(format!("Image not found: {}", request.path));
+    }
+    let output_dir = request.output_dir.as_deref().map(Path::new);
+    if let Some(dir) = output_dir {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let quality = request.jpeg_quality.unwrap_or(DEFAULT_JPEG_QUALITY);
+
+    let converted = if is_svg(source) {
+        let tree = load_svg(source)?;
+        if request.format == TargetFormat::Pdf {
+            let pdf = svg2pdf::convert_tree(&tree, svg2pdf::Options::default());
+            let (width, height) = (tree.size.width().round(), tree.size.height().round());
+            Converted::Pdf(pdf, width as u32, height as u32)
+        } else {
+            let dpi = request.dpi.unwrap_or(DEFAULT_DPI);
+            Converted::Raster(render_svg(&tree, dpi, request.max_dimension)?)
+        }
+    } else {
+        if request.format == TargetFormat::Pdf {
+            return Err("Only SVG converts to PDF; use PNG or JPEG for raster images".to_string());
+        }
+        let image = image::open(source)
+            .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        let scale = fit_scale(
+            image.width() as f32,
+            image.height() as f32,
+            request.max_dimension,
+        );
+        if scale < 1.0 {
+            let max = request.max_dimension.unwrap_or(u32::MAX);
+            Converted::Raster(image.resize(max, max, image::imageops::FilterType::Lanczos3))
+        } else {
+            Converted::Raster(image)
+        }
+    };
+
+    let width = match &converted {
+        Converted::Raster(image) => image.width(),
+        Converted::Pdf(..) => 0,
+    };
+    let target = output_path(source, request.format, output_dir, width);
+    if target.exists() && !request.overwrite {
+        return Err(format!("{} already exists", target.display()));
+    }
+    let (width, height) = match converted {
+        Converted::Raster(image) => {
+            save_raster(&image, request.format, quality, &target)?;
+            (image.width(), image.height())
+        }
+        Converted::Pdf(pdf, width, height) => {
+            fs::write(&target, pdf)
+                .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+            (width, height)
+        }
+    };
+
+    Ok(ConvertedImage {
+        output_path: target.to_string_lossy().to_string(),
+        width,
+        height,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_path() {
+        let source = Path::new("/work/figs/plot.svg");
+        assert_eq!(
+            output_path(source, TargetFormat::Pdf, None, 0),
+            Path::new("/work/figs/plot.pdf")
+        );
+        assert_eq!(
+            output_path(source, TargetFormat::Png, Some(Path::new("/work/build")), 0),
+            Path::new("/work/build/plot.png")
+        );
+        assert_eq!(
+            output_path(Path::new("/work/photo.png"), TargetFormat::Png, None, 800),
+            Path::new("/work/photo-800px.png")
+        );
+    }
+
+    #[test]
+    fn test_convert() {
+        let dir = std::env::temp_dir().join(format!("datatex-images-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let svg = dir.join("shape.svg");
+        fs::write(
+            &svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20"><rect width="40" height="20" fill="red"/></svg>"#,
+        )
+        .unwrap();
+        let request = |path: &Path, format, max_dimension| ConvertRequest {
+            path: path.to_string_lossy().to_string(),
+            format,
+            max_dimension,
+            dpi: Some(96.0),
+            jpeg_quality: None,
+            output_dir: None,
+            overwrite: false,
+        };
+
+        let pdf = convert(&request(&svg, TargetFormat::Pdf, None)).unwrap();
+        assert!(fs::read(&pdf.output_path).unwrap().starts_with(b"%PDF"));
+        assert_eq!((pdf.width, pdf.height), (40, 20));
+
+        let png = convert(&request(&svg, TargetFormat::Png, None)).unwrap();
+        assert_eq!((png.width, png.height), (40, 20));
+        assert!(convert(&request(&svg, TargetFormat::Png, None)).is_err());
+
+        let small = convert(&request(
+            Path::new(&png.output_path),
+            TargetFormat::Png,
+            Some(10),
+        ))
+        .unwrap();
+        assert!(small.output_path.ends_with("shape-10px.png"));
+        assert_eq!((small.width, small.height), (10, 5));
+
+        let jpeg = convert(&request(
+            Path::new(&png.output_path),
+            TargetFormat::Jpeg,
+            None,
+        ))
+        .unwrap();
+        let decoded = image::open(&jpeg.output_path).unwrap().to_rgb8();
+        assert!(decoded.get_pixel(5, 5).0[0] > 200);
+        assert!(convert(&request(
+            Path::new(&png.output_path),
+            TargetFormat::Pdf,
+            None
+        ))
+        .is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
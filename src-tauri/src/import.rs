@@ -0,0 +1,373 @@
+//! Data Import Module
+//!
+//! Loads rows from a CSV or JSON file into a database table. Source fields are
+//! mapped onto table columns, every value is checked against the column's
+//! declared type, and rows are inserted in a single transaction — either the
+//! whole file goes in or nothing does.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::database::schema::{self, ColumnInfo};
+
+/// A row-level problem found while validating or inserting
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RowError {
+    /// 1-based row number in the source file (header excluded)
+    pub row: usize,
+    pub column: Option<String>,
+    pub message: String,
+}
+
+/// Outcome of an import
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub total_rows: usize,
+    pub inserted: usize,
+    pub errors: Vec<RowError>,
+    pub dry_run: bool,
+}
+
+/// Import options besides file and table
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportOptions {
+    /// Source field -> target column. Empty maps fields onto same-named columns.
+    #[serde(default)]
+    pub mapping: HashMap<String, String>,
+    /// Validate only, don't insert
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Replace rows that collide on a unique key instead of failing
+    #[serde(default)]
+    pub replace_existing: bool,
+}
+
+/// SQLite type affinity of a declared column type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Affinity {
+    Integer,
+    Real,
+    Numeric,
+    Text,
+    Blob,
+}
+
+/// Affinity rules from https://www.sqlite.org/datatype3.html (section 3.1)
+fn affinity(decl_type: &str) -> Affinity {
+    let t = decl_type.to_uppercase();
+    if t.contains("INT") {
+        Affinity::Integer
+    } else if t.contains("CHAR") || t.contains("CLOB") || t.contains("TEXT") {
+        Affinity::Text
+    } else if t.is_empty() || t.contains("BLOB") {
+        Affinity::Blob
+    } else if t.contains("REAL") || t.contains("FLOA") || t.contains("DOUB") {
+        Affinity::Real
+    } else {
+        Affinity::Numeric
+    }
+}
+
+/// A validated value ready to bind
+#[derive(Debug, Clone, PartialEq)]
+enum Cell {
+    Null,
+    Int(i64),
+    Real(f64),
+    Text(String),
+}
+
+/// Convert a source value to the column's type
+fn convert_value(value: &serde_json::Value, affinity: Affinity) -> Result<Cell, String> {
+    use serde_json::Value;
+
+    // Empty CSV cells and JSON nulls are NULL
+    if value.is_null() || value.as_str().is_some_and(|s| s.trim().is_empty()) {
+        return Ok(Cell::Null);
+    }
+
+    let as_text = || match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+
+    match affinity {
+        Affinity::Text | Affinity::Blob => Ok(Cell::Text(as_text())),
+        Affinity::Integer => match value {
+            Value::Number(n) => n
+                .as_i64()
+                .map(Cell::Int)
+                .ok_or_else(|| format!("expected an integer, got {}", n)),
+            Value::Bool(b) => Ok(Cell::Int(*b as i64)),
+            Value::String(s) => match s.trim().to_lowercase().as_str() {
+                "true" => Ok(Cell::Int(1)),
+                "false" => Ok(Cell::Int(0)),
+                t => t
+                    .parse::<i64>()
+                    .map(Cell::Int)
+                    .map_err(|_| format!("expected an integer, got '{}'", s)),
+            },
+            _ => Err(format!("expected an integer, got {}", value)),
+        },
+        Affinity::Real | Affinity::Numeric => {
+            let n = match value {
+                Value::Number(n) => n.as_f64(),
+                Value::Bool(b) => Some(*b as i64 as f64),
+                Value::String(s) => match s.trim().to_lowercase().as_str() {
+                    "true" => Some(1.0),
+                    "false" => Some(0.0),
+                    t => t.parse::<f64>().ok(),
+                },
+                _ => None,
+            }
+            .ok_or_else(|| format!("expected a number, got {}", value))?;
+
+            if affinity == Affinity::Numeric && n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+                Ok(Cell::Int(n as i64))
+            } else {
+                Ok(Cell::Real(n))
+            }
+        }
+    }
+}
+
+/// Read the source file into a list of records
+fn read_records(
+    file_path: &str,
+    table: &str,
+) -> Result<Vec<serde_json::Map<String, serde_json::Value>>, String> {
+    let ext = Path::new(file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    match ext.as_str() {
+        "csv" | "tsv" => {
+            let mut reader = csv::ReaderBuilder::new()
+                .delimiter(if ext == "tsv" { b'\t' } else { b',' })
+                .flexible(true)
+                .from_path(file_path)
+                .map_err(|e| e.to_string())?;
+            let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+
+            let mut records = Vec::new();
+            for (i, record) in reader.records().enumerate() {
+                let record = record.map_err(|e| format!("Row {}: {}", i + 1, e))?;
+                let map = headers
+                    .iter()
+                    .zip(record.iter())
+                    .map(|(h, v)| {
+                        (
+                            h.trim().to_string(),
+                            serde_json::Value::String(v.to_string()),
+                        )
+                    })
+                    .collect();
+                records.push(map);
+            }
+            Ok(records)
+        }
+        "json" => {
+            let content = std::fs::read_to_string(file_path).map_err(|e| e.to_string())?;
+            let value: serde_json::Value =
+                serde_json::from_str(&content).map_err(|e| format!("Invalid JSON: {}", e))?;
+
+            // Either a plain array of objects or an export file keyed by table
+            let rows = match value {
+                serde_json::Value::Array(rows) => rows,
+                serde_json::Value::Object(mut obj) => match obj.remove(table) {
+                    Some(serde_json::Value::Array(rows)) => rows,
+                    _ => return Err(format!("JSON file has no '{}' array", table)),
+                },
+                _ => return Err("JSON file must contain an array of objects".to_string()),
+            };
+
+            rows.into_iter()
+                .enumerate()
+                .map(|(i, row)| match row {
+                    serde_json::Value::Object(map) => Ok(map),
+                    _ => Err(format!("Row {}: expected an object", i + 1)),
+                })
+                .collect()
+        }
+        other => Err(format!("Unsupported import format: .{}", other)),
+    }
+}
+
+/// Validate a CSV/JSON file against `table` and insert its rows in one transaction
+pub async fn import_table_data(
+    pool: &Pool<Sqlite>,
+    file_path: &str,
+    table: &str,
+    options: &ImportOptions,
+) -> Result<ImportReport, String> {
+    let columns = schema::column_info(pool, table).await?;
+    let records = read_records(file_path, table)?;
+
+    // Resolve the mapping to (source field, target column)
+    let mapping: Vec<(String, &ColumnInfo)> = if options.mapping.is_empty() {
+        let fields: Vec<&String> = records
+            .first()
+            .map(|r| r.keys().collect())
+            .unwrap_or_default();
+        columns
+            .iter()
+            .filter(|c| fields.contains(&&c.name))
+            .map(|c| (c.name.clone(), c))
+            .collect()
+    } else {
+        let mut resolved = Vec::new();
+        for (source, target) in &options.mapping {
+            let col = columns
+                .iter()
+                .find(|c| &c.name == target)
+                .ok_or_else(|| format!("Unknown column '{}' in table '{}'", target, table))?;
+            resolved.push((source.clone(), col));
+        }
+        resolved
+    };
+    if mapping.is_empty() {
+        return Err("No source fields map onto table columns".to_string());
+    }
+
+    // Required columns the mapping doesn't cover would fail every insert
+    let required: Vec<&ColumnInfo> = columns
+        .iter()
+        .filter(|c| {
+            c.not_null
+                && !c.has_default
+                && !(c.primary_key && affinity(&c.decl_type) == Affinity::Integer)
+        })
+        .collect();
+    for col in &required {
+        if !mapping.iter().any(|(_, c)| c.name == col.name) {
+            return Err(format!("Required column '{}' is not mapped", col.name));
+        }
+    }
+
+    let mut report = ImportReport {
+        total_rows: records.len(),
+        dry_run: options.dry_run,
+        ..Default::default()
+    };
+
+    // 1. Validate everything first
+    let mut rows: Vec<Vec<Cell>> = Vec::with_capacity(records.len());
+    for (i, record) in records.iter().enumerate() {
+        let mut cells = Vec::with_capacity(mapping.len());
+        for (source, col) in &mapping {
+            let value = record.get(source).unwrap_or(&serde_json::Value::Null);
+            match convert_value(value, affinity(&col.decl_type)) {
+                Ok(Cell::Null) if col.not_null && !col.has_default => {
+                    report.errors.push(RowError {
+                        row: i + 1,
+                        column: Some(col.name.clone()),
+                        message: "value is required".to_string(),
+                    });
+                    cells.push(Cell::Null);
+                }
+                Ok(cell) => cells.push(cell),
+                Err(message) => {
+                    report.errors.push(RowError {
+                        row: i + 1,
+                        column: Some(col.name.clone()),
+                        message,
+                    });
+                    cells.push(Cell::Null);
+                }
+            }
+        }
+        rows.push(cells);
+    }
+
+    if !report.errors.is_empty() || options.dry_run {
+        return Ok(report);
+    }
+
+    // 2. Insert in one transaction; any failing row rolls everything back
+    let column_list: Vec<String> = mapping
+        .iter()
+        .map(|(_, c)| schema::quote_identifier(&c.name))
+        .collect();
+    let placeholders: Vec<&str> = mapping.iter().map(|_| "?").collect();
+    let sql = format!(
+        "INSERT {} INTO {} ({}) VALUES ({})",
+        if options.replace_existing {
+            "OR REPLACE"
+        } else {
+            ""
+        },
+        schema::quote_identifier(table),
+        column_list.join(", "),
+        placeholders.join(", ")
+    );
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    for (i, cells) in rows.iter().enumerate() {
+        let mut q = sqlx::query(&sql);
+        for cell in cells {
+            q = match cell {
+                Cell::Null => q.bind(None::<String>),
+                Cell::Int(v) => q.bind(*v),
+                Cell::Real(v) => q.bind(*v),
+                Cell::Text(v) => q.bind(v.as_str()),
+            };
+        }
+        if let Err(e) = q.execute(&mut *tx).await {
+            report.errors.push(RowError {
+                row: i + 1,
+                column: None,
+                message: e.to_string(),
+            });
+            tx.rollback().await.map_err(|e| e.to_string())?;
+            return Ok(report);
+        }
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    report.inserted = rows.len();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_affinity() {
+        assert_eq!(affinity("INTEGER"), Affinity::Integer);
+        assert_eq!(affinity("VARCHAR(20)"), Affinity::Text);
+        assert_eq!(affinity("DOUBLE"), Affinity::Real);
+        assert_eq!(affinity("BOOLEAN"), Affinity::Numeric);
+        assert_eq!(affinity(""), Affinity::Blob);
+    }
+
+    #[test]
+    fn test_convert_value() {
+        assert_eq!(
+            convert_value(&json!("42"), Affinity::Integer),
+            Ok(Cell::Int(42))
+        );
+        assert!(convert_value(&json!("4.2"), Affinity::Integer).is_err());
+        assert_eq!(
+            convert_value(&json!("true"), Affinity::Numeric),
+            Ok(Cell::Int(1))
+        );
+        assert_eq!(
+            convert_value(&json!("2.5"), Affinity::Real),
+            Ok(Cell::Real(2.5))
+        );
+        assert_eq!(convert_value(&json!(""), Affinity::Text), Ok(Cell::Null));
+        assert_eq!(
+            convert_value(&json!({"a": 1}), Affinity::Text),
+            Ok(Cell::Text("{\"a\":1}".to_string()))
+        );
+    }
+}
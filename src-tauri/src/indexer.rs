@@ -0,0 +1,598 @@
+//! Collection Indexer Module
+//!
+//! Walks a collection's root directory and brings the `resources` table in
+//! line with what is on disk: new files are inserted, changed files (by
+//! content hash) are updated, and rows whose file disappeared are removed.
+
+use regex::Regex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Instant;
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+use crate::bib;
+use crate::database::entities::{Collection, Resource};
+use crate::database::DatabaseManager;
+use crate::dependency_scanner;
+use crate::resources;
+use crate::search;
+
+/// Build artifacts and editor leftovers that are never indexed
+const IGNORED_EXTENSIONS: &[&str] = &[
+    "aux",
+    "log",
+    "out",
+    "toc",
+    "lof",
+    "lot",
+    "fls",
+    "fdb_latexmk",
+    "bbl",
+    "blg",
+    "xdv",
+    "nav",
+    "snm",
+    "vrb",
+    "synctex",
+    "gz",
+    "bak",
+    "swp",
+    "tmp",
+];
+
+/// Summary returned after a scan
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanSummary {
+    pub collection: String,
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+    /// Resolved dependency links after the scan
+    pub dependencies: usize,
+    pub errors: Vec<String>,
+    pub duration_ms: u64,
+    /// Ids of resources that were added, updated or removed
+    #[serde(skip)]
+    pub changed_ids: Vec<String>,
+}
+
+/// Whether a path should be skipped by the indexer (hidden entries, artifacts)
+pub fn is_ignored(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    if name.starts_with('.') {
+        return true;
+    }
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    IGNORED_EXTENSIONS.contains(&ext.as_str())
+}
+
+/// SHA256 of raw file bytes
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Title for a .tex file: `\title{...}`, else the first sectioning command
+pub fn extract_title(content: &str) -> Option<String> {
+    static TITLE_RE: OnceLock<Regex> = OnceLock::new();
+    static SECTION_RE: OnceLock<Regex> = OnceLock::new();
+    let title_re = TITLE_RE.get_or_init(|| {
+        Regex::new(r"(?m)^[^%\n]*?\\title\s*(?:\[[^\]]*\])?\s*\{([^}]*)\}").unwrap()
+    });
+    let section_re = SECTION_RE.get_or_init(|| {
+        Regex::new(r"(?m)^[^%\n]*?\\(?:part|chapter|section|subsection)\*?\s*(?:\[[^\]]*\])?\s*\{([^}]*)\}").unwrap()
+    });
+
+    title_re
+        .captures(content)
+        .or_else(|| section_re.captures(content))
+        .and_then(|c| c.get(1))
+        .map(|m| m.as_str().split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|t| !t.is_empty())
+}
+
+/// Title for any file: extracted from LaTeX sources, file name otherwise
+fn title_for(path: &Path, bytes: &[u8]) -> String {
+    let file_name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let is_tex = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("tex"));
+    if is_tex {
+        if let Some(title) = extract_title(&search::encoding::decode(bytes).text) {
+            return title;
+        }
+    }
+    file_name
+}
+
+/// Files below `root` the indexer looks at, skipping ignored entries.
+/// Symlinked files are included; symlinked directories (and junctions on
+/// Windows) are only descended into with `follow_links`, in which case a
+/// link back to one of its own ancestors is reported instead of walked
+/// forever.
+pub fn walk_files(
+    root: &Path,
+    follow_links: bool,
+) -> impl Iterator<Item = Result<PathBuf, String>> {
+    WalkDir::new(root)
+        .follow_links(follow_links)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !is_ignored(e.path()))
+        .filter_map(move |entry| match entry {
+            Ok(e) if e.file_type().is_file() => Some(Ok(e.into_path())),
+            // Not followed: only a link to a file counts
+            Ok(e) if e.path_is_symlink() && !follow_links && e.path().is_file() => {
+                Some(Ok(e.into_path()))
+            }
+            Ok(_) => None,
+            Err(e) => Some(Err(match (e.path(), e.loop_ancestor()) {
+                (Some(path), Some(ancestor)) => format!(
+                    "Skipped {}: it links back to {}",
+                    path.display(),
+                    ancestor.display()
+                ),
+                _ => e.to_string(),
+            })),
+        })
+}
+
+/// Index (or re-index) a single file. Returns `Some(id)` if the resource was
+/// inserted or updated, `None` if it was unchanged.
+pub async fn index_file(
+    db: &DatabaseManager,
+    path: &Path,
+    collection: &str,
+    existing: Option<&Resource>,
+) -> Result<Option<String>, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let hash = hash_bytes(&bytes);
+    let path_str = path.to_string_lossy().to_string();
+
+    if let Some(existing) = existing {
+        if existing.content_hash.as_deref() == Some(hash.as_str()) {
+            return Ok(None);
+        }
+    }
+
+    let resource = Resource {
+        id: existing
+            .map(|r| r.id.clone())
+            .unwrap_or_else(|| Uuid::new_v4().to_string()),
+        path: path_str.clone(),
+        kind: existing
+            .map(|r| r.kind.clone())
+            .unwrap_or_else(|| resources::kind_from_path(&path_str).to_string()),
+        collection: collection.to_string(),
+        title: Some(title_for(path, &bytes)),
+        content_hash: Some(hash),
+        metadata: existing
+            .and_then(|r| r.metadata.clone())
+            .or_else(|| Some(serde_json::json!({}))),
+        created_at: None,
+        updated_at: None,
+    };
+
+    match existing {
+        Some(_) => db.update_scanned_resource(&resource).await?,
+        None => db.add_resource(&resource).await?,
+    }
+
+    // Decoded, so files in legacy encodings (Windows-1253, ...) index as text
+    let content = search::encoding::decode(&bytes).text;
+    if search::fts::is_indexable(&path_str) {
+        search::fts::update_content(&db.pool, &resource.id, &content).await?;
+    }
+
+    // Keep bib_entries in step with the file; a broken .bib doesn't stop indexing
+    if resource.kind == "bibliography" {
+        if let Err(e) = bib::sync_resource(&db.pool, &resource.id, &content).await {
+            eprintln!("Bibliography sync failed for {}: {}", path_str, e);
+        }
+    }
+
+    Ok(Some(resource.id))
+}
+
+/// Walk `root_path` and synchronise the resources of `collection` with it.
+/// `follow_links` also indexes the contents of symlinked directories.
+pub async fn scan_collection(
+    db: &DatabaseManager,
+    root_path: &str,
+    collection: &str,
+    follow_links: bool,
+) -> Result<ScanSummary, String> {
+    let start_time = Instant::now();
+    let root = Path::new(root_path);
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", root_path));
+    }
+
+    // Make sure the collection exists and points at this root
+    db.create_collection(&Collection {
+        name: collection.to_string(),
+        description: Some(format!("Indexed from {}", root_path)),
+        icon: Some("folder".to_string()),
+        kind: "files".to_string(),
+        path: Some(root_path.to_string()),
+        created_at: None,
+    })
+    .await?;
+    // An existing collection keeps its row, so move it here explicitly
+    db.set_collection_path(collection, root_path).await?;
+
+    let existing: HashMap<String, Resource> = db
+        .get_resources_by_collection(collection)
+        .await?
+        .into_iter()
+        .map(|r| (r.path.clone(), r))
+        .collect();
+
+    let mut summary = ScanSummary {
+        collection: collection.to_string(),
+        ..Default::default()
+    };
+    let mut seen: HashSet<String> = HashSet::new();
+
+    for entry in walk_files(root, follow_links) {
+        let path = match entry {
+            Ok(path) => path,
+            Err(e) => {
+                summary.errors.push(e);
+                continue;
+            }
+        };
+
+        let path_str = path.to_string_lossy().to_string();
+        seen.insert(path_str.clone());
+        let previous = existing.get(&path_str);
+
+        match index_file(db, &path, collection, previous).await {
+            Ok(Some(id)) => {
+                if previous.is_some() {
+                    summary.updated += 1;
+                } else {
+                    summary.added += 1;
+                }
+                summary.changed_ids.push(id);
+            }
+            Ok(None) => summary.unchanged += 1,
+            Err(e) => summary.errors.push(e),
+        }
+    }
+
+    // Rows whose file (or folder) no longer exists on disk
+    for (path, resource) in &existing {
+        if seen.contains(path) || Path::new(path).exists() {
+            continue;
+        }
+        match db.delete_resource_tree(&resource.id, path, false).await {
+            Ok(ids) => {
+                summary.removed += ids.len();
+                summary.changed_ids.extend(ids);
+            }
+            Err(e) => summary.errors.push(e),
+        }
+    }
+
+    // Dependencies are resolved once every file is known
+    for resource in db.get_resources_by_collection(collection).await? {
+        if !dependency_scanner::is_source(&resource.path) {
+            continue;
+        }
+        // Decoded like the indexed text, so legacy encodings keep their links
+        let Some(content) = search::fts::read_for_index(&resource.path) else {
+            continue;
+        };
+        match dependency_scanner::update_dependencies(db, &resource, &content, Some(root)).await {
+            Ok(n) => summary.dependencies += n,
+            Err(e) => summary.errors.push(e),
+        }
+    }
+
+    summary.duration_ms = start_time.elapsed().as_millis() as u64;
+    Ok(summary)
+}
+
+/// Resources touched by a batch of filesystem events in one collection
+#[derive(Debug, Default)]
+pub struct ChangeSet {
+    pub updated: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+/// Whether any component of `path` below `root` is ignored
+fn is_ignored_below(root: &Path, path: &Path) -> bool {
+    path.strip_prefix(root)
+        .map(|rel| {
+            rel.components()
+                .any(|c| is_ignored(Path::new(c.as_os_str())))
+        })
+        .unwrap_or(true)
+}
+
+/// Apply a debounced batch of changed paths to the database. `roots` maps each
+/// watched root directory to its collection. A file that disappears and
+/// reappears elsewhere with the same content in one batch is treated as a
+/// rename, so its id (and metadata) survive.
+pub async fn apply_changes(
+    db: &DatabaseManager,
+    roots: &[(PathBuf, String)],
+    paths: &HashSet<PathBuf>,
+) -> HashMap<String, ChangeSet> {
+    let mut changes: HashMap<String, ChangeSet> = HashMap::new();
+    let mut present: Vec<(PathBuf, &PathBuf, &String)> = Vec::new();
+    let mut removed: Vec<Resource> = Vec::new();
+
+    for path in paths {
+        let Some((root, collection)) = roots.iter().find(|(r, _)| path.starts_with(r)) else {
+            continue;
+        };
+        if is_ignored_below(root, path) {
+            continue;
+        }
+
+        if path.is_dir() {
+            // A directory moved or copied in: index everything below it
+            for file in walk_files(path, false).flatten() {
+                present.push((file, root, collection));
+            }
+        } else if path.is_file() {
+            present.push((path.clone(), root, collection));
+        } else {
+            let path_str = path.to_string_lossy().to_string();
+            match db.get_resource_by_path(&path_str).await {
+                Ok(Some(resource)) => removed.push(resource),
+                Ok(None) => {
+                    // Possibly a directory without a folder row: drop its files
+                    let prefix = format!("{}{}", path_str, std::path::MAIN_SEPARATOR);
+                    if let Ok(all) = db.get_resources_by_collection(collection).await {
+                        removed.extend(all.into_iter().filter(|r| r.path.starts_with(&prefix)));
+                    }
+                }
+                Err(e) => eprintln!("Indexer: {}", e),
+            }
+        }
+    }
+
+    let mut touched_sources: Vec<(String, &PathBuf)> = Vec::new();
+    let mut collections_with_additions: HashSet<&String> = HashSet::new();
+
+    for (path, root, collection) in present {
+        let path_str = path.to_string_lossy().to_string();
+        let existing = match db.get_resource_by_path(&path_str).await {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("Indexer: {}", e);
+                continue;
+            }
+        };
+
+        // New path with the content of a just-removed resource: a rename
+        if existing.is_none() {
+            if let Ok(bytes) = std::fs::read(&path) {
+                let hash = hash_bytes(&bytes);
+                if let Some(pos) = removed
+                    .iter()
+                    .position(|r| r.content_hash.as_deref() == Some(hash.as_str()))
+                {
+                    let old = removed.remove(pos);
+                    let title = title_for(&path, &bytes);
+                    match db
+                        .relocate_resource(&old.id, &old.path, &path_str, &title, collection, false)
+                        .await
+                    {
+                        Ok(()) => {
+                            changes
+                                .entry(collection.clone())
+                                .or_default()
+                                .updated
+                                .push(old.id.clone());
+                            touched_sources.push((path_str, root));
+                            collections_with_additions.insert(collection);
+                            continue;
+                        }
+                        Err(e) => {
+                            eprintln!("Indexer: {}", e);
+                            removed.push(old);
+                        }
+                    }
+                }
+            }
+        }
+
+        let is_new = existing.is_none();
+        match index_file(db, &path, collection, existing.as_ref()).await {
+            Ok(Some(id)) => {
+                changes
+                    .entry(collection.clone())
+                    .or_default()
+                    .updated
+                    .push(id);
+                touched_sources.push((path_str, root));
+                if is_new {
+                    collections_with_additions.insert(collection);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Indexer: {}", e),
+        }
+    }
+
+    for resource in removed {
+        match db
+            .delete_resource_tree(&resource.id, &resource.path, resource.kind == "folder")
+            .await
+        {
+            Ok(ids) => changes
+                .entry(resource.collection.clone())
+                .or_default()
+                .deleted
+                .extend(ids),
+            Err(e) => eprintln!("Indexer: {}", e),
+        }
+    }
+
+    // New files may satisfy references of unchanged sources, so those
+    // collections are re-linked in full; otherwise only touched sources.
+    for (root, collection) in roots {
+        if collections_with_additions.contains(collection) {
+            if let Ok(all) = db.get_resources_by_collection(collection).await {
+                touched_sources.extend(all.into_iter().map(|r| (r.path, root)));
+            }
+        }
+    }
+    let mut done: HashSet<String> = HashSet::new();
+    for (path, root) in touched_sources {
+        if !dependency_scanner::is_source(&path) || !done.insert(path.clone()) {
+            continue;
+        }
+        let (Ok(Some(resource)), Some(content)) = (
+            db.get_resource_by_path(&path).await,
+            search::fts::read_for_index(&path),
+        ) else {
+            continue;
+        };
+        if let Err(e) =
+            dependency_scanner::update_dependencies(db, &resource, &content, Some(root)).await
+        {
+            eprintln!("Indexer: {}", e);
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_title_prefers_title() {
+        let src = "\\documentclass{article}\n\\title{Limits and  Continuity}\n\\section{Intro}";
+        assert_eq!(
+            extract_title(src),
+            Some("Limits and Continuity".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extract_title_falls_back_to_section() {
+        let src = "% \\section{Commented}\n\\section*{Exercise 3}\nText";
+        assert_eq!(extract_title(src), Some("Exercise 3".to_string()));
+        assert_eq!(extract_title("no structure here"), None);
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        assert!(is_ignored(Path::new("/p/main.aux")));
+        assert!(is_ignored(Path::new("/p/.git")));
+        assert!(!is_ignored(Path::new("/p/main.tex")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_files_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let root = std::env::temp_dir().join(format!("datatex-walk-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("a.tex"), "a").unwrap();
+        std::fs::write(root.join("sub/b.tex"), "b").unwrap();
+        symlink(root.join("a.tex"), root.join("link.tex")).unwrap();
+        symlink(root.join("sub"), root.join("linked")).unwrap();
+        symlink(&root, root.join("sub/loop")).unwrap();
+
+        let walk = |follow| {
+            let (mut files, mut errors) = (Vec::new(), Vec::new());
+            for entry in walk_files(&root, follow) {
+                match entry {
+                    Ok(path) => files.push(
+                        path.strip_prefix(&root)
+                            .unwrap()
+                            .to_string_lossy()
+                            .to_string(),
+                    ),
+                    Err(e) => errors.push(e),
+                }
+            }
+            files.sort();
+            (files, errors)
+        };
+
+        let (files, errors) = walk(false);
+        assert_eq!(files, ["a.tex", "link.tex", "sub/b.tex"]);
+        assert!(errors.is_empty());
+
+        let (files, errors) = walk(true);
+        assert_eq!(files, ["a.tex", "link.tex", "linked/b.tex", "sub/b.tex"]);
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|e| e.contains("links back to")));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_scan_links_legacy_encoded_sources() {
+        let dir = std::env::temp_dir().join(format!("datatex-scan-enc-{}", Uuid::new_v4()));
+        let root = dir.join("notes");
+        std::fs::create_dir_all(&root).unwrap();
+        // ISO-8859-7 "αβγ", which isn't valid UTF-8
+        let mut source = b"\\input{b}\n% ".to_vec();
+        source.extend([0xE1, 0xE2, 0xE3, b'\n']);
+        std::fs::write(root.join("a.tex"), source).unwrap();
+        std::fs::write(root.join("b.tex"), "b").unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let db = DatabaseManager::new(&dir.to_string_lossy()).await.unwrap();
+            let summary = scan_collection(&db, &root.to_string_lossy(), "notes", false)
+                .await
+                .unwrap();
+            assert_eq!(summary.dependencies, 1);
+            db.pool.close().await;
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_scan_moves_existing_collection() {
+        let dir = std::env::temp_dir().join(format!("datatex-scan-{}", Uuid::new_v4()));
+        let (old_root, new_root) = (dir.join("old"), dir.join("new"));
+        for root in [&old_root, &new_root] {
+            std::fs::create_dir_all(root).unwrap();
+            std::fs::write(root.join("a.tex"), "a").unwrap();
+        }
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let db = DatabaseManager::new(&dir.to_string_lossy()).await.unwrap();
+            for root in [&old_root, &new_root] {
+                scan_collection(&db, &root.to_string_lossy(), "notes", false)
+                    .await
+                    .unwrap();
+            }
+            let path: String = sqlx::query_scalar("SELECT path FROM collections WHERE name = ?")
+                .bind("notes")
+                .fetch_one(&db.pool)
+                .await
+                .unwrap();
+            assert_eq!(path, new_root.to_string_lossy());
+            db.pool.close().await;
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,241 @@
+//! Label / Reference Checker
+//!
+//! Collects `\label` definitions and `\ref`-like references across a document
+//! and the files it pulls in through `\input`/`\include` (as recorded by the
+//! dependency scanner), and reports duplicate labels, undefined references
+//! and unreferenced labels as `LogEntry` diagnostics.
+
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::OnceLock;
+
+use crate::database::DatabaseManager;
+use crate::dependency_scanner::strip_comments;
+use crate::log_parser::LogEntry;
+
+/// A label or reference and where it occurs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+    pub name: String,
+    pub file: String,
+    pub line: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelReport {
+    /// Files of the document graph, root first
+    pub files: Vec<String>,
+    pub labels: usize,
+    pub references: usize,
+    pub diagnostics: Vec<LogEntry>,
+}
+
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset].matches('\n').count() + 1
+}
+
+fn collect(re: &Regex, content: &str, file: &str) -> Vec<Occurrence> {
+    let stripped = strip_comments(content);
+    let mut found = Vec::new();
+    for caps in re.captures_iter(&stripped) {
+        // Every non-empty group after the command name holds label names
+        for group in caps.iter().skip(1).flatten() {
+            let line = line_of(&stripped, group.start());
+            for name in group.as_str().split(',') {
+                let name = name.trim();
+                if !name.is_empty() {
+                    found.push(Occurrence {
+                        name: name.to_string(),
+                        file: file.to_string(),
+                        line,
+                    });
+                }
+            }
+        }
+    }
+    found
+}
+
+/// `\label{...}` definitions
+pub fn extract_labels(content: &str, file: &str) -> Vec<Occurrence> {
+    static LABEL_RE: OnceLock<Regex> = OnceLock::new();
+    let re =
+        LABEL_RE.get_or_init(|| Regex::new(r"\\label\s*(?:\[[^\]]*\]\s*)?\{([^}]*)\}").unwrap());
+    collect(re, content, file)
+}
+
+/// `\ref`, `\eqref`, `\pageref`, `\autoref`, `\nameref`, `\vref` and the
+/// cleveref family (comma lists and `\crefrange{a}{b}` included)
+pub fn extract_references(content: &str, file: &str) -> Vec<Occurrence> {
+    static REF_RE: OnceLock<Regex> = OnceLock::new();
+    let re = REF_RE.get_or_init(|| {
+        Regex::new(
+            r"\\(?:[cC]ref|[cC]pageref|labelcref|labelcpageref|eqref|ref|pageref|autoref|nameref|vref|vpageref)\*?\s*\{([^}]*)\}|\\[cC](?:page)?refrange\*?\s*\{([^}]*)\}\s*\{([^}]*)\}",
+        )
+        .unwrap()
+    });
+    collect(re, content, file)
+}
+
+fn diagnostic(kind: &str, message: String, at: &Occurrence) -> LogEntry {
+    LogEntry {
+        r#type: kind.to_string(),
+        message,
+        line: at.line as i32,
+        file: Some(at.file.clone()),
+    }
+}
+
+/// Check labels and references of the given (path, content) files
+pub fn analyze(files: &[(String, String)]) -> (usize, usize, Vec<LogEntry>) {
+    let mut labels = Vec::new();
+    let mut references = Vec::new();
+    for (path, content) in files {
+        labels.extend(extract_labels(content, path));
+        references.extend(extract_references(content, path));
+    }
+
+    let mut diagnostics = Vec::new();
+    let mut first: HashMap<&str, &Occurrence> = HashMap::new();
+    for label in &labels {
+        match first.get(label.name.as_str()) {
+            Some(original) => diagnostics.push(diagnostic(
+                "error",
+                format!(
+                    "Label '{}' multiply defined (first defined in {} line {})",
+                    label.name, original.file, original.line
+                ),
+                label,
+            )),
+            None => {
+                first.insert(&label.name, label);
+            }
+        }
+    }
+
+    let referenced: HashSet<&str> = references.iter().map(|r| r.name.as_str()).collect();
+    for reference in &references {
+        if !first.contains_key(reference.name.as_str()) {
+            diagnostics.push(diagnostic(
+                "warning",
+                format!("Reference '{}' undefined", reference.name),
+                reference,
+            ));
+        }
+    }
+    for label in first.values() {
+        if !referenced.contains(label.name.as_str()) {
+            diagnostics.push(diagnostic(
+                "info",
+                format!("Label '{}' is never referenced", label.name),
+                label,
+            ));
+        }
+    }
+
+    // Stable output: by file order, then line
+    let order: HashMap<&str, usize> = files
+        .iter()
+        .enumerate()
+        .map(|(i, (path, _))| (path.as_str(), i))
+        .collect();
+    diagnostics.sort_by_key(|d| {
+        (
+            d.file.as_deref().and_then(|f| order.get(f)).copied(),
+            d.line,
+        )
+    });
+
+    (labels.len(), references.len(), diagnostics)
+}
+
+/// Check the document `resource_id` together with everything it inputs or includes
+pub async fn check_document(
+    db: &DatabaseManager,
+    resource_id: &str,
+) -> Result<LabelReport, String> {
+    let root = db
+        .get_resource_by_id(resource_id)
+        .await?
+        .ok_or("Resource not found")?;
+
+    let mut files = Vec::new();
+    let mut seen = HashSet::from([root.id.clone()]);
+    let mut queue = VecDeque::from([root]);
+    while let Some(resource) = queue.pop_front() {
+        let Ok(content) = std::fs::read_to_string(&resource.path) else {
+            continue;
+        };
+        for relation in ["input", "include"] {
+            for child in db.get_dependencies(&resource.id, Some(relation)).await? {
+                if seen.insert(child.id.clone()) {
+                    queue.push_back(child);
+                }
+            }
+        }
+        files.push((resource.path, content));
+    }
+
+    let (labels, references, diagnostics) = analyze(&files);
+    Ok(LabelReport {
+        files: files.into_iter().map(|(path, _)| path).collect(),
+        labels,
+        references,
+        diagnostics,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_references() {
+        let refs = extract_references(
+            "see \\cref{fig:a,tab:b} and \\eqref{eq:1}\n% \\ref{hidden}\n\\crefrange{eq:2}{eq:4}",
+            "main.tex",
+        );
+        let names: Vec<(&str, usize)> = refs.iter().map(|r| (r.name.as_str(), r.line)).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("fig:a", 1),
+                ("tab:b", 1),
+                ("eq:1", 1),
+                ("eq:2", 3),
+                ("eq:4", 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_analyze() {
+        let files = vec![
+            (
+                "main.tex".to_string(),
+                "\\label{sec:intro}\nSee \\ref{sec:intro} and \\ref{sec:missing}.".to_string(),
+            ),
+            (
+                "ch1.tex".to_string(),
+                "\\label{sec:intro}\n\\label{fig:unused}".to_string(),
+            ),
+        ];
+        let (labels, references, diagnostics) = analyze(&files);
+        assert_eq!((labels, references), (3, 2));
+
+        let summary: Vec<(&str, &str, i32)> = diagnostics
+            .iter()
+            .map(|d| (d.r#type.as_str(), d.file.as_deref().unwrap(), d.line))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("warning", "main.tex", 2),
+                ("error", "ch1.tex", 1),
+                ("info", "ch1.tex", 2),
+            ]
+        );
+    }
+}
@@ -8,14 +8,53 @@ use walkdir::WalkDir; // For typed metadata queries
 
 mod agent;
 mod ai;
+mod attachments;
+mod bib;
+mod collection_archive;
+mod compile_status;
 mod compiler;
+mod custom_fields;
 mod database;
+mod dependency_scanner;
+mod document_builder;
+mod drafts;
+mod export;
+mod fonts;
 mod git;
+mod git_credentials;
+mod git_merge;
+mod git_message;
+mod git_rebase;
+mod git_signing;
+mod git_snapshots;
+mod graph_layout;
+mod graph_reports;
 mod history;
+mod http_api;
+mod images;
+mod import;
+mod indexer;
+mod labels;
 mod lsp;
+mod normalizer;
+mod outline;
+mod package_docs;
+mod pandoc;
+mod preamble;
+mod project;
+mod quick_open;
+mod recent_projects;
+mod resources;
 mod search;
+mod sessions;
+mod settings;
+mod spellcheck;
+mod submission;
+mod templates;
 mod texlab_downloader;
 mod tools;
+mod trash;
+mod tree_groups;
 mod vectors;
 mod watcher;
 
@@ -42,13 +81,162 @@ use std::sync::Arc;
 struct AppState {
     db_manager: Arc<Mutex<Option<DatabaseManager>>>,
     lsp_manager: Arc<Mutex<Option<TexlabManager>>>,
+    blame_cache: Arc<git::BlameCache>,
+    status_cache: Arc<git::StatusCache>,
+    graph_cache: Arc<graph_processor::GraphCache>,
+    quick_open_cache: Arc<quick_open::QuickOpenCache>,
+    /// Folder of the open project, set by `open_project`
+    project_root: std::sync::RwLock<Option<std::path::PathBuf>>,
 }
 
 // 2. Open Project Command
+/// How long `open_project` waits for the database opened at startup
+const DB_WAIT_ATTEMPTS: usize = 40;
+const DB_WAIT_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Make `path` the active project: remember it, re-index the collections
+/// stored in it, look for its Git repository and move texlab over to it
+#[tauri::command]
+async fn open_project(
+    path: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<project::ProjectInfo, String> {
+    let root = fs::canonicalize(&path).map_err(|e| format!("Cannot open {}: {}", path, e))?;
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", path));
+    }
+    let root_str = root.to_string_lossy().to_string();
+    println!("Setting active project path to: {}", root_str);
+
+    *state.project_root.write().map_err(|e| e.to_string())? = Some(root.clone());
+    recent_projects::record(&recent_projects::recent_projects_path()?, &root_str)?;
+
+    // The database is opened in the background at startup
+    let mut collections = None;
+    for _ in 0..DB_WAIT_ATTEMPTS {
+        if let Some(db) = state.db_manager.lock().await.as_ref() {
+            collections = Some(db.get_collections().await?);
+            break;
+        }
+        tokio::time::sleep(DB_WAIT_INTERVAL).await;
+    }
+    let database_ready = collections.is_some();
+    let in_project = project::collections_in(&root, collections.unwrap_or_default());
+    let collections: Vec<String> = in_project.iter().map(|(name, _)| name.clone()).collect();
+
+    if !in_project.is_empty() {
+        let db_manager = state.db_manager.clone();
+        tauri::async_runtime::spawn(async move {
+            let db_guard = db_manager.lock().await;
+            let Some(db) = db_guard.as_ref() else {
+                return;
+            };
+            for (name, collection_root) in in_project {
+                match indexer::scan_collection(db, &collection_root, &name, false).await {
+                    Ok(summary) if !summary.changed_ids.is_empty() => {
+                        resources::emit_changed(&app, "updated", summary.changed_ids, Some(name));
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Indexing {} failed: {}", name, e),
+                }
+            }
+        });
+    }
+
+    let git = git::detect_repo(&root_str)?;
+
+    // A running texlab keeps its first root; restart it on the new one
+    let root_uri = project::path_to_uri(&root);
+    let mut lsp_guard = state.lsp_manager.lock().await;
+    let lsp_restarted = if let Some(mut manager) = lsp_guard.take() {
+        if let Err(e) = manager.stop().await {
+            eprintln!("Failed to stop texlab: {}", e);
+        }
+        *lsp_guard = Some(start_texlab(&root_uri).await?);
+        true
+    } else {
+        false
+    };
+
+    Ok(project::ProjectInfo {
+        name: root
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| root_str.clone()),
+        root: root_str,
+        collections,
+        git,
+        database_ready,
+        root_uri,
+        lsp_restarted,
+    })
+}
+
+/// The folder passed to the last `open_project`
+#[tauri::command]
+fn get_active_project(state: State<'_, AppState>) -> Result<Option<String>, String> {
+    let root = state.project_root.read().map_err(|e| e.to_string())?;
+    Ok(root.as_ref().map(|p| p.to_string_lossy().to_string()))
+}
+
+/// Put `path` on the recent projects list without opening it
+#[tauri::command]
+fn add_recent_project(path: String) -> Result<Vec<recent_projects::RecentProject>, String> {
+    recent_projects::record(&recent_projects::recent_projects_path()?, &path)
+}
+
+#[tauri::command]
+fn list_recent_projects() -> Result<Vec<recent_projects::RecentProject>, String> {
+    Ok(recent_projects::list(
+        &recent_projects::recent_projects_path()?,
+    ))
+}
+
+#[tauri::command]
+fn pin_recent_project(
+    path: String,
+    pinned: bool,
+) -> Result<Vec<recent_projects::RecentProject>, String> {
+    recent_projects::set_pinned(&recent_projects::recent_projects_path()?, &path, pinned)
+}
+
+#[tauri::command]
+fn remove_recent_project(path: String) -> Result<Vec<recent_projects::RecentProject>, String> {
+    recent_projects::remove(&recent_projects::recent_projects_path()?, &path)
+}
+
+#[tauri::command]
+fn get_settings() -> settings::Settings {
+    settings::current()
+}
+
+/// Apply a partial settings change and tell every window about it
+#[tauri::command]
+async fn update_settings(
+    app: tauri::AppHandle,
+    patch: serde_json::Value,
+) -> Result<settings::Settings, String> {
+    let updated = settings::update(patch)?;
+    let _ = tauri::Emitter::emit(&app, "settings-changed", &updated);
+    // The settings are saved either way; a server that can't start reports
+    // why through `api_server_status_cmd`
+    if let Err(e) = http_api::sync(&app, &updated.api).await {
+        eprintln!("HTTP API server: {}", e);
+    }
+    Ok(updated)
+}
+
+/// Whether the local HTTP API is running, where, and its token
+#[tauri::command]
+async fn api_server_status_cmd(app: tauri::AppHandle) -> Result<http_api::ApiStatus, String> {
+    http_api::status(&app).await
+}
+
+/// Replace the local HTTP API token
 #[tauri::command]
-async fn open_project(path: String, _state: State<'_, AppState>) -> Result<String, String> {
-    println!("Setting active project path to: {}", path);
-    Ok("Project path set (Global DB in use)".to_string())
+async fn regenerate_api_token_cmd(app: tauri::AppHandle) -> Result<http_api::ApiStatus, String> {
+    http_api::regenerate_token(&app).await
 }
 
 #[tauri::command]
@@ -64,13 +252,29 @@ fn get_db_path() -> Result<String, String> {
 
 // ... Existing commands ...
 #[tauri::command]
-fn compile_tex(
+async fn compile_tex(
     file_path: String,
     engine: String,
     args: Vec<String>,
     output_dir: String,
+    state: State<'_, AppState>,
 ) -> Result<String, String> {
-    compiler::compile(&file_path, &engine, args, &output_dir)
+    let result = compiler::compile(&file_path, &engine, args, &output_dir);
+    if result.is_ok() {
+        if let Err(e) = git_snapshots::snapshot_after_compile(&file_path) {
+            eprintln!("Failed to snapshot {}: {}", file_path, e);
+        }
+    }
+
+    // Remember the outcome when the file is a known resource
+    let db_guard = state.db_manager.lock().await;
+    if let Some(db) = db_guard.as_ref() {
+        if let Some(resource) = db.get_resource_by_path(&file_path).await? {
+            let log_path = compile_status::log_path(&file_path, &output_dir);
+            compile_status::record(&db.pool, &resource.id, &engine, &result, &log_path).await?;
+        }
+    }
+    result
 }
 
 #[tauri::command]
@@ -97,29 +301,11 @@ async fn compile_resource_cmd(id: String, state: State<'_, AppState>) -> Result<
     let build_command = metadata_json
         .get("buildCommand")
         .and_then(|v| v.as_str())
-        .unwrap_or("pdflatex");
+        .unwrap_or(settings::current().compile.default_engine.as_str());
 
     if let Some(preamble_id) = preamble_id_opt {
         // Need to wrap content
-        let preamble_content = if preamble_id.starts_with("builtin:") {
-            // Simple built-in defaults
-            if preamble_id == "builtin:beamer" {
-                "\\documentclass{beamer}\n\\usepackage[utf8]{inputenc}\n".to_string()
-            } else {
-                "\\documentclass{article}\n\\usepackage[utf8]{inputenc}\n\\usepackage{amsmath}\n"
-                    .to_string()
-            }
-        } else {
-            // Fetch preamble resource
-            let preamble_res = db
-                .get_resource_by_id(preamble_id)
-                .await?
-                .ok_or("Preamble resource not found")?;
-
-            // If preamble resource is physically on disk, read it
-            fs::read_to_string(&preamble_res.path)
-                .map_err(|e| format!("Failed to read preamble file: {}", e))?
-        };
+        let preamble_content = document_builder::resolve_preamble(db, preamble_id).await?;
 
         // Read the actual resource content
         // Assuming resource.path is valid
@@ -153,6 +339,8 @@ async fn compile_resource_cmd(id: String, state: State<'_, AppState>) -> Result<
             vec![jobname_arg],
             &output_dir,
         );
+        let log_path = parent_dir.join(format!("{}.log", file_stem));
+        compile_status::record(&db.pool, &resource.id, build_command, &result, &log_path).await?;
 
         // Keep temp file for debugging/logging.
 
@@ -173,7 +361,11 @@ async fn compile_resource_cmd(id: String, state: State<'_, AppState>) -> Result<
             .unwrap_or(std::path::Path::new("."));
         let output_dir = parent_dir.to_string_lossy().to_string();
 
-        match compiler::compile(&resource.path, build_command, vec![], &output_dir) {
+        let result = compiler::compile(&resource.path, build_command, vec![], &output_dir);
+        let log_path = compile_status::log_path(&resource.path, "");
+        compile_status::record(&db.pool, &resource.id, build_command, &result, &log_path).await?;
+
+        match result {
             Ok(_) => {
                 // Assume PDF is [filename].pdf
                 let original_path = std::path::Path::new(&resource.path);
@@ -187,36 +379,25 @@ async fn compile_resource_cmd(id: String, state: State<'_, AppState>) -> Result<
     }
 }
 
+/// Last compile results, e.g. the exercises of a collection that currently fail
 #[tauri::command]
-fn get_system_fonts() -> Vec<String> {
-    use std::process::Command;
-    let output = if cfg!(target_os = "linux") {
-        Command::new("fc-list").arg(":").arg("family").output().ok()
-    } else {
-        None
-    };
+async fn get_compile_statuses_cmd(
+    collection: Option<String>,
+    failed_only: bool,
+    state: State<'_, AppState>,
+) -> Result<Vec<compile_status::CompileStatus>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
 
-    if let Some(output) = output {
-        if output.status.success() {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let mut fonts: Vec<String> = stdout
-                .lines()
-                .flat_map(|line| line.split(','))
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
-            fonts.sort();
-            fonts.dedup();
-            return fonts;
-        }
-    }
-    vec![
-        "Consolas".to_string(),
-        "Monaco".to_string(),
-        "Courier New".to_string(),
-        "monospace".to_string(),
-        "Arial".to_string(),
-    ]
+    compile_status::list(&db.pool, collection.as_deref(), failed_only).await
+}
+
+/// Installed font families, for the editor font picker
+#[tauri::command]
+async fn get_system_fonts() -> Result<Vec<fonts::FontFamily>, String> {
+    tauri::async_runtime::spawn_blocking(fonts::system_fonts)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 #[derive(serde::Serialize)]
@@ -233,13 +414,25 @@ async fn get_table_data_cmd(
     page_size: i64,
     search: String,
     search_cols: Vec<String>,
+    query: Option<database::manager::TableQuery>,
     state: State<'_, AppState>,
 ) -> Result<TableDataResponse, String> {
     let db_guard = state.db_manager.lock().await;
     if let Some(db) = &*db_guard {
-        let (data, total_count, columns) = db
-            .get_table_data(table_name, page, page_size, search, search_cols)
+        let is_resources = table_name == "resources";
+        let (mut data, total_count, mut columns) = db
+            .get_table_data(
+                table_name,
+                page,
+                page_size,
+                search,
+                search_cols,
+                query.unwrap_or_default(),
+            )
             .await?;
+        if is_resources {
+            compile_status::attach_to_rows(&db.pool, &mut data, &mut columns).await?;
+        }
         Ok(TableDataResponse {
             data,
             total_count,
@@ -250,6 +443,37 @@ async fn get_table_data_cmd(
     }
 }
 
+/// Lists the tables that get_table_data_cmd/update_cell_cmd will accept
+#[tauri::command]
+async fn get_database_tables_cmd(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    database::schema::list_tables(&db.pool).await
+}
+
+/// Connection pragmas and pool statistics, for diagnosing locking issues
+#[tauri::command]
+async fn db_health_cmd(state: State<'_, AppState>) -> Result<database::manager::DbHealth, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    db.health().await
+}
+
+/// Audit trail of a resource: changes to its row and to rows referencing it
+#[tauri::command]
+async fn get_change_history_cmd(
+    resource_id: String,
+    limit: Option<i64>,
+    state: State<'_, AppState>,
+) -> Result<Vec<database::audit::ChangeEntry>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    database::audit::get_change_history(&db.pool, &resource_id, limit.unwrap_or(200)).await
+}
+
 #[tauri::command]
 async fn update_cell_cmd(
     table_name: String,
@@ -354,27 +578,7 @@ async fn import_folder_cmd(
             let file_path = entry.path().to_string_lossy().to_string();
             let file_name = entry.file_name().to_string_lossy().to_string();
 
-            // Simple type detection extension
-            let kind = if file_name.ends_with(".tex") {
-                "file"
-            } else if file_name.ends_with(".bib") {
-                "bibliography"
-            } else if file_name.ends_with(".sty") {
-                "package"
-            } else if file_name.ends_with(".cls") {
-                "class"
-            } else if file_name.ends_with(".dtx") {
-                "dtx"
-            } else if file_name.ends_with(".ins") {
-                "ins"
-            } else if file_name.ends_with(".png")
-                || file_name.ends_with(".jpg")
-                || file_name.ends_with(".pdf")
-            {
-                "figure"
-            } else {
-                "file"
-            };
+            let kind = resources::kind_from_path(&file_name);
 
             let resource = Resource {
                 id: Uuid::new_v4().to_string(),
@@ -393,6 +597,7 @@ async fn import_folder_cmd(
                 // Continue despite errors? or fail?
                 // For now, log and continue.
             } else {
+                bib::sync_file(&db.pool, &resource).await;
                 count += 1;
             }
         }
@@ -409,15 +614,132 @@ async fn delete_collection_cmd(
     let db_guard = state.db_manager.lock().await;
     let db = db_guard.as_ref().ok_or("Database not initialized")?;
 
-    db.delete_collection(&collection_name).await
+    let deleted = db.delete_collection(&collection_name).await;
+    // Its resources went with it, possibly even when deleting failed halfway
+    state.graph_cache.invalidate();
+    state.quick_open_cache.invalidate();
+    deleted
+}
+
+/// Removes the resource row; with `delete_file` the file/folder on disk goes too
+#[tauri::command]
+async fn delete_resource_cmd(
+    id: String,
+    delete_file: Option<bool>,
+    permanent: Option<bool>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    // Deletions go to the recycle bin unless asked otherwise
+    let delete_file = delete_file.unwrap_or(false);
+    let removed = if permanent.unwrap_or(false) {
+        resources::delete(db, &id, delete_file).await?
+    } else {
+        trash::delete(db, &id, delete_file).await?
+    };
+    // Attachment rows went with the resource; drop their now unreferenced blobs
+    if let Err(e) = attachments::collect_garbage(db).await {
+        eprintln!("Attachment cleanup failed: {}", e);
+    }
+    resources::emit_changed(&app, "deleted", removed, None);
+    Ok(())
+}
+
+/// Remember the window state, called when the window closes
+#[tauri::command]
+async fn save_session_cmd(
+    session: sessions::Session,
+    state: State<'_, AppState>,
+) -> Result<i64, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    sessions::save(&db.pool, &session).await
+}
+
+/// The window state to restore on startup
+#[tauri::command]
+async fn get_last_session_cmd(
+    state: State<'_, AppState>,
+) -> Result<Option<sessions::Session>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    sessions::get_last_session(&db.pool).await
+}
+
+/// Keep a draft of an unsaved document, for recovery after a crash
+#[tauri::command]
+fn autosave_document(uri: String, content: String) -> Result<String, String> {
+    drafts::autosave(&drafts::drafts_dir()?, &uri, &content)
+}
+
+/// Drafts newer than the file they belong to, checked on startup
+#[tauri::command]
+fn list_recoverable_drafts() -> Result<Vec<drafts::Draft>, String> {
+    drafts::list_recoverable(&drafts::drafts_dir()?)
+}
+
+#[tauri::command]
+fn recover_draft(id: String) -> Result<String, String> {
+    drafts::recover(&drafts::drafts_dir()?, &id)
+}
+
+/// Drop the drafts of a document once it is saved or recovery is declined
+#[tauri::command]
+fn discard_drafts(uri: String) -> Result<(), String> {
+    drafts::discard(&drafts::drafts_dir()?, &uri)
+}
+
+#[tauri::command]
+async fn list_deleted_resources_cmd(
+    collection: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<trash::DeletedResource>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    trash::list(db, collection.as_deref()).await
+}
+
+#[tauri::command]
+async fn restore_deleted_resource_cmd(
+    id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    let restored = trash::restore(db, &id).await?;
+    // The content index isn't part of the audit log
+    let mut collection = None;
+    for rid in &restored {
+        let Some(resource) = db.get_resource_by_id(rid).await? else {
+            continue;
+        };
+        if let Some(content) = search::fts::read_for_index(&resource.path) {
+            search::fts::update_content(&db.pool, &resource.id, &content).await?;
+        }
+        collection.get_or_insert(resource.collection);
+    }
+    resources::emit_changed(&app, "created", restored.clone(), collection);
+    Ok(restored)
 }
 
 #[tauri::command]
-async fn delete_resource_cmd(id: String, state: State<'_, AppState>) -> Result<(), String> {
+async fn purge_deleted_resources_cmd(
+    id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
     let db_guard = state.db_manager.lock().await;
     let db = db_guard.as_ref().ok_or("Database not initialized")?;
 
-    db.delete_resource(&id).await
+    let purged = trash::purge(db, id.as_deref()).await?;
+    if let Err(e) = attachments::collect_garbage(db).await {
+        eprintln!("Attachment cleanup failed: {}", e);
+    }
+    Ok(purged)
 }
 
 #[tauri::command]
@@ -426,50 +748,126 @@ async fn create_resource_cmd(
     collection_name: String,
     content: String,
     metadata: Option<serde_json::Value>,
+    app: tauri::AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
     let db_guard = state.db_manager.lock().await;
     let db = db_guard.as_ref().ok_or("Database not initialized")?;
 
-    // 1. Write file to disk
-    fs::write(&path, &content).map_err(|e| e.to_string())?;
+    // Writes the file, then the row (file is removed again if the insert fails)
+    let resource = resources::create(db, &path, &collection_name, &content, metadata).await?;
 
-    // 2. Add to database
-    let file_name = std::path::Path::new(&path)
-        .file_name()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_string();
+    // Index content for metadata search
+    search::fts::update_content(&db.pool, &resource.id, &content).await?;
 
-    let kind = if file_name.ends_with(".tex") {
-        "file"
-    } else if file_name.ends_with(".bib") {
-        "bibliography"
-    } else if file_name.ends_with(".sty") {
-        "package"
-    } else if file_name.ends_with(".cls") {
-        "class"
-    } else if file_name.ends_with(".dtx") {
-        "dtx"
-    } else if file_name.ends_with(".ins") {
-        "ins"
-    } else {
-        "file"
-    };
+    resources::emit_changed(&app, "created", vec![resource.id], Some(collection_name));
+    Ok(())
+}
 
-    let resource = Resource {
-        id: Uuid::new_v4().to_string(),
-        path: path.clone(),
-        kind: kind.to_string(),
-        collection: collection_name,
-        title: Some(file_name),
-        content_hash: None,
-        metadata: Some(metadata.unwrap_or(serde_json::json!({}))),
-        created_at: None,
-        updated_at: None,
-    };
+#[tauri::command]
+async fn rename_resource_cmd(
+    id: String,
+    new_name: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Resource, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
 
-    db.add_resource(&resource).await
+    let old_path = db
+        .get_resource_by_id(&id)
+        .await?
+        .ok_or("Resource not found")?
+        .path;
+    let resource = resources::rename(db, &id, &new_name).await?;
+    resources::emit_changed(
+        &app,
+        "renamed",
+        vec![resource.id.clone()],
+        Some(resource.collection.clone()),
+    );
+    update_references(&app, db, &old_path, &resource).await;
+    Ok(resource)
+}
+
+#[tauri::command]
+async fn move_resource_cmd(
+    id: String,
+    target_dir: String,
+    target_collection: Option<String>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Resource, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    let old_path = db
+        .get_resource_by_id(&id)
+        .await?
+        .ok_or("Resource not found")?
+        .path;
+    let resource = resources::move_to(db, &id, &target_dir, target_collection.as_deref()).await?;
+    resources::emit_changed(
+        &app,
+        "moved",
+        vec![resource.id.clone()],
+        Some(resource.collection.clone()),
+    );
+    update_references(&app, db, &old_path, &resource).await;
+    Ok(resource)
+}
+
+/// Point references to a renamed or moved resource at its new path. The move
+/// itself already succeeded, so failures are only logged.
+async fn update_references(
+    app: &tauri::AppHandle,
+    db: &DatabaseManager,
+    old_path: &str,
+    resource: &Resource,
+) {
+    match resources::fix_references(db, old_path, resource).await {
+        Ok(ids) if !ids.is_empty() => resources::emit_changed(app, "updated", ids, None),
+        Ok(_) => {}
+        Err(e) => eprintln!("Failed to update references to {}: {}", resource.path, e),
+    }
+}
+
+/// Copy a file resource next to itself and register the copy
+#[tauri::command]
+async fn duplicate_resource_cmd(
+    id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Resource, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    let copy = resources::duplicate(db, &id).await?;
+    if let Some(content) = search::fts::read_for_index(&copy.path) {
+        search::fts::update_content(&db.pool, &copy.id, &content).await?;
+        if dependency_scanner::is_source(&copy.path) {
+            let root = db
+                .get_collections()
+                .await?
+                .into_iter()
+                .find(|c| c.name == copy.collection)
+                .and_then(|c| c.path);
+            dependency_scanner::update_dependencies(
+                db,
+                &copy,
+                &content,
+                root.as_deref().map(std::path::Path::new),
+            )
+            .await?;
+        }
+    }
+    resources::emit_changed(
+        &app,
+        "created",
+        vec![copy.id.clone()],
+        Some(copy.collection.clone()),
+    );
+    Ok(copy)
 }
 
 #[tauri::command]
@@ -503,314 +901,1361 @@ async fn create_folder_cmd(
         updated_at: None,
     };
 
-    db.add_resource(&resource).await
+    db.add_resource(&resource).await
+}
+
+#[tauri::command]
+async fn import_file_cmd(
+    path: String,
+    collection_name: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    println!(
+        "import_file_cmd called with path: '{}', collection: '{}'",
+        path, collection_name
+    );
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    // 1. Get Collection Path
+    // Since we don't have get_collection_by_name exposed yet, fetch all and find.
+    let collections = db.get_collections().await?;
+    let target_col = collections
+        .into_iter()
+        .find(|c| c.name == collection_name)
+        .ok_or("Collection not found")?;
+
+    let col_path_str = target_col.path.ok_or("Collection has no physical path")?;
+    let col_path = std::path::Path::new(&col_path_str);
+
+    // 2. Prepare Destination Path
+    let src_path = std::path::Path::new(&path);
+    let file_name = src_path.file_name().ok_or("Invalid source file name")?;
+
+    let mut dest_path = col_path.join(file_name);
+
+    // 3. Handle Duplicates (Auto-rename)
+    if dest_path.exists() {
+        let file_stem = src_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let extension = src_path
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        let mut counter = 1;
+        while dest_path.exists() {
+            let new_name = if extension.is_empty() {
+                format!("{}_{}", file_stem, counter)
+            } else {
+                format!("{}_{}.{}", file_stem, counter, extension)
+            };
+            dest_path = col_path.join(new_name);
+            counter += 1;
+        }
+    }
+
+    // 4. Perform Copy
+    // Check if source and dest are the same (already in folder)
+    if src_path != dest_path {
+        fs::copy(&src_path, &dest_path).map_err(|e| format!("Failed to copy file: {}", e))?;
+    }
+
+    // 5. Register NEW path in Database
+    let final_path_str = dest_path.to_string_lossy().to_string();
+    let final_file_name = dest_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    let kind = resources::kind_from_path(&final_file_name);
+
+    let resource = Resource {
+        id: Uuid::new_v4().to_string(),
+        path: final_path_str,
+        kind: kind.to_string(),
+        collection: collection_name,
+        title: Some(final_file_name),
+        content_hash: None,
+        metadata: Some(serde_json::json!({})),
+        created_at: None,
+        updated_at: None,
+    };
+
+    db.add_resource(&resource).await?;
+    bib::sync_file(&db.pool, &resource).await;
+    Ok(())
+}
+
+#[tauri::command]
+fn reveal_path_cmd(path: String) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    {
+        std::process::Command::new("xdg-open")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    }
+    #[cfg(target_os = "windows")]
+    {
+        std::process::Command::new("explorer")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    }
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("open")
+            .arg("-R")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn link_resources_cmd(
+    source_id: String,
+    target_id: String,
+    relation_type: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    db.add_dependency(&source_id, &target_id, &relation_type)
+        .await?;
+    state.graph_cache.invalidate();
+    Ok(())
+}
+
+#[tauri::command]
+async fn find_orphan_resources_cmd(
+    collections: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<Vec<graph_reports::OrphanResource>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    graph_reports::find_orphans(db, collections).await
+}
+
+#[tauri::command]
+async fn find_broken_links_cmd(
+    collections: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<Vec<graph_reports::BrokenLink>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    graph_reports::find_broken_links(db, collections).await
+}
+
+#[tauri::command]
+async fn get_linked_resources_cmd(
+    source_id: String,
+    relation_type: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Resource>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    db.get_dependencies(&source_id, relation_type.as_deref())
+        .await
+}
+
+#[tauri::command]
+async fn get_all_dependencies_cmd(
+    state: State<'_, AppState>,
+) -> Result<Vec<(String, String, String)>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    db.get_all_dependencies().await
+}
+
+// ===== Search Command =====
+
+/// Resources of the given collections (all if empty)
+async fn search_scope(
+    db: &DatabaseManager,
+    collections: &[String],
+) -> Result<Vec<Resource>, String> {
+    if collections.is_empty() {
+        let all_collections = db.get_collections().await?;
+        let collection_names: Vec<String> =
+            all_collections.iter().map(|c| c.name.clone()).collect();
+        db.get_resources_by_collections(&collection_names).await
+    } else {
+        db.get_resources_by_collections(collections).await
+    }
+}
+
+#[tauri::command]
+async fn search_database_files(
+    query: search::SearchQuery,
+    collections: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<search::SearchResult, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    let start_time = std::time::Instant::now();
+    let resources = search_scope(db, &collections).await?;
+
+    let metadata_matches = if query.mode == search::SearchMode::Content {
+        Vec::new()
+    } else {
+        let fields = search::fts::metadata_fields(&db.pool, &resources).await?;
+        search::search_metadata_fields(&query, &resources, &fields)?
+    };
+
+    let mut result = if query.mode == search::SearchMode::Metadata {
+        search::SearchResult {
+            matches: Vec::new(),
+            total_files_searched: 0,
+            search_duration_ms: 0,
+            used_index: false,
+            skipped_files: Vec::new(),
+            total_matches: 0,
+            file_match_counts: Vec::new(),
+        }
+    } else {
+        let (resources, used_index) =
+            search::fts::content_prefilter(&db.pool, &query, resources).await?;
+        let mut result = search::search_in_files(&query, resources)?;
+        result.used_index = used_index;
+        result
+    };
+
+    // Metadata hits first; they are usually what the user is looking for
+    if !metadata_matches.is_empty() {
+        result.total_matches += metadata_matches.len();
+        result.matches.splice(0..0, metadata_matches);
+        result.matches.truncate(query.max_results);
+    }
+    result.search_duration_ms = start_time.elapsed().as_millis() as u64;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn replace_database_files(
+    query: search::ReplaceQuery,
+    collections: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<search::ReplaceResult, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    let resources = search_scope(db, &collections).await?;
+    let result = search::replace_in_files(&query, resources)?;
+
+    let summary = format!(
+        "Before replacing '{}' with '{}'",
+        query.search.text, query.replace_with
+    );
+    for (path, previous) in &result.previous_contents {
+        history::save_snapshot(&db.pool, path, previous, Some(&summary), false).await?;
+    }
+    Ok(result)
+}
+
+/// First phase of a replace: the per-line changes, without writing anything
+#[tauri::command]
+async fn preview_replace_cmd(
+    query: search::ReplaceQuery,
+    collections: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<search::ReplacePreview, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    let resources = search_scope(db, &collections).await?;
+    search::preview_replace(&query, resources)
+}
+
+/// Second phase: applies only the hunks the user accepted from the preview
+#[tauri::command]
+async fn apply_replace_cmd(
+    files: Vec<search::FileReplacePreview>,
+    backup: Option<search::ReplaceBackup>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<search::ReplaceResult, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    // Only write files that belong to a known resource
+    for file in &files {
+        let resource = db
+            .get_resource_by_id(&file.resource_id)
+            .await?
+            .ok_or_else(|| format!("Resource not found: {}", file.resource_id))?;
+        if resource.path != file.file_path {
+            return Err(format!("Path mismatch for resource {}", file.resource_id));
+        }
+    }
+
+    let result = search::apply_replace(&files, backup.unwrap_or_default())?;
+    for (path, previous) in &result.previous_contents {
+        history::save_snapshot(&db.pool, path, previous, Some("Before replacing"), false).await?;
+    }
+    for file in &files {
+        if let Some(content) = search::fts::read_for_index(&file.file_path) {
+            search::fts::update_content(&db.pool, &file.resource_id, &content).await?;
+        }
+    }
+    resources::emit_changed(
+        &app,
+        "updated",
+        files.iter().map(|f| f.resource_id.clone()).collect(),
+        None,
+    );
+    Ok(result)
+}
+
+/// Rewrites the files of the given resources as UTF-8. Returns the ids of the
+/// files that were converted; files already in UTF-8 are left alone.
+#[tauri::command]
+async fn convert_to_utf8_cmd(
+    resource_ids: Vec<String>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    let mut converted = Vec::new();
+    for id in resource_ids {
+        let resource = db
+            .get_resource_by_id(&id)
+            .await?
+            .ok_or_else(|| format!("Resource not found: {}", id))?;
+        if search::encoding::convert_to_utf8(&resource.path)?.is_some() {
+            if let Some(content) = search::fts::read_for_index(&resource.path) {
+                search::fts::update_content(&db.pool, &id, &content).await?;
+            }
+            converted.push(id);
+        }
+    }
+    if !converted.is_empty() {
+        resources::emit_changed(&app, "updated", converted.clone(), None);
+    }
+    Ok(converted)
+}
+
+/// Files a character scan or normalization covers: the file at `path`, or
+/// the text files of `collection`, with their resource ids
+async fn normalizer_targets(
+    db: &DatabaseManager,
+    path: Option<String>,
+    collection: Option<String>,
+) -> Result<Vec<(String, Option<String>)>, String> {
+    match (path, collection) {
+        (Some(path), _) => {
+            let resource_id = db.get_resource_by_path(&path).await?.map(|r| r.id);
+            Ok(vec![(path, resource_id)])
+        }
+        (None, Some(collection)) => Ok(db
+            .get_resources_by_collection(&collection)
+            .await?
+            .into_iter()
+            .filter(|r| {
+                search::fts::is_indexable(&r.path) && std::path::Path::new(&r.path).is_file()
+            })
+            .map(|r| (r.path, Some(r.id)))
+            .collect()),
+        (None, None) => Err("Give a file or a collection".to_string()),
+    }
+}
+
+/// Problem characters (non-UTF-8 encodings, non-breaking spaces, smart
+/// quotes, confusables, ...) in a file or the text files of a collection.
+/// Only files with findings are returned.
+#[tauri::command]
+async fn scan_characters_cmd(
+    path: Option<String>,
+    collection: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<normalizer::FileCharacters>, String> {
+    let targets = {
+        let db_guard = state.db_manager.lock().await;
+        let db = db_guard.as_ref().ok_or("Database not initialized")?;
+        normalizer_targets(db, path, collection).await?
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut reports = Vec::new();
+        for (path, resource_id) in targets {
+            reports.extend(normalizer::scan_file(&path, resource_id)?);
+        }
+        Ok(reports)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Normalize the problem characters `scan_characters_cmd` finds. Without
+/// `apply` nothing is written and the result is a preview of the changes.
+#[tauri::command]
+async fn normalize_characters_cmd(
+    path: Option<String>,
+    collection: Option<String>,
+    options: Option<normalizer::NormalizeOptions>,
+    apply: bool,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<normalizer::FileNormalization>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    let targets = normalizer_targets(db, path, collection).await?;
+    let options = options.unwrap_or_default();
+    let results = tauri::async_runtime::spawn_blocking(move || {
+        let mut results = Vec::new();
+        for (path, resource_id) in targets {
+            results.extend(normalizer::normalize_file(
+                &path,
+                resource_id,
+                &options,
+                apply,
+            )?);
+        }
+        Ok::<_, String>(results)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    if apply {
+        let mut updated = Vec::new();
+        for result in &results {
+            let Some(id) = &result.resource_id else {
+                continue;
+            };
+            if let Some(content) = search::fts::read_for_index(&result.path) {
+                search::fts::update_content(&db.pool, id, &content).await?;
+            }
+            updated.push(id.clone());
+        }
+        if !updated.is_empty() {
+            resources::emit_changed(&app, "updated", updated, None);
+        }
+    }
+    Ok(results)
+}
+
+#[tauri::command]
+async fn search_metadata_cmd(
+    query: String,
+    collections: Vec<String>,
+    limit: Option<i64>,
+    state: State<'_, AppState>,
+) -> Result<Vec<search::fts::MetadataMatch>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    search::fts::search_metadata(&db.pool, &query, &collections, limit.unwrap_or(50)).await
+}
+
+/// Resources for the quick-open switcher, best matches first
+#[tauri::command]
+async fn quick_open(
+    query: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<quick_open::QuickOpenItem>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    quick_open::quick_open(&db.pool, &state.quick_open_cache, &query, limit).await
+}
+
+/// Log that a resource was opened, for quick-open ranking
+#[tauri::command]
+async fn record_resource_open(
+    resource_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    quick_open::record_open(&db.pool, &state.quick_open_cache, &resource_id).await
+}
+
+/// Re-reads file contents of the given collections (all if empty) into the FTS index
+#[tauri::command]
+async fn rebuild_metadata_index_cmd(
+    collections: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    let resources = if collections.is_empty() {
+        let all_collections = db.get_collections().await?;
+        let collection_names: Vec<String> =
+            all_collections.iter().map(|c| c.name.clone()).collect();
+        db.get_resources_by_collections(&collection_names).await?
+    } else {
+        db.get_resources_by_collections(&collections).await?
+    };
+
+    search::fts::rebuild_content(&db.pool, &resources).await
+}
+
+/// Walks a collection's root directory and syncs its resources with what is on disk
+#[tauri::command]
+async fn scan_collection_cmd(
+    root_path: String,
+    collection: String,
+    follow_links: Option<bool>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<indexer::ScanSummary, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    let summary =
+        indexer::scan_collection(db, &root_path, &collection, follow_links.unwrap_or(false))
+            .await?;
+    if !summary.changed_ids.is_empty() {
+        resources::emit_changed(
+            &app,
+            "updated",
+            summary.changed_ids.clone(),
+            Some(collection),
+        );
+    }
+    Ok(summary)
+}
+
+/// Watches the root directories of the given collections and re-indexes
+/// changed files automatically. Replaces any previously watched set.
+#[tauri::command]
+async fn watch_collections_cmd(
+    collections: Vec<String>,
+    watcher: State<'_, Mutex<watcher::CollectionWatcher>>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let roots: Vec<(std::path::PathBuf, String)> = {
+        let db_guard = state.db_manager.lock().await;
+        let db = db_guard.as_ref().ok_or("Database not initialized")?;
+        db.get_collections()
+            .await?
+            .into_iter()
+            .filter(|c| collections.contains(&c.name))
+            .filter_map(|c| {
+                let path = std::path::PathBuf::from(c.path?);
+                path.is_dir().then_some((path, c.name))
+            })
+            .collect()
+    };
+
+    let watcher = watcher.lock().await;
+    if roots.is_empty() {
+        watcher.unwatch();
+        return Ok(());
+    }
+    watcher.watch(roots, state.db_manager.clone(), app)
+}
+
+#[tauri::command]
+async fn unwatch_collections_cmd(
+    watcher: State<'_, Mutex<watcher::CollectionWatcher>>,
+) -> Result<(), String> {
+    let watcher = watcher.lock().await;
+    watcher.unwatch();
+    Ok(())
+}
+
+// ===== Export Commands =====
+
+#[tauri::command]
+fn get_export_columns_cmd() -> Vec<String> {
+    export::resource_columns()
+}
+
+#[tauri::command]
+async fn export_tables_cmd(
+    tables: Vec<export::TableSelection>,
+    format: export::ExportFormat,
+    output_path: String,
+    state: State<'_, AppState>,
+) -> Result<export::ExportSummary, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    export::export_tables(&db.pool, &tables, format, &output_path).await
+}
+
+#[tauri::command]
+async fn export_collections_cmd(
+    collections: Vec<String>,
+    columns: Option<Vec<String>>,
+    format: export::ExportFormat,
+    output_path: String,
+    state: State<'_, AppState>,
+) -> Result<export::ExportSummary, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    export::export_collections(
+        &db.pool,
+        &collections,
+        columns.as_deref(),
+        format,
+        &output_path,
+    )
+    .await
+}
+
+/// Where pandoc was found and its version; `None` when it isn't installed
+#[tauri::command]
+async fn detect_pandoc_cmd() -> Result<Option<pandoc::PandocInfo>, String> {
+    tauri::async_runtime::spawn_blocking(pandoc::detect)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Convert a resource or a .tex file with pandoc; pandoc's warnings are
+/// returned with the result
+#[tauri::command]
+async fn pandoc_export_cmd(
+    request: pandoc::PandocRequest,
+    state: State<'_, AppState>,
+) -> Result<pandoc::PandocResult, String> {
+    let source = match (&request.resource_id, &request.path) {
+        (Some(id), _) => {
+            let db_guard = state.db_manager.lock().await;
+            let db = db_guard.as_ref().ok_or("Database not initialized")?;
+            pandoc::resource_source(db, id).await?
+        }
+        (None, Some(path)) => pandoc::file_source(path)?,
+        (None, None) => return Err("Nothing to export: give a resource or a path".to_string()),
+    };
+    tauri::async_runtime::spawn_blocking(move || pandoc::convert(&source, &request))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Zip `main_tex` with the files it needs, ready for arXiv or a journal
+#[tauri::command]
+async fn package_for_submission(
+    main_tex: String,
+    options: Option<submission::SubmissionOptions>,
+) -> Result<submission::SubmissionPackage, String> {
+    let options = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || submission::package(&main_tex, &options))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Pack a collection (files, rows and attachments) into a portable zip
+#[tauri::command]
+async fn export_collection_archive_cmd(
+    collection: String,
+    output_path: String,
+    state: State<'_, AppState>,
+) -> Result<collection_archive::CollectionExportSummary, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    collection_archive::export_collection(db, &collection, &output_path).await
+}
+
+/// Import a collection archive, extracting its files under `target_dir`
+#[tauri::command]
+async fn import_collection_archive_cmd(
+    archive_path: String,
+    target_dir: String,
+    name: Option<String>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<collection_archive::CollectionImportSummary, String> {
+    let summary = {
+        let db_guard = state.db_manager.lock().await;
+        let db = db_guard.as_ref().ok_or("Database not initialized")?;
+        collection_archive::import_collection(db, &archive_path, &target_dir, name).await?
+    };
+    resources::emit_changed(
+        &app,
+        "created",
+        summary.resource_ids.clone(),
+        Some(summary.collection.clone()),
+    );
+    Ok(summary)
+}
+
+/// Convert an SVG to PDF/PNG, or a raster image to PNG/JPEG (optionally
+/// scaled down), so `\includegraphics` can use it
+#[tauri::command]
+async fn convert_image_cmd(
+    request: images::ConvertRequest,
+) -> Result<images::ConvertedImage, String> {
+    tauri::async_runtime::spawn_blocking(move || images::convert(&request))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// ===== Import Commands =====
+
+/// Imports a CSV/JSON file into `table`; validation errors are reported per row
+/// and nothing is inserted unless every row is valid.
+#[tauri::command]
+async fn import_table_data_cmd(
+    file_path: String,
+    table: String,
+    options: Option<import::ImportOptions>,
+    state: State<'_, AppState>,
+) -> Result<import::ImportReport, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    import::import_table_data(&db.pool, &file_path, &table, &options.unwrap_or_default()).await
+}
+
+// ===== Attachment Commands =====
+
+#[tauri::command]
+async fn attach_file_cmd(
+    resource_id: String,
+    source_path: String,
+    kind: Option<String>,
+    description: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<attachments::Attachment, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    attachments::attach(
+        db,
+        &resource_id,
+        &source_path,
+        kind.as_deref().unwrap_or("other"),
+        description.as_deref(),
+    )
+    .await
+}
+
+#[tauri::command]
+async fn list_attachments_cmd(
+    resource_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<attachments::Attachment>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    attachments::list(&db.pool, &resource_id).await
+}
+
+#[tauri::command]
+async fn get_attachment_path_cmd(id: String, state: State<'_, AppState>) -> Result<String, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    attachments::blob_path_of(db, &id).await
+}
+
+#[tauri::command]
+async fn export_attachment_cmd(
+    id: String,
+    target_path: String,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    attachments::export(db, &id, &target_path).await
+}
+
+#[tauri::command]
+async fn delete_attachment_cmd(id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    attachments::delete(db, &id).await
+}
+
+#[tauri::command]
+async fn gc_attachments_cmd(state: State<'_, AppState>) -> Result<attachments::GcReport, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    attachments::collect_garbage(db).await
+}
+
+// ===== Bibliography Entry Commands =====
+
+#[tauri::command]
+async fn search_bib_entries_cmd(
+    query: String,
+    collections: Vec<String>,
+    limit: Option<i64>,
+    state: State<'_, AppState>,
+) -> Result<Vec<bib::BibEntry>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    bib::search_entries(&db.pool, &query, &collections, limit.unwrap_or(100)).await
+}
+
+#[tauri::command]
+async fn get_bib_entries_by_key_cmd(
+    key: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<bib::BibEntry>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    bib::find_by_key(&db.pool, &key).await
+}
+
+#[tauri::command]
+async fn create_bib_entry_cmd(
+    resource_id: String,
+    entry: bib::writer::EntryInput,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<bib::BibEntry, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    let created = bib::create_entry(db, &resource_id, &entry).await?;
+    resources::emit_changed(&app, "updated", vec![resource_id], None);
+    Ok(created)
+}
+
+#[tauri::command]
+async fn update_bib_entry_cmd(
+    resource_id: String,
+    original_key: String,
+    entry: bib::writer::EntryInput,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<bib::BibEntry, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    let updated = bib::update_entry(db, &resource_id, &original_key, &entry).await?;
+    resources::emit_changed(&app, "updated", vec![resource_id], None);
+    Ok(updated)
+}
+
+#[tauri::command]
+async fn delete_bib_entry_cmd(
+    resource_id: String,
+    key: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    bib::delete_entry(db, &resource_id, &key).await?;
+    resources::emit_changed(&app, "updated", vec![resource_id], None);
+    Ok(())
+}
+
+/// Looks up a DOI or arXiv id and converts it to a BibTeX entry with a
+/// project-unique key. With `append_to`, the entry is also added to that
+/// .bib resource unless the work is already in the bibliography.
+#[tauri::command]
+async fn fetch_bib_entry_cmd(
+    doi_or_arxiv_id: String,
+    append_to: Option<String>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<bib::fetch::FetchedEntry, String> {
+    // Fetch before taking the database lock; the request can take a while
+    let mut entry = bib::fetch::fetch(&doi_or_arxiv_id).await?;
+
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    let existing = bib::fetch::deduplicate(&db.pool, &mut entry, &mut Default::default()).await?;
+    let mut fetched = bib::fetch::FetchedEntry::new(&entry, existing);
+
+    if let Some(resource_id) = append_to {
+        if let Some(found) = fetched.existing.first() {
+            return Err(format!(
+                "Already in the bibliography as '{}' ({})",
+                found.citation_key, found.resource_path
+            ));
+        }
+        fetched.appended = Some(bib::create_entry(db, &resource_id, &entry).await?);
+        resources::emit_changed(&app, "updated", vec![resource_id], None);
+    }
+
+    Ok(fetched)
+}
+
+/// Whether Zotero with Better BibTeX is running, and their versions
+#[tauri::command]
+async fn zotero_status_cmd() -> Result<bib::zotero::ZoteroStatus, String> {
+    bib::zotero::status().await
+}
+
+/// The collections of the local Zotero library
+#[tauri::command]
+async fn zotero_collections_cmd() -> Result<Vec<bib::zotero::ZoteroCollection>, String> {
+    bib::zotero::collections().await
+}
+
+/// Which Zotero collections a .bib resource is synced from; `None` for a
+/// bibliography that isn't synced
+#[tauri::command]
+async fn zotero_link_cmd(
+    resource_id: String,
+    state: State<'_, AppState>,
+) -> Result<Option<bib::zotero::ZoteroLink>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    bib::zotero::get_link(&db.pool, &resource_id).await
+}
+
+/// Pull Zotero collections through Better BibTeX into a managed .bib
+/// resource (created on the first sync), keeping the citation keys that
+/// entries already had
+#[tauri::command]
+async fn zotero_sync_cmd(
+    request: bib::zotero::ZoteroSyncRequest,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<bib::zotero::ZoteroSyncResult, String> {
+    let link = {
+        let db_guard = state.db_manager.lock().await;
+        let db = db_guard.as_ref().ok_or("Database not initialized")?;
+        bib::zotero::resolve_link(&db.pool, &request).await?
+    };
+    // Export before taking the database lock again; large libraries take a while
+    let exports = bib::zotero::export(link.library_id, &link.collections, link.format).await?;
+
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    let result = bib::zotero::apply(db, &request, link, &exports).await?;
+    let change = if request.resource_id.is_some() {
+        "updated"
+    } else {
+        "created"
+    };
+    resources::emit_changed(&app, change, vec![result.resource_id.clone()], None);
+    Ok(result)
+}
+
+/// Undefined citations per document and never-cited entries, for the given
+/// collections (all if empty).
+#[tauri::command]
+async fn check_citations_cmd(
+    collections: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<bib::check::CitationReport, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    bib::check::check_citations(db, &collections).await
+}
+
+/// Re-parses the .bib resources of the given collections (all if empty).
+/// Returns the entry count and per-file parse errors.
+#[tauri::command]
+async fn rebuild_bib_entries_cmd(
+    collections: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<(usize, Vec<String>), String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    let collections = if collections.is_empty() {
+        db.get_collections()
+            .await?
+            .into_iter()
+            .map(|c| c.name)
+            .collect()
+    } else {
+        collections
+    };
+    let resources = db.get_resources_by_collections(&collections).await?;
+
+    Ok(bib::rebuild(&db.pool, &resources).await)
+}
+
+// ===== Document Builder Commands =====
+
+/// Assembles the selected exercises into new document resources, one per
+/// requested solution mode (e.g. student and teacher copy), and optionally
+/// compiles them.
+#[tauri::command]
+async fn build_document_cmd(
+    request: document_builder::BuildRequest,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<document_builder::BuildResult, String> {
+    let mut result = {
+        let db_guard = state.db_manager.lock().await;
+        let db = db_guard.as_ref().ok_or("Database not initialized")?;
+        document_builder::build(db, &request).await?
+    };
+    resources::emit_changed(
+        &app,
+        "created",
+        result
+            .documents
+            .iter()
+            .map(|d| d.resource.id.clone())
+            .collect(),
+        Some(request.collection.clone()),
+    );
+
+    // LaTeX runs without holding the database lock
+    if request.options.compile {
+        let default_engine = settings::current().compile.default_engine;
+        let engine = request
+            .options
+            .engine
+            .as_deref()
+            .unwrap_or(default_engine.as_str());
+        let mut outcomes = Vec::new();
+        for document in &mut result.documents {
+            outcomes.push(document_builder::compile_output(document, engine));
+        }
+
+        let db_guard = state.db_manager.lock().await;
+        let db = db_guard.as_ref().ok_or("Database not initialized")?;
+        for (document, outcome) in result.documents.iter().zip(&outcomes) {
+            let log_path = compile_status::log_path(&document.resource.path, "");
+            compile_status::record(&db.pool, &document.resource.id, engine, outcome, &log_path)
+                .await?;
+        }
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+async fn get_solution_environments_cmd(
+    collection: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    document_builder::solution_environments(&db.pool, &collection).await
 }
 
 #[tauri::command]
-async fn import_file_cmd(
-    path: String,
-    collection_name: String,
+async fn set_solution_environments_cmd(
+    collection: String,
+    environments: Vec<String>,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    println!(
-        "import_file_cmd called with path: '{}', collection: '{}'",
-        path, collection_name
-    );
     let db_guard = state.db_manager.lock().await;
     let db = db_guard.as_ref().ok_or("Database not initialized")?;
 
-    // 1. Get Collection Path
-    // Since we don't have get_collection_by_name exposed yet, fetch all and find.
-    let collections = db.get_collections().await?;
-    let target_col = collections
-        .into_iter()
-        .find(|c| c.name == collection_name)
-        .ok_or("Collection not found")?;
-
-    let col_path_str = target_col.path.ok_or("Collection has no physical path")?;
-    let col_path = std::path::Path::new(&col_path_str);
-
-    // 2. Prepare Destination Path
-    let src_path = std::path::Path::new(&path);
-    let file_name = src_path.file_name().ok_or("Invalid source file name")?;
-
-    let mut dest_path = col_path.join(file_name);
+    document_builder::set_solution_environments(&db.pool, &collection, &environments).await
+}
 
-    // 3. Handle Duplicates (Auto-rename)
-    if dest_path.exists() {
-        let file_stem = src_path
-            .file_stem()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-        let extension = src_path
-            .extension()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
+// ===== Collection Preamble Commands =====
 
-        let mut counter = 1;
-        while dest_path.exists() {
-            let new_name = if extension.is_empty() {
-                format!("{}_{}", file_stem, counter)
-            } else {
-                format!("{}_{}.{}", file_stem, counter, extension)
-            };
-            dest_path = col_path.join(new_name);
-            counter += 1;
-        }
-    }
+#[tauri::command]
+async fn get_collection_preamble_cmd(
+    collection: String,
+    state: State<'_, AppState>,
+) -> Result<preamble::CollectionPreamble, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
 
-    // 4. Perform Copy
-    // Check if source and dest are the same (already in folder)
-    if src_path != dest_path {
-        fs::copy(&src_path, &dest_path).map_err(|e| format!("Failed to copy file: {}", e))?;
-    }
+    preamble::get(&db.pool, &collection).await
+}
 
-    // 5. Register NEW path in Database
-    let final_path_str = dest_path.to_string_lossy().to_string();
-    let final_file_name = dest_path
-        .file_name()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_string();
+#[tauri::command]
+async fn save_collection_preamble_cmd(
+    preamble: preamble::CollectionPreamble,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
 
-    // Simple type detection extension - reusing logic could be better but copying for now
-    let kind = if final_file_name.ends_with(".tex") {
-        "file"
-    } else if final_file_name.ends_with(".bib") {
-        "bibliography"
-    } else if final_file_name.ends_with(".sty") {
-        "package"
-    } else if final_file_name.ends_with(".cls") {
-        "class"
-    } else if final_file_name.ends_with(".dtx") {
-        "dtx"
-    } else if final_file_name.ends_with(".ins") {
-        "ins"
-    } else if final_file_name.ends_with(".png")
-        || final_file_name.ends_with(".jpg")
-        || final_file_name.ends_with(".pdf")
-    {
-        "figure"
-    } else {
-        "file"
-    };
+    preamble::save(&db.pool, &preamble).await?;
+    Ok(preamble.render())
+}
 
-    let resource = Resource {
-        id: Uuid::new_v4().to_string(),
-        path: final_path_str,
-        kind: kind.to_string(),
-        collection: collection_name,
-        title: Some(final_file_name),
-        content_hash: None,
-        metadata: Some(serde_json::json!({})),
-        created_at: None,
-        updated_at: None,
-    };
+/// Packages the given resources need, and which of them the preamble of
+/// `collection` is missing.
+#[tauri::command]
+async fn analyze_packages_cmd(
+    resource_ids: Vec<String>,
+    collection: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<preamble::PackageReport, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
 
-    db.add_resource(&resource).await
+    preamble::analyze_packages(db, &resource_ids, collection.as_deref()).await
 }
 
+// ===== Template Commands =====
+
 #[tauri::command]
-fn reveal_path_cmd(path: String) -> Result<(), String> {
-    #[cfg(target_os = "linux")]
-    {
-        std::process::Command::new("xdg-open")
-            .arg(&path)
-            .spawn()
-            .map_err(|e| e.to_string())?;
-    }
-    #[cfg(target_os = "windows")]
-    {
-        std::process::Command::new("explorer")
-            .arg(&path)
-            .spawn()
-            .map_err(|e| e.to_string())?;
-    }
-    #[cfg(target_os = "macos")]
-    {
-        std::process::Command::new("open")
-            .arg("-R")
-            .arg(&path)
-            .spawn()
-            .map_err(|e| e.to_string())?;
-    }
-    Ok(())
+async fn list_templates_cmd(
+    state: State<'_, AppState>,
+) -> Result<Vec<templates::Template>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    templates::list(&db.pool).await
 }
 
 #[tauri::command]
-async fn link_resources_cmd(
-    source_id: String,
-    target_id: String,
-    relation_type: String,
+async fn save_template_cmd(
+    template: templates::Template,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<templates::Template, String> {
     let db_guard = state.db_manager.lock().await;
     let db = db_guard.as_ref().ok_or("Database not initialized")?;
 
-    db.add_dependency(&source_id, &target_id, &relation_type)
-        .await
+    templates::save(&db.pool, &template).await
 }
 
 #[tauri::command]
-async fn get_linked_resources_cmd(
-    source_id: String,
-    relation_type: Option<String>,
-    state: State<'_, AppState>,
-) -> Result<Vec<Resource>, String> {
+async fn delete_template_cmd(id: String, state: State<'_, AppState>) -> Result<(), String> {
     let db_guard = state.db_manager.lock().await;
     let db = db_guard.as_ref().ok_or("Database not initialized")?;
 
-    db.get_dependencies(&source_id, relation_type.as_deref())
-        .await
+    templates::delete(&db.pool, &id).await
 }
 
+/// Creates a new resource at `path` from a template. Placeholders are filled
+/// from `values`, the fields of `source_id` and the defaults (title, author,
+/// date, collection); unknown ones are returned as `unresolved`.
 #[tauri::command]
-async fn get_all_dependencies_cmd(
+async fn create_from_template_cmd(
+    template_id: String,
+    path: String,
+    collection: String,
+    source_id: Option<String>,
+    values: Option<std::collections::HashMap<String, String>>,
+    app: tauri::AppHandle,
     state: State<'_, AppState>,
-) -> Result<Vec<(String, String, String)>, String> {
+) -> Result<templates::CreatedDocument, String> {
     let db_guard = state.db_manager.lock().await;
     let db = db_guard.as_ref().ok_or("Database not initialized")?;
 
-    db.get_all_dependencies().await
+    let created = templates::create_document(
+        db,
+        &template_id,
+        &path,
+        &collection,
+        source_id.as_deref(),
+        values.unwrap_or_default(),
+    )
+    .await?;
+    resources::emit_changed(
+        &app,
+        "created",
+        vec![created.resource.id.clone()],
+        Some(collection),
+    );
+    Ok(created)
 }
 
-// ===== Search Command =====
+// ===== Label / Reference Commands =====
 
+/// Duplicate labels, undefined references and unreferenced labels of a
+/// document and the files it inputs or includes.
 #[tauri::command]
-async fn search_database_files(
-    query: String,
-    case_sensitive: bool,
-    use_regex: bool,
-    file_types: Vec<String>,
-    collections: Vec<String>,
-    max_results: usize,
+async fn check_labels_cmd(
+    resource_id: String,
     state: State<'_, AppState>,
-) -> Result<search::SearchResult, String> {
+) -> Result<labels::LabelReport, String> {
     let db_guard = state.db_manager.lock().await;
     let db = db_guard.as_ref().ok_or("Database not initialized")?;
 
-    // Get resources from the specified collections
-    let resources = if collections.is_empty() {
-        // If no collections specified, search all
-        let all_collections = db.get_collections().await?;
-        let collection_names: Vec<String> =
-            all_collections.iter().map(|c| c.name.clone()).collect();
-        db.get_resources_by_collections(&collection_names).await?
-    } else {
-        db.get_resources_by_collections(&collections).await?
-    };
+    labels::check_document(db, &resource_id).await
+}
+
+/// Sections, theorem-like environments and labeled equations of a document
+/// and the files it inputs, with estimated numbers
+#[tauri::command]
+async fn get_document_outline(path: String) -> Result<outline::DocumentOutline, String> {
+    tauri::async_runtime::spawn_blocking(move || outline::document_outline(&path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Documentation of a package or class: installed PDFs found by texdoc, or
+/// links from CTAN when texdoc has none
+#[tauri::command]
+async fn lookup_package_docs(name: String) -> Result<package_docs::PackageDocs, String> {
+    package_docs::lookup(&name).await
+}
+
+// ===== Spell Checking Commands =====
+
+/// Custom dictionary key: the open project, or "" outside a project
+fn spelling_project(state: &AppState) -> Result<String, String> {
+    let root = state.project_root.read().map_err(|e| e.to_string())?;
+    Ok(root
+        .as_ref()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default())
+}
 
-    // Build search query
-    let search_query = search::SearchQuery {
-        text: query,
-        case_sensitive,
-        use_regex,
-        file_types,
-        max_results,
+/// Misspelled words of `text`, with positions relative to it
+async fn spellcheck(
+    text: &str,
+    language: Option<String>,
+    state: &AppState,
+) -> Result<Vec<spellcheck::Misspelling>, String> {
+    let language = language.unwrap_or_else(|| settings::current().spelling.language);
+    let dictionary = spellcheck::dictionary(&language).await?;
+    let project = spelling_project(state)?;
+    let custom = {
+        let db_guard = state.db_manager.lock().await;
+        match db_guard.as_ref() {
+            Some(db) => spellcheck::custom_words(&db.pool, &project).await?,
+            None => Default::default(),
+        }
     };
+    Ok(spellcheck::check_text(&dictionary, &custom, text))
+}
 
-    // Perform search
-    search::search_in_files(&search_query, resources)
+#[tauri::command]
+async fn spellcheck_document(
+    path: String,
+    language: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<spellcheck::Misspelling>, String> {
+    let text = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    spellcheck(&text, language, &state).await
 }
 
+/// Check a range of the editor content; `first_line` (1-based) is the line
+/// the range starts on, so positions come back in document coordinates
 #[tauri::command]
-async fn replace_database_files(
-    query: String,
-    replace_with: String,
-    case_sensitive: bool,
-    use_regex: bool,
-    file_types: Vec<String>,
-    collections: Vec<String>,
+async fn spellcheck_text(
+    text: String,
+    first_line: Option<u32>,
+    language: Option<String>,
     state: State<'_, AppState>,
-) -> Result<search::ReplaceResult, String> {
-    let db_guard = state.db_manager.lock().await;
-    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+) -> Result<Vec<spellcheck::Misspelling>, String> {
+    let offset = first_line.unwrap_or(1).saturating_sub(1);
+    let mut misspelled = spellcheck(&text, language, &state).await?;
+    for m in &mut misspelled {
+        m.line += offset;
+    }
+    Ok(misspelled)
+}
 
-    // Get resources from the specified collections
-    let resources = if collections.is_empty() {
-        // If no collections specified, search all
-        let all_collections = db.get_collections().await?;
-        let collection_names: Vec<String> =
-            all_collections.iter().map(|c| c.name.clone()).collect();
-        db.get_resources_by_collections(&collection_names).await?
-    } else {
-        db.get_resources_by_collections(&collections).await?
-    };
+#[tauri::command]
+async fn spelling_suggestions(
+    word: String,
+    language: Option<String>,
+) -> Result<Vec<String>, String> {
+    let language = language.unwrap_or_else(|| settings::current().spelling.language);
+    let dictionary = spellcheck::dictionary(&language).await?;
+    tauri::async_runtime::spawn_blocking(move || spellcheck::suggest(&dictionary, &word))
+        .await
+        .map_err(|e| e.to_string())
+}
 
-    let replace_query = search::ReplaceQuery {
-        search: search::SearchQuery {
-            text: query,
-            case_sensitive,
-            use_regex,
-            file_types,
-            max_results: usize::MAX, // Replace typically processes all matches
-        },
-        replace_with,
-    };
+#[tauri::command]
+fn list_spell_languages() -> Result<Vec<spellcheck::SpellLanguage>, String> {
+    spellcheck::languages()
+}
 
-    search::replace_in_files(&replace_query, resources)
+#[tauri::command]
+async fn add_dictionary_word(word: String, state: State<'_, AppState>) -> Result<(), String> {
+    let project = spelling_project(&state)?;
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    spellcheck::add_word(&db.pool, &project, &word).await
 }
 
-// ===== LSP Commands =====
+#[tauri::command]
+async fn remove_dictionary_word(word: String, state: State<'_, AppState>) -> Result<(), String> {
+    let project = spelling_project(&state)?;
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    spellcheck::remove_word(&db.pool, &project, &word).await
+}
 
 #[tauri::command]
-async fn lsp_initialize(root_uri: String, state: State<'_, AppState>) -> Result<(), String> {
-    let mut lsp_guard = state.lsp_manager.lock().await;
+async fn list_dictionary_words(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let project = spelling_project(&state)?;
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    let mut words: Vec<String> = spellcheck::custom_words(&db.pool, &project)
+        .await?
+        .into_iter()
+        .collect();
+    words.sort();
+    Ok(words)
+}
 
-    if lsp_guard.is_none() {
-        let mut manager = TexlabManager::new();
-        manager.start().await?;
+// ===== LSP Commands =====
 
-        let params = serde_json::json!({
-            "processId": std::process::id(),
-            "rootUri": root_uri,
-            "capabilities": {
-                "textDocument": {
-                    "completion": {
-                        "completionItem": {
-                            "snippetSupport": true,
-                            "documentationFormat": ["markdown", "plaintext"]
-                        }
-                    },
-                    "hover": {
-                        "contentFormat": ["markdown", "plaintext"]
-                    },
-                    "definition": {
-                        "linkSupport": true
+/// Start texlab and initialize it on the workspace `root_uri`
+async fn start_texlab(root_uri: &str) -> Result<TexlabManager, String> {
+    let mut manager = TexlabManager::new();
+    manager.start().await?;
+
+    let params = serde_json::json!({
+        "processId": std::process::id(),
+        "rootUri": root_uri,
+        "capabilities": {
+            "textDocument": {
+                "completion": {
+                    "completionItem": {
+                        "snippetSupport": true,
+                        "documentationFormat": ["markdown", "plaintext"]
                     }
+                },
+                "hover": {
+                    "contentFormat": ["markdown", "plaintext"]
+                },
+                "definition": {
+                    "linkSupport": true
                 }
             }
-        });
-
-        manager.send_request("initialize", params).await?;
-
-        manager
-            .send_notification("initialized", serde_json::json!({}))
-            .await?;
-
-        let config = serde_json::json!({
-            "settings": {
-                "texlab": {
-                    "completion": {
-                        "matcher": "fuzzy-ignore-case"
-                    },
-                    "build": {
-                        "onSave": false
-                    }
+        }
+    });
+
+    manager.send_request("initialize", params).await?;
+
+    manager
+        .send_notification("initialized", serde_json::json!({}))
+        .await?;
+
+    let config = serde_json::json!({
+        "settings": {
+            "texlab": {
+                "completion": {
+                    "matcher": "fuzzy-ignore-case"
+                },
+                "build": {
+                    "onSave": false
                 }
             }
-        });
-        manager
-            .send_notification("workspace/didChangeConfiguration", config)
-            .await?;
+        }
+    });
+    manager
+        .send_notification("workspace/didChangeConfiguration", config)
+        .await?;
 
-        *lsp_guard = Some(manager);
-        Ok(())
-    } else {
-        Ok(())
+    Ok(manager)
+}
+
+#[tauri::command]
+async fn lsp_initialize(root_uri: String, state: State<'_, AppState>) -> Result<(), String> {
+    let mut lsp_guard = state.lsp_manager.lock().await;
+
+    if lsp_guard.is_none() {
+        *lsp_guard = Some(start_texlab(&root_uri).await?);
     }
+    Ok(())
 }
 
 #[tauri::command]
@@ -865,6 +2310,9 @@ fn parse_log_cmd(content: String) -> Vec<log_parser::LogEntry> {
 #[tauri::command]
 async fn get_file_tree_cmd(
     collections: Vec<String>,
+    filter: Option<tree_builder::TreeFilter>,
+    sort: Option<tree_builder::TreeSort>,
+    flatten: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<Vec<tree_builder::TreeNode>, String> {
     let db_guard = state.db_manager.lock().await;
@@ -885,7 +2333,43 @@ async fn get_file_tree_cmd(
         all_resources.extend(resources);
     }
 
-    Ok(tree_builder::build_file_tree(all_resources, &roots))
+    let mut tree = tree_builder::build_file_tree(
+        all_resources,
+        &roots,
+        &filter.unwrap_or_default(),
+        &sort.unwrap_or_default(),
+        flatten.unwrap_or(true),
+    );
+    compile_status::attach_to_tree(&db.pool, &mut tree).await?;
+    tree_builder::attach_file_info(&mut tree);
+    git::attach_status_to_tree(&mut tree, &state.status_cache);
+    tree_builder::attach_folder_totals(&mut tree);
+    Ok(tree)
+}
+
+/// Collections as folders grouped by metadata (tag, difficulty, ...) in the
+/// order of `levels` instead of their folders on disk
+#[tauri::command]
+async fn get_grouped_tree_cmd(
+    collections: Vec<String>,
+    levels: Vec<tree_groups::GroupField>,
+    state: State<'_, AppState>,
+) -> Result<Vec<tree_builder::TreeNode>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    let roots: std::collections::HashMap<String, String> = db
+        .get_collections()
+        .await?
+        .into_iter()
+        .filter_map(|c| Some((c.name, c.path?)))
+        .collect();
+    let resources = db.get_resources_by_collections(&collections).await?;
+    let values = tree_groups::load_values(&db.pool, &levels).await?;
+
+    Ok(tree_groups::build_grouped_tree(
+        &resources, &levels, &values, &roots,
+    ))
 }
 
 #[tauri::command]
@@ -1903,6 +3387,20 @@ async fn save_typed_metadata_cmd(
     let db_guard = state.db_manager.lock().await;
     let manager = db_guard.as_ref().ok_or("Database not initialized")?;
 
+    let saved = save_typed_metadata(manager, resource_id, resource_type, metadata).await;
+    // Tags show up in quick open and the graph; some may be written even
+    // when saving fails
+    state.quick_open_cache.invalidate();
+    state.graph_cache.invalidate();
+    saved
+}
+
+async fn save_typed_metadata(
+    manager: &DatabaseManager,
+    resource_id: String,
+    resource_type: String,
+    metadata: serde_json::Value,
+) -> Result<(), String> {
     match resource_type.as_str() {
         "file" => {
             // Parse metadata
@@ -3868,10 +5366,17 @@ async fn lsp_shutdown(state: State<'_, AppState>) -> Result<(), String> {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Shared by the status command and the watcher that keeps it fresh
+    let status_cache: Arc<git::StatusCache> = Default::default();
     tauri::Builder::default()
         .manage(AppState {
             db_manager: std::sync::Arc::new(tokio::sync::Mutex::new(None)),
             lsp_manager: std::sync::Arc::new(tokio::sync::Mutex::new(None)),
+            blame_cache: Default::default(),
+            status_cache: status_cache.clone(),
+            graph_cache: Default::default(),
+            quick_open_cache: Default::default(),
+            project_root: Default::default(),
         })
         .setup(|app| {
             let proj_dirs = ProjectDirs::from("", "", "datatex");
@@ -3912,7 +5417,12 @@ pub fn run() {
                         let state = app_handle.state::<AppState>();
                         let mut db_guard = state.db_manager.lock().await;
                         *db_guard = Some(manager);
+                        drop(db_guard);
                         println!("Global database initialized successfully.");
+                        if let Err(e) = http_api::sync(&app_handle, &settings::current().api).await
+                        {
+                            eprintln!("HTTP API server: {}", e);
+                        }
                     }
                     Err(e) => {
                         eprintln!("Failed to initialize global database: {}", e);
@@ -3926,20 +5436,37 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_clipboard_manager::init())
-        .manage(Mutex::new(watcher::GitWatcher::new()))
+        .manage(Mutex::new(watcher::GitWatcher::new(status_cache)))
+        .manage(Mutex::new(watcher::CollectionWatcher::new()))
+        .manage(git_credentials::GitCredentialBroker::default())
+        .manage(http_api::ApiServer::default())
         .invoke_handler(tauri::generate_handler![
             git_watch_repo_cmd,
             git_unwatch_repo_cmd,
             git_read_gitignore_cmd,
             git_write_gitignore_cmd,
+            git_generate_latex_gitignore_cmd,
             open_project,
+            get_active_project,
+            add_recent_project,
+            list_recent_projects,
+            pin_recent_project,
+            remove_recent_project,
             get_db_path,
+            get_settings,
+            update_settings,
+            api_server_status_cmd,
+            regenerate_api_token_cmd,
             compile_tex,
             run_synctex_command,
             run_texcount_command,
             compile_resource_cmd,
+            get_compile_statuses_cmd,
             get_system_fonts,
             get_table_data_cmd,
+            get_database_tables_cmd,
+            db_health_cmd,
+            get_change_history_cmd,
             update_cell_cmd,
             vectors::store_embeddings,
             vectors::search_similar,
@@ -3955,12 +5482,26 @@ pub fn run() {
             import_folder_cmd,
             delete_collection_cmd,
             delete_resource_cmd,
+            list_deleted_resources_cmd,
+            save_session_cmd,
+            get_last_session_cmd,
+            autosave_document,
+            list_recoverable_drafts,
+            recover_draft,
+            discard_drafts,
+            restore_deleted_resource_cmd,
+            purge_deleted_resources_cmd,
             create_resource_cmd,
+            rename_resource_cmd,
+            move_resource_cmd,
+            duplicate_resource_cmd,
             create_folder_cmd,
             import_file_cmd,
             reveal_path_cmd,
             link_resources_cmd,
             get_linked_resources_cmd,
+            find_orphan_resources_cmd,
+            find_broken_links_cmd,
             get_all_dependencies_cmd,
             // LSP Commands
             lsp_initialize,
@@ -3972,6 +5513,7 @@ pub fn run() {
             lsp_shutdown,
             parse_log_cmd,
             get_file_tree_cmd,
+            get_grouped_tree_cmd,
             // Typed Metadata Lookup Commands (sqlx-based)
             get_fields_cmd,
             get_chapters_cmd,
@@ -4031,6 +5573,12 @@ pub fn run() {
             create_macro_command_type_cmd,
             // Graph Processing
             graph_processor::get_graph_data_cmd,
+            graph_processor::get_graph_delta_cmd,
+            graph_processor::get_graph_at_commit_cmd,
+            graph_processor::get_node_details_cmd,
+            graph_processor::get_node_neighborhood_cmd,
+            graph_processor::get_dependency_path_cmd,
+            graph_processor::get_impacted_documents_cmd,
             // CTAN Commands
             commands::ctan::get_packages,
             commands::ctan::get_all_topics,
@@ -4042,6 +5590,80 @@ pub fn run() {
             delete_preamble_type_cmd,
             search_database_files,
             replace_database_files,
+            preview_replace_cmd,
+            apply_replace_cmd,
+            convert_to_utf8_cmd,
+            scan_characters_cmd,
+            normalize_characters_cmd,
+            search_metadata_cmd,
+            quick_open,
+            record_resource_open,
+            rebuild_metadata_index_cmd,
+            scan_collection_cmd,
+            watch_collections_cmd,
+            unwatch_collections_cmd,
+            // Export
+            get_export_columns_cmd,
+            export_tables_cmd,
+            export_collections_cmd,
+            detect_pandoc_cmd,
+            pandoc_export_cmd,
+            package_for_submission,
+            export_collection_archive_cmd,
+            import_collection_archive_cmd,
+            convert_image_cmd,
+            // Import
+            import_table_data_cmd,
+            // Attachments
+            attach_file_cmd,
+            list_attachments_cmd,
+            get_attachment_path_cmd,
+            export_attachment_cmd,
+            delete_attachment_cmd,
+            gc_attachments_cmd,
+            // Bibliography entries
+            search_bib_entries_cmd,
+            get_bib_entries_by_key_cmd,
+            create_bib_entry_cmd,
+            update_bib_entry_cmd,
+            delete_bib_entry_cmd,
+            fetch_bib_entry_cmd,
+            zotero_status_cmd,
+            zotero_collections_cmd,
+            zotero_link_cmd,
+            zotero_sync_cmd,
+            check_citations_cmd,
+            rebuild_bib_entries_cmd,
+            // Labels / References
+            check_labels_cmd,
+            get_document_outline,
+            lookup_package_docs,
+            spellcheck_document,
+            spellcheck_text,
+            spelling_suggestions,
+            list_spell_languages,
+            add_dictionary_word,
+            remove_dictionary_word,
+            list_dictionary_words,
+            // Document Builder
+            build_document_cmd,
+            get_solution_environments_cmd,
+            set_solution_environments_cmd,
+            // Collection Preambles
+            get_collection_preamble_cmd,
+            save_collection_preamble_cmd,
+            analyze_packages_cmd,
+            // Templates
+            list_templates_cmd,
+            save_template_cmd,
+            delete_template_cmd,
+            create_from_template_cmd,
+            // Custom Metadata Fields
+            get_custom_fields_cmd,
+            define_custom_field_cmd,
+            delete_custom_field_cmd,
+            get_custom_field_values_cmd,
+            set_custom_field_value_cmd,
             // Local History Commands
             save_history_snapshot_cmd,
             get_file_history_cmd,
@@ -4059,23 +5681,40 @@ pub fn run() {
             git_unstage_file_cmd,
             git_commit_cmd,
             git_log_cmd,
+            git_get_commit_template_cmd,
+            git_get_commit_rules_cmd,
+            git_set_commit_rules_cmd,
+            git_get_snapshot_settings_cmd,
+            git_set_snapshot_settings_cmd,
+            git_validate_commit_message_cmd,
+            git_get_signing_config_cmd,
+            git_set_signing_enabled_cmd,
             git_file_diff_cmd,
             git_file_at_commit_cmd,
+            git_list_tree_at_commit_cmd,
+            git_repo_stats_cmd,
             git_discard_changes_cmd,
             git_init_repo_cmd,
+            git_file_log_cmd,
+            git_commit_info_cmd,
             git_get_structured_diff_cmd,
             git_get_head_content_cmd,
             git_list_branches_cmd,
             git_create_branch_cmd,
+            git_create_branch_at_cmd,
             git_switch_branch_cmd,
+            git_checkout_remote_branch_cmd,
             git_delete_branch_cmd,
             git_list_remotes_cmd,
             git_fetch_remote_cmd,
+            git_fetch_all_cmd,
             git_push_remote_cmd,
             git_pull_remote_cmd,
+            git_provide_credentials_cmd,
             // Stash Commands
             git_list_stashes_cmd,
             git_create_stash_cmd,
+            git_get_stash_diff_cmd,
             git_apply_stash_cmd,
             git_drop_stash_cmd,
             git_pop_stash_cmd,
@@ -4084,16 +5723,27 @@ pub fn run() {
             git_commit_amend_cmd,
             // Checkout & Cherry-pick
             git_checkout_commit_cmd,
+            git_reset_to_commit_cmd,
+            git_get_reflog_cmd,
+            git_list_worktrees_cmd,
+            git_add_worktree_cmd,
+            git_remove_worktree_cmd,
+            git_archive_commit_cmd,
             git_cherry_pick_cmd,
             // Blame, Tags, Revert
             git_blame_cmd,
+            git_blame_parent_cmd,
             git_list_tags_cmd,
             git_create_tag_cmd,
             git_delete_tag_cmd,
+            git_push_tag_cmd,
+            git_delete_remote_tag_cmd,
+            git_checkout_tag_cmd,
             git_revert_commit_cmd,
             // Conflict Detection & Side-by-side Diff
             git_has_conflicts_cmd,
             git_get_conflict_files_cmd,
+            git_get_conflict_merge_cmd,
             git_get_blob_content_cmd,
             git_mark_conflict_resolved_cmd,
             git_get_side_by_side_diff_cmd,
@@ -4101,6 +5751,14 @@ pub fn run() {
             git_merge_branch_cmd,
             git_rename_branch_cmd,
             git_rebase_branch_cmd,
+            git_rebase_todo_cmd,
+            git_rebase_interactive_cmd,
+            git_rebase_continue_cmd,
+            git_rebase_abort_cmd,
+            git_rebase_in_progress_cmd,
+            git_get_repo_operation_state_cmd,
+            git_continue_operation_cmd,
+            git_abort_operation_cmd,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -4191,6 +5849,71 @@ async fn delete_preamble_type_cmd(state: State<'_, AppState>, id: String) -> Res
     Ok(())
 }
 
+// ============================================================================
+// Custom Metadata Fields
+// ============================================================================
+
+#[tauri::command]
+async fn get_custom_fields_cmd(
+    collection: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<custom_fields::FieldDefinition>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let manager = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    custom_fields::list_definitions(&manager.pool, &collection).await
+}
+
+#[tauri::command]
+async fn define_custom_field_cmd(
+    definition: custom_fields::FieldDefinitionInput,
+    state: State<'_, AppState>,
+) -> Result<custom_fields::FieldDefinition, String> {
+    let db_guard = state.db_manager.lock().await;
+    let manager = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    let defined = custom_fields::define_field(&manager.pool, &definition).await?;
+    // The graph's metadata layer shows custom field values
+    state.graph_cache.invalidate();
+    Ok(defined)
+}
+
+#[tauri::command]
+async fn delete_custom_field_cmd(id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let db_guard = state.db_manager.lock().await;
+    let manager = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    custom_fields::delete_definition(&manager.pool, &id).await?;
+    state.graph_cache.invalidate();
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_custom_field_values_cmd(
+    resource_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<custom_fields::FieldValue>, String> {
+    let db_guard = state.db_manager.lock().await;
+    let manager = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    custom_fields::get_values(&manager.pool, &resource_id).await
+}
+
+#[tauri::command]
+async fn set_custom_field_value_cmd(
+    resource_id: String,
+    field_id: String,
+    value: serde_json::Value,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let db_guard = state.db_manager.lock().await;
+    let manager = db_guard.as_ref().ok_or("Database not initialized")?;
+
+    custom_fields::set_value(&manager.pool, &resource_id, &field_id, &value).await?;
+    state.graph_cache.invalidate();
+    Ok(())
+}
+
 // ============================================================================
 // Local History Commands
 // ============================================================================
@@ -4301,8 +6024,11 @@ fn git_detect_repo_cmd(path: String) -> Result<Option<git::GitRepoInfo>, String>
 }
 
 #[tauri::command]
-fn git_status_cmd(repo_path: String) -> Result<Vec<git::GitFileStatus>, String> {
-    git::get_status(&repo_path)
+fn git_status_cmd(
+    repo_path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<git::GitFileStatus>, String> {
+    state.status_cache.get(&repo_path)
 }
 
 #[tauri::command]
@@ -4321,8 +6047,15 @@ fn git_unstage_file_cmd(repo_path: String, file_path: String) -> Result<(), Stri
 }
 
 #[tauri::command]
-fn git_commit_cmd(repo_path: String, message: String) -> Result<String, String> {
-    git::commit(&repo_path, &message)
+fn git_commit_cmd(
+    repo_path: String,
+    message: String,
+    sign: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let id = git::commit(&repo_path, &message, sign)?;
+    state.blame_cache.invalidate(&repo_path);
+    Ok(id)
 }
 
 #[tauri::command]
@@ -4330,9 +6063,66 @@ fn git_log_cmd(
     repo_path: String,
     limit: Option<i32>,
     all: Option<bool>,
+    verify_signatures: Option<bool>,
 ) -> Result<Vec<git::GitCommitInfo>, String> {
     let all = all.unwrap_or(false);
-    git::get_log(&repo_path, limit, all)
+    git::get_log(
+        &repo_path,
+        limit,
+        all,
+        verify_signatures.unwrap_or_default(),
+    )
+}
+
+#[tauri::command]
+fn git_get_commit_template_cmd(repo_path: String) -> Result<Option<String>, String> {
+    git_message::get_commit_template(&repo_path)
+}
+
+#[tauri::command]
+fn git_get_commit_rules_cmd(repo_path: String) -> Result<git_message::CommitMessageRules, String> {
+    git_message::get_rules(&repo_path)
+}
+
+#[tauri::command]
+fn git_set_commit_rules_cmd(
+    repo_path: String,
+    rules: git_message::CommitMessageRules,
+) -> Result<(), String> {
+    git_message::set_rules(&repo_path, &rules)
+}
+
+#[tauri::command]
+fn git_get_snapshot_settings_cmd(
+    repo_path: String,
+) -> Result<git_snapshots::SnapshotSettings, String> {
+    git_snapshots::get_settings(&repo_path)
+}
+
+#[tauri::command]
+fn git_set_snapshot_settings_cmd(
+    repo_path: String,
+    settings: git_snapshots::SnapshotSettings,
+) -> Result<(), String> {
+    git_snapshots::set_settings(&repo_path, &settings)
+}
+
+#[tauri::command]
+fn git_validate_commit_message_cmd(
+    repo_path: String,
+    message: String,
+) -> Result<Vec<git_message::CommitMessageIssue>, String> {
+    git_message::validate_message(&repo_path, &message)
+}
+
+#[tauri::command]
+fn git_get_signing_config_cmd(repo_path: String) -> Result<git_signing::SigningConfig, String> {
+    git_signing::get_signing_config(&repo_path)
+}
+
+#[tauri::command]
+fn git_set_signing_enabled_cmd(repo_path: String, enabled: bool) -> Result<(), String> {
+    git_signing::set_signing_enabled(&repo_path, enabled)
 }
 
 #[tauri::command]
@@ -4349,6 +6139,27 @@ fn git_file_at_commit_cmd(
     git::get_file_at_commit(&repo_path, &commit_id, &file_path)
 }
 
+#[tauri::command]
+fn git_list_tree_at_commit_cmd(
+    repo_path: String,
+    commit_id: String,
+    path: String,
+) -> Result<Vec<git::CommitTreeEntry>, String> {
+    git::list_tree_at_commit(&repo_path, &commit_id, &path)
+}
+
+#[tauri::command]
+async fn git_repo_stats_cmd(
+    repo_path: String,
+    settings: Option<git::RepoStatsSettings>,
+) -> Result<git::RepoStats, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::get_repo_stats(&repo_path, &settings.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 #[tauri::command]
 fn git_discard_changes_cmd(repo_path: String, file_path: String) -> Result<(), String> {
     git::discard_changes(&repo_path, &file_path)
@@ -4356,15 +6167,30 @@ fn git_discard_changes_cmd(repo_path: String, file_path: String) -> Result<(), S
 
 #[tauri::command]
 fn git_init_repo_cmd(path: String) -> Result<git::GitRepoInfo, String> {
-    git::init_repo(&path)
+    git::init_repo(&path, &settings::current().git.default_branch)
+}
+
+#[tauri::command]
+fn git_file_log_cmd(
+    repo_path: String,
+    file_path: String,
+    limit: Option<i32>,
+) -> Result<Vec<git::GitCommitInfo>, String> {
+    git::get_file_log(&repo_path, &file_path, limit)
+}
+
+#[tauri::command]
+fn git_commit_info_cmd(repo_path: String, commit_id: String) -> Result<git::CommitDetails, String> {
+    git::get_commit_info(&repo_path, &commit_id)
 }
 
 #[tauri::command]
 fn git_get_structured_diff_cmd(
     repo_path: String,
     file_path: String,
+    mode: Option<git::DiffMode>,
 ) -> Result<git::StructuredDiff, String> {
-    git::get_structured_diff(&repo_path, &file_path)
+    git::get_structured_diff(&repo_path, &file_path, mode.unwrap_or_default())
 }
 
 #[tauri::command]
@@ -4383,8 +6209,36 @@ fn git_create_branch_cmd(repo_path: String, name: String) -> Result<(), String>
 }
 
 #[tauri::command]
-fn git_switch_branch_cmd(repo_path: String, name: String) -> Result<(), String> {
-    git::switch_branch(&repo_path, &name)
+fn git_create_branch_at_cmd(
+    repo_path: String,
+    commit_id: String,
+    name: String,
+) -> Result<(), String> {
+    git::create_branch_at(&repo_path, &commit_id, &name)
+}
+
+#[tauri::command]
+fn git_switch_branch_cmd(
+    repo_path: String,
+    name: String,
+    stash_changes: Option<bool>,
+) -> Result<git::SwitchBranchResult, String> {
+    git::switch_branch(&repo_path, &name, stash_changes.unwrap_or(false))
+}
+
+#[tauri::command]
+fn git_checkout_remote_branch_cmd(
+    repo_path: String,
+    remote_branch: String,
+    local_name: Option<String>,
+    stash_changes: Option<bool>,
+) -> Result<git::SwitchBranchResult, String> {
+    git::checkout_remote_branch(
+        &repo_path,
+        &remote_branch,
+        local_name.as_deref(),
+        stash_changes.unwrap_or(false),
+    )
 }
 
 #[tauri::command]
@@ -4411,24 +6265,160 @@ fn git_rebase_branch_cmd(repo_path: String, upstream_branch: String) -> Result<(
     git::rebase_branch(&repo_path, &upstream_branch)
 }
 
+#[tauri::command]
+fn git_rebase_todo_cmd(
+    repo_path: String,
+    upstream: String,
+) -> Result<Vec<git::GitCommitInfo>, String> {
+    git_rebase::get_todo(&repo_path, &upstream)
+}
+
+/// Emits `git-rebase-progress` before each step
+fn rebase_progress(app: &tauri::AppHandle) -> impl Fn(&git_rebase::RebaseProgress) + '_ {
+    move |progress| {
+        let _ = tauri::Emitter::emit(app, "git-rebase-progress", progress);
+    }
+}
+
+#[tauri::command]
+async fn git_rebase_interactive_cmd(
+    repo_path: String,
+    upstream: String,
+    plan: Vec<git_rebase::RebaseStep>,
+    app: tauri::AppHandle,
+) -> Result<git_rebase::RebaseStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git_rebase::start(&repo_path, &upstream, plan, &rebase_progress(&app))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn git_rebase_continue_cmd(
+    repo_path: String,
+    app: tauri::AppHandle,
+) -> Result<git_rebase::RebaseStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git_rebase::continue_rebase(&repo_path, &rebase_progress(&app))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn git_rebase_abort_cmd(repo_path: String) -> Result<(), String> {
+    git_rebase::abort(&repo_path)
+}
+
+#[tauri::command]
+fn git_rebase_in_progress_cmd(repo_path: String) -> Result<bool, String> {
+    git_rebase::is_in_progress(&repo_path)
+}
+
+#[tauri::command]
+fn git_get_repo_operation_state_cmd(repo_path: String) -> Result<git::RepoOperationState, String> {
+    git::get_repo_operation_state(&repo_path)
+}
+
+#[tauri::command]
+async fn git_continue_operation_cmd(
+    repo_path: String,
+    app: tauri::AppHandle,
+) -> Result<git::RepoOperationState, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::continue_operation(&repo_path, &rebase_progress(&app))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn git_abort_operation_cmd(repo_path: String) -> Result<(), String> {
+    git::abort_operation(&repo_path)
+}
+
 #[tauri::command]
 fn git_list_remotes_cmd(repo_path: String) -> Result<Vec<git::RemoteInfo>, String> {
     git::list_remotes(&repo_path)
 }
 
 #[tauri::command]
-fn git_fetch_remote_cmd(repo_path: String, remote: String) -> Result<(), String> {
-    git::fetch_remote(&repo_path, &remote)
+async fn git_fetch_remote_cmd(
+    repo_path: String,
+    remote: String,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    git_credentials::run_with_prompt(app, move |prompt| {
+        git::fetch_remote(&repo_path, &remote, Some(prompt))
+    })
+    .await
+}
+
+/// Emits `git-fetch-progress` while objects are received
+#[tauri::command]
+async fn git_fetch_all_cmd(
+    repo_path: String,
+    prune: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<git::FetchSummary, String> {
+    let emitter = app.clone();
+    git_credentials::run_with_prompt(app, move |prompt| {
+        git::fetch_all(
+            &repo_path,
+            prune.unwrap_or(false),
+            Some(prompt),
+            &|progress| {
+                let _ = tauri::Emitter::emit(&emitter, "git-fetch-progress", progress);
+            },
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+async fn git_push_remote_cmd(
+    repo_path: String,
+    remote: String,
+    branch: String,
+    settings: Option<git::PushSettings>,
+    app: tauri::AppHandle,
+) -> Result<git::PushResult, String> {
+    let settings = settings.unwrap_or_default();
+    git_credentials::run_with_prompt(app, move |prompt| {
+        git::push_to_remote(&repo_path, &remote, &branch, &settings, Some(prompt))
+    })
+    .await
 }
 
 #[tauri::command]
-fn git_push_remote_cmd(repo_path: String, remote: String, branch: String) -> Result<(), String> {
-    git::push_to_remote(&repo_path, &remote, &branch)
+async fn git_pull_remote_cmd(
+    repo_path: String,
+    remote: String,
+    branch: String,
+    strategy: Option<git::PullStrategy>,
+    app: tauri::AppHandle,
+) -> Result<git::PullResult, String> {
+    git_credentials::run_with_prompt(app, move |prompt| {
+        git::pull_from_remote(
+            &repo_path,
+            &remote,
+            &branch,
+            strategy.unwrap_or_default(),
+            Some(prompt),
+        )
+    })
+    .await
 }
 
+/// Answer a `git-credentials-required` prompt; `response` None cancels it
 #[tauri::command]
-fn git_pull_remote_cmd(repo_path: String, remote: String, branch: String) -> Result<(), String> {
-    git::pull_from_remote(&repo_path, &remote, &branch)
+fn git_provide_credentials_cmd(
+    request_id: u64,
+    response: Option<git::CredentialResponse>,
+    broker: State<'_, git_credentials::GitCredentialBroker>,
+) -> Result<(), String> {
+    broker.respond(request_id, response)
 }
 
 // ============================================================================
@@ -4441,8 +6431,25 @@ fn git_list_stashes_cmd(repo_path: String) -> Result<Vec<git::StashInfo>, String
 }
 
 #[tauri::command]
-fn git_create_stash_cmd(repo_path: String, message: Option<String>) -> Result<String, String> {
-    git::create_stash(&repo_path, message.as_deref()).map(|oid| oid.to_string())
+fn git_create_stash_cmd(
+    repo_path: String,
+    message: Option<String>,
+    settings: Option<git::StashSettings>,
+) -> Result<String, String> {
+    git::create_stash(
+        &repo_path,
+        message.as_deref(),
+        &settings.unwrap_or_default(),
+    )
+    .map(|oid| oid.to_string())
+}
+
+#[tauri::command]
+fn git_get_stash_diff_cmd(
+    repo_path: String,
+    index: usize,
+) -> Result<Vec<git::StructuredDiff>, String> {
+    git::get_stash_diff(&repo_path, index)
 }
 
 #[tauri::command]
@@ -4470,8 +6477,15 @@ fn git_get_last_commit_message_cmd(repo_path: String) -> Result<String, String>
 }
 
 #[tauri::command]
-fn git_commit_amend_cmd(repo_path: String, message: String) -> Result<String, String> {
-    git::commit_amend(&repo_path, &message)
+fn git_commit_amend_cmd(
+    repo_path: String,
+    message: String,
+    sign: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let id = git::commit_amend(&repo_path, &message, sign)?;
+    state.blame_cache.invalidate(&repo_path);
+    Ok(id)
 }
 
 #[tauri::command]
@@ -4479,6 +6493,67 @@ fn git_checkout_commit_cmd(repo_path: String, commit_id: String) -> Result<(), S
     git::checkout_commit(&repo_path, &commit_id)
 }
 
+#[tauri::command]
+fn git_reset_to_commit_cmd(
+    repo_path: String,
+    commit_id: String,
+    mode: git::ResetMode,
+    force: Option<bool>,
+) -> Result<String, String> {
+    git::reset_to_commit(&repo_path, &commit_id, mode, force.unwrap_or(false))
+}
+
+#[tauri::command]
+fn git_get_reflog_cmd(
+    repo_path: String,
+    limit: Option<usize>,
+) -> Result<Vec<git::ReflogEntry>, String> {
+    git::get_reflog(&repo_path, limit)
+}
+
+#[tauri::command]
+fn git_list_worktrees_cmd(repo_path: String) -> Result<Vec<git::WorktreeInfo>, String> {
+    git::list_worktrees(&repo_path)
+}
+
+#[tauri::command]
+fn git_add_worktree_cmd(
+    repo_path: String,
+    path: String,
+    branch: String,
+    start_point: Option<String>,
+) -> Result<git::WorktreeInfo, String> {
+    git::add_worktree(&repo_path, &path, &branch, start_point.as_deref())
+}
+
+#[tauri::command]
+fn git_remove_worktree_cmd(
+    repo_path: String,
+    name: String,
+    force: Option<bool>,
+) -> Result<(), String> {
+    git::remove_worktree(&repo_path, &name, force.unwrap_or(false))
+}
+
+#[tauri::command]
+async fn git_archive_commit_cmd(
+    repo_path: String,
+    commit_id: String,
+    dest_path: String,
+    format: Option<git::ArchiveFormat>,
+) -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::archive_commit(
+            &repo_path,
+            &commit_id,
+            &dest_path,
+            format.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 #[tauri::command]
 fn git_cherry_pick_cmd(repo_path: String, commit_id: String) -> Result<String, String> {
     git::cherry_pick(&repo_path, &commit_id)
@@ -4489,8 +6564,35 @@ fn git_cherry_pick_cmd(repo_path: String, commit_id: String) -> Result<String, S
 // ============================================================================
 
 #[tauri::command]
-fn git_blame_cmd(repo_path: String, file_path: String) -> Result<Vec<git::BlameInfo>, String> {
-    git::git_blame(&repo_path, &file_path)
+fn git_blame_cmd(
+    repo_path: String,
+    file_path: String,
+    settings: Option<git::BlameSettings>,
+    state: State<'_, AppState>,
+) -> Result<Vec<git::BlameInfo>, String> {
+    git::git_blame(
+        &repo_path,
+        &file_path,
+        &settings.unwrap_or_default(),
+        &state.blame_cache,
+    )
+}
+
+#[tauri::command]
+fn git_blame_parent_cmd(
+    repo_path: String,
+    file_path: String,
+    commit_id: String,
+    settings: Option<git::BlameSettings>,
+    state: State<'_, AppState>,
+) -> Result<git::BlameAtParent, String> {
+    git::blame_parent(
+        &repo_path,
+        &file_path,
+        &commit_id,
+        &settings.unwrap_or_default(),
+        &state.blame_cache,
+    )
 }
 
 #[tauri::command]
@@ -4504,8 +6606,15 @@ fn git_create_tag_cmd(
     name: String,
     commit_id: Option<String>,
     message: Option<String>,
+    sign: Option<bool>,
 ) -> Result<(), String> {
-    git::create_tag(&repo_path, &name, commit_id.as_deref(), message.as_deref())
+    git::create_tag(
+        &repo_path,
+        &name,
+        commit_id.as_deref(),
+        message.as_deref(),
+        sign,
+    )
 }
 
 #[tauri::command]
@@ -4513,6 +6622,37 @@ fn git_delete_tag_cmd(repo_path: String, name: String) -> Result<(), String> {
     git::delete_tag(&repo_path, &name)
 }
 
+#[tauri::command]
+async fn git_push_tag_cmd(
+    repo_path: String,
+    remote: String,
+    name: String,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    git_credentials::run_with_prompt(app, move |prompt| {
+        git::push_tag(&repo_path, &remote, &name, Some(prompt))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn git_delete_remote_tag_cmd(
+    repo_path: String,
+    remote: String,
+    name: String,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    git_credentials::run_with_prompt(app, move |prompt| {
+        git::delete_remote_tag(&repo_path, &remote, &name, Some(prompt))
+    })
+    .await
+}
+
+#[tauri::command]
+fn git_checkout_tag_cmd(repo_path: String, name: String) -> Result<String, String> {
+    git::checkout_tag(&repo_path, &name)
+}
+
 #[tauri::command]
 fn git_revert_commit_cmd(repo_path: String, commit_id: String) -> Result<String, String> {
     git::revert_commit(&repo_path, &commit_id)
@@ -4532,6 +6672,14 @@ fn git_get_conflict_files_cmd(repo_path: String) -> Result<Vec<git::ConflictFile
     git::get_conflict_files(&repo_path)
 }
 
+#[tauri::command]
+fn git_get_conflict_merge_cmd(
+    repo_path: String,
+    file_path: String,
+) -> Result<git::ConflictMerge, String> {
+    git::get_conflict_merge(&repo_path, &file_path)
+}
+
 #[tauri::command]
 fn git_get_blob_content_cmd(repo_path: String, blob_oid: String) -> Result<String, String> {
     git::get_blob_content(&repo_path, &blob_oid)
@@ -4589,3 +6737,8 @@ fn git_read_gitignore_cmd(repo_path: String) -> Result<String, String> {
 fn git_write_gitignore_cmd(repo_path: String, content: String) -> Result<(), String> {
     git::write_gitignore(&repo_path, &content)
 }
+
+#[tauri::command]
+fn git_generate_latex_gitignore_cmd(content: String) -> String {
+    git::generate_latex_gitignore(&content)
+}
@@ -0,0 +1,434 @@
+//! Character Normalizer
+//!
+//! Finds characters that look harmless in an editor but break or silently
+//! change a LaTeX build: files that aren't UTF-8, byte order marks,
+//! non-breaking and other Unicode spaces, zero-width characters, smart
+//! quotes, control characters, and confusables such as U+2212 MINUS SIGN,
+//! fullwidth forms or a Cyrillic `е` inside `\section`. Greek is at home in
+//! these sources, so a word is only flagged for mixing scripts when most of
+//! it is Latin or most of it is Greek.
+//!
+//! Normalizing rewrites the findings with their ASCII (or Greek) stand-ins —
+//! a non-breaking space becomes `~` — and can return a side-by-side preview
+//! of the changed lines before anything is written.
+
+use serde::{Deserialize, Serialize};
+
+use crate::git::{self, SideBySideLine};
+use crate::search::encoding;
+
+/// Issues listed per file; the rest are only counted
+const MAX_ISSUES_PER_FILE: usize = 500;
+
+/// Greek and Cyrillic letters drawn like a Latin one
+const LATIN_LOOKALIKES: &[(char, char)] = &[
+    ('Α', 'A'),
+    ('Β', 'B'),
+    ('Ε', 'E'),
+    ('Ζ', 'Z'),
+    ('Η', 'H'),
+    ('Ι', 'I'),
+    ('Κ', 'K'),
+    ('Μ', 'M'),
+    ('Ν', 'N'),
+    ('Ο', 'O'),
+    ('Ρ', 'P'),
+    ('Τ', 'T'),
+    ('Υ', 'Y'),
+    ('Χ', 'X'),
+    ('ο', 'o'),
+    ('А', 'A'),
+    ('В', 'B'),
+    ('Е', 'E'),
+    ('К', 'K'),
+    ('М', 'M'),
+    ('Н', 'H'),
+    ('О', 'O'),
+    ('Р', 'P'),
+    ('С', 'C'),
+    ('Т', 'T'),
+    ('Х', 'X'),
+    ('а', 'a'),
+    ('е', 'e'),
+    ('о', 'o'),
+    ('р', 'p'),
+    ('с', 'c'),
+    ('у', 'y'),
+    ('х', 'x'),
+    ('і', 'i'),
+    ('ј', 'j'),
+    ('ѕ', 's'),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IssueKind {
+    NonBreakingSpace,
+    /// En, em, thin and other Unicode spaces
+    UnusualSpace,
+    /// Zero-width characters and soft hyphens
+    Invisible,
+    SmartQuote,
+    Confusable,
+    ControlCharacter,
+    /// U+FFFD left behind by an earlier bad decode; can't be repaired
+    ReplacementCharacter,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CharIssue {
+    pub kind: IssueKind,
+    /// 1-based
+    pub line: usize,
+    /// 1-based, in characters
+    pub column: usize,
+    pub character: String,
+    /// "U+00A0"
+    pub code_point: String,
+    /// What normalizing writes instead; `None` when it can't be fixed
+    pub replacement: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileCharacters {
+    pub path: String,
+    pub resource_id: Option<String>,
+    /// Encoding of a file that isn't UTF-8
+    pub encoding: Option<String>,
+    /// Whether the file starts with a byte order mark
+    pub bom: bool,
+    pub issue_count: usize,
+    pub issues: Vec<CharIssue>,
+}
+
+/// What to fix; everything by default
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NormalizeOptions {
+    /// Write the file as UTF-8 without a byte order mark
+    pub to_utf8: bool,
+    pub spaces: bool,
+    pub invisible: bool,
+    pub smart_quotes: bool,
+    pub confusables: bool,
+    pub control_characters: bool,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        NormalizeOptions {
+            to_utf8: true,
+            spaces: true,
+            invisible: true,
+            smart_quotes: true,
+            confusables: true,
+            control_characters: true,
+        }
+    }
+}
+
+impl NormalizeOptions {
+    fn fixes(&self, kind: IssueKind) -> bool {
+        match kind {
+            IssueKind::NonBreakingSpace | IssueKind::UnusualSpace => self.spaces,
+            IssueKind::Invisible => self.invisible,
+            IssueKind::SmartQuote => self.smart_quotes,
+            IssueKind::Confusable => self.confusables,
+            IssueKind::ControlCharacter => self.control_characters,
+            IssueKind::ReplacementCharacter => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileNormalization {
+    pub path: String,
+    pub resource_id: Option<String>,
+    /// Encoding the file is converted from, when it wasn't UTF-8
+    pub converted_from: Option<String>,
+    /// Characters replaced or removed
+    pub replaced: usize,
+    /// Changed lines only
+    pub diff: Vec<SideBySideLine>,
+    pub written: bool,
+}
+
+/// A problem character and where it is in the text
+struct Finding {
+    offset: usize,
+    kind: IssueKind,
+    character: char,
+    replacement: Option<String>,
+}
+
+fn classify(c: char) -> Option<(IssueKind, Option<String>)> {
+    let fix = |s: &str| Some(s.to_string());
+    Some(match c {
+        '\u{00A0}' | '\u{2007}' | '\u{202F}' => (IssueKind::NonBreakingSpace, fix("~")),
+        '\u{2000}'..='\u{200A}' | '\u{205F}' | '\u{3000}' => (IssueKind::UnusualSpace, fix(" ")),
+        '\u{00AD}' | '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}' => {
+            (IssueKind::Invisible, fix(""))
+        }
+        '\u{2018}' => (IssueKind::SmartQuote, fix("`")),
+        '\u{2019}' => (IssueKind::SmartQuote, fix("'")),
+        '\u{201A}' => (IssueKind::SmartQuote, fix(",")),
+        '\u{201C}' => (IssueKind::SmartQuote, fix("``")),
+        '\u{201D}' => (IssueKind::SmartQuote, fix("''")),
+        '\u{201E}' => (IssueKind::SmartQuote, fix(",,")),
+        '\u{2010}' | '\u{2011}' | '\u{2212}' => (IssueKind::Confusable, fix("-")),
+        // Fullwidth TeX specials stay literal characters, as they were
+        '\u{FF03}' => (IssueKind::Confusable, fix("\\#")),
+        '\u{FF04}' => (IssueKind::Confusable, fix("\\$")),
+        '\u{FF05}' => (IssueKind::Confusable, fix("\\%")),
+        '\u{FF06}' => (IssueKind::Confusable, fix("\\&")),
+        '\u{FF3C}' => (IssueKind::Confusable, fix("\\textbackslash{}")),
+        '\u{FF3E}' => (IssueKind::Confusable, fix("\\textasciicircum{}")),
+        '\u{FF3F}' => (IssueKind::Confusable, fix("\\_")),
+        '\u{FF5B}' => (IssueKind::Confusable, fix("\\{")),
+        '\u{FF5D}' => (IssueKind::Confusable, fix("\\}")),
+        '\u{FF5E}' => (IssueKind::Confusable, fix("\\textasciitilde{}")),
+        '\u{FF01}'..='\u{FF5E}' => (
+            IssueKind::Confusable,
+            char::from_u32(c as u32 - 0xFEE0).map(String::from),
+        ),
+        '\u{FFFD}' => (IssueKind::ReplacementCharacter, None),
+        '\t' | '\n' | '\r' => return None,
+        c if c.is_control() => (IssueKind::ControlCharacter, fix("")),
+        _ => return None,
+    })
+}
+
+fn is_greek(c: char) -> bool {
+    matches!(c, '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}')
+}
+
+fn is_cyrillic(c: char) -> bool {
+    matches!(c, '\u{0400}'..='\u{04FF}')
+}
+
+/// Lookalike letters in a word that mixes scripts: Greek or Cyrillic ones
+/// in a mostly Latin word, Latin ones in a mostly Greek word. Ties are left
+/// alone.
+fn mixed_script(word: &str, start: usize, findings: &mut Vec<Finding>) {
+    let latin = word.chars().filter(char::is_ascii_alphabetic).count();
+    let greek = word.chars().filter(|&c| is_greek(c)).count();
+    let cyrillic = word.chars().filter(|&c| is_cyrillic(c)).count();
+    if latin == 0 || greek + cyrillic == 0 {
+        return;
+    }
+    for (i, c) in word.char_indices() {
+        let replacement = if latin > greek + cyrillic {
+            LATIN_LOOKALIKES
+                .iter()
+                .find(|(other, _)| *other == c)
+                .map(|(_, latin)| *latin)
+        } else if greek > latin + cyrillic {
+            LATIN_LOOKALIKES
+                .iter()
+                .find(|(other, latin)| *latin == c && is_greek(*other))
+                .map(|(greek, _)| *greek)
+        } else {
+            None
+        };
+        if let Some(replacement) = replacement {
+            findings.push(Finding {
+                offset: start + i,
+                kind: IssueKind::Confusable,
+                character: c,
+                replacement: Some(replacement.to_string()),
+            });
+        }
+    }
+}
+
+/// Every problem character in `text`, in order
+fn find(text: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut word_start = None;
+    for (i, c) in text.char_indices() {
+        if c.is_alphabetic() {
+            word_start.get_or_insert(i);
+        } else if let Some(start) = word_start.take() {
+            mixed_script(&text[start..i], start, &mut findings);
+        }
+        if let Some((kind, replacement)) = classify(c) {
+            findings.push(Finding {
+                offset: i,
+                kind,
+                character: c,
+                replacement,
+            });
+        }
+    }
+    if let Some(start) = word_start {
+        mixed_script(&text[start..], start, &mut findings);
+    }
+    findings.sort_by_key(|f| f.offset);
+    findings
+}
+
+/// Problem characters of `text` with their positions; at most `limit`
+/// are listed, all are counted
+pub fn scan_text(text: &str, limit: usize) -> (usize, Vec<CharIssue>) {
+    let findings = find(text);
+    let mut issues = Vec::new();
+    let (mut line, mut line_start) = (1, 0);
+    let mut counted = 0;
+    for finding in findings.iter().take(limit) {
+        for (i, _) in text[counted..finding.offset].match_indices('\n') {
+            line += 1;
+            line_start = counted + i + 1;
+        }
+        counted = finding.offset;
+        issues.push(CharIssue {
+            kind: finding.kind,
+            line,
+            column: text[line_start..finding.offset].chars().count() + 1,
+            character: finding.character.to_string(),
+            code_point: format!("U+{:04X}", finding.character as u32),
+            replacement: finding.replacement.clone(),
+        });
+    }
+    (findings.len(), issues)
+}
+
+/// `text` with the findings `options` asks for replaced, and how many were
+pub fn normalize_text(text: &str, options: &NormalizeOptions) -> (String, usize) {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    let mut replaced = 0;
+    for finding in find(text) {
+        let Some(replacement) = finding.replacement else {
+            continue;
+        };
+        if !options.fixes(finding.kind) {
+            continue;
+        }
+        out.push_str(&text[last..finding.offset]);
+        out.push_str(&replacement);
+        last = finding.offset + finding.character.len_utf8();
+        replaced += 1;
+    }
+    out.push_str(&text[last..]);
+    (out, replaced)
+}
+
+fn read(path: &str) -> Result<encoding::Decoded, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    Ok(encoding::decode(&bytes))
+}
+
+/// Report on one file; `None` when there is nothing to report
+pub fn scan_file(
+    path: &str,
+    resource_id: Option<String>,
+) -> Result<Option<FileCharacters>, String> {
+    let decoded = read(path)?;
+    let (issue_count, issues) = scan_text(&decoded.text, MAX_ISSUES_PER_FILE);
+    let encoding = decoded.non_utf8_name();
+    if issue_count == 0 && encoding.is_none() && !decoded.bom {
+        return Ok(None);
+    }
+    Ok(Some(FileCharacters {
+        path: path.to_string(),
+        resource_id,
+        encoding,
+        bom: decoded.bom,
+        issue_count,
+        issues,
+    }))
+}
+
+/// Normalize one file, writing it only when `apply` is set; `None` when
+/// nothing would change
+pub fn normalize_file(
+    path: &str,
+    resource_id: Option<String>,
+    options: &NormalizeOptions,
+    apply: bool,
+) -> Result<Option<FileNormalization>, String> {
+    let decoded = read(path)?;
+    let (text, replaced) = normalize_text(&decoded.text, options);
+    let converted_from = decoded.non_utf8_name().filter(|_| options.to_utf8);
+    let reencoded = options.to_utf8 && (converted_from.is_some() || decoded.bom);
+    if replaced == 0 && !reencoded {
+        return Ok(None);
+    }
+
+    if apply {
+        let bytes = if options.to_utf8 {
+            text.clone().into_bytes()
+        } else {
+            encoding::encode(&text, decoded.encoding, decoded.bom)?
+        };
+        std::fs::write(path, bytes).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    }
+    let diff = git::generate_side_by_side_diff(&decoded.text, &text)
+        .into_iter()
+        .filter(|line| line.change_type != "unchanged")
+        .collect();
+    Ok(Some(FileNormalization {
+        path: path.to_string(),
+        resource_id,
+        converted_from,
+        replaced,
+        diff,
+        written: apply,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_text() {
+        let text = "\\sеction{Intro}\nA\u{00A0}B “quoted” x\u{2212}y\nΤο Kαλό σπίτι\u{FFFD}";
+        let (count, issues) = scan_text(text, 10);
+        let summary: Vec<(IssueKind, usize, usize, Option<&str>)> = issues
+            .iter()
+            .map(|i| (i.kind, i.line, i.column, i.replacement.as_deref()))
+            .collect();
+        assert_eq!(count, 7);
+        assert_eq!(
+            summary,
+            [
+                (IssueKind::Confusable, 1, 3, Some("e")),
+                (IssueKind::NonBreakingSpace, 2, 2, Some("~")),
+                (IssueKind::SmartQuote, 2, 5, Some("``")),
+                (IssueKind::SmartQuote, 2, 12, Some("''")),
+                (IssueKind::Confusable, 2, 15, Some("-")),
+                (IssueKind::Confusable, 3, 4, Some("Κ")),
+                (IssueKind::ReplacementCharacter, 3, 14, None),
+            ]
+        );
+        assert_eq!(issues[0].code_point, "U+0435");
+        assert_eq!(scan_text("Τι κάνεις; ok.", 10).0, 0);
+    }
+
+    #[test]
+    fn test_normalize_text() {
+        let text = "a\u{00A0}b\u{200B}c “d” ｘ\u{2212}1\u{0007}";
+        assert_eq!(
+            normalize_text(text, &NormalizeOptions::default()),
+            ("a~bc ``d'' x-1".to_string(), 7)
+        );
+        let options = NormalizeOptions {
+            smart_quotes: false,
+            ..Default::default()
+        };
+        assert_eq!(normalize_text(text, &options).0, "a~bc “d” x-1");
+    }
+
+    #[test]
+    fn test_fullwidth_tex_specials_are_escaped() {
+        let text = "５０％ ＃１ Ａ＆Ｂ ＼ ｛ｘ＿１｝ ～";
+        assert_eq!(
+            normalize_text(text, &NormalizeOptions::default()).0,
+            "50\\% \\#1 A\\&B \\textbackslash{} \\{x\\_1\\} \\textasciitilde{}"
+        );
+    }
+}
@@ -0,0 +1,763 @@
+//! Document Outline
+//!
+//! Builds a navigable outline of a LaTeX document and the files it pulls in
+//! through `\input`/`\include`: parts, chapters and sections, the
+//! theorem-like environments declared with `\newtheorem`, and equations that
+//! carry a `\label`. Numbers are estimated the way LaTeX would count them
+//! (article vs. book classes, `\appendix`, `[within]` counters,
+//! `\numberwithin`, `\tag`, `\nonumber`) without compiling, so they can be
+//! off for documents that redefine the counters themselves.
+
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::dependency_scanner::{self, strip_comments, Reference};
+
+/// Sectioning commands with their LaTeX level (`\part` is -1, `\section` 1)
+const SECTIONS: &[(&str, i32)] = &[
+    ("part", -1),
+    ("chapter", 0),
+    ("section", 1),
+    ("subsection", 2),
+    ("subsubsection", 3),
+    ("paragraph", 4),
+    ("subparagraph", 5),
+];
+
+/// Counters reset by the one before them
+const SECTION_RESETS: &[(&str, &str)] = &[
+    ("subsection", "section"),
+    ("subsubsection", "subsection"),
+    ("paragraph", "subsubsection"),
+    ("subparagraph", "paragraph"),
+];
+
+/// Classes with `\chapter`, whose sections and equations count per chapter
+const BOOK_CLASSES: &[&str] = &["book", "report", "memoir", "scrbook", "scrreprt"];
+
+/// Numbered math environments, and whether each row gets its own number
+const EQUATIONS: &[(&str, bool)] = &[
+    ("equation", false),
+    ("multline", false),
+    ("align", true),
+    ("alignat", true),
+    ("flalign", true),
+    ("gather", true),
+    ("eqnarray", true),
+];
+
+/// Theorem-like environments taken as `\newtheorem{name}{Name}` when the
+/// declaration isn't in the files, e.g. because it lives in a separately
+/// kept preamble
+const COMMON_THEOREMS: &[&str] = &[
+    "theorem",
+    "lemma",
+    "proposition",
+    "corollary",
+    "definition",
+    "example",
+    "remark",
+    "exercise",
+    "problem",
+];
+
+/// `\input`s nested deeper than this are not followed (and a cycle ends)
+const MAX_INPUT_DEPTH: usize = 16;
+
+/// Longest equation source shown as its title
+const EQUATION_TITLE_CHARS: usize = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutlineKind {
+    Sectioning,
+    Theorem,
+    Equation,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutlineNode {
+    pub kind: OutlineKind,
+    /// Command or environment name: "section", "lemma", "align", ...
+    pub name: String,
+    /// Heading text, theorem name (with its optional note) or equation source
+    pub title: String,
+    /// Estimated number; `None` for starred and unnumbered entries
+    pub number: Option<String>,
+    pub label: Option<String>,
+    pub file: String,
+    pub line: usize,
+    pub children: Vec<OutlineNode>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentOutline {
+    /// Files the outline was read from, main file first
+    pub files: Vec<String>,
+    pub nodes: Vec<OutlineNode>,
+}
+
+fn token_regex() -> &'static Regex {
+    static TOKEN_RE: OnceLock<Regex> = OnceLock::new();
+    TOKEN_RE.get_or_init(|| {
+        Regex::new(concat!(
+            r"\\(?:",
+            r"(?P<section>part|chapter|section|subsection|subsubsection|paragraph|subparagraph)(?P<star>\*?)\s*(?:\[[^\]]*\]\s*)?\{",
+            r"|begin\s*\{(?P<begin>[^}]+)\}(?:[ \t]*\[(?P<note>[^\]]*)\])?",
+            r"|end\s*\{(?P<end>[^}]+)\}",
+            r"|label\s*\{(?P<label>[^}]*)\}",
+            r"|(?:input|include|subfile)\s*\{(?P<input>[^}]*)\}",
+            r"|newtheorem(?P<thmstar>\*?)\s*\{(?P<thm>[^}]*)\}\s*(?:\[(?P<shared>[^\]]*)\]\s*)?\{(?P<thmtitle>[^}]*)\}(?:\s*\[(?P<within>[^\]]*)\])?",
+            r"|numberwithin\s*\{(?P<nwchild>[^}]*)\}\s*\{(?P<nwparent>[^}]*)\}",
+            r"|setcounter\s*\{secnumdepth\}\s*\{(?P<secnumdepth>-?\d+)\}",
+            r"|documentclass\s*(?:\[[^\]]*\]\s*)?\{(?P<class>[^}]*)\}",
+            r"|(?P<appendix>appendix)\b",
+            r"|(?P<nonumber>nonumber|notag)\b",
+            r"|tag\*?\s*\{(?P<tag>[^}]*)\}",
+            r"|(?P<newline>\\)",
+            r")"
+        ))
+        .unwrap()
+    })
+}
+
+/// Text of the group opened just before `start`, and the offset after its `}`
+fn braced(text: &str, start: usize) -> (&str, usize) {
+    let mut depth = 0;
+    let mut escaped = false;
+    for (i, c) in text[start..].char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' if depth == 0 => return (&text[start..start + i], start + i + 1),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    (&text[start..], text.len())
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn roman(mut value: u32) -> String {
+    const NUMERALS: &[(u32, &str)] = &[
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut out = String::new();
+    for &(amount, numeral) in NUMERALS {
+        while value >= amount {
+            out.push_str(numeral);
+            value -= amount;
+        }
+    }
+    out
+}
+
+#[derive(Default)]
+struct Counters {
+    values: HashMap<String, u32>,
+    /// Counter -> the counter that resets it and prefixes its number
+    within: HashMap<String, String>,
+    /// The top-level counter printed as a letter after `\appendix`
+    appendix: Option<String>,
+}
+
+impl Counters {
+    /// Number `child` within `parent`, unless that would make a cycle
+    fn set_within(&mut self, child: &str, parent: &str) {
+        let mut ancestor = Some(parent);
+        while let Some(name) = ancestor {
+            if name == child {
+                return;
+            }
+            ancestor = self.within.get(name).map(String::as_str);
+        }
+        self.within.insert(child.to_string(), parent.to_string());
+    }
+
+    fn step(&mut self, name: &str) {
+        *self.values.entry(name.to_string()).or_insert(0) += 1;
+        self.reset_within(name);
+    }
+
+    fn reset_within(&mut self, name: &str) {
+        let children: Vec<String> = self
+            .within
+            .iter()
+            .filter(|(_, parent)| parent.as_str() == name)
+            .map(|(child, _)| child.clone())
+            .collect();
+        for child in children {
+            self.values.insert(child.clone(), 0);
+            self.reset_within(&child);
+        }
+    }
+
+    /// `\the<name>`
+    fn format(&self, name: &str) -> String {
+        let value = self.values.get(name).copied().unwrap_or(0);
+        let own = if name == "part" {
+            roman(value)
+        } else if self.appendix.as_deref() == Some(name) && (1..=26).contains(&value) {
+            char::from(b'A' + value as u8 - 1).to_string()
+        } else {
+            value.to_string()
+        };
+        match self.within.get(name) {
+            Some(parent) => format!("{}.{}", self.format(parent), own),
+            _ => own,
+        }
+    }
+}
+
+/// A declared theorem-like environment
+struct Theorem {
+    title: String,
+    /// `None` for `\newtheorem*`
+    counter: Option<String>,
+}
+
+/// One numbered line of a math environment
+struct Row {
+    start: usize,
+    label: Option<String>,
+    numbered: bool,
+    tag: Option<String>,
+}
+
+impl Row {
+    fn new(start: usize) -> Self {
+        Row {
+            start,
+            label: None,
+            numbered: true,
+            tag: None,
+        }
+    }
+}
+
+struct Equation {
+    env: String,
+    numbered: bool,
+    per_row: bool,
+    /// Environments opened inside (`aligned`, `cases`, ...), whose `\\` don't end a row
+    inner: usize,
+    row: Row,
+}
+
+struct Outliner<'a> {
+    root: &'a Path,
+    counters: Counters,
+    theorems: HashMap<String, Theorem>,
+    book: bool,
+    secnumdepth: i32,
+    /// Entries in document order with their depth in the tree
+    entries: Vec<(usize, OutlineNode)>,
+    /// Depth of the last heading; theorems and equations go below it
+    heading_depth: usize,
+    /// Entry the next `\label` names, if it comes before anything else
+    label_target: Option<usize>,
+    open_theorems: Vec<(String, usize)>,
+    equation: Option<Equation>,
+    files: Vec<String>,
+    visiting: Vec<PathBuf>,
+}
+
+impl<'a> Outliner<'a> {
+    fn new(root: &'a Path) -> Self {
+        let mut outliner = Outliner {
+            root,
+            counters: Counters::default(),
+            theorems: HashMap::new(),
+            book: false,
+            secnumdepth: 3,
+            entries: Vec::new(),
+            heading_depth: 0,
+            label_target: None,
+            open_theorems: Vec::new(),
+            equation: None,
+            files: Vec::new(),
+            visiting: Vec::new(),
+        };
+        outliner.set_class("article");
+        outliner
+    }
+
+    fn set_class(&mut self, class: &str) {
+        self.book = BOOK_CLASSES.contains(&class);
+        self.secnumdepth = if self.book { 2 } else { 3 };
+        let within = &mut self.counters.within;
+        within.clear();
+        for (child, parent) in SECTION_RESETS {
+            within.insert(child.to_string(), parent.to_string());
+        }
+        if self.book {
+            within.insert("section".to_string(), "chapter".to_string());
+            within.insert("equation".to_string(), "chapter".to_string());
+        }
+    }
+
+    fn push(&mut self, depth: usize, node: OutlineNode) -> usize {
+        self.entries.push((depth, node));
+        self.entries.len() - 1
+    }
+
+    fn walk(&mut self, path: &Path, content: &str) {
+        if self.visiting.len() > MAX_INPUT_DEPTH || self.visiting.iter().any(|p| p == path) {
+            return;
+        }
+        self.visiting.push(path.to_path_buf());
+        let file = path.to_string_lossy().to_string();
+        if !self.files.contains(&file) {
+            self.files.push(file.clone());
+        }
+
+        let text = strip_comments(content);
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let line_of = |offset: usize| line_starts.partition_point(|&start| start <= offset);
+
+        let mut pos = 0;
+        while let Some(caps) = token_regex().captures_at(&text, pos) {
+            let whole = caps.get(0).unwrap();
+            pos = whole.end();
+            let line = line_of(whole.start());
+
+            if let Some(command) = caps.name("section") {
+                let (title, end) = braced(&text, pos);
+                pos = end;
+                self.heading(
+                    command.as_str(),
+                    !caps["star"].is_empty(),
+                    title,
+                    &file,
+                    line,
+                );
+            } else if let Some(env) = caps.name("begin") {
+                let note = caps.name("note").map(|n| n.as_str());
+                self.begin(env.as_str().trim(), note, pos, &file, line);
+            } else if let Some(env) = caps.name("end") {
+                self.end(env.as_str().trim(), &text, whole.start(), &file, &line_of);
+            } else if let Some(label) = caps.name("label") {
+                self.label(label.as_str().trim());
+            } else if let Some(target) = caps.name("input") {
+                self.input(path, target.as_str());
+            } else if let Some(name) = caps.name("thm") {
+                let counter = if !caps["thmstar"].is_empty() {
+                    None
+                } else if let Some(shared) = caps.name("shared") {
+                    Some(shared.as_str().trim().to_string())
+                } else {
+                    let counter = name.as_str().trim().to_string();
+                    if let Some(within) = caps.name("within") {
+                        self.counters.set_within(&counter, within.as_str().trim());
+                    }
+                    Some(counter)
+                };
+                self.theorems.insert(
+                    name.as_str().trim().to_string(),
+                    Theorem {
+                        title: caps["thmtitle"].trim().to_string(),
+                        counter,
+                    },
+                );
+            } else if let Some(child) = caps.name("nwchild") {
+                self.counters
+                    .set_within(child.as_str().trim(), caps["nwparent"].trim());
+            } else if let Some(depth) = caps.name("secnumdepth") {
+                self.secnumdepth = depth.as_str().parse().unwrap_or(self.secnumdepth);
+            } else if let Some(class) = caps.name("class") {
+                self.set_class(class.as_str().trim());
+            } else if caps.name("appendix").is_some() {
+                let top = if self.book { "chapter" } else { "section" };
+                self.counters.values.insert(top.to_string(), 0);
+                self.counters.appendix = Some(top.to_string());
+            } else if let Some(equation) = &mut self.equation {
+                if caps.name("nonumber").is_some() {
+                    equation.row.numbered = false;
+                } else if let Some(tag) = caps.name("tag") {
+                    equation.row.tag = Some(tag.as_str().trim().to_string());
+                } else if caps.name("newline").is_some() && equation.per_row && equation.inner == 0
+                {
+                    self.close_row(&text, whole.start(), pos, &file, &line_of);
+                }
+            }
+        }
+        self.visiting.pop();
+    }
+
+    fn heading(&mut self, command: &str, starred: bool, title: &str, file: &str, line: usize) {
+        let level = SECTIONS
+            .iter()
+            .find(|(name, _)| *name == command)
+            .map(|(_, level)| *level)
+            .unwrap_or(1);
+        let number = if !starred && level <= self.secnumdepth {
+            self.counters.step(command);
+            Some(self.counters.format(command))
+        } else {
+            None
+        };
+        self.open_theorems.clear();
+        self.heading_depth = (level + 2) as usize;
+        let index = self.push(
+            self.heading_depth,
+            OutlineNode {
+                kind: OutlineKind::Sectioning,
+                name: command.to_string(),
+                title: collapse_whitespace(title),
+                number,
+                label: None,
+                file: file.to_string(),
+                line,
+                children: Vec::new(),
+            },
+        );
+        self.label_target = Some(index);
+    }
+
+    /// Depth of an entry below the current heading and open theorems
+    fn body_depth(&self) -> usize {
+        self.heading_depth + 1 + self.open_theorems.len()
+    }
+
+    fn begin(&mut self, env: &str, note: Option<&str>, start: usize, file: &str, line: usize) {
+        if let Some(equation) = &mut self.equation {
+            equation.inner += 1;
+            return;
+        }
+        let bare = env.trim_end_matches('*');
+        if let Some(&(_, per_row)) = EQUATIONS.iter().find(|(name, _)| *name == bare) {
+            self.equation = Some(Equation {
+                env: env.to_string(),
+                numbered: !env.ends_with('*'),
+                per_row,
+                inner: 0,
+                row: Row::new(start),
+            });
+            return;
+        }
+
+        let theorem = match self.theorems.get(env) {
+            Some(theorem) => Some((theorem.title.clone(), theorem.counter.clone())),
+            None if COMMON_THEOREMS.contains(&env) => {
+                let mut title = env.to_string();
+                title[..1].make_ascii_uppercase();
+                Some((title, Some(env.to_string())))
+            }
+            None => None,
+        };
+        let Some((title, counter)) = theorem else {
+            self.label_target = None;
+            return;
+        };
+        let number = counter.map(|counter| {
+            self.counters.step(&counter);
+            self.counters.format(&counter)
+        });
+        let title = match note.map(str::trim).filter(|n| !n.is_empty()) {
+            Some(note) => format!("{} ({})", title, collapse_whitespace(note)),
+            None => title,
+        };
+        let index = self.push(
+            self.body_depth(),
+            OutlineNode {
+                kind: OutlineKind::Theorem,
+                name: env.to_string(),
+                title,
+                number,
+                label: None,
+                file: file.to_string(),
+                line,
+                children: Vec::new(),
+            },
+        );
+        self.open_theorems.push((env.to_string(), index));
+        self.label_target = Some(index);
+    }
+
+    fn end(
+        &mut self,
+        env: &str,
+        text: &str,
+        at: usize,
+        file: &str,
+        line_of: &dyn Fn(usize) -> usize,
+    ) {
+        if let Some(equation) = &mut self.equation {
+            if equation.inner > 0 {
+                equation.inner -= 1;
+            } else if equation.env == env {
+                self.close_row(text, at, at, file, line_of);
+                self.equation = None;
+            }
+            return;
+        }
+        if let Some(open) = self.open_theorems.iter().rposition(|(name, _)| name == env) {
+            self.open_theorems.truncate(open);
+            self.label_target = None;
+        }
+    }
+
+    fn label(&mut self, label: &str) {
+        if let Some(equation) = &mut self.equation {
+            equation.row.label.get_or_insert_with(|| label.to_string());
+            return;
+        }
+        if let Some(index) = self.label_target.take() {
+            self.entries[index]
+                .1
+                .label
+                .get_or_insert_with(|| label.to_string());
+        }
+    }
+
+    /// Number the row of the open math environment that ends at `end` and
+    /// start the next one at `next`; rows with a label go in the outline
+    fn close_row(
+        &mut self,
+        text: &str,
+        end: usize,
+        next: usize,
+        file: &str,
+        line_of: &dyn Fn(usize) -> usize,
+    ) {
+        let Some(equation) = &mut self.equation else {
+            return;
+        };
+        let row = std::mem::replace(&mut equation.row, Row::new(next));
+        let source = &text[row.start..end];
+        if source.trim().is_empty() {
+            return;
+        }
+        let number = match row.tag {
+            Some(tag) => Some(format!("({})", tag)),
+            None if equation.numbered && row.numbered => {
+                self.counters.step("equation");
+                Some(format!("({})", self.counters.format("equation")))
+            }
+            None => None,
+        };
+        let Some(label) = row.label else {
+            return;
+        };
+        static MARKUP_RE: OnceLock<Regex> = OnceLock::new();
+        let markup = MARKUP_RE.get_or_init(|| {
+            Regex::new(r"\\(?:label|tag\*?)\s*\{[^}]*\}|\\(?:nonumber|notag)\b").unwrap()
+        });
+        let mut title = collapse_whitespace(&markup.replace_all(source, ""));
+        if title.chars().count() > EQUATION_TITLE_CHARS {
+            title = title.chars().take(EQUATION_TITLE_CHARS).collect::<String>() + "…";
+        }
+        let name = equation.env.clone();
+        let offset = row.start + (source.len() - source.trim_start().len());
+        let depth = self.body_depth();
+        self.push(
+            depth,
+            OutlineNode {
+                kind: OutlineKind::Equation,
+                name,
+                title,
+                number,
+                label: Some(label),
+                file: file.to_string(),
+                line: line_of(offset),
+                children: Vec::new(),
+            },
+        );
+    }
+
+    fn input(&mut self, source: &Path, target: &str) {
+        let reference = Reference {
+            relation: "input",
+            target: target.trim().to_string(),
+        };
+        let found = dependency_scanner::candidate_paths(source, Some(self.root), &reference)
+            .into_iter()
+            .find(|candidate| candidate.is_file());
+        if let Some(path) = found {
+            if let Ok(content) = fs::read_to_string(&path) {
+                self.walk(&path, &content);
+            }
+        }
+    }
+
+    fn finish(self) -> DocumentOutline {
+        DocumentOutline {
+            files: self.files,
+            nodes: nest(self.entries),
+        }
+    }
+}
+
+/// Turn entries with depths into a tree: each goes under the closest
+/// earlier entry that is shallower
+fn nest(entries: Vec<(usize, OutlineNode)>) -> Vec<OutlineNode> {
+    fn close(stack: &mut Vec<(usize, OutlineNode)>, roots: &mut Vec<OutlineNode>) {
+        if let Some((_, node)) = stack.pop() {
+            match stack.last_mut() {
+                Some((_, parent)) => parent.children.push(node),
+                None => roots.push(node),
+            }
+        }
+    }
+
+    let mut roots = Vec::new();
+    let mut stack: Vec<(usize, OutlineNode)> = Vec::new();
+    for (depth, node) in entries {
+        while stack.last().is_some_and(|(open, _)| *open >= depth) {
+            close(&mut stack, &mut roots);
+        }
+        stack.push((depth, node));
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut roots);
+    }
+    roots
+}
+
+/// Outline of the document at `path`, following its `\input`s and
+/// `\include`s. Blocks while reading the files.
+pub fn document_outline(path: &str) -> Result<DocumentOutline, String> {
+    let main = Path::new(path);
+    let content =
+        fs::read_to_string(main).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let root = main.parent().unwrap_or(Path::new("."));
+    let mut outliner = Outliner::new(root);
+    outliner.walk(main, &content);
+    Ok(outliner.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(nodes: &[OutlineNode], depth: usize, out: &mut Vec<String>) {
+        for node in nodes {
+            out.push(format!(
+                "{}{} {} {}{}",
+                "  ".repeat(depth),
+                node.name,
+                node.number.as_deref().unwrap_or("-"),
+                node.title,
+                node.label
+                    .as_ref()
+                    .map(|l| format!(" <{}>", l))
+                    .unwrap_or_default()
+            ));
+            summary(&node.children, depth + 1, out);
+        }
+    }
+
+    #[test]
+    fn test_article_outline() {
+        let source = r"\documentclass{article}
+\newtheorem{theorem}{Theorem}[section]
+\newtheorem{lemma}[theorem]{Lemma}
+\newtheorem*{claim}{Claim}
+\begin{document}
+\section{Intro}\label{sec:intro}
+\begin{equation}\label{eq:first} a = b \end{equation}
+% \section{Hidden}
+\section*{Thanks}
+\section{Results}
+\begin{lemma}[Key {step}]\label{lem:key}
+\begin{align}
+x &= 1 \label{eq:x} \\
+y &= 2 \nonumber \\
+z &= \begin{cases} 3 \\ 4 \end{cases} \label{eq:z}
+\end{align}
+\end{lemma}
+\begin{claim} True. \end{claim}
+\begin{theorem}\label{thm:main}\end{theorem}
+\subsection{Details}
+\begin{equation}w\tag{$*$}\label{eq:star}\end{equation}
+\appendix
+\section{Proofs}
+\end{document}
+";
+        let mut outliner = Outliner::new(Path::new("/work"));
+        outliner.walk(Path::new("/work/main.tex"), source);
+        let outline = outliner.finish();
+        let mut lines = Vec::new();
+        summary(&outline.nodes, 0, &mut lines);
+        assert_eq!(
+            lines,
+            [
+                "section 1 Intro <sec:intro>",
+                "  equation (1) a = b <eq:first>",
+                "section - Thanks",
+                "section 2 Results",
+                "  lemma 2.1 Lemma (Key {step}) <lem:key>",
+                "    align (2) x &= 1 <eq:x>",
+                "    align (3) z &= \\begin{cases} 3 \\\\ 4 \\end{cases} <eq:z>",
+                "  claim - Claim",
+                "  theorem 2.2 Theorem <thm:main>",
+                "  subsection 2.1 Details",
+                "    equation ($*$) w <eq:star>",
+                "section A Proofs",
+            ]
+        );
+        assert_eq!(outline.nodes[2].children[0].children[1].line, 15);
+    }
+
+    #[test]
+    fn test_book_outline_follows_inputs() {
+        let root = std::env::temp_dir().join(format!("datatex-outline-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(root.join("chapters")).unwrap();
+        fs::write(
+            root.join("main.tex"),
+            "\\documentclass{book}\n\\begin{document}\n\\part{Basics}\n\\input{chapters/one}\n\\chapter{Two}\n\\section{More}\n\\begin{equation}\\label{eq:two}e\\end{equation}\n\\end{document}\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("chapters/one.tex"),
+            "\\chapter{One}\n\\section{Start}\n\\subsubsection{Deep}\n\\begin{equation}\\label{eq:one}o\\end{equation}\n\\input{chapters/one}\n",
+        )
+        .unwrap();
+
+        let main = root.join("main.tex");
+        let outline = document_outline(&main.to_string_lossy()).unwrap();
+        let mut lines = Vec::new();
+        summary(&outline.nodes, 0, &mut lines);
+        assert_eq!(
+            lines,
+            [
+                "part I Basics",
+                "  chapter 1 One",
+                "    section 1.1 Start",
+                "      subsubsection - Deep",
+                "        equation (1.1) o <eq:one>",
+                "  chapter 2 Two",
+                "    section 2.1 More",
+                "      equation (2.1) e <eq:two>",
+            ]
+        );
+        assert_eq!(outline.files.len(), 2);
+        assert!(outline.nodes[0].children[0].file.ends_with("one.tex"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
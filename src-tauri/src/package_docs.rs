@@ -0,0 +1,242 @@
+//! Package Documentation Lookup
+//!
+//! Finds the manual of a LaTeX package or class. `texdoc -l -M` lists the
+//! documentation installed with the TeX distribution; when texdoc isn't
+//! there or knows nothing about the name, CTAN's JSON API is asked instead
+//! and the documentation comes back as links to a CTAN mirror.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Command;
+
+const CTAN_API: &str = "https://ctan.org/json/2.0/pkg";
+const CTAN_MIRROR: &str = "https://mirrors.ctan.org";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocsSource {
+    Texdoc,
+    Ctan,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageDoc {
+    /// A local file for texdoc results, a URL for CTAN ones
+    pub location: String,
+    pub description: Option<String>,
+    pub language: Option<String>,
+    /// texdoc's relevance score, best first
+    pub score: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageDocs {
+    pub name: String,
+    pub source: DocsSource,
+    /// CTAN's one-line description of the package
+    pub caption: Option<String>,
+    pub docs: Vec<PackageDoc>,
+}
+
+/// Package and class names as they appear in `\usepackage`; anything else
+/// isn't passed on to texdoc or into a URL
+fn valid_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+fn find_texdoc() -> Option<PathBuf> {
+    let name = if cfg!(windows) {
+        "texdoc.exe"
+    } else {
+        "texdoc"
+    };
+    let path = crate::compiler::get_augmented_path();
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Lines of `texdoc -l -M`: argument, score, file, language and
+/// description, separated by tabs
+fn parse_texdoc(output: &str) -> Vec<PackageDoc> {
+    let non_empty = |field: Option<&str>| {
+        field
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(str::to_string)
+    };
+    let mut docs: Vec<PackageDoc> = output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let _argument = fields.next()?;
+            let score = fields.next()?.trim().parse::<f32>().ok();
+            let location = non_empty(fields.next())?;
+            Some(PackageDoc {
+                location,
+                language: non_empty(fields.next()),
+                description: non_empty(fields.next()),
+                score,
+            })
+        })
+        .collect();
+    docs.sort_by(|a, b| b.score.unwrap_or(0.0).total_cmp(&a.score.unwrap_or(0.0)));
+    docs
+}
+
+/// Installed documentation for `name`; `None` without texdoc
+fn texdoc(name: &str) -> Option<Vec<PackageDoc>> {
+    let texdoc = find_texdoc()?;
+    let output = Command::new(&texdoc)
+        .args(["-l", "-M", name])
+        .env("PATH", crate::compiler::get_augmented_path())
+        .output()
+        .ok()?;
+    // texdoc exits with 3 when it finds nothing
+    Some(parse_texdoc(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[derive(Deserialize)]
+struct CtanPackage {
+    caption: Option<String>,
+    #[serde(default)]
+    documentation: Vec<CtanDocumentation>,
+}
+
+#[derive(Deserialize)]
+struct CtanDocumentation {
+    language: Option<String>,
+    details: Option<String>,
+    href: String,
+}
+
+/// `ctan:/path` links point into the archive; the mirror redirector serves them
+fn ctan_url(href: &str) -> String {
+    match href.strip_prefix("ctan:") {
+        Some(path) => format!("{}{}", CTAN_MIRROR, path),
+        None => href.to_string(),
+    }
+}
+
+fn parse_ctan(json: &str) -> Result<(Option<String>, Vec<PackageDoc>), String> {
+    let package: CtanPackage =
+        serde_json::from_str(json).map_err(|e| format!("Unexpected CTAN response: {}", e))?;
+    let docs = package
+        .documentation
+        .into_iter()
+        .map(|doc| PackageDoc {
+            location: ctan_url(&doc.href),
+            description: doc.details,
+            language: doc.language,
+            score: None,
+        })
+        .collect();
+    Ok((package.caption, docs))
+}
+
+async fn ctan(name: &str) -> Result<(Option<String>, Vec<PackageDoc>), String> {
+    let url = format!("{}/{}", CTAN_API, name);
+    let response = reqwest::get(&url)
+        .await
+        .map_err(|e| format!("Failed to reach CTAN: {}", e))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(format!("No documentation found for '{}'", name));
+    }
+    if !response.status().is_success() {
+        return Err(format!(
+            "CTAN lookup failed with status: {}",
+            response.status()
+        ));
+    }
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    parse_ctan(&body)
+}
+
+/// Documentation for the package or class `name`, from texdoc when it has
+/// some and from CTAN otherwise
+pub async fn lookup(name: &str) -> Result<PackageDocs, String> {
+    let name = name.trim();
+    if !valid_name(name) {
+        return Err(format!("Not a package name: {}", name));
+    }
+    let owned = name.to_string();
+    let installed = tauri::async_runtime::spawn_blocking(move || texdoc(&owned))
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Some(docs) = installed.filter(|docs| !docs.is_empty()) {
+        return Ok(PackageDocs {
+            name: name.to_string(),
+            source: DocsSource::Texdoc,
+            caption: None,
+            docs,
+        });
+    }
+
+    let (caption, docs) = ctan(name).await?;
+    if docs.is_empty() {
+        return Err(format!("No documentation found for '{}'", name));
+    }
+    Ok(PackageDocs {
+        name: name.to_string(),
+        source: DocsSource::Ctan,
+        caption,
+        docs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_texdoc() {
+        let output = "amsmath\t1.5\t/texlive/doc/latex/amsmath/amsmath.pdf\t\t\n\
+                      amsmath\t10\t/texlive/doc/latex/amsmath/amsldoc.pdf\ten\tUser's guide\n\
+                      garbage line\n";
+        let docs = parse_texdoc(output);
+        assert_eq!(
+            docs,
+            [
+                PackageDoc {
+                    location: "/texlive/doc/latex/amsmath/amsldoc.pdf".to_string(),
+                    description: Some("User's guide".to_string()),
+                    language: Some("en".to_string()),
+                    score: Some(10.0),
+                },
+                PackageDoc {
+                    location: "/texlive/doc/latex/amsmath/amsmath.pdf".to_string(),
+                    description: None,
+                    language: None,
+                    score: Some(1.5),
+                },
+            ]
+        );
+        assert!(valid_name("pgf-pie") && valid_name("l3kernel"));
+        assert!(!valid_name("-l") && !valid_name("../x") && !valid_name(""));
+    }
+
+    #[test]
+    fn test_parse_ctan() {
+        let json = r#"{"name":"booktabs","caption":"Publication quality tables in LaTeX","documentation":[{"language":null,"details":"Package documentation","href":"ctan:/macros/latex/contrib/booktabs/booktabs.pdf"},{"language":"de","details":"German","href":"https://example.org/booktabs-de.pdf"}]}"#;
+        let (caption, docs) = parse_ctan(json).unwrap();
+        assert_eq!(
+            caption.as_deref(),
+            Some("Publication quality tables in LaTeX")
+        );
+        assert_eq!(
+            docs.iter().map(|d| d.location.as_str()).collect::<Vec<_>>(),
+            [
+                "https://mirrors.ctan.org/macros/latex/contrib/booktabs/booktabs.pdf",
+                "https://example.org/booktabs-de.pdf",
+            ]
+        );
+        assert!(parse_ctan(r#"{"caption":null}"#).unwrap().1.is_empty());
+    }
+}
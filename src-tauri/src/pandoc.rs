@@ -0,0 +1,278 @@
+//! Pandoc Export
+//!
+//! Converts LaTeX to HTML, DOCX or Markdown with pandoc. The binary is
+//! taken from `tools.pandoc` in the settings, or looked up on the `PATH`
+//! (with the same extra directories the TeX tools get). A resource that
+//! keeps its preamble separately is assembled the way compiling it would
+//! be, and fed to pandoc on stdin from the resource's folder so relative
+//! `\input`s and images still resolve.
+//!
+//! Pandoc reports what it couldn't convert as `[WARNING]` lines on
+//! stderr; those come back with the result instead of failing the export.
+
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::database::DatabaseManager;
+use crate::document_builder;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PandocFormat {
+    Html,
+    Docx,
+    Markdown,
+}
+
+impl PandocFormat {
+    fn writer(self) -> &'static str {
+        match self {
+            PandocFormat::Html => "html5",
+            PandocFormat::Docx => "docx",
+            PandocFormat::Markdown => "markdown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PandocRequest {
+    /// Resource to convert; its preamble is added when it is kept apart
+    pub resource_id: Option<String>,
+    /// A .tex file to convert as is, when there is no `resource_id`
+    pub path: Option<String>,
+    pub format: PandocFormat,
+    pub output_path: String,
+    /// A pandoc template for HTML and Markdown, a reference .docx for DOCX
+    pub template: Option<String>,
+    /// .bib file; citations are rendered with citeproc
+    pub bibliography: Option<String>,
+    /// Citation style for the bibliography
+    pub csl: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PandocInfo {
+    pub path: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PandocResult {
+    pub output_path: String,
+    pub warnings: Vec<String>,
+}
+
+/// LaTeX ready for pandoc, and the folder it is read from
+pub struct PandocSource {
+    pub content: String,
+    pub dir: PathBuf,
+}
+
+/// The configured pandoc, or the first one on the augmented `PATH`
+pub fn find_pandoc() -> Option<PathBuf> {
+    if let Some(pandoc) = crate::settings::current().tools.pandoc {
+        return Some(PathBuf::from(pandoc));
+    }
+    let name = if cfg!(windows) {
+        "pandoc.exe"
+    } else {
+        "pandoc"
+    };
+    let path = crate::compiler::get_augmented_path();
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+pub fn detect() -> Option<PandocInfo> {
+    let pandoc = find_pandoc()?;
+    let output = Command::new(&pandoc).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // "pandoc 3.1.11"
+    let version = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()?
+        .trim_start_matches("pandoc")
+        .trim()
+        .to_string();
+    Some(PandocInfo {
+        path: pandoc.to_string_lossy().to_string(),
+        version,
+    })
+}
+
+pub async fn resource_source(db: &DatabaseManager, id: &str) -> Result<PandocSource, String> {
+    let resource = db
+        .get_resource_by_id(id)
+        .await?
+        .ok_or_else(|| format!("Resource not found: {}", id))?;
+    let body = std::fs::read_to_string(&resource.path)
+        .map_err(|e| format!("Failed to read {}: {}", resource.path, e))?;
+    let preamble_id = resource
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("preamble"))
+        .and_then(|v| v.as_str());
+    let content = match preamble_id {
+        Some(preamble_id) => format!(
+            "{}\n\\begin{{document}}\n{}\n\\end{{document}}",
+            document_builder::resolve_preamble(db, preamble_id).await?,
+            body
+        ),
+        None => body,
+    };
+    Ok(PandocSource {
+        content,
+        dir: folder_of(Path::new(&resource.path)),
+    })
+}
+
+pub fn file_source(path: &str) -> Result<PandocSource, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    Ok(PandocSource {
+        content,
+        dir: folder_of(Path::new(path)),
+    })
+}
+
+fn folder_of(path: &Path) -> PathBuf {
+    path.parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_path_buf()
+}
+
+/// Command-line arguments for `request`, reading LaTeX from stdin
+fn arguments(request: &PandocRequest) -> Vec<String> {
+    let mut args = vec![
+        "--from=latex".to_string(),
+        format!("--to={}", request.format.writer()),
+        "--standalone".to_string(),
+    ];
+    if request.format == PandocFormat::Html {
+        args.push("--mathjax".to_string());
+    }
+    if let Some(template) = &request.template {
+        args.push(match request.format {
+            PandocFormat::Docx => format!("--reference-doc={}", template),
+            _ => format!("--template={}", template),
+        });
+    }
+    if let Some(bibliography) = &request.bibliography {
+        args.push("--citeproc".to_string());
+        args.push(format!("--bibliography={}", bibliography));
+        if let Some(csl) = &request.csl {
+            args.push(format!("--csl={}", csl));
+        }
+    }
+    args.push(format!("--output={}", request.output_path));
+    args
+}
+
+/// `[WARNING]` messages from pandoc's stderr; indented lines continue the
+/// message before them
+fn parse_warnings(stderr: &str) -> Vec<String> {
+    let mut warnings: Vec<String> = Vec::new();
+    let mut in_warning = false;
+    for line in stderr.lines() {
+        if let Some(message) = line.strip_prefix("[WARNING]") {
+            warnings.push(message.trim().to_string());
+            in_warning = true;
+        } else if in_warning && line.starts_with([' ', '\t']) {
+            if let Some(last) = warnings.last_mut() {
+                last.push(' ');
+                last.push_str(line.trim());
+            }
+        } else {
+            in_warning = false;
+        }
+    }
+    warnings
+}
+
+/// Run pandoc on `source`. Blocks until it is done.
+pub fn convert(source: &PandocSource, request: &PandocRequest) -> Result<PandocResult, String> {
+    let pandoc =
+        find_pandoc().ok_or("pandoc was not found; install it or set its path in the settings")?;
+    let mut child = Command::new(&pandoc)
+        .args(arguments(request))
+        .current_dir(&source.dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", pandoc.display(), e))?;
+    child
+        .stdin
+        .take()
+        .ok_or("Failed to open pandoc's input")?
+        .write_all(source.content.as_bytes())
+        .map_err(|e| format!("Failed to pass the document to pandoc: {}", e))?;
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(format!("pandoc failed: {}", stderr.trim()));
+    }
+    Ok(PandocResult {
+        output_path: request.output_path.clone(),
+        warnings: parse_warnings(&stderr),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arguments() {
+        let mut request = PandocRequest {
+            resource_id: None,
+            path: Some("ch1.tex".to_string()),
+            format: PandocFormat::Docx,
+            output_path: "/out/ch1.docx".to_string(),
+            template: Some("ref.docx".to_string()),
+            bibliography: Some("refs.bib".to_string()),
+            csl: None,
+        };
+        assert_eq!(
+            arguments(&request),
+            [
+                "--from=latex",
+                "--to=docx",
+                "--standalone",
+                "--reference-doc=ref.docx",
+                "--citeproc",
+                "--bibliography=refs.bib",
+                "--output=/out/ch1.docx",
+            ]
+        );
+
+        request.format = PandocFormat::Html;
+        request.bibliography = None;
+        let args = arguments(&request);
+        assert!(args.contains(&"--mathjax".to_string()));
+        assert!(args.contains(&"--template=ref.docx".to_string()));
+        assert!(!args.contains(&"--citeproc".to_string()));
+    }
+
+    #[test]
+    fn test_parse_warnings() {
+        let stderr = "[WARNING] Could not convert TeX math \\foo, rendering as TeX:\n  \\foo\n[INFO] Loaded refs.bib\n[WARNING] Citeproc: citation knuth not found\n";
+        assert_eq!(
+            parse_warnings(stderr),
+            [
+                "Could not convert TeX math \\foo, rendering as TeX: \\foo",
+                "Citeproc: citation knuth not found",
+            ]
+        );
+    }
+}
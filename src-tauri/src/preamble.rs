@@ -0,0 +1,326 @@
+//! Preamble / Package Manager
+//!
+//! Each collection can store a shared preamble (document class, packages in
+//! load order, macros) in `collection_preambles` (migration 023). Assembled
+//! documents use it through the `collection:<name>` preamble id, and
+//! `analyze_packages` works out which packages a set of exercises actually
+//! needs, from their explicit `\usepackage` lines (dependency scanner) and the
+//! commands and environments they use.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::BTreeMap;
+
+use crate::database::DatabaseManager;
+use crate::dependency_scanner;
+
+/// Commands / environments and the package that provides them
+const COMMAND_PACKAGES: &[(&str, &str)] = &[
+    ("\\includegraphics", "graphicx"),
+    ("\\begin{align", "amsmath"),
+    ("\\begin{gather", "amsmath"),
+    ("\\begin{multline", "amsmath"),
+    ("\\begin{cases}", "amsmath"),
+    ("\\begin{pmatrix}", "amsmath"),
+    ("\\begin{bmatrix}", "amsmath"),
+    ("\\text{", "amsmath"),
+    ("\\dfrac", "amsmath"),
+    ("\\tfrac", "amsmath"),
+    ("\\mathbb", "amssymb"),
+    ("\\mathfrak", "amssymb"),
+    ("\\begin{theorem}", "amsthm"),
+    ("\\begin{proof}", "amsthm"),
+    ("\\begin{tikzpicture}", "tikz"),
+    ("\\begin{axis}", "pgfplots"),
+    ("\\SI{", "siunitx"),
+    ("\\si{", "siunitx"),
+    ("\\qty{", "siunitx"),
+    ("\\num{", "siunitx"),
+    ("\\toprule", "booktabs"),
+    ("\\midrule", "booktabs"),
+    ("\\multirow", "multirow"),
+    ("\\begin{tabularx}", "tabularx"),
+    ("\\begin{longtable}", "longtable"),
+    ("\\textcolor", "xcolor"),
+    ("\\colorbox", "xcolor"),
+    ("\\href{", "hyperref"),
+    ("\\url{", "hyperref"),
+    ("\\cref{", "cleveref"),
+    ("\\Cref{", "cleveref"),
+    ("\\begin{enumerate}[", "enumitem"),
+    ("\\begin{itemize}[", "enumitem"),
+    ("\\begin{multicols}", "multicol"),
+    ("\\begin{wrapfigure}", "wrapfig"),
+    ("\\begin{subfigure}", "subcaption"),
+    ("\\begin{lstlisting}", "listings"),
+    ("\\begin{minted}", "minted"),
+    ("\\ce{", "mhchem"),
+    ("\\begin{circuitikz}", "circuitikz"),
+    ("\\uline{", "ulem"),
+    ("\\cancel{", "cancel"),
+];
+
+/// A package and its options
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageSpec {
+    pub name: String,
+    pub options: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionPreamble {
+    pub collection: String,
+    pub document_class: String,
+    pub class_options: Option<String>,
+    pub packages: Vec<PackageSpec>,
+    pub macros: Option<String>,
+}
+
+/// A package some of the analyzed resources need
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageUse {
+    pub name: String,
+    /// Ids of the resources that need it
+    pub used_by: Vec<String>,
+    /// Whether the collection preamble already loads it
+    pub in_preamble: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageReport {
+    pub required: Vec<PackageUse>,
+    /// Required packages the collection preamble doesn't load
+    pub missing: Vec<String>,
+    /// Preamble packages none of the resources were seen to need. Some are
+    /// needed implicitly (fonts, encodings), so this is only a hint.
+    pub not_detected: Vec<String>,
+}
+
+impl CollectionPreamble {
+    fn default_for(collection: &str) -> Self {
+        CollectionPreamble {
+            collection: collection.to_string(),
+            document_class: "article".to_string(),
+            class_options: None,
+            packages: ["inputenc", "amsmath", "amssymb"]
+                .iter()
+                .map(|name| PackageSpec {
+                    name: name.to_string(),
+                    options: (*name == "inputenc").then(|| "utf8".to_string()),
+                })
+                .collect(),
+            macros: None,
+        }
+    }
+
+    /// The preamble as LaTeX, up to but not including `\begin{document}`
+    pub fn render(&self) -> String {
+        let options = |o: &Option<String>| match o.as_deref().map(str::trim) {
+            Some(o) if !o.is_empty() => format!("[{}]", o),
+            _ => String::new(),
+        };
+        let mut out = format!(
+            "\\documentclass{}{{{}}}\n",
+            options(&self.class_options),
+            self.document_class
+        );
+        for package in &self.packages {
+            out.push_str(&format!(
+                "\\usepackage{}{{{}}}\n",
+                options(&package.options),
+                package.name
+            ));
+        }
+        if let Some(macros) = self.macros.as_deref().filter(|m| !m.trim().is_empty()) {
+            out.push('\n');
+            out.push_str(macros.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn is_name(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// The stored preamble of a collection, or the default one
+pub async fn get(pool: &Pool<Sqlite>, collection: &str) -> Result<CollectionPreamble, String> {
+    let row = sqlx::query("SELECT * FROM collection_preambles WHERE collection = ?")
+        .bind(collection)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(match row {
+        Some(row) => {
+            let packages: String = row.get("packages");
+            CollectionPreamble {
+                collection: row.get("collection"),
+                document_class: row.get("document_class"),
+                class_options: row.get("class_options"),
+                packages: serde_json::from_str(&packages).unwrap_or_default(),
+                macros: row.get("macros"),
+            }
+        }
+        None => CollectionPreamble::default_for(collection),
+    })
+}
+
+pub async fn save(pool: &Pool<Sqlite>, preamble: &CollectionPreamble) -> Result<(), String> {
+    if !is_name(&preamble.document_class) {
+        return Err(format!(
+            "Invalid document class: {}",
+            preamble.document_class
+        ));
+    }
+    let mut seen = Vec::new();
+    for package in &preamble.packages {
+        if !is_name(&package.name) {
+            return Err(format!("Invalid package name: {}", package.name));
+        }
+        // Loading a package twice with different options is an "option clash"
+        if seen.contains(&package.name) {
+            return Err(format!("Package '{}' is listed twice", package.name));
+        }
+        seen.push(package.name.clone());
+    }
+
+    let packages = serde_json::to_string(&preamble.packages).map_err(|e| e.to_string())?;
+    sqlx::query(
+        "INSERT INTO collection_preambles (collection, document_class, class_options, packages, macros, updated_at)
+         VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+         ON CONFLICT(collection) DO UPDATE SET
+            document_class = excluded.document_class,
+            class_options = excluded.class_options,
+            packages = excluded.packages,
+            macros = excluded.macros,
+            updated_at = CURRENT_TIMESTAMP",
+    )
+    .bind(&preamble.collection)
+    .bind(&preamble.document_class)
+    .bind(&preamble.class_options)
+    .bind(packages)
+    .bind(&preamble.macros)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Packages a LaTeX source needs: its own `\usepackage` lines plus the
+/// packages behind the commands and environments it uses
+pub fn packages_used(content: &str) -> Vec<String> {
+    let stripped = dependency_scanner::strip_comments(content);
+    let mut packages: Vec<String> = Vec::new();
+    let mut add = |name: &str| {
+        if !packages.iter().any(|p| p == name) {
+            packages.push(name.to_string());
+        }
+    };
+
+    for reference in dependency_scanner::extract_references(&stripped) {
+        match reference.relation {
+            "package" => add(&reference.target),
+            "graphics" => add("graphicx"),
+            _ => {}
+        }
+    }
+    for (command, package) in COMMAND_PACKAGES {
+        if stripped.contains(command) {
+            add(package);
+        }
+    }
+    packages
+}
+
+/// Which packages the given resources need, compared with the preamble of
+/// `collection` (when given)
+pub async fn analyze_packages(
+    db: &DatabaseManager,
+    resource_ids: &[String],
+    collection: Option<&str>,
+) -> Result<PackageReport, String> {
+    let mut used: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for id in resource_ids {
+        let Some(resource) = db.get_resource_by_id(id).await? else {
+            continue;
+        };
+        let Ok(content) = std::fs::read_to_string(&resource.path) else {
+            continue;
+        };
+        for package in packages_used(&content) {
+            used.entry(package).or_default().push(resource.id.clone());
+        }
+    }
+
+    let loaded: Vec<String> = match collection {
+        Some(collection) => get(&db.pool, collection)
+            .await?
+            .packages
+            .into_iter()
+            .map(|p| p.name)
+            .collect(),
+        None => Vec::new(),
+    };
+    // tikz is loaded by pgfplots, amsmath by mathtools, ...
+    let provided = |name: &str| {
+        loaded.iter().any(|l| l == name)
+            || (name == "tikz" && loaded.iter().any(|l| l == "pgfplots" || l == "circuitikz"))
+            || (name == "amsmath" && loaded.iter().any(|l| l == "mathtools"))
+            || (name == "graphicx" && loaded.iter().any(|l| l == "tikz"))
+    };
+
+    let required: Vec<PackageUse> = used
+        .into_iter()
+        .map(|(name, used_by)| PackageUse {
+            in_preamble: provided(&name),
+            name,
+            used_by,
+        })
+        .collect();
+    let missing = required
+        .iter()
+        .filter(|p| !p.in_preamble)
+        .map(|p| p.name.clone())
+        .collect();
+    let not_detected = loaded
+        .iter()
+        .filter(|l| !required.iter().any(|r| &r.name == *l))
+        .cloned()
+        .collect();
+
+    Ok(PackageReport {
+        required,
+        missing,
+        not_detected,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packages_used() {
+        let src = "\\usepackage{tikz}\n\\[\\mathbb{R}\\]\n\\includegraphics{a}\n% \\SI{3}{m}";
+        assert_eq!(packages_used(src), vec!["tikz", "graphicx", "amssymb"]);
+    }
+
+    #[test]
+    fn test_render() {
+        let mut preamble = CollectionPreamble::default_for("Algebra");
+        preamble.class_options = Some("11pt".to_string());
+        preamble.macros = Some("\\newcommand{\\R}{\\mathbb{R}}".to_string());
+        assert_eq!(
+            preamble.render(),
+            "\\documentclass[11pt]{article}\n\\usepackage[utf8]{inputenc}\n\\usepackage{amsmath}\n\\usepackage{amssymb}\n\n\\newcommand{\\R}{\\mathbb{R}}\n"
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Project
+//!
+//! Opening a project folder points the backend at it: the folder becomes
+//! the active root in `AppState`, the collections that live in it are
+//! re-indexed, its Git repository is detected and texlab is restarted with
+//! it as the workspace root. `open_project` in `lib.rs` does the work with
+//! the helpers here and answers with a `ProjectInfo`.
+
+use serde::Serialize;
+use std::path::Path;
+
+use crate::database::entities::Collection;
+use crate::git::GitRepoInfo;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectInfo {
+    pub root: String,
+    pub name: String,
+    /// Collections stored in the folder, being re-indexed in the background
+    pub collections: Vec<String>,
+    pub git: Option<GitRepoInfo>,
+    /// False when the database didn't come up in time; nothing is indexed
+    pub database_ready: bool,
+    /// Workspace root handed to texlab
+    pub root_uri: String,
+    /// Whether a running texlab was restarted on the new root
+    pub lsp_restarted: bool,
+}
+
+/// `file://` URI of an absolute path, as texlab expects it
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let encoded: String = path
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            // Keep Windows drive letters readable (file:///C:/...)
+            b':' => ":".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    if encoded.starts_with('/') {
+        format!("file://{}", encoded)
+    } else {
+        format!("file:///{}", encoded)
+    }
+}
+
+/// The collections whose folder is `root` or inside it, as (name, folder)
+pub fn collections_in(root: &Path, collections: Vec<Collection>) -> Vec<(String, String)> {
+    collections
+        .into_iter()
+        .filter_map(|c| {
+            let path = c.path?;
+            // `root` is canonical; compare like with like
+            let canonical = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone().into());
+            canonical.starts_with(root).then_some((c.name, path))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collection(name: &str, path: Option<&str>) -> Collection {
+        Collection {
+            name: name.to_string(),
+            description: None,
+            icon: None,
+            kind: "files".to_string(),
+            path: path.map(str::to_string),
+            created_at: None,
+        }
+    }
+
+    #[test]
+    fn test_path_to_uri() {
+        assert_eq!(
+            path_to_uri(Path::new("/home/me/My Notes/ch1")),
+            "file:///home/me/My%20Notes/ch1"
+        );
+        assert_eq!(
+            path_to_uri(Path::new("C:\\Users\\me\\θέματα")),
+            "file:///C:/Users/me/%CE%B8%CE%AD%CE%BC%CE%B1%CF%84%CE%B1"
+        );
+    }
+
+    #[test]
+    fn test_collections_in() {
+        let collections = vec![
+            collection("root", Some("/work/algebra")),
+            collection("nested", Some("/work/algebra/exercises")),
+            collection("sibling", Some("/work/algebra-old")),
+            collection("virtual", None),
+        ];
+        let names: Vec<String> = collections_in(Path::new("/work/algebra"), collections)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["root", "nested"]);
+    }
+}
@@ -0,0 +1,505 @@
+//! Quick Open
+//!
+//! Ctrl+P style switcher over every resource. Each word of the query has to
+//! appear, in order but not necessarily together, in the file name, title,
+//! tags or path of a resource; matches at word starts and runs of adjacent
+//! characters score higher, as do file names and titles over tags and paths.
+//! Resources opened often and lately get a frecency bonus from the usage log
+//! (`resource_opens`, migration 031), so with an empty query the switcher
+//! lists recently used resources.
+//!
+//! The candidates are read from the database once and kept until a resource
+//! changes, so matching on every keystroke stays in memory.
+
+use serde::Serialize;
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Opens per resource kept in the usage log
+const KEPT_OPENS: i64 = 10;
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 200;
+
+const MATCH: i32 = 16;
+const BOUNDARY: i32 = 10;
+const CONSECUTIVE: i32 = 8;
+const GAP: i32 = 3;
+const GAP_EXTENSION: i32 = 1;
+/// Bonus when the query is the whole file name, extension aside
+const EXACT_NAME: i32 = 50;
+/// Frecency bonus per unit of `ln(1 + frecency)`
+const FRECENCY_WEIGHT: f64 = 10.0;
+
+/// Open times (Unix timestamps) per resource id
+type UsageLog = HashMap<String, Vec<i64>>;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickOpenItem {
+    pub id: String,
+    pub path: String,
+    /// File name, as shown in the list
+    pub name: String,
+    pub title: Option<String>,
+    pub collection: String,
+    pub kind: String,
+    pub score: f64,
+    /// Character positions in `name` and `title` that matched, for highlighting
+    pub name_positions: Vec<usize>,
+    pub title_positions: Vec<usize>,
+}
+
+/// Text of one searchable field, with a lowercase copy of the same length
+struct Field {
+    chars: Vec<char>,
+    lower: Vec<char>,
+}
+
+impl Field {
+    fn new(text: &str) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        let lower = chars
+            .iter()
+            .map(|c| c.to_lowercase().next().unwrap_or(*c))
+            .collect();
+        Field { chars, lower }
+    }
+
+    /// Whether a match at `i` starts a word
+    fn boundary(&self, i: usize) -> bool {
+        if i == 0 {
+            return true;
+        }
+        let (prev, c) = (self.chars[i - 1], self.chars[i]);
+        matches!(prev, '/' | '\\' | '_' | '-' | '.' | ' ' | ':')
+            || (prev.is_lowercase() && c.is_uppercase())
+            || (!prev.is_ascii_digit() && c.is_ascii_digit())
+    }
+}
+
+struct Candidate {
+    id: String,
+    path: String,
+    title: Option<String>,
+    collection: String,
+    kind: String,
+    name: Field,
+    title_field: Field,
+    tags: Field,
+    path_field: Field,
+}
+
+impl Candidate {
+    fn new(
+        id: String,
+        path: String,
+        title: Option<String>,
+        collection: String,
+        kind: String,
+        tags: &str,
+    ) -> Self {
+        let name = std::path::Path::new(&path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.clone());
+        Candidate {
+            name: Field::new(&name),
+            title_field: Field::new(title.as_deref().unwrap_or("")),
+            tags: Field::new(tags),
+            path_field: Field::new(&path),
+            id,
+            path,
+            title,
+            collection,
+            kind,
+        }
+    }
+}
+
+/// Score of `query` (lowercase) as a subsequence of `field`, and the
+/// positions it matched. Every occurrence of the first character is tried
+/// as a start, so a later match at a word boundary beats an earlier one
+/// in the middle of a word.
+fn match_field(query: &[char], field: &Field) -> Option<(i32, Vec<usize>)> {
+    let first = *query.first()?;
+    let mut best: Option<(i32, Vec<usize>)> = None;
+    for start in (0..field.lower.len()).filter(|&i| field.lower[i] == first) {
+        let mut positions = Vec::with_capacity(query.len());
+        let mut next = start;
+        for &q in query {
+            match (next..field.lower.len()).find(|&i| field.lower[i] == q) {
+                Some(found) => {
+                    positions.push(found);
+                    next = found + 1;
+                }
+                None => break,
+            }
+        }
+        if positions.len() < query.len() {
+            // Later starts leave even less room
+            break;
+        }
+        let score = score_positions(field, &positions);
+        if best.as_ref().is_none_or(|(b, _)| score > *b) {
+            best = Some((score, positions));
+        }
+    }
+    best
+}
+
+fn score_positions(field: &Field, positions: &[usize]) -> i32 {
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    for &i in positions {
+        score += MATCH;
+        if field.boundary(i) {
+            score += BOUNDARY;
+        }
+        match previous {
+            Some(p) if i == p + 1 => score += CONSECUTIVE,
+            Some(p) => score -= GAP + GAP_EXTENSION * (i - p - 2).min(10) as i32,
+            None => {}
+        }
+        previous = Some(i);
+    }
+    score
+}
+
+/// Firefox-style frecency: each recent open counts for more the more
+/// recent it is. `opens` are Unix timestamps.
+fn frecency(opens: &[i64], now: i64) -> f64 {
+    opens
+        .iter()
+        .map(|&opened| match (now - opened).max(0) / 86_400 {
+            0..=4 => 100.0,
+            5..=14 => 70.0,
+            15..=31 => 50.0,
+            32..=90 => 30.0,
+            _ => 10.0,
+        })
+        .sum()
+}
+
+/// Which field a query word matched best in, and where
+enum Hit {
+    Name(Vec<usize>),
+    Title(Vec<usize>),
+    Other,
+}
+
+/// Match score of a candidate for all query words, or `None` if some word
+/// matches nowhere
+fn match_candidate(words: &[Vec<char>], candidate: &Candidate) -> Option<(i32, Vec<Hit>)> {
+    let mut total = 0;
+    let mut hits = Vec::with_capacity(words.len());
+    for word in words {
+        // Weights in percent: names and titles over tags over paths
+        let fields = [
+            (&candidate.name, 100),
+            (&candidate.title_field, 100),
+            (&candidate.tags, 80),
+            (&candidate.path_field, 60),
+        ];
+        // Reversed, so that on a tie the first of the fields wins
+        let (score, hit) = fields
+            .iter()
+            .enumerate()
+            .rev()
+            .filter_map(|(index, (field, weight))| {
+                let (score, positions) = match_field(word, field)?;
+                let hit = match index {
+                    0 => Hit::Name(positions),
+                    1 => Hit::Title(positions),
+                    _ => Hit::Other,
+                };
+                Some((score * weight / 100, hit))
+            })
+            .max_by_key(|(score, _)| *score)?;
+        total += score;
+        hits.push(hit);
+    }
+    Some((total, hits))
+}
+
+fn rank(
+    candidates: &[Candidate],
+    opens: &UsageLog,
+    query: &str,
+    limit: usize,
+    now: i64,
+) -> Vec<QuickOpenItem> {
+    let words: Vec<Vec<char>> = query
+        .split_whitespace()
+        .map(|w| {
+            w.chars()
+                .map(|c| c.to_lowercase().next().unwrap_or(c))
+                .collect()
+        })
+        .collect();
+    let whole: String = query.trim().to_lowercase();
+
+    let mut items: Vec<QuickOpenItem> = candidates
+        .iter()
+        .filter_map(|candidate| {
+            let bonus = opens
+                .get(&candidate.id)
+                .map_or(0.0, |o| frecency(o, now).ln_1p() * FRECENCY_WEIGHT);
+            if words.is_empty() {
+                // Nothing typed: recently used resources only
+                return (bonus > 0.0).then(|| item(candidate, bonus, Vec::new()));
+            }
+            let (mut score, hits) = match_candidate(&words, candidate)?;
+            let name: String = candidate.name.lower.iter().collect();
+            if name == whole || name.rsplit_once('.').is_some_and(|(stem, _)| stem == whole) {
+                score += EXACT_NAME;
+            }
+            Some(item(candidate, score as f64 + bonus, hits))
+        })
+        .collect();
+
+    items.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.path.len().cmp(&b.path.len()))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    items.truncate(limit);
+    items
+}
+
+fn item(candidate: &Candidate, score: f64, hits: Vec<Hit>) -> QuickOpenItem {
+    let mut name_positions = Vec::new();
+    let mut title_positions = Vec::new();
+    for hit in hits {
+        match hit {
+            Hit::Name(positions) => name_positions.extend(positions),
+            Hit::Title(positions) => title_positions.extend(positions),
+            Hit::Other => {}
+        }
+    }
+    name_positions.sort_unstable();
+    name_positions.dedup();
+    title_positions.sort_unstable();
+    title_positions.dedup();
+    QuickOpenItem {
+        id: candidate.id.clone(),
+        path: candidate.path.clone(),
+        name: candidate.name.chars.iter().collect(),
+        title: candidate.title.clone(),
+        collection: candidate.collection.clone(),
+        kind: candidate.kind.clone(),
+        score,
+        name_positions,
+        title_positions,
+    }
+}
+
+/// Candidates and the usage log, loaded on first use. Resource changes
+/// (see `resources::emit_changed`) and metadata saves drop the candidates;
+/// opens recorded through `record_open` are added to the loaded log as they
+/// happen.
+#[derive(Default)]
+pub struct QuickOpenCache {
+    candidates: std::sync::Mutex<Option<Arc<Vec<Candidate>>>>,
+    /// Bumped by `invalidate`, so a load that raced with it isn't kept
+    generation: AtomicU64,
+    opens: std::sync::Mutex<Option<Arc<UsageLog>>>,
+}
+
+impl QuickOpenCache {
+    /// Reload the candidates on the next search
+    pub fn invalidate(&self) {
+        if let Ok(mut candidates) = self.candidates.lock() {
+            self.generation.fetch_add(1, Ordering::SeqCst);
+            *candidates = None;
+        }
+    }
+
+    async fn candidates(&self, pool: &Pool<Sqlite>) -> Result<Arc<Vec<Candidate>>, String> {
+        let generation = self.generation.load(Ordering::SeqCst);
+        if let Some(candidates) = self.candidates.lock().ok().and_then(|c| c.clone()) {
+            return Ok(candidates);
+        }
+        let rows = sqlx::query(
+            "SELECT r.id, r.path, r.title, r.collection, r.type, t.tags
+             FROM resources r
+             LEFT JOIN (SELECT resource_id, group_concat(tag, ' ') AS tags
+                        FROM resource_tags GROUP BY resource_id) t ON t.resource_id = r.id",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        let candidates: Arc<Vec<Candidate>> = Arc::new(
+            rows.iter()
+                .map(|row| {
+                    let tags: Option<String> = row.get("tags");
+                    Candidate::new(
+                        row.get("id"),
+                        row.get("path"),
+                        row.get("title"),
+                        row.get("collection"),
+                        row.get("type"),
+                        tags.as_deref().unwrap_or(""),
+                    )
+                })
+                .collect(),
+        );
+        if let Ok(mut cached) = self.candidates.lock() {
+            if self.generation.load(Ordering::SeqCst) == generation {
+                *cached = Some(candidates.clone());
+            }
+        }
+        Ok(candidates)
+    }
+
+    async fn opens(&self, pool: &Pool<Sqlite>) -> Result<Arc<UsageLog>, String> {
+        if let Some(opens) = self.opens.lock().ok().and_then(|o| o.clone()) {
+            return Ok(opens);
+        }
+        let rows = sqlx::query(
+            "SELECT resource_id, CAST(strftime('%s', opened_at) AS INTEGER) AS opened
+             FROM resource_opens",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        let mut opens = UsageLog::new();
+        for row in rows {
+            opens
+                .entry(row.get("resource_id"))
+                .or_default()
+                .push(row.get("opened"));
+        }
+        let opens = Arc::new(opens);
+        if let Ok(mut cached) = self.opens.lock() {
+            *cached = Some(opens.clone());
+        }
+        Ok(opens)
+    }
+}
+
+/// The best `limit` resources for `query`
+pub async fn quick_open(
+    pool: &Pool<Sqlite>,
+    cache: &QuickOpenCache,
+    query: &str,
+    limit: Option<usize>,
+) -> Result<Vec<QuickOpenItem>, String> {
+    let candidates = cache.candidates(pool).await?;
+    let opens = cache.opens(pool).await?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let now = chrono::Utc::now().timestamp();
+    Ok(rank(&candidates, &opens, query, limit, now))
+}
+
+/// Add an open of `resource_id` to the usage log, keeping only its most
+/// recent opens
+pub async fn record_open(
+    pool: &Pool<Sqlite>,
+    cache: &QuickOpenCache,
+    resource_id: &str,
+) -> Result<(), String> {
+    sqlx::query("INSERT INTO resource_opens (resource_id) VALUES (?)")
+        .bind(resource_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    sqlx::query(
+        "DELETE FROM resource_opens WHERE resource_id = ?1 AND rowid NOT IN
+            (SELECT rowid FROM resource_opens WHERE resource_id = ?1
+             ORDER BY opened_at DESC, rowid DESC LIMIT ?2)",
+    )
+    .bind(resource_id)
+    .bind(KEPT_OPENS)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if let Ok(mut cached) = cache.opens.lock() {
+        if let Some(opens) = cached.as_mut() {
+            let entry = Arc::make_mut(opens)
+                .entry(resource_id.to_string())
+                .or_default();
+            entry.push(chrono::Utc::now().timestamp());
+            let excess = entry.len().saturating_sub(KEPT_OPENS as usize);
+            entry.drain(..excess);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: &str, path: &str, title: Option<&str>, tags: &str) -> Candidate {
+        Candidate::new(
+            id.to_string(),
+            path.to_string(),
+            title.map(str::to_string),
+            "exercises".to_string(),
+            "file".to_string(),
+            tags,
+        )
+    }
+
+    #[test]
+    fn test_fuzzy_ranking() {
+        let candidates = [
+            candidate("1", "/work/domain/notes.tex", None, ""),
+            candidate("2", "/work/main.tex", None, ""),
+            candidate(
+                "3",
+                "/work/limits/ex1.tex",
+                Some("Limits of sequences"),
+                "calculus",
+            ),
+            candidate("4", "/work/limits/ex2.tex", None, "calculus series"),
+        ];
+        let opens = HashMap::new();
+        let ids = |query: &str| -> Vec<String> {
+            rank(&candidates, &opens, query, 10, 0)
+                .into_iter()
+                .map(|i| i.id)
+                .collect()
+        };
+
+        // A file name beats the same letters inside a folder name
+        assert_eq!(ids("main")[0], "2");
+        // Every word must match somewhere: title, tags or path
+        assert_eq!(ids("seq lim"), ["3"]);
+        assert_eq!(ids("calc ser"), ["4"]);
+        assert!(ids("xyz").is_empty());
+
+        let items = rank(&candidates, &opens, "mt", 10, 0);
+        assert_eq!(items[0].id, "2");
+        assert_eq!(items[0].name_positions, [0, 5]);
+        // Nothing typed and nothing opened yet: nothing to show
+        assert!(rank(&candidates, &opens, "", 10, 0).is_empty());
+    }
+
+    #[test]
+    fn test_frecency() {
+        let day = 86_400;
+        let now = 100 * day;
+        assert_eq!(frecency(&[now - day, now - 20 * day], now), 150.0);
+        assert_eq!(frecency(&[now - 99 * day], now), 10.0);
+
+        let candidates = [
+            candidate("1", "/work/ex1.tex", None, ""),
+            candidate("2", "/work/ex2.tex", None, ""),
+        ];
+        let mut opens = HashMap::new();
+        opens.insert("2".to_string(), vec![now - day, now - 2 * day]);
+        let ids: Vec<String> = rank(&candidates, &opens, "ex", 10, now)
+            .into_iter()
+            .map(|i| i.id)
+            .collect();
+        assert_eq!(ids, ["2", "1"]);
+        let recent = rank(&candidates, &opens, " ", 10, now);
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].id, "2");
+    }
+}
@@ -0,0 +1,186 @@
+//! Recent Projects
+//!
+//! Project folders the user opened, most recent first, kept in
+//! `recent_projects.json` in the app data directory so the start screen
+//! can offer them before the database is up. Pinned projects stay on the
+//! list for good; the others are capped at `MAX_UNPINNED`.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Unpinned projects kept; the oldest ones drop off
+const MAX_UNPINNED: usize = 15;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentProject {
+    pub path: String,
+    /// RFC 3339
+    pub last_opened: String,
+    #[serde(default)]
+    pub pinned: bool,
+    /// Whether the folder is still there, checked when listing
+    #[serde(default, skip_deserializing)]
+    pub exists: bool,
+}
+
+/// Serializes read-modify-write cycles on the file
+static LOCK: Mutex<()> = Mutex::new(());
+
+pub fn recent_projects_path() -> Result<PathBuf, String> {
+    let proj_dirs = directories::ProjectDirs::from("", "", "datatex")
+        .ok_or("Could not determine project directories")?;
+    Ok(proj_dirs.data_dir().join("recent_projects.json"))
+}
+
+/// The stored list; empty when there is no file or it can't be read
+fn load(file: &Path) -> Vec<RecentProject> {
+    fs::read_to_string(file)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save(file: &Path, projects: &[RecentProject]) -> Result<(), String> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(projects).map_err(|e| e.to_string())?;
+    let temp = file.with_extension("json.tmp");
+    fs::write(&temp, json).map_err(|e| e.to_string())?;
+    fs::rename(&temp, file).map_err(|e| e.to_string())
+}
+
+/// Pinned first, then by last opened, each with `exists` filled in
+fn sorted(mut projects: Vec<RecentProject>) -> Vec<RecentProject> {
+    projects.sort_by(|a, b| {
+        b.pinned
+            .cmp(&a.pinned)
+            .then_with(|| b.last_opened.cmp(&a.last_opened))
+    });
+    for project in &mut projects {
+        project.exists = Path::new(&project.path).is_dir();
+    }
+    projects
+}
+
+/// Load, change and save the list under the lock, returning the new list
+fn modify(
+    file: &Path,
+    change: impl FnOnce(&mut Vec<RecentProject>) -> Result<(), String>,
+) -> Result<Vec<RecentProject>, String> {
+    let _guard = LOCK.lock().map_err(|e| e.to_string())?;
+    let mut projects = load(file);
+    change(&mut projects)?;
+    let projects = sorted(projects);
+    save(file, &projects)?;
+    Ok(projects)
+}
+
+pub fn list(file: &Path) -> Vec<RecentProject> {
+    let _guard = LOCK.lock();
+    sorted(load(file))
+}
+
+/// Move `path` to the top of the list, keeping its pin
+pub fn record(file: &Path, path: &str) -> Result<Vec<RecentProject>, String> {
+    if !Path::new(path).is_dir() {
+        return Err(format!("Project folder not found: {}", path));
+    }
+    modify(file, |projects| {
+        let pinned = projects.iter().any(|p| p.path == path && p.pinned);
+        projects.retain(|p| p.path != path);
+        projects.insert(
+            0,
+            RecentProject {
+                path: path.to_string(),
+                last_opened: Utc::now().to_rfc3339(),
+                pinned,
+                exists: true,
+            },
+        );
+        // Entries are newest first here, so the oldest unpinned go
+        let mut unpinned = 0;
+        projects.retain(|p| {
+            if p.pinned {
+                return true;
+            }
+            unpinned += 1;
+            unpinned <= MAX_UNPINNED
+        });
+        Ok(())
+    })
+}
+
+pub fn set_pinned(file: &Path, path: &str, pinned: bool) -> Result<Vec<RecentProject>, String> {
+    modify(file, |projects| {
+        let project = projects
+            .iter_mut()
+            .find(|p| p.path == path)
+            .ok_or_else(|| format!("Not a recent project: {}", path))?;
+        project.pinned = pinned;
+        Ok(())
+    })
+}
+
+pub fn remove(file: &Path, path: &str) -> Result<Vec<RecentProject>, String> {
+    modify(file, |projects| {
+        projects.retain(|p| p.path != path);
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_projects() {
+        let root = std::env::temp_dir().join(format!("datatex-recent-{}", uuid::Uuid::new_v4()));
+        let file = root.join("recent_projects.json");
+        let folders: Vec<String> = (0..MAX_UNPINNED + 2)
+            .map(|i| {
+                let folder = root.join(format!("project{}", i));
+                fs::create_dir_all(&folder).unwrap();
+                folder.to_string_lossy().to_string()
+            })
+            .collect();
+
+        assert!(list(&file).is_empty());
+        assert!(record(&file, &root.join("missing").to_string_lossy()).is_err());
+
+        record(&file, &folders[0]).unwrap();
+        set_pinned(&file, &folders[0], true).unwrap();
+        for folder in &folders[1..] {
+            record(&file, folder).unwrap();
+        }
+
+        // The pinned project survives the cap and stays on top
+        let projects = list(&file);
+        assert_eq!(projects.len(), MAX_UNPINNED + 1);
+        assert_eq!(projects[0].path, folders[0]);
+        assert!(projects[0].pinned);
+        assert_eq!(projects[1].path, folders[MAX_UNPINNED + 1]);
+        assert!(!projects.iter().any(|p| p.path == folders[1]));
+
+        // Reopening keeps the pin
+        record(&file, &folders[0]).unwrap();
+        assert!(list(&file)[0].pinned);
+
+        fs::remove_dir_all(&folders[2]).unwrap();
+        let missing = list(&file)
+            .into_iter()
+            .find(|p| p.path == folders[2])
+            .unwrap();
+        assert!(!missing.exists);
+
+        let projects = remove(&file, &folders[2]).unwrap();
+        assert!(!projects.iter().any(|p| p.path == folders[2]));
+        assert!(set_pinned(&file, &folders[2], true).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,462 @@
+//! Resource CRUD Module
+//!
+//! Create, rename, move and delete resources so that the file on disk and the
+//! `resources` row never drift apart: the filesystem change happens first,
+//! then the database update, and the filesystem change is rolled back if the
+//! database update fails. A `resources-changed` event is emitted afterwards so
+//! the file tree and graph can refresh.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+use uuid::Uuid;
+
+use crate::database::entities::Resource;
+use crate::database::DatabaseManager;
+use crate::dependency_scanner;
+use crate::search;
+
+/// Payload of the `resources-changed` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourcesChanged {
+    pub action: String, // "created" | "renamed" | "moved" | "deleted" | "updated"
+    pub ids: Vec<String>,
+    pub collection: Option<String>,
+}
+
+pub fn emit_changed(app: &AppHandle, action: &str, ids: Vec<String>, collection: Option<String>) {
+    // Resource changes go through here, so it also retires cached graphs
+    // and quick-open candidates; metadata, tag and collection commands
+    // invalidate them themselves
+    if let Some(state) = app.try_state::<crate::AppState>() {
+        state.graph_cache.invalidate();
+        state.quick_open_cache.invalidate();
+    }
+    let _ = app.emit(
+        "resources-changed",
+        ResourcesChanged {
+            action: action.to_string(),
+            ids,
+            collection,
+        },
+    );
+}
+
+/// Infer the resource kind from a file name's extension
+pub fn kind_from_path(path: &str) -> &'static str {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    match ext.as_str() {
+        "bib" => "bibliography",
+        "sty" => "package",
+        "cls" => "class",
+        "dtx" => "dtx",
+        "ins" => "ins",
+        "png" | "jpg" | "jpeg" | "pdf" | "svg" | "eps" => "figure",
+        _ => "file",
+    }
+}
+
+fn file_name_of(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Create a file on disk and register it as a resource
+pub async fn create(
+    db: &DatabaseManager,
+    path: &str,
+    collection: &str,
+    content: &str,
+    metadata: Option<serde_json::Value>,
+) -> Result<Resource, String> {
+    let file_path = Path::new(path);
+    if file_path.exists() {
+        return Err(format!("File already exists: {}", path));
+    }
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    // 1. Write file to disk
+    fs::write(file_path, content).map_err(|e| e.to_string())?;
+
+    // 2. Add to database (remove the file again if that fails)
+    let resource = Resource {
+        id: Uuid::new_v4().to_string(),
+        path: path.to_string(),
+        kind: kind_from_path(path).to_string(),
+        collection: collection.to_string(),
+        title: Some(file_name_of(file_path)),
+        content_hash: None,
+        metadata: Some(metadata.unwrap_or(serde_json::json!({}))),
+        created_at: None,
+        updated_at: None,
+    };
+
+    if let Err(e) = db.add_resource(&resource).await {
+        let _ = fs::remove_file(file_path);
+        return Err(e);
+    }
+
+    Ok(resource)
+}
+
+/// Move a resource's file (or folder) to `new_path` and update its row(s).
+/// For folders, every resource below the folder is re-pathed as well.
+async fn relocate(
+    db: &DatabaseManager,
+    resource: &Resource,
+    new_path: &Path,
+    new_collection: Option<&str>,
+) -> Result<Resource, String> {
+    let old_path = PathBuf::from(&resource.path);
+    if new_path == old_path {
+        // Nothing to move, but the collection may still change
+        let collection = new_collection.unwrap_or(&resource.collection).to_string();
+        if collection != resource.collection {
+            let title = resource
+                .title
+                .clone()
+                .unwrap_or_else(|| file_name_of(new_path));
+            db.relocate_resource(
+                &resource.id,
+                &resource.path,
+                &resource.path,
+                &title,
+                &collection,
+                resource.kind == "folder",
+            )
+            .await?;
+        }
+        return Ok(Resource {
+            collection,
+            ..resource.clone()
+        });
+    }
+    if new_path.exists() {
+        return Err(format!("Target already exists: {}", new_path.display()));
+    }
+    if let Some(parent) = new_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    // 1. Filesystem
+    fs::rename(&old_path, new_path).map_err(|e| format!("Failed to move file: {}", e))?;
+
+    // 2. Database (roll back the rename on failure)
+    let new_path_str = new_path.to_string_lossy().to_string();
+    let collection = new_collection.unwrap_or(&resource.collection).to_string();
+    let title = file_name_of(new_path);
+
+    let result = db
+        .relocate_resource(
+            &resource.id,
+            &resource.path,
+            &new_path_str,
+            &title,
+            &collection,
+            resource.kind == "folder",
+        )
+        .await;
+
+    if let Err(e) = result {
+        let _ = fs::rename(new_path, &old_path);
+        return Err(e);
+    }
+
+    Ok(Resource {
+        path: new_path_str,
+        title: Some(title),
+        collection,
+        ..resource.clone()
+    })
+}
+
+/// Rename a resource in place (same directory, new file name)
+pub async fn rename(db: &DatabaseManager, id: &str, new_name: &str) -> Result<Resource, String> {
+    if new_name.is_empty() || new_name.contains('/') || new_name.contains('\\') {
+        return Err(format!("Invalid file name: {}", new_name));
+    }
+    let resource = db
+        .get_resource_by_id(id)
+        .await?
+        .ok_or("Resource not found")?;
+
+    let new_path = Path::new(&resource.path)
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(new_name);
+
+    relocate(db, &resource, &new_path, None).await
+}
+
+/// Move a resource into another directory, optionally re-assigning its collection
+pub async fn move_to(
+    db: &DatabaseManager,
+    id: &str,
+    target_dir: &str,
+    target_collection: Option<&str>,
+) -> Result<Resource, String> {
+    let resource = db
+        .get_resource_by_id(id)
+        .await?
+        .ok_or("Resource not found")?;
+
+    let file_name = Path::new(&resource.path)
+        .file_name()
+        .ok_or("Invalid resource path")?;
+    let new_path = Path::new(target_dir).join(file_name);
+
+    if resource.kind == "folder" && new_path.starts_with(&resource.path) {
+        return Err("Cannot move a folder into itself".to_string());
+    }
+
+    relocate(db, &resource, &new_path, target_collection).await
+}
+
+/// After `moved` was renamed or moved away from `old_path`, rewrite the
+/// `\input`/`\includegraphics`/... paths that no longer lead to the same
+/// files: in the documents referencing it (or anything below it, for a
+/// folder) and in the moved sources themselves. Returns the ids of the
+/// rewritten sources; their dependency rows are rescanned.
+pub async fn fix_references(
+    db: &DatabaseManager,
+    old_path: &str,
+    moved: &Resource,
+) -> Result<Vec<String>, String> {
+    let old_root = PathBuf::from(old_path);
+    let new_root = PathBuf::from(&moved.path);
+    if old_root == new_root {
+        return Ok(Vec::new());
+    }
+    // Where something that was at `path` before the move is now
+    let current = |path: &Path| {
+        path.strip_prefix(&old_root)
+            .ok()
+            .map(|rest| match rest.as_os_str() {
+                r if r.is_empty() => new_root.clone(),
+                _ => new_root.join(rest),
+            })
+    };
+    let locate = |path: &Path| {
+        let now = current(path).unwrap_or_else(|| path.to_path_buf());
+        now.is_file().then_some(now)
+    };
+
+    let moved_resources: Vec<Resource> = if moved.kind == "folder" {
+        db.get_resources_by_collection(&moved.collection)
+            .await?
+            .into_iter()
+            .filter(|r| Path::new(&r.path).starts_with(&new_root))
+            .collect()
+    } else {
+        vec![moved.clone()]
+    };
+
+    let mut sources: HashMap<String, Resource> = HashMap::new();
+    for resource in &moved_resources {
+        if dependency_scanner::is_source(&resource.path) {
+            sources.insert(resource.id.clone(), resource.clone());
+        }
+        for dependent in db.get_dependents(&resource.id).await? {
+            sources.insert(dependent.id.clone(), dependent);
+        }
+    }
+
+    let roots: HashMap<String, String> = db
+        .get_collections()
+        .await?
+        .into_iter()
+        .filter_map(|c| Some((c.name, c.path?)))
+        .collect();
+
+    let mut rewritten = Vec::new();
+    for source in sources.into_values() {
+        let path = PathBuf::from(&source.path);
+        let Some(new_dir) = path.parent() else {
+            continue;
+        };
+        let old_dir = match path.strip_prefix(&new_root) {
+            Ok(rest) => old_root.join(rest).parent().map(Path::to_path_buf),
+            Err(_) => Some(new_dir.to_path_buf()),
+        };
+        let Some(old_dir) = old_dir else {
+            continue;
+        };
+        // Binary or unreadable files have no references to fix
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let root = roots.get(&source.collection).map(Path::new);
+        let Some(updated) =
+            dependency_scanner::rewrite_references(&content, &old_dir, new_dir, root, &locate)
+        else {
+            continue;
+        };
+
+        fs::write(&path, &updated)
+            .map_err(|e| format!("Failed to update {}: {}", path.display(), e))?;
+        search::fts::update_content(&db.pool, &source.id, &updated).await?;
+        dependency_scanner::update_dependencies(db, &source, &updated, root).await?;
+        rewritten.push(source.id);
+    }
+    Ok(rewritten)
+}
+
+/// Copy a file next to itself (`name-copy.tex`, `name-copy-2.tex`, ...) and
+/// register the copy in the same collection with the same metadata
+pub async fn duplicate(db: &DatabaseManager, id: &str) -> Result<Resource, String> {
+    let resource = db
+        .get_resource_by_id(id)
+        .await?
+        .ok_or("Resource not found")?;
+    if resource.kind == "folder" {
+        return Err("Only files can be duplicated".to_string());
+    }
+
+    let path = Path::new(&resource.path);
+    let stem = path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let target = (1..)
+        .map(|n| match n {
+            1 => path.with_file_name(format!("{}-copy{}", stem, ext)),
+            n => path.with_file_name(format!("{}-copy-{}{}", stem, n, ext)),
+        })
+        .find(|p| !p.exists())
+        .unwrap();
+
+    // 1. Filesystem
+    fs::copy(path, &target).map_err(|e| format!("Failed to copy file: {}", e))?;
+
+    // 2. Database (remove the copy again if that fails)
+    let copy = Resource {
+        id: Uuid::new_v4().to_string(),
+        path: target.to_string_lossy().to_string(),
+        title: Some(file_name_of(&target)),
+        created_at: None,
+        updated_at: None,
+        ..resource
+    };
+    if let Err(e) = db.add_resource(&copy).await {
+        let _ = fs::remove_file(&target);
+        return Err(e);
+    }
+
+    Ok(copy)
+}
+
+/// Delete a resource row and (optionally) its file or folder on disk.
+/// The file is first moved aside so it can be restored if the database
+/// delete fails. Returns the ids of all removed resources.
+pub async fn delete(
+    db: &DatabaseManager,
+    id: &str,
+    delete_file: bool,
+) -> Result<Vec<String>, String> {
+    let resource = db
+        .get_resource_by_id(id)
+        .await?
+        .ok_or("Resource not found")?;
+    let is_folder = resource.kind == "folder";
+    let path = PathBuf::from(&resource.path);
+
+    // 1. Move the file aside (only if it should go and actually exists)
+    let aside = if delete_file && path.exists() {
+        let aside = path.with_file_name(format!(
+            ".{}.deleting-{}",
+            file_name_of(&path),
+            Uuid::new_v4()
+        ));
+        fs::rename(&path, &aside).map_err(|e| format!("Failed to delete file: {}", e))?;
+        Some(aside)
+    } else {
+        None
+    };
+
+    // 2. Database
+    let removed = match db
+        .delete_resource_tree(&resource.id, &resource.path, is_folder)
+        .await
+    {
+        Ok(ids) => ids,
+        Err(e) => {
+            if let Some(aside) = &aside {
+                let _ = fs::rename(aside, &path);
+            }
+            return Err(e);
+        }
+    };
+
+    // 3. Drop the file for good
+    if let Some(aside) = aside {
+        let res = if is_folder {
+            fs::remove_dir_all(&aside)
+        } else {
+            fs::remove_file(&aside)
+        };
+        if let Err(e) = res {
+            eprintln!("Failed to remove {}: {}", aside.display(), e);
+        }
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_from_path() {
+        assert_eq!(kind_from_path("/a/exercise.tex"), "file");
+        assert_eq!(kind_from_path("refs.BIB"), "bibliography");
+        assert_eq!(kind_from_path("mystyle.sty"), "package");
+        assert_eq!(kind_from_path("plot.png"), "figure");
+        assert_eq!(kind_from_path("README"), "file");
+    }
+
+    #[test]
+    fn test_move_to_same_folder_changes_collection() {
+        let dir = std::env::temp_dir().join(format!("datatex-resources-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let db = DatabaseManager::new(&dir.to_string_lossy()).await.unwrap();
+            for name in ["a", "b"] {
+                sqlx::query("INSERT INTO collections (name, type) VALUES (?, 'manual')")
+                    .bind(name)
+                    .execute(&db.pool)
+                    .await
+                    .unwrap();
+            }
+            let path = dir.join("ex.tex").to_string_lossy().to_string();
+            let resource = create(&db, &path, "a", "x", None).await.unwrap();
+
+            let moved = move_to(&db, &resource.id, &dir.to_string_lossy(), Some("b"))
+                .await
+                .unwrap();
+            assert_eq!(moved.path, path);
+            let stored = db.get_resource_by_id(&resource.id).await.unwrap().unwrap();
+            assert_eq!(stored.collection, "b");
+            db.pool.close().await;
+        });
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
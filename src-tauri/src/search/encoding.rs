@@ -0,0 +1,111 @@
+//! Source file encodings.
+//!
+//! Older Greek sources are often ISO-8859-7 or Windows-1253 rather than
+//! UTF-8. Search and replace decode files with the detected encoding and
+//! write them back in the same one, unless asked to convert to UTF-8.
+
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+
+/// File content decoded to text
+pub struct Decoded {
+    pub text: String,
+    pub encoding: &'static Encoding,
+    /// Whether the file started with a byte order mark
+    pub bom: bool,
+}
+
+impl Decoded {
+    /// Encoding name for the UI, None for UTF-8
+    pub fn non_utf8_name(&self) -> Option<String> {
+        (self.encoding != UTF_8).then(|| self.encoding.name().to_string())
+    }
+}
+
+/// Decode bytes, honouring a BOM, preferring UTF-8 and otherwise guessing
+pub fn decode(bytes: &[u8]) -> Decoded {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        return Decoded {
+            text: text.into_owned(),
+            encoding,
+            bom: true,
+        };
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Decoded {
+            text: text.to_string(),
+            encoding: UTF_8,
+            bom: false,
+        };
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    let encoding = detector.guess(None, true);
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+    Decoded {
+        text: text.into_owned(),
+        encoding,
+        bom: false,
+    }
+}
+
+/// Encode text for writing back. Fails if it contains characters the
+/// encoding can't represent (e.g. non-Greek letters in ISO-8859-7).
+pub fn encode(text: &str, encoding: &'static Encoding, bom: bool) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    if bom && encoding == UTF_8 {
+        out.extend_from_slice(b"\xEF\xBB\xBF");
+    }
+    let (bytes, _, had_errors) = encoding.encode(text);
+    if had_errors {
+        return Err(format!(
+            "Text contains characters that cannot be written as {}",
+            encoding.name()
+        ));
+    }
+    out.extend_from_slice(&bytes);
+    Ok(out)
+}
+
+/// Rewrite a file as UTF-8. Returns the encoding it had, or None if it was
+/// UTF-8 already.
+pub fn convert_to_utf8(path: &str) -> Result<Option<String>, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let decoded = decode(&bytes);
+    let Some(previous) = decoded.non_utf8_name() else {
+        return Ok(None);
+    };
+    std::fs::write(path, decoded.text.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(Some(previous))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_greek_round_trip() {
+        let text =
+            "Άσκηση 1: Να βρείτε την παράγωγο της συνάρτησης f(x) = x^2 και να λύσετε την εξίσωση.";
+        let (bytes, _, _) = encoding_rs::WINDOWS_1253.encode(text);
+
+        let decoded = decode(&bytes);
+        assert_eq!(decoded.text, text);
+        assert!(decoded.non_utf8_name().is_some());
+        assert_eq!(
+            encode(&decoded.text, decoded.encoding, decoded.bom).unwrap(),
+            bytes.into_owned()
+        );
+        assert!(encode("ü ß", decoded.encoding, false).is_err());
+    }
+
+    #[test]
+    fn test_decode_utf8() {
+        let decoded = decode("\u{feff}καλημέρα".as_bytes());
+        assert_eq!(decoded.text, "καλημέρα");
+        assert!(decoded.bom && decoded.non_utf8_name().is_none());
+    }
+}
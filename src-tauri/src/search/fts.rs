@@ -0,0 +1,354 @@
+//! Full-text search over resource metadata (SQLite FTS5).
+//!
+//! The `resources_fts` table (migration 016) holds title, description, tags and
+//! file content per resource. Triggers keep the metadata columns in sync; the
+//! content column is filled from disk by `update_content`/`rebuild_content`.
+//!
+//! `metadata_fields` reads the indexed metadata columns (plus custom field
+//! values) back for the metadata mode of the file search.
+//!
+//! `update_content` also maintains `resources_content_trigram` (migration
+//! 025), a substring index the file search uses to skip files that cannot
+//! contain a plain-text query, and records when it did (migration 032), so
+//! files changed on disk since are still searched. Contents are decoded with
+//! `encoding::decode`, so files in legacy encodings are indexed as text.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::{HashMap, HashSet};
+
+use super::{encoding, MatchOrigin, MetadataField, SearchQuery};
+use crate::database::entities::Resource;
+
+/// Text-like extensions whose contents are worth indexing
+const INDEXED_EXTENSIONS: &[&str] = &["tex", "bib", "sty", "cls", "dtx", "ins", "txt", "md"];
+
+/// A ranked metadata search hit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetadataMatch {
+    pub resource_id: String,
+    pub path: String,
+    pub kind: String,
+    pub collection: String,
+    pub title: Option<String>,
+    /// bm25 score (lower is better, as returned by SQLite)
+    pub rank: f64,
+    /// Title with matched terms wrapped in <mark></mark>
+    pub title_highlight: String,
+    /// Best matching excerpt from any indexed column
+    pub snippet: String,
+}
+
+/// Turns free user input into a safe FTS5 query: every word becomes a quoted
+/// prefix term, so punctuation or FTS operators in the input can't cause
+/// syntax errors. Input that already looks like an FTS expression (quotes,
+/// column filters, boolean operators) is passed through unchanged.
+pub fn to_fts_query(input: &str) -> String {
+    let trimmed = input.trim();
+    let is_advanced = trimmed.contains('"')
+        || trimmed.contains(':')
+        || trimmed
+            .split_whitespace()
+            .any(|w| w == "AND" || w == "OR" || w == "NOT" || w.starts_with("NEAR("));
+    if is_advanced {
+        return trimmed.to_string();
+    }
+
+    trimmed
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|w| !w.is_empty())
+        .map(|w| format!("\"{}\"*", w))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Search titles, descriptions, tags and contents, best matches first
+pub async fn search_metadata(
+    pool: &Pool<Sqlite>,
+    query: &str,
+    collections: &[String],
+    limit: i64,
+) -> Result<Vec<MetadataMatch>, String> {
+    let fts_query = to_fts_query(query);
+    if fts_query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let collection_filter = if collections.is_empty() {
+        String::new()
+    } else {
+        let placeholders: Vec<&str> = collections.iter().map(|_| "?").collect();
+        format!("AND r.collection IN ({})", placeholders.join(", "))
+    };
+
+    // Weights: title > tags > description > content
+    let sql = format!(
+        "SELECT r.id, r.path, r.type AS kind, r.collection, r.title,
+                bm25(resources_fts, 0.0, 10.0, 4.0, 6.0, 1.0) AS rank,
+                highlight(resources_fts, 1, '<mark>', '</mark>') AS title_highlight,
+                snippet(resources_fts, -1, '<mark>', '</mark>', '…', 12) AS snippet
+         FROM resources_fts
+         JOIN resources r ON r.id = resources_fts.resource_id
+         WHERE resources_fts MATCH ? {}
+         ORDER BY rank
+         LIMIT ?",
+        collection_filter
+    );
+
+    let mut q = sqlx::query(&sql).bind(&fts_query);
+    for c in collections {
+        q = q.bind(c);
+    }
+    let rows = q
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Metadata search failed: {}", e))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| MetadataMatch {
+            resource_id: row.get("id"),
+            path: row.get("path"),
+            kind: row.get("kind"),
+            collection: row.get("collection"),
+            title: row.get("title"),
+            rank: row.get("rank"),
+            title_highlight: row.get("title_highlight"),
+            snippet: row.get("snippet"),
+        })
+        .collect())
+}
+
+/// Store the file content of a resource in the index. Everything is written
+/// in one transaction, so a failure can't leave an index time for content
+/// that isn't there.
+pub async fn update_content(
+    pool: &Pool<Sqlite>,
+    resource_id: &str,
+    content: &str,
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query("UPDATE resources_fts SET content = ? WHERE resource_id = ?")
+        .bind(content)
+        .bind(resource_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    sqlx::query("DELETE FROM resources_content_trigram WHERE resource_id = ?")
+        .bind(resource_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    if !content.is_empty() {
+        sqlx::query("INSERT INTO resources_content_trigram (resource_id, content) VALUES (?, ?)")
+            .bind(resource_id)
+            .bind(content)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    sqlx::query(
+        "INSERT OR REPLACE INTO resources_content_indexed (resource_id, indexed_at)
+         VALUES (?, CAST(strftime('%s', 'now') AS INTEGER))",
+    )
+    .bind(resource_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+/// A file's text as it is indexed, whatever its encoding; `None` if it
+/// can't be read
+pub fn read_for_index(path: &str) -> Option<String> {
+    std::fs::read(path)
+        .ok()
+        .map(|bytes| encoding::decode(&bytes).text)
+}
+
+/// Whether the file search still has to look at a resource: it matched the
+/// index, was never indexed, or was modified since it was (times in Unix
+/// seconds; a file modified in the second it was indexed counts as newer).
+/// `modified` is only asked for files the index rules out.
+fn may_match(
+    matching: bool,
+    indexed_at: Option<i64>,
+    modified: impl FnOnce() -> Option<i64>,
+) -> bool {
+    match indexed_at {
+        _ if matching => true,
+        None => true,
+        Some(indexed_at) => modified().is_none_or(|modified| modified >= indexed_at),
+    }
+}
+
+fn modified_secs(path: &str) -> Option<i64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    let secs = modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    i64::try_from(secs).ok()
+}
+
+/// Drop the resources that the substring index says cannot match a
+/// plain-text query. Resources missing from the index, and files modified
+/// since they were indexed, are kept, so a stale or partial index never
+/// hides results. Returns whether the index was used; regex queries and
+/// literals under three characters can't use it.
+pub async fn content_prefilter(
+    pool: &Pool<Sqlite>,
+    query: &SearchQuery,
+    resources: Vec<Resource>,
+) -> Result<(Vec<Resource>, bool), String> {
+    if query.use_regex || query.text.chars().count() < 3 {
+        return Ok((resources, false));
+    }
+
+    // The trigram tokenizer folds case, so this is a superset of the
+    // case-sensitive matches too
+    let phrase = format!("\"{}\"", query.text.replace('"', "\"\""));
+    let matching: HashSet<String> = sqlx::query_scalar(
+        "SELECT resource_id FROM resources_content_trigram WHERE resources_content_trigram MATCH ?",
+    )
+    .bind(&phrase)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Index search failed: {}", e))?
+    .into_iter()
+    .collect();
+    let indexed: HashMap<String, i64> =
+        sqlx::query_as("SELECT resource_id, indexed_at FROM resources_content_indexed")
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .collect();
+
+    let resources = resources
+        .into_iter()
+        .filter(|r| {
+            may_match(
+                matching.contains(&r.id),
+                indexed.get(&r.id).copied(),
+                || modified_secs(&r.path),
+            )
+        })
+        .collect();
+    Ok((resources, true))
+}
+
+/// Title, description and tags (from the index) and custom field values of
+/// the given resources, for metadata search. Empty values are left out.
+pub async fn metadata_fields(
+    pool: &Pool<Sqlite>,
+    resources: &[Resource],
+) -> Result<Vec<MetadataField>, String> {
+    let ids: HashSet<&str> = resources.iter().map(|r| r.id.as_str()).collect();
+    let mut fields = Vec::new();
+
+    let rows = sqlx::query("SELECT resource_id, title, description, tags FROM resources_fts")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let resource_id: String = row.get("resource_id");
+        if !ids.contains(resource_id.as_str()) {
+            continue;
+        }
+        for (column, origin) in [
+            ("title", MatchOrigin::Title),
+            ("description", MatchOrigin::Description),
+            ("tags", MatchOrigin::Tags),
+        ] {
+            let value: Option<String> = row.get(column);
+            if let Some(value) = value.filter(|v| !v.trim().is_empty()) {
+                fields.push(MetadataField {
+                    resource_id: resource_id.clone(),
+                    origin,
+                    name: None,
+                    value,
+                });
+            }
+        }
+    }
+
+    let rows = sqlx::query(
+        "SELECT v.resource_id, d.name, v.value
+         FROM custom_field_values v
+         JOIN custom_field_definitions d ON d.id = v.field_id
+         WHERE v.value IS NOT NULL AND v.value != ''
+         ORDER BY d.order_index",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    for row in rows {
+        let resource_id: String = row.get("resource_id");
+        if ids.contains(resource_id.as_str()) {
+            fields.push(MetadataField {
+                resource_id,
+                origin: MatchOrigin::CustomField,
+                name: Some(row.get("name")),
+                value: row.get("value"),
+            });
+        }
+    }
+    Ok(fields)
+}
+
+/// Whether a resource's file content should be indexed
+pub fn is_indexable(path: &str) -> bool {
+    let ext = std::path::Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    INDEXED_EXTENSIONS.contains(&ext.as_str())
+}
+
+/// Re-read the files of the given resources into the index.
+/// Returns the number of resources whose content was indexed.
+pub async fn rebuild_content(pool: &Pool<Sqlite>, resources: &[Resource]) -> Result<usize, String> {
+    let mut count = 0;
+    for resource in resources.iter().filter(|r| is_indexable(&r.path)) {
+        // Unreadable files are indexed as empty
+        let content = read_for_index(&resource.path).unwrap_or_default();
+        update_content(pool, &resource.id, &content).await?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_input_becomes_prefix_terms() {
+        assert_eq!(to_fts_query("chain rule"), "\"chain\"* \"rule\"*");
+        assert_eq!(to_fts_query("  \\frac{a}{b} "), "\"frac\"* \"a\"* \"b\"*");
+        assert_eq!(to_fts_query("   "), "");
+    }
+
+    #[test]
+    fn test_may_match() {
+        assert!(may_match(true, Some(100), || Some(50)));
+        // Never indexed, or no file time: search the file
+        assert!(may_match(false, None, || Some(50)));
+        assert!(may_match(false, Some(100), || None));
+        // Unchanged since indexing: the index is trusted
+        assert!(!may_match(false, Some(100), || Some(99)));
+        // Edited outside the app after indexing
+        assert!(may_match(false, Some(100), || Some(100)));
+        assert!(may_match(false, Some(100), || Some(150)));
+    }
+
+    #[test]
+    fn test_advanced_input_is_passed_through() {
+        assert_eq!(to_fts_query("title:limits"), "title:limits");
+        assert_eq!(to_fts_query("calc OR algebra"), "calc OR algebra");
+    }
+}
@@ -1,19 +1,106 @@
+pub mod encoding;
+pub mod fts;
+pub mod paths;
+pub mod scope;
+
 use crate::database::entities::Resource;
 use rayon::prelude::*;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
 use std::time::Instant;
 
+/// Files larger than this are skipped unless the query sets `max_file_size`
+const DEFAULT_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+/// Context lines before/after each match unless the query sets them
+const DEFAULT_CONTEXT_LINES: usize = 2;
+/// Leading bytes checked for NUL when detecting binary files (as git does)
+const BINARY_SNIFF_LEN: usize = 8000;
+
 /// Search query parameters
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SearchQuery {
     pub text: String,
     pub case_sensitive: bool,
     pub use_regex: bool,
     pub file_types: Vec<String>,
     pub max_results: usize,
+    /// Only match whole words
+    #[serde(default)]
+    pub whole_word: bool,
+    /// Search the whole file instead of line by line, so patterns can span
+    /// lines (`.` matches newlines, `^`/`$` match at line boundaries).
+    /// Replacing works line by line and refuses it.
+    #[serde(default)]
+    pub multiline: bool,
+    /// Ignore matches inside `%` comments
+    #[serde(default)]
+    pub skip_comments: bool,
+    /// Only report matches in this part of the LaTeX source
+    #[serde(default)]
+    pub scope: scope::SearchScope,
+    /// Skip files larger than this many bytes (default 10 MiB)
+    #[serde(default)]
+    pub max_file_size: Option<u64>,
+    /// Only search paths matching one of these globs (all if empty)
+    #[serde(default)]
+    pub include_globs: Vec<String>,
+    /// Never search paths matching one of these globs
+    #[serde(default)]
+    pub exclude_globs: Vec<String>,
+    /// Leave out files ignored by a `.gitignore` of their repository
+    #[serde(default)]
+    pub respect_gitignore: bool,
+    /// Search file contents, resource metadata or both
+    #[serde(default)]
+    pub mode: SearchMode,
+    /// Lines of context kept before each match (default 2)
+    #[serde(default)]
+    pub context_before: Option<usize>,
+    /// Lines of context kept after each match (default 2)
+    #[serde(default)]
+    pub context_after: Option<usize>,
+}
+
+/// What a search looks at
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    #[default]
+    Content,
+    /// Titles, descriptions, tags and custom field values
+    Metadata,
+    All,
+}
+
+/// Where a match was found
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchOrigin {
+    #[default]
+    Content,
+    Title,
+    Description,
+    Tags,
+    CustomField,
+}
+
+/// A metadata value of a resource, as searched by `search_metadata_fields`
+#[derive(Debug, Clone)]
+pub struct MetadataField {
+    pub resource_id: String,
+    pub origin: MatchOrigin,
+    /// Custom field name
+    pub name: Option<String>,
+    pub value: String,
+}
+
+/// A file that was not searched, and why
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub file_path: String,
+    /// "binary" | "too_large" | "unreadable" | "unencodable" (the replacement
+    /// can't be written in the file's encoding) | "unwritable"
+    pub reason: String,
 }
 
 /// A single search match with context
@@ -28,6 +115,15 @@ pub struct SearchMatch {
     pub match_end: usize,
     pub context_before: Vec<String>,
     pub context_after: Vec<String>,
+    /// Last line of the match; differs from `line_number` only for multiline
+    /// matches, whose `line_content` then holds all the lines they span
+    #[serde(default)]
+    pub end_line_number: usize,
+    #[serde(default)]
+    pub origin: MatchOrigin,
+    /// Custom field the match is in, for `MatchOrigin::CustomField`
+    #[serde(default)]
+    pub field: Option<String>,
 }
 
 /// Search result containing all matches and metadata
@@ -36,13 +132,56 @@ pub struct SearchResult {
     pub matches: Vec<SearchMatch>,
     pub total_files_searched: usize,
     pub search_duration_ms: u64,
+    /// Whether the content index narrowed down the files to read
+    #[serde(default)]
+    pub used_index: bool,
+    /// Binary, oversized or unreadable files
+    #[serde(default)]
+    pub skipped_files: Vec<SkippedFile>,
+    /// Every match found, even beyond `max_results`
+    #[serde(default)]
+    pub total_matches: usize,
+    /// Number of matches in each file that has any
+    #[serde(default)]
+    pub file_match_counts: Vec<FileMatchCount>,
+}
+
+/// Matches found in one file (all of them, not only those returned)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMatchCount {
+    pub resource_id: String,
+    pub file_path: String,
+    pub count: usize,
 }
 
 /// Replace query parameters
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReplaceQuery {
     pub search: SearchQuery,
+    /// With `use_regex`, `$1`, `${2}` and `${name}` refer to capture groups
+    /// (`$$` is a literal `$`); otherwise the text is inserted as-is
     pub replace_with: String,
+    /// Match the case of each replaced occurrence: FOO→BAR, foo→bar, Foo→Bar
+    #[serde(default)]
+    pub preserve_case: bool,
+    /// Write changed files as UTF-8 instead of their detected encoding
+    #[serde(default)]
+    pub convert_to_utf8: bool,
+    /// Where to keep the previous content of changed files
+    #[serde(default)]
+    pub backup: ReplaceBackup,
+}
+
+/// Safety net for files rewritten by `replace_in_files` and `apply_replace`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplaceBackup {
+    #[default]
+    None,
+    /// Copy the original file to `<file>.bak`
+    Bak,
+    /// Snapshot the original content in the local file history
+    History,
 }
 
 /// Replace result
@@ -51,6 +190,71 @@ pub struct ReplaceResult {
     pub total_files_changed: usize,
     pub total_replacements: usize,
     pub replace_duration_ms: u64,
+    /// Accepted hunks that were not applied because the line changed since
+    /// the preview
+    #[serde(default)]
+    pub skipped_hunks: usize,
+    #[serde(default)]
+    pub skipped_files: Vec<SkippedFile>,
+    /// (path, previous content) of changed files, for `ReplaceBackup::History`
+    #[serde(skip)]
+    pub previous_contents: Vec<(String, String)>,
+}
+
+/// One changed line of a replace preview
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplaceHunk {
+    pub line_number: usize,
+    pub before: String,
+    pub after: String,
+    /// Number of matches replaced on the line
+    pub replacements: usize,
+}
+
+/// Preview of the replacements in one file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileReplacePreview {
+    pub resource_id: String,
+    pub file_path: String,
+    pub file_name: String,
+    pub hunks: Vec<ReplaceHunk>,
+    /// Detected encoding when it isn't UTF-8; applying keeps it
+    #[serde(default)]
+    pub encoding: Option<String>,
+}
+
+/// Result of `preview_replace`; nothing is written
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReplacePreview {
+    pub files: Vec<FileReplacePreview>,
+    pub total_replacements: usize,
+    pub preview_duration_ms: u64,
+    pub skipped_files: Vec<SkippedFile>,
+}
+
+/// Resources with one of the query's file types (any if none) whose paths
+/// pass its glob and `.gitignore` filters
+fn filter_resources(
+    query: &SearchQuery,
+    resources: Vec<Resource>,
+) -> Result<Vec<Resource>, String> {
+    let mut path_filter = paths::PathFilter::new(
+        &query.include_globs,
+        &query.exclude_globs,
+        query.respect_gitignore,
+    )?;
+    Ok(resources
+        .into_iter()
+        .filter(|r| {
+            let path = r.path.to_lowercase();
+            query.file_types.is_empty()
+                || query
+                    .file_types
+                    .iter()
+                    .any(|ext| path.ends_with(&format!(".{}", ext.to_lowercase())))
+        })
+        .filter(|r| path_filter.is_empty() || path_filter.allows(&r.path))
+        .collect())
 }
 
 /// Main search function - searches through multiple resources in parallel
@@ -60,32 +264,43 @@ pub fn search_in_files(
 ) -> Result<SearchResult, String> {
     let start_time = Instant::now();
 
-    // Filter resources by file type if specified
-    let filtered_resources: Vec<Resource> = if query.file_types.is_empty() {
-        resources
-    } else {
-        resources
-            .into_iter()
-            .filter(|r| {
-                let path = r.path.to_lowercase();
-                query
-                    .file_types
-                    .iter()
-                    .any(|ext| path.ends_with(&format!(".{}", ext.to_lowercase())))
-            })
-            .collect()
-    };
+    let filtered_resources = filter_resources(query, resources)?;
 
     let total_files = filtered_resources.len();
+    let regex_pattern = build_regex(query)?;
 
     // Use Rayon for parallel search across files
-    // Collect all matches from all files, then flatten and limit
-    let mut all_matches: Vec<SearchMatch> = filtered_resources
+    let results: Vec<Result<(Vec<SearchMatch>, usize), SkippedFile>> = filtered_resources
         .par_iter()
-        .map(|resource| search_single_file(&resource.path, &resource.id, query).unwrap_or_default())
-        .flatten()
+        .map(|resource| {
+            if query.multiline {
+                search_file_multiline(&resource.path, &resource.id, &regex_pattern, query)
+            } else {
+                search_single_file(&resource.path, &resource.id, &regex_pattern, query)
+            }
+        })
         .collect();
 
+    // Collect all matches from all files, then flatten and limit
+    let mut all_matches: Vec<SearchMatch> = Vec::new();
+    let mut skipped_files = Vec::new();
+    let mut file_match_counts = Vec::new();
+    for (resource, result) in filtered_resources.iter().zip(results) {
+        match result {
+            Ok((matches, count)) => {
+                all_matches.extend(matches);
+                if count > 0 {
+                    file_match_counts.push(FileMatchCount {
+                        resource_id: resource.id.clone(),
+                        file_path: resource.path.clone(),
+                        count,
+                    });
+                }
+            }
+            Err(skipped) => skipped_files.push(skipped),
+        }
+    }
+
     // Limit results
     all_matches.truncate(query.max_results);
 
@@ -95,71 +310,107 @@ pub fn search_in_files(
         matches: all_matches,
         total_files_searched: total_files,
         search_duration_ms: duration.as_millis() as u64,
+        used_index: false,
+        skipped_files,
+        total_matches: file_match_counts.iter().map(|f| f.count).sum(),
+        file_match_counts,
     })
 }
 
-/// Search within a single file
+/// Search metadata values; metadata matches have no line (`line_number` 0) and
+/// `line_content` holds the whole value. All matches are returned, so the
+/// caller can count them before applying `max_results`.
+pub fn search_metadata_fields(
+    query: &SearchQuery,
+    resources: &[Resource],
+    fields: &[MetadataField],
+) -> Result<Vec<SearchMatch>, String> {
+    let regex_pattern = build_regex(query)?;
+    let paths: std::collections::HashMap<&str, &str> = resources
+        .iter()
+        .map(|r| (r.id.as_str(), r.path.as_str()))
+        .collect();
+
+    let mut matches = Vec::new();
+    for field in fields {
+        let Some(path) = paths.get(field.resource_id.as_str()) else {
+            continue;
+        };
+        let Some(mat) = regex_pattern.find(&field.value) else {
+            continue;
+        };
+        matches.push(SearchMatch {
+            resource_id: field.resource_id.clone(),
+            file_path: path.to_string(),
+            file_name: file_name_of(path),
+            line_number: 0,
+            line_content: field.value.clone(),
+            match_start: mat.start(),
+            match_end: mat.end(),
+            context_before: Vec::new(),
+            context_after: Vec::new(),
+            end_line_number: 0,
+            origin: field.origin,
+            field: field.name.clone(),
+        });
+    }
+    Ok(matches)
+}
+
+/// (before, after) context line counts of a query
+fn context_lines(query: &SearchQuery) -> (usize, usize) {
+    (
+        query.context_before.unwrap_or(DEFAULT_CONTEXT_LINES),
+        query.context_after.unwrap_or(DEFAULT_CONTEXT_LINES),
+    )
+}
+
+/// Search within a single file. Returns the first match of each line (up to
+/// `max_results`) and the number of all matches in the file.
 fn search_single_file(
     file_path: &str,
     resource_id: &str,
+    regex_pattern: &Regex,
     query: &SearchQuery,
-) -> Result<Vec<SearchMatch>, String> {
-    let file = File::open(file_path).map_err(|e| format!("Failed to open file: {}", e))?;
-    let reader = BufReader::new(file);
+) -> Result<(Vec<SearchMatch>, usize), SkippedFile> {
+    let bytes = read_searchable(file_path, query.max_file_size)?;
+    let content = encoding::decode(&bytes).text;
 
     let mut matches = Vec::new();
+    // Read all lines first for context access, with their offsets for scopes
     let mut lines: Vec<String> = Vec::new();
-
-    // Read all lines first for context access
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            lines.push(line);
-        }
+    let mut line_offsets: Vec<usize> = Vec::new();
+    let mut offset = 0;
+    for (text, eol) in split_lines(&content) {
+        lines.push(text.to_string());
+        line_offsets.push(offset);
+        offset += text.len() + eol.len();
     }
 
-    // Prepare search pattern
-    let pattern = if query.use_regex {
-        query.text.clone()
-    } else {
-        regex::escape(&query.text)
-    };
+    let filter = scope::ScopeFilter::new(&content, &query.scope, query.skip_comments);
 
-    let regex_pattern = if query.case_sensitive {
-        Regex::new(&pattern).map_err(|e| format!("Invalid regex: {}", e))?
-    } else {
-        Regex::new(&format!("(?i){}", pattern)).map_err(|e| format!("Invalid regex: {}", e))?
-    };
-
-    // Extract file name from path
-    let file_name = std::path::Path::new(file_path)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or(file_path)
-        .to_string();
+    let file_name = file_name_of(file_path);
+    let (before, after) = context_lines(query);
+    let mut count = 0;
 
     // Search through lines
     for (line_idx, line_content) in lines.iter().enumerate() {
-        if let Some(mat) = regex_pattern.find(line_content) {
-            // Debug log
-            println!("Found match at line {}: '{}'", line_idx + 1, line_content);
-            println!("Match positions: start={}, end={}", mat.start(), mat.end());
-
-            // Get context lines (2 before and 2 after)
-            let context_before: Vec<String> = if line_idx >= 2 {
-                lines[line_idx - 2..line_idx].to_vec()
-            } else if line_idx >= 1 {
-                lines[line_idx - 1..line_idx].to_vec()
-            } else {
-                Vec::new()
-            };
+        let line_offset = line_offsets[line_idx];
+        let mut found = regex_pattern.find_iter(line_content).filter(|m| {
+            filter
+                .as_ref()
+                .is_none_or(|f| f.allows(line_offset + m.start(), line_offset + m.end()))
+        });
+        if let Some(mat) = found.next() {
+            count += 1 + found.count();
+            // Keep counting once enough matches are collected
+            if matches.len() >= query.max_results {
+                continue;
+            }
 
-            let context_after: Vec<String> = if line_idx + 3 <= lines.len() {
-                lines[line_idx + 1..line_idx + 3].to_vec()
-            } else if line_idx + 2 <= lines.len() {
-                lines[line_idx + 1..line_idx + 2].to_vec()
-            } else {
-                Vec::new()
-            };
+            let context_before = lines[line_idx.saturating_sub(before)..line_idx].to_vec();
+            let context_after =
+                lines[line_idx + 1..(line_idx + 1 + after).min(lines.len())].to_vec();
 
             matches.push(SearchMatch {
                 resource_id: resource_id.to_string(),
@@ -171,16 +422,14 @@ fn search_single_file(
                 match_end: mat.end(),
                 context_before,
                 context_after,
+                end_line_number: line_idx + 1,
+                origin: MatchOrigin::Content,
+                field: None,
             });
-
-            // Stop if we've reached max results
-            if matches.len() >= query.max_results {
-                break;
-            }
         }
     }
 
-    Ok(matches)
+    Ok((matches, count))
 }
 
 /// Replace text in files
@@ -189,32 +438,34 @@ pub fn replace_in_files(
     resources: Vec<Resource>,
 ) -> Result<ReplaceResult, String> {
     let start_time = Instant::now();
+    check_replace_query(query)?;
 
-    // Filter resources by file type if specified
-    let filtered_resources: Vec<Resource> = if query.search.file_types.is_empty() {
-        resources
-    } else {
-        resources
-            .into_iter()
-            .filter(|r| {
-                let path = r.path.to_lowercase();
-                query
-                    .search
-                    .file_types
-                    .iter()
-                    .any(|ext| path.ends_with(&format!(".{}", ext.to_lowercase())))
-            })
-            .collect()
-    };
+    let filtered_resources = filter_resources(&query.search, resources)?;
+
+    let regex_pattern = build_regex(&query.search)?;
 
     // Use Rayon for parallel replace across files
-    let results: Vec<(bool, usize)> = filtered_resources
+    let results: Vec<Result<FileReplaced, SkippedFile>> = filtered_resources
         .par_iter()
-        .map(|resource| replace_in_single_file(&resource.path, query).unwrap_or((false, 0)))
+        .map(|resource| replace_in_single_file(&resource.path, &regex_pattern, query))
         .collect();
 
-    let total_files_changed = results.iter().filter(|(changed, _)| *changed).count();
-    let total_replacements = results.iter().map(|(_, count)| count).sum();
+    let mut total_files_changed = 0;
+    let mut total_replacements = 0;
+    let mut skipped_files = Vec::new();
+    let mut previous_contents = Vec::new();
+    for (resource, result) in filtered_resources.iter().zip(results) {
+        match result {
+            Ok(replaced) => {
+                total_files_changed += (replaced.replacements > 0) as usize;
+                total_replacements += replaced.replacements;
+                if let Some(previous) = replaced.previous {
+                    previous_contents.push((resource.path.clone(), previous));
+                }
+            }
+            Err(skipped) => skipped_files.push(skipped),
+        }
+    }
 
     let duration = start_time.elapsed();
 
@@ -222,69 +473,464 @@ pub fn replace_in_files(
         total_files_changed,
         total_replacements,
         replace_duration_ms: duration.as_millis() as u64,
+        skipped_hunks: 0,
+        skipped_files,
+        previous_contents,
     })
 }
 
-/// Replace within a single file
-fn replace_in_single_file(file_path: &str, query: &ReplaceQuery) -> Result<(bool, usize), String> {
-    let file = File::open(file_path).map_err(|e| format!("Failed to open file: {}", e))?;
-    let reader = BufReader::new(file);
+/// Replacing rewrites matches line by line, so a pattern spanning lines
+/// can't be replaced
+fn check_replace_query(query: &ReplaceQuery) -> Result<(), String> {
+    if query.search.multiline {
+        return Err("Multiline patterns can't be replaced; turn off multiline search".to_string());
+    }
+    Ok(())
+}
+
+/// Outcome of replacing in one file
+struct FileReplaced {
+    replacements: usize,
+    /// Content before the replace, kept for `ReplaceBackup::History`
+    previous: Option<String>,
+}
+
+fn replace_in_single_file(
+    file_path: &str,
+    regex_pattern: &Regex,
+    query: &ReplaceQuery,
+) -> Result<FileReplaced, SkippedFile> {
+    let bytes = read_searchable(file_path, query.search.max_file_size)?;
+    let decoded = encoding::decode(&bytes);
 
-    let mut lines: Vec<String> = Vec::new();
-    let mut changed = false;
     let mut replacements = 0;
 
-    // Read all lines
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            lines.push(line);
+    // Perform replacement in memory, keeping each line's ending (and a
+    // missing final newline) as it was
+    let mut new_content = String::with_capacity(decoded.text.len());
+    for (line, eol) in split_lines(&decoded.text) {
+        match replace_line(regex_pattern, line, query) {
+            Some((replaced, count)) => {
+                replacements += count;
+                new_content.push_str(&replaced);
+            }
+            None => new_content.push_str(line),
         }
+        new_content.push_str(eol);
+    }
+
+    if replacements == 0 {
+        return Ok(FileReplaced {
+            replacements,
+            previous: None,
+        });
+    }
+
+    // Write back in the encoding the file was read in
+    let out = if query.convert_to_utf8 {
+        new_content.into_bytes()
+    } else {
+        encoding::encode(&new_content, decoded.encoding, decoded.bom)
+            .map_err(|_| skipped(file_path, "unencodable"))?
+    };
+    write_replaced(file_path, &bytes, &out, query.backup)?;
+
+    Ok(FileReplaced {
+        replacements,
+        previous: (query.backup == ReplaceBackup::History).then_some(decoded.text),
+    })
+}
+
+/// Write the replaced content of a file, first copying `original` to
+/// `<file>.bak` if asked to
+fn write_replaced(
+    file_path: &str,
+    original: &[u8],
+    out: &[u8],
+    backup: ReplaceBackup,
+) -> Result<(), SkippedFile> {
+    if backup == ReplaceBackup::Bak {
+        std::fs::write(format!("{}.bak", file_path), original)
+            .map_err(|_| skipped(file_path, "unwritable"))?;
+    }
+    write_atomic(file_path, out).map_err(|_| skipped(file_path, "unwritable"))
+}
+
+/// Write through a temporary file in the same folder and rename it over the
+/// original, so an interrupted write never leaves a truncated file. A
+/// symlink is followed, so the file it points to is replaced, not the link.
+fn write_atomic(file_path: &str, bytes: &[u8]) -> std::io::Result<()> {
+    let path = std::fs::canonicalize(file_path)?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let tmp = path.with_file_name(format!(".{}.datatex-tmp", name));
+    std::fs::write(&tmp, bytes)?;
+    if let Ok(metadata) = std::fs::metadata(&path) {
+        let _ = std::fs::set_permissions(&tmp, metadata.permissions());
+    }
+    std::fs::rename(&tmp, &path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
+fn skipped(file_path: &str, reason: &str) -> SkippedFile {
+    SkippedFile {
+        file_path: file_path.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0)
+}
+
+/// Whether a file is small enough to search, by its metadata
+fn check_size(file_path: &str, max_file_size: Option<u64>) -> Result<u64, SkippedFile> {
+    let size = std::fs::metadata(file_path)
+        .map_err(|_| skipped(file_path, "unreadable"))?
+        .len();
+    if size > max_file_size.unwrap_or(DEFAULT_MAX_FILE_SIZE) {
+        return Err(skipped(file_path, "too_large"));
+    }
+    Ok(size)
+}
+
+/// Read a file for searching, unless it is unreadable, too large or binary
+fn read_searchable(file_path: &str, max_file_size: Option<u64>) -> Result<Vec<u8>, SkippedFile> {
+    check_size(file_path, max_file_size)?;
+    let bytes = std::fs::read(file_path).map_err(|_| skipped(file_path, "unreadable"))?;
+    if is_binary(&bytes) {
+        return Err(skipped(file_path, "binary"));
     }
+    Ok(bytes)
+}
 
-    // Prepare search pattern
-    let pattern = if query.search.use_regex {
-        query.search.text.clone()
+/// Compile the search pattern of a query
+fn build_regex(query: &SearchQuery) -> Result<Regex, String> {
+    let mut pattern = if query.use_regex {
+        query.text.clone()
     } else {
-        regex::escape(&query.search.text)
+        regex::escape(&query.text)
     };
 
-    let regex_pattern = if query.search.case_sensitive {
-        Regex::new(&pattern).map_err(|e| format!("Invalid regex: {}", e))?
+    if query.whole_word {
+        // For plain text, only require a boundary next to word characters, so
+        // "\\alpha" still matches as a whole word
+        let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let (start, end) = if query.use_regex {
+            (true, true)
+        } else {
+            (
+                is_word(query.text.chars().next()),
+                is_word(query.text.chars().last()),
+            )
+        };
+        pattern = format!(
+            "{}(?:{}){}",
+            if start { r"\b" } else { "" },
+            pattern,
+            if end { r"\b" } else { "" }
+        );
+    }
+
+    let mut flags = String::new();
+    if !query.case_sensitive {
+        flags.push('i');
+    }
+    if query.multiline {
+        flags.push_str("ms");
+    }
+    if !flags.is_empty() {
+        pattern = format!("(?{}){}", flags, pattern);
+    }
+    Regex::new(&pattern).map_err(|e| format!("Invalid regex: {}", e))
+}
+
+/// Search a whole file at once, for patterns spanning several lines.
+/// Returns the matches (up to `max_results`) and the number of all of them.
+fn search_file_multiline(
+    file_path: &str,
+    resource_id: &str,
+    regex_pattern: &Regex,
+    query: &SearchQuery,
+) -> Result<(Vec<SearchMatch>, usize), SkippedFile> {
+    let bytes = read_searchable(file_path, query.max_file_size)?;
+    let content = encoding::decode(&bytes).text;
+    let file_name = file_name_of(file_path);
+
+    // Byte offset of the start of each line
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let line_of = |offset: usize| line_starts.partition_point(|&start| start <= offset) - 1;
+    let line_end = |idx: usize| {
+        line_starts
+            .get(idx + 1)
+            .map(|next| next - 1)
+            .unwrap_or(content.len())
+    };
+    let line = |idx: usize| {
+        content[line_starts[idx]..line_end(idx)]
+            .trim_end_matches('\r')
+            .to_string()
+    };
+    let line_count = if content.ends_with('\n') {
+        line_starts.len() - 1
     } else {
-        Regex::new(&format!("(?i){}", pattern)).map_err(|e| format!("Invalid regex: {}", e))?
+        line_starts.len()
     };
 
-    // Perform replacement in memory
-    let mut new_lines = Vec::new();
-    for line in lines {
-        if regex_pattern.is_match(&line) {
-            let replaced = regex_pattern.replace_all(&line, &query.replace_with);
-            if replaced != line {
-                replacements += line.match_indices(&query.search.text).count(); // Approximate count for regex
-                if query.search.use_regex {
-                    replacements = regex_pattern.find_iter(&line).count();
+    let filter = scope::ScopeFilter::new(&content, &query.scope, query.skip_comments);
+    let (before, after) = context_lines(query);
+
+    let mut matches = Vec::new();
+    let mut count = 0;
+    for mat in regex_pattern.find_iter(&content) {
+        if filter
+            .as_ref()
+            .is_some_and(|f| !f.allows(mat.start(), mat.end()))
+        {
+            continue;
+        }
+        count += 1;
+        if matches.len() >= query.max_results {
+            continue;
+        }
+        let first = line_of(mat.start());
+        let last = line_of(mat.end().saturating_sub(1).max(mat.start()));
+        let block_start = line_starts[first];
+        let block = content[block_start..line_end(last)].trim_end_matches('\r');
+
+        matches.push(SearchMatch {
+            resource_id: resource_id.to_string(),
+            file_path: file_path.to_string(),
+            file_name: file_name.clone(),
+            line_number: first + 1,
+            line_content: block.to_string(),
+            match_start: mat.start() - block_start,
+            match_end: (mat.end() - block_start).min(block.len()),
+            context_before: (first.saturating_sub(before)..first).map(line).collect(),
+            context_after: (last + 1..(last + 1 + after).min(line_count))
+                .map(line)
+                .collect(),
+            end_line_number: last + 1,
+            origin: MatchOrigin::Content,
+            field: None,
+        });
+    }
+    Ok((matches, count))
+}
+
+/// Give `replacement` the case pattern of `original`: all upper, all lower
+/// or capitalized. Mixed-case originals leave the replacement unchanged.
+fn match_case(original: &str, replacement: &str) -> String {
+    let letters: Vec<char> = original.chars().filter(|c| c.is_alphabetic()).collect();
+    let Some(first) = letters.first() else {
+        return replacement.to_string();
+    };
+    let rest_lower = letters[1..].iter().all(|c| !c.is_uppercase());
+
+    if letters.len() > 1 && letters.iter().all(|c| !c.is_lowercase()) {
+        replacement.to_uppercase()
+    } else if first.is_lowercase() && rest_lower {
+        replacement.to_lowercase()
+    } else if first.is_uppercase() && rest_lower {
+        let mut chars = replacement.chars();
+        match chars.next() {
+            Some(c) => c.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        }
+    } else {
+        replacement.to_string()
+    }
+}
+
+/// Replace all matches in one line; None if nothing changed.
+/// Returns the new line and the number of replaced matches.
+fn replace_line(regex: &Regex, line: &str, query: &ReplaceQuery) -> Option<(String, usize)> {
+    let mut count = 0;
+    let replaced = regex.replace_all(line, |caps: &regex::Captures| {
+        count += 1;
+        let mut text = String::new();
+        if query.search.use_regex {
+            caps.expand(&query.replace_with, &mut text);
+        } else {
+            text.push_str(&query.replace_with);
+        }
+        if query.preserve_case {
+            text = match_case(&caps[0], &text);
+        }
+        text
+    });
+    (replaced != line).then(|| (replaced.into_owned(), count))
+}
+
+/// Split text into (line, line ending) pairs; the last line may have no ending
+fn split_lines(content: &str) -> Vec<(&str, &str)> {
+    content
+        .split_inclusive('\n')
+        .map(|line| {
+            let text = line
+                .strip_suffix("\r\n")
+                .or_else(|| line.strip_suffix('\n'))
+                .unwrap_or(line);
+            (text, &line[text.len()..])
+        })
+        .collect()
+}
+
+fn file_name_of(file_path: &str) -> String {
+    std::path::Path::new(file_path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(file_path)
+        .to_string()
+}
+
+/// Compute the replacements without writing anything
+pub fn preview_replace(
+    query: &ReplaceQuery,
+    resources: Vec<Resource>,
+) -> Result<ReplacePreview, String> {
+    let start_time = Instant::now();
+    check_replace_query(query)?;
+    let regex_pattern = build_regex(&query.search)?;
+    let resources = filter_resources(&query.search, resources)?;
+
+    let results: Vec<Result<Option<FileReplacePreview>, SkippedFile>> = resources
+        .par_iter()
+        .map(|resource| {
+            let bytes = read_searchable(&resource.path, query.search.max_file_size)?;
+            let decoded = encoding::decode(&bytes);
+            let hunks: Vec<ReplaceHunk> = split_lines(&decoded.text)
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, (line, _))| {
+                    let (after, replacements) = replace_line(&regex_pattern, line, query)?;
+                    Some(ReplaceHunk {
+                        line_number: idx + 1,
+                        before: line.to_string(),
+                        after,
+                        replacements,
+                    })
+                })
+                .collect();
+            Ok((!hunks.is_empty()).then(|| FileReplacePreview {
+                resource_id: resource.id.clone(),
+                file_path: resource.path.clone(),
+                file_name: file_name_of(&resource.path),
+                hunks,
+                encoding: decoded.non_utf8_name(),
+            }))
+        })
+        .collect();
+
+    let mut files = Vec::new();
+    let mut skipped_files = Vec::new();
+    for result in results {
+        match result {
+            Ok(Some(file)) => files.push(file),
+            Ok(None) => {}
+            Err(skipped) => skipped_files.push(skipped),
+        }
+    }
+
+    let total_replacements = files
+        .iter()
+        .flat_map(|f| &f.hunks)
+        .map(|h| h.replacements)
+        .sum();
+
+    Ok(ReplacePreview {
+        files,
+        total_replacements,
+        preview_duration_ms: start_time.elapsed().as_millis() as u64,
+        skipped_files,
+    })
+}
+
+/// Apply the hunks of a preview the user accepted. A hunk is only applied if
+/// its line still reads as in the preview; line endings are kept as they are.
+/// Previous contents are kept as `backup` says.
+pub fn apply_replace(
+    files: &[FileReplacePreview],
+    backup: ReplaceBackup,
+) -> Result<ReplaceResult, String> {
+    let start_time = Instant::now();
+
+    // (outcome, skipped hunks) per file
+    let results: Vec<Result<(FileReplaced, usize), SkippedFile>> = files
+        .par_iter()
+        .map(|file| apply_to_single_file(file, backup))
+        .collect();
+
+    let mut result = ReplaceResult {
+        total_files_changed: 0,
+        total_replacements: 0,
+        replace_duration_ms: 0,
+        skipped_hunks: 0,
+        skipped_files: Vec::new(),
+        previous_contents: Vec::new(),
+    };
+    for (file, outcome) in files.iter().zip(results) {
+        match outcome {
+            Ok((replaced, skipped_hunks)) => {
+                result.total_files_changed += (replaced.replacements > 0) as usize;
+                result.total_replacements += replaced.replacements;
+                result.skipped_hunks += skipped_hunks;
+                if let Some(previous) = replaced.previous {
+                    result
+                        .previous_contents
+                        .push((file.file_path.clone(), previous));
                 }
-                new_lines.push(replaced.to_string());
-                changed = true;
-            } else {
-                new_lines.push(line);
             }
-        } else {
-            new_lines.push(line);
+            Err(skipped) => result.skipped_files.push(skipped),
         }
     }
+    result.replace_duration_ms = start_time.elapsed().as_millis() as u64;
+    Ok(result)
+}
+
+fn apply_to_single_file(
+    file: &FileReplacePreview,
+    backup: ReplaceBackup,
+) -> Result<(FileReplaced, usize), SkippedFile> {
+    let bytes =
+        std::fs::read(&file.file_path).map_err(|_| skipped(&file.file_path, "unreadable"))?;
+    let decoded = encoding::decode(&bytes);
+    let mut lines: Vec<(String, &str)> = split_lines(&decoded.text)
+        .into_iter()
+        .map(|(text, eol)| (text.to_string(), eol))
+        .collect();
 
-    // Write back to file if changed
-    if changed {
-        use std::io::Write;
-        let mut file = File::create(file_path)
-            .map_err(|e| format!("Failed to create file for writing: {}", e))?;
-        for line in new_lines {
-            writeln!(file, "{}", line).map_err(|e| format!("Failed to write line: {}", e))?;
+    let mut replacements = 0;
+    let mut skipped_hunks = 0;
+    for hunk in &file.hunks {
+        match lines.get_mut(hunk.line_number.wrapping_sub(1)) {
+            Some((text, _)) if *text == hunk.before => {
+                *text = hunk.after.clone();
+                replacements += hunk.replacements;
+            }
+            _ => skipped_hunks += 1,
         }
     }
 
-    Ok((changed, replacements))
+    if replacements > 0 {
+        let new_content: String = lines
+            .iter()
+            .flat_map(|(text, eol)| [text.as_str(), *eol])
+            .collect();
+        let out = encoding::encode(&new_content, decoded.encoding, decoded.bom)
+            .map_err(|_| skipped(&file.file_path, "unencodable"))?;
+        write_replaced(&file.file_path, &bytes, &out, backup)?;
+    }
+    let replaced = FileReplaced {
+        replacements,
+        previous: (replacements > 0 && backup == ReplaceBackup::History).then_some(decoded.text),
+    };
+    Ok((replaced, skipped_hunks))
 }
 
 #[cfg(test)]
@@ -301,6 +947,7 @@ mod tests {
             use_regex: false,
             file_types: vec!["tex".to_string()],
             max_results: 100,
+            ..Default::default()
         };
 
         assert_eq!(query.text, "test");
@@ -314,4 +961,257 @@ mod tests {
         // Regex special chars should be escaped
         assert!(escaped.contains("\\\\"));
     }
+
+    fn replace_query(text: &str, replace_with: &str, use_regex: bool) -> ReplaceQuery {
+        ReplaceQuery {
+            search: SearchQuery {
+                text: text.to_string(),
+                case_sensitive: false,
+                use_regex,
+                file_types: vec![],
+                max_results: usize::MAX,
+                ..Default::default()
+            },
+            replace_with: replace_with.to_string(),
+            preserve_case: false,
+            convert_to_utf8: false,
+            backup: ReplaceBackup::None,
+        }
+    }
+
+    #[test]
+    fn test_replace_line_captures() {
+        let query = replace_query(r"\\frac\{(\w+)\}\{(\w+)\}", r"\dfrac{$1}{${2}}$$", true);
+        let regex = build_regex(&query.search).unwrap();
+        assert_eq!(
+            replace_line(&regex, r"\frac{a}{b} + \frac{c}{d}", &query),
+            Some((r"\dfrac{a}{b}$ + \dfrac{c}{d}$".to_string(), 2))
+        );
+
+        // Plain-text replacements are literal
+        let query = replace_query("x", "$1", false);
+        let regex = build_regex(&query.search).unwrap();
+        assert_eq!(
+            replace_line(&regex, "x = 1", &query),
+            Some(("$1 = 1".to_string(), 1))
+        );
+    }
+
+    #[test]
+    fn test_replace_line_preserve_case() {
+        let mut query = replace_query("foo", "bar", false);
+        query.preserve_case = true;
+        let regex = build_regex(&query.search).unwrap();
+        assert_eq!(
+            replace_line(&regex, "FOO Foo foo fOo", &query),
+            Some(("BAR Bar bar bar".to_string(), 4))
+        );
+        assert_eq!(replace_line(&regex, "nothing", &query), None);
+    }
+
+    #[test]
+    fn test_whole_word() {
+        let mut query = SearchQuery {
+            text: "\\int".to_string(),
+            whole_word: true,
+            ..Default::default()
+        };
+        let regex = build_regex(&query).unwrap();
+        assert!(regex.is_match("x = \\int f"));
+        assert!(!regex.is_match("\\interval"));
+
+        query.text = "int".to_string();
+        let regex = build_regex(&query).unwrap();
+        assert!(regex.is_match("an int here"));
+        assert!(!regex.is_match("integral"));
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert!(is_binary(b"%PDF-1.5\n\x00\x01"));
+        assert!(!is_binary("\\section{Ελληνικά}".as_bytes()));
+    }
+
+    #[test]
+    fn test_split_lines_keeps_endings() {
+        let content = "a\r\nb\n\nc";
+        let lines = split_lines(content);
+        assert_eq!(
+            lines,
+            vec![("a", "\r\n"), ("b", "\n"), ("", "\n"), ("c", "")]
+        );
+        let joined: String = lines.iter().flat_map(|(t, e)| [*t, *e]).collect();
+        assert_eq!(joined, content);
+    }
+
+    #[test]
+    fn test_context_and_counts() {
+        let path = std::env::temp_dir().join(format!("datatex-context-{}.tex", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        std::fs::write(&path, "x x\na\nb\nx\nc\n").unwrap();
+
+        let query = SearchQuery {
+            text: "x".to_string(),
+            max_results: 1,
+            context_before: Some(5),
+            context_after: Some(1),
+            ..Default::default()
+        };
+        let regex = build_regex(&query).unwrap();
+        let (matches, count) = search_single_file(&path, "r1", &regex, &query).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(matches.len(), 1);
+        assert!(matches[0].context_before.is_empty());
+        assert_eq!(matches[0].context_after, vec!["a"]);
+
+        let query = SearchQuery {
+            max_results: 10,
+            context_before: None,
+            ..query
+        };
+        let (matches, _) = search_single_file(&path, "r1", &regex, &query).unwrap();
+        assert_eq!(matches[1].context_before, vec!["a", "b"]);
+        assert_eq!(matches[1].context_after, vec!["c"]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_search_metadata_fields() {
+        let resource = Resource {
+            id: "r1".to_string(),
+            path: "/data/algebra/ex1.tex".to_string(),
+            kind: "file".to_string(),
+            collection: "algebra".to_string(),
+            title: None,
+            content_hash: None,
+            metadata: None,
+            created_at: None,
+            updated_at: None,
+        };
+        let field = |origin, name: Option<&str>, value: &str| MetadataField {
+            resource_id: "r1".to_string(),
+            origin,
+            name: name.map(str::to_string),
+            value: value.to_string(),
+        };
+        let fields = vec![
+            field(MatchOrigin::Title, None, "Παράγωγοι"),
+            field(MatchOrigin::Tags, None, "algebra derivatives"),
+            field(
+                MatchOrigin::CustomField,
+                Some("source"),
+                "Derivatives workbook",
+            ),
+        ];
+        let query = SearchQuery {
+            text: "derivative".to_string(),
+            max_results: 10,
+            ..Default::default()
+        };
+
+        let matches = search_metadata_fields(&query, &[resource], &fields).unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].origin, MatchOrigin::Tags);
+        assert_eq!((matches[0].match_start, matches[0].match_end), (8, 18));
+        assert_eq!(matches[1].field.as_deref(), Some("source"));
+        assert_eq!(matches[1].file_name, "ex1.tex");
+    }
+
+    #[test]
+    fn test_replace_keeps_line_endings_and_backup() {
+        let path = std::env::temp_dir().join(format!("datatex-replace-{}.tex", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let original = "\\alpha\r\nbeta\r\n\\alpha";
+        std::fs::write(&path, original).unwrap();
+
+        let mut query = replace_query("\\alpha", "\\gamma", false);
+        query.backup = ReplaceBackup::Bak;
+        let regex = build_regex(&query.search).unwrap();
+        let replaced = replace_in_single_file(&path, &regex, &query).unwrap();
+        assert_eq!(replaced.replacements, 2);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "\\gamma\r\nbeta\r\n\\gamma"
+        );
+        let backup = format!("{}.bak", path);
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), original);
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&backup).unwrap();
+    }
+
+    #[test]
+    fn test_replace_refuses_multiline() {
+        let mut query = replace_query("a.b", "c", true);
+        query.search.multiline = true;
+        assert!(preview_replace(&query, Vec::new()).is_err());
+        assert!(replace_in_files(&query, Vec::new()).is_err());
+        query.search.multiline = false;
+        assert!(preview_replace(&query, Vec::new()).is_ok());
+    }
+
+    #[test]
+    fn test_apply_replace_reports_failed_files() {
+        let path = std::env::temp_dir().join(format!("datatex-apply-{}.tex", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        std::fs::write(&path, "alpha\nbeta\n").unwrap();
+        let hunk = |line_number: usize, before: &str| ReplaceHunk {
+            line_number,
+            before: before.to_string(),
+            after: "gamma".to_string(),
+            replacements: 1,
+        };
+        let preview = |file_path: &str, hunks: Vec<ReplaceHunk>| FileReplacePreview {
+            resource_id: "r".to_string(),
+            file_path: file_path.to_string(),
+            file_name: file_name_of(file_path),
+            hunks,
+            encoding: None,
+        };
+
+        let missing = format!("{}.missing", path);
+        let result = apply_replace(
+            &[
+                // The second line changed since the preview
+                preview(&path, vec![hunk(1, "alpha"), hunk(2, "delta")]),
+                preview(&missing, vec![hunk(1, "alpha")]),
+            ],
+            ReplaceBackup::History,
+        )
+        .unwrap();
+        assert_eq!(result.total_files_changed, 1);
+        assert_eq!(result.total_replacements, 1);
+        assert_eq!(result.skipped_hunks, 1);
+        assert_eq!(result.skipped_files.len(), 1);
+        assert_eq!(result.skipped_files[0].file_path, missing);
+        assert_eq!(result.skipped_files[0].reason, "unreadable");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "gamma\nbeta\n");
+        assert_eq!(
+            result.previous_contents,
+            [(path.clone(), "alpha\nbeta\n".to_string())]
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_follows_symlinks() {
+        let dir = std::env::temp_dir().join(format!("datatex-symlink-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("target.tex");
+        let link = dir.join("link.tex");
+        std::fs::write(&target, "old").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_atomic(&link.to_string_lossy(), b"new").unwrap();
+        assert!(std::fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "new");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,143 @@
+//! Path filters for search.
+//!
+//! Include/exclude globs are matched against the whole path of a resource.
+//! A glob that doesn't start with `/` or `**` may match anywhere in it, so
+//! `*.sty` and `build/**` work without spelling out the collection folder.
+//! Optionally, files ignored by a `.gitignore` are left out as well.
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::Gitignore;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+fn build_set(patterns: &[String]) -> Result<Option<GlobSet>, String> {
+    let patterns: Vec<&str> = patterns
+        .iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .collect();
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let anchored = pattern.starts_with('/') || pattern.starts_with("**");
+        let pattern = if anchored {
+            pattern.to_string()
+        } else {
+            format!("**/{}", pattern)
+        };
+        let glob = Glob::new(&pattern).map_err(|e| format!("Invalid glob '{}': {}", pattern, e))?;
+        builder.add(glob);
+    }
+    builder.build().map(Some).map_err(|e| e.to_string())
+}
+
+/// Decides which resource paths a search looks at
+pub struct PathFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    respect_gitignore: bool,
+    /// Parsed `.gitignore` of each directory seen so far (None: has none)
+    gitignores: HashMap<PathBuf, Option<Gitignore>>,
+}
+
+impl PathFilter {
+    pub fn new(
+        include: &[String],
+        exclude: &[String],
+        respect_gitignore: bool,
+    ) -> Result<Self, String> {
+        Ok(PathFilter {
+            include: build_set(include)?,
+            exclude: build_set(exclude)?,
+            respect_gitignore,
+            gitignores: HashMap::new(),
+        })
+    }
+
+    /// Whether nothing is filtered out
+    pub fn is_empty(&self) -> bool {
+        self.include.is_none() && self.exclude.is_none() && !self.respect_gitignore
+    }
+
+    pub fn allows(&mut self, path: &str) -> bool {
+        let path = Path::new(path);
+        if let Some(include) = &self.include {
+            if !include.is_match(path) {
+                return false;
+            }
+        }
+        if let Some(exclude) = &self.exclude {
+            if exclude.is_match(path) {
+                return false;
+            }
+        }
+        !(self.respect_gitignore && self.is_gitignored(path))
+    }
+
+    /// Checks the `.gitignore` files from the file's folder up to the root of
+    /// its repository; the nearest one that mentions the file decides
+    fn is_gitignored(&mut self, path: &Path) -> bool {
+        for dir in path.ancestors().skip(1) {
+            let gitignore = self.gitignores.entry(dir.to_path_buf()).or_insert_with(|| {
+                let file = dir.join(".gitignore");
+                file.is_file().then(|| Gitignore::new(&file).0)
+            });
+            if let Some(gitignore) = gitignore {
+                let matched = gitignore.matched_path_or_any_parents(path, false);
+                if matched.is_ignore() {
+                    return true;
+                }
+                if matched.is_whitelist() {
+                    return false;
+                }
+            }
+            if dir.join(".git").exists() {
+                break;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_globs() {
+        let mut filter = PathFilter::new(
+            &["*.tex".to_string(), "*.sty".to_string()],
+            &["build/**".to_string(), "vendor/*.sty".to_string()],
+            false,
+        )
+        .unwrap();
+        assert!(filter.allows("/data/algebra/ex1.tex"));
+        assert!(!filter.allows("/data/algebra/ex1.log"));
+        assert!(!filter.allows("/data/algebra/build/ex1.tex"));
+        assert!(!filter.allows("/data/vendor/tikz.sty"));
+        assert!(filter.allows("/data/styles/mine.sty"));
+        assert!(PathFilter::new(&["[".to_string()], &[], false).is_err());
+    }
+
+    #[test]
+    fn test_gitignore() {
+        let root = std::env::temp_dir().join(format!("datatex-gitignore-{}", std::process::id()));
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.aux\nbuild/\n").unwrap();
+        std::fs::write(root.join("sub/.gitignore"), "!keep.aux\n").unwrap();
+
+        let mut filter = PathFilter::new(&[], &[], true).unwrap();
+        let path = |p: &str| root.join(p).to_string_lossy().into_owned();
+        assert!(filter.allows(&path("ex.tex")));
+        assert!(!filter.allows(&path("ex.aux")));
+        assert!(!filter.allows(&path("build/ex.tex")));
+        assert!(filter.allows(&path("sub/keep.aux")));
+        assert!(!filter.allows(&path("sub/other.aux")));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,270 @@
+//! LaTeX-aware search scopes.
+//!
+//! A light tokenizer finds comments, math (`$…$`, `$$…$$`, `\(…\)`, `\[…\]`
+//! and the display math environments), environment bodies and the preamble
+//! of a source, so matches can be limited to (or kept out of) them.
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Environments whose body is math
+const MATH_ENVIRONMENTS: &[&str] = &[
+    "equation",
+    "align",
+    "alignat",
+    "flalign",
+    "gather",
+    "multline",
+    "eqnarray",
+    "math",
+    "displaymath",
+];
+
+/// Part of a LaTeX source to search in
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SearchScope {
+    #[default]
+    All,
+    /// Inline and display math
+    Math,
+    /// Inside `\begin{name}…\end{name}`
+    Environment { name: String },
+    /// Before `\begin{document}`; files without one have no preamble
+    Preamble,
+}
+
+/// Regions of a source found by `scan`
+#[derive(Debug, Default)]
+struct Regions {
+    comments: Vec<Range<usize>>,
+    math: Vec<Range<usize>>,
+    /// (name, body) of every closed environment
+    environments: Vec<(String, Range<usize>)>,
+    document_start: Option<usize>,
+}
+
+enum MathOpen {
+    Dollar,
+    DoubleDollar,
+    Paren,
+    Bracket,
+    Environment(String),
+}
+
+fn is_math_environment(name: &str) -> bool {
+    MATH_ENVIRONMENTS.contains(&name.trim_end_matches('*'))
+}
+
+/// `{name}` after `\begin`/`\end` at `from`: (name, offset after the brace)
+fn environment_name(content: &str, from: usize) -> Option<(&str, usize)> {
+    let rest = &content[from..];
+    let open = rest.len() - rest.trim_start().len();
+    if !rest[open..].starts_with('{') {
+        return None;
+    }
+    let close = rest[open..].find('}')? + open;
+    Some((rest[open + 1..close].trim(), from + close + 1))
+}
+
+fn scan(content: &str) -> Regions {
+    let bytes = content.as_bytes();
+    let mut regions = Regions::default();
+    let mut math: Option<(MathOpen, usize)> = None;
+    let mut stack: Vec<(String, usize)> = Vec::new();
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let end = content[i..].find('\n').map_or(content.len(), |n| i + n);
+                regions.comments.push(i..end);
+                i = end;
+            }
+            b'$' => {
+                let double = bytes.get(i + 1) == Some(&b'$');
+                let width = if double { 2 } else { 1 };
+                match &math {
+                    None => {
+                        math = Some((
+                            if double {
+                                MathOpen::DoubleDollar
+                            } else {
+                                MathOpen::Dollar
+                            },
+                            i + width,
+                        ))
+                    }
+                    Some((MathOpen::Dollar, start)) if !double => {
+                        regions.math.push(*start..i);
+                        math = None;
+                    }
+                    Some((MathOpen::DoubleDollar, start)) if double => {
+                        regions.math.push(*start..i);
+                        math = None;
+                    }
+                    _ => {}
+                }
+                i += width;
+            }
+            b'\\' => {
+                let name_len = bytes[i + 1..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_alphabetic())
+                    .count();
+                if name_len == 0 {
+                    // Control symbol: \( \) \[ \] or an escaped character
+                    match (bytes.get(i + 1), &math) {
+                        (Some(b'('), None) => math = Some((MathOpen::Paren, i + 2)),
+                        (Some(b'['), None) => math = Some((MathOpen::Bracket, i + 2)),
+                        (Some(b')'), Some((MathOpen::Paren, start)))
+                        | (Some(b']'), Some((MathOpen::Bracket, start))) => {
+                            regions.math.push(*start..i);
+                            math = None;
+                        }
+                        _ => {}
+                    }
+                    // Skip the escaped character (which may be multi-byte)
+                    i += 1 + content[i + 1..].chars().next().map_or(0, char::len_utf8);
+                    continue;
+                }
+
+                let after = i + 1 + name_len;
+                let command = &content[i + 1..after];
+                if command != "begin" && command != "end" {
+                    i = after;
+                    continue;
+                }
+                let Some((name, body_start)) = environment_name(content, after) else {
+                    i = after;
+                    continue;
+                };
+
+                if command == "begin" {
+                    if name == "document" && regions.document_start.is_none() {
+                        regions.document_start = Some(i);
+                    }
+                    if math.is_none() && is_math_environment(name) {
+                        math = Some((MathOpen::Environment(name.to_string()), body_start));
+                    }
+                    stack.push((name.to_string(), body_start));
+                } else {
+                    if let Some((MathOpen::Environment(open), start)) = &math {
+                        if open == name {
+                            regions.math.push(*start..i);
+                            math = None;
+                        }
+                    }
+                    if let Some(pos) = stack.iter().rposition(|(open, _)| open == name) {
+                        let (_, start) = stack.remove(pos);
+                        stack.truncate(pos);
+                        regions.environments.push((name.to_string(), start..i));
+                    }
+                }
+                i = body_start;
+            }
+            _ => i += 1,
+        }
+    }
+
+    // Unclosed math runs to the end of the file
+    if let Some((_, start)) = math {
+        regions.math.push(start..content.len());
+    }
+    regions
+}
+
+/// Decides which matches of a source lie in the requested scope
+pub struct ScopeFilter {
+    comments: Vec<Range<usize>>,
+    /// None: anywhere outside comments (if skipped)
+    allowed: Option<Vec<Range<usize>>>,
+}
+
+impl ScopeFilter {
+    /// None when nothing needs filtering
+    pub fn new(content: &str, scope: &SearchScope, skip_comments: bool) -> Option<Self> {
+        if *scope == SearchScope::All && !skip_comments {
+            return None;
+        }
+        let regions = scan(content);
+        let allowed = match scope {
+            SearchScope::All => None,
+            SearchScope::Math => Some(regions.math),
+            SearchScope::Environment { name } => Some(
+                regions
+                    .environments
+                    .into_iter()
+                    .filter(|(env, _)| env == name)
+                    .map(|(_, range)| range)
+                    .collect(),
+            ),
+            SearchScope::Preamble => Some(
+                regions
+                    .document_start
+                    .map(|end| 0..end)
+                    .into_iter()
+                    .collect(),
+            ),
+        };
+        Some(ScopeFilter {
+            comments: if skip_comments {
+                regions.comments
+            } else {
+                Vec::new()
+            },
+            allowed,
+        })
+    }
+
+    /// Whether the match at `start..end` (byte offsets in the source) counts
+    pub fn allows(&self, start: usize, end: usize) -> bool {
+        if self
+            .comments
+            .iter()
+            .any(|c| start < c.end && c.start < end.max(start + 1))
+        {
+            return false;
+        }
+        match &self.allowed {
+            Some(ranges) => ranges.iter().any(|r| r.start <= start && end <= r.end),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = "\\usepackage{x} % alpha in comment\n\\begin{document}\nalpha $\\alpha + 1$ and \\% alpha\n\\begin{align}\n\\beta \\\\ \\alpha\n\\end{align}\n\\begin{proof}alpha\\end{proof}\n\\end{document}\n";
+
+    fn allowed(scope: SearchScope, skip_comments: bool, needle: &str) -> Vec<usize> {
+        let filter = ScopeFilter::new(SRC, &scope, skip_comments).unwrap();
+        SRC.match_indices(needle)
+            .filter(|(i, m)| filter.allows(*i, i + m.len()))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn test_scopes() {
+        let all = SRC.match_indices("alpha").count();
+        assert_eq!(all, 6);
+        assert_eq!(allowed(SearchScope::All, true, "alpha").len(), 5);
+        assert_eq!(allowed(SearchScope::Math, false, "alpha").len(), 2);
+        assert_eq!(
+            allowed(
+                SearchScope::Environment {
+                    name: "proof".to_string()
+                },
+                false,
+                "alpha"
+            )
+            .len(),
+            1
+        );
+        assert_eq!(allowed(SearchScope::Preamble, true, "usepackage").len(), 1);
+        assert_eq!(allowed(SearchScope::Preamble, true, "alpha").len(), 0);
+    }
+}
@@ -0,0 +1,158 @@
+//! Sessions
+//!
+//! The state of the window when it was closed (open tabs with their cursor,
+//! expanded tree folders, project folder, collections) is saved in
+//! `sessions` / `session_tabs` (migration 027) and handed back on the next
+//! start, so the user resumes where they left off.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite};
+use std::path::Path;
+
+/// Sessions kept; older ones are dropped when a new one is saved
+const MAX_SESSIONS: i64 = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionTab {
+    pub path: String,
+    pub cursor_line: i64,
+    pub cursor_column: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    /// Set by the database
+    #[serde(default)]
+    pub id: Option<i64>,
+    pub project_root: Option<String>,
+    pub active_collection: Option<String>,
+    #[serde(default)]
+    pub loaded_collections: Vec<String>,
+    #[serde(default)]
+    pub expanded_nodes: Vec<String>,
+    #[serde(default)]
+    pub tabs: Vec<SessionTab>,
+    pub active_tab: Option<String>,
+    #[serde(default)]
+    pub saved_at: Option<String>,
+}
+
+/// Store `session` as the latest one and return its id
+pub async fn save(pool: &Pool<Sqlite>, session: &Session) -> Result<i64, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    let id = sqlx::query(
+        "INSERT INTO sessions (project_root, active_collection, loaded_collections, expanded_nodes, active_tab)
+         VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(&session.project_root)
+    .bind(&session.active_collection)
+    .bind(serde_json::to_string(&session.loaded_collections).map_err(|e| e.to_string())?)
+    .bind(serde_json::to_string(&session.expanded_nodes).map_err(|e| e.to_string())?)
+    .bind(&session.active_tab)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?
+    .last_insert_rowid();
+
+    for (position, tab) in session.tabs.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO session_tabs (session_id, position, path, cursor_line, cursor_column)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(position as i64)
+        .bind(&tab.path)
+        .bind(tab.cursor_line.max(1))
+        .bind(tab.cursor_column.max(1))
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    // Keep only the latest sessions
+    let keep = "SELECT id FROM sessions ORDER BY id DESC LIMIT ?";
+    sqlx::query(&format!(
+        "DELETE FROM session_tabs WHERE session_id NOT IN ({})",
+        keep
+    ))
+    .bind(MAX_SESSIONS)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    sqlx::query(&format!("DELETE FROM sessions WHERE id NOT IN ({})", keep))
+        .bind(MAX_SESSIONS)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(id)
+}
+
+/// The most recent session, without the tabs whose file is gone and the
+/// collections that were deleted since
+pub async fn get_last_session(pool: &Pool<Sqlite>) -> Result<Option<Session>, String> {
+    let Some(row) = sqlx::query(
+        "SELECT id, project_root, active_collection, loaded_collections, expanded_nodes,
+                active_tab, saved_at
+         FROM sessions ORDER BY id DESC LIMIT 1",
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    else {
+        return Ok(None);
+    };
+    let id: i64 = row.get("id");
+
+    let tabs: Vec<SessionTab> = sqlx::query(
+        "SELECT path, cursor_line, cursor_column FROM session_tabs
+         WHERE session_id = ? ORDER BY position",
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .iter()
+    .map(|r| SessionTab {
+        path: r.get("path"),
+        cursor_line: r.get("cursor_line"),
+        cursor_column: r.get("cursor_column"),
+    })
+    .filter(|tab| Path::new(&tab.path).is_file())
+    .collect();
+
+    let existing: Vec<String> = sqlx::query_scalar("SELECT name FROM collections")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let parse_list = |column: &str| -> Vec<String> {
+        serde_json::from_str(&row.get::<String, _>(column)).unwrap_or_default()
+    };
+    let loaded_collections: Vec<String> = parse_list("loaded_collections")
+        .into_iter()
+        .filter(|c| existing.contains(c))
+        .collect();
+    let active_collection: Option<String> = row
+        .get::<Option<String>, _>("active_collection")
+        .filter(|c| existing.contains(c));
+    let active_tab: Option<String> = row
+        .get::<Option<String>, _>("active_tab")
+        .filter(|path| tabs.iter().any(|t| &t.path == path));
+
+    Ok(Some(Session {
+        id: Some(id),
+        project_root: row
+            .get::<Option<String>, _>("project_root")
+            .filter(|p| Path::new(p).is_dir()),
+        active_collection,
+        loaded_collections,
+        expanded_nodes: parse_list("expanded_nodes"),
+        tabs,
+        active_tab,
+        saved_at: row.get("saved_at"),
+    }))
+}
@@ -0,0 +1,335 @@
+//! Settings
+//!
+//! Application preferences the backend acts on (editor font, default LaTeX
+//! engine, tool paths, search defaults, git preferences, spelling language,
+//! the local HTTP API),
+//! stored as `settings.json` in the platform config directory. Every
+//! section has defaults, so a file from an older version, or a partial one,
+//! still loads; unknown keys and out-of-range values are rejected on update.
+//!
+//! The settings are read once and kept in memory; `current` hands out a
+//! copy to modules that need them, and `update` validates and persists a
+//! change before it takes effect.
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{OnceLock, RwLock};
+
+/// LaTeX engines a document can be compiled with by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    #[default]
+    Pdflatex,
+    Xelatex,
+    Lualatex,
+    Latexmk,
+}
+
+impl Engine {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Engine::Pdflatex => "pdflatex",
+            Engine::Xelatex => "xelatex",
+            Engine::Lualatex => "lualatex",
+            Engine::Latexmk => "latexmk",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct EditorSettings {
+    pub font_family: String,
+    pub font_size: u32,
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        EditorSettings {
+            font_family: "Consolas, monospace".to_string(),
+            font_size: 14,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct CompileSettings {
+    /// Used when a resource or request doesn't name an engine
+    pub default_engine: Engine,
+}
+
+/// Where external programs are found, when not on the `PATH`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct ToolPaths {
+    /// Directories searched for the TeX binaries before the usual ones
+    pub tex_bin_dirs: Vec<String>,
+    /// A texlab binary to use instead of the downloaded one
+    pub texlab: Option<String>,
+    /// The pandoc binary, when it isn't on the `PATH`
+    pub pandoc: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct SearchDefaults {
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    pub use_regex: bool,
+    pub max_results: usize,
+    pub respect_gitignore: bool,
+}
+
+impl Default for SearchDefaults {
+    fn default() -> Self {
+        SearchDefaults {
+            case_sensitive: false,
+            whole_word: false,
+            use_regex: false,
+            max_results: 1000,
+            respect_gitignore: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct GitPreferences {
+    /// Branch created by `git init`
+    pub default_branch: String,
+    /// Snapshot after each successful compile, in repositories that have
+    /// snapshots turned on (see `git_snapshots`)
+    pub snapshot_after_compile: bool,
+}
+
+impl Default for GitPreferences {
+    fn default() -> Self {
+        GitPreferences {
+            default_branch: "main".to_string(),
+            snapshot_after_compile: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct SpellingSettings {
+    /// Hunspell dictionary used when a check doesn't name one
+    pub language: String,
+}
+
+impl Default for SpellingSettings {
+    fn default() -> Self {
+        SpellingSettings {
+            language: "en_US".to_string(),
+        }
+    }
+}
+
+/// The read-only HTTP API for external tools (see `http_api`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct ApiSettings {
+    pub enabled: bool,
+    /// Port on 127.0.0.1 the server listens on
+    pub port: u16,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        ApiSettings {
+            enabled: false,
+            port: 27183,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct Settings {
+    pub editor: EditorSettings,
+    pub compile: CompileSettings,
+    pub tools: ToolPaths,
+    pub search: SearchDefaults,
+    pub git: GitPreferences,
+    pub spelling: SpellingSettings,
+    pub api: ApiSettings,
+}
+
+impl Settings {
+    /// Checks that serde can't express
+    pub fn validate(&self) -> Result<(), String> {
+        if !(6..=72).contains(&self.editor.font_size) {
+            return Err(format!(
+                "Editor font size must be between 6 and 72, got {}",
+                self.editor.font_size
+            ));
+        }
+        if self.editor.font_family.trim().is_empty() {
+            return Err("Editor font family can't be empty".to_string());
+        }
+        if self.search.max_results == 0 {
+            return Err("Search needs to return at least one result".to_string());
+        }
+        let branch = self.git.default_branch.trim();
+        if branch.is_empty() || !git2::Reference::is_valid_name(&format!("refs/heads/{}", branch)) {
+            return Err(format!("Invalid default branch name: {:?}", branch));
+        }
+        let language = &self.spelling.language;
+        if language.is_empty()
+            || !language
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(format!("Invalid spelling language: {:?}", language));
+        }
+        if self.api.port < 1024 {
+            return Err(format!(
+                "The API port must be 1024 or higher, got {}",
+                self.api.port
+            ));
+        }
+        if let Some(texlab) = &self.tools.texlab {
+            if texlab.trim().is_empty() {
+                return Err("The texlab path can't be empty".to_string());
+            }
+        }
+        if let Some(pandoc) = &self.tools.pandoc {
+            if pandoc.trim().is_empty() {
+                return Err("The pandoc path can't be empty".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Merge `patch` into `target`: objects merge key by key, `null` removes
+/// a key (so it falls back to its default) and anything else replaces
+fn merge(target: &mut Value, patch: Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                if value.is_null() {
+                    target.remove(&key);
+                } else {
+                    merge(target.entry(key).or_insert(Value::Null), value);
+                }
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+/// `base` with `patch` applied, checked field by field
+fn apply(base: &Settings, patch: Value) -> Result<Settings, String> {
+    let mut value = serde_json::to_value(base).map_err(|e| e.to_string())?;
+    merge(&mut value, patch);
+    let settings: Settings =
+        serde_json::from_value(value).map_err(|e| format!("Invalid settings: {}", e))?;
+    settings.validate()?;
+    Ok(settings)
+}
+
+pub fn settings_path() -> Result<PathBuf, String> {
+    let proj_dirs =
+        ProjectDirs::from("", "", "datatex").ok_or("Could not determine project directories")?;
+    Ok(proj_dirs.config_dir().join("settings.json"))
+}
+
+/// The settings on disk; defaults when there is no file yet
+fn load() -> Result<Settings, String> {
+    let path = settings_path()?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Settings::default()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    let settings: Settings = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid settings in {}: {}", path.display(), e))?;
+    settings.validate()?;
+    Ok(settings)
+}
+
+/// Write through a temporary file, so a crash never leaves half a file
+fn save(settings: &Settings) -> Result<(), String> {
+    let path = settings_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, json).map_err(|e| e.to_string())?;
+    fs::rename(&temp, &path).map_err(|e| e.to_string())
+}
+
+fn cell() -> &'static RwLock<Settings> {
+    static SETTINGS: OnceLock<RwLock<Settings>> = OnceLock::new();
+    SETTINGS.get_or_init(|| {
+        RwLock::new(load().unwrap_or_else(|e| {
+            // Keep the file as it is, so the user can fix it by hand
+            eprintln!("{}; using default settings", e);
+            Settings::default()
+        }))
+    })
+}
+
+/// The settings in effect
+pub fn current() -> Settings {
+    cell().read().map(|s| s.clone()).unwrap_or_default()
+}
+
+/// Apply a partial change (same shape as `Settings`, any subset of keys),
+/// save it and return the new settings. Nothing changes if it's invalid.
+pub fn update(patch: Value) -> Result<Settings, String> {
+    let mut guard = cell().write().map_err(|e| e.to_string())?;
+    let updated = apply(&guard, patch)?;
+    save(&updated)?;
+    *guard = updated.clone();
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_partial_file_uses_defaults() {
+        let settings: Settings =
+            serde_json::from_value(json!({ "compile": { "defaultEngine": "xelatex" } })).unwrap();
+        assert_eq!(settings.compile.default_engine, Engine::Xelatex);
+        assert_eq!(settings.editor, EditorSettings::default());
+        assert_eq!(settings.git.default_branch, "main");
+    }
+
+    #[test]
+    fn test_apply() {
+        let base = Settings::default();
+
+        let updated = apply(
+            &base,
+            json!({ "editor": { "fontSize": 16 }, "tools": { "texBinDirs": ["/opt/tex"] } }),
+        )
+        .unwrap();
+        assert_eq!(updated.editor.font_size, 16);
+        assert_eq!(updated.editor.font_family, base.editor.font_family);
+        assert_eq!(updated.tools.tex_bin_dirs, ["/opt/tex"]);
+
+        // null goes back to the default
+        let reset = apply(&updated, json!({ "editor": { "fontSize": null } })).unwrap();
+        assert_eq!(reset.editor.font_size, 14);
+
+        // Unknown keys, wrong types and bad values are refused
+        assert!(apply(&base, json!({ "editor": { "fontSzie": 16 } })).is_err());
+        assert!(apply(&base, json!({ "compile": { "defaultEngine": "tex" } })).is_err());
+        assert!(apply(&base, json!({ "editor": { "fontSize": 200 } })).is_err());
+        assert!(apply(&base, json!({ "search": { "maxResults": 0 } })).is_err());
+        assert!(apply(&base, json!({ "git": { "defaultBranch": "a..b" } })).is_err());
+        assert!(apply(&base, json!({ "api": { "port": 80 } })).is_err());
+    }
+}
@@ -0,0 +1,543 @@
+//! Spell Checking
+//!
+//! Checks the prose of LaTeX sources against Hunspell dictionaries (read
+//! with `spellbook`). The tokenizer skips commands, comments, math and
+//! verbatim-like environments, and the arguments of commands that hold
+//! keys or paths (`\label`, `\cite`, `\includegraphics`, ...), so only text
+//! that ends up on the page is checked.
+//!
+//! Dictionaries are downloaded from the LibreOffice dictionaries repository
+//! on first use and kept under `<data dir>/dictionaries`; any other
+//! `<code>.aff`/`<code>.dic` pair dropped there works too. Words the user
+//! adds are stored per project in `spelling_words` (migration 029).
+
+use serde::Serialize;
+use spellbook::Dictionary;
+use sqlx::{Pool, Sqlite};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+const DICTIONARY_BASE_URL: &str =
+    "https://raw.githubusercontent.com/LibreOffice/dictionaries/master";
+
+/// Downloadable dictionaries: language code and location in the
+/// LibreOffice repository, without the `.aff`/`.dic` extension
+const DOWNLOADABLE: &[(&str, &str)] = &[
+    ("en_US", "en/en_US"),
+    ("en_GB", "en/en_GB"),
+    ("el_GR", "el_GR/el_GR"),
+    ("de_DE", "de/de_DE_frami"),
+    ("fr_FR", "fr_FR/fr"),
+    ("es_ES", "es/es_ES"),
+    ("it_IT", "it_IT/it_IT"),
+    ("pt_PT", "pt_PT/pt_PT"),
+    ("ru_RU", "ru_RU/ru_RU"),
+];
+
+/// Environments whose body is not prose
+const SKIPPED_ENVIRONMENTS: &[&str] = &[
+    "equation",
+    "align",
+    "alignat",
+    "gather",
+    "multline",
+    "flalign",
+    "eqnarray",
+    "math",
+    "displaymath",
+    "verbatim",
+    "Verbatim",
+    "lstlisting",
+    "minted",
+    "comment",
+    "tikzpicture",
+];
+
+/// Commands whose first argument is a key, a path or code
+const SKIPPED_ARGUMENTS: &[&str] = &[
+    "label",
+    "ref",
+    "eqref",
+    "pageref",
+    "autoref",
+    "cref",
+    "Cref",
+    "cite",
+    "citep",
+    "citet",
+    "parencite",
+    "textcite",
+    "nocite",
+    "input",
+    "include",
+    "includeonly",
+    "includegraphics",
+    "graphicspath",
+    "usepackage",
+    "RequirePackage",
+    "documentclass",
+    "bibliography",
+    "bibliographystyle",
+    "addbibresource",
+    "url",
+    "href",
+    "newcommand",
+    "renewcommand",
+    "providecommand",
+    "newenvironment",
+    "renewenvironment",
+    "setlength",
+    "addtolength",
+    "setcounter",
+    "addtocounter",
+    "vspace",
+    "hspace",
+    "color",
+    "textcolor",
+    "pagestyle",
+    "thispagestyle",
+    "lstinputlisting",
+    "hypersetup",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Misspelling {
+    pub word: String,
+    /// 1-based
+    pub line: u32,
+    /// 1-based, in UTF-16 code units like the editor
+    pub column: u32,
+    /// In UTF-16 code units
+    pub length: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpellLanguage {
+    pub code: String,
+    pub installed: bool,
+    pub downloadable: bool,
+}
+
+/// Skip a `{...}` group starting at `i` (which must be `{`); returns the
+/// index after the closing brace
+fn skip_group(bytes: &[u8], mut i: usize) -> usize {
+    let mut depth = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// Index after the first `needle` at or after `from`, or the end
+fn skip_past(source: &str, from: usize, needle: &str) -> usize {
+    source[from..]
+        .find(needle)
+        .map_or(source.len(), |pos| from + pos + needle.len())
+}
+
+fn skip_spaces(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && (bytes[i] == b' ' || bytes[i] == b'\t') {
+        i += 1;
+    }
+    i
+}
+
+/// The words of `source` that are prose, with their byte offsets
+pub fn prose_words(source: &str) -> Vec<(usize, &str)> {
+    let bytes = source.as_bytes();
+    let mut words = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => i = skip_past(source, i, "\n"),
+            b'$' => {
+                i = if bytes.get(i + 1) == Some(&b'$') {
+                    skip_past(source, i + 2, "$$")
+                } else {
+                    // Up to the next unescaped dollar
+                    let mut j = i + 1;
+                    while j < bytes.len() && bytes[j] != b'$' {
+                        j += if bytes[j] == b'\\' { 2 } else { 1 };
+                    }
+                    (j + 1).min(bytes.len())
+                };
+            }
+            b'\\' => {
+                let start = i + 1;
+                let mut end = start;
+                while end < bytes.len() && bytes[end].is_ascii_alphabetic() {
+                    end += 1;
+                }
+                if end == start {
+                    // Control symbol: \( \[ math, anything else (\%, \\) as is
+                    i = match bytes.get(start) {
+                        Some(b'(') => skip_past(source, start, "\\)"),
+                        Some(b'[') => skip_past(source, start, "\\]"),
+                        Some(_) => start + source[start..].chars().next().map_or(1, char::len_utf8),
+                        None => bytes.len(),
+                    };
+                    continue;
+                }
+                let name = &source[start..end];
+                i = end;
+                if bytes.get(i) == Some(&b'*') {
+                    i += 1;
+                }
+                match name {
+                    "begin" | "end" => {
+                        let open = skip_spaces(bytes, i);
+                        if bytes.get(open) != Some(&b'{') {
+                            continue;
+                        }
+                        i = skip_group(bytes, open);
+                        if bytes[i - 1] != b'}' {
+                            continue;
+                        }
+                        let env = &source[open + 1..i - 1];
+                        if name == "begin"
+                            && SKIPPED_ENVIRONMENTS.contains(&env.trim_end_matches('*'))
+                        {
+                            i = skip_past(source, i, &format!("\\end{{{}}}", env));
+                        }
+                    }
+                    "verb" => {
+                        // \verb|...| with any delimiter
+                        if let Some(delim) = source[i..].chars().next() {
+                            let from = i + delim.len_utf8();
+                            i = skip_past(source, from, delim.encode_utf8(&mut [0; 4]));
+                        }
+                    }
+                    _ if SKIPPED_ARGUMENTS.contains(&name) => loop {
+                        let next = skip_spaces(bytes, i);
+                        match bytes.get(next) {
+                            Some(b'[') => i = skip_past(source, next, "]"),
+                            Some(b'{') => {
+                                i = skip_group(bytes, next);
+                                break;
+                            }
+                            _ => break,
+                        }
+                    },
+                    _ => {}
+                }
+            }
+            _ => {
+                let Some(c) = source[i..].chars().next() else {
+                    break;
+                };
+                if !c.is_alphabetic() {
+                    i += c.len_utf8();
+                    continue;
+                }
+                // Letters, with apostrophes inside (don't, l'école)
+                let start = i;
+                let mut end = i;
+                let mut chars = source[i..].char_indices().peekable();
+                while let Some((offset, c)) = chars.next() {
+                    let inner_apostrophe = (c == '\'' || c == '’')
+                        && chars.peek().is_some_and(|(_, next)| next.is_alphabetic());
+                    if !c.is_alphabetic() && !inner_apostrophe {
+                        break;
+                    }
+                    end = i + offset + c.len_utf8();
+                }
+                words.push((start, &source[start..end]));
+                i = end;
+            }
+        }
+    }
+    words
+}
+
+/// Byte offset to 1-based (line, UTF-16 column)
+struct LineIndex<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(source: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex { source, starts }
+    }
+
+    fn position(&self, offset: usize) -> (u32, u32) {
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.source[self.starts[line]..offset]
+            .encode_utf16()
+            .count();
+        (line as u32 + 1, column as u32 + 1)
+    }
+}
+
+/// Words of `source` that are neither in `dictionary` nor in `custom`.
+/// Acronyms (all capitals) and single letters are left alone.
+pub fn check_text(
+    dictionary: &Dictionary,
+    custom: &HashSet<String>,
+    source: &str,
+) -> Vec<Misspelling> {
+    let index = LineIndex::new(source);
+    prose_words(source)
+        .into_iter()
+        .filter(|(_, word)| {
+            let letters = word.chars().filter(|c| c.is_alphabetic()).count();
+            letters > 1
+                && !word.chars().all(|c| !c.is_lowercase())
+                && !custom.contains(*word)
+                && !custom.contains(&word.to_lowercase())
+                && !dictionary.check(word)
+        })
+        .map(|(offset, word)| {
+            let (line, column) = index.position(offset);
+            Misspelling {
+                word: word.to_string(),
+                line,
+                column,
+                length: word.encode_utf16().count() as u32,
+            }
+        })
+        .collect()
+}
+
+pub fn suggest(dictionary: &Dictionary, word: &str) -> Vec<String> {
+    let mut suggestions = Vec::new();
+    dictionary.suggest(word, &mut suggestions);
+    suggestions
+}
+
+pub fn dictionaries_dir() -> Result<PathBuf, String> {
+    let proj_dirs = directories::ProjectDirs::from("", "", "datatex")
+        .ok_or("Could not determine project directories")?;
+    Ok(proj_dirs.data_dir().join("dictionaries"))
+}
+
+/// Language codes are file names; keep them to letters, digits, `_`, `-`
+fn valid_code(code: &str) -> bool {
+    !code.is_empty()
+        && code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Installed dictionaries, plus the ones that can be downloaded
+pub fn languages() -> Result<Vec<SpellLanguage>, String> {
+    let dir = dictionaries_dir()?;
+    let mut codes: Vec<String> = fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "dic" {
+                return None;
+            }
+            Some(path.file_stem()?.to_str()?.to_string())
+        })
+        .collect();
+    codes.extend(DOWNLOADABLE.iter().map(|(code, _)| code.to_string()));
+    codes.sort();
+    codes.dedup();
+    Ok(codes
+        .into_iter()
+        .map(|code| SpellLanguage {
+            installed: dir.join(format!("{}.dic", code)).exists(),
+            downloadable: DOWNLOADABLE.iter().any(|(c, _)| *c == code),
+            code,
+        })
+        .collect())
+}
+
+/// Hunspell files name their encoding in the `SET` line of the .aff
+fn decode(bytes: &[u8], encoding: Option<&str>) -> String {
+    let encoding = encoding
+        .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    encoding.decode(bytes).0.into_owned()
+}
+
+fn aff_encoding(aff: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(aff).lines().find_map(|line| {
+        let label = line.trim().strip_prefix("SET")?;
+        Some(label.trim().to_string())
+    })
+}
+
+async fn download(url: &str) -> Result<Vec<u8>, String> {
+    let response = reqwest::get(url)
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Download of {} failed with status: {}",
+            url,
+            response.status()
+        ));
+    }
+    let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+    Ok(bytes.to_vec())
+}
+
+fn cache() -> &'static Mutex<HashMap<String, Arc<Dictionary>>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Arc<Dictionary>>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// The dictionary for `code`, downloading it the first time
+pub async fn dictionary(code: &str) -> Result<Arc<Dictionary>, String> {
+    if !valid_code(code) {
+        return Err(format!("Invalid language code: {:?}", code));
+    }
+    if let Some(dictionary) = cache().lock().map_err(|e| e.to_string())?.get(code) {
+        return Ok(dictionary.clone());
+    }
+
+    let dir = dictionaries_dir()?;
+    let aff_path = dir.join(format!("{}.aff", code));
+    let dic_path = dir.join(format!("{}.dic", code));
+    if !aff_path.exists() || !dic_path.exists() {
+        let (_, location) = DOWNLOADABLE
+            .iter()
+            .find(|(c, _)| *c == code)
+            .ok_or_else(|| format!("No dictionary for {} is installed", code))?;
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        println!("📥 Downloading {} dictionary", code);
+        let aff = download(&format!("{}/{}.aff", DICTIONARY_BASE_URL, location)).await?;
+        let dic = download(&format!("{}/{}.dic", DICTIONARY_BASE_URL, location)).await?;
+        fs::write(&aff_path, aff).map_err(|e| e.to_string())?;
+        fs::write(&dic_path, dic).map_err(|e| e.to_string())?;
+    }
+
+    let aff = fs::read(&aff_path).map_err(|e| e.to_string())?;
+    let dic = fs::read(&dic_path).map_err(|e| e.to_string())?;
+    let encoding = aff_encoding(&aff);
+    let dictionary = tauri::async_runtime::spawn_blocking(move || {
+        Dictionary::new(
+            &decode(&aff, encoding.as_deref()),
+            &decode(&dic, encoding.as_deref()),
+        )
+        .map_err(|e| format!("Invalid dictionary: {}", e))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let dictionary = Arc::new(dictionary);
+    cache()
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(code.to_string(), dictionary.clone());
+    Ok(dictionary)
+}
+
+/// Words added to the dictionary of `project` ("" outside a project)
+pub async fn custom_words(pool: &Pool<Sqlite>, project: &str) -> Result<HashSet<String>, String> {
+    let words: Vec<String> =
+        sqlx::query_scalar("SELECT word FROM spelling_words WHERE project = ? ORDER BY word")
+            .bind(project)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    Ok(words.into_iter().collect())
+}
+
+pub async fn add_word(pool: &Pool<Sqlite>, project: &str, word: &str) -> Result<(), String> {
+    let word = word.trim();
+    if word.is_empty() || word.chars().any(char::is_whitespace) {
+        return Err(format!("Not a single word: {:?}", word));
+    }
+    sqlx::query("INSERT OR IGNORE INTO spelling_words (project, word) VALUES (?, ?)")
+        .bind(project)
+        .bind(word)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub async fn remove_word(pool: &Pool<Sqlite>, project: &str, word: &str) -> Result<(), String> {
+    sqlx::query("DELETE FROM spelling_words WHERE project = ? AND word = ?")
+        .bind(project)
+        .bind(word)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(source: &str) -> Vec<&str> {
+        prose_words(source).into_iter().map(|(_, w)| w).collect()
+    }
+
+    #[test]
+    fn test_prose_words() {
+        assert_eq!(
+            words("Let \\(x\\) be $y_1$, see~\\ref{eq:one} and \\textbf{bold} text."),
+            ["Let", "be", "see", "and", "bold", "text"]
+        );
+        assert_eq!(
+            words("A % comment here\n\\begin{equation*}\nfoo\n\\end{equation*} don't"),
+            ["A", "don't"]
+        );
+        assert_eq!(
+            words("\\includegraphics[width=5cm]{figs/plot} \\verb|code| Καλημέρα"),
+            ["Καλημέρα"]
+        );
+        assert_eq!(words("\\section{Intro} \\cite[p.~3]{knuth}"), ["Intro"]);
+    }
+
+    #[test]
+    fn test_check_text() {
+        let dictionary = Dictionary::new("SET UTF-8\n", "3\nhello\nworld\nκόσμος\n").unwrap();
+        let custom: HashSet<String> = ["datatex".to_string()].into();
+        let source = "hello wrold\n% tpyo\nκόσμος DataTeX NASA \\textit{wolrd}";
+        let misspelled = check_text(&dictionary, &custom, source);
+        assert_eq!(
+            misspelled,
+            [
+                Misspelling {
+                    word: "wrold".to_string(),
+                    line: 1,
+                    column: 7,
+                    length: 5
+                },
+                Misspelling {
+                    word: "wolrd".to_string(),
+                    line: 3,
+                    column: 29,
+                    length: 5
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_aff_encoding() {
+        assert_eq!(
+            aff_encoding(b"# x\nSET ISO8859-7\nTRY abc").as_deref(),
+            Some("ISO8859-7")
+        );
+        assert_eq!(decode(&[0xe1], Some("ISO8859-7")), "α");
+    }
+}
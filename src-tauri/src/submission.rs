@@ -0,0 +1,508 @@
+//! Submission Packaging
+//!
+//! Zips a document with exactly the files it needs, the way arXiv and most
+//! journals want it. The file list comes from the `.fls` recorder file of
+//! the last compilation when there is one, and from the dependency scanner
+//! otherwise. The compiled `.bbl` goes in instead of the `.bib` files, so
+//! the bibliography comes out as the author saw it. Comments are stripped
+//! from the sources, and `\input`/`\include` can be flattened into the
+//! main file.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::dependency_scanner::{self, Reference};
+use crate::search::encoding::{self, Decoded};
+
+/// Files TeX writes and reads back; they don't belong in a submission
+const GENERATED_EXTENSIONS: &[&str] = &[
+    "aux",
+    "bcf",
+    "fls",
+    "lof",
+    "log",
+    "lot",
+    "nav",
+    "out",
+    "run.xml",
+    "snm",
+    "synctex.gz",
+    "toc",
+    "vrb",
+    "xdv",
+];
+
+/// Environments whose `%` signs are text, not comments
+const VERBATIM_ENVIRONMENTS: &[&str] = &["verbatim", "Verbatim", "lstlisting", "minted"];
+
+/// `\input`s nested deeper than this are left alone (and a cycle ends)
+const MAX_FLATTEN_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SubmissionOptions {
+    /// Inline `\input` and `\include` into the main file
+    pub flatten: bool,
+    pub strip_comments: bool,
+    /// Defaults to `<main>-submission.zip` next to the main file
+    pub output_path: Option<String>,
+}
+
+impl Default for SubmissionOptions {
+    fn default() -> Self {
+        SubmissionOptions {
+            flatten: false,
+            strip_comments: true,
+            output_path: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionPackage {
+    pub output_path: String,
+    /// Paths inside the zip
+    pub files: Vec<String>,
+    /// Whether the file list came from the `.fls` of a compilation
+    pub from_recorder: bool,
+    pub bbl_included: bool,
+    /// References that couldn't be resolved or point outside the folder
+    pub warnings: Vec<String>,
+}
+
+fn is_generated(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    GENERATED_EXTENSIONS
+        .iter()
+        .any(|ext| name.ends_with(&format!(".{}", ext)))
+}
+
+fn is_tex(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "tex")
+}
+
+/// Files a `.fls` says were read, made absolute against its `PWD` lines
+fn fls_inputs(content: &str) -> Vec<PathBuf> {
+    let mut pwd = PathBuf::new();
+    let mut inputs = Vec::new();
+    for line in content.lines() {
+        if let Some(dir) = line.strip_prefix("PWD ") {
+            pwd = PathBuf::from(dir);
+        } else if let Some(file) = line.strip_prefix("INPUT ") {
+            let path = pwd.join(file);
+            if !inputs.contains(&path) {
+                inputs.push(path);
+            }
+        }
+    }
+    inputs
+}
+
+/// A source decoded in whatever encoding it is in, so Greek sources in
+/// ISO-8859-7 and the like survive packaging
+fn read_source(path: &Path) -> Option<Decoded> {
+    fs::read(path).ok().map(|bytes| encoding::decode(&bytes))
+}
+
+/// Files `main` references, following `\input` and `\include`. Packages and
+/// classes only count when they are in the folder; the rest are installed.
+fn scan_references(main: &Path, root: &Path, warnings: &mut Vec<String>) -> BTreeSet<PathBuf> {
+    let mut files = BTreeSet::new();
+    let mut queue = vec![main.to_path_buf()];
+    files.insert(main.to_path_buf());
+    while let Some(source) = queue.pop() {
+        let Some(decoded) = read_source(&source) else {
+            continue;
+        };
+        for reference in dependency_scanner::extract_references(&decoded.text) {
+            let found = dependency_scanner::candidate_paths(&source, Some(root), &reference)
+                .into_iter()
+                .find(|candidate| candidate.is_file());
+            let Some(path) = found else {
+                if !matches!(reference.relation, "package" | "class") {
+                    warnings.push(format!("Not found: {}", reference.target));
+                }
+                continue;
+            };
+            if files.insert(path.clone())
+                && matches!(reference.relation, "input" | "include")
+                && is_tex(&path)
+            {
+                queue.push(path);
+            }
+        }
+    }
+    files
+}
+
+/// Remove comments without changing what TeX reads: comment-only lines go
+/// entirely, a trailing comment keeps its `%` so the line end stays eaten.
+/// Verbatim-like environments are left as they are.
+pub fn strip_comments(content: &str) -> String {
+    let mut lines = Vec::new();
+    let mut verbatim: Option<String> = None;
+    for line in content.lines() {
+        if let Some(end) = &verbatim {
+            if line.contains(end.as_str()) {
+                verbatim = None;
+            }
+            lines.push(line.to_string());
+            continue;
+        }
+        let start = dependency_scanner::comment_start(line);
+        let code = &line[..start];
+        if let Some(env) = VERBATIM_ENVIRONMENTS
+            .iter()
+            .find(|env| code.contains(&format!("\\begin{{{}}}", env)))
+        {
+            let end = format!("\\end{{{}}}", env);
+            if !line.contains(&end) {
+                verbatim = Some(end);
+            }
+            lines.push(line.to_string());
+            continue;
+        }
+        if start == line.len() {
+            lines.push(line.to_string());
+        } else if !code.trim().is_empty() {
+            lines.push(format!("{}%", code));
+        }
+    }
+    let mut stripped = lines.join("\n");
+    if content.ends_with('\n') {
+        stripped.push('\n');
+    }
+    stripped
+}
+
+fn input_regex() -> &'static Regex {
+    static INPUT_RE: OnceLock<Regex> = OnceLock::new();
+    INPUT_RE.get_or_init(|| Regex::new(r"\\(input|include)\s*\{([^}]*)\}").unwrap())
+}
+
+/// The file an `\input`/`\include` of `target` reads, if it is there
+fn resolve_input(source: &Path, root: &Path, target: &str) -> Option<PathBuf> {
+    let reference = Reference {
+        relation: "input",
+        target: target.trim().to_string(),
+    };
+    dependency_scanner::candidate_paths(source, Some(root), &reference)
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+}
+
+/// `content` of `source` with its `\input`s and `\include`s replaced by the
+/// files they read, recursively; the inlined files are added to `inlined`
+fn flatten(
+    source: &Path,
+    content: &str,
+    root: &Path,
+    depth: usize,
+    inlined: &mut BTreeSet<PathBuf>,
+) -> String {
+    let mut out = Vec::new();
+    for line in content.lines() {
+        // Commented-out inputs stay as they are
+        let start = dependency_scanner::comment_start(line);
+        let (code, comment) = line.split_at(start);
+        let mut flattened = String::new();
+        let mut last = 0;
+        for caps in input_regex().captures_iter(code) {
+            let whole = caps.get(0).unwrap();
+            let Some(path) = resolve_input(source, root, &caps[2]) else {
+                continue;
+            };
+            let Some(decoded) = read_source(&path) else {
+                continue;
+            };
+            if depth >= MAX_FLATTEN_DEPTH {
+                continue;
+            }
+            let text = flatten(&path, &decoded.text, root, depth + 1, inlined);
+            inlined.insert(path);
+            flattened.push_str(&code[last..whole.start()]);
+            if &caps[1] == "include" {
+                flattened.push_str(&format!("\\clearpage\n{}\n\\clearpage", text.trim_end()));
+            } else {
+                flattened.push_str(text.trim_end());
+                // A comment on the last inlined line must not swallow what
+                // follows the \input
+                let last_line = text.trim_end().lines().last().unwrap_or("");
+                if dependency_scanner::comment_start(last_line) < last_line.len()
+                    && !code[whole.end()..].trim().is_empty()
+                {
+                    flattened.push('\n');
+                }
+            }
+            last = whole.end();
+        }
+        flattened.push_str(&code[last..]);
+        flattened.push_str(comment);
+        out.push(flattened);
+    }
+    let mut flat = out.join("\n");
+    if content.ends_with('\n') {
+        flat.push('\n');
+    }
+    flat
+}
+
+/// Path inside the zip, with `/` separators; `None` outside `root`
+fn archive_name(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    Some(
+        relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+/// Build the submission zip for `main_tex`. Blocks while reading and
+/// compressing the files.
+pub fn package(main_tex: &str, options: &SubmissionOptions) -> Result<SubmissionPackage, String> {
+    let main = fs::canonicalize(main_tex).map_err(|e| format!("{}: {}", main_tex, e))?;
+    let root = main
+        .parent()
+        .ok_or("The main file has no folder")?
+        .to_path_buf();
+    let stem = main
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let output_path = options
+        .output_path
+        .clone()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| root.join(format!("{}-submission.zip", stem)));
+
+    let mut warnings = Vec::new();
+    let scanned = scan_references(&main, &root, &mut warnings);
+    let fls = fs::read_to_string(root.join(format!("{}.fls", stem))).ok();
+    let from_recorder = fls.is_some();
+    let mut files: BTreeSet<PathBuf> = match &fls {
+        // Only the .fls knows about files loaded by packages, but bibtex
+        // reads the .bib files after LaTeX has finished
+        Some(fls) => fls_inputs(fls)
+            .into_iter()
+            .map(|path| fs::canonicalize(&path).unwrap_or(path))
+            .chain(
+                scanned
+                    .iter()
+                    .filter(|p| p.extension().is_some_and(|e| e == "bib"))
+                    .cloned(),
+            )
+            .collect(),
+        None => scanned,
+    };
+    files.insert(main.clone());
+
+    let bbl = root.join(format!("{}.bbl", stem));
+    let bbl_included = bbl.is_file();
+    if bbl_included {
+        files.retain(|p| p.extension().is_none_or(|e| e != "bib"));
+        files.insert(bbl);
+    } else {
+        warnings.push(format!(
+            "No {}.bbl found; compile the bibliography first so it can be included",
+            stem
+        ));
+    }
+
+    // Only files in the folder travel; the rest is the TeX installation
+    let output = fs::canonicalize(&output_path).unwrap_or(output_path.clone());
+    let mut entries: Vec<(String, PathBuf)> = Vec::new();
+    for path in files {
+        if !path.is_file() || path == output || is_generated(&path) {
+            continue;
+        }
+        match archive_name(&root, &path) {
+            Some(name) => entries.push((name, path)),
+            None if fls.is_none() => {
+                warnings.push(format!("Outside the document folder: {}", path.display()))
+            }
+            None => {}
+        }
+    }
+
+    let mut inlined = BTreeSet::new();
+    let main_source = read_source(&main).ok_or(format!("Failed to read {}", main.display()))?;
+    let main_content = if options.flatten {
+        flatten(&main, &main_source.text, &root, 0, &mut inlined)
+    } else {
+        main_source.text.clone()
+    };
+
+    let file = fs::File::create(&output_path)
+        .map_err(|e| format!("Failed to create {}: {}", output_path.display(), e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let zip_options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut names = Vec::new();
+    for (name, path) in entries {
+        if inlined.contains(&path) {
+            continue;
+        }
+        // Sources go back out in the encoding they came in
+        let content = if path == main {
+            let text = if options.strip_comments {
+                strip_comments(&main_content)
+            } else {
+                main_content.clone()
+            };
+            encoding::encode(&text, main_source.encoding, main_source.bom)
+                .map_err(|e| format!("{}: {}", path.display(), e))?
+        } else if options.strip_comments && is_tex(&path) {
+            let source = read_source(&path).ok_or(format!("Failed to read {}", path.display()))?;
+            encoding::encode(&strip_comments(&source.text), source.encoding, source.bom)
+                .map_err(|e| format!("{}: {}", path.display(), e))?
+        } else {
+            fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        };
+        zip.start_file(name.as_str(), zip_options)
+            .map_err(|e| e.to_string())?;
+        zip.write_all(&content).map_err(|e| e.to_string())?;
+        names.push(name);
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+
+    Ok(SubmissionPackage {
+        output_path: output_path.to_string_lossy().to_string(),
+        files: names,
+        from_recorder,
+        bbl_included,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fls_inputs() {
+        let fls = "PWD /work/paper\nINPUT /usr/share/texmf/tex/latex/base/article.cls\nINPUT main.tex\nOUTPUT main.aux\nINPUT ./figs/plot.pdf\nINPUT main.tex\n";
+        assert_eq!(
+            fls_inputs(fls),
+            [
+                PathBuf::from("/usr/share/texmf/tex/latex/base/article.cls"),
+                PathBuf::from("/work/paper/main.tex"),
+                PathBuf::from("/work/paper/./figs/plot.pdf"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        let source = "% header\nText % note\nmore 50\\% off\n  % indented\n\\begin{verbatim}\n% kept\n\\end{verbatim}\n";
+        assert_eq!(
+            strip_comments(source),
+            "Text %\nmore 50\\% off\n\\begin{verbatim}\n% kept\n\\end{verbatim}\n"
+        );
+    }
+
+    #[test]
+    fn test_package() {
+        let root =
+            std::env::temp_dir().join(format!("datatex-submission-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(root.join("sections")).unwrap();
+        fs::create_dir_all(root.join("figs")).unwrap();
+        fs::write(
+            root.join("main.tex"),
+            "\\documentclass{article}\n\\usepackage{amsmath}\n\\begin{document}\n\\input{sections/intro} % first\n% \\input{sections/old}\n\\includegraphics{figs/plot}\n\\bibliography{refs}\n\\end{document}\n",
+        )
+        .unwrap();
+        fs::write(root.join("sections/intro.tex"), "Intro. % todo\n").unwrap();
+        fs::write(root.join("sections/old.tex"), "Old.\n").unwrap();
+        fs::write(root.join("figs/plot.png"), [0u8; 4]).unwrap();
+        fs::write(root.join("figs/unused.png"), [0u8; 4]).unwrap();
+        fs::write(root.join("refs.bib"), "@book{k, title={T}}\n").unwrap();
+        fs::write(
+            root.join("main.bbl"),
+            "\\begin{thebibliography}{1}\\end{thebibliography}\n",
+        )
+        .unwrap();
+        fs::write(root.join("main.aux"), "\\relax\n").unwrap();
+
+        let main = root.join("main.tex").to_string_lossy().to_string();
+        let result = package(&main, &SubmissionOptions::default()).unwrap();
+        assert_eq!(
+            result.files,
+            [
+                "figs/plot.png",
+                "main.bbl",
+                "main.tex",
+                "sections/intro.tex"
+            ]
+        );
+        assert!(result.bbl_included && !result.from_recorder);
+        assert!(result.warnings.is_empty());
+
+        let flat = package(
+            &main,
+            &SubmissionOptions {
+                flatten: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(flat.files, ["figs/plot.png", "main.bbl", "main.tex"]);
+        let mut archive = zip::ZipArchive::new(fs::File::open(&flat.output_path).unwrap()).unwrap();
+        let mut main_tex = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name("main.tex").unwrap(), &mut main_tex)
+            .unwrap();
+        assert!(main_tex.contains("\\begin{document}\nIntro. %\n"));
+        assert!(!main_tex.contains("old"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_package_keeps_source_encoding() {
+        let root =
+            std::env::temp_dir().join(format!("datatex-submission-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        let greek = encoding_rs::WINDOWS_1253;
+        let write = |name: &str, text: &str| {
+            fs::write(
+                root.join(name),
+                encoding::encode(text, greek, false).unwrap(),
+            )
+            .unwrap();
+        };
+        write(
+            "main.tex",
+            "\\documentclass{article}\n\\begin{document}\nΕισαγωγή στην άλγεβρα. % σχόλιο\n\\input{part}\n\\end{document}\n",
+        );
+        write("part.tex", "Ασκήσεις και λύσεις.\n");
+
+        let main = root.join("main.tex").to_string_lossy().to_string();
+        let flat = package(
+            &main,
+            &SubmissionOptions {
+                flatten: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let mut archive = zip::ZipArchive::new(fs::File::open(&flat.output_path).unwrap()).unwrap();
+        let mut bytes = Vec::new();
+        std::io::Read::read_to_end(&mut archive.by_name("main.tex").unwrap(), &mut bytes).unwrap();
+        assert!(std::str::from_utf8(&bytes).is_err());
+        let text = encoding::decode(&bytes).text;
+        assert!(text.contains("Εισαγωγή στην άλγεβρα. %\nΑσκήσεις και λύσεις."));
+        assert!(!text.contains("σχόλιο"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
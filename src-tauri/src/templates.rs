@@ -0,0 +1,380 @@
+//! Templates Module
+//!
+//! Document templates are LaTeX sources with `{{placeholders}}`. User
+//! templates live in the `templates` table (migration 021); the built-in
+//! exam, worksheet and beamer templates are compiled in and addressed as
+//! `builtin:<name>`, like the built-in preambles.
+//!
+//! Placeholders are filled from, in order of precedence: values passed by
+//! the caller, the custom fields (`{{field.<name>}}`) and metadata
+//! (`{{meta.<key>}}`) of an optional source resource, and the defaults
+//! `title`, `author`, `date` and `collection`.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+use crate::custom_fields;
+use crate::database::audit;
+use crate::database::entities::Resource;
+use crate::database::DatabaseManager;
+use crate::resources;
+use crate::search;
+
+const EXAM_TEMPLATE: &str = r"\documentclass[a4paper,11pt]{exam}
+\usepackage[utf8]{inputenc}
+\usepackage{amsmath,amssymb}
+
+\title{{{title}}}
+\author{{{author}}}
+\date{{{date}}}
+
+\begin{document}
+\begin{center}
+    {\Large\bfseries {{title}}}\\[4pt]
+    {{date}}
+\end{center}
+
+\noindent Name: \rule{8cm}{0.4pt} \hfill Class: \rule{2cm}{0.4pt}
+
+\begin{questions}
+\question
+
+\end{questions}
+\end{document}
+";
+
+const WORKSHEET_TEMPLATE: &str = r"\documentclass[a4paper,11pt]{article}
+\usepackage[utf8]{inputenc}
+\usepackage{amsmath,amssymb}
+\usepackage[margin=2cm]{geometry}
+\usepackage{enumitem}
+
+\begin{document}
+\noindent\textbf{\large {{title}}} \hfill {{date}}\\
+\noindent {{author}}
+\medskip\hrule\medskip
+
+\begin{enumerate}[label=\textbf{\arabic*.}]
+    \item
+\end{enumerate}
+\end{document}
+";
+
+const BEAMER_TEMPLATE: &str = r"\documentclass{beamer}
+\usepackage[utf8]{inputenc}
+\usepackage{amsmath,amssymb}
+\usetheme{Madrid}
+
+\title{{{title}}}
+\author{{{author}}}
+\date{{{date}}}
+
+\begin{document}
+\frame{\titlepage}
+
+\begin{frame}{Outline}
+    \tableofcontents
+\end{frame}
+
+\section{Introduction}
+\begin{frame}{Introduction}
+
+\end{frame}
+\end{document}
+";
+
+/// (id, name, kind, description, content)
+const BUILTINS: &[(&str, &str, &str, &str, &str)] = &[
+    (
+        "builtin:exam",
+        "Exam",
+        "exam",
+        "Exam paper using the exam class",
+        EXAM_TEMPLATE,
+    ),
+    (
+        "builtin:worksheet",
+        "Worksheet",
+        "worksheet",
+        "Numbered exercise sheet",
+        WORKSHEET_TEMPLATE,
+    ),
+    (
+        "builtin:beamer",
+        "Beamer presentation",
+        "beamer",
+        "Slides with title page and outline",
+        BEAMER_TEMPLATE,
+    ),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Template {
+    /// Empty when creating a new template
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub kind: String,
+    pub content: String,
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+/// Result of filling a template
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rendered {
+    pub content: String,
+    /// Placeholders without a value; they are left in the text as-is
+    pub unresolved: Vec<String>,
+}
+
+/// A document created from a template
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedDocument {
+    pub resource: Resource,
+    pub unresolved: Vec<String>,
+}
+
+fn builtin(id: &str) -> Option<Template> {
+    BUILTINS
+        .iter()
+        .find(|(builtin_id, ..)| *builtin_id == id)
+        .map(|(id, name, kind, description, content)| Template {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            kind: kind.to_string(),
+            content: content.to_string(),
+            builtin: true,
+        })
+}
+
+fn row_to_template(row: &sqlx::sqlite::SqliteRow) -> Template {
+    Template {
+        id: row.get("id"),
+        name: row.get("name"),
+        description: row.get("description"),
+        kind: row.get("kind"),
+        content: row.get("content"),
+        builtin: false,
+    }
+}
+
+/// Built-in templates followed by user templates
+pub async fn list(pool: &Pool<Sqlite>) -> Result<Vec<Template>, String> {
+    let mut templates: Vec<Template> = BUILTINS.iter().filter_map(|(id, ..)| builtin(id)).collect();
+
+    let rows = sqlx::query("SELECT * FROM templates ORDER BY name")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    templates.extend(rows.iter().map(row_to_template));
+    Ok(templates)
+}
+
+pub async fn get(pool: &Pool<Sqlite>, id: &str) -> Result<Template, String> {
+    if let Some(template) = builtin(id) {
+        return Ok(template);
+    }
+    let row = sqlx::query("SELECT * FROM templates WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Template not found: {}", id))?;
+    Ok(row_to_template(&row))
+}
+
+/// Create (empty id) or update a user template
+pub async fn save(pool: &Pool<Sqlite>, template: &Template) -> Result<Template, String> {
+    if template.id.starts_with("builtin:") {
+        return Err("Built-in templates cannot be modified".to_string());
+    }
+    let name = template.name.trim();
+    if name.is_empty() {
+        return Err("Template name is empty".to_string());
+    }
+
+    let id = if template.id.is_empty() {
+        Uuid::new_v4().to_string()
+    } else {
+        template.id.clone()
+    };
+    sqlx::query(
+        "INSERT INTO templates (id, name, description, kind, content) VALUES (?, ?, ?, ?, ?)
+         ON CONFLICT(id) DO UPDATE SET
+            name = excluded.name,
+            description = excluded.description,
+            kind = excluded.kind,
+            content = excluded.content",
+    )
+    .bind(&id)
+    .bind(name)
+    .bind(&template.description)
+    .bind(&template.kind)
+    .bind(&template.content)
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        if e.to_string().contains("UNIQUE") {
+            format!("A template named '{}' already exists", name)
+        } else {
+            e.to_string()
+        }
+    })?;
+
+    get(pool, &id).await
+}
+
+pub async fn delete(pool: &Pool<Sqlite>, id: &str) -> Result<(), String> {
+    if id.starts_with("builtin:") {
+        return Err("Built-in templates cannot be deleted".to_string());
+    }
+    sqlx::query("DELETE FROM templates WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Replace `{{name}}` placeholders (whitespace inside the braces allowed)
+pub fn render(content: &str, values: &HashMap<String, String>) -> Rendered {
+    static PLACEHOLDER_RE: OnceLock<Regex> = OnceLock::new();
+    let re =
+        PLACEHOLDER_RE.get_or_init(|| Regex::new(r"\{\{\s*([A-Za-z_][\w.\-]*)\s*\}\}").unwrap());
+
+    let mut unresolved = BTreeSet::new();
+    let content = re
+        .replace_all(content, |caps: &regex::Captures| {
+            match values.get(&caps[1]) {
+                Some(value) => value.clone(),
+                None => {
+                    unresolved.insert(caps[1].to_string());
+                    caps[0].to_string()
+                }
+            }
+        })
+        .to_string();
+
+    Rendered {
+        content,
+        unresolved: unresolved.into_iter().collect(),
+    }
+}
+
+fn json_to_text(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::Null => None,
+        serde_json::Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Placeholder values: defaults, then the source resource's fields, then `values`
+pub async fn collect_values(
+    db: &DatabaseManager,
+    collection: &str,
+    source_id: Option<&str>,
+    values: HashMap<String, String>,
+) -> Result<HashMap<String, String>, String> {
+    let mut all = HashMap::new();
+    all.insert(
+        "date".to_string(),
+        chrono::Local::now().format("%d/%m/%Y").to_string(),
+    );
+    all.insert("collection".to_string(), collection.to_string());
+    if let Some(author) = audit::current_actor() {
+        all.insert("author".to_string(), author);
+    }
+
+    if let Some(source_id) = source_id {
+        let source = db
+            .get_resource_by_id(source_id)
+            .await?
+            .ok_or("Source resource not found")?;
+        if let Some(title) = &source.title {
+            all.insert("title".to_string(), title.clone());
+        }
+        if let Some(serde_json::Value::Object(meta)) = &source.metadata {
+            for (key, value) in meta {
+                if let Some(text) = json_to_text(value) {
+                    all.insert(format!("meta.{}", key), text);
+                }
+            }
+        }
+        for field in custom_fields::get_values(&db.pool, source_id).await? {
+            if let Some(text) = json_to_text(&field.value) {
+                all.insert(format!("field.{}", field.name), text);
+            }
+        }
+    }
+
+    all.extend(values);
+    Ok(all)
+}
+
+/// Fill a template and store the result as a new resource at `path`
+pub async fn create_document(
+    db: &DatabaseManager,
+    template_id: &str,
+    path: &str,
+    collection: &str,
+    source_id: Option<&str>,
+    values: HashMap<String, String>,
+) -> Result<CreatedDocument, String> {
+    let template = get(&db.pool, template_id).await?;
+    let values = collect_values(db, collection, source_id, values).await?;
+    let rendered = render(&template.content, &values);
+
+    let metadata = serde_json::json!({ "template": template.id });
+    let resource =
+        resources::create(db, path, collection, &rendered.content, Some(metadata)).await?;
+    search::fts::update_content(&db.pool, &resource.id, &rendered.content).await?;
+
+    Ok(CreatedDocument {
+        resource,
+        unresolved: rendered.unresolved,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let values = HashMap::from([
+            ("title".to_string(), "Algebra".to_string()),
+            ("field.difficulty".to_string(), "3".to_string()),
+        ]);
+        let rendered = render(
+            "\\title{{{title}}} {{ field.difficulty }} {{missing}} {x}",
+            &values,
+        );
+        assert_eq!(rendered.content, "\\title{Algebra} 3 {{missing}} {x}");
+        assert_eq!(rendered.unresolved, vec!["missing".to_string()]);
+    }
+
+    #[test]
+    fn test_builtins_render() {
+        let values = HashMap::from([
+            ("title".to_string(), "T".to_string()),
+            ("author".to_string(), "A".to_string()),
+            ("date".to_string(), "D".to_string()),
+        ]);
+        for (id, ..) in BUILTINS {
+            let rendered = render(&builtin(id).unwrap().content, &values);
+            assert!(rendered.unresolved.is_empty(), "{}", id);
+            assert!(rendered.content.contains("\\begin{document}"));
+        }
+    }
+}
@@ -176,8 +176,12 @@ fn extract_zip(data: &[u8], dest_dir: &PathBuf) -> Result<(), String> {
     Err("texlab binary not found in archive".to_string())
 }
 
-/// Ensure texlab is available (download if needed)
+/// Ensure texlab is available (download if needed), unless the settings
+/// point to a binary of the user's own
 pub async fn ensure_texlab() -> Result<PathBuf, String> {
+    if let Some(texlab) = crate::settings::current().tools.texlab {
+        return Ok(PathBuf::from(texlab));
+    }
     if is_texlab_installed() {
         get_texlab_path()
     } else {
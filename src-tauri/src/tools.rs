@@ -292,6 +292,7 @@ impl Tool for DatabaseSearchTool {
                     use_regex: use_regex,
                     file_types: extensions,
                     max_results: 20,
+                    ..Default::default()
                 };
 
                 match crate::search::search_in_files(&search_query, resources) {
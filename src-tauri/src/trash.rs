@@ -0,0 +1,467 @@
+//! Recycle Bin
+//!
+//! Deleting a resource moves its file into `<data dir>/trash/<deletion id>/`
+//! instead of removing it, and remembers which `change_log` entries hold the
+//! rows the deletion took with it (the resource, its metadata, dependencies,
+//! attachments, ...). Restoring moves the file back and re-inserts those rows
+//! from their logged values, so ids, tags and links all come back. BLOB
+//! columns and local history are not in the audit log and are not restored.
+
+use serde::Serialize;
+use sqlx::Row;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+use crate::database::DatabaseManager;
+use crate::database::{audit, schema};
+
+/// A deletion that can still be restored
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedResource {
+    pub id: String,
+    pub resource_id: String,
+    pub collection: String,
+    pub kind: String,
+    pub original_path: String,
+    /// Whether the file itself is in the trash, not just the database rows
+    pub has_file: bool,
+    /// Resources deleted together (the folder and its contents)
+    pub resource_count: usize,
+    pub deleted_at: String,
+}
+
+fn trash_root(db: &DatabaseManager) -> PathBuf {
+    db.data_dir.join("trash")
+}
+
+/// Copy a file or folder, for moves across filesystems
+fn copy_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    if from.is_file() {
+        fs::copy(from, to).map(|_| ())
+    } else {
+        for entry in WalkDir::new(from) {
+            let entry = entry.map_err(std::io::Error::other)?;
+            let rel = entry.path().strip_prefix(from).unwrap_or(Path::new(""));
+            let target = to.join(rel);
+            if entry.file_type().is_dir() {
+                fs::create_dir_all(&target)?;
+            } else {
+                fs::copy(entry.path(), &target)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rename, falling back to copy and remove when `to` is on another device
+fn move_path(from: &Path, to: &Path) -> Result<(), String> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_path(from, to).map_err(|e| format!("Failed to move {}: {}", from.display(), e))?;
+    let removed = if from.is_dir() {
+        fs::remove_dir_all(from)
+    } else {
+        fs::remove_file(from)
+    };
+    removed.map_err(|e| format!("Failed to remove {}: {}", from.display(), e))
+}
+
+async fn last_log_id(db: &DatabaseManager) -> Result<i64, String> {
+    sqlx::query_scalar::<_, Option<i64>>("SELECT MAX(id) FROM change_log")
+        .fetch_one(&db.pool)
+        .await
+        .map(|id| id.unwrap_or(0))
+        .map_err(|e| e.to_string())
+}
+
+/// Delete a resource (and a folder's contents), keeping everything needed
+/// to restore it. `with_file` also moves the file on disk to the trash.
+/// Returns the deleted resource ids.
+pub async fn delete(
+    db: &DatabaseManager,
+    id: &str,
+    with_file: bool,
+) -> Result<Vec<String>, String> {
+    let resource = db
+        .get_resource_by_id(id)
+        .await?
+        .ok_or("Resource not found")?;
+    // Restoring re-inserts the rows from the audit log; without it the
+    // deletion couldn't be undone
+    if !audit::is_logging_deletes(&db.pool, "resources").await? {
+        return Err(
+            "Changes aren't being logged, so this deletion couldn't be undone; not deleting"
+                .to_string(),
+        );
+    }
+    let is_folder = resource.kind == "folder";
+    let original = PathBuf::from(&resource.path);
+    let deletion_id = Uuid::new_v4().to_string();
+
+    // 1. File into the trash
+    let trashed = if with_file && original.exists() {
+        let name = original
+            .file_name()
+            .map(|n| n.to_os_string())
+            .unwrap_or_else(|| "resource".into());
+        let target = trash_root(db).join(&deletion_id).join(name);
+        move_path(&original, &target)?;
+        Some(target)
+    } else {
+        None
+    };
+    let put_back = |trashed: &Option<PathBuf>| {
+        if let Some(target) = trashed {
+            if let Err(e) = move_path(target, &original) {
+                eprintln!("Failed to put {} back: {}", original.display(), e);
+            }
+        }
+    };
+
+    // 2. Rows, bracketed by the change_log entries they produce
+    let log_start = match last_log_id(db).await {
+        Ok(id) => id,
+        Err(e) => {
+            put_back(&trashed);
+            return Err(e);
+        }
+    };
+    let removed = match db
+        .delete_resource_tree(&resource.id, &resource.path, is_folder)
+        .await
+    {
+        Ok(ids) => ids,
+        Err(e) => {
+            put_back(&trashed);
+            return Err(e);
+        }
+    };
+    let log_end = last_log_id(db).await?;
+    if log_end <= log_start {
+        // Nothing to restore the rows from: the file stays where it was
+        put_back(&trashed);
+        return Err("Deleted, but nothing was logged to restore it from".to_string());
+    }
+
+    // 3. Recycle bin entry. The rows are gone either way; without the entry
+    // the deletion just can't be undone.
+    sqlx::query(
+        "INSERT INTO deleted_resources
+         (id, resource_id, resource_ids, collection, kind, original_path, trash_path, log_start, log_end)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&deletion_id)
+    .bind(&resource.id)
+    .bind(serde_json::to_string(&removed).map_err(|e| e.to_string())?)
+    .bind(&resource.collection)
+    .bind(&resource.kind)
+    .bind(&resource.path)
+    .bind(trashed.as_ref().map(|p| p.to_string_lossy().to_string()))
+    .bind(log_start)
+    .bind(log_end)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| format!("Deleted, but could not record it in the recycle bin: {}", e))?;
+
+    Ok(removed)
+}
+
+/// Deletions that can be restored, newest first
+pub async fn list(
+    db: &DatabaseManager,
+    collection: Option<&str>,
+) -> Result<Vec<DeletedResource>, String> {
+    let rows = sqlx::query(
+        "SELECT id, resource_id, resource_ids, collection, kind, original_path, trash_path,
+                CAST(deleted_at AS TEXT) AS deleted_at
+         FROM deleted_resources
+         WHERE ? IS NULL OR collection = ?
+         ORDER BY deleted_at DESC, rowid DESC",
+    )
+    .bind(collection)
+    .bind(collection)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .iter()
+        .map(|r| DeletedResource {
+            id: r.get("id"),
+            resource_id: r.get("resource_id"),
+            collection: r.get("collection"),
+            kind: r.get("kind"),
+            original_path: r.get("original_path"),
+            has_file: r.get::<Option<String>, _>("trash_path").is_some(),
+            resource_count: serde_json::from_str::<Vec<String>>(r.get("resource_ids"))
+                .map(|ids| ids.len())
+                .unwrap_or(1),
+            deleted_at: r.get::<Option<String>, _>("deleted_at").unwrap_or_default(),
+        })
+        .collect())
+}
+
+struct Deletion {
+    resource_ids: Vec<String>,
+    original_path: String,
+    trash_path: Option<String>,
+    log_start: i64,
+    log_end: i64,
+}
+
+async fn get_deletion(db: &DatabaseManager, id: &str) -> Result<Deletion, String> {
+    let row = sqlx::query(
+        "SELECT resource_ids, original_path, trash_path, log_start, log_end
+         FROM deleted_resources WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or("Deleted resource not found")?;
+    Ok(Deletion {
+        resource_ids: serde_json::from_str(row.get("resource_ids")).map_err(|e| e.to_string())?,
+        original_path: row.get("original_path"),
+        trash_path: row.get("trash_path"),
+        log_start: row.get("log_start"),
+        log_end: row.get("log_end"),
+    })
+}
+
+/// Re-insert the rows a deletion removed, from their logged values
+async fn restore_rows(db: &DatabaseManager, deletion: &Deletion) -> Result<(), String> {
+    let ids = serde_json::to_string(&deletion.resource_ids).map_err(|e| e.to_string())?;
+    let logged = sqlx::query(
+        "SELECT table_name, old_values FROM change_log
+         WHERE id > ? AND id <= ? AND action = 'delete' AND old_values IS NOT NULL
+           AND (resource_id IN (SELECT value FROM json_each(?))
+                OR (table_name = 'dependencies'
+                    AND json_extract(old_values, '$.target_id') IN (SELECT value FROM json_each(?))))
+         ORDER BY table_name != 'resources', id",
+    )
+    .bind(deletion.log_start)
+    .bind(deletion.log_end)
+    .bind(&ids)
+    .bind(&ids)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+    // Rows come back in any order; references are checked at commit
+    sqlx::query("PRAGMA defer_foreign_keys = ON")
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for row in &logged {
+        let table: String = row.get("table_name");
+        let values: String = row.get("old_values");
+        let Ok(columns) = schema::column_info(&db.pool, &table).await else {
+            // The table was dropped since
+            continue;
+        };
+        let logged_columns: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(&values).map_err(|e| e.to_string())?;
+        let columns: Vec<&str> = columns
+            .iter()
+            .map(|c| c.name.as_str())
+            .filter(|c| logged_columns.contains_key(*c))
+            .collect();
+        if columns.is_empty() {
+            continue;
+        }
+
+        let mut sql = format!(
+            "INSERT OR IGNORE INTO {} ({}) SELECT {}",
+            schema::quote_identifier(&table),
+            columns
+                .iter()
+                .map(|c| schema::quote_identifier(c))
+                .collect::<Vec<_>>()
+                .join(", "),
+            columns
+                .iter()
+                .map(|c| format!("json_extract(?1, '$.\"{}\"')", c.replace('\'', "''")))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        // A link from a resource that is gone by now stays gone
+        if table == "dependencies" {
+            sql.push_str(
+                " WHERE EXISTS (SELECT 1 FROM resources WHERE id = json_extract(?1, '$.source_id'))
+                  AND EXISTS (SELECT 1 FROM resources WHERE id = json_extract(?1, '$.target_id'))",
+            );
+        }
+        sqlx::query(&sql)
+            .bind(&values)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to restore a row of {}: {}", table, e))?;
+    }
+
+    // Nothing logged for the resource itself means nothing was restored
+    let first = deletion.resource_ids.first().ok_or("Nothing to restore")?;
+    let restored: Option<String> = sqlx::query_scalar("SELECT id FROM resources WHERE id = ?")
+        .bind(first)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    if restored.is_none() {
+        return Err(
+            "The change log no longer holds this resource; it can't be restored".to_string(),
+        );
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to restore (was its collection deleted?): {}", e))
+}
+
+/// Undo a deletion: the file goes back where it was and the rows are
+/// re-inserted. Returns the restored resource ids.
+pub async fn restore(db: &DatabaseManager, deletion_id: &str) -> Result<Vec<String>, String> {
+    let deletion = get_deletion(db, deletion_id).await?;
+    let original = PathBuf::from(&deletion.original_path);
+    let trashed = deletion.trash_path.as_ref().map(PathBuf::from);
+
+    if let Some(trashed) = &trashed {
+        if original.exists() {
+            return Err(format!(
+                "{} already exists; move it away before restoring",
+                original.display()
+            ));
+        }
+        if !trashed.exists() {
+            return Err(format!("{} is no longer in the trash", trashed.display()));
+        }
+    }
+    if db
+        .get_resource_by_id(deletion.resource_ids.first().ok_or("Nothing to restore")?)
+        .await?
+        .is_some()
+    {
+        return Err("The resource already exists in the database".to_string());
+    }
+
+    if let Some(trashed) = &trashed {
+        move_path(trashed, &original)?;
+    }
+    if let Err(e) = restore_rows(db, &deletion).await {
+        if let Some(trashed) = &trashed {
+            if let Err(e) = move_path(&original, trashed) {
+                eprintln!(
+                    "Failed to move {} back to the trash: {}",
+                    original.display(),
+                    e
+                );
+            }
+        }
+        return Err(e);
+    }
+
+    forget(db, deletion_id).await?;
+    Ok(deletion.resource_ids)
+}
+
+/// Drop the recycle bin entry and whatever is left of its trash directory
+async fn forget(db: &DatabaseManager, deletion_id: &str) -> Result<(), String> {
+    sqlx::query("DELETE FROM deleted_resources WHERE id = ?")
+        .bind(deletion_id)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let dir = trash_root(db).join(deletion_id);
+    if dir.exists() {
+        if let Err(e) = fs::remove_dir_all(&dir) {
+            eprintln!("Failed to remove {}: {}", dir.display(), e);
+        }
+    }
+    Ok(())
+}
+
+/// Delete for good: one deletion, or the whole recycle bin. Returns how
+/// many deletions were purged.
+pub async fn purge(db: &DatabaseManager, deletion_id: Option<&str>) -> Result<usize, String> {
+    let ids: Vec<String> = match deletion_id {
+        Some(id) => vec![id.to_string()],
+        None => sqlx::query_scalar("SELECT id FROM deleted_resources")
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| e.to_string())?,
+    };
+    for id in &ids {
+        forget(db, id).await?;
+    }
+    Ok(ids.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delete_and_restore_need_logged_rows() {
+        let dir = std::env::temp_dir().join(format!("datatex-trash-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("a.tex");
+        let path = file.to_string_lossy().to_string();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let db = DatabaseManager::new(&dir.to_string_lossy()).await.unwrap();
+            let add = || async {
+                fs::write(&file, "x").unwrap();
+                sqlx::query(
+                    "INSERT INTO resources (id, path, type, collection) VALUES ('r1', ?, 'file', 'c')",
+                )
+                .bind(&path)
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            };
+            sqlx::query("INSERT INTO collections (name, type) VALUES ('c', 'manual')")
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            add().await;
+
+            // Round trip
+            delete(&db, "r1", true).await.unwrap();
+            assert!(!file.exists());
+            let deletion = list(&db, None).await.unwrap().remove(0);
+            restore(&db, &deletion.id).await.unwrap();
+            assert!(file.exists());
+            assert!(db.get_resource_by_id("r1").await.unwrap().is_some());
+
+            // An entry whose log range holds nothing stays in the trash
+            delete(&db, "r1", true).await.unwrap();
+            let deletion = list(&db, None).await.unwrap().remove(0);
+            sqlx::query("UPDATE deleted_resources SET log_start = log_end")
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            assert!(restore(&db, &deletion.id).await.is_err());
+            assert!(!file.exists());
+            assert_eq!(list(&db, None).await.unwrap().len(), 1);
+
+            // Without the audit triggers nothing is deleted
+            add().await;
+            sqlx::query("DROP TRIGGER audit_resources_delete")
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            assert!(delete(&db, "r1", true).await.is_err());
+            assert!(file.exists());
+            assert!(db.get_resource_by_id("r1").await.unwrap().is_some());
+            db.pool.close().await;
+        });
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
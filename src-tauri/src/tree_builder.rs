@@ -1,5 +1,5 @@
 use crate::database::entities::Resource;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 #[derive(Serialize, Clone, Debug)]
@@ -15,32 +15,398 @@ pub struct TreeNode {
     pub metadata: Option<serde_json::Value>,
 }
 
-const ALLOWED_EXTENSIONS: [&str; 10] = [
-    "tex", "pdf", "bib", "sty", "cls", "dtx", "ins", "png", "jpg", "jpeg",
-];
+impl TreeNode {
+    /// Set a key of the node's metadata, creating the object if needed
+    pub fn set_metadata(&mut self, key: &str, value: serde_json::Value) {
+        match self.metadata.as_mut().and_then(|m| m.as_object_mut()) {
+            Some(metadata) => {
+                metadata.insert(key.to_string(), value);
+            }
+            None => self.metadata = Some(serde_json::json!({ key: value })),
+        }
+    }
+}
+
+/// Add the size in bytes (`size`) and last modification as Unix seconds
+/// (`modified`) of every file found on disk, and mark symlinks (junctions
+/// on Windows) with `symlink` and their `linkTarget`, so a folder reached
+/// through a link is recognisable when it shows up twice
+pub fn attach_file_info(nodes: &mut [TreeNode]) {
+    for node in nodes {
+        let is_link =
+            std::fs::symlink_metadata(&node.path).is_ok_and(|meta| meta.file_type().is_symlink());
+        if is_link {
+            node.set_metadata("symlink", true.into());
+            if let Ok(target) = std::fs::read_link(&node.path) {
+                node.set_metadata("linkTarget", target.to_string_lossy().into());
+            }
+        }
+        if node.r#type == "file" {
+            if let Ok(meta) = std::fs::metadata(&node.path) {
+                node.set_metadata("size", meta.len().into());
+                if let Some(modified) = meta
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                {
+                    node.set_metadata("modified", modified.as_secs().into());
+                }
+            }
+        }
+        attach_file_info(&mut node.children);
+    }
+}
+
+/// Rollup of the files below a folder
+#[derive(Default, Clone, Copy)]
+struct FolderTotals {
+    files: u64,
+    size: u64,
+    failing: u64,
+    changed: u64,
+}
+
+/// Give every folder the totals of the files below it: `fileCount`,
+/// `totalSize` (bytes), `failingCount` (last compile failed) and
+/// `changedCount` (uncommitted changes). Runs after the per-file metadata
+/// (`size`, `compileStatus`, `gitStatus`) has been attached.
+pub fn attach_folder_totals(nodes: &mut [TreeNode]) {
+    fn walk(nodes: &mut [TreeNode]) -> FolderTotals {
+        let mut totals = FolderTotals::default();
+        for node in nodes {
+            if node.r#type == "file" {
+                let metadata = node.metadata.as_ref();
+                let get = |key: &str| metadata.and_then(|m| m.get(key));
+                totals.files += 1;
+                totals.size += get("size").and_then(|v| v.as_u64()).unwrap_or(0);
+                if get("compileStatus").and_then(|s| s.get("status")) == Some(&"error".into()) {
+                    totals.failing += 1;
+                }
+                if get("gitStatus").is_some() {
+                    totals.changed += 1;
+                }
+                continue;
+            }
+            let below = walk(&mut node.children);
+            node.set_metadata("fileCount", below.files.into());
+            node.set_metadata("totalSize", below.size.into());
+            node.set_metadata("failingCount", below.failing.into());
+            node.set_metadata("changedCount", below.changed.into());
+            totals.files += below.files;
+            totals.size += below.size;
+            totals.failing += below.failing;
+            totals.changed += below.changed;
+        }
+        totals
+    }
+
+    walk(nodes);
+}
+
+/// Which files show up in the tree. Folders are always kept.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TreeFilter {
+    /// Extensions to show, without the dot
+    pub extensions: Vec<String>,
+    /// Show files and folders whose name starts with a dot
+    pub show_hidden: bool,
+    /// Ignore `extensions` and show every file
+    pub all_files: bool,
+}
+
+impl Default for TreeFilter {
+    fn default() -> Self {
+        TreeFilter {
+            extensions: [
+                "tex", "pdf", "bib", "sty", "cls", "dtx", "ins", "png", "jpg", "jpeg", "csv", "md",
+            ]
+            .iter()
+            .map(|e| e.to_string())
+            .collect(),
+            show_hidden: false,
+            all_files: false,
+        }
+    }
+}
+
+impl TreeFilter {
+    pub fn allows(&self, path: &str, is_folder: bool) -> bool {
+        if !self.show_hidden && (path.contains("/.") || path.contains("\\.")) {
+            return false;
+        }
+        if is_folder || self.all_files {
+            return true;
+        }
+        let ext = std::path::Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("");
+        self.extensions
+            .iter()
+            .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(ext))
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    #[default]
+    Name,
+    /// Last modification
+    Modified,
+    Size,
+}
+
+/// Order of the nodes of each folder
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TreeSort {
+    pub by: SortKey,
+    pub folders_first: bool,
+    /// Compare numbers by value, so `exercise_2` comes before `exercise_10`
+    pub natural: bool,
+    pub descending: bool,
+}
+
+impl Default for TreeSort {
+    fn default() -> Self {
+        TreeSort {
+            by: SortKey::Name,
+            folders_first: true,
+            natural: true,
+            descending: false,
+        }
+    }
+}
+
+/// Leading run of digits or of non-digits, and the rest
+fn split_run(s: &str) -> (&str, &str) {
+    let digits = s.starts_with(|c: char| c.is_ascii_digit());
+    let end = s
+        .find(|c: char| c.is_ascii_digit() != digits)
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// Case-insensitive order in which runs of digits compare by value
+pub fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    let (mut rest_a, mut rest_b) = (a, b);
+    while !rest_a.is_empty() && !rest_b.is_empty() {
+        let (run_a, next_a) = split_run(rest_a);
+        let (run_b, next_b) = split_run(rest_b);
+        let numbers = run_a.starts_with(|c: char| c.is_ascii_digit())
+            && run_b.starts_with(|c: char| c.is_ascii_digit());
+        let order = if numbers {
+            let (na, nb) = (run_a.trim_start_matches('0'), run_b.trim_start_matches('0'));
+            na.len().cmp(&nb.len()).then_with(|| na.cmp(nb))
+        } else {
+            run_a.to_lowercase().cmp(&run_b.to_lowercase())
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+        rest_a = next_a;
+        rest_b = next_b;
+    }
+    rest_a
+        .is_empty()
+        .cmp(&rest_b.is_empty())
+        .reverse()
+        .then_with(|| a.cmp(b))
+}
+
+/// Sort every folder of the tree. `Modified` and `Size` read the file info
+/// in the metadata; nodes without it (folders) sort by name among themselves.
+pub fn sort_nodes(nodes: &mut [TreeNode], sort: &TreeSort) {
+    let key = |node: &TreeNode, name: &str| {
+        node.metadata
+            .as_ref()
+            .and_then(|m| m.get(name))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0)
+    };
+    nodes.sort_by(|a, b| {
+        let (a_folder, b_folder) = (a.r#type == "folder", b.r#type == "folder");
+        if sort.folders_first && a_folder != b_folder {
+            return b_folder.cmp(&a_folder);
+        }
+        let by_name = || {
+            if sort.natural {
+                natural_cmp(&a.name, &b.name)
+            } else {
+                a.name.cmp(&b.name)
+            }
+        };
+        let order = match sort.by {
+            SortKey::Name => by_name(),
+            SortKey::Modified => key(a, "modified")
+                .cmp(&key(b, "modified"))
+                .then_with(by_name),
+            SortKey::Size => key(a, "size").cmp(&key(b, "size")).then_with(by_name),
+        };
+        if sort.descending {
+            order.reverse()
+        } else {
+            order
+        }
+    });
+    for node in nodes {
+        sort_nodes(&mut node.children, sort);
+    }
+}
+
+/// A path split into its root (`/`, `C:`, `\\server\share`, or empty for a
+/// relative path) and the names below it
+#[derive(Debug, PartialEq)]
+struct SplitPath {
+    root: String,
+    parts: Vec<String>,
+}
+
+impl SplitPath {
+    /// Windows accepts both separators and drive letters; elsewhere
+    /// `std::path` knows best
+    fn new(path: &str, windows: bool) -> Self {
+        if !windows {
+            let mut root = String::new();
+            let mut parts = Vec::new();
+            for component in std::path::Path::new(path).components() {
+                match component {
+                    std::path::Component::RootDir => root.push('/'),
+                    std::path::Component::CurDir => {}
+                    c => parts.push(c.as_os_str().to_string_lossy().to_string()),
+                }
+            }
+            return SplitPath { root, parts };
+        }
+
+        let unc = path.starts_with("\\\\") || path.starts_with("//");
+        let mut parts: Vec<String> = path
+            .split(['/', '\\'])
+            .filter(|p| !p.is_empty() && *p != ".")
+            .map(str::to_string)
+            .collect();
+        let root = if unc && parts.len() >= 2 {
+            let share: Vec<String> = parts.drain(..2).collect();
+            format!("\\\\{}\\{}", share[0], share[1])
+        } else if parts
+            .first()
+            .is_some_and(|p| p.len() == 2 && p.ends_with(':'))
+        {
+            parts.remove(0).to_uppercase()
+        } else if path.starts_with(['/', '\\']) {
+            "\\".to_string()
+        } else {
+            String::new()
+        };
+        SplitPath { root, parts }
+    }
+
+    /// The root and the first `depth` names, with the platform's separator
+    fn join(&self, depth: usize, windows: bool) -> String {
+        let sep = if windows { "\\" } else { "/" };
+        let rest = self.parts[..depth].join(sep);
+        match self.root.as_str() {
+            "" => rest,
+            "/" | "\\" => format!("{}{}", self.root, rest),
+            root if rest.is_empty() => format!("{}{}", root, sep),
+            root => format!("{}{}{}", root, sep, rest),
+        }
+    }
+}
+
+/// Names are case-insensitive on Windows
+fn same_name(a: &str, b: &str, windows: bool) -> bool {
+    if windows {
+        a.to_lowercase() == b.to_lowercase()
+    } else {
+        a == b
+    }
+}
+
+/// Key a name is grouped under
+fn name_key(name: &str, windows: bool) -> String {
+    if windows {
+        name.to_lowercase()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Names of `path` below `root`, or `None` if it isn't inside it
+fn relative_parts<'a>(
+    root: &SplitPath,
+    path: &'a SplitPath,
+    windows: bool,
+) -> Option<&'a [String]> {
+    if !same_name(&root.root, &path.root, windows) || path.parts.len() < root.parts.len() {
+        return None;
+    }
+    root.parts
+        .iter()
+        .zip(&path.parts)
+        .all(|(a, b)| same_name(a, b, windows))
+        .then(|| &path.parts[root.parts.len()..])
+}
+
+/// Longest shared root of `paths`
+fn shared_root(paths: &[SplitPath], windows: bool) -> SplitPath {
+    let Some(first) = paths.first() else {
+        return SplitPath {
+            root: String::new(),
+            parts: Vec::new(),
+        };
+    };
+    let mut depth = first.parts.len();
+    for path in &paths[1..] {
+        if !same_name(&first.root, &path.root, windows) {
+            depth = 0;
+            break;
+        }
+        depth = first.parts[..depth]
+            .iter()
+            .zip(&path.parts)
+            .take_while(|(a, b)| same_name(a, b, windows))
+            .count();
+    }
+    SplitPath {
+        root: first.root.clone(),
+        parts: first.parts[..depth].to_vec(),
+    }
+}
 
 pub fn build_file_tree(
     resources: Vec<Resource>,
     collection_roots: &HashMap<String, String>,
+    filter: &TreeFilter,
+    sort: &TreeSort,
+    flatten: bool,
 ) -> Vec<TreeNode> {
-    // 1. Filter resources (ignore hidden files and non-allowed extensions)
+    build_tree(
+        resources,
+        collection_roots,
+        filter,
+        sort,
+        flatten,
+        cfg!(windows),
+    )
+}
+
+fn build_tree(
+    resources: Vec<Resource>,
+    collection_roots: &HashMap<String, String>,
+    filter: &TreeFilter,
+    sort: &TreeSort,
+    flatten: bool,
+    windows: bool,
+) -> Vec<TreeNode> {
+    // 1. Filter resources (hidden files and extensions per the filter)
     let filtered_resources: Vec<&Resource> = resources
         .iter()
-        .filter(|r| {
-            if r.path.contains("/.") || r.path.contains("\\.") {
-                return false;
-            }
-            // Always include folders
-            if r.kind == "folder" {
-                return true;
-            }
-            let ext = std::path::Path::new(&r.path)
-                .extension()
-                .and_then(|e| e.to_str())
-                .unwrap_or("")
-                .to_lowercase();
-            ALLOWED_EXTENSIONS.contains(&ext.as_str())
-        })
+        .filter(|r| filter.allows(&r.path, r.kind == "folder"))
         .collect();
 
     if filtered_resources.is_empty() {
@@ -60,39 +426,22 @@ pub fn build_file_tree(
 
     // 3. Build tree for each collection
     for (collection_name, res_list) in by_collection {
-        let paths: Vec<String> = res_list.iter().map(|r| r.path.clone()).collect();
+        let paths: Vec<SplitPath> = res_list
+            .iter()
+            .map(|r| SplitPath::new(&r.path, windows))
+            .collect();
         if paths.is_empty() {
             continue;
         }
 
-        // Determine separator
-        let separator = if paths[0].contains('\\') { "\\" } else { "/" };
-
-        // Determine Root Path
-        // New strategy: Use the explicit collection path if provided
-        let common_root = if let Some(root) = collection_roots.get(&collection_name) {
-            root.clone()
-        } else {
-            // Fallback to purely common prefix (legacy behavior, though buggy for single files)
-            // Better fallback: Parent of the first file? Or common prefix but handle strict parents?
-            // Let's stick to common prefix logic for fallback but warn?
-            // Or simply use the parent of the first resource as a guess if it's a file?
-            let parts_list: Vec<Vec<&str>> =
-                paths.iter().map(|p| p.split(separator).collect()).collect();
-
-            let mut common_prefix: Vec<&str> = Vec::new();
-            if let Some(first) = parts_list.first() {
-                common_prefix = first.clone();
-                for parts in parts_list.iter().skip(1) {
-                    let mut j = 0;
-                    while j < common_prefix.len() && j < parts.len() && common_prefix[j] == parts[j]
-                    {
-                        j += 1;
-                    }
-                    common_prefix.truncate(j);
-                }
+        // Root: the collection's folder, or else the common prefix of its files
+        let (root, common_root) = match collection_roots.get(&collection_name) {
+            Some(root) => (SplitPath::new(root, windows), root.clone()),
+            None => {
+                let root = shared_root(&paths, windows);
+                let joined = root.join(root.parts.len(), windows);
+                (root, joined)
             }
-            common_prefix.join(separator)
         };
 
         // Root Node
@@ -107,9 +456,7 @@ pub fn build_file_tree(
         };
 
         // Add files to tree (Virtual construction)
-        // We'll use a temporary recursive structure or modify TreeNode directly?
-        // Modifying TreeNode recursively in Rust is tricky with ownership.
-        // Better to use an intermediate Map-based structure.
+        // Built in a map-based structure first, keyed by (normalized) name
 
         #[derive(Debug)]
         struct TempNode {
@@ -122,183 +469,458 @@ pub fn build_file_tree(
 
         let mut root_children: HashMap<String, TempNode> = HashMap::new();
 
-        fn insert_path(
-            map: &mut HashMap<String, TempNode>,
-            parts: &[&str],
-            collection_name: &str,
-            r: &Resource,
-            is_file_check: bool,
-            current_path_prefix: String,
-            separator: &str,
-        ) {
-            if parts.is_empty() {
-                return;
+        for (r, path) in res_list.iter().zip(&paths) {
+            // Files outside the root are shown by name at the top level, instead of
+            // rendering their whole absolute directory structure in the collection
+            let (parts, folder_base): (Vec<String>, Option<&SplitPath>) =
+                match relative_parts(&root, path, windows) {
+                    Some(rel) => (rel.to_vec(), Some(path)),
+                    None => (path.parts.last().cloned().into_iter().collect(), None),
+                };
+
+            let mut map = &mut root_children;
+            for (i, part) in parts.iter().enumerate() {
+                let is_file = i + 1 == parts.len();
+                // Folder paths come from the file's own path, so they keep its
+                // spelling; the file itself keeps its resource path
+                let depth = root.parts.len() + i + 1;
+                let folder_path = folder_base
+                    .map(|p| p.join(depth, windows))
+                    .unwrap_or_else(|| root.join(root.parts.len(), windows));
+                let node = map
+                    .entry(name_key(part, windows))
+                    .or_insert_with(|| TempNode {
+                        id: if is_file {
+                            r.id.clone()
+                        } else {
+                            format!("{}-{}", collection_name, folder_path)
+                        },
+                        name: part.clone(),
+                        r#type: if is_file && r.kind != "folder" {
+                            "file"
+                        } else {
+                            "folder"
+                        }
+                        .to_string(),
+                        path: if is_file { r.path.clone() } else { folder_path },
+                        children: HashMap::new(),
+                    });
+                map = &mut node.children;
             }
+        }
 
-            let part = parts[0];
-            let is_last = parts.len() == 1;
-            let is_file = is_last && is_file_check;
+        // Convert TempNode to TreeNode recursively
+        fn convert(map: HashMap<String, TempNode>) -> Vec<TreeNode> {
+            map.into_iter()
+                .map(|(_, v)| TreeNode {
+                    id: v.id,
+                    name: v.name,
+                    r#type: v.r#type,
+                    path: v.path,
+                    children: convert(v.children),
+                    is_root: None,
+                    metadata: None,
+                })
+                .collect()
+        }
 
-            // Build path
-            let mut my_path = current_path_prefix.clone();
-            if !my_path.is_empty() && !my_path.ends_with(separator) {
-                my_path.push_str(separator);
+        root_node.children = convert(root_children);
+
+        // 4. Flatten single-child directory chains below the root: wrapper
+        // folders holding nothing but one other folder are peeled off, and
+        // their names kept in `collapsed` so the UI can show the breadcrumb
+        if flatten {
+            let mut current_children = std::mem::take(&mut root_node.children);
+            let mut collapsed: Vec<String> = Vec::new();
+            while current_children.len() == 1
+                && current_children[0].r#type == "folder"
+                && current_children[0].children.len() == 1
+                && current_children[0].children[0].r#type == "folder"
+            {
+                let wrapper = current_children.remove(0);
+                collapsed.push(wrapper.name);
+                current_children = wrapper.children;
+            }
+            if !collapsed.is_empty() {
+                root_node.set_metadata("collapsed", collapsed.into());
             }
-            my_path.push_str(part);
+            root_node.children = current_children;
+        }
 
-            // FIX: If it is a file, use the actual resource path, do not reconstruct it from prefix
-            // This prevents external files from having the collection root prepended to their absolute path
-            let node_path = if is_file {
-                r.path.clone()
-            } else {
-                my_path.clone()
-            };
+        // 5. Sort, with the file info the order needs
+        if sort.by != SortKey::Name {
+            attach_file_info(&mut root_node.children);
+        }
+        sort_nodes(&mut root_node.children, sort);
 
-            let id = if is_file {
-                r.id.clone()
-            } else {
-                format!("{}-{}", collection_name, my_path)
-            };
-
-            let node = map.entry(part.to_string()).or_insert_with(|| TempNode {
-                id,
-                name: part.to_string(),
-                r#type: (if is_file {
-                    if r.kind == "folder" {
-                        "folder"
-                    } else {
-                        "file"
-                    }
-                } else {
-                    "folder"
-                })
-                .to_string(),
-                path: node_path.clone(),
-                children: HashMap::new(),
-            });
-
-            if !is_last {
-                insert_path(
-                    &mut node.children,
-                    &parts[1..],
-                    collection_name,
-                    r,
-                    is_file_check,
-                    my_path, // Continue passing constructed path for folders
-                    separator,
-                );
-            }
+        collection_trees.push(root_node);
+    }
+
+    collection_trees
+}
+
+/// A structural change between two builds of a tree. Applying the removals
+/// first and then the rest in order turns the old tree into the new one.
+#[derive(Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum TreeChange {
+    /// `node` and everything below it is new
+    Added {
+        /// `None` for a collection root
+        parent_path: Option<String>,
+        /// Position among the parent's children
+        index: usize,
+        node: TreeNode,
+    },
+    Removed {
+        id: String,
+        path: String,
+    },
+    /// `node` moved from `old_path`; a folder keeps its files but gets new ids
+    Renamed {
+        old_id: String,
+        old_path: String,
+        parent_path: Option<String>,
+        index: usize,
+        node: TreeNode,
+    },
+}
+
+impl TreeChange {
+    /// Name of the event the change is emitted as
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            TreeChange::Added { .. } => "tree-node-added",
+            TreeChange::Removed { .. } => "tree-node-removed",
+            TreeChange::Renamed { .. } => "tree-node-renamed",
         }
+    }
+}
 
-        for r in res_list {
-            // Relative path using robust Path component logic
-            let root_path = std::path::Path::new(&common_root);
-            let resource_path = std::path::Path::new(&r.path);
-
-            let relative_clean = match resource_path.strip_prefix(root_path) {
-                Ok(p) => p.to_string_lossy().to_string(),
-                Err(_) => {
-                    // File is outside the common root
-                    // Flatten it for the view: just show the filename.
-                    // This prevents the full absolute path directory structure (e.g., /home/user/...)
-                    // from being rendered inside the collection tree.
-                    resource_path
-                        .file_name()
-                        .map(|n| n.to_string_lossy().to_string())
-                        .unwrap_or_else(|| r.path.clone())
-                }
-            };
-
-            // Split
-            let parts: Vec<&str> = relative_clean
-                .split(separator)
-                .filter(|s| !s.is_empty())
-                .collect();
-
-            insert_path(
-                &mut root_children,
-                &parts,
-                &collection_name,
-                r,
-                true,
-                common_root.clone(),
-                separator,
-            );
+struct Placed<'a> {
+    node: &'a TreeNode,
+    parent: Option<&'a TreeNode>,
+    index: usize,
+}
+
+/// Nodes by id, in depth-first order
+fn place<'a>(
+    nodes: &'a [TreeNode],
+    parent: Option<&'a TreeNode>,
+    order: &mut Vec<&'a str>,
+    out: &mut HashMap<&'a str, Placed<'a>>,
+) {
+    for (index, node) in nodes.iter().enumerate() {
+        order.push(&node.id);
+        out.insert(
+            &node.id,
+            Placed {
+                node,
+                parent,
+                index,
+            },
+        );
+        place(&node.children, Some(node), order, out);
+    }
+}
+
+fn file_ids(node: &TreeNode, out: &mut Vec<String>) {
+    if node.r#type == "file" {
+        out.push(node.id.clone());
+    }
+    for child in &node.children {
+        file_ids(child, out);
+    }
+}
+
+/// The smallest set of changes that turns `old` into `new`. Files are
+/// matched by resource id, so a moved file is a rename; a folder whose files
+/// all moved together to a new folder is a rename as well.
+pub fn diff_trees(old: &[TreeNode], new: &[TreeNode]) -> Vec<TreeChange> {
+    let (mut old_order, mut new_order) = (Vec::new(), Vec::new());
+    let (mut old_nodes, mut new_nodes) = (HashMap::new(), HashMap::new());
+    place(old, None, &mut old_order, &mut old_nodes);
+    place(new, None, &mut new_order, &mut new_nodes);
+
+    // Same place in both trees, so nothing to do for the node itself
+    let stable = |id: &str| match (old_nodes.get(id), new_nodes.get(id)) {
+        (Some(o), Some(n)) => {
+            o.node.path == n.node.path && o.parent.map(|p| &p.id) == n.parent.map(|p| &p.id)
+        }
+        _ => false,
+    };
+    // Changes below a parent that changed itself are part of its subtree
+    let parent_stable = |p: &Placed| p.parent.is_none_or(|p| stable(&p.id));
+
+    let mut removed = Vec::new();
+    for id in &old_order {
+        let o = &old_nodes[id];
+        if stable(id) || !parent_stable(o) {
+            continue;
+        }
+        // Moved somewhere a rename can be emitted for
+        if new_nodes.get(id).is_some_and(&parent_stable) {
+            continue;
         }
+        removed.push(o.node);
+    }
 
-        // Convert TempNode to TreeNode recursively and sort
-        fn convert(map: HashMap<String, TempNode>) -> Vec<TreeNode> {
-            let mut nodes: Vec<TreeNode> = map
-                .into_iter()
-                .map(|(_, v)| {
-                    let mut children = convert(v.children);
-                    // Sort children: folders first, then alphabetical
-                    children.sort_by(|a, b| {
-                        if a.r#type != b.r#type {
-                            if a.r#type == "folder" {
-                                std::cmp::Ordering::Less
-                            } else {
-                                std::cmp::Ordering::Greater
-                            }
-                        } else {
-                            a.name.cmp(&b.name)
-                        }
-                    });
+    let mut changes = Vec::new();
+    let mut added = Vec::new();
+    for id in &new_order {
+        let n = &new_nodes[id];
+        if stable(id) || !parent_stable(n) {
+            continue;
+        }
+        let parent_path = n.parent.map(|p| p.path.clone());
+        match old_nodes.get(id) {
+            Some(o) => changes.push(TreeChange::Renamed {
+                old_id: o.node.id.clone(),
+                old_path: o.node.path.clone(),
+                parent_path,
+                index: n.index,
+                node: n.node.clone(),
+            }),
+            None => {
+                added.push((changes.len(), n.node));
+                changes.push(TreeChange::Added {
+                    parent_path,
+                    index: n.index,
+                    node: n.node.clone(),
+                });
+            }
+        }
+    }
 
-                    TreeNode {
-                        id: v.id,
-                        name: v.name,
-                        r#type: v.r#type,
-                        path: v.path,
-                        children,
-                        is_root: None,
-                        metadata: None,
-                    }
+    // Pair up removed and added folders holding the same files
+    let mut removals = Vec::new();
+    for node in removed {
+        let mut ids = Vec::new();
+        file_ids(node, &mut ids);
+        ids.sort();
+        let twin = (node.r#type == "folder" && !ids.is_empty())
+            .then(|| {
+                added.iter().position(|(_, a)| {
+                    let mut other = Vec::new();
+                    file_ids(a, &mut other);
+                    other.sort();
+                    a.r#type == "folder" && other == ids
                 })
-                .collect();
-
-            // Sort at this level too
-            nodes.sort_by(|a, b| {
-                if a.r#type != b.r#type {
-                    if a.r#type == "folder" {
-                        std::cmp::Ordering::Less
-                    } else {
-                        std::cmp::Ordering::Greater
-                    }
-                } else {
-                    a.name.cmp(&b.name)
+            })
+            .flatten();
+        match twin {
+            Some(pos) => {
+                let (at, _) = added.remove(pos);
+                if let TreeChange::Added {
+                    parent_path,
+                    index,
+                    node: new_node,
+                } = changes[at].clone()
+                {
+                    changes[at] = TreeChange::Renamed {
+                        old_id: node.id.clone(),
+                        old_path: node.path.clone(),
+                        parent_path,
+                        index,
+                        node: new_node,
+                    };
                 }
-            });
-
-            nodes
+            }
+            None => removals.push(TreeChange::Removed {
+                id: node.id.clone(),
+                path: node.path.clone(),
+            }),
         }
+    }
 
-        root_node.children = convert(root_children);
+    removals.extend(changes);
+    removals
+}
 
-        // 4. Flatten single-child directory chains
-        // Logic: recursively peel off wrapper folders
-        // Frontend logic specifically for ROOT:
-        // let currentChildren = rootNode.children;
-        // while (currentChildren.length === 1 && currentChildren[0].type === "folder") { ... }
-        // rootNode.children = currentChildren;
-
-        let mut current_children = std::mem::take(&mut root_node.children);
-        while current_children.len() == 1 && current_children[0].r#type == "folder" {
-            let child = &mut current_children[0];
-            let has_single_folder_grandchild =
-                child.children.len() == 1 && child.children[0].r#type == "folder";
-
-            if has_single_folder_grandchild {
-                // Peel
-                current_children = std::mem::take(&mut child.children);
-            } else {
-                break;
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resource(id: &str, path: &str) -> Resource {
+        Resource {
+            id: id.to_string(),
+            path: path.to_string(),
+            kind: "file".to_string(),
+            collection: "algebra".to_string(),
+            title: None,
+            content_hash: None,
+            metadata: None,
+            created_at: None,
+            updated_at: None,
         }
-        root_node.children = current_children;
+    }
 
-        collection_trees.push(root_node);
+    fn names(nodes: &[TreeNode]) -> Vec<&str> {
+        nodes.iter().map(|n| n.name.as_str()).collect()
     }
 
-    collection_trees
+    #[test]
+    fn test_natural_cmp() {
+        let mut names = vec![
+            "exercise_10.tex",
+            "Exercise_2.tex",
+            "exercise_1.tex",
+            "exercise_02.tex",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            [
+                "exercise_1.tex",
+                "Exercise_2.tex",
+                "exercise_02.tex",
+                "exercise_10.tex"
+            ]
+        );
+        assert_eq!(natural_cmp("ch", "ch1"), std::cmp::Ordering::Less);
+    }
+
+    #[test]
+    fn test_sort_nodes() {
+        let node = |name: &str, kind: &str, size: u64| TreeNode {
+            id: name.to_string(),
+            name: name.to_string(),
+            r#type: kind.to_string(),
+            path: name.to_string(),
+            children: Vec::new(),
+            is_root: None,
+            metadata: Some(serde_json::json!({ "size": size })),
+        };
+        let mut nodes = vec![
+            node("b10.tex", "file", 1),
+            node("b2.tex", "file", 30),
+            node("figures", "folder", 0),
+            node("a.tex", "file", 20),
+        ];
+
+        sort_nodes(&mut nodes, &TreeSort::default());
+        assert_eq!(names(&nodes), ["figures", "a.tex", "b2.tex", "b10.tex"]);
+
+        let by_size = TreeSort {
+            by: SortKey::Size,
+            folders_first: false,
+            descending: true,
+            ..Default::default()
+        };
+        sort_nodes(&mut nodes, &by_size);
+        assert_eq!(names(&nodes), ["b2.tex", "a.tex", "b10.tex", "figures"]);
+
+        let plain = TreeSort {
+            natural: false,
+            ..Default::default()
+        };
+        sort_nodes(&mut nodes, &plain);
+        assert_eq!(names(&nodes), ["figures", "a.tex", "b10.tex", "b2.tex"]);
+    }
+
+    #[test]
+    fn test_split_path() {
+        let split = SplitPath::new("c:/Math\\limits/./ex.tex", true);
+        assert_eq!(split.root, "C:");
+        assert_eq!(split.parts, ["Math", "limits", "ex.tex"]);
+        assert_eq!(split.join(2, true), "C:\\Math\\limits");
+        assert_eq!(split.join(0, true), "C:\\");
+
+        let unc = SplitPath::new("\\\\server\\share/math/ex.tex", true);
+        assert_eq!(unc.root, "\\\\server\\share");
+        assert_eq!(unc.join(1, true), "\\\\server\\share\\math");
+
+        let unix = SplitPath::new("/home/me/math/ex.tex", false);
+        assert_eq!(unix.root, "/");
+        assert_eq!(unix.join(3, false), "/home/me/math");
+    }
+
+    #[test]
+    fn test_mixed_separators_on_windows() {
+        let resources = vec![
+            resource("a", "C:\\Math\\limits\\a.tex"),
+            resource("b", "c:/math/Limits/b.tex"),
+            resource("c", "C:\\Math/series\\c.tex"),
+            resource("d", "D:\\elsewhere\\d.tex"),
+        ];
+        let roots = HashMap::from([("algebra".to_string(), "C:/Math/".to_string())]);
+        let tree = build_tree(
+            resources,
+            &roots,
+            &TreeFilter::default(),
+            &TreeSort::default(),
+            true,
+            true,
+        );
+
+        let root = &tree[0];
+        assert_eq!(root.path, "C:/Math/");
+        assert_eq!(names(&root.children), ["limits", "series", "d.tex"]);
+        let limits = &root.children[0];
+        assert_eq!(limits.path, "C:\\Math\\limits");
+        assert_eq!(names(&limits.children), ["a.tex", "b.tex"]);
+        assert_eq!(limits.children[1].path, "c:/math/Limits/b.tex");
+    }
+
+    #[test]
+    fn test_common_root_without_collection_path() {
+        let windows = build_tree(
+            vec![
+                resource("a", "C:\\Math\\limits\\a.tex"),
+                resource("b", "C:/Math/series/b.tex"),
+            ],
+            &HashMap::new(),
+            &TreeFilter::default(),
+            &TreeSort::default(),
+            true,
+            true,
+        );
+        assert_eq!(windows[0].path, "C:\\Math");
+        assert_eq!(names(&windows[0].children), ["limits", "series"]);
+
+        let unix = build_tree(
+            vec![
+                resource("a", "/home/me/math/limits/a.tex"),
+                resource("b", "/home/me/math/Limits/b.tex"),
+            ],
+            &HashMap::new(),
+            &TreeFilter::default(),
+            &TreeSort::default(),
+            true,
+            false,
+        );
+        assert_eq!(unix[0].path, "/home/me/math");
+        assert_eq!(names(&unix[0].children), ["Limits", "limits"]);
+    }
+
+    #[test]
+    fn test_flatten_chain() {
+        let build = |flatten| {
+            build_tree(
+                vec![resource("a", "/c/x/y/a.tex"), resource("b", "/c/x/y/b.tex")],
+                &HashMap::from([("algebra".to_string(), "/c".to_string())]),
+                &TreeFilter::default(),
+                &TreeSort::default(),
+                flatten,
+                false,
+            )
+        };
+
+        let flat = build(true);
+        assert_eq!(names(&flat[0].children), ["y"]);
+        assert_eq!(flat[0].children[0].path, "/c/x/y");
+        assert_eq!(
+            flat[0].metadata.as_ref().unwrap()["collapsed"],
+            serde_json::json!(["x"])
+        );
+
+        let full = build(false);
+        assert_eq!(names(&full[0].children), ["x"]);
+        assert!(full[0]
+            .metadata
+            .as_ref()
+            .unwrap()
+            .get("collapsed")
+            .is_none());
+    }
 }
@@ -0,0 +1,280 @@
+//! Virtual Folders
+//!
+//! An alternative file tree where resources are grouped by their database
+//! metadata (tag → difficulty → resource, ...) instead of the folders they
+//! live in on disk, so the explorer can mirror the pedagogical structure of
+//! a collection. A resource with several values for a level (two tags, two
+//! chapters) appears under each of them.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::database::entities::Resource;
+use crate::tree_builder::TreeNode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupField {
+    Tag,
+    Difficulty,
+    Field,
+    Chapter,
+    Section,
+    FileType,
+    /// Resource type (file, document, figure, ...)
+    Kind,
+}
+
+impl GroupField {
+    /// Folder holding the resources without a value
+    fn missing_label(self) -> &'static str {
+        match self {
+            GroupField::Tag => "No tag",
+            GroupField::Difficulty => "No difficulty",
+            GroupField::Field => "No field",
+            GroupField::Chapter => "No chapter",
+            GroupField::Section => "No section",
+            GroupField::FileType => "No file type",
+            GroupField::Kind => "Other",
+        }
+    }
+
+    pub fn folder_name(self, value: &str) -> String {
+        match self {
+            GroupField::Difficulty => format!("Difficulty {}", value),
+            _ => value.to_string(),
+        }
+    }
+
+    /// `(resource_id, value)` rows, `None` when the value is on the resource
+    fn query(self) -> Option<&'static str> {
+        match self {
+            GroupField::Tag => Some("SELECT resource_id, tag AS value FROM resource_tags"),
+            GroupField::Difficulty => Some(
+                "SELECT resource_id, CAST(difficulty AS TEXT) AS value
+                 FROM resource_files WHERE difficulty IS NOT NULL",
+            ),
+            GroupField::Field => Some(
+                "SELECT rf.resource_id, f.name AS value
+                 FROM resource_files rf JOIN fields f ON f.id = rf.field_id",
+            ),
+            GroupField::Chapter => Some(
+                "SELECT rc.resource_id, c.name AS value
+                 FROM resource_file_chapters rc JOIN chapters c ON c.id = rc.chapter_id",
+            ),
+            GroupField::Section => Some(
+                "SELECT rs.resource_id, s.name AS value
+                 FROM resource_file_sections rs JOIN sections s ON s.id = rs.section_id",
+            ),
+            GroupField::FileType => Some(
+                "SELECT rf.resource_id, ft.name AS value
+                 FROM resource_files rf JOIN file_types ft ON ft.id = rf.file_type_id",
+            ),
+            GroupField::Kind => None,
+        }
+    }
+}
+
+/// Values of each resource for one field
+pub type GroupValues = HashMap<String, Vec<String>>;
+
+/// Load the values of every field in `levels`
+pub async fn load_values(
+    pool: &Pool<Sqlite>,
+    levels: &[GroupField],
+) -> Result<HashMap<GroupField, GroupValues>, String> {
+    let mut all = HashMap::new();
+    for &field in levels {
+        let Some(sql) = field.query() else {
+            continue;
+        };
+        let rows = sqlx::query(sql)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut values: GroupValues = HashMap::new();
+        for row in rows {
+            values
+                .entry(row.get("resource_id"))
+                .or_default()
+                .push(row.get("value"));
+        }
+        all.insert(field, values);
+    }
+    Ok(all)
+}
+
+fn values_of(
+    resource: &Resource,
+    field: GroupField,
+    values: &HashMap<GroupField, GroupValues>,
+) -> Vec<String> {
+    if field == GroupField::Kind {
+        return vec![resource.kind.clone()];
+    }
+    values
+        .get(&field)
+        .and_then(|v| v.get(&resource.id))
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn group(
+    resources: Vec<&Resource>,
+    levels: &[GroupField],
+    values: &HashMap<GroupField, GroupValues>,
+    parent_id: &str,
+) -> Vec<TreeNode> {
+    let Some((&field, rest)) = levels.split_first() else {
+        let mut files: Vec<TreeNode> = resources
+            .into_iter()
+            .map(|r| {
+                let name = Path::new(&r.path)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| r.path.clone());
+                TreeNode {
+                    id: format!("{}/{}", parent_id, r.id),
+                    name,
+                    r#type: "file".to_string(),
+                    path: r.path.clone(),
+                    children: Vec::new(),
+                    is_root: None,
+                    metadata: Some(serde_json::json!({ "resourceId": r.id })),
+                }
+            })
+            .collect();
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+        return files;
+    };
+
+    let mut buckets: BTreeMap<String, Vec<&Resource>> = BTreeMap::new();
+    let mut missing = Vec::new();
+    for r in resources {
+        let found = values_of(r, field, values);
+        if found.is_empty() {
+            missing.push(r);
+        }
+        for value in found {
+            buckets.entry(value).or_default().push(r);
+        }
+    }
+
+    let folder = |name: String, members: Vec<&Resource>| {
+        let id = format!("{}/{}", parent_id, name);
+        TreeNode {
+            children: group(members, rest, values, &id),
+            id: id.clone(),
+            name,
+            r#type: "folder".to_string(),
+            path: id,
+            is_root: None,
+            metadata: Some(serde_json::json!({ "virtual": true })),
+        }
+    };
+    let mut nodes: Vec<TreeNode> = buckets
+        .into_iter()
+        .map(|(value, members)| folder(field.folder_name(&value), members))
+        .collect();
+    if !missing.is_empty() {
+        nodes.push(folder(field.missing_label().to_string(), missing));
+    }
+    nodes
+}
+
+/// One root per collection, with folders for each level of `levels`.
+/// Folder ids and paths are virtual; files keep the path of their resource.
+pub fn build_grouped_tree(
+    resources: &[Resource],
+    levels: &[GroupField],
+    values: &HashMap<GroupField, GroupValues>,
+    collection_roots: &HashMap<String, String>,
+) -> Vec<TreeNode> {
+    let mut by_collection: BTreeMap<&str, Vec<&Resource>> = BTreeMap::new();
+    for r in resources.iter().filter(|r| r.kind != "folder") {
+        by_collection.entry(&r.collection).or_default().push(r);
+    }
+
+    by_collection
+        .into_iter()
+        .map(|(collection, members)| TreeNode {
+            id: collection.to_string(),
+            name: collection.to_string(),
+            r#type: "folder".to_string(),
+            path: collection_roots
+                .get(collection)
+                .cloned()
+                .unwrap_or_default(),
+            children: group(members, levels, values, collection),
+            is_root: Some(true),
+            metadata: Some(serde_json::json!({
+                "collectionName": collection,
+                "virtual": true,
+            })),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resource(id: &str, path: &str) -> Resource {
+        Resource {
+            id: id.to_string(),
+            path: path.to_string(),
+            kind: "file".to_string(),
+            collection: "algebra".to_string(),
+            title: None,
+            content_hash: None,
+            metadata: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_build_grouped_tree() {
+        let resources = vec![
+            resource("a", "/c/a.tex"),
+            resource("b", "/c/b.tex"),
+            resource("c", "/c/c.tex"),
+        ];
+        let values = HashMap::from([
+            (
+                GroupField::Tag,
+                HashMap::from([
+                    ("a".to_string(), vec!["limits".to_string()]),
+                    (
+                        "b".to_string(),
+                        vec!["limits".to_string(), "series".to_string()],
+                    ),
+                ]),
+            ),
+            (
+                GroupField::Difficulty,
+                HashMap::from([("a".to_string(), vec!["3".to_string()])]),
+            ),
+        ]);
+        let tree = build_grouped_tree(
+            &resources,
+            &[GroupField::Tag, GroupField::Difficulty],
+            &values,
+            &HashMap::new(),
+        );
+
+        assert_eq!(tree.len(), 1);
+        let names = |nodes: &[TreeNode]| nodes.iter().map(|n| n.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&tree[0].children), ["limits", "series", "No tag"]);
+        let limits = &tree[0].children[0];
+        assert_eq!(names(&limits.children), ["Difficulty 3", "No difficulty"]);
+        assert_eq!(limits.children[0].children[0].path, "/c/a.tex");
+        assert_eq!(
+            limits.children[1].children[0].id,
+            "algebra/limits/No difficulty/b"
+        );
+        assert_eq!(tree[0].children[2].children[0].children[0].name, "c.tex");
+    }
+}
@@ -1,48 +1,118 @@
-use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::Path;
-use std::sync::mpsc::channel;
+use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tauri::{AppHandle, Emitter};
 
+use crate::database::DatabaseManager;
+use crate::tree_builder::{self, TreeChange, TreeNode};
+use crate::{git, indexer, resources};
+
+/// Quiet period after the last filesystem event before the status is rescanned
+const STATUS_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Payload of `git-status-changed`
+#[derive(Clone, serde::Serialize)]
+pub struct GitStatusChanged {
+    pub repo_path: String,
+    pub files: Vec<git::GitFileStatus>,
+    /// HEAD or a branch/tag moved, so branch lists need reloading too
+    pub refs_changed: bool,
+}
+
+/// How a changed path affects the status of the repository at `repo`
+fn classify_git_path(repo: &Path, path: &Path) -> (bool, bool) {
+    let Ok(inside) = path.strip_prefix(repo.join(".git")) else {
+        // Working tree files always count
+        return (true, false);
+    };
+    let name = inside.to_string_lossy();
+    let name = name.trim_end_matches(".lock");
+    let refs_changed = name == "HEAD" || name == "packed-refs" || name.starts_with("refs");
+    (refs_changed || name == "index", refs_changed)
+}
+
+/// Watches a repository's working tree, keeps its cached status fresh and
+/// emits `git-status-changed` when the status or refs change.
 pub struct GitWatcher {
     watcher: Arc<Mutex<Option<RecommendedWatcher>>>,
+    repo_path: Mutex<Option<String>>,
+    cache: Arc<git::StatusCache>,
 }
 
 impl GitWatcher {
-    pub fn new() -> Self {
+    pub fn new(cache: Arc<git::StatusCache>) -> Self {
         Self {
             watcher: Arc::new(Mutex::new(None)),
+            repo_path: Mutex::new(None),
+            cache,
         }
     }
 
     pub fn watch(&self, path: &str, app: AppHandle) -> Result<(), String> {
-        let (tx, rx) = channel();
+        let (tx, rx) = channel::<notify::Result<Event>>();
 
-        // Create a watcher object, delivering debounced events.
-        // The notification back-end is selected based on the platform.
         let mut watcher =
             RecommendedWatcher::new(tx, Config::default()).map_err(|e| e.to_string())?;
-
-        // Add a path to be watched. All files and directories at that path and
-        // below will be monitored for changes.
         watcher
             .watch(Path::new(path), RecursiveMode::Recursive)
             .map_err(|e| e.to_string())?;
 
-        // Store the watcher so it stays alive
+        // Replacing the previous watcher drops its sender, ending its thread
+        self.unwatch();
         *self.watcher.lock().unwrap() = Some(watcher);
+        *self.repo_path.lock().unwrap() = Some(path.to_string());
+        self.cache.track(path);
 
-        // Spawn a thread to handle events
+        let cache = self.cache.clone();
+        let repo_path = path.to_string();
         std::thread::spawn(move || {
-            for res in rx {
-                match res {
-                    Ok(_) => {
-                        // Debounce logic could be here, or frontend can debounce.
-                        // For simply telling frontend "something changed", we emit event.
-                        // Filter for relevant git events if needed, but monitoring whole repo is safer.
-                        let _ = app.emit("git-refresh", ());
+            let root = PathBuf::from(&repo_path);
+            while let Ok(first) = rx.recv() {
+                let (mut relevant, mut refs_changed) = (false, false);
+                // Invalidate right away, so a status requested before the
+                // batch settles is already rescanned
+                let mut collect = |res: notify::Result<Event>| match res {
+                    Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                        for path in &event.paths {
+                            let (counts, refs) = classify_git_path(&root, path);
+                            relevant |= counts;
+                            refs_changed |= refs;
+                        }
+                        if relevant {
+                            cache.invalidate(&repo_path);
+                        }
                     }
+                    Ok(_) => {}
                     Err(e) => println!("watch error: {:?}", e),
+                };
+                collect(first);
+                loop {
+                    match rx.recv_timeout(STATUS_DEBOUNCE) {
+                        Ok(res) => collect(res),
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                if !relevant {
+                    continue;
+                }
+
+                match cache.refresh(&repo_path) {
+                    Ok((files, changed)) if changed || refs_changed => {
+                        let _ = app.emit(
+                            "git-status-changed",
+                            GitStatusChanged {
+                                repo_path: repo_path.clone(),
+                                files,
+                                refs_changed,
+                            },
+                        );
+                    }
+                    Ok(_) => {}
+                    Err(e) => println!("git status error: {}", e),
                 }
             }
         });
@@ -53,5 +123,158 @@ impl GitWatcher {
     pub fn unwatch(&self) {
         // Dropping the watcher stops it
         *self.watcher.lock().unwrap() = None;
+        if let Some(path) = self.repo_path.lock().unwrap().take() {
+            self.cache.untrack(&path);
+        }
+    }
+}
+
+/// Quiet period after the last filesystem event before a batch is indexed
+const INDEX_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Payload of `tree-node-added`, `tree-node-removed` and `tree-node-renamed`
+#[derive(Clone, serde::Serialize)]
+pub struct TreeNodeChanged<'a> {
+    pub collection: &'a str,
+    #[serde(flatten)]
+    pub change: &'a TreeChange,
+}
+
+/// Unfiltered tree of one collection; the explorer applies its own filter
+async fn collection_tree(db: &DatabaseManager, root: &Path, collection: &str) -> Vec<TreeNode> {
+    let resources = db
+        .get_resources_by_collection(collection)
+        .await
+        .unwrap_or_default();
+    let roots = HashMap::from([(collection.to_string(), root.to_string_lossy().to_string())]);
+    let filter = tree_builder::TreeFilter {
+        all_files: true,
+        ..Default::default()
+    };
+    tree_builder::build_file_tree(resources, &roots, &filter, &Default::default(), true)
+}
+
+/// Watches collection root directories and incrementally re-indexes changed
+/// files, emitting `resources-changed` once per debounced batch along with
+/// the tree nodes that were added, removed or renamed.
+pub struct CollectionWatcher {
+    watcher: Arc<Mutex<Option<RecommendedWatcher>>>,
+}
+
+impl CollectionWatcher {
+    pub fn new() -> Self {
+        Self {
+            watcher: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Replace the watched set with `roots` (root directory, collection name)
+    pub fn watch(
+        &self,
+        roots: Vec<(PathBuf, String)>,
+        db: Arc<tokio::sync::Mutex<Option<DatabaseManager>>>,
+        app: AppHandle,
+    ) -> Result<(), String> {
+        let (tx, rx) = channel::<notify::Result<Event>>();
+
+        let mut watcher =
+            RecommendedWatcher::new(tx, Config::default()).map_err(|e| e.to_string())?;
+        for (root, _) in &roots {
+            watcher
+                .watch(root, RecursiveMode::Recursive)
+                .map_err(|e| format!("{}: {}", root.display(), e))?;
+        }
+
+        // Replacing the previous watcher drops its sender, ending its thread
+        *self.watcher.lock().unwrap() = Some(watcher);
+
+        std::thread::spawn(move || {
+            // Last tree of each collection, diffed against after every batch
+            let mut trees: HashMap<String, Vec<TreeNode>> = tauri::async_runtime::block_on(async {
+                let guard = db.lock().await;
+                let mut trees = HashMap::new();
+                if let Some(db) = guard.as_ref() {
+                    for (root, collection) in &roots {
+                        trees.insert(
+                            collection.clone(),
+                            collection_tree(db, root, collection).await,
+                        );
+                    }
+                }
+                trees
+            });
+
+            // Block for the first event, then collect until things go quiet
+            while let Ok(first) = rx.recv() {
+                let mut paths: HashSet<PathBuf> = HashSet::new();
+                let mut collect = |res: notify::Result<Event>| match res {
+                    Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                        paths.extend(event.paths)
+                    }
+                    Ok(_) => {}
+                    Err(e) => println!("watch error: {:?}", e),
+                };
+                collect(first);
+                loop {
+                    match rx.recv_timeout(INDEX_DEBOUNCE) {
+                        Ok(res) => collect(res),
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                if paths.is_empty() {
+                    continue;
+                }
+
+                let (changes, rebuilt) = tauri::async_runtime::block_on(async {
+                    let guard = db.lock().await;
+                    let Some(db) = guard.as_ref() else {
+                        return (HashMap::new(), Vec::new());
+                    };
+                    let changes = indexer::apply_changes(db, &roots, &paths).await;
+                    let mut rebuilt = Vec::new();
+                    for (root, collection) in &roots {
+                        if changes.contains_key(collection) {
+                            let tree = collection_tree(db, root, collection).await;
+                            rebuilt.push((collection.clone(), tree));
+                        }
+                    }
+                    (changes, rebuilt)
+                });
+
+                for (collection, tree) in rebuilt {
+                    let old = trees.insert(collection.clone(), tree).unwrap_or_default();
+                    for change in tree_builder::diff_trees(&old, &trees[&collection]) {
+                        let _ = app.emit(
+                            change.event_name(),
+                            TreeNodeChanged {
+                                collection: &collection,
+                                change: &change,
+                            },
+                        );
+                    }
+                }
+
+                for (collection, set) in changes {
+                    if !set.updated.is_empty() {
+                        resources::emit_changed(
+                            &app,
+                            "updated",
+                            set.updated,
+                            Some(collection.clone()),
+                        );
+                    }
+                    if !set.deleted.is_empty() {
+                        resources::emit_changed(&app, "deleted", set.deleted, Some(collection));
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    pub fn unwatch(&self) {
+        *self.watcher.lock().unwrap() = None;
     }
 }
@@ -59,6 +59,7 @@ import { templates, getTemplateById } from "./services/templateService";
 
 import { AISidebar } from "./components/ai/AISidebar";
 import { UnsavedChangesModal } from "./components/modals/UnsavedChangesModal";
+import { GitCredentialsModal } from "./components/modals/GitCredentialsModal";
 import { DtexImportModal } from "./components/modals/DtexImportModal";
 import { BatchExportModal } from "./components/modals/BatchExportModal";
 import type { DtexFile, DtexDatabaseInfo } from "./types/dtex";
@@ -92,6 +93,9 @@ import { useCompilation } from "./hooks/useCompilation";
 import { usePdfState } from "./hooks/usePdfState";
 import { useCursorStore } from "./stores/cursorStore";
 import { usePendingWriteListener } from "./hooks/usePendingWriteListener";
+import { useDraftRecovery, useDrafts } from "./hooks/useDrafts";
+import { useSession } from "./hooks/useSession";
+import { useRecentProjectsStore } from "./stores/recentProjectsStore";
 
 // --- CSS Variables Resolver ---
 const resolver: CSSVariablesResolver = (theme) => ({
@@ -189,14 +193,12 @@ export default function App() {
   // --- File System & DB State ---
 
   // --- Recent Projects State ---
-  const [recentProjects, setRecentProjects] = useState<string[]>([]);
-  const addToRecent = useCallback((path: string) => {
-    setRecentProjects((prev) => {
-      const newRecent = [path, ...prev.filter((p) => p !== path)].slice(0, 10);
-      localStorage.setItem("recentProjects", JSON.stringify(newRecent));
-      return newRecent;
-    });
-  }, []);
+  const recentProjectList = useRecentProjectsStore((state) => state.projects);
+  const addToRecent = useRecentProjectsStore((state) => state.open);
+  const recentProjects = useMemo(
+    () => recentProjectList.map((p) => p.path),
+    [recentProjectList],
+  );
 
   // --- Project Files Hook ---
   const {
@@ -293,6 +295,7 @@ export default function App() {
 
   // --- Auto-Save Hook ---
   useDtexAutoSave();
+  const { autosaveDraft, discardDrafts } = useDrafts();
 
   // --- Derived State (UI) ---from Zustand selectors) ---
   const activeTab = useActiveTab();
@@ -331,6 +334,7 @@ export default function App() {
           markDirty(targetId, false);
           updateTabContent(targetId, contentToSave);
         }
+        discardDrafts(tab.id);
 
         // Save local history snapshot (fire and forget)
         invoke("save_history_snapshot_cmd", {
@@ -345,7 +349,7 @@ export default function App() {
         console.error("Failed to save file:", e);
       }
     },
-    [tabs, activeTabId, markDirty, updateTabContent],
+    [tabs, activeTabId, markDirty, updateTabContent, discardDrafts],
   );
 
   // --- Compilation Hook ---
@@ -458,15 +462,7 @@ export default function App() {
   // --- Handlers ---
   // --- Load Recent Projects on Mount ---
   useEffect(() => {
-    const saved = localStorage.getItem("recentProjects");
-    if (saved) {
-      try {
-        const parsed = JSON.parse(saved);
-        setRecentProjects(parsed);
-      } catch (e) {
-        console.error("Failed to parse recent projects", e);
-      }
-    }
+    useRecentProjectsStore.getState().load();
   }, []);
 
   // --- Initialize LSP when rootPath or loaded collections change ---
@@ -847,12 +843,18 @@ export default function App() {
       if (tab && !tab.isDirty) {
         debouncedMarkDirty(id);
       }
+      autosaveDraft(id, val);
 
       if (activeActivity === "outline") {
         debouncedOutlineUpdate(val);
       }
     },
-    [activeActivity, debouncedOutlineUpdate, debouncedMarkDirty],
+    [
+      activeActivity,
+      debouncedOutlineUpdate,
+      debouncedMarkDirty,
+      autosaveDraft,
+    ],
   );
 
   // --- FIX: Update structure on view change ---
@@ -1077,6 +1079,26 @@ export default function App() {
     [handleOpenFileNode, handleRevealLine],
   );
 
+  // --- Session: reopen what was open when the window closed ---
+  const handleOpenFilePath = useCallback(
+    (path: string) =>
+      handleOpenFileNode({
+        id: path,
+        name: path.split(/[/\\]/).pop() || path,
+        type: "file",
+        path,
+        children: [],
+      }),
+    [handleOpenFileNode],
+  );
+  useSession({
+    rootPath,
+    onOpenProject: handleOpenRecent,
+    onOpenFile: handleOpenFilePath,
+    onRevealLine: handleRevealLine,
+  });
+  useDraftRecovery({ onOpenFile: handleOpenFilePath });
+
   const handleExportToTex = useCallback(
     async (resourceId?: string) => {
       try {
@@ -1779,6 +1801,7 @@ export default function App() {
                     >
                       <DatabaseView
                         onOpenFile={handleOpenFileFromTable}
+                        onOpenFileAtLine={handleOpenFileAtLine}
                         canInsert={(() => {
                           if (!activeTab) return false;
 
@@ -1835,7 +1858,6 @@ export default function App() {
                         onCreateEmpty={handleCreateEmpty}
                         onOpenWizard={handleOpenPreambleWizard}
                         onCreateFromTemplate={handleCreateFromTemplate}
-                        recentProjects={recentProjects}
                         onOpenRecent={handleOpenRecent}
                         onOpenDatabase={handleOpenDatabase}
                         onOpenPackageBrowser={handleOpenPackageBrowser}
@@ -1883,7 +1905,6 @@ export default function App() {
                         onCreateEmpty={handleCreateEmpty}
                         onOpenWizard={handleOpenPreambleWizard}
                         onCreateFromTemplate={handleCreateFromTemplate}
-                        recentProjects={recentProjects}
                         onOpenRecent={handleOpenRecent}
                         onOpenDatabase={handleOpenDatabase}
                         onOpenPackageBrowser={handleOpenPackageBrowser}
@@ -1922,6 +1943,7 @@ export default function App() {
                         >
                           <DatabaseView
                             onOpenFile={handleOpenFileFromTable}
+                            onOpenFileAtLine={handleOpenFileAtLine}
                             canInsert={(() => {
                               if (!activeTab) return false;
 
@@ -2330,6 +2352,7 @@ export default function App() {
         onSave={handleConfirmSave}
         fileName={tabs.find((t) => t.id === tabToCloseId)?.title || "this file"}
       />
+      <GitCredentialsModal />
       <DtexImportModal
         opened={dtexImportModal.opened}
         onClose={() =>
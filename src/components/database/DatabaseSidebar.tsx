@@ -17,6 +17,7 @@ import {
   MultiSelect,
   Checkbox,
   Menu,
+  Select,
 } from "@mantine/core";
 import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
 import {
@@ -38,9 +39,15 @@ import {
   faExpand,
   faFileImport,
   faEllipsisVertical,
+  faPen,
+  faCopy,
+  faTrashArrowUp,
+  faLink,
 } from "@fortawesome/free-solid-svg-icons";
 import { useDatabaseStore } from "../../stores/databaseStore";
+import { useSettingsStore } from "../../stores/settingsStore";
 import { open } from "@tauri-apps/plugin-dialog";
+import { invoke } from "@tauri-apps/api/core";
 import { useTranslation } from "react-i18next";
 
 // Import shared tree components
@@ -52,6 +59,7 @@ import {
   ToolbarAction,
 } from "../shared/tree";
 import { FileSystemNode } from "../layout/Sidebar";
+import { RecentlyDeleted } from "./RecentlyDeleted";
 
 // Props for project folder operations
 interface DatabaseSidebarProps {
@@ -73,6 +81,40 @@ interface DatabaseSidebarProps {
   onExportToTex?: (resourceId?: string) => void;
 }
 
+const formatSize = (size: number) => {
+  if (size < 1024) return `${size} B`;
+  if (size < 1024 * 1024) return `${(size / 1024).toFixed(1)} KB`;
+  return `${(size / 1024 / 1024).toFixed(1)} MB`;
+};
+
+// Expanded tree folders, as a list of node values and as Tree state
+const toExpandedState = (nodes: string[]) =>
+  Object.fromEntries(nodes.map((node) => [node, true]));
+
+const expandedNodes = (state: Record<string, boolean>) =>
+  Object.keys(state).filter((node) => state[node]);
+
+const sameNodes = (a: string[], b: string[]) =>
+  a.length === b.length && a.every((node) => b.includes(node));
+
+// Ways to arrange the collection tree; `levels` are the metadata fields
+// folders are made of, outermost first (see get_grouped_tree_cmd)
+const GROUPINGS: { value: string; label: string; levels?: string[] }[] = [
+  { value: "folders", label: "Folders on Disk" },
+  { value: "tag", label: "Tag → Difficulty", levels: ["tag", "difficulty"] },
+  {
+    value: "field",
+    label: "Field → Chapter → Section",
+    levels: ["field", "chapter", "section"],
+  },
+  {
+    value: "file_type",
+    label: "File Type → Difficulty",
+    levels: ["file_type", "difficulty"],
+  },
+  { value: "kind", label: "Resource Type", levels: ["kind"] },
+];
+
 /**
  * Database Sidebar component.
  * Shows Collections, DB File Tree, or Project Folders (3-way toggle).
@@ -114,12 +156,41 @@ export const DatabaseSidebar = ({
   const createResource = useDatabaseStore((state) => state.createResource);
   const deleteResource = useDatabaseStore((state) => state.deleteResource);
   const createFolder = useDatabaseStore((state) => state.createFolder);
+  const renameResource = useDatabaseStore((state) => state.renameResource);
+  const duplicateResource = useDatabaseStore(
+    (state) => state.duplicateResource,
+  );
   const toggleCollectionLoaded = useDatabaseStore(
     (state) => state.toggleCollectionLoaded,
   );
 
   // Cast to any to bypass strict type check for now
-  const tree = useTree() as any;
+  const tree = useTree({
+    initialExpandedState: toExpandedState(
+      useDatabaseStore.getState().expandedTreeNodes,
+    ),
+  }) as any;
+
+  // Expanded folders go both ways with the store: they are saved with the
+  // session, and a restored session expands them again
+  const expandedTreeNodes = useDatabaseStore(
+    (state) => state.expandedTreeNodes,
+  );
+  const setExpandedTreeNodes = useDatabaseStore(
+    (state) => state.setExpandedTreeNodes,
+  );
+  useEffect(() => {
+    const expanded = expandedNodes(tree.expandedState);
+    if (!sameNodes(expanded, expandedTreeNodes)) {
+      tree.setExpandedState(toExpandedState(expandedTreeNodes));
+    }
+  }, [expandedTreeNodes]);
+  useEffect(() => {
+    const expanded = expandedNodes(tree.expandedState);
+    if (!sameNodes(expanded, useDatabaseStore.getState().expandedTreeNodes)) {
+      setExpandedTreeNodes(expanded);
+    }
+  }, [tree.expandedState, setExpandedTreeNodes]);
 
   // Use shared tree state hook
   const {
@@ -130,9 +201,25 @@ export const DatabaseSidebar = ({
     filterNodes,
   } = useTreeState<TreeNode>();
 
+  const {
+    treeExtensions,
+    showHiddenFiles,
+    showAllFiles,
+    treeSortBy,
+    treeFoldersFirst,
+    treeNaturalSort,
+    treeSortDescending,
+    treeFlattenChains,
+  } = useSettingsStore((s) => s.settings.database);
+
   // Toggle tree expansion when toolbar button is clicked
   const [fileTree, setFileTree] = useState<TreeNode[]>([]);
 
+  // Virtual folders: group by metadata instead of folders on disk
+  const [grouping, setGrouping] = useState("folders");
+  const [groupedTree, setGroupedTree] = useState<TreeNode[]>([]);
+  const groupLevels = GROUPINGS.find((g) => g.value === grouping)?.levels;
+
   // Helper to get all folder paths for expansion
   const getAllFolderPaths = useCallback((nodes: TreeNode[]): string[] => {
     let paths: string[] = [];
@@ -180,6 +267,7 @@ export const DatabaseSidebar = ({
   );
   const [isSearchVisible, setIsSearchVisible] = useState(false);
   const [deleteModalOpen, setDeleteModalOpen] = useState(false);
+  const [recycleBinOpen, setRecycleBinOpen] = useState(false);
   const [itemToDelete, setItemToDelete] = useState<{
     type: "collection" | "file" | "folder";
     name: string;
@@ -259,100 +347,79 @@ export const DatabaseSidebar = ({
     fetchCollections();
   }, [fetchCollections]);
 
-  // --- New Logic to Populate File Tree ---
+  // --- File tree, built, filtered and sorted by the backend ---
   useEffect(() => {
     if (!collections || collections.length === 0) {
       setFileTree([]);
       return;
     }
 
-    const buildTree = () => {
-      // 1. Create root nodes for each collection
-      const roots: TreeNode[] = collections.map((col) => ({
-        id: col.name, // Using name as ID for root (matches logic elsewhere)
-        name: col.name,
-        type: "folder",
-        path: col.path || "",
-        children: [],
-      }));
-
-      // 2. Populate each collection with its resources
-      allLoadedResources.forEach((resource) => {
-        // Find which collection this resource belongs to
-        const root = roots.find((r) => r.name === resource.collection);
-        if (!root) return;
-
-        // Skip if resource has no path
-        if (!resource.path) return;
-
-        // Determine relative path parts (basic implementation)
-        // Ensure paths are normalized
-        const rootPath = normalizePath(root.path);
-        const resourcePath = normalizePath(resource.path);
-
-        // If resource is not inside collection path, we might just list it at root?
-        // Or we try to build hierarchy.
-        // Let's assume standard behavior: resource.path starts with root.path
-        let relativePath = "";
-        if (resourcePath.startsWith(rootPath)) {
-          relativePath = resourcePath.substring(rootPath.length);
-        } else {
-          // Fallback: just use full path or filename if outside logic
-          relativePath = "/" + resourcePath.split("/").pop();
-        }
-
-        // Remove leading slash
-        if (relativePath.startsWith("/")) relativePath = relativePath.slice(1);
-
-        const parts = relativePath.split("/");
-        let currentNode = root;
-
-        parts.forEach((part, index) => {
-          const isFile = index === parts.length - 1;
-          const currentPath =
-            rootPath + "/" + parts.slice(0, index + 1).join("/");
-
-          if (!currentNode.children) currentNode.children = [];
-
-          let child = currentNode.children.find((c) => c.name === part);
-
-          if (!child) {
-            const nodeType = isFile
-              ? resource.kind === "folder"
-                ? "folder"
-                : "file"
-              : "folder";
-            child = {
-              id: currentPath,
-              name: part,
-              type: nodeType,
-              path: currentPath,
+    let cancelled = false;
+    invoke<TreeNode[]>("get_file_tree_cmd", {
+      collections: loadedCollections,
+      filter: {
+        extensions: treeExtensions,
+        showHidden: showHiddenFiles,
+        allFiles: showAllFiles,
+      },
+      sort: {
+        by: treeSortBy,
+        foldersFirst: treeFoldersFirst,
+        natural: treeNaturalSort,
+        descending: treeSortDescending,
+      },
+      flatten: treeFlattenChains,
+    })
+      .catch((err) => {
+        console.error("Failed to build file tree", err);
+        return [] as TreeNode[];
+      })
+      .then((built) => {
+        if (cancelled) return;
+        // Every collection gets a root, loaded (and non-empty) or not
+        const roots = collections.map(
+          (col): TreeNode =>
+            built.find((n) => n.name === col.name) ?? {
+              id: col.name,
+              name: col.name,
+              type: "folder",
+              path: col.path || "",
               children: [],
-            };
-            currentNode.children.push(child);
-          }
-          currentNode = child;
-        });
+            },
+        );
+        roots.sort((a, b) => a.name.localeCompare(b.name));
+        setFileTree(roots);
       });
 
-      // Sort: Folders first, then files, alphabetical
-      const sortNodes = (nodes: TreeNode[]) => {
-        nodes.sort((a, b) => {
-          if (a.type === b.type) return a.name.localeCompare(b.name);
-          return a.type === "folder" ? -1 : 1;
-        });
-        nodes.forEach((node) => {
-          if (node.children) sortNodes(node.children);
-        });
-      };
-
-      sortNodes(roots);
-
-      setFileTree(roots);
+    return () => {
+      cancelled = true;
     };
+  }, [
+    collections,
+    loadedCollections,
+    allLoadedResources,
+    treeExtensions,
+    showHiddenFiles,
+    showAllFiles,
+    treeSortBy,
+    treeFoldersFirst,
+    treeNaturalSort,
+    treeSortDescending,
+    treeFlattenChains,
+  ]);
 
-    buildTree();
-  }, [collections, allLoadedResources, normalizePath]);
+  useEffect(() => {
+    if (!groupLevels || loadedCollections.length === 0) {
+      setGroupedTree([]);
+      return;
+    }
+    invoke<TreeNode[]>("get_grouped_tree_cmd", {
+      collections: loadedCollections,
+      levels: groupLevels,
+    })
+      .then(setGroupedTree)
+      .catch((err) => console.error("Failed to group collections:", err));
+  }, [groupLevels, loadedCollections, allLoadedResources]);
 
   // --- Handlers ---
   const handleImport = useCallback(async () => {
@@ -562,26 +629,18 @@ export const DatabaseSidebar = ({
 
   const mapNode = useCallback(
     (node: TreeNode): TreeNodeData => {
-      const ALLOWED_EXTENSIONS = [
-        "tex",
-        "bib",
-        "pdf",
-        "png",
-        "jpg",
-        "jpeg",
-        "sty",
-        "cls",
-        "dtx",
-        "ins",
-      ];
+      const extensions = treeExtensions.map((e) =>
+        e.replace(/^\./, "").toLowerCase(),
+      );
 
       let children: TreeNodeData[] = [];
       if (node.children) {
         children = node.children
           .filter((child) => {
-            if (child.type === "folder") return true;
+            if (!showHiddenFiles && child.name.startsWith(".")) return false;
+            if (child.type === "folder" || showAllFiles) return true;
             const ext = child.name.split(".").pop()?.toLowerCase();
-            return ALLOWED_EXTENSIONS.includes(ext || "");
+            return extensions.includes(ext || "");
           })
           .map(mapNode);
       }
@@ -602,16 +661,33 @@ export const DatabaseSidebar = ({
       }
 
       return {
-        value: node.path,
+        // A grouped file can show up in several folders; its id is unique
+        value: groupLevels && node.type === "file" ? node.id : node.path,
         label: node.name,
         children: children.length > 0 ? children : undefined,
         nodeProps: {
           "data-type": node.type,
           "data-path": node.path,
+          // Folder rollups (see attach_folder_totals)
+          "data-files": node.metadata?.fileCount,
+          "data-size": node.metadata?.totalSize,
+          "data-failing": node.metadata?.failingCount,
+          "data-changed": node.metadata?.changedCount,
+          // Wrapper folders hidden by flattening, shown as a breadcrumb
+          "data-collapsed": node.metadata?.collapsed?.join(" / "),
+          "data-link-target": node.metadata?.symlink
+            ? (node.metadata.linkTarget ?? "")
+            : undefined,
         },
       };
     },
-    [creatingCollectionItem],
+    [
+      creatingCollectionItem,
+      treeExtensions,
+      showHiddenFiles,
+      showAllFiles,
+      groupLevels,
+    ],
   );
 
   const handleCommitCreation = useCallback(
@@ -670,6 +746,27 @@ export const DatabaseSidebar = ({
     setFocusedPath(node.path);
   }, []);
 
+  // --- Rename / duplicate (files and indexed folders) ---
+  const handleRenameNode = useCallback(
+    (path: string, name: string) => {
+      const resource = allLoadedResources.find((r) => r.path === path);
+      if (!resource) return;
+      const newName = window.prompt("Rename to", name)?.trim();
+      if (newName && newName !== name) {
+        renameResource(resource.id, newName);
+      }
+    },
+    [allLoadedResources, renameResource],
+  );
+
+  const handleDuplicateNode = useCallback(
+    (path: string) => {
+      const resource = allLoadedResources.find((r) => r.path === path);
+      if (resource) duplicateResource(resource.id);
+    },
+    [allLoadedResources, duplicateResource],
+  );
+
   // --- File click handler ---
   const handleFileClick = useCallback(
     (node: TreeNode) => {
@@ -861,6 +958,11 @@ export const DatabaseSidebar = ({
         tooltip: isToggleExpanded ? t("file.collapseAll") : t("file.expandAll"),
         onClick: handleToggleExpand,
       },
+      {
+        icon: faTrashArrowUp,
+        tooltip: t("database.recycleBin.title"),
+        onClick: () => setRecycleBinOpen(true),
+      },
       {
         icon: faSync,
         tooltip: t("common.refresh"),
@@ -891,6 +993,10 @@ export const DatabaseSidebar = ({
     ({ node, expanded, elementProps, level }: any) => {
       const type = node.nodeProps?.["data-type"];
       const indentSize = 1.2;
+      const failing = node.nodeProps?.["data-failing"] ?? 0;
+      const changed = node.nodeProps?.["data-changed"] ?? 0;
+      const linkTarget = node.nodeProps?.["data-link-target"];
+      const collapsed = node.nodeProps?.["data-collapsed"];
 
       if (type === "empty_placeholder") {
         return (
@@ -937,7 +1043,7 @@ export const DatabaseSidebar = ({
 
       // File/Folder Node
       const isFolder = type === "folder";
-      const path = node.value as string;
+      const path = (node.nodeProps?.["data-path"] ?? node.value) as string;
       const isCollectionRoot = level === 1; // Assuming collections are at level 1
 
       const getFileIcon = (name: string) => {
@@ -1047,8 +1153,52 @@ export const DatabaseSidebar = ({
             />
             <Text size="xs" truncate style={{ lineHeight: 1.2, flex: 1 }}>
               {node.label}
+              {collapsed && (
+                <Text component="span" size="xs" c="dimmed">
+                  {` / ${collapsed}`}
+                </Text>
+              )}
             </Text>
 
+            {linkTarget !== undefined && (
+              <Tooltip
+                label={`Symbolic link to ${linkTarget}`}
+                withArrow
+                position="top"
+              >
+                <Box component="span" style={{ display: "flex" }}>
+                  <FontAwesomeIcon
+                    icon={faLink}
+                    style={{ width: 10, height: 10, color: "#868e96" }}
+                  />
+                </Box>
+              </Tooltip>
+            )}
+
+            {/* Rollup of the files below a folder */}
+            {(failing > 0 || changed > 0) && (
+              <Tooltip
+                label={`${node.nodeProps["data-files"]} files · ${formatSize(
+                  node.nodeProps["data-size"] ?? 0,
+                )}`}
+                withArrow
+                position="top"
+              >
+                <Group gap={4} wrap="nowrap">
+                  {failing > 0 && (
+                    <Text size="xs" c="red" fw={600}>
+                      {failing}
+                    </Text>
+                  )}
+                  {changed > 0 && (
+                    <Text size="xs" c="orange" fw={600}>
+                      {changed}
+                    </Text>
+                  )}
+                </Group>
+              </Tooltip>
+            )}
+
             {/* Actions for Collection Root */}
             {isCollectionRoot && (
               <Group gap={1} wrap="nowrap" onClick={(e) => e.stopPropagation()}>
@@ -1192,6 +1342,17 @@ export const DatabaseSidebar = ({
                       >
                         New Folder
                       </Menu.Item>
+                      <Menu.Item
+                        leftSection={
+                          <FontAwesomeIcon icon={faPen} style={{ width: 14 }} />
+                        }
+                        onClick={(e) => {
+                          e.stopPropagation();
+                          handleRenameNode(path, node.label as string);
+                        }}
+                      >
+                        Rename...
+                      </Menu.Item>
                       <Menu.Divider />
                       <Menu.Item
                         color="red"
@@ -1238,6 +1399,31 @@ export const DatabaseSidebar = ({
                       >
                         Export to .dtex
                       </Menu.Item>
+                      <Menu.Item
+                        leftSection={
+                          <FontAwesomeIcon icon={faPen} style={{ width: 14 }} />
+                        }
+                        onClick={(e) => {
+                          e.stopPropagation();
+                          handleRenameNode(path, node.label as string);
+                        }}
+                      >
+                        Rename...
+                      </Menu.Item>
+                      <Menu.Item
+                        leftSection={
+                          <FontAwesomeIcon
+                            icon={faCopy}
+                            style={{ width: 14 }}
+                          />
+                        }
+                        onClick={(e) => {
+                          e.stopPropagation();
+                          handleDuplicateNode(path);
+                        }}
+                      >
+                        Duplicate
+                      </Menu.Item>
                       <Menu.Divider />
                       <Menu.Item
                         color="red"
@@ -1413,13 +1599,15 @@ export const DatabaseSidebar = ({
       handleFileClick,
       handleCommitCreation,
       setCreatingCollectionItem,
+      handleRenameNode,
+      handleDuplicateNode,
     ],
   );
 
   const filteredTreeData = useMemo(() => {
     // 1. Filter roots by loaded collections
-    const visibleRoots = fileTree.filter((node) =>
-      loadedCollections.includes(node.id),
+    const visibleRoots = (groupLevels ? groupedTree : fileTree).filter(
+      (node) => loadedCollections.includes(node.id),
     );
 
     // 2. Filter content by search query
@@ -1427,7 +1615,15 @@ export const DatabaseSidebar = ({
 
     // 3. Map to Mantine data format
     return searchedRoots.map(mapNode);
-  }, [fileTree, loadedCollections, searchQuery, filterNodes, mapNode]);
+  }, [
+    fileTree,
+    groupedTree,
+    groupLevels,
+    loadedCollections,
+    searchQuery,
+    filterNodes,
+    mapNode,
+  ]);
 
   return (
     <Stack p="xs" gap="xs" h="100%" style={{ overflow: "hidden" }}>
@@ -1495,6 +1691,16 @@ export const DatabaseSidebar = ({
             },
           }}
         />
+        {activeView === "collections" && (
+          <Select
+            size="xs"
+            aria-label="Group By"
+            data={GROUPINGS.map(({ value, label }) => ({ value, label }))}
+            value={grouping}
+            onChange={(val) => val && setGrouping(val)}
+            allowDeselect={false}
+          />
+        )}
       </Stack>
 
       {/* Main Content Area */}
@@ -1655,6 +1861,10 @@ export const DatabaseSidebar = ({
           </Group>
         </Stack>
       </Modal>
+      <RecentlyDeleted
+        opened={recycleBinOpen}
+        onClose={() => setRecycleBinOpen(false)}
+      />
     </Stack>
   );
 };
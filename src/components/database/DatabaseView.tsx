@@ -54,6 +54,7 @@ import {
 
 interface DatabaseViewProps {
   onOpenFile?: (path: string) => void;
+  onOpenFileAtLine?: (path: string, line: number) => void;
   onOpenTemplateModal?: () => void;
   canInsert?: boolean;
 }
@@ -66,7 +67,12 @@ interface SortState {
 }
 
 export const DatabaseView = React.memo(
-  ({ onOpenFile, onOpenTemplateModal, canInsert }: DatabaseViewProps) => {
+  ({
+    onOpenFile,
+    onOpenFileAtLine,
+    onOpenTemplateModal,
+    canInsert,
+  }: DatabaseViewProps) => {
     const { t } = useTranslation();
     // Granular selectors - prevents re-renders when unrelated state changes
     const allLoadedResources = useDatabaseStore(
@@ -711,7 +717,10 @@ export const DatabaseView = React.memo(
           }}
         >
           {viewMode === "graph" ? (
-            <VisualGraphView onOpenFile={onOpenFile} />
+            <VisualGraphView
+              onOpenFile={onOpenFile}
+              onOpenFileAtLine={onOpenFileAtLine}
+            />
           ) : (
             <>
               {/* Table Area */}
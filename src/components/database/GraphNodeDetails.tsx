@@ -0,0 +1,138 @@
+import React, { useEffect, useState } from "react";
+import { Badge, Group, Loader, Paper, Stack, Text } from "@mantine/core";
+import { invoke } from "@tauri-apps/api/core";
+import { useTranslation } from "react-i18next";
+
+export interface NodeDetails {
+  id: string;
+  path: string;
+  title: string | null;
+  kind: string;
+  collection: string;
+  wordCount: number | null;
+  size: number | null;
+  modified: number | null;
+  compileStatus: {
+    status: string;
+    errorCount: number;
+    warningCount: number;
+    message: string | null;
+    compiledAt: string;
+  } | null;
+  tags: string[];
+  inDegree: number;
+  outDegree: number;
+}
+
+interface GraphNodeDetailsProps {
+  resourceId: string;
+  /** Refetch when the graph changes, e.g. after a rescan */
+  revision?: unknown;
+}
+
+const formatSize = (size: number) => {
+  if (size < 1024) return `${size} B`;
+  if (size < 1024 * 1024) return `${(size / 1024).toFixed(1)} KB`;
+  return `${(size / 1024 / 1024).toFixed(1)} MB`;
+};
+
+/**
+ * Inspector card for the selected graph node: size, words, last change,
+ * last compile, tags and how many resources it links to and from.
+ */
+export const GraphNodeDetails: React.FC<GraphNodeDetailsProps> = ({
+  resourceId,
+  revision,
+}) => {
+  const { t } = useTranslation();
+  const [details, setDetails] = useState<NodeDetails | null>(null);
+
+  useEffect(() => {
+    let active = true;
+    setDetails(null);
+    invoke<NodeDetails>("get_node_details_cmd", { resourceId })
+      .then((d) => active && setDetails(d))
+      .catch(() => {
+        // Not a resource (e.g. a citation node): nothing to show
+      });
+    return () => {
+      active = false;
+    };
+  }, [resourceId, revision]);
+
+  if (!details) return <Loader size="xs" />;
+
+  const row = (label: string, value: React.ReactNode) => (
+    <Group justify="space-between" gap="xs" wrap="nowrap">
+      <Text size="xs" c="dimmed">
+        {label}
+      </Text>
+      <Text size="xs" truncate>
+        {value}
+      </Text>
+    </Group>
+  );
+  const status = details.compileStatus;
+
+  return (
+    <Paper
+      shadow="sm"
+      p="xs"
+      withBorder
+      style={{ width: 240, backgroundColor: "var(--mantine-color-body)" }}
+    >
+      <Stack gap={4}>
+        <Text size="sm" fw={700} truncate>
+          {details.title || details.path.split(/[\\/]/).pop()}
+        </Text>
+        <Text size="xs" c="dimmed" truncate>
+          {details.collection} · {details.kind}
+        </Text>
+        {details.wordCount !== null &&
+          row(t("database.graph.details.words"), details.wordCount)}
+        {details.size !== null &&
+          row(t("database.graph.details.size"), formatSize(details.size))}
+        {details.modified !== null &&
+          row(
+            t("database.graph.details.modified"),
+            new Date(details.modified * 1000).toLocaleString(),
+          )}
+        {row(
+          t("database.graph.details.links"),
+          t("database.graph.details.linkCounts", {
+            in: details.inDegree,
+            out: details.outDegree,
+          }),
+        )}
+        {row(
+          t("database.graph.details.compile"),
+          status ? (
+            <Text
+              span
+              size="xs"
+              c={status.status === "ok" ? "green" : "red"}
+              title={status.message ?? undefined}
+            >
+              {status.status === "ok"
+                ? t("database.graph.details.compileOk")
+                : t("database.graph.details.compileErrors", {
+                    count: status.errorCount,
+                  })}
+            </Text>
+          ) : (
+            t("database.graph.details.neverCompiled")
+          ),
+        )}
+        {details.tags.length > 0 && (
+          <Group gap={4}>
+            {details.tags.map((tag) => (
+              <Badge key={tag} size="xs" variant="light">
+                {tag}
+              </Badge>
+            ))}
+          </Group>
+        )}
+      </Stack>
+    </Paper>
+  );
+};
@@ -0,0 +1,187 @@
+import React, { useEffect, useState } from "react";
+import {
+  Badge,
+  Group,
+  Loader,
+  Modal,
+  ScrollArea,
+  Stack,
+  Tabs,
+  Text,
+  UnstyledButton,
+} from "@mantine/core";
+import { invoke } from "@tauri-apps/api/core";
+import { useTranslation } from "react-i18next";
+import { useDatabaseStore } from "../../stores/databaseStore";
+import { getFileIcon } from "../shared/tree";
+
+export interface OrphanResource {
+  id: string;
+  path: string;
+  kind: string;
+  collection: string;
+}
+
+export interface BrokenLink {
+  sourceId: string;
+  sourcePath: string;
+  collection: string;
+  relation: string;
+  targetId: string;
+  targetPath: string | null;
+  line: number | null;
+}
+
+interface GraphReportsProps {
+  opened: boolean;
+  onClose: () => void;
+  onOpenFile?: (path: string) => void;
+  onOpenFileAtLine?: (path: string, line: number) => void;
+}
+
+const fileName = (path: string) => path.split(/[\\/]/).pop() || path;
+
+/**
+ * Health reports for the loaded collections: files nothing links to and
+ * that link to nothing, and references to files that no longer exist.
+ */
+export const GraphReports: React.FC<GraphReportsProps> = ({
+  opened,
+  onClose,
+  onOpenFile,
+  onOpenFileAtLine,
+}) => {
+  const { t } = useTranslation();
+  const loadedCollections = useDatabaseStore(
+    (state) => state.loadedCollections,
+  );
+  const [orphans, setOrphans] = useState<OrphanResource[] | null>(null);
+  const [broken, setBroken] = useState<BrokenLink[] | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    if (!opened) return;
+    setOrphans(null);
+    setBroken(null);
+    setError(null);
+    const args = { collections: loadedCollections };
+    invoke<OrphanResource[]>("find_orphan_resources_cmd", args)
+      .then(setOrphans)
+      .catch((err) => setError(String(err)));
+    invoke<BrokenLink[]>("find_broken_links_cmd", args)
+      .then(setBroken)
+      .catch((err) => setError(String(err)));
+  }, [opened, loadedCollections]);
+
+  const open = (path: string, line?: number | null) => {
+    if (line && onOpenFileAtLine) {
+      onOpenFileAtLine(path, line);
+    } else if (onOpenFile) {
+      onOpenFile(path);
+    }
+    onClose();
+  };
+
+  const list = <T,>(
+    items: T[] | null,
+    render: (item: T) => React.ReactNode,
+  ) =>
+    items === null ? (
+      <Loader size="sm" />
+    ) : items.length === 0 ? (
+      <Text size="sm" c="dimmed">
+        {t("database.graph.reports.none")}
+      </Text>
+    ) : (
+      <ScrollArea.Autosize mah={400} type="auto">
+        <Stack gap={4}>{items.map(render)}</Stack>
+      </ScrollArea.Autosize>
+    );
+
+  return (
+    <Modal
+      opened={opened}
+      onClose={onClose}
+      title={t("database.graph.reports.title")}
+      centered
+      size="lg"
+    >
+      {error && (
+        <Text size="xs" c="red" mb="xs">
+          {error}
+        </Text>
+      )}
+      <Tabs defaultValue="broken">
+        <Tabs.List mb="sm">
+          <Tabs.Tab
+            value="broken"
+            rightSection={
+              broken && (
+                <Badge size="xs" color="red" variant="light">
+                  {broken.length}
+                </Badge>
+              )
+            }
+          >
+            {t("database.graph.reports.brokenLinks")}
+          </Tabs.Tab>
+          <Tabs.Tab
+            value="orphans"
+            rightSection={
+              orphans && (
+                <Badge size="xs" variant="light">
+                  {orphans.length}
+                </Badge>
+              )
+            }
+          >
+            {t("database.graph.reports.orphans")}
+          </Tabs.Tab>
+        </Tabs.List>
+
+        <Tabs.Panel value="broken">
+          {list(broken, (link) => (
+            <UnstyledButton
+              key={`${link.sourceId}:${link.targetId}:${link.relation}`}
+              onClick={() => open(link.sourcePath, link.line)}
+            >
+              <Group gap="xs" wrap="nowrap">
+                {getFileIcon(fileName(link.sourcePath), "file")}
+                <Stack gap={0} style={{ flex: 1, minWidth: 0 }}>
+                  <Text size="sm" truncate>
+                    {fileName(link.sourcePath)}
+                    {link.line !== null && `:${link.line}`}
+                  </Text>
+                  <Text size="xs" c="dimmed" truncate>
+                    {link.relation} →{" "}
+                    {link.targetPath
+                      ? fileName(link.targetPath)
+                      : t("database.graph.reports.deletedResource")}
+                  </Text>
+                </Stack>
+              </Group>
+            </UnstyledButton>
+          ))}
+        </Tabs.Panel>
+
+        <Tabs.Panel value="orphans">
+          {list(orphans, (item) => (
+            <UnstyledButton key={item.id} onClick={() => open(item.path)}>
+              <Group gap="xs" wrap="nowrap">
+                {getFileIcon(fileName(item.path), "file")}
+                <Stack gap={0} style={{ flex: 1, minWidth: 0 }}>
+                  <Text size="sm" truncate>
+                    {fileName(item.path)}
+                  </Text>
+                  <Text size="xs" c="dimmed" truncate>
+                    {item.collection} · {item.path}
+                  </Text>
+                </Stack>
+              </Group>
+            </UnstyledButton>
+          ))}
+        </Tabs.Panel>
+      </Tabs>
+    </Modal>
+  );
+};
@@ -0,0 +1,159 @@
+import React, { useCallback, useEffect, useState } from "react";
+import {
+  Button,
+  Group,
+  Loader,
+  Modal,
+  ScrollArea,
+  Stack,
+  Text,
+} from "@mantine/core";
+import { invoke } from "@tauri-apps/api/core";
+import { useTranslation } from "react-i18next";
+import { useDatabaseStore } from "../../stores/databaseStore";
+import { getFileIcon } from "../shared/tree";
+
+export interface DeletedResource {
+  id: string;
+  resourceId: string;
+  collection: string;
+  kind: string;
+  originalPath: string;
+  hasFile: boolean;
+  resourceCount: number;
+  deletedAt: string;
+}
+
+interface RecentlyDeletedProps {
+  opened: boolean;
+  onClose: () => void;
+}
+
+const fileName = (path: string) => path.split(/[\\/]/).pop() || path;
+
+/**
+ * The recycle bin: resources deleted from the sidebar, which can be put
+ * back (file and metadata) or deleted for good.
+ */
+export const RecentlyDeleted: React.FC<RecentlyDeletedProps> = ({
+  opened,
+  onClose,
+}) => {
+  const { t } = useTranslation();
+  const refresh = useDatabaseStore(
+    (state) => state.fetchResourcesForLoadedCollections,
+  );
+  const [items, setItems] = useState<DeletedResource[] | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  const load = useCallback(() => {
+    invoke<DeletedResource[]>("list_deleted_resources_cmd", {})
+      .then(setItems)
+      .catch((err) => setError(String(err)));
+  }, []);
+
+  useEffect(() => {
+    if (!opened) return;
+    setItems(null);
+    setError(null);
+    load();
+  }, [opened, load]);
+
+  const run = async (command: string, args: Record<string, unknown>) => {
+    setError(null);
+    try {
+      await invoke(command, args);
+      await refresh();
+    } catch (err) {
+      setError(String(err));
+    }
+    load();
+  };
+
+  const handleEmpty = () => {
+    if (window.confirm(t("database.recycleBin.emptyConfirm"))) {
+      run("purge_deleted_resources_cmd", {});
+    }
+  };
+
+  return (
+    <Modal
+      opened={opened}
+      onClose={onClose}
+      title={t("database.recycleBin.title")}
+      centered
+      size="lg"
+    >
+      <Stack gap="sm">
+        {error && (
+          <Text size="xs" c="red">
+            {error}
+          </Text>
+        )}
+        {items === null ? (
+          <Loader size="sm" />
+        ) : items.length === 0 ? (
+          <Text size="sm" c="dimmed">
+            {t("database.recycleBin.empty")}
+          </Text>
+        ) : (
+          <ScrollArea.Autosize mah={400} type="auto">
+            <Stack gap={6}>
+              {items.map((item) => (
+                <Group key={item.id} gap="xs" wrap="nowrap">
+                  {getFileIcon(
+                    fileName(item.originalPath),
+                    item.kind === "folder" ? "folder" : "file",
+                  )}
+                  <Stack gap={0} style={{ flex: 1, minWidth: 0 }}>
+                    <Text size="sm" truncate>
+                      {fileName(item.originalPath)}
+                      {item.resourceCount > 1 &&
+                        ` (${t("database.recycleBin.withContents", {
+                          count: item.resourceCount - 1,
+                        })})`}
+                    </Text>
+                    <Text size="xs" c="dimmed" truncate>
+                      {item.collection} · {item.deletedAt}
+                      {!item.hasFile &&
+                        ` · ${t("database.recycleBin.databaseOnly")}`}
+                    </Text>
+                  </Stack>
+                  <Button
+                    size="compact-xs"
+                    variant="light"
+                    onClick={() =>
+                      run("restore_deleted_resource_cmd", { id: item.id })
+                    }
+                  >
+                    {t("database.recycleBin.restore")}
+                  </Button>
+                  <Button
+                    size="compact-xs"
+                    variant="subtle"
+                    color="red"
+                    onClick={() =>
+                      run("purge_deleted_resources_cmd", { id: item.id })
+                    }
+                  >
+                    {t("database.recycleBin.deletePermanently")}
+                  </Button>
+                </Group>
+              ))}
+            </Stack>
+          </ScrollArea.Autosize>
+        )}
+        <Group justify="flex-end">
+          <Button
+            color="red"
+            variant="default"
+            disabled={!items?.length}
+            onClick={handleEmpty}
+          >
+            {t("database.recycleBin.emptyBin")}
+          </Button>
+        </Group>
+      </Stack>
+    </Modal>
+  );
+};
@@ -12,6 +12,7 @@ import {
   Checkbox,
   Slider,
   Select,
+  MultiSelect,
   Divider,
   ColorSwatch,
   ActionIcon,
@@ -30,14 +31,82 @@ import {
   IconFolder,
   IconEye,
   IconEyeOff,
+  IconAlertTriangle,
 } from "@tabler/icons-react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { GraphNodeDetails } from "./GraphNodeDetails";
+import { GraphReports } from "./GraphReports";
+
+interface GraphDelta {
+  version: number;
+  full: boolean;
+  nodes: any[];
+  removedNodes: string[];
+  links: any[];
+  removedLinks: any[];
+}
+
+// Link ends are ids until the force graph swaps in the node objects
+const endpointId = (end: any): string =>
+  typeof end === "object" ? end.id : end;
+
+// The backend merges all relations between two nodes into one link
+const linkKey = (link: any) =>
+  `${endpointId(link.source)}->${endpointId(link.target)}`;
+
+// Relation types that can be hidden from the graph
+const RELATIONS = [
+  "input",
+  "include",
+  "package",
+  "class",
+  "graphics",
+  "bibliography",
+  "cite",
+];
+
+/**
+ * Apply backend changes to the rendered graph. Node objects that stay are
+ * updated in place so they keep their positions.
+ */
+const applyGraphDelta = (
+  graph: { nodes: any[]; links: any[] },
+  delta: GraphDelta,
+) => {
+  const removed = new Set(delta.removedNodes);
+  const changed = new Map(delta.nodes.map((n) => [n.id, n]));
+  const nodes = graph.nodes
+    .filter((n) => !removed.has(n.id))
+    .map((n) => {
+      const update = changed.get(n.id);
+      if (!update) return n;
+      changed.delete(n.id);
+      return Object.assign(n, update);
+    });
+  nodes.push(...changed.values());
+
+  const removedLinks = new Set(delta.removedLinks.map(linkKey));
+  const links = graph.links
+    .filter(
+      (l) =>
+        !removedLinks.has(linkKey(l)) &&
+        !removed.has(endpointId(l.source)) &&
+        !removed.has(endpointId(l.target)),
+    )
+    .concat(delta.links);
+  return { nodes, links };
+};
 
 interface VisualGraphViewProps {
   onOpenFile?: (path: string) => void;
+  onOpenFileAtLine?: (path: string, line: number) => void;
 }
 
-export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
+export const VisualGraphView = ({
+  onOpenFile,
+  onOpenFileAtLine,
+}: VisualGraphViewProps) => {
   const { t } = useTranslation();
   const theme = useMantineTheme();
   // Granular selectors - prevents re-renders when unrelated state changes
@@ -67,9 +136,20 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
     showClasses: false,
     showDtx: false,
     showIns: false,
+    showCitations: false,
   });
+  // Metadata shown as nodes: built-in fields (tag, difficulty, ...) and
+  // custom fields, the latter prefixed with "custom:"
+  const [metadataNodes, setMetadataNodes] = React.useState<string[]>([]);
+  const [hiddenRelations, setHiddenRelations] = React.useState<string[]>([]);
+  const [customFieldNames, setCustomFieldNames] = React.useState<string[]>(
+    [],
+  );
 
   // Physics State
+  // Positions computed by the backend instead of the force simulation,
+  // for graphs too large to settle smoothly in the browser
+  const [backendLayout, setBackendLayout] = React.useState(false);
   const [physics, setPhysics] = React.useState({
     linkDistance: 30,
     chargeStrength: -100,
@@ -79,8 +159,17 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
 
   // Search & Navigation State
   const [focusMode, setFocusMode] = React.useState(false);
+  // Hops around the active resource to load; 0 loads the whole graph
+  const [neighborhoodDepth, setNeighborhoodDepth] = React.useState(0);
   const [searchValue, setSearchValue] = React.useState("");
   const [showControls, setShowControls] = React.useState(true);
+  const [reportsOpen, setReportsOpen] = React.useState(false);
+  // Nodes and "source->target" links of an impact or path query; the rest
+  // of the graph is dimmed while it is set
+  const [highlight, setHighlight] = React.useState<{
+    nodes: Set<string>;
+    links: Set<string>;
+  } | null>(null);
 
   useEffect(() => {
     fetchGraphLinks();
@@ -128,32 +217,116 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
     (state) => state.loadedCollections,
   );
 
+  // Time travel: commits of the repository holding the loaded collections,
+  // oldest first, and the one shown (null shows the live graph)
+  const collections = useDatabaseStore((state) => state.collections);
+  const [history, setHistory] = React.useState<{
+    repoPath: string;
+    commits: { id: string; short_id: string; message: string }[];
+  } | null>(null);
+  const [historyIndex, setHistoryIndex] = React.useState<number | null>(null);
+  const historyCommit =
+    history && historyIndex !== null ? history.commits[historyIndex] : null;
+
+  useEffect(() => {
+    let active = true;
+    setHistory(null);
+    setHistoryIndex(null);
+    const root = collections.find(
+      (c) => loadedCollections.includes(c.name) && c.path,
+    )?.path;
+    if (!root) return;
+
+    (async () => {
+      try {
+        const repo = await invoke<{ path: string } | null>(
+          "git_detect_repo_cmd",
+          { path: root },
+        );
+        if (!repo) return;
+        const log = await invoke<
+          { id: string; short_id: string; message: string }[]
+        >("git_log_cmd", { repoPath: repo.path, limit: 200 });
+        if (active && log.length > 0) {
+          setHistory({ repoPath: repo.path, commits: log.reverse() });
+        }
+      } catch (err) {
+        // Not a repository, or no commits yet
+      }
+    })();
+
+    return () => {
+      active = false;
+    };
+  }, [collections, loadedCollections]);
+
+  // Version of the full graph on screen, to ask the backend for changes
+  const graphVersion = useRef<number | null>(null);
+  const neighborhoodCenter = neighborhoodDepth > 0 ? activeResourceId : null;
+
+  const graphFilters = React.useMemo(
+    () => ({
+      showPackages: filters.showPackages,
+      showBibliographies: filters.showBibliographies,
+      showImages: filters.showImages,
+      showClasses: filters.showClasses,
+      showDtx: filters.showDtx,
+      showIns: filters.showIns,
+      showCitations: filters.showCitations,
+      metadataFields: metadataNodes.filter((f) => !f.startsWith("custom:")),
+      customFields: metadataNodes
+        .filter((f) => f.startsWith("custom:"))
+        .map((f) => f.slice("custom:".length)),
+      layout: backendLayout,
+      hiddenRelations,
+    }),
+    [filters, metadataNodes, backendLayout, hiddenRelations],
+  );
+
   useEffect(() => {
     let active = true;
 
     const fetchGraphData = async () => {
+      graphVersion.current = null;
       if (loadedCollections.length === 0) {
         setGraphData({ nodes: [], links: [] });
         return;
       }
 
       try {
-        const data = await invoke<{ nodes: any[]; links: any[] }>(
-          "get_graph_data_cmd",
-          {
-            collections: loadedCollections,
-            filters: {
-              showPackages: filters.showPackages,
-              showBibliographies: filters.showBibliographies,
-              showImages: filters.showImages,
-              showClasses: filters.showClasses,
-              showDtx: filters.showDtx,
-              showIns: filters.showIns,
+        if (history && historyCommit) {
+          const data = await invoke<{ nodes: any[]; links: any[] }>(
+            "get_graph_at_commit_cmd",
+            {
+              repoPath: history.repoPath,
+              commitId: historyCommit.id,
+              filters: graphFilters,
             },
-          },
-        );
+          );
+          if (active) setGraphData(data);
+          return;
+        }
+        if (neighborhoodCenter) {
+          const data = await invoke<{ nodes: any[]; links: any[] }>(
+            "get_node_neighborhood_cmd",
+            {
+              resourceId: neighborhoodCenter,
+              depth: neighborhoodDepth,
+              filters: graphFilters,
+            },
+          );
+          if (active) setGraphData(data);
+          return;
+        }
+
+        const delta = await invoke<GraphDelta>("get_graph_delta_cmd", {
+          collections: loadedCollections,
+          filters: graphFilters,
+          since: null,
+        });
         if (active) {
-          setGraphData(data);
+          graphVersion.current = delta.version;
+          setGraphData({ nodes: delta.nodes, links: delta.links });
         }
       } catch (err) {
         // Failed to fetch graph data
@@ -165,7 +338,69 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
     return () => {
       active = false;
     };
-  }, [loadedCollections, filters]);
+  }, [
+    loadedCollections,
+    graphFilters,
+    neighborhoodDepth,
+    neighborhoodCenter,
+    history,
+    historyCommit,
+  ]);
+
+  // Patch the full graph when resources change, keeping the layout of the
+  // nodes that stay
+  useEffect(() => {
+    if (
+      neighborhoodCenter ||
+      historyCommit ||
+      loadedCollections.length === 0
+    ) {
+      return;
+    }
+
+    const unlisten = listen("resources-changed", async () => {
+      try {
+        const delta = await invoke<GraphDelta>("get_graph_delta_cmd", {
+          collections: loadedCollections,
+          filters: graphFilters,
+          since: graphVersion.current,
+        });
+        graphVersion.current = delta.version;
+        setGraphData((graph) =>
+          delta.full
+            ? { nodes: delta.nodes, links: delta.links }
+            : applyGraphDelta(graph, delta),
+        );
+      } catch (err) {
+        // Keep the graph on screen; the next change retries
+      }
+    });
+
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, [loadedCollections, graphFilters, neighborhoodCenter, historyCommit]);
+
+  // Custom fields of the loaded collections, offered as metadata nodes
+  useEffect(() => {
+    let active = true;
+    Promise.all(
+      loadedCollections.map((collection) =>
+        invoke<{ name: string }[]>("get_custom_fields_cmd", { collection }),
+      ),
+    )
+      .then((lists) => {
+        if (!active) return;
+        const names = new Set(lists.flat().map((f) => f.name));
+        setCustomFieldNames(Array.from(names).sort());
+      })
+      .catch(() => {
+        // Custom fields are optional here
+      });
+    return () => {
+      active = false;
+    };
+  }, [loadedCollections]);
 
   // Helper: Get connected nodes for focus mode
   const getNeighbors = useCallback((nodeId: string, links: any[]) => {
@@ -182,6 +417,46 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
     return neighbors;
   }, []);
 
+  const endpoints = (link: any): [string, string] => [
+    endpointId(link.source),
+    endpointId(link.target),
+  ];
+
+  const showImpact = async (nodeId: string) => {
+    try {
+      const impacted = await invoke<{ id: string }[]>(
+        "get_impacted_documents_cmd",
+        { resourceId: nodeId },
+      );
+      const nodes = new Set([nodeId, ...impacted.map((n) => n.id)]);
+      const links = new Set<string>();
+      graphData.links.forEach((link) => {
+        const [s, t] = endpoints(link);
+        if (nodes.has(s) && nodes.has(t)) links.add(`${s}->${t}`);
+      });
+      setHighlight({ nodes, links });
+    } catch (err) {
+      console.error("Failed to load impacted documents:", err);
+    }
+  };
+
+  const showPath = async (sourceId: string, targetId: string) => {
+    try {
+      const path = await invoke<{
+        nodes: { id: string }[];
+        links: { source: string; target: string }[];
+      } | null>("get_dependency_path_cmd", { sourceId, targetId });
+      setHighlight({
+        nodes: new Set(path ? path.nodes.map((n) => n.id) : [sourceId]),
+        links: new Set(
+          (path?.links ?? []).map((l) => `${l.source}->${l.target}`),
+        ),
+      });
+    } catch (err) {
+      console.error("Failed to find dependency path:", err);
+    }
+  };
+
   const handleSearch = (val: string) => {
     setSearchValue(val);
     const node = graphData.nodes.find((n) => n.name === val || n.path === val);
@@ -226,7 +501,9 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
 
   const handleNodeClick = useCallback(
     (node: any) => {
-      selectResource(node.id);
+      // Citation and metadata nodes are not resources
+      const isResource = !/^(bib|meta):/.test(node.id);
+      if (isResource) selectResource(node.id);
       if (onOpenFile && node.path) {
         onOpenFile(node.path);
       }
@@ -265,6 +542,12 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
         return theme.colors.cyan[7];
       case "ins":
         return theme.colors.grape[7];
+      case "citation":
+        return theme.colors.lime[7];
+      case "tag":
+        return theme.colors.red[7];
+      case "metadata":
+        return theme.colors.gray[6];
       default:
         return theme.colors.blue[7]; // Standard document
     }
@@ -285,6 +568,15 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
     { label: t("database.graph.legend.image"), color: theme.colors.pink[7] },
     { label: t("database.graph.legend.dtx"), color: theme.colors.cyan[7] },
     { label: t("database.graph.legend.ins"), color: theme.colors.grape[7] },
+    {
+      label: t("database.graph.legend.citation"),
+      color: theme.colors.lime[7],
+    },
+    { label: t("database.graph.legend.tag"), color: theme.colors.red[7] },
+    {
+      label: t("database.graph.legend.metadata"),
+      color: theme.colors.gray[6],
+    },
   ];
 
   return (
@@ -368,6 +660,18 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
                 <IconTarget size={14} />
               </ActionIcon>
             </Tooltip>
+            <Tooltip
+              label={t("database.graph.reports.title")}
+              position="right"
+            >
+              <ActionIcon
+                variant="subtle"
+                color="gray"
+                onClick={() => setReportsOpen(true)}
+              >
+                <IconAlertTriangle size={14} />
+              </ActionIcon>
+            </Tooltip>
             <Divider my={2} />
             <Tooltip
               label={
@@ -391,12 +695,33 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
             </Tooltip>
           </Group>
 
+          {highlight && (
+            <Badge
+              size="xs"
+              variant="light"
+              color="orange"
+              style={{ cursor: "pointer" }}
+              onClick={() => setHighlight(null)}
+            >
+              {t("database.graph.highlighted", {
+                count: highlight.nodes.size,
+              })}{" "}
+              ✕
+            </Badge>
+          )}
           <Badge size="xs" variant="light">
             {graphData.nodes.length} nodes, {graphData.links.length} links
           </Badge>
         </Group>
       </Paper>
 
+      <GraphReports
+        opened={reportsOpen}
+        onClose={() => setReportsOpen(false)}
+        onOpenFile={onOpenFile}
+        onOpenFileAtLine={onOpenFileAtLine}
+      />
+
       {/* Controls Overlay */}
       {showControls && (
         <Paper
@@ -484,6 +809,37 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
                 })
               }
             />
+            <Checkbox
+              label={t("database.graph.citations")}
+              size="xs"
+              checked={filters.showCitations}
+              onChange={(event) =>
+                setFilters({
+                  ...filters,
+                  showCitations: event.currentTarget.checked,
+                })
+              }
+            />
+            <Text size="xs">{t("database.graph.relationsShown")}</Text>
+            <Checkbox.Group
+              value={RELATIONS.filter((r) => !hiddenRelations.includes(r))}
+              onChange={(shown) =>
+                setHiddenRelations(
+                  RELATIONS.filter((r) => !shown.includes(r)),
+                )
+              }
+            >
+              <Group gap={6}>
+                {RELATIONS.map((relation) => (
+                  <Checkbox
+                    key={relation}
+                    value={relation}
+                    size="xs"
+                    label={t(`database.graph.relations.${relation}`)}
+                  />
+                ))}
+              </Group>
+            </Checkbox.Group>
 
             <Divider my="xs" />
 
@@ -516,17 +872,101 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
                 onChange={(event) => setFocusMode(event.currentTarget.checked)}
               />
             </Group>
+            <Text size="xs">{t("database.graph.metadataNodes")}</Text>
+            <MultiSelect
+              size="xs"
+              placeholder={t("database.graph.metadataNone")}
+              data={[
+                { value: "tag", label: t("database.graph.metadata.tag") },
+                {
+                  value: "difficulty",
+                  label: t("database.graph.metadata.difficulty"),
+                },
+                { value: "field", label: t("database.graph.metadata.field") },
+                {
+                  value: "chapter",
+                  label: t("database.graph.metadata.chapter"),
+                },
+                {
+                  value: "section",
+                  label: t("database.graph.metadata.section"),
+                },
+                {
+                  value: "file_type",
+                  label: t("database.graph.metadata.fileType"),
+                },
+                ...customFieldNames.map((name) => ({
+                  value: `custom:${name}`,
+                  label: name,
+                })),
+              ]}
+              value={metadataNodes}
+              onChange={setMetadataNodes}
+              clearable
+            />
+            <Text size="xs">{t("database.graph.neighborhood")}</Text>
+            <Select
+              size="xs"
+              data={[
+                { value: "0", label: t("database.graph.neighborhoodOff") },
+                { value: "1", label: t("database.graph.hops", { count: 1 }) },
+                { value: "2", label: t("database.graph.hops", { count: 2 }) },
+                { value: "3", label: t("database.graph.hops", { count: 3 }) },
+              ]}
+              value={String(neighborhoodDepth)}
+              onChange={(val) => setNeighborhoodDepth(Number(val) || 0)}
+              allowDeselect={false}
+            />
+            {history && (
+              <>
+                <Group justify="space-between" wrap="nowrap">
+                  <Text size="xs">{t("database.graph.history")}</Text>
+                  <Text size="xs" c="dimmed" truncate>
+                    {historyCommit
+                      ? historyCommit.short_id
+                      : t("database.graph.historyLive")}
+                  </Text>
+                </Group>
+                <Slider
+                  key={history.repoPath}
+                  size="xs"
+                  min={0}
+                  max={history.commits.length}
+                  defaultValue={history.commits.length}
+                  label={(i) =>
+                    i < history.commits.length
+                      ? history.commits[i].message.split("\n")[0]
+                      : t("database.graph.historyLive")
+                  }
+                  onChangeEnd={(i) =>
+                    setHistoryIndex(i < history.commits.length ? i : null)
+                  }
+                />
+              </>
+            )}
             <Divider my="xs" />
 
             <Text size="xs" fw={700} c="dimmed">
               {t("database.graph.physics")}
             </Text>
 
+            <Group justify="space-between">
+              <Text size="xs">{t("database.graph.backendLayout")}</Text>
+              <Switch
+                size="xs"
+                checked={backendLayout}
+                onChange={(event) =>
+                  setBackendLayout(event.currentTarget.checked)
+                }
+              />
+            </Group>
+
             <Text size="xs">{t("database.graph.linkDistance")}</Text>
             <Slider
               size="xs"
               min={0}
               max={200}
+              disabled={backendLayout}
               value={physics.linkDistance}
               onChange={(val) => setPhysics({ ...physics, linkDistance: val })}
             />
@@ -536,6 +976,7 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
               size="xs"
               min={-500}
               max={0}
+              disabled={backendLayout}
               value={physics.chargeStrength}
               onChange={(val) =>
                 setPhysics({ ...physics, chargeStrength: val })
@@ -545,6 +986,25 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
         </Paper>
       )}
 
+      {/* Selected Node Details */}
+      {showControls &&
+        activeResourceId &&
+        graphData.nodes.some((n) => n.id === activeResourceId) && (
+          <div
+            style={{
+              position: "absolute",
+              bottom: 10,
+              left: 10,
+              zIndex: 100,
+            }}
+          >
+            <GraphNodeDetails
+              resourceId={activeResourceId}
+              revision={graphData}
+            />
+          </div>
+        )}
+
       {/* Legend Overlay */}
       {showControls && (
         <Paper
@@ -611,17 +1071,20 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
           width={dimensions.width}
           height={dimensions.height}
           graphData={graphData}
+          cooldownTicks={backendLayout ? 0 : undefined}
           dagMode={
-            dagMode as
-              | "td"
-              | "bu"
-              | "lr"
-              | "rl"
-              | "zout"
-              | "zin"
-              | "radialout"
-              | "radialin"
-              | undefined
+            backendLayout
+              ? undefined
+              : (dagMode as
+                  | "td"
+                  | "bu"
+                  | "lr"
+                  | "rl"
+                  | "zout"
+                  | "zin"
+                  | "radialout"
+                  | "radialin"
+                  | undefined)
           }
           nodeCanvasObject={(
             node: any,
@@ -642,7 +1105,9 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
 
             // Focus Mode Logic
             let opacity = 1.0;
-            if (focusMode && activeResourceId) {
+            if (highlight) {
+              if (!highlight.nodes.has(node.id)) opacity = 0.1;
+            } else if (focusMode && activeResourceId) {
               const neighbors = getNeighbors(activeResourceId, graphData.links);
               if (!neighbors.has(node.id)) {
                 opacity = 0.1;
@@ -744,6 +1209,12 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
             }\nConnections: ${connections}`;
           }}
           linkColor={(link: any) => {
+            if (highlight) {
+              const [s, t] = endpoints(link);
+              return highlight.links.has(`${s}->${t}`)
+                ? getAccentColor()
+                : "rgba(0,0,0,0.02)";
+            }
             if (focusMode && activeResourceId) {
               const s =
                 typeof link.source === "object" ? link.source.id : link.source;
@@ -756,10 +1227,20 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
             }
             return theme.colors.gray[4];
           }}
+          linkWidth={(link: any) =>
+            Math.min(1 + ((link.weight ?? 1) - 1) * 0.75, 5)
+          }
+          linkLabel={(link: any) =>
+            link.weight > 1
+              ? `${link.relations.join(", ")} (×${link.weight})`
+              : link.type
+          }
           linkLineDash={(link: any) => {
             if (link.type === "package") return [5, 5];
             if (link.type === "bibliography") return [2, 2];
-            if (link.type === "image") return [4, 2];
+            if (link.type === "graphics") return [4, 2];
+            if (link.type === "cite") return [1, 3];
+            if (link.type === "tag" || link.type === "metadata") return [1, 2];
             return null; // Solid
           }}
           backgroundColor="var(--mantine-color-body)"
@@ -785,6 +1266,7 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
             <Menu.Label>{contextMenu.node.name}</Menu.Label>
             <Menu.Item
               leftSection={<IconFileText size={14} />}
+              disabled={!contextMenu.node.path}
               onClick={() => {
                 if (onOpenFile) onOpenFile(contextMenu.node.path);
                 setContextMenu(null);
@@ -804,6 +1286,7 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
             </Menu.Item>
             <Menu.Item
               leftSection={<IconFolder size={14} />}
+              disabled={!contextMenu.node.path}
               onClick={() => {
                 invoke("reveal_path_cmd", { path: contextMenu.node.path });
                 setContextMenu(null);
@@ -811,6 +1294,28 @@ export const VisualGraphView = ({ onOpenFile }: VisualGraphViewProps) => {
             >
               Reveal in Folder
             </Menu.Item>
+            <Menu.Divider />
+            <Menu.Item
+              onClick={() => {
+                showImpact(contextMenu.node.id);
+                setContextMenu(null);
+              }}
+            >
+              {t("database.graph.showImpact")}
+            </Menu.Item>
+            <Menu.Item
+              disabled={
+                !activeResourceId || activeResourceId === contextMenu.node.id
+              }
+              onClick={() => {
+                if (activeResourceId) {
+                  showPath(activeResourceId, contextMenu.node.id);
+                }
+                setContextMenu(null);
+              }}
+            >
+              {t("database.graph.showPath")}
+            </Menu.Item>
           </Menu.Dropdown>
         </Menu>
       )}
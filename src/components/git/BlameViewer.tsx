@@ -1,6 +1,17 @@
-import React, { useEffect, useState } from "react";
-import { Box, Group, Text, ScrollArea, Tooltip, Loader } from "@mantine/core";
+import React, { useEffect, useRef, useState } from "react";
+import {
+  ActionIcon,
+  Box,
+  Button,
+  Group,
+  Text,
+  ScrollArea,
+  Tooltip,
+  Loader,
+} from "@mantine/core";
 import { invoke } from "@tauri-apps/api/core";
+import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
+import { faArrowLeft, faHistory } from "@fortawesome/free-solid-svg-icons";
 
 export interface BlameInfo {
   line_number: number;
@@ -9,8 +20,26 @@ export interface BlameInfo {
   author: string;
   timestamp: number;
   line_content: string;
+  // Line number and (if renamed since) path in the blamed commit
+  orig_line_number: number;
+  orig_path: string | null;
 }
 
+interface BlameAtParent {
+  commit_id: string;
+  file_path: string;
+  lines: BlameInfo[];
+}
+
+// An older revision being blamed after drilling down
+interface BlameRevision {
+  commitId: string;
+  filePath: string;
+}
+
+// Lines blamed per request; more are loaded while scrolling
+const CHUNK_SIZE = 200;
+
 interface BlameViewerProps {
   repoPath: string;
   filePath: string;
@@ -23,17 +52,39 @@ export const BlameViewer: React.FC<BlameViewerProps> = ({
   const [blameData, setBlameData] = useState<BlameInfo[]>([]);
   const [loading, setLoading] = useState(true);
   const [error, setError] = useState<string | null>(null);
+  // Whether the end of the file has been blamed
+  const [complete, setComplete] = useState(false);
+  const loadingMore = useRef(false);
+  // Revision being blamed (null for HEAD) and the ones drilled down from
+  const [revision, setRevision] = useState<BlameRevision | null>(null);
+  const [history, setHistory] = useState<(BlameRevision | null)[]>([]);
+  const [focusLine, setFocusLine] = useState<number | null>(null);
+
+  const loadChunk = (fromLine: number) =>
+    invoke<BlameInfo[]>("git_blame_cmd", {
+      repoPath,
+      filePath: revision?.filePath ?? filePath,
+      settings: {
+        min_line: fromLine,
+        max_line: fromLine + CHUNK_SIZE - 1,
+        commit: revision?.commitId ?? null,
+      },
+    });
+
+  useEffect(() => {
+    setRevision(null);
+    setHistory([]);
+  }, [repoPath, filePath]);
 
   useEffect(() => {
     const loadBlame = async () => {
       setLoading(true);
       setError(null);
+      setComplete(false);
       try {
-        const data = await invoke<BlameInfo[]>("git_blame_cmd", {
-          repoPath,
-          filePath,
-        });
+        const data = await loadChunk(1);
         setBlameData(data);
+        setComplete(data.length < CHUNK_SIZE);
       } catch (err) {
         console.error("Failed to load blame:", err);
         setError(String(err));
@@ -45,7 +96,74 @@ export const BlameViewer: React.FC<BlameViewerProps> = ({
     if (repoPath && filePath) {
       loadBlame();
     }
-  }, [repoPath, filePath]);
+  }, [repoPath, filePath, revision]);
+
+  // Who wrote this line before the commit blamed for it?
+  const blamePrior = async (line: BlameInfo) => {
+    const path = line.orig_path ?? revision?.filePath ?? filePath;
+    try {
+      const result = await invoke<BlameAtParent>("git_blame_parent_cmd", {
+        repoPath,
+        filePath: path,
+        commitId: line.commit_id,
+        settings: {
+          min_line: 1,
+          max_line: line.orig_line_number + CHUNK_SIZE,
+        },
+      });
+      setHistory((prev) => [...prev, revision]);
+      setRevision({ commitId: result.commit_id, filePath: result.file_path });
+      setFocusLine(line.orig_line_number);
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
+  const goBack = () => {
+    setRevision(history[history.length - 1] ?? null);
+    setHistory((prev) => prev.slice(0, -1));
+    setFocusLine(null);
+  };
+
+  // Scroll the drilled-down line into view once its chunk is loaded
+  useEffect(() => {
+    if (focusLine === null || loading) return;
+    const row = viewportRef.current?.querySelector(
+      `[data-line="${focusLine}"]`,
+    );
+    if (row) {
+      row.scrollIntoView({ block: "center" });
+      setFocusLine(null);
+    } else if (!complete) {
+      loadMore();
+    }
+  }, [focusLine, loading, blameData]);
+
+  const loadMore = async () => {
+    if (complete || loadingMore.current || blameData.length === 0) return;
+    loadingMore.current = true;
+    try {
+      const next = blameData[blameData.length - 1].line_number + 1;
+      const data = await loadChunk(next);
+      setBlameData((prev) => [...prev, ...data]);
+      setComplete(data.length < CHUNK_SIZE);
+    } catch (err) {
+      console.error("Failed to load blame:", err);
+      setComplete(true);
+    } finally {
+      loadingMore.current = false;
+    }
+  };
+
+  const viewportRef = useRef<HTMLDivElement>(null);
+  const handleScroll = ({ y }: { x: number; y: number }) => {
+    const viewport = viewportRef.current;
+    if (!viewport) return;
+    // Blame the next lines before the user reaches them
+    if (y + viewport.clientHeight > viewport.scrollHeight - 400) {
+      loadMore();
+    }
+  };
 
   if (loading) {
     return (
@@ -55,7 +173,7 @@ export const BlameViewer: React.FC<BlameViewerProps> = ({
     );
   }
 
-  if (error) {
+  if (error && blameData.length === 0) {
     return (
       <Box p="md">
         <Text c="red">Error loading blame: {error}</Text>
@@ -65,6 +183,30 @@ export const BlameViewer: React.FC<BlameViewerProps> = ({
 
   return (
     <Box>
+      {(revision || error) && (
+        <Group gap="xs" p={4}>
+          {revision && (
+            <>
+              <Button
+                size="compact-xs"
+                variant="default"
+                leftSection={<FontAwesomeIcon icon={faArrowLeft} />}
+                onClick={goBack}
+              >
+                Back
+              </Button>
+              <Text size="xs" c="dimmed">
+                {revision.filePath} at {revision.commitId.substring(0, 7)}
+              </Text>
+            </>
+          )}
+          {error && (
+            <Text size="xs" c="red">
+              {error}
+            </Text>
+          )}
+        </Group>
+      )}
       <Box
         style={{
           display: "grid",
@@ -82,7 +224,12 @@ export const BlameViewer: React.FC<BlameViewerProps> = ({
         </Text>
       </Box>
 
-      <ScrollArea.Autosize mah="70vh" type="auto">
+      <ScrollArea.Autosize
+        mah="70vh"
+        type="auto"
+        viewportRef={viewportRef}
+        onScrollPositionChange={handleScroll}
+      >
         <Box style={{ fontFamily: "monospace", fontSize: "12px" }}>
           {blameData.map((line, idx) => {
             // Check if previous line commit is same to group visually
@@ -92,6 +239,7 @@ export const BlameViewer: React.FC<BlameViewerProps> = ({
             return (
               <Box
                 key={idx}
+                data-line={line.line_number}
                 style={{
                   display: "grid",
                   gridTemplateColumns: "180px 1fr",
@@ -130,6 +278,16 @@ export const BlameViewer: React.FC<BlameViewerProps> = ({
                         >
                           {new Date(line.timestamp * 1000).toLocaleDateString()}
                         </Text>
+                        <Tooltip label="Blame prior to this commit">
+                          <ActionIcon
+                            size="xs"
+                            variant="subtle"
+                            color="gray"
+                            onClick={() => blamePrior(line)}
+                          >
+                            <FontAwesomeIcon icon={faHistory} size="xs" />
+                          </ActionIcon>
+                        </Tooltip>
                       </Group>
                     </Tooltip>
                   )}
@@ -155,6 +313,11 @@ export const BlameViewer: React.FC<BlameViewerProps> = ({
               </Box>
             );
           })}
+          {!complete && (
+            <Box p="xs" style={{ display: "flex", justifyContent: "center" }}>
+              <Loader size="xs" />
+            </Box>
+          )}
         </Box>
       </ScrollArea.Autosize>
     </Box>
@@ -0,0 +1,180 @@
+import React, { useEffect, useState } from "react";
+import {
+  Anchor,
+  Box,
+  Breadcrumbs,
+  Code,
+  Group,
+  Loader,
+  ScrollArea,
+  Stack,
+  Text,
+  UnstyledButton,
+} from "@mantine/core";
+import { invoke } from "@tauri-apps/api/core";
+import { getFileIcon } from "../shared/tree";
+
+export interface CommitTreeEntry {
+  name: string;
+  path: string;
+  kind: "file" | "directory" | "symlink" | "submodule";
+  size: number | null;
+  id: string;
+}
+
+interface CommitTreeBrowserProps {
+  repoPath: string;
+  commitId: string;
+}
+
+const formatSize = (size: number | null) => {
+  if (size === null) return "";
+  if (size < 1024) return `${size} B`;
+  if (size < 1024 * 1024) return `${(size / 1024).toFixed(1)} KB`;
+  return `${(size / 1024 / 1024).toFixed(1)} MB`;
+};
+
+/**
+ * Browses the files of a commit and previews any of them as it was at
+ * that revision.
+ */
+export const CommitTreeBrowser: React.FC<CommitTreeBrowserProps> = ({
+  repoPath,
+  commitId,
+}) => {
+  const [folder, setFolder] = useState("");
+  const [entries, setEntries] = useState<CommitTreeEntry[]>([]);
+  const [loading, setLoading] = useState(true);
+  const [error, setError] = useState<string | null>(null);
+  // File being previewed and its content at the commit
+  const [selected, setSelected] = useState<CommitTreeEntry | null>(null);
+  const [content, setContent] = useState<string | null>(null);
+  const [contentError, setContentError] = useState<string | null>(null);
+
+  useEffect(() => {
+    setFolder("");
+    setSelected(null);
+  }, [repoPath, commitId]);
+
+  useEffect(() => {
+    setLoading(true);
+    setError(null);
+    invoke<CommitTreeEntry[]>("git_list_tree_at_commit_cmd", {
+      repoPath,
+      commitId,
+      path: folder,
+    })
+      .then(setEntries)
+      .catch((err) => setError(String(err)))
+      .finally(() => setLoading(false));
+  }, [repoPath, commitId, folder]);
+
+  const handleOpen = async (entry: CommitTreeEntry) => {
+    if (entry.kind === "directory") {
+      setFolder(entry.path);
+      setSelected(null);
+      return;
+    }
+    setSelected(entry);
+    setContent(null);
+    setContentError(null);
+    if (entry.kind === "submodule") {
+      setContentError(`Submodule at commit ${entry.id.substring(0, 7)}`);
+      return;
+    }
+    try {
+      setContent(
+        await invoke<string>("git_file_at_commit_cmd", {
+          repoPath,
+          commitId,
+          filePath: entry.path,
+        }),
+      );
+    } catch {
+      setContentError("Binary file, no preview available");
+    }
+  };
+
+  const crumbs = folder ? folder.split("/") : [];
+
+  return (
+    <Stack gap="xs">
+      <Breadcrumbs separator="/">
+        <Anchor size="xs" onClick={() => setFolder("")}>
+          {commitId.substring(0, 7)}
+        </Anchor>
+        {crumbs.map((name, i) => (
+          <Anchor
+            key={i}
+            size="xs"
+            onClick={() => setFolder(crumbs.slice(0, i + 1).join("/"))}
+          >
+            {name}
+          </Anchor>
+        ))}
+      </Breadcrumbs>
+      {error && (
+        <Text size="xs" c="red">
+          {error}
+        </Text>
+      )}
+      <Group align="flex-start" gap="xs" wrap="nowrap">
+        <ScrollArea h={400} w={260} type="auto" offsetScrollbars>
+          {loading ? (
+            <Loader size="sm" />
+          ) : (
+            <Stack gap={0}>
+              {entries.map((entry) => (
+                <UnstyledButton
+                  key={entry.path}
+                  onClick={() => handleOpen(entry)}
+                  px={4}
+                  py={2}
+                  bg={
+                    selected?.path === entry.path
+                      ? "var(--mantine-color-default-hover)"
+                      : undefined
+                  }
+                >
+                  <Group gap={6} wrap="nowrap">
+                    {getFileIcon(
+                      entry.name,
+                      entry.kind === "directory" ? "folder" : "file",
+                    )}
+                    <Text size="xs" truncate style={{ flex: 1 }}>
+                      {entry.name}
+                    </Text>
+                    <Text size="xs" c="dimmed">
+                      {formatSize(entry.size)}
+                    </Text>
+                  </Group>
+                </UnstyledButton>
+              ))}
+            </Stack>
+          )}
+        </ScrollArea>
+        <Box style={{ flex: 1, minWidth: 0 }}>
+          {selected ? (
+            contentError ? (
+              <Text size="xs" c="dimmed">
+                {contentError}
+              </Text>
+            ) : content === null ? (
+              <Loader size="sm" />
+            ) : (
+              <ScrollArea h={400} type="auto">
+                <Code block style={{ whiteSpace: "pre" }}>
+                  {content}
+                </Code>
+              </ScrollArea>
+            )
+          ) : (
+            <Text size="xs" c="dimmed">
+              Select a file to see it as it was at this commit
+            </Text>
+          )}
+        </Box>
+      </Group>
+    </Stack>
+  );
+};
@@ -9,8 +9,10 @@ import {
   Code,
   ScrollArea,
   Badge,
+  SegmentedControl,
 } from "@mantine/core";
 import { invoke } from "@tauri-apps/api/core";
+import { writeTextFile } from "@tauri-apps/plugin-fs";
 import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
 import { faCheck } from "@fortawesome/free-solid-svg-icons";
 
@@ -21,6 +23,29 @@ export interface ConflictFile {
   their_oid: string | null;
 }
 
+// A run of lines in the three-way merge; lines keep their line endings
+type MergeRegion =
+  | {
+      kind: "resolved";
+      source: "unchanged" | "ours" | "theirs" | "both";
+      lines: string[];
+    }
+  | { kind: "conflict"; base: string[]; ours: string[]; theirs: string[] };
+
+interface ConflictMerge {
+  path: string;
+  ancestor: string | null;
+  ours: string | null;
+  theirs: string | null;
+  merge: {
+    regions: MergeRegion[];
+    conflict_count: number;
+    merged: string;
+  };
+}
+
+type ConflictChoice = "ours" | "theirs" | "both";
+
 interface ConflictResolverProps {
   repoPath: string;
   files: ConflictFile[];
@@ -39,6 +64,9 @@ export const ConflictResolver: React.FC<ConflictResolverProps> = ({
   );
   const [ourContent, setOurContent] = useState<string>("");
   const [theirContent, setTheirContent] = useState<string>("");
+  const [merge, setMerge] = useState<ConflictMerge | null>(null);
+  // Chosen side for each conflict region, by region index
+  const [choices, setChoices] = useState<Record<number, ConflictChoice>>({});
   const [resolvedFiles, setResolvedFiles] = useState<Set<string>>(new Set());
 
   useEffect(() => {
@@ -49,47 +77,46 @@ export const ConflictResolver: React.FC<ConflictResolverProps> = ({
 
   const loadContents = async (file: ConflictFile) => {
     try {
-      if (file.our_oid) {
-        const content = await invoke<string>("git_get_blob_content_cmd", {
-          repoPath,
-          blobOid: file.our_oid,
-        });
-        setOurContent(content);
-      } else {
-        setOurContent("(Deleted in our branch)");
-      }
-
-      if (file.their_oid) {
-        const content = await invoke<string>("git_get_blob_content_cmd", {
-          repoPath,
-          blobOid: file.their_oid,
-        });
-        setTheirContent(content);
-      } else {
-        setTheirContent("(Deleted in their branch)");
-      }
+      const result = await invoke<ConflictMerge>("git_get_conflict_merge_cmd", {
+        repoPath,
+        filePath: file.path,
+      });
+      setMerge(result);
+      setChoices({});
+      setOurContent(result.ours ?? "(Deleted in our branch)");
+      setTheirContent(result.theirs ?? "(Deleted in their branch)");
     } catch (err) {
-      console.error("Failed to load blob content:", err);
+      console.error("Failed to load conflict:", err);
+      setMerge(null);
     }
   };
 
-  const handleResolve = async (_strategy: "ours" | "theirs") => {
-    if (!selectedFile) return;
+  const conflictIndices = (merge?.merge.regions ?? [])
+    .map((region, index) => (region.kind === "conflict" ? index : -1))
+    .filter((index) => index >= 0);
+  const allChosen = conflictIndices.every((index) => choices[index]);
 
-    try {
-      // Logic would go here to actually write the file with chosen content
-      // For now, we'll assume we just pick one side completely (simple resolution)
-      // In a real app, we might need a way to edit/merge manually
+  // Merged document with each conflict replaced by the chosen side(s)
+  const buildMerged = () =>
+    (merge?.merge.regions ?? [])
+      .map((region, index) => {
+        if (region.kind === "resolved") return region.lines.join("");
+        const choice = choices[index];
+        if (choice === "ours") return region.ours.join("");
+        if (choice === "theirs") return region.theirs.join("");
+        return region.ours.join("") + region.theirs.join("");
+      })
+      .join("");
 
-      // This part requires a backend function we haven't strictly defined for *writing* content arbitrarily
-      // But we can use mark_conflict_resolved if we assume the user edited the file externally OR
-      // we need to add a "write_file" command. For this MVP, let's assume manual resolution or
-      // just "marking" as resolved after user action.
+  const handleResolve = async (strategy: "ours" | "theirs" | "merged") => {
+    if (!selectedFile || !merge) return;
 
-      // NOTE: For true content resolution we need to write to the file first.
-      // Since we don't have a specific `git_resolve_conflict_using_oid`, we will use
-      // standard file writing (which we have access to via tauri fs, or we can add a helper).
-      // For now, let's just MARK it as resolved to show the flow.
+    try {
+      const content =
+        strategy === "merged"
+          ? buildMerged()
+          : ((strategy === "ours" ? merge.ours : merge.theirs) ?? "");
+      await writeTextFile(`${repoPath}/${selectedFile.path}`, content);
 
       await invoke("git_mark_conflict_resolved_cmd", {
         repoPath,
@@ -179,13 +206,21 @@ export const ConflictResolver: React.FC<ConflictResolverProps> = ({
                   >
                     Accept Theirs
                   </Button>
+                  <Button
+                    size="xs"
+                    color="green"
+                    disabled={!merge || !allChosen}
+                    onClick={() => handleResolve("merged")}
+                  >
+                    Accept Merged
+                  </Button>
                 </Group>
               </Group>
 
               <Box
                 style={{
                   display: "grid",
-                  gridTemplateColumns: "1fr 1fr",
+                  gridTemplateColumns: "1fr 1fr 1fr",
                   gap: "8px",
                   height: "400px",
                 }}
@@ -200,6 +235,64 @@ export const ConflictResolver: React.FC<ConflictResolverProps> = ({
                     </Code>
                   </ScrollArea>
                 </Box>
+                <Box>
+                  <Text size="xs" fw={700} c="green">
+                    Merged ({merge?.merge.conflict_count ?? 0} conflicts)
+                  </Text>
+                  <ScrollArea h={380} type="auto" offsetScrollbars>
+                    <Stack gap={4}>
+                      {merge?.merge.regions.map((region, index) =>
+                        region.kind === "resolved" ? (
+                          <Code
+                            key={index}
+                            block
+                            style={{ whiteSpace: "pre-wrap" }}
+                          >
+                            {region.lines.join("")}
+                          </Code>
+                        ) : (
+                          <Box
+                            key={index}
+                            p={4}
+                            style={{
+                              border: "1px solid var(--mantine-color-orange-6)",
+                              borderRadius: 4,
+                            }}
+                          >
+                            <SegmentedControl
+                              size="xs"
+                              fullWidth
+                              value={choices[index] ?? ""}
+                              onChange={(value) =>
+                                setChoices({
+                                  ...choices,
+                                  [index]: value as ConflictChoice,
+                                })
+                              }
+                              data={[
+                                { label: "Ours", value: "ours" },
+                                { label: "Theirs", value: "theirs" },
+                                { label: "Both", value: "both" },
+                              ]}
+                            />
+                            <Code
+                              block
+                              mt={4}
+                              style={{ whiteSpace: "pre-wrap" }}
+                            >
+                              {choices[index] === "theirs"
+                                ? region.theirs.join("")
+                                : choices[index] === "both"
+                                  ? region.ours.join("") +
+                                    region.theirs.join("")
+                                  : region.ours.join("")}
+                            </Code>
+                          </Box>
+                        ),
+                      )}
+                    </Stack>
+                  </ScrollArea>
+                </Box>
                 <Box>
                   <Text size="xs" fw={700} c="cyan">
                     Theirs (Incoming)
@@ -10,6 +10,8 @@ export interface GitViewData {
   repoPath: string;
   filePath: string;
   initialView: "diff" | "blame";
+  // Staged files show HEAD↔index, unstaged ones index↔workdir
+  diffMode?: "head_to_workdir" | "staged" | "unstaged";
 }
 
 interface GitFileViewerProps {
@@ -32,6 +34,7 @@ export const GitFileViewer: React.FC<GitFileViewerProps> = ({ data }) => {
             {
               repoPath: data.repoPath,
               filePath: data.filePath,
+              mode: data.diffMode ?? "head_to_workdir",
             },
           );
           setDiffData(diff);
@@ -43,7 +46,14 @@ export const GitFileViewer: React.FC<GitFileViewerProps> = ({ data }) => {
       };
       loadDiff();
     }
-  }, [viewMode, data.repoPath, data.filePath, diffData, loadingDiff]);
+  }, [
+    viewMode,
+    data.repoPath,
+    data.filePath,
+    data.diffMode,
+    diffData,
+    loadingDiff,
+  ]);
 
   return (
     <Stack gap={0} h="100%" w="100%">
@@ -7,9 +7,32 @@ import {
   faCodeBranch,
   faCheck,
   faUndo,
+  faFileExport,
+  faFolderTree,
 } from "@fortawesome/free-solid-svg-icons";
 
 // Types
+export interface CommitSignature {
+  format: "openpgp" | "x509" | "ssh";
+  status: "unverified" | "good" | "bad" | "expired" | "unknown_key";
+  signer: string | null;
+}
+
+export const signatureLabel = (signature: CommitSignature) => {
+  switch (signature.status) {
+    case "good":
+      return `Good signature${signature.signer ? ` from ${signature.signer}` : ""}`;
+    case "bad":
+      return "Bad signature";
+    case "expired":
+      return "Signed with an expired or revoked key";
+    case "unknown_key":
+      return "Signed with an unknown key";
+    default:
+      return "Signed";
+  }
+};
+
 export interface GitCommitInfo {
   id: string;
   short_id: string;
@@ -19,6 +42,9 @@ export interface GitCommitInfo {
   timestamp: number;
   parent_ids: string[];
   refs?: string[];
+  // Path of the file in this commit (git_file_log_cmd only)
+  file_path?: string;
+  signature?: CommitSignature;
 }
 
 interface GitGraphProps {
@@ -28,6 +54,9 @@ interface GitGraphProps {
   onCheckoutCommit?: (commitId: string) => void;
   onCherryPick?: (commitId: string) => void;
   onRevertCommit?: (commitId: string) => void;
+  onResetToCommit?: (commitId: string, mode: "soft" | "mixed" | "hard") => void;
+  onArchiveCommit?: (commit: GitCommitInfo) => void;
+  onBrowseCommit?: (commit: GitCommitInfo) => void;
 }
 
 interface GraphNode {
@@ -67,6 +96,9 @@ export const GitGraph: React.FC<GitGraphProps> = ({
   onCheckoutCommit,
   onCherryPick,
   onRevertCommit,
+  onResetToCommit,
+  onArchiveCommit,
+  onBrowseCommit,
 }) => {
   const [copiedId, setCopiedId] = useState<string | null>(null);
   const [contextMenuId, setContextMenuId] = useState<string | null>(null);
@@ -190,6 +222,20 @@ export const GitGraph: React.FC<GitGraphProps> = ({
           Refs: {commit.refs.join(", ")}
         </Text>
       )}
+      {commit.signature && (
+        <Text
+          size="xs"
+          c={
+            commit.signature.status === "good"
+              ? "green"
+              : commit.signature.status === "bad"
+                ? "red"
+                : "dimmed"
+          }
+        >
+          {signatureLabel(commit.signature)}
+        </Text>
+      )}
     </Box>
   );
 
@@ -389,6 +435,44 @@ export const GitGraph: React.FC<GitGraphProps> = ({
                   Revert this commit
                 </Menu.Item>
               )}
+              {onBrowseCommit && (
+                <Menu.Item
+                  leftSection={<FontAwesomeIcon icon={faFolderTree} />}
+                  onClick={() => onBrowseCommit(node.commit)}
+                >
+                  Browse Files...
+                </Menu.Item>
+              )}
+              {onArchiveCommit && (
+                <Menu.Item
+                  leftSection={<FontAwesomeIcon icon={faFileExport} />}
+                  onClick={() => onArchiveCommit(node.commit)}
+                >
+                  Export Snapshot...
+                </Menu.Item>
+              )}
+              {onResetToCommit && (
+                <>
+                  <Menu.Divider />
+                  <Menu.Label>Reset current branch here</Menu.Label>
+                  <Menu.Item
+                    onClick={() => onResetToCommit(node.commit.id, "soft")}
+                  >
+                    Soft (keep changes staged)
+                  </Menu.Item>
+                  <Menu.Item
+                    onClick={() => onResetToCommit(node.commit.id, "mixed")}
+                  >
+                    Mixed (keep changes unstaged)
+                  </Menu.Item>
+                  <Menu.Item
+                    onClick={() => onResetToCommit(node.commit.id, "hard")}
+                    color="red"
+                  >
+                    Hard (discard changes)
+                  </Menu.Item>
+                </>
+              )}
             </Menu.Dropdown>
           </Menu>
         ))}
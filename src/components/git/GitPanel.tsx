@@ -17,6 +17,10 @@ import {
   Menu,
   TextInput,
   Checkbox,
+  Select,
+  NumberInput,
+  TagsInput,
+  Progress,
 } from "@mantine/core";
 import { notifications } from "@mantine/notifications";
 import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
@@ -40,13 +44,21 @@ import {
   faTriangleExclamation,
   faHistory,
   faPencilAlt,
+  faCloudArrowUp,
+  faCloudArrowDown,
+  faChartColumn,
 } from "@fortawesome/free-solid-svg-icons";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { useDebouncedCallback } from "use-debounce";
 
-import { GitGraph } from "./GitGraph";
+import { GitGraph, CommitSignature, signatureLabel } from "./GitGraph";
 import { ConflictResolver, ConflictFile } from "./ConflictResolver";
+import { ReflogViewer } from "./ReflogViewer";
+import { WorktreeManager } from "./WorktreeManager";
+import { CommitTreeBrowser } from "./CommitTreeBrowser";
+import { RepoStats } from "./RepoStats";
+import { DiffViewer, StructuredDiff } from "./DiffViewer";
 import { getFileIcon } from "../shared/tree";
 import { useTabsStore } from "../../stores/useTabsStore";
 
@@ -57,6 +69,11 @@ interface GitRepoInfo {
   remote_url: string | null;
   is_dirty: boolean;
   head_commit: string | null;
+  upstream: string | null;
+  ahead: number | null;
+  behind: number | null;
+  detached: boolean;
+  head_tags: string[];
 }
 
 interface GitFileStatus {
@@ -65,6 +82,29 @@ interface GitFileStatus {
   is_staged: boolean;
 }
 
+interface FetchProgress {
+  remote: string;
+  received_objects: number;
+  total_objects: number;
+  received_bytes: number;
+}
+
+interface FetchSummary {
+  remotes: string[];
+  updated: {
+    reference: string;
+    old_id: string | null;
+    new_id: string | null;
+  }[];
+  errors: string[];
+}
+
+interface GitStatusChanged {
+  repo_path: string;
+  files: GitFileStatus[];
+  refs_changed: boolean;
+}
+
 interface GitCommitInfo {
   id: string;
   short_id: string;
@@ -74,12 +114,37 @@ interface GitCommitInfo {
   timestamp: number;
   parent_ids: string[];
   refs?: string[];
+  // Path of the file in this commit (git_file_log_cmd only)
+  file_path?: string;
+  signature?: CommitSignature;
+}
+
+interface SwitchBranchResult {
+  switched: boolean;
+  conflicts: string[];
+  stashed: boolean;
+  stash_conflicts: string[];
+}
+
+interface CommitMessageRules {
+  max_summary_length: number;
+  require_blank_line: boolean;
+  conventional_types: string[];
+}
+
+interface CommitMessageIssue {
+  line: number;
+  severity: "error" | "warning";
+  message: string;
 }
 
 interface BranchInfo {
   name: string;
   is_head: boolean;
   is_remote: boolean;
+  upstream: string | null;
+  ahead: number | null;
+  behind: number | null;
 }
 
 interface RemoteInfo {
@@ -87,6 +152,49 @@ interface RemoteInfo {
   url: string;
 }
 
+interface RepoOperationState {
+  operation:
+    | "clean"
+    | "merging"
+    | "rebasing"
+    | "cherry_picking"
+    | "reverting"
+    | "other";
+  conflicts: string[];
+  can_continue: boolean;
+  interactive_rebase: boolean;
+}
+
+const OPERATION_LABELS: Record<RepoOperationState["operation"], string> = {
+  clean: "",
+  merging: "Merge",
+  rebasing: "Rebase",
+  cherry_picking: "Cherry-pick",
+  reverting: "Revert",
+  other: "Operation",
+};
+
+interface PushResult {
+  refs: { reference: string; ok: boolean; message: string | null }[];
+  upstream_set: string | null;
+}
+
+interface PullResult {
+  outcome: "up_to_date" | "fast_forward" | "merged" | "rebased" | "conflicts";
+  head: string | null;
+  upstream: string;
+  rebased_commits: number;
+  conflicts: string[];
+}
+
+const PULL_OUTCOME_MESSAGES: Record<PullResult["outcome"], string> = {
+  up_to_date: "Already up to date.",
+  fast_forward: "Fast-forwarded to the remote branch.",
+  merged: "Merged the remote branch.",
+  rebased: "Rebased local commits onto the remote branch.",
+  conflicts: "Conflicts detected. Please resolve them.",
+};
+
 interface GitPanelProps {
   projectPath: string | null;
   onOpenFile: (path: string) => void;
@@ -110,6 +218,15 @@ export const GitPanel: React.FC<GitPanelProps> = ({
 
   // Branch state
   const [createBranchOpen, setCreateBranchOpen] = useState(false);
+  const [reflogOpen, setReflogOpen] = useState(false);
+  const [worktreesOpen, setWorktreesOpen] = useState(false);
+  const [statsOpen, setStatsOpen] = useState(false);
+  const [fetchProgress, setFetchProgress] = useState<FetchProgress | null>(
+    null,
+  );
+  const [browsedCommit, setBrowsedCommit] = useState<GitCommitInfo | null>(
+    null,
+  );
   const [newBranchName, setNewBranchName] = useState("");
 
   const [gitignoreOpen, setGitignoreOpen] = useState(false);
@@ -127,10 +244,38 @@ export const GitPanel: React.FC<GitPanelProps> = ({
   const [showStashes, setShowStashes] = useState(false);
   const [stashMessage, setStashMessage] = useState("");
   const [stashModalOpen, setStashModalOpen] = useState(false);
+  const [stashIncludeUntracked, setStashIncludeUntracked] = useState(false);
+  const [stashKeepIndex, setStashKeepIndex] = useState(false);
+  const [stashSelectedOnly, setStashSelectedOnly] = useState(false);
+  // Stash being previewed: one diff per file and the file shown
+  const [stashPreview, setStashPreview] = useState<{
+    index: number;
+    diffs: StructuredDiff[];
+    selected: number;
+  } | null>(null);
 
   // Amend state
   const [amendMode, setAmendMode] = useState(false);
 
+  // Signing state (commit.gpgsign / tag.gpgsign of the repository)
+  const [signCommits, setSignCommits] = useState(false);
+  const [verifySignatures, setVerifySignatures] = useState(false);
+
+  // Compile snapshots (datatex.snapshots.* of the repository)
+  interface SnapshotSettings {
+    enabled: boolean;
+    branch: string;
+  }
+  const [snapshotSettings, setSnapshotSettings] =
+    useState<SnapshotSettings | null>(null);
+
+  // Commit message template and rules
+  const [commitTemplate, setCommitTemplate] = useState<string | null>(null);
+  const [commitIssues, setCommitIssues] = useState<CommitMessageIssue[]>([]);
+  const [commitRules, setCommitRules] = useState<CommitMessageRules | null>(
+    null,
+  );
+
   // Tags state
   interface TagInfo {
     name: string;
@@ -153,6 +298,8 @@ export const GitPanel: React.FC<GitPanelProps> = ({
 
   const [conflicts, setConflicts] = useState<ConflictFile[]>([]);
   const [showConflictResolver, setShowConflictResolver] = useState(false);
+  const [operationState, setOperationState] =
+    useState<RepoOperationState | null>(null);
 
   // Branch Ops
   const [renameModalOpen, setRenameModalOpen] = useState(false);
@@ -308,10 +455,17 @@ export const GitPanel: React.FC<GitPanelProps> = ({
         console.warn("Failed to start git watcher:", e);
       }
 
-      // Listen for event
-      unlisten = await listen("git-refresh", () => {
-        debouncedRefresh(repoInfo.path);
-      });
+      // The backend rescans the status when files change and sends it
+      unlisten = await listen<GitStatusChanged>(
+        "git-status-changed",
+        (event) => {
+          if (event.payload.repo_path !== repoInfo.path) return;
+          const { files, refs_changed } = event.payload;
+          setStagedFiles(files.filter((f) => f.is_staged));
+          setUnstagedFiles(files.filter((f) => !f.is_staged));
+          if (refs_changed) debouncedRefresh(repoInfo.path);
+        },
+      );
     };
 
     startWatching();
@@ -358,6 +512,11 @@ export const GitPanel: React.FC<GitPanelProps> = ({
 
       // Check for conflicts
       checkConflicts(repoPath);
+      invoke<RepoOperationState>("git_get_repo_operation_state_cmd", {
+        repoPath,
+      })
+        .then(setOperationState)
+        .catch((e) => console.warn("Failed to get operation state:", e));
     } catch (err) {
       console.error("Failed to get status:", err);
     }
@@ -367,13 +526,14 @@ export const GitPanel: React.FC<GitPanelProps> = ({
     (file: GitFileStatus) => {
       if (!repoInfo) return;
       openTab({
-        id: `git-diff-${file.path}`,
+        id: `git-diff-${file.is_staged ? "staged" : "unstaged"}-${file.path}`,
         title: file.path.split("/").pop() || "Diff",
         type: "git-view",
         gitData: {
           repoPath: repoInfo.path,
           filePath: file.path,
           initialView: "diff",
+          diffMode: file.is_staged ? "staged" : "unstaged",
         },
       });
     },
@@ -413,13 +573,45 @@ export const GitPanel: React.FC<GitPanelProps> = ({
     }
   };
 
-  const handleSwitchBranch = async (name: string) => {
+  const handleSwitchBranch = async (branchName: string, isRemote = false) => {
     if (!repoInfo) return;
+    // A remote branch is checked out as a local branch tracking it
+    const name = isRemote
+      ? branchName.substring(branchName.indexOf("/") + 1)
+      : branchName;
+    const switchTo = (stashChanges: boolean) =>
+      isRemote
+        ? invoke<SwitchBranchResult>("git_checkout_remote_branch_cmd", {
+            repoPath: repoInfo.path,
+            remoteBranch: branchName,
+            localName: null,
+            stashChanges,
+          })
+        : invoke<SwitchBranchResult>("git_switch_branch_cmd", {
+            repoPath: repoInfo.path,
+            name,
+            stashChanges,
+          });
     try {
-      await invoke("git_switch_branch_cmd", {
-        repoPath: repoInfo.path,
-        name,
-      });
+      let result = await switchTo(false);
+      if (!result.switched) {
+        const files = result.conflicts.join("\n");
+        if (
+          !window.confirm(
+            `Your changes to these files would be overwritten by switching to ${name}:\n\n${files}\n\nStash them, switch, and re-apply them on ${name}?`,
+          )
+        ) {
+          return;
+        }
+        result = await switchTo(true);
+      }
+      if (result.stash_conflicts.length > 0) {
+        notifications.show({
+          title: `Switched to ${name}`,
+          message: `Re-applying your changes conflicted in ${result.stash_conflicts.join(", ")}. They are also kept in the stash.`,
+          color: "yellow",
+        });
+      }
       await refreshStatus(repoInfo.path);
     } catch (err) {
       console.error("Failed to switch branch:", err);
@@ -441,16 +633,71 @@ export const GitPanel: React.FC<GitPanelProps> = ({
     }
   };
 
-  const handlePush = async () => {
+  const handleContinueOperation = async () => {
+    if (!repoInfo) return;
+    setLoading(true);
+    try {
+      const state = await invoke<RepoOperationState>(
+        "git_continue_operation_cmd",
+        { repoPath: repoInfo.path },
+      );
+      if (state.conflicts.length > 0) {
+        notifications.show({
+          title: "Conflicts",
+          message: `${OPERATION_LABELS[state.operation]} stopped with conflicts in ${state.conflicts.join(", ")}`,
+          color: "orange",
+        });
+      }
+      await refreshStatus(repoInfo.path);
+    } catch (err) {
+      setError("Continue failed: " + String(err));
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  const handleAbortOperation = async () => {
+    if (!repoInfo) return;
+    setLoading(true);
+    try {
+      await invoke("git_abort_operation_cmd", { repoPath: repoInfo.path });
+      await refreshStatus(repoInfo.path);
+    } catch (err) {
+      setError("Abort failed: " + String(err));
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  const handlePush = async (
+    settings: { force_with_lease?: boolean; tags?: boolean } = {}
+  ) => {
     if (!repoInfo || !repoInfo.branch) return;
     setLoading(true);
     try {
       const remote = remotes.length > 0 ? remotes[0].name : "origin";
-      await invoke("git_push_remote_cmd", {
+      const result = await invoke<PushResult>("git_push_remote_cmd", {
         repoPath: repoInfo.path,
         remote,
         branch: repoInfo.branch,
+        settings,
       });
+      const rejected = result.refs.filter((r) => !r.ok);
+      if (rejected.length > 0) {
+        notifications.show({
+          title: "Push Rejected",
+          message: rejected
+            .map((r) => `${r.reference}: ${r.message ?? "rejected"}`)
+            .join("\n"),
+          color: "orange",
+        });
+      } else if (result.upstream_set) {
+        notifications.show({
+          title: "Push Result",
+          message: `Upstream set to ${result.upstream_set}`,
+          color: "green",
+        });
+      }
       await refreshStatus(repoInfo.path);
     } catch (err) {
       console.error("Push failed:", err);
@@ -460,16 +707,28 @@ export const GitPanel: React.FC<GitPanelProps> = ({
     }
   };
 
-  const handlePull = async () => {
+  const handlePull = async (strategy: "merge" | "rebase" = "merge") => {
     if (!repoInfo || !repoInfo.branch) return;
     setLoading(true);
     try {
       const remote = remotes.length > 0 ? remotes[0].name : "origin";
-      await invoke("git_pull_remote_cmd", {
+      const result = await invoke<PullResult>("git_pull_remote_cmd", {
         repoPath: repoInfo.path,
         remote,
         branch: repoInfo.branch,
+        strategy,
       });
+      const conflicted = result.outcome === "conflicts";
+      notifications.show({
+        title: "Pull Result",
+        message: conflicted
+          ? `${PULL_OUTCOME_MESSAGES.conflicts} (${result.conflicts.join(", ")})`
+          : PULL_OUTCOME_MESSAGES[result.outcome],
+        color: conflicted ? "orange" : "green",
+      });
+      if (conflicted) {
+        checkConflicts(repoInfo.path);
+      }
       await refreshStatus(repoInfo.path);
     } catch (err) {
       console.error("Pull failed:", err);
@@ -494,6 +753,37 @@ export const GitPanel: React.FC<GitPanelProps> = ({
     }
   };
 
+  const handleFetchAll = async () => {
+    if (!repoInfo) return;
+    const unlisten = await listen<FetchProgress>("git-fetch-progress", (e) =>
+      setFetchProgress(e.payload),
+    );
+    try {
+      const summary = await invoke<FetchSummary>("git_fetch_all_cmd", {
+        repoPath: repoInfo.path,
+        prune: true,
+      });
+      const pruned = summary.updated.filter((u) => u.new_id === null).length;
+      const updated = summary.updated.length - pruned;
+      notifications.show({
+        title: `Fetched ${summary.remotes.length} remote(s)`,
+        message:
+          `${updated} reference(s) updated, ${pruned} pruned` +
+          (summary.errors.length > 0
+            ? `\nFailed: ${summary.errors.join("; ")}`
+            : ""),
+        color: summary.errors.length > 0 ? "yellow" : "green",
+      });
+      await refreshStatus(repoInfo.path);
+    } catch (err) {
+      console.error("Fetch failed:", err);
+      setError("Fetch failed: " + String(err));
+    } finally {
+      unlisten();
+      setFetchProgress(null);
+    }
+  };
+
   const handleOpenGitignore = async () => {
     if (!repoInfo) return;
     try {
@@ -508,6 +798,18 @@ export const GitPanel: React.FC<GitPanelProps> = ({
     }
   };
 
+  const handleAddLatexGitignore = async () => {
+    try {
+      setGitignoreContent(
+        await invoke<string>("git_generate_latex_gitignore_cmd", {
+          content: gitignoreContent,
+        }),
+      );
+    } catch (err) {
+      console.error("Failed to generate .gitignore:", err);
+    }
+  };
+
   const handleSaveGitignore = async () => {
     if (!repoInfo) return;
     try {
@@ -545,8 +847,16 @@ export const GitPanel: React.FC<GitPanelProps> = ({
       await invoke("git_create_stash_cmd", {
         repoPath: repoInfo.path,
         message: stashMessage || null,
+        settings: {
+          include_untracked: stashIncludeUntracked,
+          keep_index: stashKeepIndex,
+          paths: stashSelectedOnly
+            ? [...selectedUnstaged, ...selectedStaged]
+            : [],
+        },
       });
       setStashMessage("");
+      setStashSelectedOnly(false);
       setStashModalOpen(false);
       await refreshStatus(repoInfo.path);
       await loadStashes();
@@ -556,6 +866,20 @@ export const GitPanel: React.FC<GitPanelProps> = ({
     }
   };
 
+  const handlePreviewStash = async (index: number) => {
+    if (!repoInfo) return;
+    try {
+      const diffs = await invoke<StructuredDiff[]>("git_get_stash_diff_cmd", {
+        repoPath: repoInfo.path,
+        index,
+      });
+      setStashPreview({ index, diffs, selected: 0 });
+    } catch (err) {
+      console.error("Failed to load stash diff:", err);
+      setError("Failed to load stash diff: " + String(err));
+    }
+  };
+
   const handleApplyStash = async (index: number) => {
     if (!repoInfo) return;
     try {
@@ -684,6 +1008,7 @@ export const GitPanel: React.FC<GitPanelProps> = ({
         repoPath: repoInfo.path,
         limit: 200,
         all: true,
+        verifySignatures,
       });
       setCommits(log);
     } catch (err) {
@@ -740,6 +1065,80 @@ export const GitPanel: React.FC<GitPanelProps> = ({
     }
   };
 
+  // Move the current branch; "HEAD~1" with "soft" undoes the last commit
+  const handleResetToCommit = async (
+    commitId: string,
+    mode: "soft" | "mixed" | "hard",
+  ) => {
+    if (!repoInfo) return;
+    const reset = (force: boolean) =>
+      invoke<string>("git_reset_to_commit_cmd", {
+        repoPath: repoInfo.path,
+        commitId,
+        mode,
+        force,
+      });
+    try {
+      try {
+        await reset(false);
+      } catch (err) {
+        // Hard reset refused because of uncommitted changes
+        if (
+          !String(err).includes("uncommitted") ||
+          !window.confirm(`${String(err)}\n\nDiscard them and reset anyway?`)
+        ) {
+          throw err;
+        }
+        await reset(true);
+      }
+      await refreshStatus(repoInfo.path);
+      await loadCommitHistory();
+    } catch (err) {
+      console.error("Failed to reset:", err);
+      setError("Failed to reset: " + String(err));
+    }
+  };
+
+  // Write a snapshot of the commit's files, without history
+  const handleArchiveCommit = async (commit: GitCommitInfo) => {
+    if (!repoInfo) return;
+    const repoName = repoInfo.path.split(/[\\/]/).filter(Boolean).pop();
+    const destPath = await import("@tauri-apps/plugin-dialog").then(
+      ({ save }) =>
+        save({
+          defaultPath: `${repoName}-${commit.short_id}.zip`,
+          filters: [
+            { name: "Zip Archive", extensions: ["zip"] },
+            { name: "Tar Archive", extensions: ["tar", "tar.gz", "tgz"] },
+          ],
+        }),
+    );
+    if (!destPath) return;
+
+    const lower = destPath.toLowerCase();
+    const format =
+      lower.endsWith(".tar.gz") || lower.endsWith(".tgz")
+        ? "tar_gz"
+        : lower.endsWith(".tar")
+          ? "tar"
+          : "zip";
+    try {
+      const count = await invoke<number>("git_archive_commit_cmd", {
+        repoPath: repoInfo.path,
+        commitId: commit.id,
+        destPath,
+        format,
+      });
+      notifications.show({
+        title: "Snapshot Exported",
+        message: `${count} files written to ${destPath}`,
+        color: "green",
+      });
+    } catch (err) {
+      setError("Failed to export snapshot: " + String(err));
+    }
+  };
+
   // ============================================================================
   // Tags Handlers
   // ============================================================================
@@ -789,6 +1188,58 @@ export const GitPanel: React.FC<GitPanelProps> = ({
     }
   };
 
+  const handlePushTag = async (name: string) => {
+    if (!repoInfo) return;
+    const remote = remotes.length > 0 ? remotes[0].name : "origin";
+    try {
+      await invoke("git_push_tag_cmd", {
+        repoPath: repoInfo.path,
+        remote,
+        name,
+      });
+      notifications.show({
+        title: "Tag pushed",
+        message: `${name} is now on ${remote}`,
+        color: "green",
+      });
+    } catch (err) {
+      console.error("Failed to push tag:", err);
+      setError("Failed to push tag: " + String(err));
+    }
+  };
+
+  const handleDeleteRemoteTag = async (name: string) => {
+    if (!repoInfo) return;
+    const remote = remotes.length > 0 ? remotes[0].name : "origin";
+    if (!window.confirm(`Delete the tag ${name} from ${remote}?`)) return;
+    try {
+      await invoke("git_delete_remote_tag_cmd", {
+        repoPath: repoInfo.path,
+        remote,
+        name,
+      });
+      notifications.show({
+        title: "Remote tag deleted",
+        message: `${name} was removed from ${remote}`,
+        color: "green",
+      });
+    } catch (err) {
+      console.error("Failed to delete remote tag:", err);
+      setError("Failed to delete remote tag: " + String(err));
+    }
+  };
+
+  const handleCheckoutTag = async (name: string) => {
+    if (!repoInfo) return;
+    try {
+      await invoke("git_checkout_tag_cmd", { repoPath: repoInfo.path, name });
+      await refreshStatus(repoInfo.path);
+    } catch (err) {
+      console.error("Failed to check out tag:", err);
+      setError(String(err));
+    }
+  };
+
   useEffect(() => {
     if (showTags && repoInfo) {
       loadTags();
@@ -799,7 +1250,126 @@ export const GitPanel: React.FC<GitPanelProps> = ({
     if (showHistory && repoInfo) {
       loadCommitHistory();
     }
-  }, [showHistory, repoInfo]);
+  }, [showHistory, repoInfo, verifySignatures]);
+
+  useEffect(() => {
+    if (!repoInfo) return;
+    invoke<{ sign_commits: boolean }>("git_get_signing_config_cmd", {
+      repoPath: repoInfo.path,
+    })
+      .then((config) => setSignCommits(config.sign_commits))
+      .catch((err) => console.error("Failed to load signing config:", err));
+  }, [repoInfo?.path]);
+
+  useEffect(() => {
+    if (!repoInfo) return;
+    invoke<SnapshotSettings>("git_get_snapshot_settings_cmd", {
+      repoPath: repoInfo.path,
+    })
+      .then(setSnapshotSettings)
+      .catch((err) => console.error("Failed to load snapshot settings:", err));
+  }, [repoInfo?.path]);
+
+  const toggleSnapshots = async () => {
+    if (!repoInfo || !snapshotSettings) return;
+    const settings = { ...snapshotSettings, enabled: !snapshotSettings.enabled };
+    try {
+      await invoke("git_set_snapshot_settings_cmd", {
+        repoPath: repoInfo.path,
+        settings,
+      });
+      setSnapshotSettings(settings);
+      if (settings.enabled) {
+        notifications.show({
+          title: "Compile snapshots on",
+          message: `Every successful compile is saved to the ${settings.branch} branch.`,
+          color: "blue",
+        });
+      }
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
+  useEffect(() => {
+    if (!repoInfo) return;
+    invoke<string | null>("git_get_commit_template_cmd", {
+      repoPath: repoInfo.path,
+    })
+      .then((template) => {
+        setCommitTemplate(template);
+        if (template) setCommitMessage((msg) => (msg.trim() ? msg : template));
+      })
+      .catch((err) => console.error("Failed to load commit template:", err));
+  }, [repoInfo?.path]);
+
+  const validateCommitMessage = useDebouncedCallback(
+    async (path: string, message: string) => {
+      try {
+        setCommitIssues(
+          await invoke<CommitMessageIssue[]>(
+            "git_validate_commit_message_cmd",
+            { repoPath: path, message },
+          ),
+        );
+      } catch (err) {
+        console.error("Failed to validate commit message:", err);
+      }
+    },
+    300,
+  );
+
+  useEffect(() => {
+    if (!repoInfo) return;
+    if (commitMessage.trim() && commitMessage !== commitTemplate) {
+      validateCommitMessage(repoInfo.path, commitMessage);
+    } else {
+      validateCommitMessage.cancel();
+      setCommitIssues([]);
+    }
+  }, [commitMessage, commitTemplate, repoInfo?.path]);
+
+  const openCommitRules = async () => {
+    if (!repoInfo) return;
+    try {
+      setCommitRules(
+        await invoke<CommitMessageRules>("git_get_commit_rules_cmd", {
+          repoPath: repoInfo.path,
+        }),
+      );
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
+  const handleSaveCommitRules = async () => {
+    if (!repoInfo || !commitRules) return;
+    try {
+      await invoke("git_set_commit_rules_cmd", {
+        repoPath: repoInfo.path,
+        rules: commitRules,
+      });
+      setCommitRules(null);
+      if (commitMessage.trim()) {
+        validateCommitMessage(repoInfo.path, commitMessage);
+      }
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
+  const handleSignToggle = async (checked: boolean) => {
+    if (!repoInfo) return;
+    try {
+      await invoke("git_set_signing_enabled_cmd", {
+        repoPath: repoInfo.path,
+        enabled: checked,
+      });
+      setSignCommits(checked);
+    } catch (err) {
+      setError(String(err));
+    }
+  };
 
   const handleStageFile = async (file: GitFileStatus) => {
     if (!repoInfo) return;
@@ -844,11 +1414,13 @@ export const GitPanel: React.FC<GitPanelProps> = ({
     if (!repoInfo || !commitMessage.trim()) return;
 
     try {
-      await invoke("git_commit_cmd", {
+      await invoke(amendMode ? "git_commit_amend_cmd" : "git_commit_cmd", {
         repoPath: repoInfo.path,
         message: commitMessage,
+        sign: signCommits,
       });
-      setCommitMessage("");
+      setCommitMessage(commitTemplate ?? "");
+      setAmendMode(false);
       await refreshStatus(repoInfo.path);
       await loadCommitHistory();
     } catch (err) {
@@ -1015,7 +1587,16 @@ export const GitPanel: React.FC<GitPanelProps> = ({
                   },
                 }}
               >
-                {repoInfo.branch || "HEAD"}
+                {repoInfo.detached
+                  ? `Detached at ${
+                      repoInfo.head_tags[0] ??
+                      repoInfo.head_commit?.substring(0, 7) ??
+                      "HEAD"
+                    }`
+                  : repoInfo.branch || "HEAD"}
+                {repoInfo.upstream && (repoInfo.ahead || repoInfo.behind)
+                  ? ` ↑${repoInfo.ahead ?? 0} ↓${repoInfo.behind ?? 0}`
+                  : null}
               </Button>
             </Menu.Target>
 
@@ -1035,7 +1616,9 @@ export const GitPanel: React.FC<GitPanelProps> = ({
                           <Box w={12} />
                         )
                       }
-                      onClick={() => handleSwitchBranch(branch.name)}
+                      onClick={() =>
+                        handleSwitchBranch(branch.name, branch.is_remote)
+                      }
                       rightSection={
                         !branch.is_head &&
                         !branch.is_remote && (
@@ -1086,6 +1669,43 @@ export const GitPanel: React.FC<GitPanelProps> = ({
               >
                 Merge Branch...
               </Menu.Item>
+              <Menu.Item
+                leftSection={<FontAwesomeIcon icon={faHistory} />}
+                onClick={() => setReflogOpen(true)}
+              >
+                Recover Lost Commits...
+              </Menu.Item>
+              <Menu.Item
+                leftSection={<FontAwesomeIcon icon={faFolderPlus} />}
+                onClick={() => setWorktreesOpen(true)}
+              >
+                Worktrees...
+              </Menu.Item>
+              <Menu.Item
+                leftSection={<FontAwesomeIcon icon={faChartColumn} />}
+                onClick={() => setStatsOpen(true)}
+              >
+                Statistics...
+              </Menu.Item>
+              <Menu.Item
+                leftSection={<FontAwesomeIcon icon={faPencilAlt} />}
+                onClick={openCommitRules}
+              >
+                Commit Message Rules...
+              </Menu.Item>
+              <Menu.Item
+                leftSection={
+                  snapshotSettings?.enabled ? (
+                    <FontAwesomeIcon icon={faCheck} />
+                  ) : (
+                    <Box w={12} />
+                  )
+                }
+                onClick={toggleSnapshots}
+                disabled={!snapshotSettings}
+              >
+                Snapshot on Compile
+              </Menu.Item>
             </Menu.Dropdown>
           </Menu>
           {repoInfo.is_dirty && (
@@ -1113,6 +1733,14 @@ export const GitPanel: React.FC<GitPanelProps> = ({
               >
                 Fetch
               </Menu.Item>
+              <Menu.Item
+                leftSection={
+                  <FontAwesomeIcon icon={faSync} color="gray.4" size="xs" />
+                }
+                onClick={handleFetchAll}
+              >
+                Fetch All & Prune
+              </Menu.Item>
               <Menu.Item
                 leftSection={
                   <FontAwesomeIcon
@@ -1121,18 +1749,46 @@ export const GitPanel: React.FC<GitPanelProps> = ({
                     size="xs"
                   />
                 }
-                onClick={handlePull}
+                onClick={() => handlePull("merge")}
               >
                 Pull
               </Menu.Item>
+              <Menu.Item
+                leftSection={
+                  <FontAwesomeIcon
+                    icon={faArrowDown}
+                    color="gray.4"
+                    size="xs"
+                  />
+                }
+                onClick={() => handlePull("rebase")}
+              >
+                Pull (Rebase)
+              </Menu.Item>
               <Menu.Item
                 leftSection={
                   <FontAwesomeIcon icon={faArrowUp} color="gray.4" size="xs" />
                 }
-                onClick={handlePush}
+                onClick={() => handlePush()}
               >
                 Push
               </Menu.Item>
+              <Menu.Item
+                leftSection={
+                  <FontAwesomeIcon icon={faArrowUp} color="gray.4" size="xs" />
+                }
+                onClick={() => handlePush({ tags: true })}
+              >
+                Push with Tags
+              </Menu.Item>
+              <Menu.Item
+                leftSection={
+                  <FontAwesomeIcon icon={faArrowUp} color="red" size="xs" />
+                }
+                onClick={() => handlePush({ force_with_lease: true })}
+              >
+                Force Push (Lease)
+              </Menu.Item>
               <Menu.Divider />
               <Menu.Item
                 leftSection={
@@ -1158,6 +1814,23 @@ export const GitPanel: React.FC<GitPanelProps> = ({
         </Group>
       </Group>
 
+      {fetchProgress && fetchProgress.total_objects > 0 && (
+        <Stack gap={2}>
+          <Text size="xs" c="dimmed">
+            Fetching {fetchProgress.remote}: {fetchProgress.received_objects}/
+            {fetchProgress.total_objects} objects (
+            {(fetchProgress.received_bytes / 1024).toFixed(0)} KB)
+          </Text>
+          <Progress
+            size="xs"
+            value={
+              (fetchProgress.received_objects / fetchProgress.total_objects) *
+              100
+            }
+          />
+        </Stack>
+      )}
+
       {/* Error */}
       {error && (
         <Text size="xs" c="red">
@@ -1174,6 +1847,15 @@ export const GitPanel: React.FC<GitPanelProps> = ({
         maxRows={4}
         size="xs"
       />
+      {commitIssues.map((issue, i) => (
+        <Text
+          key={i}
+          size="xs"
+          c={issue.severity === "error" ? "red" : "yellow"}
+        >
+          Line {issue.line}: {issue.message}
+        </Text>
+      ))}
 
       <Group gap="xs">
         <Checkbox
@@ -1183,6 +1865,14 @@ export const GitPanel: React.FC<GitPanelProps> = ({
           onChange={(e) => handleAmendToggle(e.currentTarget.checked)}
           disabled={commits.length === 0}
         />
+        <Tooltip label="Sign commits and tags with your key (user.signingkey)">
+          <Checkbox
+            size="xs"
+            label="Sign"
+            checked={signCommits}
+            onChange={(e) => handleSignToggle(e.currentTarget.checked)}
+          />
+        </Tooltip>
         <Tooltip label="Stash changes">
           <Button
             size="xs"
@@ -1193,12 +1883,26 @@ export const GitPanel: React.FC<GitPanelProps> = ({
             Stash
           </Button>
         </Tooltip>
+        <Tooltip label="Undo the last commit, keeping its changes staged">
+          <Button
+            size="xs"
+            variant="light"
+            color="gray"
+            onClick={() => handleResetToCommit("HEAD~1", "soft")}
+            disabled={commits.length < 2}
+          >
+            Undo Commit
+          </Button>
+        </Tooltip>
       </Group>
 
       <Button
         size="xs"
         disabled={
-          !commitMessage.trim() || (stagedFiles.length === 0 && !amendMode)
+          !commitMessage.trim() ||
+          commitMessage === commitTemplate ||
+          commitIssues.some((issue) => issue.severity === "error") ||
+          (stagedFiles.length === 0 && !amendMode)
         }
         onClick={handleCommit}
         leftSection={<FontAwesomeIcon icon={faCheck} />}
@@ -1247,6 +1951,31 @@ export const GitPanel: React.FC<GitPanelProps> = ({
               )}
             </Group>
 
+            {/* Unfinished merge/rebase/cherry-pick/revert */}
+            {operationState && operationState.operation !== "clean" && (
+              <Group gap="xs" mb="xs" wrap="nowrap">
+                <Text size="xs" c="orange" style={{ flex: 1 }}>
+                  {OPERATION_LABELS[operationState.operation]} in progress
+                </Text>
+                <Button
+                  size="compact-xs"
+                  color="green"
+                  disabled={!operationState.can_continue}
+                  onClick={handleContinueOperation}
+                >
+                  Continue
+                </Button>
+                <Button
+                  size="compact-xs"
+                  color="red"
+                  variant="light"
+                  onClick={handleAbortOperation}
+                >
+                  Abort
+                </Button>
+              </Group>
+            )}
+
             {/* Conflict Warning */}
             {conflicts.length > 0 && (
               <Button
@@ -1523,6 +2252,25 @@ export const GitPanel: React.FC<GitPanelProps> = ({
                       <FontAwesomeIcon icon={faList} size="xs" />
                     </ActionIcon>
                   </Tooltip>
+                  <Tooltip
+                    label={
+                      verifySignatures
+                        ? "Stop verifying signatures"
+                        : "Verify commit signatures"
+                    }
+                  >
+                    <ActionIcon
+                      size="xs"
+                      variant="subtle"
+                      color={verifySignatures ? "green" : "gray.4"}
+                      onClick={(e) => {
+                        e.stopPropagation();
+                        setVerifySignatures(!verifySignatures);
+                      }}
+                    >
+                      <FontAwesomeIcon icon={faCheck} size="xs" />
+                    </ActionIcon>
+                  </Tooltip>
                 </Group>
               )}
             </Group>
@@ -1539,6 +2287,9 @@ export const GitPanel: React.FC<GitPanelProps> = ({
                     onCheckoutCommit={handleCheckoutCommit}
                     onCherryPick={handleCherryPick}
                     onRevertCommit={handleRevertCommit}
+                    onResetToCommit={handleResetToCommit}
+                    onArchiveCommit={handleArchiveCommit}
+                    onBrowseCommit={setBrowsedCommit}
                   />
                 ) : (
                   <Stack gap={2}>
@@ -1558,6 +2309,28 @@ export const GitPanel: React.FC<GitPanelProps> = ({
                           >
                             {commit.message.split("\n")[0]}
                           </Text>
+                          {commit.signature && (
+                            <Tooltip label={signatureLabel(commit.signature)}>
+                              <Badge
+                                size="xs"
+                                variant="light"
+                                color={
+                                  commit.signature.status === "good"
+                                    ? "green"
+                                    : commit.signature.status === "bad"
+                                      ? "red"
+                                      : "gray"
+                                }
+                                style={{ flexShrink: 0 }}
+                              >
+                                {commit.signature.status === "good"
+                                  ? "Verified"
+                                  : commit.signature.status === "bad"
+                                    ? "Bad Sig"
+                                    : "Signed"}
+                              </Badge>
+                            </Tooltip>
+                          )}
                         </Group>
                         <Text size="xs" c="dimmed">
                           {commit.author_name} •{" "}
@@ -1607,6 +2380,116 @@ export const GitPanel: React.FC<GitPanelProps> = ({
           </Group>
         </Stack>
       </Modal>
+      <Modal
+        opened={reflogOpen}
+        onClose={() => setReflogOpen(false)}
+        title="Reflog"
+        size="lg"
+      >
+        {repoInfo && (
+          <ReflogViewer
+            repoPath={repoInfo.path}
+            onRecovered={(name) => {
+              setReflogOpen(false);
+              notifications.show({
+                title: "Commit Recovered",
+                message: `Created branch ${name}`,
+                color: "green",
+              });
+              refreshStatus(repoInfo.path);
+            }}
+          />
+        )}
+      </Modal>
+      <Modal
+        opened={commitRules !== null}
+        onClose={() => setCommitRules(null)}
+        title="Commit Message Rules"
+        size="sm"
+      >
+        {commitRules && (
+          <Stack gap="md">
+            <NumberInput
+              size="xs"
+              label="Summary length limit"
+              description="Longer summaries get a warning; 0 for no limit"
+              min={0}
+              value={commitRules.max_summary_length}
+              onChange={(value) =>
+                setCommitRules({
+                  ...commitRules,
+                  max_summary_length: Number(value) || 0,
+                })
+              }
+            />
+            <Checkbox
+              size="xs"
+              label="Require a blank line after the summary"
+              checked={commitRules.require_blank_line}
+              onChange={(e) =>
+                setCommitRules({
+                  ...commitRules,
+                  require_blank_line: e.currentTarget.checked,
+                })
+              }
+            />
+            <TagsInput
+              size="xs"
+              label="Conventional commit types"
+              description="Summaries must start with one of these, e.g. 'fix(algebra): ...'. Leave empty to allow any summary."
+              placeholder="feat, fix, docs..."
+              value={commitRules.conventional_types}
+              onChange={(types) =>
+                setCommitRules({ ...commitRules, conventional_types: types })
+              }
+            />
+            <Group justify="flex-end">
+              <Button variant="default" onClick={() => setCommitRules(null)}>
+                Cancel
+              </Button>
+              <Button onClick={handleSaveCommitRules}>Save</Button>
+            </Group>
+          </Stack>
+        )}
+      </Modal>
+      <Modal
+        opened={browsedCommit !== null}
+        onClose={() => setBrowsedCommit(null)}
+        title={
+          browsedCommit
+            ? `Files at ${browsedCommit.short_id}: ${browsedCommit.message.split("\n")[0]}`
+            : ""
+        }
+        size="xl"
+      >
+        {repoInfo && browsedCommit && (
+          <CommitTreeBrowser
+            repoPath={repoInfo.path}
+            commitId={browsedCommit.id}
+          />
+        )}
+      </Modal>
+      <Modal
+        opened={statsOpen}
+        onClose={() => setStatsOpen(false)}
+        title="Repository Statistics"
+        size="lg"
+      >
+        {repoInfo && <RepoStats repoPath={repoInfo.path} />}
+      </Modal>
+      <Modal
+        opened={worktreesOpen}
+        onClose={() => setWorktreesOpen(false)}
+        title="Worktrees"
+        size="lg"
+      >
+        {repoInfo && (
+          <WorktreeManager
+            repoPath={repoInfo.path}
+            onChanged={() => refreshStatus(repoInfo.path)}
+          />
+        )}
+      </Modal>
       <Modal
         opened={gitignoreOpen}
         onClose={() => setGitignoreOpen(false)}
@@ -1625,11 +2508,21 @@ export const GitPanel: React.FC<GitPanelProps> = ({
             maxRows={20}
             autosize
           />
-          <Group justify="flex-end">
-            <Button variant="default" onClick={() => setGitignoreOpen(false)}>
-              Cancel
-            </Button>
-            <Button onClick={handleSaveGitignore}>Save</Button>
+          <Group justify="space-between">
+            <Tooltip label="Add the usual LaTeX build files, keeping your own entries">
+              <Button variant="light" onClick={handleAddLatexGitignore}>
+                Add LaTeX Defaults
+              </Button>
+            </Tooltip>
+            <Group gap="xs">
+              <Button
+                variant="default"
+                onClick={() => setGitignoreOpen(false)}
+              >
+                Cancel
+              </Button>
+              <Button onClick={handleSaveGitignore}>Save</Button>
+            </Group>
           </Group>
         </Stack>
       </Modal>
@@ -1736,6 +2629,26 @@ export const GitPanel: React.FC<GitPanelProps> = ({
             value={stashMessage}
             onChange={(e) => setStashMessage(e.currentTarget.value)}
           />
+          <Checkbox
+            size="xs"
+            label="Only selected files"
+            checked={stashSelectedOnly}
+            onChange={(e) => setStashSelectedOnly(e.currentTarget.checked)}
+            disabled={selectedUnstaged.size + selectedStaged.size === 0}
+          />
+          <Checkbox
+            size="xs"
+            label="Include untracked files"
+            checked={stashIncludeUntracked && !stashSelectedOnly}
+            onChange={(e) => setStashIncludeUntracked(e.currentTarget.checked)}
+            disabled={stashSelectedOnly}
+          />
+          <Checkbox
+            size="xs"
+            label="Keep staged changes"
+            checked={stashKeepIndex}
+            onChange={(e) => setStashKeepIndex(e.currentTarget.checked)}
+          />
           <Group justify="flex-end">
             <Button variant="default" onClick={() => setStashModalOpen(false)}>
               Cancel
@@ -1745,6 +2658,48 @@ export const GitPanel: React.FC<GitPanelProps> = ({
         </Stack>
       </Modal>
 
+      <Modal
+        opened={stashPreview !== null}
+        onClose={() => setStashPreview(null)}
+        title={`stash@{${stashPreview?.index ?? 0}}`}
+        size="xl"
+      >
+        {stashPreview && stashPreview.diffs.length > 0 && repoInfo && (
+          <Stack gap="xs">
+            <Select
+              size="xs"
+              data={stashPreview.diffs.map((diff, i) => ({
+                value: String(i),
+                label: `${diff.file_path} (+${diff.stats.additions} -${diff.stats.deletions})`,
+              }))}
+              value={String(stashPreview.selected)}
+              onChange={(value) =>
+                value !== null &&
+                setStashPreview({ ...stashPreview, selected: Number(value) })
+              }
+              allowDeselect={false}
+            />
+            <Box h={500}>
+              <DiffViewer
+                diff={stashPreview.diffs[stashPreview.selected]}
+                repoPath={repoInfo.path}
+              />
+            </Box>
+            <Group justify="flex-end">
+              <Button
+                size="xs"
+                onClick={() => {
+                  handleApplyStash(stashPreview.index);
+                  setStashPreview(null);
+                }}
+              >
+                Apply
+              </Button>
+            </Group>
+          </Stack>
+        )}
+      </Modal>
+
       {/* Stash List Section - Collapsible */}
       {stashes.length > 0 && (
         <Box mt="xs">
@@ -1771,6 +2726,15 @@ export const GitPanel: React.FC<GitPanelProps> = ({
                       {stash.message}
                     </Text>
                     <Group gap={2}>
+                      <Tooltip label="Preview">
+                        <ActionIcon
+                          size="xs"
+                          variant="subtle"
+                          onClick={() => handlePreviewStash(stash.index)}
+                        >
+                          <FontAwesomeIcon icon={faEye} />
+                        </ActionIcon>
+                      </Tooltip>
                       <Tooltip label="Apply">
                         <ActionIcon
                           size="xs"
@@ -1852,6 +2816,34 @@ export const GitPanel: React.FC<GitPanelProps> = ({
                       <Badge size="xs" variant="light" color="gray">
                         {tag.commit_id.slice(0, 7)}
                       </Badge>
+                      <Tooltip label="Check Out (detached)">
+                        <ActionIcon
+                          size="xs"
+                          variant="subtle"
+                          onClick={() => handleCheckoutTag(tag.name)}
+                        >
+                          <FontAwesomeIcon icon={faCheck} />
+                        </ActionIcon>
+                      </Tooltip>
+                      <Tooltip label="Push to Remote">
+                        <ActionIcon
+                          size="xs"
+                          variant="subtle"
+                          onClick={() => handlePushTag(tag.name)}
+                        >
+                          <FontAwesomeIcon icon={faCloudArrowUp} />
+                        </ActionIcon>
+                      </Tooltip>
+                      <Tooltip label="Delete from Remote">
+                        <ActionIcon
+                          size="xs"
+                          variant="subtle"
+                          color="orange"
+                          onClick={() => handleDeleteRemoteTag(tag.name)}
+                        >
+                          <FontAwesomeIcon icon={faCloudArrowDown} />
+                        </ActionIcon>
+                      </Tooltip>
                       <Tooltip label="Delete">
                         <ActionIcon
                           size="xs"
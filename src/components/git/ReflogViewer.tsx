@@ -0,0 +1,151 @@
+import React, { useEffect, useState } from "react";
+import {
+  Button,
+  Group,
+  Text,
+  ScrollArea,
+  Stack,
+  Paper,
+  Badge,
+  TextInput,
+  Loader,
+} from "@mantine/core";
+import { invoke } from "@tauri-apps/api/core";
+
+export interface ReflogEntry {
+  index: number;
+  old_id: string;
+  new_id: string;
+  message: string;
+  committer_name: string;
+  timestamp: number;
+  summary: string | null;
+}
+
+interface ReflogViewerProps {
+  repoPath: string;
+  onRecovered: (branchName: string) => void;
+}
+
+/**
+ * Lists where HEAD has been so commits lost by a reset or rebase can be
+ * recovered onto a new branch.
+ */
+export const ReflogViewer: React.FC<ReflogViewerProps> = ({
+  repoPath,
+  onRecovered,
+}) => {
+  const [entries, setEntries] = useState<ReflogEntry[]>([]);
+  const [loading, setLoading] = useState(true);
+  const [error, setError] = useState<string | null>(null);
+  // Entry being recovered and the branch name typed for it
+  const [recovering, setRecovering] = useState<number | null>(null);
+  const [branchName, setBranchName] = useState("");
+
+  useEffect(() => {
+    setLoading(true);
+    invoke<ReflogEntry[]>("git_get_reflog_cmd", { repoPath, limit: 200 })
+      .then(setEntries)
+      .catch((err) => setError(String(err)))
+      .finally(() => setLoading(false));
+  }, [repoPath]);
+
+  const handleCreateBranch = async (entry: ReflogEntry) => {
+    const name = branchName.trim();
+    if (!name) return;
+    try {
+      await invoke("git_create_branch_at_cmd", {
+        repoPath,
+        commitId: entry.new_id,
+        name,
+      });
+      setRecovering(null);
+      setBranchName("");
+      onRecovered(name);
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
+  if (loading) return <Loader size="sm" />;
+
+  return (
+    <Stack gap="xs">
+      {error && (
+        <Text size="xs" c="red">
+          {error}
+        </Text>
+      )}
+      <ScrollArea h={400} type="auto" offsetScrollbars>
+        <Stack gap={4}>
+          {entries.map((entry) => (
+            <Paper key={entry.index} p={6} withBorder>
+              <Group gap="xs" wrap="nowrap" justify="space-between">
+                <Stack gap={0} style={{ minWidth: 0 }}>
+                  <Group gap="xs" wrap="nowrap">
+                    <Badge size="xs" variant="light" color="gray">
+                      HEAD@{"{"}
+                      {entry.index}
+                      {"}"}
+                    </Badge>
+                    <Text size="xs" ff="monospace">
+                      {entry.new_id.substring(0, 7)}
+                    </Text>
+                    <Text size="xs" truncate>
+                      {entry.summary ?? "(commit no longer exists)"}
+                    </Text>
+                  </Group>
+                  <Text size="xs" c="dimmed" truncate>
+                    {entry.message} ·{" "}
+                    {new Date(entry.timestamp * 1000).toLocaleString()}
+                  </Text>
+                </Stack>
+                {entry.summary !== null && recovering !== entry.index && (
+                  <Button
+                    size="compact-xs"
+                    variant="light"
+                    onClick={() => {
+                      setRecovering(entry.index);
+                      setBranchName("");
+                    }}
+                  >
+                    Create Branch
+                  </Button>
+                )}
+              </Group>
+              {recovering === entry.index && (
+                <Group gap="xs" mt={4}>
+                  <TextInput
+                    size="xs"
+                    placeholder="recovered-work"
+                    value={branchName}
+                    onChange={(e) => setBranchName(e.currentTarget.value)}
+                    onKeyDown={(e) => {
+                      if (e.key === "Enter") handleCreateBranch(entry);
+                    }}
+                    style={{ flex: 1 }}
+                    data-autofocus
+                  />
+                  <Button
+                    size="compact-xs"
+                    disabled={!branchName.trim()}
+                    onClick={() => handleCreateBranch(entry)}
+                  >
+                    Create
+                  </Button>
+                  <Button
+                    size="compact-xs"
+                    variant="default"
+                    onClick={() => setRecovering(null)}
+                  >
+                    Cancel
+                  </Button>
+                </Group>
+              )}
+            </Paper>
+          ))}
+        </Stack>
+      </ScrollArea>
+    </Stack>
+  );
+};
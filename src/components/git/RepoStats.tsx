@@ -0,0 +1,156 @@
+import React, { useEffect, useState } from "react";
+import {
+  Box,
+  Group,
+  Loader,
+  Progress,
+  SegmentedControl,
+  Stack,
+  Text,
+  Tooltip,
+} from "@mantine/core";
+import { invoke } from "@tauri-apps/api/core";
+
+type StatsInterval = "day" | "week" | "month";
+
+export interface RepoStatsData {
+  total_commits: number;
+  authors: { name: string; email: string; commits: number }[];
+  activity: { start: number; commits: number }[];
+  top_files: { path: string; commits: number }[];
+}
+
+interface RepoStatsProps {
+  repoPath: string;
+}
+
+const bucketLabel = (start: number, interval: StatsInterval) => {
+  const date = new Date(start * 1000);
+  return interval === "month"
+    ? date.toLocaleDateString(undefined, {
+        year: "numeric",
+        month: "short",
+        timeZone: "UTC",
+      })
+    : date.toLocaleDateString(undefined, { timeZone: "UTC" });
+};
+
+/**
+ * Commit counts per author and over time, and the files changed most
+ * often, for the history of the current branch.
+ */
+export const RepoStats: React.FC<RepoStatsProps> = ({ repoPath }) => {
+  const [interval, setStatsInterval] = useState<StatsInterval>("week");
+  const [stats, setStats] = useState<RepoStatsData | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    setStats(null);
+    setError(null);
+    invoke<RepoStatsData>("git_repo_stats_cmd", {
+      repoPath,
+      settings: { interval },
+    })
+      .then(setStats)
+      .catch((err) => setError(String(err)));
+  }, [repoPath, interval]);
+
+  if (error) {
+    return (
+      <Text size="xs" c="red">
+        {error}
+      </Text>
+    );
+  }
+  if (!stats) return <Loader size="sm" />;
+
+  const maxActivity = Math.max(1, ...stats.activity.map((b) => b.commits));
+  const maxFile = Math.max(1, ...stats.top_files.map((f) => f.commits));
+
+  return (
+    <Stack gap="md">
+      <Text size="sm">
+        {stats.total_commits} commits by {stats.authors.length} authors
+      </Text>
+
+      <Stack gap={4}>
+        <Group justify="space-between">
+          <Text size="xs" fw={500} tt="uppercase" c="dimmed">
+            Activity
+          </Text>
+          <SegmentedControl
+            size="xs"
+            value={interval}
+            onChange={(v) => setStatsInterval(v as StatsInterval)}
+            data={[
+              { value: "day", label: "Daily" },
+              { value: "week", label: "Weekly" },
+              { value: "month", label: "Monthly" },
+            ]}
+          />
+        </Group>
+        <Group gap={1} align="flex-end" h={80} wrap="nowrap">
+          {stats.activity.map((bucket) => (
+            <Tooltip
+              key={bucket.start}
+              label={`${bucketLabel(bucket.start, interval)}: ${bucket.commits}`}
+            >
+              <Box
+                style={{
+                  flex: 1,
+                  minWidth: 1,
+                  height: `${(bucket.commits / maxActivity) * 100}%`,
+                  minHeight: 1,
+                  backgroundColor: "var(--mantine-color-blue-filled)",
+                }}
+              />
+            </Tooltip>
+          ))}
+        </Group>
+      </Stack>
+
+      <Stack gap={4}>
+        <Text size="xs" fw={500} tt="uppercase" c="dimmed">
+          Authors
+        </Text>
+        {stats.authors.map((author) => (
+          <Group key={author.email} gap="xs" wrap="nowrap">
+            <Tooltip label={author.email}>
+              <Text size="xs" w={140} truncate>
+                {author.name}
+              </Text>
+            </Tooltip>
+            <Progress
+              value={(author.commits / stats.total_commits) * 100}
+              style={{ flex: 1 }}
+            />
+            <Text size="xs" c="dimmed" w={40} ta="right">
+              {author.commits}
+            </Text>
+          </Group>
+        ))}
+      </Stack>
+
+      <Stack gap={4}>
+        <Text size="xs" fw={500} tt="uppercase" c="dimmed">
+          Most Changed Files
+        </Text>
+        {stats.top_files.map((file) => (
+          <Group key={file.path} gap="xs" wrap="nowrap">
+            <Text size="xs" w={220} truncate>
+              {file.path}
+            </Text>
+            <Progress
+              value={(file.commits / maxFile) * 100}
+              color="teal"
+              style={{ flex: 1 }}
+            />
+            <Text size="xs" c="dimmed" w={40} ta="right">
+              {file.commits}
+            </Text>
+          </Group>
+        ))}
+      </Stack>
+    </Stack>
+  );
+};
@@ -0,0 +1,274 @@
+import React, { useEffect, useState } from "react";
+import {
+  ActionIcon,
+  Badge,
+  Button,
+  Checkbox,
+  Group,
+  Loader,
+  Paper,
+  Select,
+  Stack,
+  Text,
+  TextInput,
+  Tooltip,
+} from "@mantine/core";
+import { invoke } from "@tauri-apps/api/core";
+import { open } from "@tauri-apps/plugin-dialog";
+import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
+import {
+  faDatabase,
+  faFolderOpen,
+  faTrash,
+} from "@fortawesome/free-solid-svg-icons";
+import { useDatabaseStore } from "../../stores/databaseStore";
+
+export interface WorktreeInfo {
+  name: string | null;
+  path: string;
+  branch: string | null;
+  head: string | null;
+  is_main: boolean;
+  locked: boolean;
+  prunable: boolean;
+}
+
+interface WorktreeManagerProps {
+  repoPath: string;
+  onChanged: () => void;
+}
+
+const folderName = (path: string) =>
+  path.split(/[\\/]/).filter(Boolean).pop() || path;
+
+/**
+ * Lists the checkouts of the repository and adds or removes linked
+ * worktrees, so another branch can be opened as its own collection.
+ */
+export const WorktreeManager: React.FC<WorktreeManagerProps> = ({
+  repoPath,
+  onChanged,
+}) => {
+  const importFolder = useDatabaseStore((s) => s.importFolder);
+  const [worktrees, setWorktrees] = useState<WorktreeInfo[]>([]);
+  const [branches, setBranches] = useState<string[]>([]);
+  const [loading, setLoading] = useState(true);
+  const [error, setError] = useState<string | null>(null);
+  // New worktree form
+  const [branch, setBranch] = useState<string | null>(null);
+  const [newBranch, setNewBranch] = useState(false);
+  const [newBranchName, setNewBranchName] = useState("");
+  const [path, setPath] = useState("");
+
+  const load = async () => {
+    try {
+      const [list, branchList] = await Promise.all([
+        invoke<WorktreeInfo[]>("git_list_worktrees_cmd", { repoPath }),
+        invoke<{ name: string }[]>("git_list_branches_cmd", { repoPath }),
+      ]);
+      setWorktrees(list);
+      setBranches(branchList.map((b) => b.name));
+    } catch (err) {
+      setError(String(err));
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  useEffect(() => {
+    setLoading(true);
+    load();
+  }, [repoPath]);
+
+  const checkedOut = new Set(
+    worktrees.map((w) => w.branch).filter((b): b is string => b !== null),
+  );
+
+  const handleBrowse = async () => {
+    const selected = await open({ directory: true, title: "Worktree Folder" });
+    if (selected && typeof selected === "string") setPath(selected);
+  };
+
+  const handleAdd = async () => {
+    if (!branch || !path.trim()) return;
+    setError(null);
+    try {
+      await invoke("git_add_worktree_cmd", {
+        repoPath,
+        path: path.trim(),
+        branch: newBranch ? newBranchName.trim() : branch,
+        startPoint: newBranch ? branch : null,
+      });
+      setPath("");
+      setNewBranchName("");
+      await load();
+      onChanged();
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
+  const handleRemove = async (worktree: WorktreeInfo) => {
+    if (!worktree.name) return;
+    if (!window.confirm(`Remove the worktree at ${worktree.path}?`)) return;
+    setError(null);
+    try {
+      await invoke("git_remove_worktree_cmd", {
+        repoPath,
+        name: worktree.name,
+        force: false,
+      });
+    } catch (err) {
+      if (
+        !window.confirm(
+          `${String(err)}\n\nRemove it anyway? Uncommitted changes will be lost.`,
+        )
+      ) {
+        return;
+      }
+      try {
+        await invoke("git_remove_worktree_cmd", {
+          repoPath,
+          name: worktree.name,
+          force: true,
+        });
+      } catch (forceErr) {
+        setError(String(forceErr));
+        return;
+      }
+    }
+    await load();
+    onChanged();
+  };
+
+  if (loading) return <Loader size="sm" />;
+
+  return (
+    <Stack gap="sm">
+      {error && (
+        <Text size="xs" c="red">
+          {error}
+        </Text>
+      )}
+      <Stack gap={4}>
+        {worktrees.map((worktree) => (
+          <Paper key={worktree.path} p={6} withBorder>
+            <Group gap="xs" wrap="nowrap" justify="space-between">
+              <Stack gap={0} style={{ minWidth: 0 }}>
+                <Group gap="xs" wrap="nowrap">
+                  <Text size="xs" fw={500} truncate>
+                    {worktree.branch ??
+                      (worktree.head
+                        ? `detached at ${worktree.head.substring(0, 7)}`
+                        : "no commits")}
+                  </Text>
+                  {worktree.is_main && (
+                    <Badge size="xs" variant="light">
+                      main
+                    </Badge>
+                  )}
+                  {worktree.locked && (
+                    <Badge size="xs" variant="light" color="yellow">
+                      locked
+                    </Badge>
+                  )}
+                  {worktree.prunable && (
+                    <Badge size="xs" variant="light" color="red">
+                      missing
+                    </Badge>
+                  )}
+                </Group>
+                <Text size="xs" c="dimmed" truncate>
+                  {worktree.path}
+                </Text>
+              </Stack>
+              <Group gap={2} wrap="nowrap">
+                {!worktree.is_main && !worktree.prunable && (
+                  <Tooltip label="Open as Collection">
+                    <ActionIcon
+                      size="sm"
+                      variant="subtle"
+                      onClick={() =>
+                        importFolder(worktree.path, folderName(worktree.path))
+                      }
+                    >
+                      <FontAwesomeIcon icon={faDatabase} size="xs" />
+                    </ActionIcon>
+                  </Tooltip>
+                )}
+                {!worktree.is_main && (
+                  <Tooltip label="Remove Worktree">
+                    <ActionIcon
+                      size="sm"
+                      variant="subtle"
+                      color="red"
+                      onClick={() => handleRemove(worktree)}
+                    >
+                      <FontAwesomeIcon icon={faTrash} size="xs" />
+                    </ActionIcon>
+                  </Tooltip>
+                )}
+              </Group>
+            </Group>
+          </Paper>
+        ))}
+      </Stack>
+
+      <Text size="xs" fw={500} tt="uppercase" c="dimmed">
+        Add Worktree
+      </Text>
+      <Select
+        size="xs"
+        label={newBranch ? "Start From" : "Branch"}
+        placeholder="Select a branch"
+        data={branches.map((name) => ({
+          value: name,
+          label: name,
+          disabled: !newBranch && checkedOut.has(name),
+        }))}
+        value={branch}
+        onChange={setBranch}
+        searchable
+      />
+      <Checkbox
+        size="xs"
+        label="Create a new branch"
+        checked={newBranch}
+        onChange={(e) => setNewBranch(e.currentTarget.checked)}
+      />
+      {newBranch && (
+        <TextInput
+          size="xs"
+          label="New Branch"
+          placeholder="exams-2024"
+          value={newBranchName}
+          onChange={(e) => setNewBranchName(e.currentTarget.value)}
+        />
+      )}
+      <Group gap="xs" align="flex-end">
+        <TextInput
+          size="xs"
+          label="Folder"
+          placeholder="/path/to/new/folder"
+          value={path}
+          onChange={(e) => setPath(e.currentTarget.value)}
+          style={{ flex: 1 }}
+        />
+        <ActionIcon size="md" variant="default" onClick={handleBrowse}>
+          <FontAwesomeIcon icon={faFolderOpen} size="xs" />
+        </ActionIcon>
+      </Group>
+      <Group justify="flex-end">
+        <Button
+          size="xs"
+          onClick={handleAdd}
+          disabled={
+            !branch || !path.trim() || (newBranch && !newBranchName.trim())
+          }
+        >
+          Add Worktree
+        </Button>
+      </Group>
+    </Stack>
+  );
+};
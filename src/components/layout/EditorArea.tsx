@@ -84,7 +84,6 @@ interface EditorAreaProps {
   onOpenWizard: () => void;
   onCreateFromTemplate: (code: string) => void;
 
-  recentProjects?: string[];
   onOpenRecent?: (path: string) => void;
   onOpenDatabase?: () => void;
   onOpenExamGenerator?: () => void;
@@ -346,7 +345,6 @@ export const EditorArea = React.memo<EditorAreaProps>(
     onCreateEmpty,
     onOpenWizard,
     onCreateFromTemplate,
-    recentProjects = [],
     onOpenRecent,
     onOpenDatabase,
     onOpenExamGenerator,
@@ -1657,7 +1655,6 @@ export const EditorArea = React.memo<EditorAreaProps>(
               onOpenWizard={onOpenWizard}
               onCreateFromTemplate={onCreateFromTemplate}
               onOpenFile={onOpenFileDialog}
-              recentProjects={recentProjects}
               onOpenRecent={onOpenRecent!}
               onOpenDatabase={onOpenDatabase!}
               onOpenExamGenerator={onOpenExamGenerator!}
@@ -13,6 +13,8 @@ import {
   ScrollArea,
   Box,
   Badge,
+  ActionIcon,
+  Tooltip,
   useComputedColorScheme,
 } from "@mantine/core";
 import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
@@ -26,14 +28,16 @@ import {
   faFileSignature,
   faBoxOpen,
   faFolderOpen,
+  faThumbtack,
+  faTimes,
 } from "@fortawesome/free-solid-svg-icons";
 import { templates } from "../../services/templateService";
+import { useRecentProjectsStore } from "../../stores/recentProjectsStore";
 
 interface StartPageProps {
   onCreateEmpty: () => void;
   onOpenWizard: () => void;
   onCreateFromTemplate: (code: string) => void;
-  recentProjects?: string[];
   onOpenRecent?: (path: string) => void;
   onOpenDatabase: () => void;
   onOpenExamGenerator: () => void;
@@ -46,7 +50,6 @@ export const StartPage: React.FC<StartPageProps> = ({
   onCreateEmpty,
   onOpenWizard,
   onCreateFromTemplate,
-  recentProjects = [],
   onOpenRecent,
   onOpenDatabase,
   onOpenExamGenerator,
@@ -55,6 +58,9 @@ export const StartPage: React.FC<StartPageProps> = ({
   onOpenFile,
 }) => {
   const { t } = useTranslation();
+  const recentProjects = useRecentProjectsStore((state) => state.projects);
+  const setPinned = useRecentProjectsStore((state) => state.setPinned);
+  const removeRecent = useRecentProjectsStore((state) => state.remove);
   const colorScheme = useComputedColorScheme("dark", {
     getInitialValueInEffect: true,
   });
@@ -272,44 +278,78 @@ export const StartPage: React.FC<StartPageProps> = ({
               {recentProjects.length > 0 ? (
                 <Card withBorder bg="var(--mantine-color-default)" p={0}>
                   <Stack gap={0}>
-                    {recentProjects.map((path, idx) => (
-                      <UnstyledButton
-                        key={idx}
-                        onClick={() => onOpenRecent && onOpenRecent(path)}
-                        p="md"
+                    {recentProjects.map((project, idx) => (
+                      <Group
+                        key={project.path}
+                        gap="xs"
+                        pr="sm"
+                        wrap="nowrap"
                         style={{
                           borderBottom:
                             idx < recentProjects.length - 1
                               ? "1px solid var(--mantine-color-default-border)"
                               : "none",
-                          transition: "background-color 0.2s",
-                          ":hover": {
-                            backgroundColor:
-                              "var(--mantine-color-default-hover)",
-                          },
+                          opacity: project.exists ? 1 : 0.5,
                         }}
                       >
-                        <Group>
-                          <ThemeIcon color="yellow" variant="light" size="lg">
-                            <FontAwesomeIcon
-                              icon={faClock}
-                              style={{ width: 18, height: 18 }}
-                            />
-                          </ThemeIcon>
-                          <Box style={{ flex: 1, overflow: "hidden" }}>
-                            <Text size="sm" fw={500} truncate>
-                              {path.split(/[/\\]/).pop()}
-                            </Text>
-                            <Text size="xs" c="dimmed" truncate>
-                              {path}
-                            </Text>
-                          </Box>
-                          <FontAwesomeIcon
-                            icon={faChevronRight}
-                            style={{ width: 16, height: 16, color: "gray" }}
-                          />
-                        </Group>
-                      </UnstyledButton>
+                        <UnstyledButton
+                          onClick={() => onOpenRecent?.(project.path)}
+                          disabled={!project.exists}
+                          p="md"
+                          style={{ flex: 1, overflow: "hidden" }}
+                        >
+                          <Group wrap="nowrap">
+                            <ThemeIcon
+                              color={project.pinned ? "blue" : "yellow"}
+                              variant="light"
+                              size="lg"
+                            >
+                              <FontAwesomeIcon
+                                icon={project.pinned ? faThumbtack : faClock}
+                                style={{ width: 18, height: 18 }}
+                              />
+                            </ThemeIcon>
+                            <Box style={{ flex: 1, overflow: "hidden" }}>
+                              <Text size="sm" fw={500} truncate>
+                                {project.path.split(/[/\\]/).pop()}
+                              </Text>
+                              <Text size="xs" c="dimmed" truncate>
+                                {project.exists
+                                  ? project.path
+                                  : t("startPage.projectMissing", {
+                                      path: project.path,
+                                    })}
+                              </Text>
+                            </Box>
+                          </Group>
+                        </UnstyledButton>
+                        <Tooltip
+                          label={
+                            project.pinned
+                              ? t("startPage.unpinProject")
+                              : t("startPage.pinProject")
+                          }
+                        >
+                          <ActionIcon
+                            variant={project.pinned ? "light" : "subtle"}
+                            color="gray"
+                            onClick={() =>
+                              setPinned(project.path, !project.pinned)
+                            }
+                          >
+                            <FontAwesomeIcon icon={faThumbtack} />
+                          </ActionIcon>
+                        </Tooltip>
+                        <Tooltip label={t("startPage.removeRecentProject")}>
+                          <ActionIcon
+                            variant="subtle"
+                            color="gray"
+                            onClick={() => removeRecent(project.path)}
+                          >
+                            <FontAwesomeIcon icon={faTimes} />
+                          </ActionIcon>
+                        </Tooltip>
+                      </Group>
                     ))}
                   </Stack>
                 </Card>
@@ -0,0 +1,127 @@
+import React, { useEffect, useState } from "react";
+import {
+  Modal,
+  Button,
+  Text,
+  Group,
+  Stack,
+  TextInput,
+  PasswordInput,
+  Checkbox,
+} from "@mantine/core";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+// Payload of the backend's "git-credentials-required" event
+interface CredentialsRequired {
+  requestId: number;
+  url: string;
+  username: string | null;
+  kind: "userpass" | "ssh_passphrase";
+  key_path: string | null;
+  retry: boolean;
+}
+
+/**
+ * Asks for credentials whenever a fetch/pull/push needs them and none are
+ * available from the SSH agent, a credential helper or the keyring.
+ */
+export const GitCredentialsModal: React.FC = () => {
+  const [request, setRequest] = useState<CredentialsRequired | null>(null);
+  const [username, setUsername] = useState("");
+  const [secret, setSecret] = useState("");
+  const [remember, setRemember] = useState(true);
+
+  useEffect(() => {
+    const unlisten = listen<CredentialsRequired>(
+      "git-credentials-required",
+      (event) => {
+        setRequest(event.payload);
+        setUsername(event.payload.username ?? "");
+        setSecret("");
+      }
+    );
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  const answer = async (accepted: boolean) => {
+    if (!request) return;
+    const response = accepted ? { username, secret, remember } : null;
+    setRequest(null);
+    setSecret("");
+    try {
+      await invoke("git_provide_credentials_cmd", {
+        requestId: request.requestId,
+        response,
+      });
+    } catch (e) {
+      console.error("Failed to provide Git credentials", e);
+    }
+  };
+
+  const isPassphrase = request?.kind === "ssh_passphrase";
+
+  return (
+    <Modal
+      opened={request !== null}
+      onClose={() => answer(false)}
+      title={isPassphrase ? "SSH Key Passphrase" : "Git Credentials"}
+      centered
+      size="sm"
+    >
+      <form
+        onSubmit={(e) => {
+          e.preventDefault();
+          answer(true);
+        }}
+      >
+        <Stack>
+          <Text size="sm">
+            {isPassphrase ? (
+              <>
+                Enter the passphrase for <b>{request?.key_path}</b>
+              </>
+            ) : (
+              <>
+                Authentication required for <b>{request?.url}</b>
+              </>
+            )}
+          </Text>
+          {request?.retry && (
+            <Text size="xs" c="red">
+              The previous credentials were rejected.
+            </Text>
+          )}
+          {!isPassphrase && (
+            <TextInput
+              label="Username"
+              value={username}
+              onChange={(e) => setUsername(e.currentTarget.value)}
+            />
+          )}
+          <PasswordInput
+            label={isPassphrase ? "Passphrase" : "Password or token"}
+            value={secret}
+            onChange={(e) => setSecret(e.currentTarget.value)}
+            data-autofocus
+          />
+          <Checkbox
+            label="Remember in the system keyring"
+            checked={remember}
+            onChange={(e) => setRemember(e.currentTarget.checked)}
+          />
+          <Group justify="flex-end" mt="md">
+            <Button variant="default" onClick={() => answer(false)}>
+              Cancel
+            </Button>
+            <Button type="submit" color="blue">
+              Continue
+            </Button>
+          </Group>
+        </Stack>
+      </form>
+    </Modal>
+  );
+};
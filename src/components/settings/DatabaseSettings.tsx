@@ -1,5 +1,13 @@
 import React from "react";
-import { Stack, Title, Text, Select, NumberInput, Switch } from "@mantine/core";
+import {
+  Stack,
+  Title,
+  Text,
+  Select,
+  NumberInput,
+  Switch,
+  TagsInput,
+} from "@mantine/core";
 import { useTranslation } from "react-i18next";
 import { DatabaseSettings as IDatabaseSettings } from "../../hooks/useSettings";
 import { SettingGroup } from "./SettingGroup";
@@ -71,6 +79,98 @@ export const DatabaseSettings: React.FC<DatabaseSettingsProps> = ({
         />
       </SettingGroup>
 
+      <SettingGroup
+        title={t("settings.database.fileTree.title")}
+        description={t("settings.database.fileTree.description")}
+      >
+        <TagsInput
+          label={t("settings.database.fileTree.extensions.label")}
+          description={t("settings.database.fileTree.extensions.description")}
+          value={settings.treeExtensions}
+          onChange={(val) =>
+            onUpdate(
+              "treeExtensions",
+              val.map((e) => e.trim().replace(/^\./, "").toLowerCase()),
+            )
+          }
+          disabled={settings.showAllFiles}
+          clearable
+        />
+
+        <Switch
+          label={t("settings.database.fileTree.showAllFiles.label")}
+          description={t("settings.database.fileTree.showAllFiles.description")}
+          checked={settings.showAllFiles}
+          onChange={(e) => onUpdate("showAllFiles", e.currentTarget.checked)}
+        />
+
+        <Switch
+          label={t("settings.database.fileTree.showHiddenFiles.label")}
+          description={t(
+            "settings.database.fileTree.showHiddenFiles.description"
+          )}
+          checked={settings.showHiddenFiles}
+          onChange={(e) => onUpdate("showHiddenFiles", e.currentTarget.checked)}
+        />
+
+        <Select
+          label={t("settings.database.fileTree.sortBy.label")}
+          description={t("settings.database.fileTree.sortBy.description")}
+          data={[
+            {
+              value: "name",
+              label: t("settings.database.fileTree.sortBy.name"),
+            },
+            {
+              value: "modified",
+              label: t("settings.database.fileTree.sortBy.modified"),
+            },
+            {
+              value: "size",
+              label: t("settings.database.fileTree.sortBy.size"),
+            },
+          ]}
+          value={settings.treeSortBy}
+          onChange={(val) => val && onUpdate("treeSortBy", val as any)}
+        />
+
+        <Switch
+          label={t("settings.database.fileTree.sortDescending.label")}
+          description={t(
+            "settings.database.fileTree.sortDescending.description"
+          )}
+          checked={settings.treeSortDescending}
+          onChange={(e) =>
+            onUpdate("treeSortDescending", e.currentTarget.checked)
+          }
+        />
+
+        <Switch
+          label={t("settings.database.fileTree.foldersFirst.label")}
+          description={t("settings.database.fileTree.foldersFirst.description")}
+          checked={settings.treeFoldersFirst}
+          onChange={(e) => onUpdate("treeFoldersFirst", e.currentTarget.checked)}
+        />
+
+        <Switch
+          label={t("settings.database.fileTree.naturalSort.label")}
+          description={t("settings.database.fileTree.naturalSort.description")}
+          checked={settings.treeNaturalSort}
+          onChange={(e) => onUpdate("treeNaturalSort", e.currentTarget.checked)}
+        />
+
+        <Switch
+          label={t("settings.database.fileTree.flattenChains.label")}
+          description={t(
+            "settings.database.fileTree.flattenChains.description"
+          )}
+          checked={settings.treeFlattenChains}
+          onChange={(e) =>
+            onUpdate("treeFlattenChains", e.currentTarget.checked)
+          }
+        />
+      </SettingGroup>
+
       <SettingGroup
         title={t("settings.database.graph.title")}
         description={t("settings.database.graph.description")}
@@ -1,9 +1,14 @@
-import React, { useEffect, useState } from "react";
+import React, { useEffect, useMemo, useState } from "react";
 import { Stack, Title, Text, Select, NumberInput, Switch } from "@mantine/core";
 import { useTranslation } from "react-i18next";
 import { invoke } from "@tauri-apps/api/core";
 import { EditorSettings as IEditorSettings } from "../../hooks/useSettings";
 
+interface FontFamily {
+  name: string;
+  monospace: boolean;
+}
+
 interface EditorSettingsProps {
   settings: IEditorSettings;
   onUpdate: <K extends keyof IEditorSettings>(
@@ -17,16 +22,32 @@ export const EditorSettings: React.FC<EditorSettingsProps> = ({
   onUpdate,
 }) => {
   const { t } = useTranslation();
-  const [systemFonts, setSystemFonts] = useState<string[]>([]);
+  const [systemFonts, setSystemFonts] = useState<FontFamily[]>([]);
 
   useEffect(() => {
-    invoke<string[]>("get_system_fonts")
+    invoke<FontFamily[]>("get_system_fonts")
       .then((fonts) => {
         setSystemFonts(fonts);
       })
       .catch((err) => console.error("Failed to load fonts", err));
   }, []);
 
+  // Fixed-width fonts first, they are what most people want for code
+  const fontOptions = useMemo(
+    () =>
+      [
+        {
+          group: t("settings.editor.fontFamily.monospace"),
+          items: systemFonts.filter((f) => f.monospace).map((f) => f.name),
+        },
+        {
+          group: t("settings.editor.fontFamily.other"),
+          items: systemFonts.filter((f) => !f.monospace).map((f) => f.name),
+        },
+      ].filter((g) => g.items.length > 0),
+    [systemFonts, t],
+  );
+
   return (
     <Stack gap="md" maw={600}>
       <Title order={4}>{t("settings.editor.title")}</Title>
@@ -46,7 +67,7 @@ export const EditorSettings: React.FC<EditorSettingsProps> = ({
       <Select
         label={t("settings.editor.fontFamily.label")}
         description={t("settings.editor.fontFamily.description")}
-        data={systemFonts}
+        data={fontOptions}
         value={settings.fontFamily}
         onChange={(val) => onUpdate("fontFamily", val || "Consolas")}
         searchable
@@ -7,6 +7,9 @@ export * from "./types";
 // Hooks
 export { useTreeState, sortTreeNodes, sortTreeRecursive } from "./useTreeState";
 
+// Utilities
+export { applyTreeChange } from "./treeChanges";
+
 // Components
 export { UnifiedTreeItem, getFileIcon, InlineInput } from "./UnifiedTreeItem";
 export { TreeToolbar } from "./TreeToolbar";
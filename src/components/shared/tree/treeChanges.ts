@@ -0,0 +1,49 @@
+import { TreeNode, TreeNodeChange } from "./types";
+
+const removePath = (nodes: TreeNode[], path: string): TreeNode[] =>
+  nodes
+    .filter((n) => n.path !== path)
+    .map((n) =>
+      n.children ? { ...n, children: removePath(n.children, path) } : n,
+    );
+
+const insertAt = (
+  nodes: TreeNode[],
+  parentPath: string | null,
+  index: number,
+  node: TreeNode,
+): TreeNode[] => {
+  if (parentPath === null) {
+    const next = [...nodes];
+    next.splice(index, 0, node);
+    return next;
+  }
+  return nodes.map((n) => {
+    if (n.path === parentPath) {
+      const children = [...(n.children || [])];
+      children.splice(index, 0, node);
+      return { ...n, children };
+    }
+    return n.children
+      ? { ...n, children: insertAt(n.children, parentPath, index, node) }
+      : n;
+  });
+};
+
+/**
+ * Apply one `tree-node-*` event to a tree, returning the updated tree.
+ * Unchanged branches are shared with the input.
+ */
+export function applyTreeChange(
+  nodes: TreeNode[],
+  change: TreeNodeChange,
+): TreeNode[] {
+  if ("old_path" in change) {
+    const rest = removePath(nodes, change.old_path);
+    return insertAt(rest, change.parent_path, change.index, change.node);
+  }
+  if ("node" in change) {
+    return insertAt(nodes, change.parent_path, change.index, change.node);
+  }
+  return removePath(nodes, change.path);
+}
@@ -22,10 +22,42 @@ export interface TreeNode {
   metadata?: {
     collectionName?: string; // Database: which collection this belongs to
     isLoaded?: boolean; // Database: is this collection loaded
+    size?: number; // Database: file size in bytes
+    modified?: number; // Database: last modification, Unix seconds
+    gitStatus?: string; // Database: uncommitted change ("modified", ...)
+    gitStaged?: boolean; // Database: the change is staged
+    fileCount?: number; // Database folders: files below
+    totalSize?: number; // Database folders: their size in bytes
+    failingCount?: number; // Database folders: files failing to compile
+    changedCount?: number; // Database folders: files with uncommitted changes
+    symlink?: boolean; // Database: the file or folder is a symbolic link
+    linkTarget?: string; // Database: where the link points
     [key: string]: any;
   };
 }
 
+/**
+ * Payloads of the `tree-node-added`, `tree-node-removed` and
+ * `tree-node-renamed` events the collection watcher emits after indexing.
+ * Removals come first; a rename removes `oldPath` and inserts `node`.
+ */
+export type TreeNodeChange =
+  | {
+      collection: string;
+      parent_path: string | null;
+      index: number;
+      node: TreeNode;
+    }
+  | { collection: string; id: string; path: string }
+  | {
+      collection: string;
+      old_id: string;
+      old_path: string;
+      parent_path: string | null;
+      index: number;
+      node: TreeNode;
+    };
+
 // ============================================================================
 // CONFIGURATION TYPES
 // ============================================================================
@@ -0,0 +1,97 @@
+import { useCallback, useEffect, useRef } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { notifications } from "@mantine/notifications";
+import { useTranslation } from "react-i18next";
+import { useTabsStore } from "../stores/useTabsStore";
+
+interface Draft {
+  id: string;
+  uri: string;
+  savedAt: number;
+  size: number;
+  fileExists: boolean;
+}
+
+interface UseDraftRecoveryOptions {
+  onOpenFile: (path: string) => Promise<void> | void;
+}
+
+// Time without typing before a draft is written
+const AUTOSAVE_DELAY_MS = 3000;
+// Give the session restore a head start, so recovered tabs aren't reopened
+const RECOVERY_DELAY_MS = 3000;
+
+const isFilePath = (id: string) => /[\\/]/.test(id);
+
+/**
+ * Drafts of unsaved edits, kept by the backend while typing so they
+ * survive a crash. Drafts are dropped once the file is saved.
+ */
+export const useDrafts = () => {
+  const timers = useRef(new Map<string, ReturnType<typeof setTimeout>>());
+
+  const autosaveDraft = useCallback((uri: string, content: string) => {
+    if (!isFilePath(uri)) return;
+    clearTimeout(timers.current.get(uri));
+    timers.current.set(
+      uri,
+      setTimeout(() => {
+        timers.current.delete(uri);
+        invoke("autosave_document", { uri, content }).catch((err) =>
+          console.warn("Failed to autosave draft:", err),
+        );
+      }, AUTOSAVE_DELAY_MS),
+    );
+  }, []);
+
+  const discardDrafts = useCallback((uri: string) => {
+    clearTimeout(timers.current.get(uri));
+    timers.current.delete(uri);
+    invoke("discard_drafts", { uri }).catch((err) =>
+      console.warn("Failed to discard drafts:", err),
+    );
+  }, []);
+
+  return { autosaveDraft, discardDrafts };
+};
+
+/**
+ * On startup, puts drafts that never made it to disk (the app crashed or
+ * was killed) back in their tab as unsaved changes.
+ */
+export const useDraftRecovery = ({ onOpenFile }: UseDraftRecoveryOptions) => {
+  const { t } = useTranslation();
+  const openFile = useRef(onOpenFile);
+  openFile.current = onOpenFile;
+  const recovered = useRef(false);
+
+  useEffect(() => {
+    if (recovered.current) return;
+    const recover = async () => {
+      const drafts = await invoke<Draft[]>("list_recoverable_drafts");
+      for (const draft of drafts.filter((d) => d.fileExists)) {
+        const content = await invoke<string>("recover_draft", {
+          id: draft.id,
+        });
+        await openFile.current(draft.uri);
+        const { updateTabContent, markDirty } = useTabsStore.getState();
+        updateTabContent(draft.uri, content);
+        markDirty(draft.uri, true);
+        notifications.show({
+          title: t("drafts.recoveredTitle"),
+          message: t("drafts.recoveredMessage", {
+            file: draft.uri.split(/[\\/]/).pop(),
+            time: new Date(draft.savedAt).toLocaleString(),
+          }),
+          color: "yellow",
+          autoClose: false,
+        });
+      }
+    };
+    const timer = setTimeout(() => {
+      recovered.current = true;
+      recover().catch((err) => console.error("Draft recovery failed:", err));
+    }, RECOVERY_DELAY_MS);
+    return () => clearTimeout(timer);
+  }, [t]);
+};
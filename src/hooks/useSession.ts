@@ -0,0 +1,161 @@
+import { useEffect, useRef } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWindow } from "@tauri-apps/api/window";
+import { useCursorStore } from "../stores/cursorStore";
+import { useDatabaseStore } from "../stores/databaseStore";
+import { useSettingsStore } from "../stores/settingsStore";
+import { useTabsStore } from "../stores/useTabsStore";
+
+interface SessionTab {
+  path: string;
+  cursorLine: number;
+  cursorColumn: number;
+}
+
+interface Session {
+  projectRoot: string | null;
+  activeCollection: string | null;
+  loadedCollections: string[];
+  expandedNodes: string[];
+  tabs: SessionTab[];
+  activeTab: string | null;
+}
+
+interface UseSessionOptions {
+  rootPath: string | null;
+  onOpenProject: (path: string) => Promise<void>;
+  onOpenFile: (path: string) => Promise<void> | void;
+  onRevealLine: (line: number) => void;
+}
+
+// The database opens in the background at startup
+const LOAD_ATTEMPTS = 10;
+const LOAD_RETRY_MS = 500;
+
+// Tabs backed by a file on disk (not the start page, wizards, diffs, ...)
+const isFileTab = (id: string, type: string) =>
+  type === "editor" && /[\\/]/.test(id);
+
+/**
+ * Saves the window state (open files and their cursor, expanded tree
+ * folders, project folder, collections) when the window closes, and puts
+ * it back on startup when the "restore" startup behavior is selected.
+ */
+export const useSession = (options: UseSessionOptions) => {
+  const startupBehavior = useSettingsStore(
+    (s) => s.settings.general.startupBehavior,
+  );
+  // Last cursor of every tab, and cursors still to apply after a restore
+  const cursors = useRef(new Map<string, { line: number; column: number }>());
+  const pendingCursors = useRef(new Map<string, number>());
+  // The callbacks change as tabs open; effects read the latest ones
+  const latest = useRef(options);
+  latest.current = options;
+  const restored = useRef(false);
+
+  useEffect(
+    () =>
+      useCursorStore.subscribe(({ lineNumber, column }) => {
+        const { activeTabId } = useTabsStore.getState();
+        cursors.current.set(activeTabId, { line: lineNumber, column });
+      }),
+    [],
+  );
+
+  // Restored tabs scroll to their cursor the first time they are shown
+  useEffect(
+    () =>
+      useTabsStore.subscribe(
+        (state) => state.activeTabId,
+        (activeTabId) => {
+          const line = pendingCursors.current.get(activeTabId);
+          if (line === undefined) return;
+          pendingCursors.current.delete(activeTabId);
+          setTimeout(() => latest.current.onRevealLine(line), 150);
+        },
+      ),
+    [],
+  );
+
+  useEffect(() => {
+    if (restored.current || startupBehavior !== "restore") return;
+    restored.current = true;
+
+    const restore = async () => {
+      let session: Session | null = null;
+      for (let attempt = 0; attempt < LOAD_ATTEMPTS; attempt++) {
+        try {
+          session = await invoke<Session | null>("get_last_session_cmd");
+          break;
+        } catch (err) {
+          await new Promise((r) => setTimeout(r, LOAD_RETRY_MS));
+        }
+      }
+      if (!session) return;
+
+      const { onOpenProject, onRevealLine } = latest.current;
+      const db = useDatabaseStore.getState();
+      if (session.projectRoot) await onOpenProject(session.projectRoot);
+      if (session.loadedCollections.length > 0) {
+        await db.setLoadedCollections(session.loadedCollections);
+      }
+      if (session.activeCollection) {
+        await db.selectCollection(session.activeCollection);
+      }
+      db.setExpandedTreeNodes(session.expandedNodes);
+
+      for (const tab of session.tabs) {
+        cursors.current.set(tab.path, {
+          line: tab.cursorLine,
+          column: tab.cursorColumn,
+        });
+        pendingCursors.current.set(tab.path, tab.cursorLine);
+        await latest.current.onOpenFile(tab.path);
+      }
+      const { setActiveTab, activeTabId } = useTabsStore.getState();
+      const focus = session.activeTab ?? activeTabId;
+      if (focus === activeTabId) {
+        // Already active, so the subscription above won't fire
+        const line = pendingCursors.current.get(focus);
+        pendingCursors.current.delete(focus);
+        if (line !== undefined) setTimeout(() => onRevealLine(line), 150);
+      } else {
+        setActiveTab(focus);
+      }
+    };
+
+    restore().catch((err) =>
+      console.error("Failed to restore session:", err),
+    );
+  }, [startupBehavior]);
+
+  useEffect(() => {
+    const unlisten = getCurrentWindow().onCloseRequested(async () => {
+      const { tabs, activeTabId } = useTabsStore.getState();
+      const db = useDatabaseStore.getState();
+      const fileTabs = tabs.filter((t) => isFileTab(t.id, t.type));
+      const session: Session = {
+        projectRoot: latest.current.rootPath,
+        activeCollection: db.activeCollection,
+        loadedCollections: db.loadedCollections,
+        expandedNodes: db.expandedTreeNodes,
+        tabs: fileTabs.map((t) => ({
+          path: t.id,
+          cursorLine: cursors.current.get(t.id)?.line ?? 1,
+          cursorColumn: cursors.current.get(t.id)?.column ?? 1,
+        })),
+        activeTab: fileTabs.some((t) => t.id === activeTabId)
+          ? activeTabId
+          : null,
+      };
+      try {
+        await invoke("save_session_cmd", { session });
+      } catch (err) {
+        console.error("Failed to save session:", err);
+      }
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, []);
+};
@@ -1,5 +1,12 @@
 import { invoke } from "@tauri-apps/api/core";
 
+// Part of a LaTeX source to search in
+export type SearchScope =
+  | { kind: "all" }
+  | { kind: "math" }
+  | { kind: "environment"; name: string }
+  | { kind: "preamble" };
+
 // Search query parameters
 export interface SearchQuery {
   query: string;
@@ -8,6 +15,27 @@ export interface SearchQuery {
   fileTypes: string[];
   collections: string[];
   maxResults: number;
+  wholeWord?: boolean;
+  multiline?: boolean;
+  skipComments?: boolean;
+  scope?: SearchScope;
+  // Bytes; the backend defaults to 10 MiB
+  maxFileSize?: number;
+  // Globs matched against the whole path; `*.sty` matches in any folder
+  includeGlobs?: string[];
+  excludeGlobs?: string[];
+  respectGitignore?: boolean;
+  // File contents, resource metadata (titles, tags, custom fields) or both
+  mode?: "content" | "metadata" | "all";
+  // Context lines around each match; the backend defaults to 2
+  contextBefore?: number;
+  contextAfter?: number;
+}
+
+// A file that was not searched
+export interface SkippedFile {
+  file_path: string;
+  reason: "binary" | "too_large" | "unreadable" | "unencodable" | "unwritable";
 }
 
 // A single search match
@@ -21,6 +49,11 @@ export interface SearchMatch {
   match_end: number;
   context_before: string[];
   context_after: string[];
+  end_line_number: number;
+  // Metadata matches have line_number 0 and the whole value as line_content
+  origin: "content" | "title" | "description" | "tags" | "custom_field";
+  // Custom field name when origin is "custom_field"
+  field: string | null;
 }
 
 // Complete search result
@@ -28,6 +61,40 @@ export interface SearchResult {
   matches: SearchMatch[];
   total_files_searched: number;
   search_duration_ms: number;
+  used_index: boolean;
+  skipped_files: SkippedFile[];
+  // All matches found, also those beyond maxResults
+  total_matches: number;
+  file_match_counts: FileMatchCount[];
+}
+
+// Number of matches in one file
+export interface FileMatchCount {
+  resource_id: string;
+  file_path: string;
+  count: number;
+}
+
+// Query as the backend's SearchQuery expects it
+function toBackendQuery(query: SearchQuery, maxResults: number) {
+  return {
+    text: query.query,
+    case_sensitive: query.caseSensitive,
+    use_regex: query.useRegex,
+    file_types: query.fileTypes,
+    max_results: maxResults,
+    whole_word: query.wholeWord ?? false,
+    multiline: query.multiline ?? false,
+    skip_comments: query.skipComments ?? false,
+    scope: query.scope ?? { kind: "all" },
+    max_file_size: query.maxFileSize ?? null,
+    include_globs: query.includeGlobs ?? [],
+    exclude_globs: query.excludeGlobs ?? [],
+    respect_gitignore: query.respectGitignore ?? false,
+    mode: query.mode ?? "content",
+    context_before: query.contextBefore ?? null,
+    context_after: query.contextAfter ?? null,
+  };
 }
 
 /**
@@ -37,12 +104,8 @@ export async function searchDatabaseFiles(
   query: SearchQuery
 ): Promise<SearchResult> {
   return await invoke<SearchResult>("search_database_files", {
-    query: query.query,
-    caseSensitive: query.caseSensitive,
-    useRegex: query.useRegex,
-    fileTypes: query.fileTypes,
+    query: toBackendQuery(query, query.maxResults),
     collections: query.collections,
-    maxResults: query.maxResults,
   });
 }
 
@@ -51,11 +114,18 @@ export interface ReplaceResult {
   total_files_changed: number;
   total_replacements: number;
   replace_duration_ms: number;
+  skipped_hunks: number;
+  skipped_files: SkippedFile[];
 }
 
 // Replace query parameters
 export interface ReplaceQuery extends SearchQuery {
   replaceWith: string;
+  preserveCase?: boolean;
+  // Write changed files as UTF-8 instead of their detected encoding
+  convertToUtf8?: boolean;
+  // Keep the previous content: "<file>.bak" or a local history snapshot
+  backup?: "none" | "bak" | "history";
 }
 
 /**
@@ -65,11 +135,21 @@ export async function replaceDatabaseFiles(
   query: ReplaceQuery
 ): Promise<ReplaceResult> {
   return await invoke<ReplaceResult>("replace_database_files", {
-    query: query.query,
-    replaceWith: query.replaceWith,
-    caseSensitive: query.caseSensitive,
-    useRegex: query.useRegex,
-    fileTypes: query.fileTypes,
+    query: {
+      // Replace processes every match
+      search: toBackendQuery(query, Number.MAX_SAFE_INTEGER),
+      replace_with: query.replaceWith,
+      preserve_case: query.preserveCase ?? false,
+      convert_to_utf8: query.convertToUtf8 ?? false,
+      backup: query.backup ?? "none",
+    },
     collections: query.collections,
   });
 }
+
+/**
+ * Rewrite the files of the given resources as UTF-8; returns the converted ids
+ */
+export async function convertToUtf8(resourceIds: string[]): Promise<string[]> {
+  return await invoke<string[]>("convert_to_utf8_cmd", { resourceIds });
+}
@@ -51,6 +51,8 @@ interface DatabaseState {
   isLoading: boolean;
   error: string | null;
   loadedCollections: string[];
+  /** Expanded folders of the sidebar tree, kept for the saved session */
+  expandedTreeNodes: string[];
 
   allLoadedResources: Resource[];
   isWizardOpen: boolean;
@@ -65,6 +67,7 @@ interface DatabaseState {
   ) => Promise<void>;
   createCollection: (name: string, path: string) => Promise<void>;
   selectResource: (id: string | null) => void;
+  setExpandedTreeNodes: (nodes: string[]) => void;
   toggleCollectionLoaded: (name: string) => Promise<void>;
   setLoadedCollections: (collections: string[]) => Promise<void>;
   fetchResourcesForLoadedCollections: () => Promise<void>;
@@ -93,6 +96,8 @@ interface DatabaseState {
   ) => Promise<Resource[]>;
   updateResourceKind: (id: string, kind: string) => Promise<void>;
   moveResource: (id: string, newCollection: string) => Promise<void>;
+  renameResource: (id: string, newName: string) => Promise<void>;
+  duplicateResource: (id: string) => Promise<void>;
   compileResource: (id: string) => Promise<string>; // Returns PDF path
 
   // Graph
@@ -119,6 +124,7 @@ export const useDatabaseStore = create<DatabaseState>((set, get) => ({
   isLoading: false,
   error: null,
   loadedCollections: [],
+  expandedTreeNodes: [],
   allLoadedResources: [],
 
   fetchCollections: async () => {
@@ -286,6 +292,8 @@ export const useDatabaseStore = create<DatabaseState>((set, get) => ({
   activeResourceId: null as string | null,
   selectResource: (id: string | null) => set({ activeResourceId: id }),
 
+  setExpandedTreeNodes: (nodes: string[]) => set({ expandedTreeNodes: nodes }),
+
   deleteResource: async (id: string) => {
     set({ isLoading: true, error: null });
     try {
@@ -452,6 +460,30 @@ export const useDatabaseStore = create<DatabaseState>((set, get) => ({
     }
   },
 
+  // Renames the file on disk; documents referencing it are updated too
+  renameResource: async (id: string, newName: string) => {
+    set({ isLoading: true, error: null });
+    try {
+      await invoke("rename_resource_cmd", { id, newName });
+      // Folder renames re-path everything below, so reload
+      await get().fetchResourcesForLoadedCollections();
+      set({ isLoading: false });
+    } catch (err: any) {
+      set({ error: err.toString(), isLoading: false });
+    }
+  },
+
+  duplicateResource: async (id: string) => {
+    set({ isLoading: true, error: null });
+    try {
+      await invoke("duplicate_resource_cmd", { id });
+      await get().fetchResourcesForLoadedCollections();
+      set({ isLoading: false });
+    } catch (err: any) {
+      set({ error: err.toString(), isLoading: false });
+    }
+  },
+
   compileResource: async (id: string) => {
     try {
       return await invoke("compile_resource_cmd", { id });
@@ -0,0 +1,77 @@
+import { create } from "zustand";
+import { invoke } from "@tauri-apps/api/core";
+
+export interface RecentProject {
+  path: string;
+  lastOpened: string;
+  pinned: boolean;
+  /** False when the folder was moved or deleted since */
+  exists: boolean;
+}
+
+/** What the backend set up for an opened project */
+export interface ProjectInfo {
+  root: string;
+  name: string;
+  collections: string[];
+  git: { path: string; branch: string | null } | null;
+  databaseReady: boolean;
+  rootUri: string;
+  lspRestarted: boolean;
+}
+
+interface RecentProjectsState {
+  projects: RecentProject[];
+  load: () => Promise<void>;
+  /** Open `path` as the active project, which also puts it on top */
+  open: (path: string) => Promise<ProjectInfo | null>;
+  setPinned: (path: string, pinned: boolean) => Promise<void>;
+  remove: (path: string) => Promise<void>;
+}
+
+// Older versions kept the list in localStorage
+const LEGACY_KEY = "recentProjects";
+
+/** Recent and pinned project folders, kept by the backend */
+export const useRecentProjectsStore = create<RecentProjectsState>((set) => {
+  const update = async (command: string, args: Record<string, unknown>) => {
+    try {
+      set({ projects: await invoke<RecentProject[]>(command, args) });
+    } catch (err) {
+      console.error(`${command} failed:`, err);
+    }
+  };
+
+  return {
+    projects: [],
+    load: async () => {
+      const legacy = localStorage.getItem(LEGACY_KEY);
+      if (legacy) {
+        localStorage.removeItem(LEGACY_KEY);
+        try {
+          const paths: string[] = JSON.parse(legacy);
+          // Oldest first, so the most recent ends up on top
+          for (const path of [...paths].reverse()) {
+            await invoke("add_recent_project", { path }).catch(() => {});
+          }
+        } catch (err) {
+          console.error("Failed to import recent projects", err);
+        }
+      }
+      await update("list_recent_projects", {});
+    },
+    open: async (path) => {
+      try {
+        const info = await invoke<ProjectInfo>("open_project", { path });
+        await update("list_recent_projects", {});
+        return info;
+      } catch (err) {
+        console.error("open_project failed:", err);
+        return null;
+      }
+    },
+    setPinned: (path, pinned) =>
+      update("pin_recent_project", { path, pinned }),
+    remove: (path) => update("remove_recent_project", { path }),
+  };
+});
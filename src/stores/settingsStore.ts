@@ -1,5 +1,6 @@
 import { create } from "zustand";
 import { persist } from "zustand/middleware";
+import { invoke } from "@tauri-apps/api/core";
 import i18n from "../i18n";
 
 export interface EditorSettings {
@@ -62,6 +63,18 @@ export interface DatabaseSettings {
   graphPhysics: boolean;
   graphAnimation: boolean;
   showFilePreview: boolean;
+  // File tree filter: extensions shown (without the dot), dotfiles, and an
+  // escape hatch that shows every file
+  treeExtensions: string[];
+  showHiddenFiles: boolean;
+  showAllFiles: boolean;
+  // File tree order (applied by the backend, see TreeSort)
+  treeSortBy: "name" | "modified" | "size";
+  treeFoldersFirst: boolean;
+  treeNaturalSort: boolean;
+  treeSortDescending: boolean;
+  // Hide folders that only wrap another folder below a collection root
+  treeFlattenChains: boolean;
 }
 
 export interface AccessibilitySettings {
@@ -171,6 +184,27 @@ export const DEFAULT_SETTINGS: AppSettings = {
     graphPhysics: true,
     graphAnimation: true,
     showFilePreview: true,
+    treeExtensions: [
+      "tex",
+      "pdf",
+      "bib",
+      "sty",
+      "cls",
+      "dtx",
+      "ins",
+      "png",
+      "jpg",
+      "jpeg",
+      "csv",
+      "md",
+    ],
+    showHiddenFiles: false,
+    showAllFiles: false,
+    treeSortBy: "name",
+    treeFoldersFirst: true,
+    treeNaturalSort: true,
+    treeSortDescending: false,
+    treeFlattenChains: false,
   },
   accessibility: {
     highContrastMode: false,
@@ -205,6 +239,14 @@ export const DEFAULT_SETTINGS: AppSettings = {
   },
 };
 
+// The part of the settings the backend acts on lives in its own config
+// file (see settings.rs); changes made here are forwarded to it
+const syncBackendSettings = (patch: Record<string, unknown>) => {
+  invoke("update_settings", { patch }).catch((err) =>
+    console.error("Failed to save backend settings:", err)
+  );
+};
+
 interface SettingsState {
   settings: AppSettings;
   updateEditorSetting: <K extends keyof EditorSettings>(
@@ -257,12 +299,17 @@ export const useSettingsStore = create<SettingsState>()(
 
       // Actions
       updateEditorSetting: (key, value) =>
-        set((state) => ({
-          settings: {
-            ...state.settings,
-            editor: { ...state.settings.editor, [key]: value },
-          },
-        })),
+        set((state) => {
+          if (key === "fontSize" || key === "fontFamily") {
+            syncBackendSettings({ editor: { [key]: value } });
+          }
+          return {
+            settings: {
+              ...state.settings,
+              editor: { ...state.settings.editor, [key]: value },
+            },
+          };
+        }),
 
       updateEditorBehaviorSetting: (key, value) =>
         set((state) => ({
@@ -289,12 +336,17 @@ export const useSettingsStore = create<SettingsState>()(
         })),
 
       updateTexEngineSetting: (key, value) =>
-        set((state) => ({
-          settings: {
-            ...state.settings,
-            texEngine: { ...state.settings.texEngine, [key]: value },
-          },
-        })),
+        set((state) => {
+          if (key === "defaultEngine") {
+            syncBackendSettings({ compile: { defaultEngine: value } });
+          }
+          return {
+            settings: {
+              ...state.settings,
+              texEngine: { ...state.settings.texEngine, [key]: value },
+            },
+          };
+        }),
 
       updateDatabaseSetting: (key, value) =>
         set((state) => ({
@@ -385,6 +437,10 @@ export const useSettingsStore = create<SettingsState>()(
         const mergedSettings = {
           ...DEFAULT_SETTINGS,
           ...loadedSettings,
+          database: {
+            ...DEFAULT_SETTINGS.database,
+            ...(loadedSettings.database || {}),
+          },
           shortcuts: {
             ...DEFAULT_SETTINGS.shortcuts,
             ...(loadedSettings.shortcuts || {}),
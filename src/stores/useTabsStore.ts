@@ -21,6 +21,7 @@ export interface AppTab {
     repoPath: string;
     filePath: string;
     initialView: "diff" | "blame";
+    diffMode?: "head_to_workdir" | "staged" | "unstaged";
   };
   diffData?: {
     original: string;